@@ -0,0 +1,23 @@
+-- migrate:up
+-- Durable idempotency records backing the Redis idempotency cache
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key TEXT PRIMARY KEY,
+    scope TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE idempotency_keys IS 'Idempotency records for payment initiation and mutating API requests. Redis is checked first; this table is the fallback and survives cache eviction.';
+COMMENT ON COLUMN idempotency_keys.key IS 'Fully qualified cache key (v1:idempotency:{scope}:{client key}).';
+COMMENT ON COLUMN idempotency_keys.scope IS 'Namespace of the record, e.g. orchestrator or http:POST:/api/payments/initiate.';
+COMMENT ON COLUMN idempotency_keys.payload IS 'Serialized record, including the original response once the request completes.';
+COMMENT ON COLUMN idempotency_keys.expires_at IS 'After this time the key may be reused.';
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
+
+CREATE TRIGGER set_updated_at_idempotency_keys
+  BEFORE UPDATE ON idempotency_keys
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
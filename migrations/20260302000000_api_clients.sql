@@ -0,0 +1,48 @@
+-- migrate:up
+-- API clients, hashed API keys, and the link from end users to the client that onboarded them
+
+CREATE TABLE IF NOT EXISTS api_clients (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'suspended')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE api_clients IS 'Partners and internal services allowed to call the API.';
+COMMENT ON COLUMN api_clients.status IS 'Suspended clients are rejected regardless of key state.';
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id UUID NOT NULL REFERENCES api_clients(id) ON DELETE CASCADE,
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE api_keys IS 'API keys issued to clients. Only the SHA-256 digest of the key is stored.';
+COMMENT ON COLUMN api_keys.key_prefix IS 'First characters of the key, kept in clear for identification.';
+COMMENT ON COLUMN api_keys.key_hash IS 'Hex-encoded SHA-256 of the full key.';
+COMMENT ON COLUMN api_keys.scopes IS 'Granted scopes, e.g. quotes:write, payments:initiate.';
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_client_id ON api_keys(client_id);
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS api_client_id UUID REFERENCES api_clients(id) ON DELETE SET NULL;
+
+COMMENT ON COLUMN users.api_client_id IS 'API client that onboarded this user, if any.';
+
+CREATE INDEX IF NOT EXISTS idx_users_api_client_id ON users(api_client_id);
+
+CREATE TRIGGER set_updated_at_api_clients
+  BEFORE UPDATE ON api_clients
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER set_updated_at_api_keys
+  BEFORE UPDATE ON api_keys
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
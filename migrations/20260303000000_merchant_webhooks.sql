@@ -0,0 +1,46 @@
+-- migrate:up
+-- Partner-registered webhook endpoints and outgoing delivery tracking
+
+CREATE TABLE IF NOT EXISTS webhook_endpoints (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id UUID NOT NULL REFERENCES api_clients(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    event_types TEXT[] NOT NULL DEFAULT '{}',
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE webhook_endpoints IS 'Endpoints API clients registered to receive event notifications.';
+COMMENT ON COLUMN webhook_endpoints.secret IS 'HMAC-SHA256 signing secret. Kept in clear because every delivery is signed with it.';
+COMMENT ON COLUMN webhook_endpoints.event_types IS 'Subscribed event types; empty means all events.';
+
+CREATE INDEX IF NOT EXISTS idx_webhook_endpoints_client_active
+    ON webhook_endpoints(client_id) WHERE is_active;
+
+-- Outgoing deliveries are not tied to an incoming provider event
+ALTER TABLE webhook_deliveries
+    ALTER COLUMN event_id DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS endpoint_id UUID REFERENCES webhook_endpoints(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS outbound_event_id UUID,
+    ADD COLUMN IF NOT EXISTS event_type TEXT,
+    ADD COLUMN IF NOT EXISTS payload JSONB,
+    ADD COLUMN IF NOT EXISTS transaction_id UUID REFERENCES transactions(transaction_id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS last_error TEXT,
+    ADD COLUMN IF NOT EXISTS delivered_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN webhook_deliveries.endpoint_id IS 'Partner endpoint for outgoing event deliveries.';
+COMMENT ON COLUMN webhook_deliveries.outbound_event_id IS 'Event ID sent to the receiver; shared by every delivery and replay of the same event.';
+COMMENT ON COLUMN webhook_deliveries.next_attempt_at IS 'Earliest time the dispatcher may attempt (or retry) the delivery.';
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due
+    ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_endpoint
+    ON webhook_deliveries(endpoint_id, created_at DESC);
+
+CREATE TRIGGER set_updated_at_webhook_endpoints
+  BEFORE UPDATE ON webhook_endpoints
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1,9 @@
+-- migrate:up
+-- Allow data bundle purchases and support bill payment lookups from the API
+
+ALTER TABLE bill_payments DROP CONSTRAINT IF EXISTS bill_payments_bill_type_check;
+ALTER TABLE bill_payments
+    ADD CONSTRAINT bill_payments_bill_type_check
+    CHECK (bill_type IN ('electricity', 'water', 'airtime', 'data', 'internet', 'cable_tv'));
+
+COMMENT ON COLUMN bill_payments.provider_name IS 'Biller code from the provider catalog (e.g. ekedc-electric, mtn-ng).';
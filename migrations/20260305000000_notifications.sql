@@ -0,0 +1,55 @@
+-- migrate:up
+-- User notification preferences and a persistent outbox for email, SMS and partner webhook sends
+
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    email_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    sms_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    webhook_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    muted_types TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE notification_preferences IS 'Per-user notification channel opt-outs. Users without a row get the column defaults.';
+COMMENT ON COLUMN notification_preferences.muted_types IS 'Notification types (e.g. cngn_received) the user does not want on any channel.';
+
+CREATE TABLE IF NOT EXISTS notification_outbox (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    transaction_id UUID REFERENCES transactions(transaction_id) ON DELETE SET NULL,
+    notification_type TEXT NOT NULL,
+    channel TEXT NOT NULL CHECK (channel IN ('email', 'sms', 'webhook')),
+    recipient TEXT NOT NULL,
+    subject TEXT,
+    body TEXT NOT NULL,
+    payload JSONB,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sent', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error TEXT,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE notification_outbox IS 'Rendered notifications waiting to be sent, so sends survive restarts.';
+COMMENT ON COLUMN notification_outbox.recipient IS 'Email address, MSISDN or wallet address depending on the channel.';
+COMMENT ON COLUMN notification_outbox.next_attempt_at IS 'Earliest time the dispatcher may attempt (or retry) the send.';
+
+-- A worker re-running a stage must not notify the user twice
+CREATE UNIQUE INDEX IF NOT EXISTS idx_notification_outbox_dedupe
+    ON notification_outbox(transaction_id, notification_type, channel)
+    WHERE transaction_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_notification_outbox_due
+    ON notification_outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_notification_outbox_user
+    ON notification_outbox(user_id, created_at DESC);
+
+CREATE TRIGGER set_updated_at_notification_preferences
+  BEFORE UPDATE ON notification_preferences
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TRIGGER set_updated_at_notification_outbox
+  BEFORE UPDATE ON notification_outbox
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
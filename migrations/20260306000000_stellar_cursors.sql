@@ -0,0 +1,18 @@
+-- migrate:up
+-- Durable Horizon paging cursors so the transaction monitor resumes where it left off
+
+CREATE TABLE IF NOT EXISTS stellar_cursors (
+    account_address TEXT NOT NULL,
+    stream TEXT NOT NULL,
+    paging_token TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (account_address, stream)
+);
+
+COMMENT ON TABLE stellar_cursors IS 'Last processed Horizon paging_token per watched account and stream.';
+COMMENT ON COLUMN stellar_cursors.stream IS 'Consumer name, e.g. incoming for the system wallet payment scan.';
+
+CREATE TRIGGER set_updated_at_stellar_cursors
+  BEFORE UPDATE ON stellar_cursors
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
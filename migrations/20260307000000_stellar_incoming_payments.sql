@@ -0,0 +1,27 @@
+-- migrate:up
+-- Per-operation ingestion ledger for incoming Stellar payments and operator-requested rescans
+
+CREATE TABLE IF NOT EXISTS stellar_incoming_payments (
+    tx_hash TEXT NOT NULL,
+    op_index INTEGER NOT NULL CHECK (op_index >= 0),
+    account_address TEXT NOT NULL,
+    paging_token TEXT NOT NULL,
+    ledger BIGINT,
+    amount NUMERIC(36, 7),
+    asset_code TEXT,
+    memo TEXT,
+    transaction_id UUID REFERENCES transactions(transaction_id) ON DELETE SET NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('matched', 'not_pending', 'unmatched', 'no_memo')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (tx_hash, op_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_stellar_incoming_payments_account_ledger
+    ON stellar_incoming_payments(account_address, ledger);
+
+COMMENT ON TABLE stellar_incoming_payments IS 'Every incoming payment operation the monitor has applied; replays skip rows already present.';
+COMMENT ON COLUMN stellar_incoming_payments.outcome IS 'matched = credited to transaction_id; not_pending = matching transaction was no longer pending; unmatched = memo matched nothing; no_memo = payment had no memo.';
+
+ALTER TABLE stellar_cursors ADD COLUMN IF NOT EXISTS rescan_from TEXT;
+
+COMMENT ON COLUMN stellar_cursors.rescan_from IS 'Paging token an operator asked the monitor to replay from; cleared once picked up.';
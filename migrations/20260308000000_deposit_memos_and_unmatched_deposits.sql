@@ -0,0 +1,44 @@
+-- migrate:up
+-- Short unique deposit memos per order, and a quarantine for cNGN that arrives without a usable one
+
+CREATE TABLE IF NOT EXISTS deposit_memos (
+    memo TEXT PRIMARY KEY CHECK (octet_length(memo) <= 28),
+    transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(transaction_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE deposit_memos IS 'Text memo a user must attach when sending cNGN for a transaction; fits the 28-byte Stellar text memo.';
+
+CREATE TABLE IF NOT EXISTS unmatched_deposits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tx_hash TEXT NOT NULL,
+    op_index INTEGER NOT NULL CHECK (op_index >= 0),
+    source_account TEXT NOT NULL,
+    amount NUMERIC(36, 7) NOT NULL CHECK (amount > 0),
+    asset_code TEXT NOT NULL,
+    asset_issuer TEXT,
+    memo TEXT,
+    ledger BIGINT,
+    reason TEXT NOT NULL CHECK (reason IN ('no_memo', 'unknown_memo', 'not_pending', 'overpaid')),
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'attached', 'refund_processing', 'refunded', 'refund_failed')),
+    transaction_id UUID REFERENCES transactions(transaction_id) ON DELETE SET NULL,
+    refund_tx_hash TEXT,
+    resolved_by TEXT,
+    resolution_note TEXT,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (tx_hash, op_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unmatched_deposits_status_created
+    ON unmatched_deposits(status, created_at);
+
+COMMENT ON TABLE unmatched_deposits IS 'cNGN received by the system wallet that could not be credited to an order; resolved by an operator.';
+COMMENT ON COLUMN unmatched_deposits.reason IS 'no_memo / unknown_memo = nothing to match; not_pending = order already settled; overpaid = amount above what the order expected (row holds the excess).';
+COMMENT ON COLUMN unmatched_deposits.transaction_id IS 'Order the deposit was matched to (not_pending, overpaid) or attached to by an operator.';
+
+CREATE TRIGGER set_updated_at_unmatched_deposits
+  BEFORE UPDATE ON unmatched_deposits
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
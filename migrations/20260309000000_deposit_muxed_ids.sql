@@ -0,0 +1,20 @@
+-- migrate:up
+-- Per-order muxed IDs so deposits can be attributed by SEP-23 M-address instead of memo
+
+CREATE SEQUENCE IF NOT EXISTS deposit_muxed_id_seq START WITH 1000;
+
+ALTER TABLE deposit_memos
+    ADD COLUMN IF NOT EXISTS muxed_id BIGINT NOT NULL DEFAULT nextval('deposit_muxed_id_seq') UNIQUE;
+
+ALTER SEQUENCE deposit_muxed_id_seq OWNED BY deposit_memos.muxed_id;
+
+COMMENT ON COLUMN deposit_memos.muxed_id IS 'Muxed sub-account ID of the system wallet for this order; payments to the M-address need no memo.';
+
+-- Senders can pick any uint64, which overflows BIGINT
+ALTER TABLE unmatched_deposits ADD COLUMN IF NOT EXISTS muxed_id NUMERIC(20, 0);
+
+ALTER TABLE unmatched_deposits DROP CONSTRAINT IF EXISTS unmatched_deposits_reason_check;
+ALTER TABLE unmatched_deposits ADD CONSTRAINT unmatched_deposits_reason_check
+    CHECK (reason IN ('no_memo', 'unknown_memo', 'unknown_muxed_id', 'not_pending', 'overpaid'));
+
+COMMENT ON COLUMN unmatched_deposits.muxed_id IS 'Muxed ID the deposit was sent to, when the destination was an M-address.';
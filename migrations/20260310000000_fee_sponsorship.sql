@@ -0,0 +1,41 @@
+-- migrate:up
+-- Network fees the platform fee account pays for users via CAP-15 fee-bump transactions
+
+CREATE TABLE IF NOT EXISTS fee_sponsorship_budgets (
+    fee_account TEXT NOT NULL,
+    day DATE NOT NULL,
+    spent_stroops BIGINT NOT NULL DEFAULT 0 CHECK (spent_stroops >= 0),
+    sponsored_count INTEGER NOT NULL DEFAULT 0 CHECK (sponsored_count >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (fee_account, day)
+);
+
+COMMENT ON TABLE fee_sponsorship_budgets IS 'Fees reserved against the daily sponsorship budget, per fee account and UTC day.';
+COMMENT ON COLUMN fee_sponsorship_budgets.spent_stroops IS 'Sum of the maximum fees of sponsored envelopes; the network may charge less.';
+
+CREATE TRIGGER set_updated_at_fee_sponsorship_budgets
+  BEFORE UPDATE ON fee_sponsorship_budgets
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TABLE IF NOT EXISTS sponsored_transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    fee_account TEXT NOT NULL,
+    inner_source TEXT NOT NULL,
+    inner_tx_hash TEXT NOT NULL,
+    fee_bump_tx_hash TEXT NOT NULL UNIQUE,
+    fee_stroops BIGINT NOT NULL CHECK (fee_stroops > 0),
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'submitted', 'failed')),
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sponsored_transactions_inner_source
+    ON sponsored_transactions(inner_source, created_at);
+
+COMMENT ON TABLE sponsored_transactions IS 'Fee-bump envelopes signed by the platform fee account.';
+
+CREATE TRIGGER set_updated_at_sponsored_transactions
+  BEFORE UPDATE ON sponsored_transactions
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1,18 @@
+-- migrate:up
+-- Trustline reserves paid by the platform sponsor account (CAP-33)
+
+ALTER TABLE trustline_operations
+    ADD COLUMN IF NOT EXISTS sponsor_account TEXT,
+    ADD COLUMN IF NOT EXISTS sponsorship_status TEXT
+        CHECK (sponsorship_status IN ('pending', 'active', 'revoked'));
+
+ALTER TABLE trustline_operations
+    ADD CONSTRAINT chk_trustline_operations_sponsorship
+    CHECK ((sponsor_account IS NULL) = (sponsorship_status IS NULL));
+
+COMMENT ON COLUMN trustline_operations.sponsor_account IS 'Account paying the trustline base reserve, if sponsored.';
+COMMENT ON COLUMN trustline_operations.sponsorship_status IS 'pending until the sponsored trustline is created, active while it exists, revoked once the trustline is closed and the reserve returned.';
+
+CREATE INDEX IF NOT EXISTS idx_trustline_operations_active_sponsorships
+    ON trustline_operations(wallet_address, asset_code)
+    WHERE sponsorship_status = 'active';
@@ -0,0 +1,59 @@
+-- migrate:up
+-- Hot wallet treasury: balance threshold alerts and the sweep/top-up transfers they raise
+
+CREATE TABLE IF NOT EXISTS treasury_alerts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    wallet_address TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('below_min', 'above_max')),
+    balance NUMERIC(36, 7) NOT NULL,
+    threshold NUMERIC(36, 7) NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'resolved')),
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- At most one open alert per wallet, asset and breach direction
+CREATE UNIQUE INDEX IF NOT EXISTS idx_treasury_alerts_open
+    ON treasury_alerts(wallet_address, asset, kind)
+    WHERE status = 'open';
+
+COMMENT ON TABLE treasury_alerts IS 'Hot wallet balances that crossed a configured treasury threshold.';
+
+CREATE TRIGGER set_updated_at_treasury_alerts
+  BEFORE UPDATE ON treasury_alerts
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+CREATE TABLE IF NOT EXISTS treasury_transfers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL CHECK (kind IN ('sweep', 'top_up')),
+    asset TEXT NOT NULL,
+    amount NUMERIC(36, 7) NOT NULL CHECK (amount > 0),
+    from_address TEXT NOT NULL,
+    to_address TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'submitted', 'completed', 'failed', 'cancelled')),
+    tx_hash TEXT,
+    error TEXT,
+    resolved_by TEXT,
+    resolution_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- One outstanding transfer per direction and asset; a new one is queued only
+-- after the previous one settles
+CREATE UNIQUE INDEX IF NOT EXISTS idx_treasury_transfers_outstanding
+    ON treasury_transfers(kind, asset, from_address, to_address)
+    WHERE status IN ('queued', 'submitted');
+
+CREATE INDEX IF NOT EXISTS idx_treasury_transfers_created_at
+    ON treasury_transfers(created_at DESC);
+
+COMMENT ON TABLE treasury_transfers IS 'Sweeps from the hot wallet to cold storage, and top-up requests for operators to fund from cold storage.';
+COMMENT ON COLUMN treasury_transfers.status IS 'Sweeps left in submitted with an error had an unknown outcome; check tx_hash on-chain before resolving.';
+
+CREATE TRIGGER set_updated_at_treasury_transfers
+  BEFORE UPDATE ON treasury_transfers
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
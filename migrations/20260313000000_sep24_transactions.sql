@@ -0,0 +1,14 @@
+-- migrate:up
+-- SEP-24 interactive deposits and withdrawals: transactions start incomplete until the user finishes the interactive flow
+
+INSERT INTO transaction_statuses (code, description) VALUES
+  ('incomplete', 'Opened through SEP-24; waiting for the user to finish the interactive flow')
+ON CONFLICT (code) DO NOTHING;
+
+-- SEP-24 /transactions lists a wallet's transfers newest first
+CREATE INDEX IF NOT EXISTS idx_transactions_wallet_type_created
+    ON transactions(wallet_address, type, created_at DESC);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_blockchain_tx_hash
+    ON transactions(blockchain_tx_hash)
+    WHERE blockchain_tx_hash IS NOT NULL;
@@ -0,0 +1,22 @@
+-- migrate:up
+-- SEP-38 firm quotes: onramp_quotes also holds cNGN → NGN quotes and the
+-- account and context a quote was issued for.
+--
+-- For offramp rows amount_ngn is the NGN paid out and gross_cngn = net_cngn is
+-- the cNGN sold; the fee is charged on the NGN side and only recorded in fee_total.
+
+ALTER TABLE onramp_quotes
+    ADD COLUMN IF NOT EXISTS direction TEXT NOT NULL DEFAULT 'onramp'
+        CHECK (direction IN ('onramp', 'offramp')),
+    ADD COLUMN IF NOT EXISTS wallet_address TEXT,
+    ADD COLUMN IF NOT EXISTS context TEXT,
+    ADD COLUMN IF NOT EXISTS sell_amount NUMERIC(36, 18),
+    ADD COLUMN IF NOT EXISTS buy_amount NUMERIC(36, 18),
+    ADD COLUMN IF NOT EXISTS fee_total NUMERIC(36, 18);
+
+COMMENT ON COLUMN onramp_quotes.direction IS 'onramp: NGN sold for cNGN; offramp: cNGN sold for NGN.';
+COMMENT ON COLUMN onramp_quotes.fee_total IS 'Total fee, in the asset being bought.';
+
+CREATE INDEX IF NOT EXISTS idx_onramp_quotes_wallet_address
+    ON onramp_quotes(wallet_address)
+    WHERE wallet_address IS NOT NULL;
@@ -0,0 +1,44 @@
+-- migrate:up
+-- SEP-12 customers: KYC fields submitted by a Stellar account (and memo, for
+-- shared accounts), encrypted by the application, with the verification
+-- status and the tier that sets the account's transaction limits.
+
+CREATE TABLE IF NOT EXISTS kyc_customers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    stellar_account TEXT NOT NULL,
+    memo TEXT,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    country_code TEXT CHECK (country_code IN ('NGA', 'KEN')),
+    status TEXT NOT NULL DEFAULT 'needs_info'
+        CHECK (status IN ('needs_info', 'processing', 'accepted', 'rejected')),
+    tier SMALLINT NOT NULL DEFAULT 0 CHECK (tier BETWEEN 0 AND 2),
+    requested_tier SMALLINT CHECK (requested_tier BETWEEN 1 AND 2),
+    provided_fields TEXT[] NOT NULL DEFAULT '{}',
+    fields_nonce BYTEA,
+    fields_ciphertext BYTEA,
+    provider TEXT,
+    provider_reference TEXT,
+    status_message TEXT,
+    verified_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- One customer per account and memo; NULL memo is the account holder itself
+CREATE UNIQUE INDEX IF NOT EXISTS idx_kyc_customers_account_memo
+    ON kyc_customers(stellar_account, COALESCE(memo, ''));
+
+CREATE INDEX IF NOT EXISTS idx_kyc_customers_user_id
+    ON kyc_customers(user_id)
+    WHERE user_id IS NOT NULL;
+
+COMMENT ON TABLE kyc_customers IS 'SEP-12 customers and their KYC verification state.';
+COMMENT ON COLUMN kyc_customers.tier IS '0: unverified, 1: identity number verified, 2: full profile verified.';
+COMMENT ON COLUMN kyc_customers.requested_tier IS 'Tier under verification while status is processing.';
+COMMENT ON COLUMN kyc_customers.provided_fields IS 'Names of the SEP-9 fields held, so status can be reported without decrypting.';
+COMMENT ON COLUMN kyc_customers.fields_ciphertext IS 'AES-256-GCM encrypted JSON object of the field values; the customer id is the associated data.';
+
+CREATE TRIGGER set_updated_at_kyc_customers
+  BEFORE UPDATE ON kyc_customers
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
@@ -0,0 +1,12 @@
+-- migrate:up
+-- Deposits short of the order amount are quarantined instead of credited
+
+ALTER TABLE unmatched_deposits DROP CONSTRAINT IF EXISTS unmatched_deposits_reason_check;
+ALTER TABLE unmatched_deposits ADD CONSTRAINT unmatched_deposits_reason_check
+    CHECK (reason IN ('no_memo', 'unknown_memo', 'unknown_muxed_id', 'not_pending', 'overpaid', 'underpaid'));
+
+ALTER TABLE stellar_incoming_payments DROP CONSTRAINT IF EXISTS stellar_incoming_payments_outcome_check;
+ALTER TABLE stellar_incoming_payments ADD CONSTRAINT stellar_incoming_payments_outcome_check
+    CHECK (outcome IN ('matched', 'not_pending', 'unmatched', 'no_memo', 'underpaid'));
+
+COMMENT ON COLUMN unmatched_deposits.reason IS 'no_memo / unknown_memo / unknown_muxed_id = nothing to match; not_pending = order already settled; overpaid = amount above what the order expected (row holds the excess); underpaid = amount below what the order expected (row holds all of it, the order keeps waiting).';
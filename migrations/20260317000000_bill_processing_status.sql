@@ -0,0 +1,7 @@
+-- migrate:up
+-- Bill payments being paid out by the bill processor. Kept apart from
+-- 'processing' so the Stellar confirmation monitor leaves them alone.
+
+INSERT INTO transaction_statuses (code, description) VALUES
+  ('bill_processing', 'Bill payment funded; with the bill payment provider')
+ON CONFLICT (code) DO NOTHING;
@@ -0,0 +1,603 @@
+//! Operator endpoints
+//!
+//! Require the `admin` scope. Used to recover from incidents, e.g. replaying
+//! Horizon history the transaction monitor may have missed, resolving cNGN
+//! deposits that arrived without a usable memo, or checking hot wallet
+//! treasury balances.
+
+use crate::auth::Principal;
+use crate::chains::stellar::client::StellarClient;
+use crate::chains::stellar::payment::CngnMemo;
+use crate::chains::stellar::submission::{QueuedPayment, SubmissionQueue};
+use crate::chains::stellar::types::is_valid_stellar_address;
+use crate::database::error::DatabaseError;
+use crate::database::repository::Repository;
+use crate::database::stellar_cursor_repository::StellarCursorRepository;
+use crate::database::transaction_repository::TransactionRepository;
+use crate::database::unmatched_deposit_repository::{UnmatchedDeposit, UnmatchedDepositRepository};
+use crate::middleware::error::{get_request_id_from_headers, json_error_response, ErrorResponse};
+use crate::services::fee_sponsorship::FeeSponsorshipService;
+use crate::services::treasury::TreasuryService;
+use crate::workers::transaction_monitor::{
+    covers_expected, credit_deposit, credited_status, is_awaiting_deposit, INCOMING_CURSOR_STREAM,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const MAX_MEMO_LEN: usize = 28;
+const DEFAULT_DEPOSIT_LIMIT: i64 = 50;
+const MAX_DEPOSIT_LIMIT: i64 = 200;
+
+#[derive(Clone)]
+pub struct MonitorAdminState {
+    pub cursor_repo: Arc<StellarCursorRepository>,
+    /// Account watched by the transaction monitor
+    pub system_wallet_address: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RescanQuery {
+    pub from_ledger: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RescanResponse {
+    pub account: String,
+    pub from_ledger: u32,
+    pub cursor: String,
+    pub status: &'static str,
+}
+
+#[derive(Clone)]
+pub struct DepositAdminState {
+    pub pool: PgPool,
+    pub deposits: Arc<UnmatchedDepositRepository>,
+    pub transactions: Arc<TransactionRepository>,
+    /// Hot wallet queue holding quarantined cNGN; refunds are sent through it
+    pub payout_queue: Option<SubmissionQueue>,
+}
+
+#[derive(Clone)]
+pub struct FeeSponsorshipAdminState {
+    pub pool: PgPool,
+    pub stellar_client: Option<StellarClient>,
+}
+
+#[derive(Clone)]
+pub struct TreasuryAdminState {
+    /// `None` when the treasury is not configured
+    pub service: Option<Arc<TreasuryService>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnmatchedDepositQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachDepositRequest {
+    pub transaction_id: Uuid,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RefundDepositRequest {
+    /// Defaults to the account the deposit came from
+    pub destination: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveTransferRequest {
+    /// `completed`, `failed` or `cancelled`
+    pub status: String,
+    pub note: Option<String>,
+}
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+/// POST /admin/monitor/rescan?from_ledger=N
+///
+/// Moves the incoming payment cursor back to the start of `from_ledger`. The
+/// monitor picks the request up on its next poll and replays from there;
+/// payments it already applied are skipped.
+pub async fn rescan(
+    State(state): State<MonitorAdminState>,
+    headers: HeaderMap,
+    Query(query): Query<RescanQuery>,
+) -> Result<Response, HandlerError> {
+    let request_id = get_request_id_from_headers(&headers);
+    if state.system_wallet_address.is_empty() {
+        return Err(json_error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "transaction monitor has no SYSTEM_WALLET_ADDRESS configured",
+            request_id,
+        ));
+    }
+    let from_ledger = match query.from_ledger {
+        Some(ledger) if ledger > 0 => ledger,
+        _ => {
+            return Err(json_error_response(
+                StatusCode::BAD_REQUEST,
+                "from_ledger must be a positive ledger sequence",
+                request_id,
+            ))
+        }
+    };
+
+    let cursor = ledger_start_cursor(from_ledger);
+    state
+        .cursor_repo
+        .request_rescan(
+            &state.system_wallet_address,
+            INCOMING_CURSOR_STREAM,
+            &cursor,
+        )
+        .await
+        .map_err(|e| {
+            error!(error = %e, "failed to record incoming payment rescan request");
+            json_error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to record rescan request",
+                get_request_id_from_headers(&headers),
+            )
+        })?;
+
+    info!(
+        account = %state.system_wallet_address,
+        from_ledger,
+        cursor = %cursor,
+        "incoming payment rescan requested"
+    );
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(RescanResponse {
+            account: state.system_wallet_address,
+            from_ledger,
+            cursor,
+            status: "scheduled",
+        }),
+    )
+        .into_response())
+}
+
+/// GET /admin/deposits/unmatched?status=pending
+pub async fn list_unmatched_deposits(
+    State(state): State<DepositAdminState>,
+    headers: HeaderMap,
+    Query(query): Query<UnmatchedDepositQuery>,
+) -> Result<Response, HandlerError> {
+    if let Some(status) = query.status.as_deref() {
+        if !matches!(
+            status,
+            "pending" | "attached" | "refund_processing" | "refunded" | "refund_failed"
+        ) {
+            return Err(json_error_response(
+                StatusCode::BAD_REQUEST,
+                "status must be one of pending, attached, refund_processing, refunded, refund_failed",
+                get_request_id_from_headers(&headers),
+            ));
+        }
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DEPOSIT_LIMIT)
+        .clamp(1, MAX_DEPOSIT_LIMIT);
+    let deposits = state
+        .deposits
+        .list(query.status.as_deref(), limit)
+        .await
+        .map_err(|e| database_error(e, &headers))?;
+    Ok(Json(serde_json::json!({ "deposits": deposits })).into_response())
+}
+
+/// POST /admin/deposits/unmatched/{id}/attach
+///
+/// Credits a quarantined deposit to an order that is still waiting for its
+/// cNGN, exactly as if it had arrived with the right memo.
+pub async fn attach_unmatched_deposit(
+    State(state): State<DepositAdminState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Path(deposit_id): Path<Uuid>,
+    Json(request): Json<AttachDepositRequest>,
+) -> Result<Response, HandlerError> {
+    let pending = load_pending(&state, deposit_id, &headers).await?;
+
+    let tx = state
+        .transactions
+        .find_by_id(&request.transaction_id.to_string())
+        .await
+        .map_err(|e| database_error(e, &headers))?
+        .ok_or_else(|| not_found("transaction", &headers))?;
+    if !is_awaiting_deposit(&tx.status) {
+        return Err(json_error_response(
+            StatusCode::CONFLICT,
+            format!(
+                "transaction is {} and no longer awaiting a deposit",
+                tx.status
+            ),
+            get_request_id_from_headers(&headers),
+        ));
+    }
+    if !covers_expected(Some(&pending.amount), &tx.cngn_amount) {
+        return Err(json_error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!(
+                "deposit of {} cNGN is below the {} cNGN the transaction expects",
+                pending.amount, tx.cngn_amount
+            ),
+            get_request_id_from_headers(&headers),
+        ));
+    }
+
+    // Resolving the deposit and crediting the order commit together
+    let mut db_tx = state
+        .pool
+        .begin()
+        .await
+        .map_err(|e| database_error(DatabaseError::from_sqlx(e), &headers))?;
+    let deposit = UnmatchedDepositRepository::attach_in(
+        &mut db_tx,
+        deposit_id,
+        tx.transaction_id,
+        &principal.subject,
+        request.note.as_deref(),
+    )
+    .await
+    .map_err(|e| database_error(e, &headers))?
+    .ok_or_else(|| already_resolved(&headers))?;
+
+    let next_status = credited_status(&tx.r#type, false);
+    let credited = credit_deposit(
+        &mut db_tx,
+        &tx,
+        next_status,
+        &deposit.tx_hash,
+        deposit.ledger,
+        Some(&deposit.amount),
+    )
+    .await
+    .map_err(|e| {
+        error!(
+            deposit_id = %deposit_id,
+            transaction_id = %tx.transaction_id,
+            error = %e,
+            "failed to credit transaction with unmatched deposit"
+        );
+        json_error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "the transaction could not be updated",
+            get_request_id_from_headers(&headers),
+        )
+    })?
+    .ok_or_else(|| {
+        json_error_response(
+            StatusCode::CONFLICT,
+            "transaction is no longer awaiting a deposit",
+            get_request_id_from_headers(&headers),
+        )
+    })?;
+    db_tx
+        .commit()
+        .await
+        .map_err(|e| database_error(DatabaseError::from_sqlx(e), &headers))?;
+    credited.announce(&state.pool).await;
+
+    info!(
+        deposit_id = %deposit_id,
+        transaction_id = %tx.transaction_id,
+        operator = %principal.subject,
+        "unmatched deposit attached to transaction"
+    );
+    Ok(Json(deposit).into_response())
+}
+
+/// POST /admin/deposits/unmatched/{id}/refund
+///
+/// Sends the quarantined cNGN back, to the sender unless `destination` says
+/// otherwise. A submission whose outcome is unknown is left in
+/// `refund_failed` for review rather than retried, so it can't be paid twice.
+pub async fn refund_unmatched_deposit(
+    State(state): State<DepositAdminState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Path(deposit_id): Path<Uuid>,
+    request: Option<Json<RefundDepositRequest>>,
+) -> Result<Response, HandlerError> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    let Some(queue) = state.payout_queue.clone() else {
+        return Err(unavailable(
+            "refunds need a hot wallet signer (HOT_WALLET_SIGNER)",
+            &headers,
+        ));
+    };
+
+    let deposit = load_pending(&state, deposit_id, &headers).await?;
+    let destination = request
+        .destination
+        .clone()
+        .unwrap_or_else(|| deposit.source_account.clone());
+    if !is_valid_stellar_address(&destination) {
+        return Err(json_error_response(
+            StatusCode::BAD_REQUEST,
+            "destination must be a valid Stellar account",
+            get_request_id_from_headers(&headers),
+        ));
+    }
+
+    let deposit = state
+        .deposits
+        .begin_refund(deposit_id, &principal.subject, request.note.as_deref())
+        .await
+        .map_err(|e| database_error(e, &headers))?
+        .ok_or_else(|| already_resolved(&headers))?;
+
+    let payment = QueuedPayment::cngn(
+        &destination,
+        deposit.amount.to_string(),
+        CngnMemo::Text(refund_memo(&deposit.tx_hash)),
+    );
+    let (status, refund_hash, error_message) = match queue.pay(payment).await {
+        Ok(submitted) => ("refunded", submitted.tx_hash, None),
+        Err(e) if e.nothing_sent() => {
+            // Nothing was sent; hand the deposit back to the queue
+            warn!(deposit_id = %deposit_id, error = %e, "failed to send deposit refund");
+            let _ = state
+                .deposits
+                .finish_refund(deposit_id, "pending", None, Some(&e.to_string()))
+                .await;
+            return Err(json_error_response(
+                StatusCode::BAD_GATEWAY,
+                format!("failed to send refund: {}", e),
+                get_request_id_from_headers(&headers),
+            ));
+        }
+        Err(e) => (
+            "refund_failed",
+            e.in_flight_hash.clone().unwrap_or_default(),
+            Some(e.to_string()),
+        ),
+    };
+    let deposit = state
+        .deposits
+        .finish_refund(
+            deposit_id,
+            status,
+            Some(&refund_hash),
+            error_message.as_deref(),
+        )
+        .await
+        .map_err(|e| database_error(e, &headers))?;
+
+    if let Some(message) = error_message {
+        error!(
+            deposit_id = %deposit_id,
+            refund_tx_hash = %refund_hash,
+            error = %message,
+            "deposit refund submission failed; check the hash on-chain before retrying"
+        );
+        return Err(json_error_response(
+            StatusCode::BAD_GATEWAY,
+            format!("refund submission failed: {}", message),
+            get_request_id_from_headers(&headers),
+        ));
+    }
+
+    info!(
+        deposit_id = %deposit_id,
+        refund_tx_hash = %refund_hash,
+        destination = %destination,
+        operator = %principal.subject,
+        "unmatched deposit refunded"
+    );
+    Ok(Json(deposit).into_response())
+}
+
+/// GET /admin/fee-sponsorship
+///
+/// Today's spend against the fee-bump sponsorship budget.
+pub async fn fee_sponsorship_budget(
+    State(state): State<FeeSponsorshipAdminState>,
+    headers: HeaderMap,
+) -> Result<Response, HandlerError> {
+    let Some(stellar_client) = state.stellar_client else {
+        return Err(unavailable(
+            "Stellar client disabled by configuration",
+            &headers,
+        ));
+    };
+    let service = FeeSponsorshipService::from_env(stellar_client, state.pool)
+        .map_err(|e| unavailable(&e.to_string(), &headers))?;
+    let status = service.budget_status().await.map_err(|e| {
+        error!(error = %e, "failed to load fee sponsorship budget");
+        json_error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to load fee sponsorship budget",
+            get_request_id_from_headers(&headers),
+        )
+    })?;
+    Ok(Json(status).into_response())
+}
+
+/// GET /admin/treasury
+///
+/// Hot wallet balances against their thresholds, open alerts and recent
+/// sweeps and top-up requests.
+pub async fn treasury_overview(
+    State(state): State<TreasuryAdminState>,
+    headers: HeaderMap,
+) -> Result<Response, HandlerError> {
+    let service = treasury_service(&state, &headers)?;
+    let snapshot = service.snapshot().await.map_err(|e| {
+        error!(error = %e, "failed to load treasury snapshot");
+        json_error_response(
+            StatusCode::BAD_GATEWAY,
+            format!("failed to load treasury snapshot: {}", e),
+            get_request_id_from_headers(&headers),
+        )
+    })?;
+    Ok(Json(snapshot).into_response())
+}
+
+/// POST /admin/treasury/transfers/{id}/resolve
+///
+/// Settles a queued or submitted transfer by hand, e.g. a top-up once it has
+/// been funded from cold storage, or a sweep whose outcome was unknown.
+pub async fn resolve_treasury_transfer(
+    State(state): State<TreasuryAdminState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Path(transfer_id): Path<Uuid>,
+    Json(request): Json<ResolveTransferRequest>,
+) -> Result<Response, HandlerError> {
+    let service = treasury_service(&state, &headers)?;
+    if !matches!(
+        request.status.as_str(),
+        "completed" | "failed" | "cancelled"
+    ) {
+        return Err(json_error_response(
+            StatusCode::BAD_REQUEST,
+            "status must be one of completed, failed or cancelled",
+            get_request_id_from_headers(&headers),
+        ));
+    }
+
+    let transfer = service
+        .resolve_transfer(
+            transfer_id,
+            &request.status,
+            &principal.subject,
+            request.note.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            error!(error = %e, "admin request failed");
+            json_error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database error",
+                get_request_id_from_headers(&headers),
+            )
+        })?
+        .ok_or_else(|| {
+            json_error_response(
+                StatusCode::CONFLICT,
+                "transfer not found or already settled",
+                get_request_id_from_headers(&headers),
+            )
+        })?;
+
+    info!(
+        transfer_id = %transfer_id,
+        status = %transfer.status,
+        operator = %principal.subject,
+        "treasury transfer resolved"
+    );
+    Ok(Json(transfer).into_response())
+}
+
+fn treasury_service(
+    state: &TreasuryAdminState,
+    headers: &HeaderMap,
+) -> Result<Arc<TreasuryService>, HandlerError> {
+    state.service.clone().ok_or_else(|| {
+        unavailable(
+            "treasury is not configured (TREASURY_HOT_WALLET_ADDRESS or SYSTEM_WALLET_ADDRESS)",
+            headers,
+        )
+    })
+}
+
+async fn load_pending(
+    state: &DepositAdminState,
+    deposit_id: Uuid,
+    headers: &HeaderMap,
+) -> Result<UnmatchedDeposit, HandlerError> {
+    let deposit = state
+        .deposits
+        .find_by_id(deposit_id)
+        .await
+        .map_err(|e| database_error(e, headers))?
+        .ok_or_else(|| not_found("unmatched deposit", headers))?;
+    if deposit.status != "pending" {
+        return Err(already_resolved(headers));
+    }
+    Ok(deposit)
+}
+
+fn refund_memo(deposit_hash: &str) -> String {
+    let mut memo = format!("REFUND-{}", deposit_hash);
+    memo.truncate(MAX_MEMO_LEN);
+    memo
+}
+
+fn not_found(what: &str, headers: &HeaderMap) -> HandlerError {
+    json_error_response(
+        StatusCode::NOT_FOUND,
+        format!("{} not found", what),
+        get_request_id_from_headers(headers),
+    )
+}
+
+fn already_resolved(headers: &HeaderMap) -> HandlerError {
+    json_error_response(
+        StatusCode::CONFLICT,
+        "deposit has already been resolved",
+        get_request_id_from_headers(headers),
+    )
+}
+
+fn unavailable(message: &str, headers: &HeaderMap) -> HandlerError {
+    json_error_response(
+        StatusCode::SERVICE_UNAVAILABLE,
+        message,
+        get_request_id_from_headers(headers),
+    )
+}
+
+fn database_error(e: DatabaseError, headers: &HeaderMap) -> HandlerError {
+    error!(error = %e, "admin request failed");
+    json_error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "database error",
+        get_request_id_from_headers(headers),
+    )
+}
+
+/// Paging token just before the first operation of `ledger`. Horizon cursors
+/// are exclusive, so paging from it starts with the ledger's first payment.
+fn ledger_start_cursor(ledger: u32) -> String {
+    ((ledger as i64) << 32).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_start_cursor() {
+        assert_eq!(ledger_start_cursor(1), "4294967296");
+        // first operation of the first transaction in ledger 100
+        let first_op = (100i64 << 32) | (1 << 12) | 1;
+        assert!(ledger_start_cursor(100).parse::<i64>().unwrap() < first_op);
+        assert!(ledger_start_cursor(99).parse::<i64>().unwrap() < (100i64 << 32));
+    }
+
+    #[test]
+    fn test_refund_memo_fits_stellar_limit() {
+        let memo = refund_memo(&"ab".repeat(32));
+        assert_eq!(memo.len(), MAX_MEMO_LEN);
+        assert!(memo.starts_with("REFUND-abab"));
+    }
+}
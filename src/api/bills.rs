@@ -2,11 +2,39 @@
 //!
 //! Provides a public endpoint to list available bill payment providers in Nigeria.
 //! Users can discover what services they can pay for using cNGN.
+//!
+//! Authenticated clients can verify a customer account, create a bill payment
+//! funded by a cNGN deposit, and poll it until the token is issued. A SEP-10
+//! session may only pay from and read bills for its own account; an API client
+//! only reads the bills it created.
 
-use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use crate::auth::{Principal, PrincipalKind};
+use crate::chains::stellar::types::{is_valid_stellar_address, muxed_address};
+use crate::database::bill_payment_repository::BillPaymentRepository;
+use crate::database::deposit_memo_repository::DepositMemoRepository;
+use crate::database::error::{DatabaseError, DatabaseErrorKind};
+use crate::database::repository::Repository;
+use crate::database::transaction_repository::TransactionRepository;
+use crate::workers::bill_processor::account_verification::AccountVerifier;
+use crate::workers::bill_processor::providers::{provider_chain, BillProviders};
+use crate::workers::bill_processor::token_manager::TokenManager;
+use crate::workers::bill_processor::types::{
+    BillProcessingState, ProcessingError, VerificationRequest,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bigdecimal::{BigDecimal, Zero};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use std::collections::HashMap;
-use tracing::info;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
 /// Supported provider categories
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -1748,6 +1776,510 @@ pub fn get_all_providers() -> Vec<BillProvider> {
     ]
 }
 
+// ==================== BILL PAYMENTS ====================
+
+/// Shared state for the bill verification, payment and status endpoints
+#[derive(Clone)]
+pub struct BillsState {
+    pub pool: PgPool,
+    pub bill_repo: Arc<BillPaymentRepository>,
+    pub transaction_repo: Arc<TransactionRepository>,
+    /// Issues the memo users attach to their cNGN deposit
+    pub deposit_memo_repo: Arc<DepositMemoRepository>,
+    pub providers: Arc<BillProviders>,
+    /// Account that receives cNGN for bill payments
+    pub system_wallet_address: String,
+    pub cngn_asset_code: String,
+    pub cngn_issuer: String,
+}
+
+/// Request body for POST /api/bills/verify
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifyBillAccountRequest {
+    /// `provider_code` (or `provider_id`) from the provider catalog
+    pub provider_code: String,
+    pub account_number: String,
+    #[serde(default)]
+    pub account_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyBillAccountResponse {
+    pub valid: bool,
+    pub provider_code: String,
+    pub bill_type: String,
+    pub account_number: String,
+    pub customer_name: String,
+    pub account_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outstanding_balance: Option<f64>,
+}
+
+/// Request body for POST /api/bills/pay
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayBillRequest {
+    pub wallet_address: String,
+    pub provider_code: String,
+    pub account_number: String,
+    /// Bill amount in NGN
+    pub amount: String,
+    #[serde(default)]
+    pub account_type: Option<String>,
+    #[serde(default)]
+    pub phone_number: Option<String>,
+    /// Data bundle or cable package code
+    #[serde(default)]
+    pub variation_code: Option<String>,
+}
+
+/// Where and how the user sends cNGN to fund the bill
+#[derive(Debug, Clone, Serialize)]
+pub struct DepositInstructions {
+    pub destination: String,
+    pub asset_code: String,
+    pub asset_issuer: String,
+    pub amount: String,
+    pub memo: String,
+    pub memo_type: String,
+    /// SEP-23 M-address for this order; wallets that support it can pay here
+    /// without a memo
+    pub muxed_destination: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayBillResponse {
+    pub transaction_id: Uuid,
+    pub status: String,
+    pub bill_type: String,
+    pub provider_code: String,
+    pub account_number: String,
+    pub amount: String,
+    pub fee: String,
+    pub total_cngn: String,
+    pub deposit: DepositInstructions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BillStatusResponse {
+    pub transaction_id: Uuid,
+    pub status: String,
+    pub bill_type: String,
+    pub provider_code: String,
+    pub account_number: String,
+    pub amount: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Customer-facing message, including the formatted token once the bill is paid
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refund_tx_hash: Option<String>,
+    pub retry_count: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// POST /api/bills/verify
+pub async fn verify_bill_account(
+    State(state): State<BillsState>,
+    Json(request): Json<VerifyBillAccountRequest>,
+) -> Response {
+    let Some(provider) = find_catalog_provider(&request.provider_code) else {
+        return unknown_provider(&request.provider_code);
+    };
+    let bill_type = provider.category.to_string();
+    let verification = VerificationRequest {
+        provider_code: provider.provider_code.clone(),
+        account_number: request.account_number.trim().to_string(),
+        account_type: request
+            .account_type
+            .clone()
+            .unwrap_or_else(|| "prepaid".to_string()),
+        bill_type: bill_type.clone(),
+    };
+
+    let chain = provider_chain(&state.providers, &bill_type);
+    if chain.is_empty() {
+        return bill_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "PROVIDER_UNAVAILABLE",
+            "No bill payment provider is configured for this bill type".to_string(),
+        );
+    }
+
+    for (name, aggregator) in chain {
+        match AccountVerifier::verify(aggregator.as_ref(), &verification).await {
+            Ok(account) => {
+                info!(provider = name, bill_type = %bill_type, "bill account verified");
+                return (
+                    StatusCode::OK,
+                    Json(VerifyBillAccountResponse {
+                        valid: true,
+                        provider_code: provider.provider_code,
+                        bill_type,
+                        account_number: account.account_number,
+                        customer_name: account.customer_name,
+                        account_type: account.account_type,
+                        outstanding_balance: account.outstanding_balance,
+                    }),
+                )
+                    .into_response();
+            }
+            Err(ProcessingError::AccountVerificationFailed { reason }) => {
+                return bill_error(StatusCode::UNPROCESSABLE_ENTITY, "ACCOUNT_INVALID", reason);
+            }
+            Err(e) => warn!(provider = name, error = %e, "bill account verification unavailable"),
+        }
+    }
+
+    bill_error(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "PROVIDER_UNAVAILABLE",
+        "Account verification is temporarily unavailable. Please try again.".to_string(),
+    )
+}
+
+/// POST /api/bills/pay
+///
+/// Creates the transaction and bill payment in `pending_payment` and returns the
+/// cNGN deposit that funds it. The bill processor takes over once the deposit lands.
+pub async fn pay_bill(
+    State(state): State<BillsState>,
+    principal: Principal,
+    Json(request): Json<PayBillRequest>,
+) -> Response {
+    if !is_valid_stellar_address(&request.wallet_address) {
+        return bill_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_WALLET",
+            "wallet_address is not a valid Stellar address".to_string(),
+        );
+    }
+    if !may_pay_from(&principal, &request.wallet_address) {
+        return bill_error(
+            StatusCode::FORBIDDEN,
+            "WALLET_MISMATCH",
+            "wallet_address does not match the authenticated account".to_string(),
+        );
+    }
+    let Some(provider) = find_catalog_provider(&request.provider_code) else {
+        return unknown_provider(&request.provider_code);
+    };
+    if provider.status != ProviderStatus::Active {
+        return bill_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "PROVIDER_UNAVAILABLE",
+            format!("{} is not accepting payments right now", provider.name),
+        );
+    }
+    let account_number = request.account_number.trim();
+    if account_number.is_empty() {
+        return bill_error(
+            StatusCode::BAD_REQUEST,
+            "INVALID_ACCOUNT",
+            "account_number is required".to_string(),
+        );
+    }
+
+    let amount = match BigDecimal::from_str(request.amount.trim()) {
+        Ok(amount) if amount > BigDecimal::zero() => amount.round(2),
+        _ => {
+            return bill_error(
+                StatusCode::BAD_REQUEST,
+                "INVALID_AMOUNT",
+                "amount must be a positive number".to_string(),
+            )
+        }
+    };
+    if let Err(message) = check_amount_limits(&provider, &amount) {
+        return bill_error(StatusCode::BAD_REQUEST, "AMOUNT_OUT_OF_RANGE", message);
+    }
+    let fee = bill_fee(&provider, &amount);
+    let total = &amount + &fee;
+
+    let bill_type = provider.category.to_string();
+    let metadata = serde_json::json!({
+        "bill_type": bill_type,
+        "provider_code": provider.provider_code,
+        "account_number": account_number,
+        "account_type": request.account_type,
+        "phone_number": request.phone_number,
+        "variation_code": request.variation_code,
+        "fee": fee.to_string(),
+        "requested_by": principal.subject,
+    });
+
+    // The processor only picks up bills, so an orphaned transaction row would
+    // never be funded or cleaned up
+    let mut db_tx = match state.pool.begin().await {
+        Ok(db_tx) => db_tx,
+        Err(e) => return database_error(DatabaseError::from_sqlx(e)),
+    };
+    let tx = match TransactionRepository::create_transaction_in(
+        &mut db_tx,
+        &request.wallet_address,
+        "bill_payment",
+        "cNGN",
+        "NGN",
+        total.clone(),
+        amount.clone(),
+        total.clone(),
+        "pending",
+        None,
+        None,
+        metadata,
+    )
+    .await
+    {
+        Ok(tx) => tx,
+        Err(e) => return database_error(e),
+    };
+    if let Err(e) = BillPaymentRepository::create_bill_payment_in(
+        &mut db_tx,
+        tx.transaction_id,
+        &provider.provider_code,
+        account_number,
+        &bill_type,
+        None,
+        false,
+    )
+    .await
+    {
+        error!(transaction_id = %tx.transaction_id, error = %e, "failed to create bill payment");
+        return database_error(e);
+    }
+    if let Err(e) = db_tx.commit().await {
+        return database_error(DatabaseError::from_sqlx(e));
+    }
+
+    let reference = match state.deposit_memo_repo.assign(tx.transaction_id).await {
+        Ok(reference) => reference,
+        Err(e) => {
+            error!(transaction_id = %tx.transaction_id, error = %e, "failed to assign deposit memo");
+            let _ = state
+                .transaction_repo
+                .update_error(
+                    &tx.transaction_id.to_string(),
+                    "failed to assign deposit memo",
+                )
+                .await;
+            return database_error(e);
+        }
+    };
+
+    info!(
+        transaction_id = %tx.transaction_id,
+        provider_code = %provider.provider_code,
+        amount = %amount,
+        "bill payment created"
+    );
+
+    (
+        StatusCode::CREATED,
+        Json(PayBillResponse {
+            transaction_id: tx.transaction_id,
+            status: BillProcessingState::PendingPayment.as_str().to_string(),
+            bill_type,
+            provider_code: provider.provider_code,
+            account_number: account_number.to_string(),
+            amount: amount.to_string(),
+            fee: fee.to_string(),
+            total_cngn: total.to_string(),
+            deposit: DepositInstructions {
+                destination: state.system_wallet_address.clone(),
+                asset_code: state.cngn_asset_code.clone(),
+                asset_issuer: state.cngn_issuer.clone(),
+                amount: total.to_string(),
+                memo: reference.memo,
+                memo_type: "text".to_string(),
+                muxed_destination: u64::try_from(reference.muxed_id)
+                    .ok()
+                    .and_then(|id| muxed_address(&state.system_wallet_address, id)),
+            },
+        }),
+    )
+        .into_response()
+}
+
+/// GET /api/bills/{id}
+pub async fn get_bill_status(
+    State(state): State<BillsState>,
+    principal: Principal,
+    Path(transaction_id): Path<Uuid>,
+) -> Response {
+    let not_found = || {
+        bill_error(
+            StatusCode::NOT_FOUND,
+            "BILL_NOT_FOUND",
+            format!("No bill payment found for transaction {}", transaction_id),
+        )
+    };
+    let bill = match state
+        .bill_repo
+        .find_processing_by_transaction_id(transaction_id)
+        .await
+    {
+        Ok(Some(bill)) => bill,
+        Ok(None) => return not_found(),
+        Err(e) => return database_error(e),
+    };
+
+    // Someone else's bill looks exactly like a missing one
+    let owned = match principal.stellar_account() {
+        Some(account) => bill.wallet_address == account,
+        None => match state
+            .transaction_repo
+            .find_by_id(&transaction_id.to_string())
+            .await
+        {
+            Ok(tx) => {
+                tx.is_some_and(|tx| requested_by(&tx.metadata) == Some(principal.subject.as_str()))
+            }
+            Err(e) => return database_error(e),
+        },
+    };
+    if !owned {
+        return not_found();
+    }
+
+    let processing_state = BillProcessingState::from_str(&bill.status);
+    let message = match processing_state {
+        Some(BillProcessingState::Completed) => {
+            TokenManager::format_for_notification(bill.token.as_deref(), &bill.bill_type)
+        }
+        _ => status_message(processing_state).to_string(),
+    };
+
+    (
+        StatusCode::OK,
+        Json(BillStatusResponse {
+            transaction_id: bill.transaction_id,
+            status: bill.status,
+            bill_type: bill.bill_type,
+            provider_code: bill.provider_code,
+            account_number: bill.account_number,
+            amount: format!("{}.{:02}", bill.amount / 100, bill.amount % 100),
+            provider_reference: bill.provider_reference,
+            token: bill.token,
+            message,
+            error_message: bill.error_message,
+            refund_tx_hash: bill.refund_tx_hash,
+            retry_count: bill.retry_count,
+            created_at: bill.created_at.to_rfc3339(),
+            updated_at: bill.updated_at.to_rfc3339(),
+        }),
+    )
+        .into_response()
+}
+
+/// API clients pay on behalf of their users; a user may only pay from the
+/// account their SEP-10 session proved they control
+fn may_pay_from(principal: &Principal, wallet_address: &str) -> bool {
+    match principal.kind {
+        PrincipalKind::ApiClient => true,
+        PrincipalKind::User => principal.stellar_account() == Some(wallet_address),
+    }
+}
+
+/// Subject of the principal that created a bill payment
+fn requested_by(metadata: &serde_json::Value) -> Option<&str> {
+    metadata.get("requested_by").and_then(|v| v.as_str())
+}
+
+/// Look up a catalog entry by `provider_code` or `provider_id`
+fn find_catalog_provider(code: &str) -> Option<BillProvider> {
+    let code = code.trim().to_lowercase();
+    get_all_providers()
+        .into_iter()
+        .find(|p| p.provider_code == code || p.provider_id == code)
+}
+
+fn check_amount_limits(provider: &BillProvider, amount: &BigDecimal) -> Result<(), String> {
+    let limits = &provider.amount_limits;
+    let min = BigDecimal::from_str(&limits.min_amount).unwrap_or_else(|_| BigDecimal::from(0));
+    let max = BigDecimal::from_str(&limits.max_amount).ok();
+    if *amount < min || max.as_ref().is_some_and(|max| amount > max) {
+        return Err(format!(
+            "amount must be between {} and {} {}",
+            limits.min_amount, limits.max_amount, limits.currency
+        ));
+    }
+    Ok(())
+}
+
+/// Flat service fee plus the provider's convenience percentage, rounded to kobo
+fn bill_fee(provider: &BillProvider, amount: &BigDecimal) -> BigDecimal {
+    let fees = &provider.processing.fees;
+    let service = BigDecimal::from_str(&fees.service_fee).unwrap_or_else(|_| BigDecimal::from(0));
+    let percentage = BigDecimal::from_str(&fees.convenience_fee_percentage.to_string())
+        .unwrap_or_else(|_| BigDecimal::from(0));
+    (service + amount * percentage / BigDecimal::from(100)).round(2)
+}
+
+fn status_message(state: Option<BillProcessingState>) -> &'static str {
+    match state {
+        Some(BillProcessingState::PendingPayment) => "Waiting for your cNGN payment",
+        Some(BillProcessingState::CngnReceived)
+        | Some(BillProcessingState::VerifyingAccount)
+        | Some(BillProcessingState::ProcessingBill)
+        | Some(BillProcessingState::ProviderProcessing) => "Your bill payment is being processed",
+        Some(BillProcessingState::RetryScheduled) | Some(BillProcessingState::ProviderFailed) => {
+            "The provider could not complete the payment yet; we will retry shortly"
+        }
+        Some(BillProcessingState::AccountInvalid)
+        | Some(BillProcessingState::RefundInitiated)
+        | Some(BillProcessingState::RefundProcessing) => {
+            "The bill could not be paid; your cNGN is being refunded"
+        }
+        Some(BillProcessingState::Refunded) => "Your cNGN has been refunded",
+        Some(BillProcessingState::Completed) | None => "Bill payment status unavailable",
+    }
+}
+
+fn bill_error(status: StatusCode, code: &str, message: String) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: ErrorDetails {
+                code: code.to_string(),
+                message,
+                supported_categories: None,
+                supported_countries: None,
+            },
+        }),
+    )
+        .into_response()
+}
+
+fn unknown_provider(code: &str) -> Response {
+    bill_error(
+        StatusCode::BAD_REQUEST,
+        "UNKNOWN_PROVIDER",
+        format!("Unknown bill provider: {}", code),
+    )
+}
+
+fn database_error(e: DatabaseError) -> Response {
+    match e.kind {
+        DatabaseErrorKind::ForeignKeyViolation { .. } => bill_error(
+            StatusCode::BAD_REQUEST,
+            "WALLET_NOT_REGISTERED",
+            "Wallet must be registered before paying bills".to_string(),
+        ),
+        _ => {
+            error!(error = %e, "bill payment database error");
+            bill_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "INTERNAL_ERROR",
+                "Something went wrong. Please try again.".to_string(),
+            )
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1840,4 +2372,76 @@ mod tests {
             .unwrap();
         assert_eq!(cable_cat.count, 3);
     }
+
+    #[test]
+    fn test_find_catalog_provider_by_code_or_id() {
+        let by_code = find_catalog_provider("ekedc-electric").unwrap();
+        assert_eq!(by_code.category, ProviderCategory::Electricity);
+
+        let by_id = find_catalog_provider(" EKEDC ").unwrap();
+        assert_eq!(by_id.provider_code, "ekedc-electric");
+
+        assert!(find_catalog_provider("unknown-biller").is_none());
+    }
+
+    #[test]
+    fn test_bill_fee_adds_percentage_and_service_fee() {
+        let mut provider = find_catalog_provider("ekedc-electric").unwrap();
+        let amount = BigDecimal::from_str("5000.00").unwrap();
+        assert_eq!(
+            bill_fee(&provider, &amount),
+            BigDecimal::from_str("25.00").unwrap()
+        );
+
+        provider.processing.fees.service_fee = "100.00".to_string();
+        provider.processing.fees.convenience_fee_percentage = 0.0;
+        assert_eq!(
+            bill_fee(&provider, &amount),
+            BigDecimal::from_str("100.00").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_check_amount_limits() {
+        let provider = find_catalog_provider("ekedc-electric").unwrap();
+        assert!(check_amount_limits(&provider, &BigDecimal::from(500)).is_ok());
+        assert!(check_amount_limits(&provider, &BigDecimal::from(100000)).is_ok());
+        assert!(check_amount_limits(&provider, &BigDecimal::from(499)).is_err());
+        assert!(check_amount_limits(&provider, &BigDecimal::from(100001)).is_err());
+    }
+
+    #[test]
+    fn test_users_pay_only_from_their_own_account() {
+        let account = "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7";
+        let other = "GBRPYHIL2CI3FNQ4BXLFMNDLFJUNPU2HY3ZMFSHONUCEOASW7QC7OX2H";
+        let principal = |kind, subject: &str| Principal {
+            kind,
+            subject: subject.to_string(),
+            client_id: None,
+            user_id: None,
+            scopes: vec![],
+            session_id: None,
+        };
+
+        assert!(may_pay_from(
+            &principal(PrincipalKind::User, account),
+            account
+        ));
+        assert!(!may_pay_from(
+            &principal(PrincipalKind::User, account),
+            other
+        ));
+        assert!(!may_pay_from(
+            &principal(PrincipalKind::User, "user-123"),
+            account
+        ));
+        assert!(may_pay_from(
+            &principal(PrincipalKind::ApiClient, "key-1"),
+            other
+        ));
+
+        let metadata = serde_json::json!({ "requested_by": "key-1" });
+        assert_eq!(requested_by(&metadata), Some("key-1"));
+        assert_eq!(requested_by(&serde_json::json!({})), None);
+    }
 }
@@ -0,0 +1,193 @@
+//! Merchant webhook endpoint management
+//!
+//! API clients register the URLs that receive signed event notifications,
+//! inspect delivery history, and replay past deliveries.
+
+use crate::auth::Principal;
+use crate::middleware::error::{get_request_id_from_headers, json_error_response, ErrorResponse};
+use crate::services::merchant_webhooks::{MerchantWebhookError, MerchantWebhookService};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+const MAX_DELIVERY_LIMIT: i64 = 200;
+
+#[derive(Clone)]
+pub struct MerchantWebhookState {
+    pub service: Arc<MerchantWebhookService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterEndpointRequest {
+    pub url: String,
+    /// Event types to receive; omitted or empty subscribes to all
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub endpoint_id: Option<Uuid>,
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+/// POST /api/webhooks/endpoints
+pub async fn register_endpoint(
+    State(state): State<MerchantWebhookState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Json(request): Json<RegisterEndpointRequest>,
+) -> Result<Response, HandlerError> {
+    let client_id = client_id(&principal, &headers)?;
+
+    match state
+        .service
+        .register_endpoint(
+            client_id,
+            request.url.trim(),
+            &request.event_types,
+            request.description.as_deref(),
+        )
+        .await
+    {
+        Ok(registered) => Ok((StatusCode::CREATED, Json(registered)).into_response()),
+        Err(e) => Err(service_error(e, &headers)),
+    }
+}
+
+/// GET /api/webhooks/endpoints
+pub async fn list_endpoints(
+    State(state): State<MerchantWebhookState>,
+    principal: Principal,
+    headers: HeaderMap,
+) -> Result<Response, HandlerError> {
+    let client_id = client_id(&principal, &headers)?;
+    let endpoints = state
+        .service
+        .repository()
+        .list_endpoints(client_id)
+        .await
+        .map_err(|e| service_error(e.into(), &headers))?;
+    Ok(Json(serde_json::json!({ "endpoints": endpoints })).into_response())
+}
+
+/// DELETE /api/webhooks/endpoints/{id}
+pub async fn delete_endpoint(
+    State(state): State<MerchantWebhookState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Path(endpoint_id): Path<Uuid>,
+) -> Result<Response, HandlerError> {
+    let client_id = client_id(&principal, &headers)?;
+    let removed = state
+        .service
+        .repository()
+        .deactivate_endpoint(client_id, endpoint_id)
+        .await
+        .map_err(|e| service_error(e.into(), &headers))?;
+
+    if !removed {
+        return Err(not_found("webhook endpoint", &headers));
+    }
+    info!(endpoint_id = %endpoint_id, client_id = %client_id, "merchant webhook endpoint removed");
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// GET /api/webhooks/deliveries
+pub async fn list_deliveries(
+    State(state): State<MerchantWebhookState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Response, HandlerError> {
+    let client_id = client_id(&principal, &headers)?;
+    if let Some(status) = query.status.as_deref() {
+        if !matches!(status, "pending" | "delivered" | "failed") {
+            return Err(json_error_response(
+                StatusCode::BAD_REQUEST,
+                "status must be one of pending, delivered, failed",
+                get_request_id_from_headers(&headers),
+            ));
+        }
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+        .clamp(1, MAX_DELIVERY_LIMIT);
+    let deliveries = state
+        .service
+        .repository()
+        .list_deliveries(client_id, query.endpoint_id, query.status.as_deref(), limit)
+        .await
+        .map_err(|e| service_error(e.into(), &headers))?;
+    Ok(Json(serde_json::json!({ "deliveries": deliveries })).into_response())
+}
+
+/// POST /api/webhooks/deliveries/{id}/replay
+pub async fn replay_delivery(
+    State(state): State<MerchantWebhookState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Path(delivery_id): Path<Uuid>,
+) -> Result<Response, HandlerError> {
+    let client_id = client_id(&principal, &headers)?;
+    let replay = state
+        .service
+        .repository()
+        .replay_delivery(client_id, delivery_id)
+        .await
+        .map_err(|e| service_error(e.into(), &headers))?
+        .ok_or_else(|| not_found("webhook delivery", &headers))?;
+
+    info!(delivery_id = %delivery_id, replay_id = %replay.id, "merchant webhook replay queued");
+    Ok((StatusCode::ACCEPTED, Json(replay)).into_response())
+}
+
+/// Endpoints belong to API clients; user sessions without one can't manage them
+fn client_id(principal: &Principal, headers: &HeaderMap) -> Result<Uuid, HandlerError> {
+    principal.client_id.ok_or_else(|| {
+        (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::unauthorized(
+                get_request_id_from_headers(headers),
+                "Webhook endpoints can only be managed by API clients",
+            )),
+        )
+    })
+}
+
+fn not_found(what: &str, headers: &HeaderMap) -> HandlerError {
+    json_error_response(
+        StatusCode::NOT_FOUND,
+        format!("{} not found", what),
+        get_request_id_from_headers(headers),
+    )
+}
+
+fn service_error(error: MerchantWebhookError, headers: &HeaderMap) -> HandlerError {
+    let request_id = get_request_id_from_headers(headers);
+    match error {
+        MerchantWebhookError::InvalidUrl(_)
+        | MerchantWebhookError::ForbiddenAddress(_)
+        | MerchantWebhookError::UnknownEventType(_) => {
+            json_error_response(StatusCode::BAD_REQUEST, error.to_string(), request_id)
+        }
+        MerchantWebhookError::Database(e) => {
+            error!(error = %e, "merchant webhook request failed");
+            json_error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string(), request_id)
+        }
+    }
+}
@@ -1,7 +1,15 @@
+pub mod admin;
 pub mod onramp;
 pub mod rates;
 pub mod bills;
 pub mod fees;
+pub mod merchant_webhooks;
+pub mod notifications;
+pub mod sep10;
+pub mod sep12;
+pub mod sep24;
+pub mod sep38;
+pub mod stellar_toml;
 pub mod wallet;
 pub mod webhooks;
 pub mod onramp;
@@ -0,0 +1,161 @@
+//! Notification preferences
+//!
+//! Signed-in users choose which channels (email, SMS, partner webhook) they
+//! are notified on and can mute individual notification types.
+
+use crate::auth::Principal;
+use crate::database::notification_repository::{NotificationPreferences, NotificationRepository};
+use crate::middleware::error::{get_request_id_from_headers, json_error_response, ErrorResponse};
+use crate::services::notification::NotificationType;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct NotificationState {
+    pub repository: Arc<NotificationRepository>,
+}
+
+/// Partial update; omitted fields keep their current value
+#[derive(Debug, Deserialize)]
+pub struct UpdatePreferencesRequest {
+    pub email_enabled: Option<bool>,
+    pub sms_enabled: Option<bool>,
+    pub webhook_enabled: Option<bool>,
+    pub muted_types: Option<Vec<String>>,
+}
+
+type HandlerError = (StatusCode, Json<ErrorResponse>);
+
+/// GET /api/notifications/preferences
+pub async fn get_preferences(
+    State(state): State<NotificationState>,
+    principal: Principal,
+    headers: HeaderMap,
+) -> Result<Response, HandlerError> {
+    let user_id = user_id(&principal, &headers)?;
+    let preferences = load(&state, user_id, &headers).await?;
+    Ok(Json(preferences).into_response())
+}
+
+/// PUT /api/notifications/preferences
+pub async fn update_preferences(
+    State(state): State<NotificationState>,
+    principal: Principal,
+    headers: HeaderMap,
+    Json(request): Json<UpdatePreferencesRequest>,
+) -> Result<Response, HandlerError> {
+    let user_id = user_id(&principal, &headers)?;
+    let mut preferences = load(&state, user_id, &headers).await?;
+
+    if let Some(enabled) = request.email_enabled {
+        preferences.email_enabled = enabled;
+    }
+    if let Some(enabled) = request.sms_enabled {
+        preferences.sms_enabled = enabled;
+    }
+    if let Some(enabled) = request.webhook_enabled {
+        preferences.webhook_enabled = enabled;
+    }
+    if let Some(muted_types) = request.muted_types {
+        preferences.muted_types = validate_muted_types(muted_types, &headers)?;
+    }
+
+    let saved = state
+        .repository
+        .upsert_preferences(&preferences)
+        .await
+        .map_err(|e| database_error(e, &headers))?;
+    info!(user_id = %user_id, "notification preferences updated");
+    Ok(Json(saved).into_response())
+}
+
+async fn load(
+    state: &NotificationState,
+    user_id: Uuid,
+    headers: &HeaderMap,
+) -> Result<NotificationPreferences, HandlerError> {
+    state
+        .repository
+        .get_preferences(user_id)
+        .await
+        .map(|stored| stored.unwrap_or_else(|| NotificationPreferences::defaults(user_id)))
+        .map_err(|e| database_error(e, headers))
+}
+
+/// Preferences belong to end users; API clients have none
+fn user_id(principal: &Principal, headers: &HeaderMap) -> Result<Uuid, HandlerError> {
+    principal.user_id.ok_or_else(|| {
+        (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::unauthorized(
+                get_request_id_from_headers(headers),
+                "Notification preferences can only be managed by users",
+            )),
+        )
+    })
+}
+
+fn validate_muted_types(
+    mut muted_types: Vec<String>,
+    headers: &HeaderMap,
+) -> Result<Vec<String>, HandlerError> {
+    if let Some(unknown) = muted_types
+        .iter()
+        .find(|t| NotificationType::parse(t).is_none())
+    {
+        let known: Vec<&str> = NotificationType::ALL.iter().map(|t| t.as_str()).collect();
+        return Err(json_error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "unknown notification type '{}', expected one of: {}",
+                unknown,
+                known.join(", ")
+            ),
+            get_request_id_from_headers(headers),
+        ));
+    }
+    muted_types.sort();
+    muted_types.dedup();
+    Ok(muted_types)
+}
+
+fn database_error(e: crate::database::error::DatabaseError, headers: &HeaderMap) -> HandlerError {
+    error!(error = %e, "notification preferences request failed");
+    json_error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "failed to load notification preferences",
+        get_request_id_from_headers(headers),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_muted_types() {
+        let headers = HeaderMap::new();
+        let muted = validate_muted_types(
+            vec![
+                "offramp_completed".to_string(),
+                "cngn_received".to_string(),
+                "offramp_completed".to_string(),
+            ],
+            &headers,
+        )
+        .unwrap();
+        assert_eq!(muted, vec!["cngn_received", "offramp_completed"]);
+
+        let (status, _) =
+            validate_muted_types(vec!["marketing".to_string()], &headers).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
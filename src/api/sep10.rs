@@ -0,0 +1,178 @@
+//! SEP-10 web authentication endpoints
+//!
+//! `GET /auth` hands a wallet a challenge transaction for its account and
+//! `POST /auth` exchanges the signed challenge for a JWT whose subject is
+//! that account. Errors use the `{"error": "..."}` body SEP-10 clients expect.
+
+use crate::auth::sep10::Sep10Service;
+use crate::auth::{scopes, AuthError, AuthService};
+use crate::chains::stellar::client::StellarClient;
+use crate::chains::stellar::errors::StellarError;
+use axum::{
+    extract::{FromRequest, Query, Request, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Clone)]
+pub struct Sep10State {
+    pub service: Arc<Sep10Service>,
+    pub auth: Arc<AuthService>,
+    pub stellar_client: StellarClient,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChallengeQuery {
+    pub account: String,
+    pub home_domain: Option<String>,
+    pub client_domain: Option<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChallengeResponse {
+    pub transaction: String,
+    pub network_passphrase: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub transaction: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Sep10Error {
+    error: String,
+}
+
+/// GET /auth
+pub async fn get_challenge(
+    State(state): State<Sep10State>,
+    Query(query): Query<ChallengeQuery>,
+) -> Response {
+    if query.client_domain.is_some() {
+        return sep10_error(StatusCode::BAD_REQUEST, "client_domain is not supported");
+    }
+    if query.memo.is_some() {
+        return sep10_error(StatusCode::BAD_REQUEST, "memo is not supported");
+    }
+    if let Some(home_domain) = &query.home_domain {
+        if *home_domain != state.service.config().home_domain {
+            return sep10_error(StatusCode::BAD_REQUEST, "home_domain is not served here");
+        }
+    }
+
+    match state.service.challenge(&query.account).await {
+        Ok(transaction) => Json(ChallengeResponse {
+            transaction,
+            network_passphrase: state.service.network_passphrase().to_string(),
+        })
+        .into_response(),
+        Err(e) => auth_error(e),
+    }
+}
+
+/// POST /auth, with a JSON or form-encoded body
+pub async fn post_token(State(state): State<Sep10State>, request: Request) -> Response {
+    let form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"));
+    let body = if form {
+        Form::<TokenRequest>::from_request(request, &())
+            .await
+            .map(|Form(body)| body)
+            .map_err(|e| e.body_text())
+    } else {
+        Json::<TokenRequest>::from_request(request, &())
+            .await
+            .map(|Json(body)| body)
+            .map_err(|e| e.body_text())
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(message) => return sep10_error(StatusCode::BAD_REQUEST, message),
+    };
+
+    let challenge = match state.service.read_challenge(&body.transaction) {
+        Ok(challenge) => challenge,
+        Err(e) => return auth_error(e),
+    };
+
+    let account = match state
+        .stellar_client
+        .get_account(&challenge.client_account)
+        .await
+    {
+        Ok(info) => Some(info),
+        Err(StellarError::AccountNotFound { .. }) => None,
+        Err(e) => {
+            warn!(account = %challenge.client_account, error = %e, "could not load account signers");
+            return sep10_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Stellar network temporarily unavailable",
+            );
+        }
+    };
+
+    if let Err(e) = state.service.verify_signers(&challenge, account.as_ref()) {
+        return auth_error(e);
+    }
+
+    match state
+        .auth
+        .issue_user_session(
+            &challenge.client_account,
+            &[
+                scopes::WALLET_READ,
+                scopes::TRANSFERS_WRITE,
+                scopes::QUOTES_WRITE,
+                scopes::KYC_WRITE,
+            ],
+        )
+        .await
+    {
+        Ok(issued) => {
+            info!(
+                account = %challenge.client_account,
+                session_id = %issued.session_id,
+                "SEP-10 session issued"
+            );
+            Json(TokenResponse {
+                token: issued.token,
+            })
+            .into_response()
+        }
+        Err(e) => auth_error(e),
+    }
+}
+
+fn auth_error(error: AuthError) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if status.is_server_error() {
+        warn!(error = %error, "SEP-10 request failed");
+        return sep10_error(status, "authentication is temporarily unavailable");
+    }
+    sep10_error(status, error.to_string())
+}
+
+fn sep10_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(Sep10Error {
+            error: message.into(),
+        }),
+    )
+        .into_response()
+}
@@ -0,0 +1,129 @@
+//! SEP-12 KYC customer endpoints
+//!
+//! Wallets call these with the JWT from SEP-10 and only ever see or change
+//! the customer of the authenticated account. Fields are sent as JSON or a
+//! form; binary SEP-9 fields (photo IDs) are not collected. Errors use the
+//! `{"error": "..."}` body SEP-12 clients expect.
+
+use crate::api::sep24::read_body;
+use crate::auth::Principal;
+use crate::services::kyc::{CustomerQuery, KycError, KycService, PutCustomer};
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, Request, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::warn;
+
+#[derive(Clone)]
+pub struct Sep12State {
+    pub service: Arc<KycService>,
+}
+
+#[derive(Debug, Serialize)]
+struct Sep12ErrorBody {
+    error: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DeleteCustomer {
+    memo: Option<String>,
+}
+
+/// GET /kyc/customer
+pub async fn get_customer(
+    State(state): State<Sep12State>,
+    principal: Principal,
+    query: Result<Query<CustomerQuery>, QueryRejection>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(e) => return sep12_error(StatusCode::BAD_REQUEST, e.body_text()),
+    };
+    match state.service.customer(account, query).await {
+        Ok(customer) => Json(customer).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// PUT /kyc/customer
+pub async fn put_customer(
+    State(state): State<Sep12State>,
+    principal: Principal,
+    request: Request,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    let body = match read_body::<PutCustomer>(request).await {
+        Ok(body) => body,
+        Err(message) => return sep12_error(StatusCode::BAD_REQUEST, message),
+    };
+    match state.service.put_customer(account, body).await {
+        Ok(id) => (StatusCode::ACCEPTED, Json(json!({ "id": id }))).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// DELETE /kyc/customer/{account}
+pub async fn delete_customer(
+    State(state): State<Sep12State>,
+    principal: Principal,
+    Path(target): Path<String>,
+    request: Request,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    // The memo is optional and so is the body carrying it
+    let body = if request.headers().contains_key(header::CONTENT_TYPE) {
+        match read_body::<DeleteCustomer>(request).await {
+            Ok(body) => body,
+            Err(message) => return sep12_error(StatusCode::BAD_REQUEST, message),
+        }
+    } else {
+        DeleteCustomer::default()
+    };
+    match state
+        .service
+        .delete_customer(account, &target, body.memo.as_deref())
+        .await
+    {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+fn forbidden() -> Response {
+    sep12_error(
+        StatusCode::FORBIDDEN,
+        "a SEP-10 token for a Stellar account is required",
+    )
+}
+
+fn service_error(error: KycError) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if status.is_server_error() {
+        warn!(error = %error, "SEP-12 request failed");
+        return sep12_error(status, "customer information is temporarily unavailable");
+    }
+    sep12_error(status, error.to_string())
+}
+
+fn sep12_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(Sep12ErrorBody {
+            error: message.into(),
+        }),
+    )
+        .into_response()
+}
@@ -0,0 +1,190 @@
+//! SEP-24 hosted deposit and withdrawal endpoints
+//!
+//! Wallets call these with the JWT from SEP-10. The interactive endpoints
+//! return the URL of our hosted flow, which submits the collected details
+//! back through `POST /sep24/transactions/{id}/deposit` or `/withdraw` with
+//! the token embedded in that URL. Errors use the `{"error": "..."}` body
+//! SEP-24 clients expect.
+
+use crate::auth::Principal;
+use crate::services::sep24::{
+    DepositDetails, InteractiveRequest, Sep24Error, Sep24Kind, Sep24Service, TransactionLookup,
+    TransactionsQuery, WithdrawalDetails,
+};
+use axum::{
+    extract::{FromRequest, Path, Query, Request, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::warn;
+
+#[derive(Clone)]
+pub struct Sep24State {
+    pub service: Arc<Sep24Service>,
+}
+
+#[derive(Debug, Serialize)]
+struct Sep24ErrorBody {
+    error: String,
+}
+
+/// GET /sep24/info
+pub async fn get_info(State(state): State<Sep24State>) -> Response {
+    Json(state.service.info()).into_response()
+}
+
+/// POST /transactions/deposit/interactive
+pub async fn post_deposit_interactive(
+    State(state): State<Sep24State>,
+    principal: Principal,
+    request: Request,
+) -> Response {
+    start(state, principal, Sep24Kind::Deposit, request).await
+}
+
+/// POST /transactions/withdraw/interactive
+pub async fn post_withdraw_interactive(
+    State(state): State<Sep24State>,
+    principal: Principal,
+    request: Request,
+) -> Response {
+    start(state, principal, Sep24Kind::Withdrawal, request).await
+}
+
+async fn start(
+    state: Sep24State,
+    principal: Principal,
+    kind: Sep24Kind,
+    request: Request,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    let body = match read_body::<InteractiveRequest>(request).await {
+        Ok(body) => body,
+        Err(message) => return sep24_error(StatusCode::BAD_REQUEST, message),
+    };
+    match state.service.start(kind, account, body).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// POST /sep24/transactions/{id}/deposit, called by the interactive flow
+pub async fn post_deposit_details(
+    State(state): State<Sep24State>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(details): Json<DepositDetails>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    match state.service.submit_deposit(&id, account, details).await {
+        Ok(submitted) => Json(submitted).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// POST /sep24/transactions/{id}/withdraw, called by the interactive flow
+pub async fn post_withdraw_details(
+    State(state): State<Sep24State>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(details): Json<WithdrawalDetails>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    match state.service.submit_withdrawal(&id, account, details).await {
+        Ok(transaction) => Json(json!({ "transaction": transaction })).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// GET /transaction
+pub async fn get_transaction(
+    State(state): State<Sep24State>,
+    principal: Principal,
+    Query(lookup): Query<TransactionLookup>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    match state.service.transaction(account, lookup).await {
+        Ok(transaction) => Json(json!({ "transaction": transaction })).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// GET /transactions
+pub async fn get_transactions(
+    State(state): State<Sep24State>,
+    principal: Principal,
+    Query(query): Query<TransactionsQuery>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    match state.service.transactions(account, query).await {
+        Ok(transactions) => Json(json!({ "transactions": transactions })).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// Read a JSON or form-encoded body, both of which SEP-24 and SEP-12 allow
+pub(crate) async fn read_body<T: DeserializeOwned>(request: Request) -> Result<T, String> {
+    let form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"));
+    if form {
+        Form::<T>::from_request(request, &())
+            .await
+            .map(|Form(body)| body)
+            .map_err(|e| e.body_text())
+    } else {
+        Json::<T>::from_request(request, &())
+            .await
+            .map(|Json(body)| body)
+            .map_err(|e| e.body_text())
+    }
+}
+
+fn forbidden() -> Response {
+    sep24_error(
+        StatusCode::FORBIDDEN,
+        "a SEP-10 token for a Stellar account is required",
+    )
+}
+
+fn service_error(error: Sep24Error) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if status.is_server_error() {
+        warn!(error = %error, "SEP-24 request failed");
+        let message = match error {
+            Sep24Error::Payment(_) => "payment provider is temporarily unavailable",
+            Sep24Error::Rate(_) => "exchange rate is temporarily unavailable",
+            _ => "the request could not be processed",
+        };
+        return sep24_error(status, message);
+    }
+    sep24_error(status, error.to_string())
+}
+
+fn sep24_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(Sep24ErrorBody {
+            error: message.into(),
+        }),
+    )
+        .into_response()
+}
@@ -0,0 +1,126 @@
+//! SEP-38 quote endpoints
+//!
+//! `/info`, `/prices` and `/price` are public; `POST /quote` and
+//! `GET /quote/{id}` need a SEP-10 token and only ever show the caller's own
+//! quotes. Errors use the `{"error": "..."}` body SEP-38 clients expect.
+
+use crate::auth::Principal;
+use crate::services::sep38::{PriceRequest, PricesQuery, Sep38Error, Sep38Service};
+use axum::{
+    extract::{rejection::JsonRejection, rejection::QueryRejection, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Clone)]
+pub struct Sep38State {
+    pub service: Arc<Sep38Service>,
+}
+
+#[derive(Debug, Serialize)]
+struct Sep38ErrorBody {
+    error: String,
+}
+
+/// GET /sep38/info
+pub async fn get_info(State(state): State<Sep38State>) -> Response {
+    Json(state.service.info()).into_response()
+}
+
+/// GET /sep38/prices
+pub async fn get_prices(
+    State(state): State<Sep38State>,
+    query: Result<Query<PricesQuery>, QueryRejection>,
+) -> Response {
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(e) => return sep38_error(StatusCode::BAD_REQUEST, e.body_text()),
+    };
+    match state.service.prices(query).await {
+        Ok(prices) => Json(prices).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// GET /sep38/price
+pub async fn get_price(
+    State(state): State<Sep38State>,
+    query: Result<Query<PriceRequest>, QueryRejection>,
+) -> Response {
+    let Query(request) = match query {
+        Ok(query) => query,
+        Err(e) => return sep38_error(StatusCode::BAD_REQUEST, e.body_text()),
+    };
+    match state.service.price(request).await {
+        Ok(price) => Json(price).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+/// POST /sep38/quote
+pub async fn post_quote(
+    State(state): State<Sep38State>,
+    principal: Principal,
+    body: Result<Json<PriceRequest>, JsonRejection>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    let Json(request) = match body {
+        Ok(body) => body,
+        Err(e) => return sep38_error(StatusCode::BAD_REQUEST, e.body_text()),
+    };
+    match state.service.create_quote(account, request).await {
+        Ok(quote) => {
+            info!(quote_id = %quote.id, account, "SEP-38 quote issued");
+            (StatusCode::CREATED, Json(quote)).into_response()
+        }
+        Err(e) => service_error(e),
+    }
+}
+
+/// GET /sep38/quote/{id}
+pub async fn get_quote(
+    State(state): State<Sep38State>,
+    principal: Principal,
+    Path(id): Path<String>,
+) -> Response {
+    let Some(account) = principal.stellar_account() else {
+        return forbidden();
+    };
+    match state.service.quote(account, &id).await {
+        Ok(quote) => Json(quote).into_response(),
+        Err(e) => service_error(e),
+    }
+}
+
+fn forbidden() -> Response {
+    sep38_error(
+        StatusCode::FORBIDDEN,
+        "a SEP-10 token for a Stellar account is required",
+    )
+}
+
+fn service_error(error: Sep38Error) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if status.is_server_error() {
+        warn!(error = %error, "SEP-38 request failed");
+        return sep38_error(status, "quotes are temporarily unavailable");
+    }
+    sep38_error(status, error.to_string())
+}
+
+fn sep38_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (
+        status,
+        Json(Sep38ErrorBody {
+            error: message.into(),
+        }),
+    )
+        .into_response()
+}
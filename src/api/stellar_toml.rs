@@ -0,0 +1,49 @@
+//! `GET /.well-known/stellar.toml`
+//!
+//! Serves the SEP-1 file rendered at boot. Wallets fetch it from browsers, so
+//! it is readable from any origin.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct StellarTomlState {
+    pub body: Arc<str>,
+}
+
+/// GET /.well-known/stellar.toml
+pub async fn get_stellar_toml(State(state): State<StellarTomlState>) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=300"),
+    );
+    add_cors_headers(&mut headers);
+    (StatusCode::OK, headers, state.body.to_string()).into_response()
+}
+
+/// Handle OPTIONS preflight requests
+pub async fn options_stellar_toml() -> Response {
+    let mut headers = HeaderMap::new();
+    add_cors_headers(&mut headers);
+    (StatusCode::NO_CONTENT, headers).into_response()
+}
+
+fn add_cors_headers(headers: &mut HeaderMap) {
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static("GET, OPTIONS"),
+    );
+}
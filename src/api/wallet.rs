@@ -1,3 +1,4 @@
+use crate::auth::Principal;
 use crate::services::balance::{BalanceService, WalletBalance};
 use axum::{
     extract::{Query, State},
@@ -37,6 +38,7 @@ pub struct ErrorDetail {
 
 pub async fn get_balance(
     State(state): State<WalletState>,
+    principal: Option<Principal>,
     Query(params): Query<BalanceQuery>,
 ) -> Response {
     info!(
@@ -44,6 +46,25 @@ pub async fn get_balance(
         params.address, params.refresh
     );
 
+    // A SEP-10 session may only look at the account it authenticated
+    if let Some(account) = principal.as_ref().and_then(Principal::stellar_account) {
+        if account != params.address {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: ErrorDetail {
+                        code: "WALLET_MISMATCH".to_string(),
+                        message: "Wallet address does not match the authenticated account"
+                            .to_string(),
+                        details: None,
+                        wallet_address: Some(params.address),
+                    },
+                }),
+            )
+                .into_response();
+        }
+    }
+
     match state
         .balance_service
         .get_balance(&params.address, params.refresh)
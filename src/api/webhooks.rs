@@ -2,12 +2,14 @@ use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 use serde_json::Value as JsonValue;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
+use crate::middleware::client_ip::ClientIp;
+use crate::payments::providers::mpesa::{callback_signature, CALLBACK_SECRET_HEADER};
 use crate::services::webhook_processor::{WebhookProcessor, WebhookProcessorError};
 
 pub struct WebhookState {
@@ -18,6 +20,7 @@ pub struct WebhookState {
 pub async fn handle_webhook(
     State(state): State<Arc<WebhookState>>,
     Path(provider): Path<String>,
+    client_ip: Option<Extension<ClientIp>>,
     headers: axum::http::HeaderMap,
     body: String,
 ) -> impl IntoResponse {
@@ -33,6 +36,15 @@ pub async fn handle_webhook(
             .get("x-paystack-signature")
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string()),
+        // Daraja callbacks are unsigned: pair the secret header our gateway
+        // may add with the client address for IP allowlisting
+        "mpesa" => client_ip.map(|Extension(ClientIp(ip))| {
+            let secret = headers
+                .get(CALLBACK_SECRET_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            callback_signature(ip, secret)
+        }),
         _ => None,
     };
 
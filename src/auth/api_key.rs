@@ -0,0 +1,72 @@
+//! API key generation and hashing
+//!
+//! Keys are shown to the partner once and only their SHA-256 digest is stored.
+//! They carry 256 bits of randomness, so a fast hash is sufficient.
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Prefix identifying Aframp API keys
+pub const API_KEY_PREFIX: &str = "ak_";
+
+/// Characters of the key kept in clear for display and support lookups
+const DISPLAY_PREFIX_LEN: usize = 11;
+
+/// A freshly generated key. `plaintext` must be handed to the partner and discarded.
+#[derive(Debug, Clone)]
+pub struct GeneratedApiKey {
+    pub plaintext: String,
+    pub display_prefix: String,
+    pub hash: String,
+}
+
+/// Generate a new random API key
+pub fn generate_api_key() -> GeneratedApiKey {
+    let plaintext = format!(
+        "{}{}{}",
+        API_KEY_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    );
+    GeneratedApiKey {
+        display_prefix: plaintext[..DISPLAY_PREFIX_LEN].to_string(),
+        hash: hash_api_key(&plaintext),
+        plaintext,
+    }
+}
+
+/// Digest stored in `api_keys.key_hash`
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Whether a credential looks like one of our API keys
+pub fn looks_like_api_key(value: &str) -> bool {
+    value.starts_with(API_KEY_PREFIX) && value.len() > DISPLAY_PREFIX_LEN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_key_format() {
+        let key = generate_api_key();
+        assert!(key.plaintext.starts_with(API_KEY_PREFIX));
+        assert_eq!(key.plaintext.len(), API_KEY_PREFIX.len() + 64);
+        assert!(key.plaintext.starts_with(&key.display_prefix));
+        assert_eq!(key.hash, hash_api_key(&key.plaintext));
+        assert!(looks_like_api_key(&key.plaintext));
+    }
+
+    #[test]
+    fn test_keys_are_unique() {
+        assert_ne!(generate_api_key().plaintext, generate_api_key().plaintext);
+    }
+
+    #[test]
+    fn test_looks_like_api_key() {
+        assert!(!looks_like_api_key("eyJhbGciOiJIUzI1NiJ9.e30.sig"));
+        assert!(!looks_like_api_key("ak_"));
+    }
+}
@@ -0,0 +1,419 @@
+//! Compact JWT signing and verification (HS256 and EdDSA)
+//!
+//! Only the two algorithms we issue are accepted; the `alg` header must match
+//! the configured algorithm, so `none` and algorithm-confusion tokens are
+//! rejected before any signature work.
+
+use super::AuthError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Allowed clock skew when checking `exp` and `nbf`
+const LEEWAY_SECS: i64 = 30;
+
+/// Minimum HS256 secret length
+const MIN_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    HS256,
+    EdDSA,
+}
+
+impl JwtAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JwtAlgorithm::HS256 => "HS256",
+            JwtAlgorithm::EdDSA => "EdDSA",
+        }
+    }
+}
+
+impl std::str::FromStr for JwtAlgorithm {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "HS256" => Ok(JwtAlgorithm::HS256),
+            "EdDSA" => Ok(JwtAlgorithm::EdDSA),
+            other => Err(AuthError::Configuration(format!(
+                "unsupported JWT algorithm: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Key material for the configured algorithm
+#[derive(Clone)]
+enum JwtKeys {
+    Hmac(Vec<u8>),
+    Ed25519 {
+        signing: Option<Box<SigningKey>>,
+        verifying: VerifyingKey,
+    },
+}
+
+/// JWT configuration
+#[derive(Clone)]
+pub struct JwtConfig {
+    keys: JwtKeys,
+    pub issuer: String,
+    pub ttl_secs: u64,
+}
+
+impl JwtConfig {
+    /// HS256 with a shared secret
+    pub fn hs256(secret: impl Into<Vec<u8>>, issuer: impl Into<String>) -> Result<Self, AuthError> {
+        let secret = secret.into();
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(AuthError::Configuration(format!(
+                "JWT secret must be at least {} bytes",
+                MIN_SECRET_LEN
+            )));
+        }
+        Ok(Self {
+            keys: JwtKeys::Hmac(secret),
+            issuer: issuer.into(),
+            ttl_secs: 3600,
+        })
+    }
+
+    /// EdDSA (Ed25519) with a signing key; can issue and verify
+    pub fn ed25519(seed: [u8; 32], issuer: impl Into<String>) -> Self {
+        let signing = SigningKey::from_bytes(&seed);
+        Self {
+            keys: JwtKeys::Ed25519 {
+                verifying: signing.verifying_key(),
+                signing: Some(Box::new(signing)),
+            },
+            issuer: issuer.into(),
+            ttl_secs: 3600,
+        }
+    }
+
+    /// EdDSA with only a public key; can verify but not issue
+    pub fn ed25519_verify_only(
+        public_key: [u8; 32],
+        issuer: impl Into<String>,
+    ) -> Result<Self, AuthError> {
+        let verifying = VerifyingKey::from_bytes(&public_key)
+            .map_err(|e| AuthError::Configuration(format!("invalid Ed25519 public key: {}", e)))?;
+        Ok(Self {
+            keys: JwtKeys::Ed25519 {
+                signing: None,
+                verifying,
+            },
+            issuer: issuer.into(),
+            ttl_secs: 3600,
+        })
+    }
+
+    pub fn with_ttl(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    pub fn algorithm(&self) -> JwtAlgorithm {
+        match self.keys {
+            JwtKeys::Hmac(_) => JwtAlgorithm::HS256,
+            JwtKeys::Ed25519 { .. } => JwtAlgorithm::EdDSA,
+        }
+    }
+
+    /// Load from environment. Returns `Ok(None)` when no key material is set.
+    ///
+    /// - `JWT_ALGORITHM`: `HS256` (default) or `EdDSA`
+    /// - `JWT_SECRET`: HS256 shared secret
+    /// - `JWT_ED25519_SECRET_KEY` / `JWT_ED25519_PUBLIC_KEY`: hex-encoded 32-byte keys
+    /// - `JWT_ISSUER` (default `aframp`), `JWT_TTL_SECONDS` (default 3600)
+    pub fn from_env() -> Result<Option<Self>, AuthError> {
+        let algorithm: JwtAlgorithm = std::env::var("JWT_ALGORITHM")
+            .unwrap_or_else(|_| "HS256".to_string())
+            .parse()?;
+        let issuer = std::env::var("JWT_ISSUER").unwrap_or_else(|_| "aframp".to_string());
+        let ttl_secs = std::env::var("JWT_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600);
+
+        let config = match algorithm {
+            JwtAlgorithm::HS256 => match std::env::var("JWT_SECRET") {
+                Ok(secret) => Self::hs256(secret.into_bytes(), issuer)?,
+                Err(_) => return Ok(None),
+            },
+            JwtAlgorithm::EdDSA => {
+                if let Ok(secret) = std::env::var("JWT_ED25519_SECRET_KEY") {
+                    Self::ed25519(decode_key_hex(&secret)?, issuer)
+                } else if let Ok(public) = std::env::var("JWT_ED25519_PUBLIC_KEY") {
+                    Self::ed25519_verify_only(decode_key_hex(&public)?, issuer)?
+                } else {
+                    return Ok(None);
+                }
+            }
+        };
+
+        Ok(Some(config.with_ttl(ttl_secs)))
+    }
+}
+
+fn decode_key_hex(value: &str) -> Result<[u8; 32], AuthError> {
+    let bytes = hex::decode(value.trim())
+        .map_err(|e| AuthError::Configuration(format!("invalid hex key: {}", e)))?;
+    bytes
+        .try_into()
+        .map_err(|_| AuthError::Configuration("Ed25519 key must be 32 bytes".to_string()))
+}
+
+/// Registered and private claims carried by our tokens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    /// Subject: user ID or Stellar account
+    pub sub: String,
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+    /// Token ID
+    pub jti: String,
+    /// Space-separated scopes
+    #[serde(default)]
+    pub scope: String,
+    /// Server-side session backing this token, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// API client the subject belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+impl Claims {
+    pub fn scopes(&self) -> Vec<String> {
+        self.scope.split_whitespace().map(str::to_string).collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default)]
+    typ: Option<String>,
+}
+
+/// Signs and verifies tokens for one configured key
+#[derive(Clone)]
+pub struct JwtService {
+    config: JwtConfig,
+}
+
+impl JwtService {
+    pub fn new(config: JwtConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &JwtConfig {
+        &self.config
+    }
+
+    /// Build claims for `sub` with the configured issuer and lifetime
+    pub fn claims_for(&self, sub: impl Into<String>, scopes: &[&str]) -> Claims {
+        let now = chrono::Utc::now().timestamp();
+        Claims {
+            sub: sub.into(),
+            iss: self.config.issuer.clone(),
+            iat: now,
+            exp: now + self.config.ttl_secs as i64,
+            nbf: None,
+            jti: uuid::Uuid::new_v4().to_string(),
+            scope: scopes.join(" "),
+            sid: None,
+            client_id: None,
+        }
+    }
+
+    /// Encode and sign claims
+    pub fn encode(&self, claims: &Claims) -> Result<String, AuthError> {
+        let header = Header {
+            alg: self.config.algorithm().as_str().to_string(),
+            typ: Some("JWT".to_string()),
+        };
+        let header = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&header).map_err(|e| AuthError::Internal(e.to_string()))?);
+        let payload = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(claims).map_err(|e| AuthError::Internal(e.to_string()))?);
+        let signing_input = format!("{}.{}", header, payload);
+
+        let signature = match &self.config.keys {
+            JwtKeys::Hmac(secret) => {
+                let mut mac = HmacSha256::new_from_slice(secret)
+                    .map_err(|e| AuthError::Internal(e.to_string()))?;
+                mac.update(signing_input.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            }
+            JwtKeys::Ed25519 { signing, .. } => {
+                let signing = signing.as_ref().ok_or_else(|| {
+                    AuthError::Configuration("no Ed25519 signing key configured".to_string())
+                })?;
+                signing.sign(signing_input.as_bytes()).to_bytes().to_vec()
+            }
+        };
+
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Verify signature, algorithm, issuer and validity window
+    pub fn decode(&self, token: &str) -> Result<Claims, AuthError> {
+        let mut parts = token.split('.');
+        let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AuthError::InvalidToken("malformed token".to_string()));
+        };
+
+        let header: Header = decode_json(header_b64)?;
+        if header.alg != self.config.algorithm().as_str() {
+            return Err(AuthError::InvalidToken(format!(
+                "unexpected algorithm {}",
+                header.alg
+            )));
+        }
+
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature_b64)
+            .map_err(|_| AuthError::InvalidToken("malformed signature".to_string()))?;
+        let signing_input = &token[..header_b64.len() + 1 + payload_b64.len()];
+
+        let valid = match &self.config.keys {
+            JwtKeys::Hmac(secret) => {
+                let mut mac = HmacSha256::new_from_slice(secret)
+                    .map_err(|e| AuthError::Internal(e.to_string()))?;
+                mac.update(signing_input.as_bytes());
+                mac.verify_slice(&signature).is_ok()
+            }
+            JwtKeys::Ed25519 { verifying, .. } => match Signature::from_slice(&signature) {
+                Ok(signature) => verifying
+                    .verify_strict(signing_input.as_bytes(), &signature)
+                    .is_ok(),
+                Err(_) => false,
+            },
+        };
+        if !valid {
+            return Err(AuthError::InvalidToken("bad signature".to_string()));
+        }
+
+        let claims: Claims = decode_json(payload_b64)?;
+        let now = chrono::Utc::now().timestamp();
+        if claims.exp + LEEWAY_SECS < now {
+            return Err(AuthError::Expired);
+        }
+        if let Some(nbf) = claims.nbf {
+            if nbf - LEEWAY_SECS > now {
+                return Err(AuthError::InvalidToken("token not yet valid".to_string()));
+            }
+        }
+        if claims.iss != self.config.issuer {
+            return Err(AuthError::InvalidToken("unexpected issuer".to_string()));
+        }
+
+        Ok(claims)
+    }
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(segment: &str) -> Result<T, AuthError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|_| AuthError::InvalidToken("malformed token segment".to_string()))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|_| AuthError::InvalidToken("malformed token segment".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn hs256() -> JwtService {
+        JwtService::new(JwtConfig::hs256(SECRET, "aframp").unwrap())
+    }
+
+    #[test]
+    fn test_hs256_round_trip() {
+        let service = hs256();
+        let claims = service.claims_for("user-1", &["quotes:write", "payments:initiate"]);
+        let token = service.encode(&claims).unwrap();
+        let decoded = service.decode(&token).unwrap();
+        assert_eq!(decoded, claims);
+        assert_eq!(decoded.scopes(), vec!["quotes:write", "payments:initiate"]);
+    }
+
+    #[test]
+    fn test_eddsa_round_trip_and_verify_only() {
+        let issuer = JwtService::new(JwtConfig::ed25519([7u8; 32], "aframp"));
+        let token = issuer.encode(&issuer.claims_for("user-1", &[])).unwrap();
+        assert!(issuer.decode(&token).is_ok());
+
+        let public = SigningKey::from_bytes(&[7u8; 32])
+            .verifying_key()
+            .to_bytes();
+        let verifier = JwtService::new(JwtConfig::ed25519_verify_only(public, "aframp").unwrap());
+        assert!(verifier.decode(&token).is_ok());
+        assert!(verifier.encode(&verifier.claims_for("x", &[])).is_err());
+    }
+
+    #[test]
+    fn test_rejects_tampered_payload() {
+        let service = hs256();
+        let token = service.encode(&service.claims_for("user-1", &[])).unwrap();
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let forged = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&service.claims_for("admin", &["admin"])).unwrap());
+        parts[1] = &forged;
+        assert!(matches!(
+            service.decode(&parts.join(".")),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_algorithm_mismatch_and_none() {
+        let hs = hs256();
+        let ed = JwtService::new(JwtConfig::ed25519([7u8; 32], "aframp"));
+        let token = ed.encode(&ed.claims_for("user-1", &[])).unwrap();
+        assert!(hs.decode(&token).is_err());
+
+        let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"none"}"#);
+        let payload =
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&hs.claims_for("user-1", &[])).unwrap());
+        assert!(hs.decode(&format!("{}.{}.", header, payload)).is_err());
+    }
+
+    #[test]
+    fn test_rejects_expired_and_wrong_issuer() {
+        let service = hs256();
+        let mut claims = service.claims_for("user-1", &[]);
+        claims.exp = chrono::Utc::now().timestamp() - 3600;
+        let token = service.encode(&claims).unwrap();
+        assert!(matches!(service.decode(&token), Err(AuthError::Expired)));
+
+        let other = JwtService::new(JwtConfig::hs256(SECRET, "someone-else").unwrap());
+        let token = other.encode(&other.claims_for("user-1", &[])).unwrap();
+        assert!(service.decode(&token).is_err());
+    }
+
+    #[test]
+    fn test_short_secret_rejected() {
+        assert!(JwtConfig::hs256(b"short".to_vec(), "aframp").is_err());
+    }
+}
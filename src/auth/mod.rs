@@ -0,0 +1,180 @@
+//! Authentication for the HTTP API
+//!
+//! Partners authenticate with API keys stored hashed in Postgres; end users
+//! authenticate with JWT sessions signed with HS256 or EdDSA. Wallets obtain
+//! such a session through SEP-10, with the Stellar account as the subject.
+//! All resolve to a [`Principal`] whose scopes are checked per route group by
+//! `middleware::auth`.
+
+pub mod api_key;
+pub mod jwt;
+pub mod sep10;
+pub mod service;
+
+pub use service::AuthService;
+
+use crate::chains::stellar::types::is_valid_account_id;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Scopes understood by the API
+pub mod scopes {
+    pub const QUOTES_WRITE: &str = "quotes:write";
+    pub const PAYMENTS_INITIATE: &str = "payments:initiate";
+    pub const PAYMENTS_READ: &str = "payments:read";
+    pub const TRUSTLINES_WRITE: &str = "trustlines:write";
+    pub const WEBHOOKS_MANAGE: &str = "webhooks:manage";
+    pub const WALLET_READ: &str = "wallet:read";
+    pub const TRANSFERS_WRITE: &str = "transfers:write";
+    pub const KYC_WRITE: &str = "kyc:write";
+    pub const ADMIN: &str = "admin";
+}
+
+/// How the caller authenticated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrincipalKind {
+    /// Partner or service using an API key
+    ApiClient,
+    /// End user holding a JWT session
+    User,
+}
+
+/// Authenticated caller, inserted into request extensions by the auth middleware
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Principal {
+    pub kind: PrincipalKind,
+    /// API key ID for clients, JWT `sub` for users
+    pub subject: String,
+    pub client_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    /// JWT session ID, when the token is backed by one
+    pub session_id: Option<String>,
+}
+
+impl Principal {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Scopes from `required` that this principal lacks
+    pub fn missing_scopes<'a>(&self, required: &[&'a str]) -> Vec<&'a str> {
+        required
+            .iter()
+            .copied()
+            .filter(|scope| !self.has_scope(scope))
+            .collect()
+    }
+
+    /// Stellar account a SEP-10 session was issued for
+    pub fn stellar_account(&self) -> Option<&str> {
+        (self.kind == PrincipalKind::User && is_valid_account_id(&self.subject))
+            .then_some(self.subject.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("missing credentials")]
+    MissingCredentials,
+
+    #[error("invalid API key")]
+    InvalidApiKey,
+
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("token expired")]
+    Expired,
+
+    #[error("session revoked or expired")]
+    Revoked,
+
+    #[error("invalid challenge: {0}")]
+    InvalidChallenge(String),
+
+    #[error("missing required scope: {0}")]
+    InsufficientScope(String),
+
+    #[error("authentication method not configured: {0}")]
+    Unavailable(String),
+
+    #[error("auth configuration error: {0}")]
+    Configuration(String),
+
+    #[error("internal auth error: {0}")]
+    Internal(String),
+}
+
+impl AuthError {
+    /// HTTP status for this error
+    pub fn status_code(&self) -> u16 {
+        match self {
+            AuthError::MissingCredentials
+            | AuthError::InvalidApiKey
+            | AuthError::InvalidToken(_)
+            | AuthError::Expired
+            | AuthError::Revoked => 401,
+            AuthError::InvalidChallenge(_) => 400,
+            AuthError::InsufficientScope(_) => 403,
+            AuthError::Unavailable(_) => 503,
+            AuthError::Configuration(_) | AuthError::Internal(_) => 500,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_scopes() {
+        let principal = Principal {
+            kind: PrincipalKind::ApiClient,
+            subject: "key-1".to_string(),
+            client_id: Some(Uuid::new_v4()),
+            user_id: None,
+            scopes: vec![scopes::QUOTES_WRITE.to_string()],
+            session_id: None,
+        };
+
+        assert!(principal.has_scope(scopes::QUOTES_WRITE));
+        assert!(principal.missing_scopes(&[scopes::QUOTES_WRITE]).is_empty());
+        assert_eq!(
+            principal.missing_scopes(&[scopes::QUOTES_WRITE, scopes::PAYMENTS_INITIATE]),
+            vec![scopes::PAYMENTS_INITIATE]
+        );
+    }
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(AuthError::MissingCredentials.status_code(), 401);
+        assert_eq!(AuthError::Expired.status_code(), 401);
+        assert_eq!(
+            AuthError::InsufficientScope("admin".to_string()).status_code(),
+            403
+        );
+        assert_eq!(AuthError::Unavailable("jwt".to_string()).status_code(), 503);
+        assert_eq!(
+            AuthError::InvalidChallenge("expired".to_string()).status_code(),
+            400
+        );
+    }
+
+    #[test]
+    fn test_stellar_account_only_for_account_subjects() {
+        let mut principal = Principal {
+            kind: PrincipalKind::User,
+            subject: "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7".to_string(),
+            client_id: None,
+            user_id: None,
+            scopes: vec![scopes::WALLET_READ.to_string()],
+            session_id: Some("session-1".to_string()),
+        };
+        assert_eq!(principal.stellar_account(), Some(principal.subject.as_str()));
+
+        principal.subject = Uuid::new_v4().to_string();
+        assert_eq!(principal.stellar_account(), None);
+    }
+}
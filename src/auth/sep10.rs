@@ -0,0 +1,590 @@
+//! SEP-10 Stellar Web Authentication
+//!
+//! A wallet proves control of a Stellar account by signing a challenge
+//! transaction we build and sign with the server key. The challenge is never
+//! submitted: it has sequence number 0 and only carries `ManageData`
+//! operations with a random nonce. Verification accepts the signatures of the
+//! account's signers when their combined weight reaches the medium threshold,
+//! or the master key alone for accounts that do not exist on the ledger yet.
+
+use super::AuthError;
+use crate::chains::stellar::payment::{network_id, parse_muxed_account, unix_time};
+use crate::chains::stellar::signer::{signer_from_env, LocalSigner, TransactionSigner};
+use crate::chains::stellar::types::{is_valid_account_id, StellarAccountInfo};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Verifier, VerifyingKey};
+use std::collections::HashSet;
+use std::sync::Arc;
+use stellar_strkey::ed25519::PublicKey as StrkeyPublicKey;
+use stellar_xdr::next::{
+    BytesM, DataValue, DecoratedSignature, Limits, ManageDataOp, Memo, MuxedAccount, Operation,
+    OperationBody, Preconditions, ReadXdr, SequenceNumber, String64, StringM, TimeBounds,
+    TimePoint, Transaction, TransactionEnvelope, TransactionExt, TransactionV1Envelope, Uint256,
+    VecM, WriteXdr,
+};
+
+/// Name of the operation carrying the web auth domain
+const WEB_AUTH_DOMAIN_KEY: &str = "web_auth_domain";
+
+/// Random bytes in the nonce; base64 encodes them to the 64-byte data value
+const NONCE_BYTES: usize = 48;
+
+/// Allowed clock skew when checking the challenge time bounds
+const LEEWAY_SECS: u64 = 30;
+
+/// SEP-10 server configuration
+#[derive(Clone)]
+pub struct Sep10Config {
+    signer: Arc<dyn TransactionSigner>,
+    server_key: VerifyingKey,
+    /// Domain the challenge's first operation is named after (`<domain> auth`)
+    pub home_domain: String,
+    /// Host serving the auth endpoint
+    pub web_auth_domain: String,
+    pub challenge_ttl_secs: u64,
+}
+
+impl Sep10Config {
+    pub fn new(
+        signer: Arc<dyn TransactionSigner>,
+        home_domain: impl Into<String>,
+    ) -> Result<Self, AuthError> {
+        let server_key = verifying_key(signer.account_id()).ok_or_else(|| {
+            AuthError::Configuration("SEP-10 signer account is not an ed25519 key".to_string())
+        })?;
+        let home_domain = home_domain.into();
+        if home_domain.is_empty() || home_domain.len() + " auth".len() > 64 {
+            return Err(AuthError::Configuration(format!(
+                "SEP-10 home domain must be 1-59 characters: {:?}",
+                home_domain
+            )));
+        }
+        Ok(Self {
+            signer,
+            server_key,
+            web_auth_domain: home_domain.clone(),
+            home_domain,
+            challenge_ttl_secs: 900,
+        })
+    }
+
+    pub fn with_web_auth_domain(mut self, domain: impl Into<String>) -> Self {
+        self.web_auth_domain = domain.into();
+        self
+    }
+
+    pub fn with_challenge_ttl(mut self, ttl_secs: u64) -> Self {
+        self.challenge_ttl_secs = ttl_secs;
+        self
+    }
+
+    /// Public key of the server signing key, as published in `stellar.toml`
+    pub fn server_account(&self) -> String {
+        self.signer.account_id().to_string()
+    }
+
+    /// Load from environment. Returns `Ok(None)` when no signing key is set.
+    ///
+    /// - `SEP10_SIGNER`: server signing key, loaded like the other platform
+    ///   keys (see [`signer_from_env`]); `SEP10_SIGNING_KEY` is still read as
+    ///   a plain secret seed when no signer is configured
+    /// - `SEP10_HOME_DOMAIN` (default `localhost`)
+    /// - `SEP10_WEB_AUTH_DOMAIN` (default: the home domain)
+    /// - `SEP10_CHALLENGE_TTL_SECONDS` (default 900)
+    pub fn from_env() -> Result<Option<Self>, AuthError> {
+        let configured = signer_from_env("SEP10").map_err(|e| {
+            AuthError::Configuration(format!("invalid SEP-10 signing key: {}", e))
+        })?;
+        let signer = match configured {
+            Some(signer) => signer,
+            None => {
+                let Ok(seed) = std::env::var("SEP10_SIGNING_KEY") else {
+                    return Ok(None);
+                };
+                let signer = LocalSigner::from_secret(seed.trim()).map_err(|_| {
+                    AuthError::Configuration("invalid SEP-10 signing key secret seed".to_string())
+                })?;
+                Arc::new(signer)
+            }
+        };
+        let home_domain =
+            std::env::var("SEP10_HOME_DOMAIN").unwrap_or_else(|_| "localhost".to_string());
+        let mut config = Self::new(signer, home_domain)?;
+        if let Ok(domain) = std::env::var("SEP10_WEB_AUTH_DOMAIN") {
+            config = config.with_web_auth_domain(domain);
+        }
+        if let Some(ttl) = std::env::var("SEP10_CHALLENGE_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config = config.with_challenge_ttl(ttl);
+        }
+        Ok(Some(config))
+    }
+}
+
+/// A challenge that passed the structural and server-signature checks
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    /// Account the client claims to control
+    pub client_account: String,
+    /// Transaction hash; signatures are over this
+    pub hash: [u8; 32],
+    /// Signatures other than the server's
+    client_signatures: Vec<DecoratedSignature>,
+}
+
+/// Builds and verifies SEP-10 challenge transactions
+pub struct Sep10Service {
+    config: Sep10Config,
+    server_account: String,
+    network_passphrase: String,
+}
+
+impl Sep10Service {
+    pub fn new(config: Sep10Config, network_passphrase: impl Into<String>) -> Self {
+        Self {
+            server_account: config.server_account(),
+            config,
+            network_passphrase: network_passphrase.into(),
+        }
+    }
+
+    pub fn config(&self) -> &Sep10Config {
+        &self.config
+    }
+
+    pub fn server_account(&self) -> &str {
+        &self.server_account
+    }
+
+    pub fn network_passphrase(&self) -> &str {
+        &self.network_passphrase
+    }
+
+    /// Build a server-signed challenge for `account`, base64 envelope XDR
+    pub async fn challenge(&self, account: &str) -> Result<String, AuthError> {
+        let client = client_account(account)?;
+
+        let mut nonce = [0u8; NONCE_BYTES];
+        OsRng.fill_bytes(&mut nonce);
+
+        let operations = vec![
+            manage_data(
+                Some(client),
+                &format!("{} auth", self.config.home_domain),
+                STANDARD.encode(nonce).as_bytes(),
+            )?,
+            manage_data(
+                Some(self.server_muxed()?),
+                WEB_AUTH_DOMAIN_KEY,
+                self.config.web_auth_domain.as_bytes(),
+            )?,
+        ];
+
+        let now = unix_time();
+        let tx = Transaction {
+            source_account: self.server_muxed()?,
+            fee: 100 * operations.len() as u32,
+            seq_num: SequenceNumber(0),
+            cond: Preconditions::Time(TimeBounds {
+                min_time: TimePoint(now),
+                max_time: TimePoint(now + self.config.challenge_ttl_secs),
+            }),
+            memo: Memo::None,
+            operations: VecM::try_from(operations).map_err(internal)?,
+            ext: TransactionExt::V0,
+        };
+
+        let hash = self.hash(&tx)?;
+        let signatures = self
+            .config
+            .signer
+            .sign_hash(&hash)
+            .await
+            .map_err(internal)?;
+        TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::try_from(signatures).map_err(internal)?,
+        })
+        .to_xdr_base64(Limits::none())
+        .map_err(internal)
+    }
+
+    /// Decode a signed challenge and check that we issued it, it has not
+    /// expired and it is shaped like one of ours
+    pub fn read_challenge(&self, xdr: &str) -> Result<Challenge, AuthError> {
+        let envelope = TransactionEnvelope::from_xdr_base64(xdr.trim(), Limits::none())
+            .map_err(|_| invalid("transaction is not a valid envelope"))?;
+        let TransactionEnvelope::Tx(TransactionV1Envelope { tx, signatures }) = envelope else {
+            return Err(invalid("challenge must be a v1 transaction envelope"));
+        };
+
+        if account_of(&tx.source_account).as_deref() != Some(self.server_account.as_str()) {
+            return Err(invalid("challenge source is not the server account"));
+        }
+        if tx.seq_num.0 != 0 {
+            return Err(invalid("challenge sequence number must be 0"));
+        }
+
+        let Preconditions::Time(bounds) = &tx.cond else {
+            return Err(invalid("challenge has no time bounds"));
+        };
+        let now = unix_time();
+        if bounds.max_time.0 == 0
+            || now + LEEWAY_SECS < bounds.min_time.0
+            || now > bounds.max_time.0 + LEEWAY_SECS
+        {
+            return Err(invalid("challenge has expired"));
+        }
+
+        let client_account = self.check_operations(&tx.operations)?;
+
+        let hash = self.hash(&tx)?;
+        let server_key = self.config.server_key;
+        let mut client_signatures = Vec::with_capacity(signatures.len());
+        let mut server_signed = false;
+        for signature in signatures.iter() {
+            if !server_signed && verifies(&server_key, &hash, signature) {
+                server_signed = true;
+            } else {
+                client_signatures.push(signature.clone());
+            }
+        }
+        if !server_signed {
+            return Err(invalid("challenge is not signed by the server"));
+        }
+
+        Ok(Challenge {
+            client_account,
+            hash,
+            client_signatures,
+        })
+    }
+
+    /// Check the client's signatures against its signers.
+    ///
+    /// With the account loaded from Horizon the signers' combined weight must
+    /// reach the medium threshold; without it (the account is not funded yet)
+    /// only the master key may sign. Any signature that does not belong to a
+    /// signer rejects the challenge. Returns the accumulated weight.
+    pub fn verify_signers(
+        &self,
+        challenge: &Challenge,
+        account: Option<&StellarAccountInfo>,
+    ) -> Result<u32, AuthError> {
+        let (signers, threshold) = match account {
+            Some(info) => {
+                if info.account_id != challenge.client_account {
+                    return Err(invalid("account does not match the challenge"));
+                }
+                let signers = info
+                    .signers
+                    .iter()
+                    .filter(|s| s.weight > 0 && s.key != self.server_account)
+                    .filter_map(|s| Some((verifying_key(&s.key)?, s.weight as u32)))
+                    .collect::<Vec<_>>();
+                (signers, (info.thresholds.med_threshold as u32).max(1))
+            }
+            None => {
+                let master = verifying_key(&challenge.client_account)
+                    .ok_or_else(|| invalid("challenge account is not a valid public key"))?;
+                (vec![(master, 1)], 1)
+            }
+        };
+
+        let mut used = HashSet::new();
+        let mut weight = 0u32;
+        for signature in &challenge.client_signatures {
+            let signer = signers.iter().enumerate().find(|(index, (key, _))| {
+                !used.contains(index) && verifies(key, &challenge.hash, signature)
+            });
+            match signer {
+                Some((index, (_, signer_weight))) => {
+                    used.insert(index);
+                    weight += signer_weight;
+                }
+                None => return Err(invalid("challenge has unrecognized signatures")),
+            }
+        }
+
+        if weight == 0 {
+            return Err(invalid("challenge is not signed by the client"));
+        }
+        if weight < threshold {
+            return Err(invalid(format!(
+                "signers weight {} is below the account's medium threshold {}",
+                weight, threshold
+            )));
+        }
+        Ok(weight)
+    }
+
+    /// Validate the challenge operations and return the client account
+    fn check_operations(&self, operations: &[Operation]) -> Result<String, AuthError> {
+        let (first, rest) = operations
+            .split_first()
+            .ok_or_else(|| invalid("challenge has no operations"))?;
+
+        let (source, data) = match (&first.source_account, &first.body) {
+            (Some(source), OperationBody::ManageData(data)) => (source, data),
+            _ => {
+                return Err(invalid(
+                    "first operation must be manage data with a source account",
+                ))
+            }
+        };
+        let client_account =
+            account_of(source).ok_or_else(|| invalid("client account must be a G... address"))?;
+        if data.data_name.0.to_utf8_string_lossy() != format!("{} auth", self.config.home_domain) {
+            return Err(invalid("challenge is not for this home domain"));
+        }
+        match &data.data_value {
+            Some(value) if value.0.len() == 64 => {}
+            _ => return Err(invalid("challenge nonce must be 64 bytes")),
+        }
+
+        for op in rest {
+            let OperationBody::ManageData(data) = &op.body else {
+                return Err(invalid("challenge may only contain manage data operations"));
+            };
+            let source = op.source_account.as_ref().and_then(account_of);
+            if source.as_deref() != Some(self.server_account.as_str()) {
+                return Err(invalid(
+                    "subsequent operations must have the server as source",
+                ));
+            }
+            if data.data_name.0.to_utf8_string_lossy() == WEB_AUTH_DOMAIN_KEY
+                && data.data_value.as_ref().map(|v| v.0.as_slice())
+                    != Some(self.config.web_auth_domain.as_bytes())
+            {
+                return Err(invalid("challenge web_auth_domain does not match"));
+            }
+        }
+
+        Ok(client_account)
+    }
+
+    fn server_muxed(&self) -> Result<MuxedAccount, AuthError> {
+        parse_muxed_account(&self.server_account).map_err(internal)
+    }
+
+    fn hash(&self, tx: &Transaction) -> Result<[u8; 32], AuthError> {
+        tx.hash(network_id(&self.network_passphrase))
+            .map_err(internal)
+    }
+}
+
+fn client_account(account: &str) -> Result<MuxedAccount, AuthError> {
+    if !is_valid_account_id(account) {
+        return Err(invalid("account must be a G... Stellar address"));
+    }
+    parse_muxed_account(account).map_err(|_| invalid("account is not a valid Stellar address"))
+}
+
+fn manage_data(
+    source: Option<MuxedAccount>,
+    name: &str,
+    value: &[u8],
+) -> Result<Operation, AuthError> {
+    let name: StringM<64> = name.try_into().map_err(internal)?;
+    let value: BytesM<64> = value.to_vec().try_into().map_err(internal)?;
+    Ok(Operation {
+        source_account: source,
+        body: OperationBody::ManageData(ManageDataOp {
+            data_name: String64(name),
+            data_value: Some(DataValue(value)),
+        }),
+    })
+}
+
+fn account_of(account: &MuxedAccount) -> Option<String> {
+    match account {
+        MuxedAccount::Ed25519(Uint256(key)) => {
+            Some(StrkeyPublicKey(*key).to_string().as_str().to_owned())
+        }
+        MuxedAccount::MuxedEd25519(_) => None,
+    }
+}
+
+fn verifying_key(account: &str) -> Option<VerifyingKey> {
+    let public = StrkeyPublicKey::from_string(account).ok()?;
+    VerifyingKey::from_bytes(&public.0).ok()
+}
+
+fn verifies(key: &VerifyingKey, hash: &[u8; 32], signature: &DecoratedSignature) -> bool {
+    let bytes = key.to_bytes();
+    if signature.hint.0 != bytes[bytes.len() - 4..] {
+        return false;
+    }
+    let Ok(signature) = ed25519_dalek::Signature::from_slice(signature.signature.as_slice()) else {
+        return false;
+    };
+    key.verify(hash, &signature).is_ok()
+}
+
+fn invalid(message: impl Into<String>) -> AuthError {
+    AuthError::InvalidChallenge(message.into())
+}
+
+fn internal(e: impl std::fmt::Display) -> AuthError {
+    AuthError::Internal(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::stellar::payment::signature_hint;
+    use crate::chains::stellar::types::{AccountFlags, Signer as AccountSigner, Thresholds};
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::collections::HashMap;
+    use stellar_xdr::next::Signature;
+
+    const PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+    fn service_with_key(seed: [u8; 32]) -> Sep10Service {
+        let signer = LocalSigner::from_signing_key(SigningKey::from_bytes(&seed));
+        let config = Sep10Config::new(Arc::new(signer), "aframp.test").unwrap();
+        Sep10Service::new(config, PASSPHRASE)
+    }
+
+    fn service() -> Sep10Service {
+        service_with_key([7u8; 32])
+    }
+
+    fn account(key: &SigningKey) -> String {
+        StrkeyPublicKey(key.verifying_key().to_bytes())
+            .to_string()
+            .as_str()
+            .to_owned()
+    }
+
+    fn sign(xdr: &str, keys: &[&SigningKey]) -> String {
+        let TransactionEnvelope::Tx(mut envelope) =
+            TransactionEnvelope::from_xdr_base64(xdr, Limits::none()).unwrap()
+        else {
+            panic!("expected v1 envelope");
+        };
+        let hash = envelope.tx.hash(network_id(PASSPHRASE)).unwrap();
+        let mut signatures = envelope.signatures.to_vec();
+        for key in keys {
+            signatures.push(DecoratedSignature {
+                hint: signature_hint(key).unwrap(),
+                signature: Signature::try_from(key.sign(&hash).to_bytes().to_vec()).unwrap(),
+            });
+        }
+        envelope.signatures = signatures.try_into().unwrap();
+        TransactionEnvelope::Tx(envelope)
+            .to_xdr_base64(Limits::none())
+            .unwrap()
+    }
+
+    fn account_info(id: &str, signers: Vec<(String, u8)>, med_threshold: u8) -> StellarAccountInfo {
+        StellarAccountInfo {
+            account_id: id.to_string(),
+            sequence: 1,
+            subentry_count: 0,
+            thresholds: Thresholds {
+                low_threshold: 0,
+                med_threshold,
+                high_threshold: 0,
+            },
+            flags: AccountFlags {
+                auth_required: false,
+                auth_revocable: false,
+                auth_immutable: false,
+                auth_clawback_enabled: false,
+            },
+            balances: vec![],
+            signers: signers
+                .into_iter()
+                .map(|(key, weight)| AccountSigner {
+                    key,
+                    weight,
+                    r#type: "ed25519_public_key".to_string(),
+                })
+                .collect(),
+            data: HashMap::new(),
+            last_modified_ledger: 0,
+            created_at: String::new(),
+            num_sponsoring: 0,
+            num_sponsored: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_master_key_signs_challenge_for_new_account() {
+        let service = service();
+        let client = SigningKey::from_bytes(&[1u8; 32]);
+        let challenge = service.challenge(&account(&client)).await.unwrap();
+
+        let read = service
+            .read_challenge(&sign(&challenge, &[&client]))
+            .unwrap();
+        assert_eq!(read.client_account, account(&client));
+        assert_eq!(service.verify_signers(&read, None).unwrap(), 1);
+
+        let unsigned = service.read_challenge(&challenge).unwrap();
+        assert!(service.verify_signers(&unsigned, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multisig_must_reach_medium_threshold() {
+        let service = service();
+        let master = SigningKey::from_bytes(&[1u8; 32]);
+        let cosigner = SigningKey::from_bytes(&[2u8; 32]);
+        let id = account(&master);
+        let info = account_info(&id, vec![(id.clone(), 1), (account(&cosigner), 1)], 2);
+        let challenge = service.challenge(&id).await.unwrap();
+
+        let one = service
+            .read_challenge(&sign(&challenge, &[&master]))
+            .unwrap();
+        assert!(matches!(
+            service.verify_signers(&one, Some(&info)),
+            Err(AuthError::InvalidChallenge(_))
+        ));
+
+        let both = service
+            .read_challenge(&sign(&challenge, &[&master, &cosigner]))
+            .unwrap();
+        assert_eq!(service.verify_signers(&both, Some(&info)).unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_signature_is_rejected() {
+        let service = service();
+        let master = SigningKey::from_bytes(&[1u8; 32]);
+        let stranger = SigningKey::from_bytes(&[3u8; 32]);
+        let id = account(&master);
+        let info = account_info(&id, vec![(id.clone(), 1)], 1);
+        let challenge = service.challenge(&id).await.unwrap();
+
+        let read = service
+            .read_challenge(&sign(&challenge, &[&master, &stranger]))
+            .unwrap();
+        assert!(service.verify_signers(&read, Some(&info)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_challenge_from_another_server_is_rejected() {
+        let other = service_with_key([9u8; 32]);
+        let client = SigningKey::from_bytes(&[1u8; 32]);
+        let challenge = other.challenge(&account(&client)).await.unwrap();
+
+        assert!(service()
+            .read_challenge(&sign(&challenge, &[&client]))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_account_address() {
+        assert!(matches!(
+            service().challenge("not-an-account").await,
+            Err(AuthError::InvalidChallenge(_))
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+//! Credential verification and session management
+
+use super::api_key::hash_api_key;
+use super::jwt::{Claims, JwtService};
+use super::{AuthError, Principal, PrincipalKind};
+use crate::cache::cache::{ttl, Cache};
+use crate::cache::keys::auth::{JwtKey, SessionKey};
+use crate::cache::RedisCache;
+use crate::database::api_client_repository::ApiClientRepository;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Server-side session backing a user JWT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub subject: String,
+    pub client_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Cached result of verifying a token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum CachedToken {
+    Valid(Claims),
+    Revoked,
+}
+
+/// A token handed to a client
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedToken {
+    pub token: String,
+    pub expires_at: i64,
+    pub session_id: String,
+}
+
+/// Verifies API keys and JWTs and manages user sessions
+pub struct AuthService {
+    jwt: Option<JwtService>,
+    clients: Option<Arc<ApiClientRepository>>,
+    cache: Option<RedisCache>,
+}
+
+impl AuthService {
+    pub fn new(
+        jwt: Option<JwtService>,
+        clients: Option<Arc<ApiClientRepository>>,
+        cache: Option<RedisCache>,
+    ) -> Self {
+        Self {
+            jwt,
+            clients,
+            cache,
+        }
+    }
+
+    pub fn jwt(&self) -> Option<&JwtService> {
+        self.jwt.as_ref()
+    }
+
+    /// Resolve an API key to its client principal
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<Principal, AuthError> {
+        let clients = self
+            .clients
+            .as_ref()
+            .ok_or_else(|| AuthError::Unavailable("API keys".to_string()))?;
+
+        let record = clients
+            .find_active_key_by_hash(&hash_api_key(key))
+            .await
+            .map_err(|e| AuthError::Internal(e.to_string()))?
+            .ok_or(AuthError::InvalidApiKey)?;
+
+        if let Err(e) = clients.touch_key(record.id).await {
+            debug!(key_id = %record.id, error = %e, "failed to record API key usage");
+        }
+
+        Ok(Principal {
+            kind: PrincipalKind::ApiClient,
+            subject: record.id.to_string(),
+            client_id: Some(record.client_id),
+            user_id: None,
+            scopes: record.scopes,
+            session_id: None,
+        })
+    }
+
+    /// Resolve a bearer JWT to a user principal
+    pub async fn authenticate_jwt(&self, token: &str) -> Result<Principal, AuthError> {
+        let jwt = self
+            .jwt
+            .as_ref()
+            .ok_or_else(|| AuthError::Unavailable("JWT".to_string()))?;
+        let cache_key = JwtKey::new(token_hash(token)).to_string();
+
+        let cached = match &self.cache {
+            Some(cache) => <RedisCache as Cache<CachedToken>>::get(cache, &cache_key)
+                .await
+                .unwrap_or(None),
+            None => None,
+        };
+
+        let claims = match cached {
+            Some(CachedToken::Revoked) => return Err(AuthError::Revoked),
+            Some(CachedToken::Valid(claims)) if claims.exp > chrono::Utc::now().timestamp() => {
+                claims
+            }
+            _ => {
+                let claims = jwt.decode(token)?;
+                if let Some(cache) = &self.cache {
+                    let ttl = remaining(claims.exp).min(ttl::JWT_VALIDATION);
+                    if !ttl.is_zero() {
+                        let _ = cache
+                            .set(&cache_key, &CachedToken::Valid(claims.clone()), Some(ttl))
+                            .await;
+                    }
+                }
+                claims
+            }
+        };
+
+        if let (Some(sid), Some(cache)) = (&claims.sid, &self.cache) {
+            let session = <RedisCache as Cache<SessionRecord>>::get(
+                cache,
+                &SessionKey::new(sid.clone()).to_string(),
+            )
+            .await
+            .unwrap_or(None);
+            if session.is_none() {
+                return Err(AuthError::Revoked);
+            }
+        }
+
+        Ok(Principal {
+            kind: PrincipalKind::User,
+            user_id: Uuid::parse_str(&claims.sub).ok(),
+            client_id: claims
+                .client_id
+                .as_deref()
+                .and_then(|id| Uuid::parse_str(id).ok()),
+            scopes: claims.scopes(),
+            session_id: claims.sid.clone(),
+            subject: claims.sub,
+        })
+    }
+
+    /// Issue a session-backed JWT for an end user.
+    ///
+    /// The user's API client (from `users.api_client_id`) is embedded when known.
+    pub async fn issue_user_session(
+        &self,
+        subject: &str,
+        scopes: &[&str],
+    ) -> Result<IssuedToken, AuthError> {
+        let jwt = self
+            .jwt
+            .as_ref()
+            .ok_or_else(|| AuthError::Unavailable("JWT".to_string()))?;
+
+        let client_id = match (&self.clients, Uuid::parse_str(subject)) {
+            (Some(clients), Ok(user_id)) => clients
+                .find_client_for_user(user_id)
+                .await
+                .map_err(|e| AuthError::Internal(e.to_string()))?,
+            _ => None,
+        };
+
+        let session_id = Uuid::new_v4().to_string();
+        let mut claims = jwt.claims_for(subject, scopes);
+        claims.sid = Some(session_id.clone());
+        claims.client_id = client_id.map(|id| id.to_string());
+
+        if let Some(cache) = &self.cache {
+            let record = SessionRecord {
+                subject: subject.to_string(),
+                client_id,
+                scopes: claims.scopes(),
+                created_at: claims.iat,
+            };
+            cache
+                .set(
+                    &SessionKey::new(session_id.clone()).to_string(),
+                    &record,
+                    Some(remaining(claims.exp)),
+                )
+                .await
+                .map_err(|e| AuthError::Internal(e.to_string()))?;
+        }
+
+        Ok(IssuedToken {
+            token: jwt.encode(&claims)?,
+            expires_at: claims.exp,
+            session_id,
+        })
+    }
+
+    /// End a session; every token issued for it stops working
+    pub async fn revoke_session(&self, session_id: &str) -> Result<(), AuthError> {
+        if let Some(cache) = &self.cache {
+            <RedisCache as Cache<SessionRecord>>::delete(
+                cache,
+                &SessionKey::new(session_id).to_string(),
+            )
+            .await
+            .map_err(|e| AuthError::Internal(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Deny a single token until it expires
+    pub async fn revoke_token(&self, token: &str, expires_at: i64) -> Result<(), AuthError> {
+        let Some(cache) = &self.cache else {
+            warn!("token revocation requested but no cache is configured");
+            return Ok(());
+        };
+        let ttl = remaining(expires_at);
+        if ttl.is_zero() {
+            return Ok(());
+        }
+        cache
+            .set(
+                &JwtKey::new(token_hash(token)).to_string(),
+                &CachedToken::Revoked,
+                Some(ttl),
+            )
+            .await
+            .map_err(|e| AuthError::Internal(e.to_string()))
+    }
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn remaining(exp: i64) -> Duration {
+    Duration::from_secs((exp - chrono::Utc::now().timestamp()).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::JwtConfig;
+    use crate::auth::scopes;
+
+    fn service() -> AuthService {
+        let config =
+            JwtConfig::hs256(b"0123456789abcdef0123456789abcdef".to_vec(), "aframp").unwrap();
+        AuthService::new(Some(JwtService::new(config)), None, None)
+    }
+
+    #[tokio::test]
+    async fn test_issued_session_authenticates() {
+        let service = service();
+        let user_id = Uuid::new_v4().to_string();
+        let issued = service
+            .issue_user_session(&user_id, &[scopes::QUOTES_WRITE])
+            .await
+            .unwrap();
+
+        let principal = service.authenticate_jwt(&issued.token).await.unwrap();
+        assert_eq!(principal.kind, PrincipalKind::User);
+        assert_eq!(principal.user_id.unwrap().to_string(), user_id);
+        assert_eq!(
+            principal.session_id.as_deref(),
+            Some(issued.session_id.as_str())
+        );
+        assert!(principal.has_scope(scopes::QUOTES_WRITE));
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_unavailable_without_database() {
+        let result = service().authenticate_api_key("ak_whatever").await;
+        assert!(matches!(result, Err(AuthError::Unavailable(_))));
+    }
+
+    #[test]
+    fn test_token_hash_is_stable() {
+        assert_eq!(token_hash("abc"), token_hash("abc"));
+        assert_ne!(token_hash("abc"), token_hash("abd"));
+    }
+}
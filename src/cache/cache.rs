@@ -402,6 +402,9 @@ pub mod ttl {
 
     /// Bill payment providers: 30 minutes
     pub const BILL_PROVIDERS: Duration = Duration::from_secs(1800);
+
+    /// Idempotency keys: 24 hours
+    pub const IDEMPOTENCY_KEYS: Duration = Duration::from_secs(86400);
 }
 
 #[cfg(test)]
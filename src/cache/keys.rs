@@ -399,6 +399,33 @@ pub mod quote {
     }
 }
 
+pub mod idempotency {
+    use super::*;
+
+    pub const NAMESPACE: &str = "idempotency";
+
+    #[derive(Debug, Clone)]
+    pub struct IdempotencyKey {
+        pub scope: String,
+        pub key: String,
+    }
+
+    impl IdempotencyKey {
+        pub fn new(scope: impl Into<String>, key: impl Into<String>) -> Self {
+            Self {
+                scope: scope.into(),
+                key: key.into(),
+            }
+        }
+    }
+
+    impl fmt::Display for IdempotencyKey {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}:{}:{}:{}", VERSION, NAMESPACE, self.scope, self.key)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -432,4 +459,10 @@ mod tests {
         let key = auth::RateLimitKey::new("user_123", "login");
         assert_eq!(key.to_string(), "v1:auth:rate_limit:user_123:login");
     }
+
+    #[test]
+    fn test_idempotency_key() {
+        let key = idempotency::IdempotencyKey::new("orchestrator", "abc123");
+        assert_eq!(key.to_string(), "v1:idempotency:orchestrator:abc123");
+    }
 }
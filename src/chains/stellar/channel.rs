@@ -0,0 +1,578 @@
+//! Channel accounts
+//!
+//! A source account can only have one transaction per sequence number in
+//! flight, which caps payouts at roughly one transaction per ledger. Channel
+//! accounts are extra funded accounts that act as the transaction source, and
+//! pay its fee, while the hot wallet stays the source of every operation, so
+//! several payouts can be submitted in parallel. Each channel key is loaded
+//! like the other platform keys, from its own keystore file or remote
+//! signer, and the pool creates and funds the accounts itself.
+
+use crate::chains::stellar::client::StellarClient;
+use crate::chains::stellar::errors::{StellarError, StellarResult};
+use crate::chains::stellar::payment::{
+    decimal_from_stroops, decimal_to_stroops, unix_time, CngnMemo,
+};
+use crate::chains::stellar::signer::{signer_from_env, TransactionSigner};
+use crate::chains::stellar::submission::{PaymentAsset, QueuedPayment, SubmissionQueue};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// Keeps a mistyped count from creating and funding thousands of accounts
+pub const MAX_CHANNEL_ACCOUNTS: usize = 100;
+
+const DEFAULT_STARTING_BALANCE: &str = "5";
+const DEFAULT_MIN_BALANCE: &str = "2";
+const DEFAULT_LEASE_TIMEOUT_SECONDS: u64 = 30;
+const DEFAULT_HEALTH_CHECK_INTERVAL_SECONDS: u64 = 300;
+/// Between looks at the pool while waiting for a parked channel to expire
+const LEASE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Ledger close times can trail the local clock by a few seconds
+const PARKED_GRACE_SECONDS: u64 = 10;
+
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+    /// One signer per channel account
+    pub signers: Vec<Arc<dyn TransactionSigner>>,
+    /// XLM a channel is created with and topped back up to
+    pub starting_balance: String,
+    /// A channel below this XLM balance is topped up
+    pub min_balance: String,
+    /// How long a build waits for a free channel
+    pub lease_timeout: Duration,
+    pub health_check_interval: Duration,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            signers: Vec::new(),
+            starting_balance: DEFAULT_STARTING_BALANCE.to_string(),
+            min_balance: DEFAULT_MIN_BALANCE.to_string(),
+            lease_timeout: Duration::from_secs(DEFAULT_LEASE_TIMEOUT_SECONDS),
+            health_check_interval: Duration::from_secs(DEFAULT_HEALTH_CHECK_INTERVAL_SECONDS),
+        }
+    }
+}
+
+impl ChannelConfig {
+    /// Channel `n` of `STELLAR_CHANNEL_COUNT` is signed for by the signer
+    /// selected by `STELLAR_CHANNEL_<n>_SIGNER`, counting from 1; see
+    /// [`signer_from_env`]
+    pub fn from_env() -> StellarResult<Self> {
+        let mut cfg = Self::default();
+
+        let count = std::env::var("STELLAR_CHANNEL_COUNT")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        if count > MAX_CHANNEL_ACCOUNTS {
+            return Err(StellarError::config_error(format!(
+                "STELLAR_CHANNEL_COUNT must be between 1 and {}",
+                MAX_CHANNEL_ACCOUNTS
+            )));
+        }
+        for n in 1..=count {
+            let prefix = format!("STELLAR_CHANNEL_{}", n);
+            let signer = signer_from_env(&prefix)?.ok_or_else(|| {
+                StellarError::config_error(format!("{}_SIGNER is not configured", prefix))
+            })?;
+            cfg.signers.push(signer);
+        }
+
+        if let Ok(balance) = std::env::var("STELLAR_CHANNEL_STARTING_BALANCE") {
+            cfg.starting_balance = balance;
+        }
+        if let Ok(balance) = std::env::var("STELLAR_CHANNEL_MIN_BALANCE") {
+            cfg.min_balance = balance;
+        }
+
+        cfg.lease_timeout = Duration::from_secs(
+            std::env::var("STELLAR_CHANNEL_LEASE_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.lease_timeout.as_secs()),
+        );
+
+        cfg.health_check_interval = Duration::from_secs(
+            std::env::var("STELLAR_CHANNEL_HEALTH_CHECK_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.health_check_interval.as_secs()),
+        );
+
+        Ok(cfg)
+    }
+
+    /// Whether channel accounts are configured at all
+    pub fn is_enabled(&self) -> bool {
+        !self.signers.is_empty()
+    }
+
+    pub fn validate(&self) -> StellarResult<()> {
+        if self.signers.is_empty() || self.signers.len() > MAX_CHANNEL_ACCOUNTS {
+            return Err(StellarError::config_error(format!(
+                "STELLAR_CHANNEL_COUNT must be between 1 and {}",
+                MAX_CHANNEL_ACCOUNTS
+            )));
+        }
+        let mut accounts = HashSet::new();
+        if let Some(duplicate) = self
+            .signers
+            .iter()
+            .map(|signer| signer.account_id())
+            .find(|account| !accounts.insert(*account))
+        {
+            return Err(StellarError::config_error(format!(
+                "channel account {} is configured more than once",
+                duplicate
+            )));
+        }
+        let starting = decimal_to_stroops(&self.starting_balance)?;
+        let min = decimal_to_stroops(&self.min_balance)?;
+        if min <= 0 || starting <= min {
+            return Err(StellarError::config_error(
+                "STELLAR_CHANNEL_STARTING_BALANCE must be above STELLAR_CHANNEL_MIN_BALANCE",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Result of a channel health check
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelStatus {
+    pub account_id: String,
+    pub healthy: bool,
+    pub xlm_balance: Option<String>,
+    /// Hash of the create or top-up payment sent during the check
+    pub funding_tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotState {
+    Idle,
+    Leased,
+    /// Reserved for a built envelope until it is submitted or its
+    /// timebounds pass
+    Parked {
+        sequence: i64,
+        until: u64,
+    },
+}
+
+struct ChannelSlot {
+    signer: Arc<dyn TransactionSigner>,
+    healthy: bool,
+    /// Next unused sequence, or `None` to read it from Horizon
+    next_sequence: Option<i64>,
+    state: SlotState,
+}
+
+struct PoolInner {
+    stellar_client: StellarClient,
+    config: ChannelConfig,
+    slots: Mutex<Vec<ChannelSlot>>,
+    released: Notify,
+}
+
+impl PoolInner {
+    fn slots(&self) -> MutexGuard<'_, Vec<ChannelSlot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Pool of channel accounts. Cheap to clone; clones share the pool.
+#[derive(Clone)]
+pub struct ChannelAccountPool {
+    inner: Arc<PoolInner>,
+}
+
+impl fmt::Debug for ChannelAccountPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelAccountPool")
+            .field("accounts", &self.accounts())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChannelAccountPool {
+    /// Channels start unhealthy until [`check`] has confirmed they exist and
+    /// are funded.
+    ///
+    /// [`check`]: ChannelAccountPool::check
+    pub fn new(stellar_client: StellarClient, config: ChannelConfig) -> StellarResult<Self> {
+        config.validate()?;
+        let slots = config
+            .signers
+            .iter()
+            .map(|signer| ChannelSlot {
+                signer: signer.clone(),
+                healthy: false,
+                next_sequence: None,
+                state: SlotState::Idle,
+            })
+            .collect();
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                stellar_client,
+                config,
+                slots: Mutex::new(slots),
+                released: Notify::new(),
+            }),
+        })
+    }
+
+    pub fn config(&self) -> &ChannelConfig {
+        &self.inner.config
+    }
+
+    pub fn accounts(&self) -> Vec<String> {
+        self.inner
+            .slots()
+            .iter()
+            .map(|slot| slot.signer.account_id().to_string())
+            .collect()
+    }
+
+    /// Lease a free healthy channel if there is one right now
+    pub fn try_lease(&self) -> Option<ChannelLease> {
+        let now = unix_time();
+        let mut slots = self.inner.slots();
+        let (index, slot) = slots.iter_mut().enumerate().find(|(_, slot)| {
+            slot.healthy
+                && match slot.state {
+                    SlotState::Idle => true,
+                    SlotState::Leased => false,
+                    SlotState::Parked { until, .. } => until < now,
+                }
+        })?;
+        if matches!(slot.state, SlotState::Parked { .. }) {
+            // The parked envelope may or may not have been submitted
+            slot.next_sequence = None;
+        }
+        slot.state = SlotState::Leased;
+        Some(ChannelLease {
+            pool: self.inner.clone(),
+            index,
+            signer: slot.signer.clone(),
+            next_sequence: slot.next_sequence,
+            parked: false,
+        })
+    }
+
+    /// Lease a channel, waiting up to the lease timeout for one to free up
+    pub async fn lease(&self) -> StellarResult<ChannelLease> {
+        let timeout = self.inner.config.lease_timeout;
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let released = self.inner.released.notified();
+            if let Some(lease) = self.try_lease() {
+                return Ok(lease);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(StellarError::timeout_error(timeout.as_secs()));
+            }
+            let _ = tokio::time::timeout(LEASE_POLL_INTERVAL, released).await;
+        }
+    }
+
+    /// Signer of a channel in this pool
+    pub(crate) fn signer_for(&self, account_id: &str) -> Option<Arc<dyn TransactionSigner>> {
+        self.inner
+            .slots()
+            .iter()
+            .find(|slot| slot.signer.account_id() == account_id)
+            .map(|slot| slot.signer.clone())
+    }
+
+    /// Free a channel parked for the envelope with `sequence`. Ignored if the
+    /// channel has since expired and been leased again.
+    pub(crate) fn release_parked(
+        &self,
+        account_id: &str,
+        sequence: i64,
+        next_sequence: Option<i64>,
+    ) {
+        let mut slots = self.inner.slots();
+        let Some(slot) = slots
+            .iter_mut()
+            .find(|slot| slot.signer.account_id() == account_id)
+        else {
+            return;
+        };
+        if matches!(slot.state, SlotState::Parked { sequence: s, .. } if s == sequence) {
+            slot.state = SlotState::Idle;
+            slot.next_sequence = next_sequence;
+            drop(slots);
+            self.inner.released.notify_one();
+        }
+    }
+
+    /// Create missing channels and top up low ones from `funder`, marking
+    /// each channel healthy only once it is usable
+    pub async fn check(&self, funder: &SubmissionQueue) -> Vec<ChannelStatus> {
+        let starting = decimal_to_stroops(&self.inner.config.starting_balance).unwrap_or(0);
+        let min = decimal_to_stroops(&self.inner.config.min_balance).unwrap_or(0);
+
+        let mut statuses = Vec::new();
+        for account_id in self.accounts() {
+            let mut status = ChannelStatus {
+                account_id: account_id.clone(),
+                healthy: false,
+                xlm_balance: None,
+                funding_tx_hash: None,
+            };
+
+            let funding = match self.inner.stellar_client.get_account(&account_id).await {
+                Ok(account) => {
+                    let balance = account
+                        .balances
+                        .iter()
+                        .find(|b| b.asset_type == "native")
+                        .and_then(|b| decimal_to_stroops(&b.balance).ok())
+                        .unwrap_or(0);
+                    status.xlm_balance = Some(decimal_from_stroops(balance));
+                    (balance < min).then(|| QueuedPayment {
+                        destination: account_id.clone(),
+                        amount: decimal_from_stroops(starting - balance),
+                        asset: PaymentAsset::Native,
+                        memo: CngnMemo::None,
+                    })
+                }
+                Err(StellarError::AccountNotFound { .. }) => Some(QueuedPayment {
+                    destination: account_id.clone(),
+                    amount: self.inner.config.starting_balance.clone(),
+                    asset: PaymentAsset::CreateAccount,
+                    memo: CngnMemo::None,
+                }),
+                Err(e) => {
+                    warn!(channel = %account_id, error = %e, "channel account lookup failed");
+                    self.set_healthy(&account_id, false);
+                    statuses.push(status);
+                    continue;
+                }
+            };
+
+            status.healthy = match funding {
+                None => true,
+                Some(payment) => match funder.pay(payment).await {
+                    Ok(submitted) => {
+                        info!(channel = %account_id, tx_hash = %submitted.tx_hash, "channel account funded");
+                        status.funding_tx_hash = Some(submitted.tx_hash);
+                        status.xlm_balance = Some(decimal_from_stroops(starting));
+                        true
+                    }
+                    Err(e) => {
+                        warn!(channel = %account_id, error = %e, "failed to fund channel account");
+                        false
+                    }
+                },
+            };
+            self.set_healthy(&account_id, status.healthy);
+            statuses.push(status);
+        }
+        statuses
+    }
+
+    fn set_healthy(&self, account_id: &str, healthy: bool) {
+        let mut slots = self.inner.slots();
+        if let Some(slot) = slots
+            .iter_mut()
+            .find(|slot| slot.signer.account_id() == account_id)
+        {
+            slot.healthy = healthy;
+        }
+        drop(slots);
+        if healthy {
+            self.inner.released.notify_one();
+        }
+    }
+}
+
+/// Exclusive use of one channel account. Dropping the lease returns the
+/// channel to the pool with the sequence recorded on it.
+pub struct ChannelLease {
+    pool: Arc<PoolInner>,
+    index: usize,
+    signer: Arc<dyn TransactionSigner>,
+    next_sequence: Option<i64>,
+    parked: bool,
+}
+
+impl fmt::Debug for ChannelLease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelLease")
+            .field("account_id", &self.account_id())
+            .field("next_sequence", &self.next_sequence)
+            .finish()
+    }
+}
+
+impl ChannelLease {
+    pub fn account_id(&self) -> &str {
+        self.signer.account_id()
+    }
+
+    pub fn signer(&self) -> Arc<dyn TransactionSigner> {
+        self.signer.clone()
+    }
+
+    /// Next unused sequence, or `None` if it has to be read from Horizon
+    pub fn next_sequence(&self) -> Option<i64> {
+        self.next_sequence
+    }
+
+    pub fn set_next_sequence(&mut self, next_sequence: Option<i64>) {
+        self.next_sequence = next_sequence;
+    }
+
+    /// Keep the channel reserved for an envelope built with `sequence` that
+    /// will be submitted later. It is freed when the envelope is submitted,
+    /// or once `max_time` has passed and the envelope can no longer apply.
+    pub fn park(mut self, sequence: i64, max_time: u64) {
+        self.parked = true;
+        let mut slots = self.pool.slots();
+        if let Some(slot) = slots.get_mut(self.index) {
+            slot.state = SlotState::Parked {
+                sequence,
+                until: max_time + PARKED_GRACE_SECONDS,
+            };
+            slot.next_sequence = None;
+        }
+    }
+}
+
+impl Drop for ChannelLease {
+    fn drop(&mut self) {
+        if self.parked {
+            return;
+        }
+        let mut slots = self.pool.slots();
+        if let Some(slot) = slots.get_mut(self.index) {
+            slot.state = SlotState::Idle;
+            slot.next_sequence = self.next_sequence;
+        }
+        drop(slots);
+        self.pool.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::stellar::config::StellarConfig;
+    use crate::chains::stellar::signer::LocalSigner;
+    use ed25519_dalek::SigningKey;
+
+    fn signers(count: usize) -> Vec<Arc<dyn TransactionSigner>> {
+        (1..=count as u8)
+            .map(|seed| {
+                Arc::new(LocalSigner::from_signing_key(SigningKey::from_bytes(
+                    &[seed; 32],
+                ))) as Arc<dyn TransactionSigner>
+            })
+            .collect()
+    }
+
+    fn pool(count: usize) -> ChannelAccountPool {
+        let config = ChannelConfig {
+            signers: signers(count),
+            ..ChannelConfig::default()
+        };
+        ChannelAccountPool::new(
+            StellarClient::new(StellarConfig::default()).unwrap(),
+            config,
+        )
+        .unwrap()
+    }
+
+    fn mark_all_healthy(pool: &ChannelAccountPool) {
+        for account in pool.accounts() {
+            pool.set_healthy(&account, true);
+        }
+    }
+
+    #[test]
+    fn test_channels_follow_the_configured_signers() {
+        let accounts = pool(3).accounts();
+        let expected: Vec<String> = signers(3)
+            .iter()
+            .map(|signer| signer.account_id().to_string())
+            .collect();
+        assert_eq!(accounts, expected);
+        assert!(accounts.iter().all(|a| a.starts_with('G')));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(!ChannelConfig::default().is_enabled());
+        let mut config = ChannelConfig {
+            signers: signers(2),
+            ..ChannelConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        config.min_balance = "5".to_string();
+        assert!(config.validate().is_err());
+        config.min_balance = "2".to_string();
+        config.signers.push(config.signers[0].clone());
+        assert!(config.validate().is_err(), "duplicate channel accounts");
+        config.signers = signers(MAX_CHANNEL_ACCOUNTS + 1);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_leases_are_exclusive_and_keep_the_sequence() {
+        let pool = pool(2);
+        assert!(
+            pool.try_lease().is_none(),
+            "unchecked channels are not leased"
+        );
+        mark_all_healthy(&pool);
+
+        let mut first = pool.try_lease().unwrap();
+        let second = pool.try_lease().unwrap();
+        assert_ne!(first.account_id(), second.account_id());
+        assert!(pool.try_lease().is_none());
+
+        let account = first.account_id().to_string();
+        first.set_next_sequence(Some(42));
+        drop(first);
+        let again = pool.try_lease().unwrap();
+        assert_eq!(again.account_id(), account);
+        assert_eq!(again.next_sequence(), Some(42));
+    }
+
+    #[test]
+    fn test_parked_channel_is_freed_by_its_envelope_only() {
+        let pool = pool(1);
+        mark_all_healthy(&pool);
+
+        let lease = pool.try_lease().unwrap();
+        let account = lease.account_id().to_string();
+        lease.park(10, unix_time() + 60);
+        assert!(pool.try_lease().is_none());
+
+        pool.release_parked(&account, 9, Some(10));
+        assert!(pool.try_lease().is_none());
+
+        pool.release_parked(&account, 10, Some(11));
+        assert_eq!(pool.try_lease().unwrap().next_sequence(), Some(11));
+    }
+
+    #[test]
+    fn test_expired_parked_channel_resyncs() {
+        let pool = pool(1);
+        mark_all_healthy(&pool);
+
+        let lease = pool.try_lease().unwrap();
+        lease.park(10, 0);
+        let lease = pool.try_lease().unwrap();
+        assert_eq!(lease.next_sequence(), None);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::chains::stellar::{
     config::StellarConfig,
     errors::{StellarError, StellarResult},
+    streaming::HorizonPaymentRecord,
     types::{
-        extract_afri_balance, extract_asset_balance, extract_cngn_balance,
-        is_valid_stellar_address, HealthStatus, HorizonAccount, StellarAccountInfo,
+        extract_afri_balance, extract_asset_balance, extract_cngn_balance, is_valid_account_id,
+        is_valid_stellar_address, HealthStatus, HorizonAccount, StellarAccountInfo, StellarAddress,
     },
 };
 use reqwest::Client;
@@ -42,6 +43,11 @@ pub struct HorizonTransactionsPage {
     pub records: Vec<HorizonTransactionRecord>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HorizonPaymentsPage {
+    pub records: Vec<HorizonPaymentRecord>,
+}
+
 #[allow(dead_code)]
 impl StellarClient {
     pub fn new(config: StellarConfig) -> StellarResult<Self> {
@@ -70,14 +76,20 @@ impl StellarClient {
         })
     }
 
+    /// Account details. A muxed `M...` address resolves to its underlying
+    /// account, which is what holds the balances and trustlines.
     pub async fn get_account(&self, address: &str) -> StellarResult<StellarAccountInfo> {
-        if !is_valid_stellar_address(address) {
+        let Some(parsed) = StellarAddress::parse(address) else {
             return Err(StellarError::invalid_address(address));
-        }
+        };
 
         debug!("Fetching account details for address: {}", address);
 
-        let url = format!("{}/accounts/{}", self.config.horizon_url(), address);
+        let url = format!(
+            "{}/accounts/{}",
+            self.config.horizon_url(),
+            parsed.account_id()
+        );
 
         let response = timeout(
             self.config.request_timeout,
@@ -380,7 +392,7 @@ impl StellarClient {
         limit: usize,
         cursor: Option<&str>,
     ) -> StellarResult<HorizonTransactionsPage> {
-        if !is_valid_stellar_address(account) {
+        if !is_valid_account_id(account) {
             return Err(StellarError::invalid_address(account));
         }
 
@@ -435,6 +447,99 @@ impl StellarClient {
         Ok(HorizonTransactionsPage { records })
     }
 
+    /// Payment operations for `account` in ledger order, with parent
+    /// transactions joined in. Same records and cursors as the payments stream.
+    pub async fn list_account_payments(
+        &self,
+        account: &str,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> StellarResult<HorizonPaymentsPage> {
+        self.fetch_account_payments(account, "asc", limit, cursor)
+            .await
+    }
+
+    /// The most recent payment operation for `account`, if it has any. Its
+    /// paging token marks the head of the account's payment history.
+    pub async fn latest_account_payment(
+        &self,
+        account: &str,
+    ) -> StellarResult<Option<HorizonPaymentRecord>> {
+        let page = self
+            .fetch_account_payments(account, "desc", 1, None)
+            .await?;
+        Ok(page.records.into_iter().next())
+    }
+
+    async fn fetch_account_payments(
+        &self,
+        account: &str,
+        order: &str,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> StellarResult<HorizonPaymentsPage> {
+        if !is_valid_account_id(account) {
+            return Err(StellarError::invalid_address(account));
+        }
+
+        let mut url = format!(
+            "{}/accounts/{}/payments?order={}&limit={}&join=transactions",
+            self.config.horizon_url(),
+            account,
+            order,
+            limit.min(200)
+        );
+        if let Some(c) = cursor {
+            url.push_str("&cursor=");
+            url.push_str(&encode_form_component(c));
+        }
+
+        let response = timeout(
+            self.config.request_timeout,
+            self.http_client.get(&url).send(),
+        )
+        .await
+        .map_err(|_| StellarError::timeout_error(self.config.request_timeout.as_secs()))?
+        .map_err(|e| {
+            if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                StellarError::RateLimitError
+            } else {
+                StellarError::network_error(format!(
+                    "Horizon account payments listing error: {}",
+                    e
+                ))
+            }
+        })?
+        .error_for_status()
+        .map_err(|e| {
+            if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                StellarError::RateLimitError
+            } else {
+                StellarError::network_error(format!(
+                    "Horizon account payments listing error: {}",
+                    e
+                ))
+            }
+        })?;
+
+        let body = response
+            .json::<JsonValue>()
+            .await
+            .map_err(|e| StellarError::serialization_error(format!("JSON parsing error: {}", e)))?;
+
+        let records = body
+            .get("_embedded")
+            .and_then(|v| v.get("records"))
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|record| serde_json::from_value::<HorizonPaymentRecord>(record).ok())
+            .collect::<Vec<_>>();
+
+        Ok(HorizonPaymentsPage { records })
+    }
+
     pub async fn get_transaction_operations(&self, tx_hash: &str) -> StellarResult<Vec<JsonValue>> {
         let response = timeout(
             self.config.request_timeout,
@@ -478,7 +583,7 @@ impl StellarClient {
     }
 }
 
-fn encode_form_component(input: &str) -> String {
+pub(crate) fn encode_form_component(input: &str) -> String {
     let mut output = String::with_capacity(input.len());
     for &b in input.as_bytes() {
         if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {
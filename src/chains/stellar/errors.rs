@@ -3,7 +3,7 @@ use thiserror::Error;
 pub type StellarResult<T> = Result<T, StellarError>;
 
 #[allow(dead_code)]
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum StellarError {
     #[error("Account not found: {address}")]
     AccountNotFound { address: String },
@@ -43,6 +43,9 @@ pub enum StellarError {
 
     #[error("Signing error: {message}")]
     SigningError { message: String },
+
+    #[error("Daily fee sponsorship budget exhausted. Retry in {retry_after} seconds")]
+    FeeBudgetExhausted { retry_after: u64 },
 }
 
 #[allow(dead_code)]
@@ -118,8 +121,73 @@ impl StellarError {
             message: message.into(),
         }
     }
+
+    pub fn fee_budget_exhausted(retry_after: u64) -> Self {
+        Self::FeeBudgetExhausted { retry_after }
+    }
+
+    /// Whether a submission error proves the envelope was not applied. Horizon
+    /// answers 504 when it has forwarded the transaction but not yet seen it
+    /// ingested, so that case (like timeouts and network errors) is ambiguous.
+    pub fn is_definitive_rejection(&self) -> bool {
+        match self {
+            StellarError::RateLimitError => true,
+            StellarError::TransactionFailed { message } => !message.contains("status 504"),
+            _ => false,
+        }
+    }
+
+    /// Whether a submission error proves the envelope never reached a ledger,
+    /// so its fee was not charged. Stricter than `is_definitive_rejection`: a
+    /// transaction that failed while being applied (`tx_failed`) changed
+    /// nothing but still paid its fee.
+    pub fn is_rejected_before_apply(&self) -> bool {
+        let message = match self {
+            StellarError::RateLimitError => return true,
+            StellarError::TransactionFailed { message } => message,
+            _ => return false,
+        };
+        if !message.contains("status 400") {
+            return false;
+        }
+        let Some(body) = message
+            .find('{')
+            .and_then(|start| serde_json::from_str::<serde_json::Value>(&message[start..]).ok())
+        else {
+            return false;
+        };
+
+        // Horizon rejects undecodable envelopes without result codes
+        if body["type"]
+            .as_str()
+            .is_some_and(|t| t.ends_with("transaction_malformed"))
+        {
+            return true;
+        }
+        let codes = &body["extras"]["result_codes"];
+        let code = match codes["transaction"].as_str() {
+            Some("tx_fee_bump_inner_failed") => codes["inner_transaction"].as_str(),
+            code => code,
+        };
+        code.is_some_and(|code| PRE_APPLY_RESULT_CODES.contains(&code))
+    }
 }
 
+/// Result codes core returns when it refuses an envelope at validation, before
+/// any fee is taken
+const PRE_APPLY_RESULT_CODES: [&str; 10] = [
+    "tx_bad_seq",
+    "tx_insufficient_fee",
+    "tx_malformed",
+    "tx_missing_operation",
+    "tx_too_early",
+    "tx_too_late",
+    "tx_bad_auth",
+    "tx_bad_auth_extra",
+    "tx_insufficient_balance",
+    "tx_no_account",
+];
+
 impl From<Box<dyn std::error::Error + Send + Sync>> for StellarError {
     fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
         let err_str = err.to_string();
@@ -0,0 +1,509 @@
+//! CAP-15 fee-bump wrapping. The user signs their transaction as usual and a
+//! platform fee account wraps it, paying the network fee so the user's
+//! account needs no XLM for fees.
+
+use crate::chains::stellar::config::StellarNetwork;
+use crate::chains::stellar::errors::{StellarError, StellarResult};
+use crate::chains::stellar::payment::{build_asset, network_id};
+use crate::chains::stellar::signer::{signer_from_env, TransactionSigner};
+use crate::chains::stellar::trustline::CngnAssetConfig;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use stellar_strkey::ed25519::{MuxedAccount as StrkeyMuxedAccount, PublicKey as StrkeyPublicKey};
+use stellar_xdr::next::{
+    AccountId, Asset, ChangeTrustAsset, FeeBumpTransaction, FeeBumpTransactionEnvelope,
+    FeeBumpTransactionExt, FeeBumpTransactionInnerTx, Limits, MuxedAccount, OperationBody,
+    PublicKey, ReadXdr, Transaction, TransactionEnvelope, Uint256, VecM, WriteXdr,
+};
+
+const DEFAULT_BASE_FEE_STROOPS: i64 = 100;
+/// 0.001 XLM: ten times the base fee for a single-operation transaction
+const DEFAULT_MAX_FEE_STROOPS: i64 = 10_000;
+/// 10 XLM a day
+const DEFAULT_DAILY_BUDGET_STROOPS: i64 = 100_000_000;
+
+#[derive(Debug, Clone)]
+pub struct FeeBumpConfig {
+    /// Signer for the platform account that pays sponsored fees
+    pub fee_account: Option<Arc<dyn TransactionSigner>>,
+    /// Fee per operation offered on the outer transaction
+    pub base_fee_stroops: i64,
+    /// Refuse to sponsor any single transaction above this fee
+    pub max_fee_stroops: i64,
+    /// Total fees the platform will sponsor per UTC day
+    pub daily_budget_stroops: i64,
+}
+
+impl FeeBumpConfig {
+    /// The fee account signer is selected by `STELLAR_FEE_ACCOUNT_SIGNER`;
+    /// see [`signer_from_env`]
+    pub fn from_env() -> StellarResult<Self> {
+        let stroops = |key: &str, default: i64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        Ok(Self {
+            fee_account: signer_from_env("STELLAR_FEE_ACCOUNT")?,
+            base_fee_stroops: stroops(
+                "STELLAR_FEE_BUMP_BASE_FEE_STROOPS",
+                DEFAULT_BASE_FEE_STROOPS,
+            ),
+            max_fee_stroops: stroops("STELLAR_FEE_BUMP_MAX_FEE_STROOPS", DEFAULT_MAX_FEE_STROOPS),
+            daily_budget_stroops: stroops(
+                "STELLAR_FEE_BUMP_DAILY_BUDGET_STROOPS",
+                DEFAULT_DAILY_BUDGET_STROOPS,
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeBumpedTransaction {
+    pub fee_source: String,
+    /// Maximum fee the fee account can be charged for this envelope
+    pub fee_stroops: i64,
+    pub inner_source: String,
+    pub inner_transaction_hash: String,
+    pub transaction_hash: String,
+    pub envelope_xdr: String,
+}
+
+/// Wraps user-signed transactions in fee-bump envelopes paid by the platform
+/// fee account. Only the cNGN trustline and payment transactions the platform
+/// builds are sponsored.
+#[derive(Debug, Clone)]
+pub struct FeeBumpSponsor {
+    signer: Arc<dyn TransactionSigner>,
+    sponsored_asset: Asset,
+    base_fee_stroops: i64,
+    max_fee_stroops: i64,
+    network_passphrase: &'static str,
+}
+
+impl FeeBumpSponsor {
+    pub fn new(
+        config: &FeeBumpConfig,
+        asset: &CngnAssetConfig,
+        network: &StellarNetwork,
+    ) -> StellarResult<Self> {
+        let signer = config.fee_account.clone().ok_or_else(|| {
+            StellarError::config_error(
+                "no fee account signer (STELLAR_FEE_ACCOUNT_SIGNER); fee sponsorship is disabled",
+            )
+        })?;
+        let sponsored_asset = build_asset(&asset.asset_code, asset.issuer_for_network(network))?;
+        Ok(Self::with_signer(signer, sponsored_asset, config, network))
+    }
+
+    /// Pay fees from the account `signer` authorises for transactions
+    /// involving `sponsored_asset`
+    pub fn with_signer(
+        signer: Arc<dyn TransactionSigner>,
+        sponsored_asset: Asset,
+        config: &FeeBumpConfig,
+        network: &StellarNetwork,
+    ) -> Self {
+        Self {
+            signer,
+            sponsored_asset,
+            base_fee_stroops: config.base_fee_stroops,
+            max_fee_stroops: config.max_fee_stroops,
+            network_passphrase: network.network_passphrase(),
+        }
+    }
+
+    pub fn fee_source(&self) -> &str {
+        self.signer.account_id()
+    }
+
+    /// Wrap a signed v1 transaction envelope and sign it as the fee source
+    pub async fn wrap(&self, signed_inner_xdr: &str) -> StellarResult<FeeBumpedTransaction> {
+        let envelope = TransactionEnvelope::from_xdr_base64(signed_inner_xdr, Limits::none())
+            .map_err(|e| StellarError::signing_error(format!("invalid envelope xdr: {}", e)))?;
+        let inner = match envelope {
+            TransactionEnvelope::Tx(v1) => v1,
+            TransactionEnvelope::TxV0(_) => {
+                return Err(StellarError::transaction_failed(
+                    "v0 envelopes cannot be fee-bumped; rebuild the transaction",
+                ))
+            }
+            TransactionEnvelope::TxFeeBump(_) => {
+                return Err(StellarError::transaction_failed(
+                    "transaction is already fee-bumped",
+                ))
+            }
+        };
+        if inner.signatures.is_empty() {
+            return Err(StellarError::signing_error(
+                "inner transaction must be signed before it is fee-bumped",
+            ));
+        }
+        self.ensure_sponsorable(&inner.tx)?;
+
+        let fee = sponsored_fee(
+            self.base_fee_stroops,
+            inner.tx.fee,
+            inner.tx.operations.len(),
+        );
+        if fee > self.max_fee_stroops {
+            return Err(StellarError::transaction_failed(format!(
+                "sponsored fee of {} stroops exceeds the per-transaction maximum of {}",
+                fee, self.max_fee_stroops
+            )));
+        }
+
+        let network_id = network_id(self.network_passphrase);
+        let inner_hash = inner
+            .hash(network_id)
+            .map_err(|e| StellarError::serialization_error(e.to_string()))?;
+        let inner_source = muxed_account_address(&inner.tx.source_account);
+
+        let fee_source = StrkeyPublicKey::from_string(self.fee_source())
+            .map_err(|_| StellarError::invalid_address(self.fee_source()))?;
+        let tx = FeeBumpTransaction {
+            fee_source: MuxedAccount::Ed25519(Uint256(fee_source.0)),
+            fee,
+            inner_tx: FeeBumpTransactionInnerTx::Tx(inner),
+            ext: FeeBumpTransactionExt::V0,
+        };
+        let hash = tx
+            .hash(network_id)
+            .map_err(|e| StellarError::serialization_error(e.to_string()))?;
+
+        let signatures = self.signer.sign_hash(&hash).await?;
+        let envelope = TransactionEnvelope::TxFeeBump(FeeBumpTransactionEnvelope {
+            tx,
+            signatures: VecM::try_from(signatures)
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+        });
+        let envelope_xdr = envelope
+            .to_xdr_base64(Limits::none())
+            .map_err(|e| StellarError::serialization_error(e.to_string()))?;
+
+        Ok(FeeBumpedTransaction {
+            fee_source: self.fee_source().to_string(),
+            fee_stroops: fee,
+            inner_source,
+            inner_transaction_hash: hex::encode(inner_hash),
+            transaction_hash: hex::encode(hash),
+            envelope_xdr,
+        })
+    }
+
+    /// Refuse anything but the operations the platform builds: a cNGN
+    /// trustline change (optionally inside a reserve sponsorship sandwich)
+    /// or a cNGN payment, all on behalf of the transaction source
+    fn ensure_sponsorable(&self, tx: &Transaction) -> StellarResult<()> {
+        let tx_source = match &tx.source_account {
+            MuxedAccount::Ed25519(key) => key.clone(),
+            MuxedAccount::MuxedEd25519(muxed) => muxed.ed25519.clone(),
+        };
+        if tx.operations.is_empty() {
+            return Err(StellarError::transaction_failed(
+                "transaction has no operations to sponsor",
+            ));
+        }
+        for op in tx.operations.iter() {
+            let allowed = match &op.body {
+                OperationBody::ChangeTrust(change) => {
+                    op.source_account.is_none()
+                        && change_trust_matches(&change.line, &self.sponsored_asset)
+                }
+                OperationBody::Payment(payment) => {
+                    op.source_account.is_none() && payment.asset == self.sponsored_asset
+                }
+                // The reserve sponsor opens the sandwich for the user
+                OperationBody::BeginSponsoringFutureReserves(begin) => {
+                    begin.sponsored_id
+                        == AccountId(PublicKey::PublicKeyTypeEd25519(tx_source.clone()))
+                }
+                OperationBody::EndSponsoringFutureReserves => op.source_account.is_none(),
+                _ => false,
+            };
+            if !allowed {
+                return Err(StellarError::transaction_failed(format!(
+                    "operation {} is not eligible for fee sponsorship",
+                    op.body.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn change_trust_matches(line: &ChangeTrustAsset, asset: &Asset) -> bool {
+    match (line, asset) {
+        (ChangeTrustAsset::CreditAlphanum4(line), Asset::CreditAlphanum4(asset)) => line == asset,
+        (ChangeTrustAsset::CreditAlphanum12(line), Asset::CreditAlphanum12(asset)) => line == asset,
+        _ => false,
+    }
+}
+
+/// Outer fee for a fee-bump of `operations` operations. CAP-15 counts the
+/// fee-bump itself as one extra operation and requires the outer fee rate to
+/// be at least the inner one.
+fn sponsored_fee(base_fee_stroops: i64, inner_fee: u32, operations: usize) -> i64 {
+    let operations = operations.max(1) as i64;
+    let by_base_fee = base_fee_stroops * (operations + 1);
+    let by_inner_rate = (inner_fee as i64 * (operations + 1) + operations - 1) / operations;
+    by_base_fee.max(by_inner_rate)
+}
+
+fn muxed_account_address(account: &MuxedAccount) -> String {
+    match account {
+        MuxedAccount::Ed25519(Uint256(key)) => {
+            StrkeyPublicKey(*key).to_string().as_str().to_owned()
+        }
+        MuxedAccount::MuxedEd25519(muxed) => StrkeyMuxedAccount {
+            ed25519: muxed.ed25519.0,
+            id: muxed.id,
+        }
+        .to_string()
+        .as_str()
+        .to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::stellar::payment::signature_hint;
+    use crate::chains::stellar::signer::LocalSigner;
+    use ed25519_dalek::SigningKey;
+    use stellar_strkey::ed25519::PrivateKey as StrkeyPrivateKey;
+    use stellar_xdr::next::{
+        BeginSponsoringFutureReservesOp, BumpSequenceOp, ChangeTrustOp, DecoratedSignature, Memo,
+        Operation, PaymentOp, Preconditions, SequenceNumber, Signature, TransactionExt,
+        TransactionV1Envelope,
+    };
+
+    fn secret(seed: u8) -> String {
+        StrkeyPrivateKey([seed; 32]).to_string().as_str().to_owned()
+    }
+
+    fn issuer() -> String {
+        StrkeyPublicKey([9; 32]).to_string().as_str().to_owned()
+    }
+
+    fn cngn() -> Asset {
+        build_asset("cNGN", &issuer()).unwrap()
+    }
+
+    fn sponsor() -> FeeBumpSponsor {
+        let config = FeeBumpConfig {
+            fee_account: Some(Arc::new(LocalSigner::from_secret(&secret(7)).unwrap())),
+            base_fee_stroops: 100,
+            max_fee_stroops: 1_000,
+            daily_budget_stroops: 1_000_000,
+        };
+        let asset = CngnAssetConfig {
+            asset_code: "cNGN".to_string(),
+            issuer_testnet: issuer(),
+            issuer_mainnet: issuer(),
+            default_limit: None,
+        };
+        FeeBumpSponsor::new(&config, &asset, &StellarNetwork::Testnet).unwrap()
+    }
+
+    fn payment(asset: Asset) -> Operation {
+        Operation {
+            source_account: None,
+            body: OperationBody::Payment(PaymentOp {
+                destination: MuxedAccount::Ed25519(Uint256([2; 32])),
+                asset,
+                amount: 10_000_000,
+            }),
+        }
+    }
+
+    fn signed_inner(fee: u32, operations: usize) -> String {
+        signed_with(fee, vec![payment(cngn()); operations])
+    }
+
+    fn signed_with(fee: u32, operations: Vec<Operation>) -> String {
+        let tx = Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256([1; 32])),
+            fee,
+            seq_num: SequenceNumber(1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: VecM::try_from(operations).unwrap(),
+            ext: TransactionExt::V0,
+        };
+        let signature = DecoratedSignature {
+            hint: signature_hint(&SigningKey::from_bytes(&[1; 32])).unwrap(),
+            signature: Signature::try_from(vec![0u8; 64]).unwrap(),
+        };
+        TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::try_from(vec![signature]).unwrap(),
+        })
+        .to_xdr_base64(Limits::none())
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sponsored_fee_covers_extra_operation_and_inner_rate() {
+        assert_eq!(sponsored_fee(100, 100, 1), 200);
+        assert_eq!(sponsored_fee(100, 0, 3), 400);
+        assert_eq!(sponsored_fee(100, 1_000, 1), 2_000);
+        assert_eq!(sponsored_fee(100, 301, 3), 402);
+    }
+
+    #[tokio::test]
+    async fn test_wrap_signs_fee_bump_envelope() {
+        let sponsor = sponsor();
+        let bumped = sponsor.wrap(&signed_inner(100, 1)).await.unwrap();
+
+        assert_eq!(bumped.fee_source, sponsor.fee_source());
+        assert_eq!(bumped.fee_stroops, 200);
+        assert_eq!(
+            bumped.inner_source,
+            muxed_account_address(&MuxedAccount::Ed25519(Uint256([1; 32])))
+        );
+        assert_ne!(bumped.transaction_hash, bumped.inner_transaction_hash);
+
+        let envelope =
+            TransactionEnvelope::from_xdr_base64(&bumped.envelope_xdr, Limits::none()).unwrap();
+        let TransactionEnvelope::TxFeeBump(fee_bump) = envelope else {
+            panic!("expected a fee-bump envelope");
+        };
+        assert_eq!(fee_bump.tx.fee, 200);
+        assert_eq!(fee_bump.signatures.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wrap_rejects_unsigned_and_expensive_transactions() {
+        let sponsor = sponsor();
+
+        let unsigned = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx: Transaction {
+                source_account: MuxedAccount::Ed25519(Uint256([1; 32])),
+                fee: 100,
+                seq_num: SequenceNumber(1),
+                cond: Preconditions::None,
+                memo: Memo::None,
+                operations: VecM::default(),
+                ext: TransactionExt::V0,
+            },
+            signatures: VecM::default(),
+        })
+        .to_xdr_base64(Limits::none())
+        .unwrap();
+        assert!(matches!(
+            sponsor.wrap(&unsigned).await,
+            Err(StellarError::SigningError { .. })
+        ));
+
+        assert!(matches!(
+            sponsor.wrap(&signed_inner(100, 10)).await,
+            Err(StellarError::TransactionFailed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wrap_only_sponsors_platform_cngn_operations() {
+        let sponsor = sponsor();
+        let cngn_line = match cngn() {
+            Asset::CreditAlphanum4(asset) => ChangeTrustAsset::CreditAlphanum4(asset),
+            _ => unreachable!(),
+        };
+        let trustline = vec![
+            Operation {
+                source_account: Some(MuxedAccount::Ed25519(Uint256([3; 32]))),
+                body: OperationBody::BeginSponsoringFutureReserves(
+                    BeginSponsoringFutureReservesOp {
+                        sponsored_id: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32]))),
+                    },
+                ),
+            },
+            Operation {
+                source_account: None,
+                body: OperationBody::ChangeTrust(ChangeTrustOp {
+                    line: cngn_line,
+                    limit: i64::MAX,
+                }),
+            },
+            Operation {
+                source_account: None,
+                body: OperationBody::EndSponsoringFutureReserves,
+            },
+        ];
+        assert!(sponsor.wrap(&signed_with(100, trustline)).await.is_ok());
+
+        let mut other_source = payment(cngn());
+        other_source.source_account = Some(MuxedAccount::Ed25519(Uint256([4; 32])));
+        let rejected = [
+            vec![payment(Asset::Native)],
+            vec![other_source],
+            vec![Operation {
+                source_account: None,
+                body: OperationBody::BumpSequence(BumpSequenceOp {
+                    bump_to: SequenceNumber(100),
+                }),
+            }],
+        ];
+        for operations in rejected {
+            assert!(matches!(
+                sponsor.wrap(&signed_with(100, operations)).await,
+                Err(StellarError::TransactionFailed { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_only_pre_apply_rejections_leave_fee_uncharged() {
+        let horizon = |body: &str| {
+            StellarError::transaction_failed(format!(
+                "Horizon submit failed (status 400 Bad Request): {}",
+                body
+            ))
+        };
+        assert!(
+            horizon(r#"{"extras":{"result_codes":{"transaction":"tx_bad_seq"}}}"#)
+                .is_rejected_before_apply()
+        );
+        assert!(horizon(
+            r#"{"extras":{"result_codes":{"transaction":"tx_fee_bump_inner_failed","inner_transaction":"tx_insufficient_fee"}}}"#
+        )
+        .is_rejected_before_apply());
+        assert!(
+            horizon(r#"{"type":"https://stellar.org/horizon-errors/transaction_malformed"}"#)
+                .is_rejected_before_apply()
+        );
+        assert!(StellarError::RateLimitError.is_rejected_before_apply());
+
+        let applied = horizon(
+            r#"{"extras":{"result_codes":{"transaction":"tx_failed","operations":["op_underfunded"]}}}"#,
+        );
+        assert!(applied.is_definitive_rejection());
+        assert!(!applied.is_rejected_before_apply());
+        assert!(!horizon(
+            r#"{"extras":{"result_codes":{"transaction":"tx_fee_bump_inner_failed","inner_transaction":"tx_failed"}}}"#
+        )
+        .is_rejected_before_apply());
+        assert!(!horizon("not json").is_rejected_before_apply());
+        assert!(!StellarError::timeout_error(30).is_rejected_before_apply());
+    }
+
+    #[test]
+    fn test_sponsor_requires_fee_account() {
+        let config = FeeBumpConfig {
+            fee_account: None,
+            base_fee_stroops: 100,
+            max_fee_stroops: 1_000,
+            daily_budget_stroops: 1_000_000,
+        };
+        assert!(matches!(
+            FeeBumpSponsor::new(
+                &config,
+                &CngnAssetConfig::from_env(),
+                &StellarNetwork::Testnet
+            ),
+            Err(StellarError::ConfigError { .. })
+        ));
+    }
+}
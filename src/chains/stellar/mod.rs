@@ -1,8 +1,13 @@
+pub mod channel;
 pub mod client;
 pub mod config;
 pub mod errors;
+pub mod fee_bump;
 pub mod payment;
 pub mod service;
+pub mod signer;
+pub mod streaming;
+pub mod submission;
 pub mod trustline;
 pub mod types;
 
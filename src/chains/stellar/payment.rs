@@ -1,8 +1,10 @@
+use crate::chains::stellar::channel::ChannelAccountPool;
 use crate::chains::stellar::client::StellarClient;
 use crate::chains::stellar::errors::{StellarError, StellarResult};
+use crate::chains::stellar::signer::TransactionSigner;
 use crate::chains::stellar::trustline::CngnAssetConfig;
 use crate::chains::stellar::types::{extract_asset_balance, is_valid_stellar_address};
-use ed25519_dalek::{Signer, SigningKey};
+use ed25519_dalek::SigningKey;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -11,17 +13,16 @@ use stellar_strkey::ed25519::{
     PublicKey as StrkeyPublicKey,
 };
 use stellar_xdr::next::{
-    AccountId, AlphaNum12, AlphaNum4, Asset, AssetCode12, AssetCode4, DecoratedSignature, Hash,
-    Limits, Memo, MuxedAccount, MuxedAccountMed25519, Operation, OperationBody, PaymentOp,
-    Preconditions, PublicKey, ReadXdr, SequenceNumber, Signature, SignatureHint, StringM,
-    TimeBounds, TimePoint, Transaction, TransactionEnvelope, TransactionExt, TransactionV1Envelope,
-    Uint256, VecM, WriteXdr,
+    AccountId, AlphaNum12, AlphaNum4, Asset, AssetCode12, AssetCode4, Hash, Limits, Memo,
+    MuxedAccount, MuxedAccountMed25519, Operation, OperationBody, PaymentOp, Preconditions,
+    PublicKey, ReadXdr, SequenceNumber, SignatureHint, StringM, TimeBounds, TimePoint, Transaction,
+    TransactionEnvelope, TransactionExt, TransactionV1Envelope, Uint256, VecM, WriteXdr,
 };
 
 const DEFAULT_BASE_FEE_STROOPS: u32 = 100;
 const DEFAULT_TIMEOUT_SECONDS: u64 = 300;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum CngnMemo {
     None,
@@ -44,6 +45,13 @@ pub struct CngnPaymentDraft {
     pub transaction_hash: String,
     pub unsigned_envelope_xdr: String,
     pub memo: CngnMemo,
+    /// Submit with `sponsor_fee` so the platform fee account pays via fee-bump
+    #[serde(default)]
+    pub fee_sponsored: bool,
+    /// Channel account that is the transaction source and pays the fee;
+    /// `source` is then only the source of the payment operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_account: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +67,8 @@ pub struct CngnPaymentBuilder {
     config: CngnAssetConfig,
     base_fee_stroops: u32,
     timeout: Duration,
+    fee_sponsored: bool,
+    channels: Option<ChannelAccountPool>,
 }
 
 impl CngnPaymentBuilder {
@@ -68,6 +78,8 @@ impl CngnPaymentBuilder {
             config: CngnAssetConfig::from_env(),
             base_fee_stroops: DEFAULT_BASE_FEE_STROOPS,
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECONDS),
+            fee_sponsored: false,
+            channels: None,
         }
     }
 
@@ -81,6 +93,24 @@ impl CngnPaymentBuilder {
         self
     }
 
+    /// Build for fee-bump submission: the source needs no XLM for the fee
+    pub fn with_fee_sponsorship(mut self, sponsored: bool) -> Self {
+        self.fee_sponsored = sponsored;
+        self
+    }
+
+    /// Lease a channel account as the transaction source of each build, so
+    /// payments from one source don't wait on each other's sequence numbers.
+    /// The channel is held until the payment is submitted through this
+    /// builder or its timebounds pass.
+    pub fn with_channel_pool(mut self, channels: ChannelAccountPool) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Build an unsigned cNGN payment. Either side may be a SEP-23 muxed
+    /// `M...` address; balances and trustlines are checked on the underlying
+    /// account and the muxed ID is carried in the operation.
     pub async fn build_payment(
         &self,
         source: &str,
@@ -112,30 +142,130 @@ impl CngnPaymentBuilder {
         )?;
 
         let fee = fee_stroops.unwrap_or(self.base_fee_stroops);
-        ensure_source_has_xlm_for_fee(&source_account.balances, fee)?;
+        if !self.fee_sponsored && self.channels.is_none() {
+            ensure_source_has_xlm(&source_account.balances, fee as i64)?;
+        }
 
-        let sequence = source_account.sequence + 1;
-        let (tx, envelope) = build_unsigned_transaction(
+        let tx = build_unsigned_transaction(
             source,
             destination,
             amount_stroops,
-            sequence,
+            source_account.sequence + 1,
             fee,
             self.timeout,
             &memo,
-            &asset_code,
-            &issuer,
+            build_asset(&asset_code, &issuer)?,
         )?;
 
+        self.finish_draft(source, destination, amount, asset_code, issuer, memo, tx)
+            .await
+    }
+
+    /// Build an unsigned native XLM payment, e.g. a treasury sweep. The
+    /// source must hold the amount plus the fee; the destination must exist.
+    pub async fn build_xlm_payment(
+        &self,
+        source: &str,
+        destination: &str,
+        amount: &str,
+        memo: CngnMemo,
+        fee_stroops: Option<u32>,
+    ) -> StellarResult<CngnPaymentDraft> {
+        validate_address(source)?;
+        validate_address(destination)?;
+
+        let source_account = self.stellar_client.get_account(source).await?;
+        self.stellar_client.get_account(destination).await?;
+
+        let amount_stroops = decimal_to_stroops(amount)?;
+        let fee = fee_stroops.unwrap_or(self.base_fee_stroops);
+        let fee_paid = if self.fee_sponsored || self.channels.is_some() {
+            0
+        } else {
+            fee as i64
+        };
+        ensure_source_has_xlm(&source_account.balances, amount_stroops + fee_paid)?;
+
+        let tx = build_unsigned_transaction(
+            source,
+            destination,
+            amount_stroops,
+            source_account.sequence + 1,
+            fee,
+            self.timeout,
+            &memo,
+            Asset::Native,
+        )?;
+
+        self.finish_draft(
+            source,
+            destination,
+            amount,
+            "XLM".to_string(),
+            String::new(),
+            memo,
+            tx,
+        )
+        .await
+    }
+
+    /// Move the transaction onto a leased channel account if the builder has
+    /// a pool, then hash and encode it
+    #[allow(clippy::too_many_arguments)]
+    async fn finish_draft(
+        &self,
+        source: &str,
+        destination: &str,
+        amount: &str,
+        asset_code: String,
+        issuer: String,
+        memo: CngnMemo,
+        mut tx: Transaction,
+    ) -> StellarResult<CngnPaymentDraft> {
+        let lease = match &self.channels {
+            Some(channels) => {
+                let lease = channels.lease().await?;
+                let sequence = match lease.next_sequence() {
+                    Some(sequence) => sequence,
+                    None => {
+                        self.stellar_client
+                            .get_account(lease.account_id())
+                            .await?
+                            .sequence
+                            + 1
+                    }
+                };
+                route_through_channel(&mut tx, lease.account_id(), sequence)?;
+                Some(lease)
+            }
+            None => None,
+        };
+
         let network_id = network_id(self.stellar_client.network().network_passphrase());
         let tx_hash = tx
             .hash(network_id)
             .map_err(|e| StellarError::serialization_error(e.to_string()))?;
 
+        let sequence = tx.seq_num.0;
+        let fee = tx.fee;
+        let max_time = match &tx.cond {
+            Preconditions::Time(bounds) => bounds.max_time.0,
+            _ => unix_time() + self.timeout.as_secs(),
+        };
+        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::default(),
+        });
         let unsigned_envelope_xdr = envelope
             .to_xdr_base64(Limits::none())
             .map_err(|e| StellarError::serialization_error(e.to_string()))?;
 
+        let channel_account = lease.map(|lease| {
+            let account = lease.account_id().to_string();
+            lease.park(sequence, max_time);
+            account
+        });
+
         Ok(CngnPaymentDraft {
             source: source.to_string(),
             destination: destination.to_string(),
@@ -149,16 +279,17 @@ impl CngnPaymentBuilder {
             transaction_hash: hex::encode(tx_hash),
             unsigned_envelope_xdr,
             memo,
+            fee_sponsored: self.fee_sponsored,
+            channel_account,
         })
     }
 
-    pub fn sign_payment(
+    pub async fn sign_payment(
         &self,
         draft: CngnPaymentDraft,
-        secret_seed: &str,
+        signer: &dyn TransactionSigner,
     ) -> StellarResult<SignedCngnPayment> {
-        let signing_key = decode_signing_key(secret_seed)?;
-        ensure_signing_key_matches_source(&signing_key, &draft.source)?;
+        ensure_signer_matches_source(signer.account_id(), &draft.source)?;
 
         let envelope =
             TransactionEnvelope::from_xdr_base64(&draft.unsigned_envelope_xdr, Limits::none())
@@ -178,18 +309,27 @@ impl CngnPaymentBuilder {
             .hash(network_id)
             .map_err(|e| StellarError::serialization_error(e.to_string()))?;
 
-        let signature_bytes = signing_key
-            .try_sign(&hash)
-            .map_err(|_| StellarError::signing_error("failed to sign transaction hash"))?
-            .to_bytes()
-            .to_vec();
-        let hint = signature_hint(&signing_key)?;
-        let signature = Signature::try_from(signature_bytes.clone())
-            .map_err(|e| StellarError::serialization_error(e.to_string()))?;
-        let decorated = DecoratedSignature { hint, signature };
+        let mut signatures = signer.sign_hash(&hash).await?;
+        let signature = signatures
+            .first()
+            .map(|s| hex::encode(s.signature.as_slice()))
+            .ok_or_else(|| StellarError::signing_error("signer returned no signatures"))?;
+        if let Some(channel) = &draft.channel_account {
+            let channel_signer = self
+                .channels
+                .as_ref()
+                .and_then(|channels| channels.signer_for(channel))
+                .ok_or_else(|| {
+                    StellarError::signing_error(format!(
+                        "channel account {} is not in this builder's pool",
+                        channel
+                    ))
+                })?;
+            signatures.extend(channel_signer.sign_hash(&hash).await?);
+        }
         let signed_env = TransactionEnvelope::Tx(TransactionV1Envelope {
             tx,
-            signatures: VecM::try_from(vec![decorated])
+            signatures: VecM::try_from(signatures)
                 .map_err(|e| StellarError::serialization_error(e.to_string()))?,
         });
         let signed_envelope_xdr = signed_env
@@ -198,7 +338,7 @@ impl CngnPaymentBuilder {
 
         Ok(SignedCngnPayment {
             draft,
-            signature: hex::encode(signature_bytes),
+            signature,
             signed_envelope_xdr,
         })
     }
@@ -208,9 +348,25 @@ impl CngnPaymentBuilder {
         signed_envelope_xdr: &str,
     ) -> StellarResult<serde_json::Value> {
         validate_signed_envelope_has_signatures(signed_envelope_xdr)?;
-        self.stellar_client
+        let result = self
+            .stellar_client
             .submit_transaction_xdr(signed_envelope_xdr)
-            .await
+            .await;
+
+        // Free a channel parked for this envelope. An unknown outcome keeps it
+        // parked until the envelope's timebounds pass.
+        if let Some(channels) = &self.channels {
+            if let Some((channel, sequence)) = envelope_source(signed_envelope_xdr) {
+                match &result {
+                    Ok(_) => channels.release_parked(&channel, sequence, Some(sequence + 1)),
+                    Err(e) if e.is_definitive_rejection() => {
+                        channels.release_parked(&channel, sequence, None)
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+        result
     }
 }
 
@@ -236,16 +392,16 @@ fn ensure_destination_has_trustline(
     }
 }
 
-fn ensure_source_has_xlm_for_fee(
+fn ensure_source_has_xlm(
     balances: &[crate::chains::stellar::types::AssetBalance],
-    fee_stroops: u32,
+    required_stroops: i64,
 ) -> StellarResult<()> {
     let available = balances
         .iter()
         .find(|b| b.asset_type == "native")
         .and_then(|b| b.balance.parse::<f64>().ok())
         .unwrap_or(0.0);
-    let required = (fee_stroops as f64) / 10_000_000.0;
+    let required = (required_stroops as f64) / 10_000_000.0;
     if available >= required {
         Ok(())
     } else {
@@ -284,12 +440,10 @@ fn build_unsigned_transaction(
     fee_stroops: u32,
     timeout: Duration,
     memo: &CngnMemo,
-    asset_code: &str,
-    issuer: &str,
-) -> StellarResult<(Transaction, TransactionEnvelope)> {
+    asset: Asset,
+) -> StellarResult<Transaction> {
     let source_account = parse_muxed_account(source)?;
     let destination_account = parse_muxed_account(destination)?;
-    let asset = build_asset(asset_code, issuer)?;
 
     let op = Operation {
         source_account: None,
@@ -314,16 +468,40 @@ fn build_unsigned_transaction(
             .map_err(|e| StellarError::serialization_error(e.to_string()))?,
         ext: TransactionExt::V0,
     };
+    Ok(tx)
+}
 
-    let env = TransactionEnvelope::Tx(TransactionV1Envelope {
-        tx: tx.clone(),
-        signatures: VecM::try_from(Vec::<DecoratedSignature>::new())
-            .map_err(|e| StellarError::serialization_error(e.to_string()))?,
-    });
-    Ok((tx, env))
+/// Make `channel` the transaction source, keeping the original source on
+/// every operation
+fn route_through_channel(tx: &mut Transaction, channel: &str, sequence: i64) -> StellarResult<()> {
+    let source = std::mem::replace(&mut tx.source_account, parse_muxed_account(channel)?);
+    let mut operations = tx.operations.to_vec();
+    for op in &mut operations {
+        op.source_account.get_or_insert_with(|| source.clone());
+    }
+    tx.operations =
+        VecM::try_from(operations).map_err(|e| StellarError::serialization_error(e.to_string()))?;
+    tx.seq_num = SequenceNumber(sequence);
+    Ok(())
 }
 
-fn parse_muxed_account(address: &str) -> StellarResult<MuxedAccount> {
+/// Source account and sequence of a v1 envelope
+fn envelope_source(xdr: &str) -> Option<(String, i64)> {
+    match TransactionEnvelope::from_xdr_base64(xdr, Limits::none()).ok()? {
+        TransactionEnvelope::Tx(v1) => {
+            let MuxedAccount::Ed25519(Uint256(key)) = v1.tx.source_account else {
+                return None;
+            };
+            Some((
+                StrkeyPublicKey(key).to_string().as_str().to_owned(),
+                v1.tx.seq_num.0,
+            ))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_muxed_account(address: &str) -> StellarResult<MuxedAccount> {
     if address.starts_with('M') {
         let muxed = StrkeyMuxedAccount::from_string(address)
             .map_err(|_| StellarError::invalid_address(address))?;
@@ -338,7 +516,7 @@ fn parse_muxed_account(address: &str) -> StellarResult<MuxedAccount> {
     }
 }
 
-fn parse_account_id(address: &str) -> StellarResult<AccountId> {
+pub(crate) fn parse_account_id(address: &str) -> StellarResult<AccountId> {
     let public_key = StrkeyPublicKey::from_string(address)
         .map_err(|_| StellarError::invalid_address(address))?;
     Ok(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
@@ -346,7 +524,7 @@ fn parse_account_id(address: &str) -> StellarResult<AccountId> {
     ))))
 }
 
-fn build_asset(asset_code: &str, issuer: &str) -> StellarResult<Asset> {
+pub(crate) fn build_asset(asset_code: &str, issuer: &str) -> StellarResult<Asset> {
     let issuer = parse_account_id(issuer)?;
     let code = asset_code.trim().to_uppercase();
     let bytes = code.as_bytes();
@@ -373,7 +551,7 @@ fn build_asset(asset_code: &str, issuer: &str) -> StellarResult<Asset> {
     }
 }
 
-fn memo_to_xdr(memo: &CngnMemo) -> StellarResult<Memo> {
+pub(crate) fn memo_to_xdr(memo: &CngnMemo) -> StellarResult<Memo> {
     match memo {
         CngnMemo::None => Ok(Memo::None),
         CngnMemo::Text(text) => {
@@ -397,7 +575,7 @@ fn memo_to_xdr(memo: &CngnMemo) -> StellarResult<Memo> {
     }
 }
 
-fn decimal_to_stroops(amount: &str) -> StellarResult<i64> {
+pub(crate) fn decimal_to_stroops(amount: &str) -> StellarResult<i64> {
     let trimmed = amount.trim();
     if trimmed.is_empty() {
         return Err(StellarError::transaction_failed("amount is required"));
@@ -443,20 +621,22 @@ fn decimal_to_stroops(amount: &str) -> StellarResult<i64> {
         .ok_or_else(|| StellarError::transaction_failed("amount overflow"))
 }
 
-fn decimal_from_stroops(stroops: i64) -> String {
+pub(crate) fn decimal_from_stroops(stroops: i64) -> String {
     let whole = stroops / 10_000_000;
     let frac = (stroops % 10_000_000).abs();
     format!("{whole}.{frac:07}")
 }
 
-fn decode_signing_key(secret_seed: &str) -> StellarResult<SigningKey> {
+pub(crate) fn decode_signing_key(secret_seed: &str) -> StellarResult<SigningKey> {
     let private = StrkeyPrivateKey::from_string(secret_seed)
         .map_err(|_| StellarError::signing_error("invalid secret seed"))?;
     Ok(SigningKey::from_bytes(&private.0))
 }
 
-fn ensure_signing_key_matches_source(signing_key: &SigningKey, source: &str) -> StellarResult<()> {
-    let public_key_bytes = signing_key.verifying_key().to_bytes();
+fn ensure_signer_matches_source(signer_account: &str, source: &str) -> StellarResult<()> {
+    let public_key_bytes = StrkeyPublicKey::from_string(signer_account)
+        .map(|p| p.0)
+        .map_err(|_| StellarError::invalid_address(signer_account))?;
     let expected = if source.starts_with('M') {
         StrkeyMuxedAccount::from_string(source)
             .map(|m| m.ed25519)
@@ -471,22 +651,22 @@ fn ensure_signing_key_matches_source(signing_key: &SigningKey, source: &str) ->
         Ok(())
     } else {
         Err(StellarError::signing_error(
-            "secret seed does not match source account",
+            "signer does not match source account",
         ))
     }
 }
 
-fn signature_hint(signing_key: &SigningKey) -> StellarResult<SignatureHint> {
+pub(crate) fn signature_hint(signing_key: &SigningKey) -> StellarResult<SignatureHint> {
     let bytes = signing_key.verifying_key().to_bytes();
     SignatureHint::try_from(&bytes[bytes.len() - 4..])
         .map_err(|e| StellarError::serialization_error(e.to_string()))
 }
 
-fn network_id(passphrase: &str) -> [u8; 32] {
+pub(crate) fn network_id(passphrase: &str) -> [u8; 32] {
     Sha256::digest(passphrase.as_bytes()).into()
 }
 
-fn unix_time() -> u64 {
+pub(crate) fn unix_time() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -527,4 +707,36 @@ mod tests {
         assert!(decimal_to_stroops("1.12345678").is_err());
         assert!(decimal_to_stroops("abc").is_err());
     }
+
+    #[test]
+    fn test_route_through_channel_keeps_source_on_operation() {
+        let source = StrkeyPublicKey([1; 32]).to_string().as_str().to_owned();
+        let channel = StrkeyPublicKey([2; 32]).to_string().as_str().to_owned();
+        let mut tx = build_unsigned_transaction(
+            &source,
+            &channel,
+            1,
+            5,
+            100,
+            Duration::from_secs(60),
+            &CngnMemo::None,
+            Asset::Native,
+        )
+        .unwrap();
+
+        route_through_channel(&mut tx, &channel, 42).unwrap();
+        assert_eq!(tx.source_account, parse_muxed_account(&channel).unwrap());
+        assert_eq!(
+            tx.operations[0].source_account,
+            Some(parse_muxed_account(&source).unwrap())
+        );
+
+        let xdr = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::default(),
+        })
+        .to_xdr_base64(Limits::none())
+        .unwrap();
+        assert_eq!(envelope_source(&xdr), Some((channel, 42)));
+    }
 }
@@ -40,7 +40,9 @@ impl From<StellarError> for BlockchainError {
                 BlockchainError::TransactionFailed { message }
             }
             StellarError::TimeoutError { seconds } => BlockchainError::Timeout { seconds },
-            StellarError::RateLimitError => BlockchainError::RateLimitExceeded,
+            StellarError::RateLimitError | StellarError::FeeBudgetExhausted { .. } => {
+                BlockchainError::RateLimitExceeded
+            }
             StellarError::InsufficientXlm {
                 required,
                 available,
@@ -337,9 +339,16 @@ mod tests {
         let client = StellarClient::new(config).unwrap();
         let service = StellarBlockchainService::new(client);
 
-        // Muxed address format
-        let muxed_address = "MAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABGCTA2";
-        // This should fail validation as it's not a valid public key format
-        assert!(service.validate_address(muxed_address).is_err());
+        // SEP-23 muxed addresses are accepted as payment destinations
+        let muxed_address = crate::chains::stellar::types::muxed_address(
+            "GCJRI5CIWK5IU67Q6DGA7QW52JDKRO7JEAHQKFNDUJUPEZGURDBX3LDX",
+            42,
+        )
+        .unwrap();
+        assert!(service.validate_address(&muxed_address).is_ok());
+
+        // Malformed (wrong length, bad checksum) muxed strings are still rejected
+        let malformed = "MAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABGCTA2";
+        assert!(service.validate_address(malformed).is_err());
     }
 }
@@ -0,0 +1,576 @@
+//! Transaction signing for platform-held keys. Code that signs Stellar
+//! transactions works against [`TransactionSigner`] and never handles key
+//! material itself.
+//!
+//! - [`LocalSigner`]: an in-memory key, from a secret seed or from an
+//!   encrypted [`Keystore`] file decrypted at boot
+//! - [`RemoteSigner`]: an HTTP signing service fronting an HSM or KMS
+//! - [`MultiSigner`]: gathers signatures from several signers until a
+//!   threshold is met
+
+use crate::chains::stellar::errors::{StellarError, StellarResult};
+use crate::chains::stellar::payment::{decode_signing_key, signature_hint};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use async_trait::async_trait;
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use stellar_strkey::ed25519::PublicKey as StrkeyPublicKey;
+use stellar_xdr::next::{DecoratedSignature, Signature, SignatureHint};
+use tracing::warn;
+
+const KEYSTORE_VERSION: u32 = 1;
+/// OWASP guidance for PBKDF2-HMAC-SHA256
+const DEFAULT_KDF_ITERATIONS: u32 = 600_000;
+const DEFAULT_REMOTE_TIMEOUT_SECONDS: u64 = 10;
+
+#[async_trait]
+pub trait TransactionSigner: fmt::Debug + Send + Sync {
+    /// Account whose transactions this signer authorises
+    fn account_id(&self) -> &str;
+
+    /// Sign a network-bound transaction hash. Multi-party signers return one
+    /// signature per participating key.
+    async fn sign_hash(&self, hash: &[u8; 32]) -> StellarResult<Vec<DecoratedSignature>>;
+}
+
+/// Signing key held in memory
+#[derive(Clone)]
+pub struct LocalSigner {
+    signing_key: SigningKey,
+    account_id: String,
+}
+
+impl LocalSigner {
+    pub fn from_secret(secret_seed: &str) -> StellarResult<Self> {
+        decode_signing_key(secret_seed).map(Self::from_signing_key)
+    }
+
+    pub fn from_signing_key(signing_key: SigningKey) -> Self {
+        let account_id = strkey_account_id(&signing_key.verifying_key());
+        Self {
+            signing_key,
+            account_id,
+        }
+    }
+
+    /// Read and decrypt a keystore file written by [`Keystore::encrypt`]
+    pub fn from_keystore_file(path: impl AsRef<Path>, passphrase: &str) -> StellarResult<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path).map_err(|e| {
+            StellarError::config_error(format!("cannot read keystore {}: {}", path.display(), e))
+        })?;
+        let keystore: Keystore = serde_json::from_str(&raw).map_err(|e| {
+            StellarError::config_error(format!("invalid keystore {}: {}", path.display(), e))
+        })?;
+        keystore.decrypt(passphrase)
+    }
+
+    fn sign(&self, hash: &[u8; 32]) -> StellarResult<DecoratedSignature> {
+        let signature = self
+            .signing_key
+            .try_sign(hash)
+            .map_err(|_| StellarError::signing_error("failed to sign transaction hash"))?;
+        Ok(DecoratedSignature {
+            hint: signature_hint(&self.signing_key)?,
+            signature: Signature::try_from(signature.to_bytes().to_vec())
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+        })
+    }
+}
+
+impl fmt::Debug for LocalSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalSigner")
+            .field("account_id", &self.account_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    async fn sign_hash(&self, hash: &[u8; 32]) -> StellarResult<Vec<DecoratedSignature>> {
+        self.sign(hash).map(|signature| vec![signature])
+    }
+}
+
+/// Secret seed encrypted with AES-256-GCM under a PBKDF2-HMAC-SHA256 key.
+/// The account ID is bound in as associated data. Byte fields are hex.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub account_id: String,
+    pub kdf_iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    pub fn encrypt(secret_seed: &str, passphrase: &str) -> StellarResult<Self> {
+        Self::encrypt_with_iterations(secret_seed, passphrase, DEFAULT_KDF_ITERATIONS)
+    }
+
+    pub fn encrypt_with_iterations(
+        secret_seed: &str,
+        passphrase: &str,
+        kdf_iterations: u32,
+    ) -> StellarResult<Self> {
+        let signing_key = decode_signing_key(secret_seed)?;
+        let account_id = strkey_account_id(&signing_key.verifying_key());
+
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let cipher = keystore_cipher(passphrase, &salt, kdf_iterations);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: signing_key.as_bytes(),
+                    aad: account_id.as_bytes(),
+                },
+            )
+            .map_err(|_| StellarError::signing_error("failed to encrypt keystore"))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            account_id,
+            kdf_iterations,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> StellarResult<LocalSigner> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(StellarError::config_error(format!(
+                "unsupported keystore version {}",
+                self.version
+            )));
+        }
+        let decode = |field: &str, value: &str| {
+            hex::decode(value)
+                .map_err(|_| StellarError::config_error(format!("keystore {} is not hex", field)))
+        };
+        let salt = decode("salt", &self.salt)?;
+        let nonce = decode("nonce", &self.nonce)?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        let nonce: [u8; 12] = nonce
+            .try_into()
+            .map_err(|_| StellarError::config_error("keystore nonce must be 12 bytes"))?;
+
+        let cipher = keystore_cipher(passphrase, &salt, self.kdf_iterations);
+        let seed = cipher
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: self.account_id.as_bytes(),
+                },
+            )
+            .map_err(|_| StellarError::signing_error("wrong keystore passphrase"))?;
+        let seed: [u8; 32] = seed
+            .try_into()
+            .map_err(|_| StellarError::signing_error("keystore does not hold an ed25519 seed"))?;
+
+        let signer = LocalSigner::from_signing_key(SigningKey::from_bytes(&seed));
+        if signer.account_id != self.account_id {
+            return Err(StellarError::signing_error(
+                "keystore key does not match its account_id",
+            ));
+        }
+        Ok(signer)
+    }
+}
+
+fn keystore_cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    Aes256Gcm::new(&Key::<Aes256Gcm>::from(key))
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteSignerConfig {
+    /// Endpoint accepting signing requests
+    pub url: String,
+    /// Key (G...) the service signs with
+    pub public_key: String,
+    /// Sent as a bearer token when set
+    pub auth_token: Option<String>,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Serialize)]
+struct RemoteSignRequest<'a> {
+    public_key: &'a str,
+    transaction_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteSignResponse {
+    signature: String,
+}
+
+/// Signing service speaking a single-call HTTP protocol: `POST {url}` with
+/// `{"public_key", "transaction_hash"}` (hash hex-encoded) returns
+/// `{"signature"}`, the hex-encoded ed25519 signature. Signatures are
+/// verified before use.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    http: reqwest::Client,
+    config: RemoteSignerConfig,
+    verifying_key: VerifyingKey,
+}
+
+impl RemoteSigner {
+    pub fn new(config: RemoteSignerConfig) -> StellarResult<Self> {
+        let public_key = StrkeyPublicKey::from_string(&config.public_key)
+            .map_err(|_| StellarError::invalid_address(&config.public_key))?;
+        let verifying_key = VerifyingKey::from_bytes(&public_key.0)
+            .map_err(|_| StellarError::invalid_address(&config.public_key))?;
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| StellarError::config_error(format!("remote signer client: {}", e)))?;
+        Ok(Self {
+            http,
+            config,
+            verifying_key,
+        })
+    }
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("url", &self.config.url)
+            .field("public_key", &self.config.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn account_id(&self) -> &str {
+        &self.config.public_key
+    }
+
+    async fn sign_hash(&self, hash: &[u8; 32]) -> StellarResult<Vec<DecoratedSignature>> {
+        let mut request = self.http.post(&self.config.url).json(&RemoteSignRequest {
+            public_key: &self.config.public_key,
+            transaction_hash: hex::encode(hash),
+        });
+        if let Some(token) = self.config.auth_token.as_deref() {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            StellarError::network_error(format!("remote signer {}: {}", self.config.url, e))
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(StellarError::signing_error(format!(
+                "remote signer {} refused to sign: HTTP {}",
+                self.config.url, status
+            )));
+        }
+        let body: RemoteSignResponse = response.json().await.map_err(|e| {
+            StellarError::serialization_error(format!("remote signer response: {}", e))
+        })?;
+
+        let bytes: [u8; 64] = hex::decode(body.signature.trim())
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| {
+                StellarError::signing_error("remote signer returned a malformed signature")
+            })?;
+        let signature = ed25519_dalek::Signature::from_bytes(&bytes);
+        self.verifying_key
+            .verify(hash, &signature)
+            .map_err(|_| StellarError::signing_error("remote signer signature does not verify"))?;
+
+        let key = self.verifying_key.to_bytes();
+        Ok(vec![DecoratedSignature {
+            hint: SignatureHint::try_from(&key[key.len() - 4..])
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+            signature: Signature::try_from(bytes.to_vec())
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+        }])
+    }
+}
+
+/// Signs for a multisig account by asking every cosigner and keeping the
+/// first `threshold` signatures. Cosigners are assumed to carry equal weight
+/// and `threshold` signatures to meet the account's threshold on-chain; any
+/// extra signature would fail the transaction with `txBAD_AUTH_EXTRA`.
+#[derive(Debug, Clone)]
+pub struct MultiSigner {
+    account_id: String,
+    signers: Vec<Arc<dyn TransactionSigner>>,
+    threshold: usize,
+}
+
+impl MultiSigner {
+    pub fn new(
+        account_id: impl Into<String>,
+        signers: Vec<Arc<dyn TransactionSigner>>,
+        threshold: usize,
+    ) -> StellarResult<Self> {
+        if threshold == 0 || threshold > signers.len() {
+            return Err(StellarError::config_error(format!(
+                "multisig threshold must be between 1 and {} (got {})",
+                signers.len(),
+                threshold
+            )));
+        }
+        Ok(Self {
+            account_id: account_id.into(),
+            signers,
+            threshold,
+        })
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for MultiSigner {
+    fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    async fn sign_hash(&self, hash: &[u8; 32]) -> StellarResult<Vec<DecoratedSignature>> {
+        let results =
+            futures::future::join_all(self.signers.iter().map(|signer| signer.sign_hash(hash)))
+                .await;
+
+        let mut signatures: Vec<DecoratedSignature> = Vec::with_capacity(self.threshold);
+        for (signer, result) in self.signers.iter().zip(results) {
+            match result {
+                Ok(collected) => {
+                    for signature in collected {
+                        if !signatures.iter().any(|s| s.hint == signature.hint) {
+                            signatures.push(signature);
+                        }
+                    }
+                }
+                Err(e) => warn!(
+                    account_id = %self.account_id,
+                    cosigner = signer.account_id(),
+                    error = %e,
+                    "cosigner failed to sign"
+                ),
+            }
+        }
+
+        if signatures.len() < self.threshold {
+            return Err(StellarError::signing_error(format!(
+                "collected {} of {} required signatures for {}",
+                signatures.len(),
+                self.threshold,
+                self.account_id
+            )));
+        }
+        signatures.truncate(self.threshold);
+        Ok(signatures)
+    }
+}
+
+/// Hot wallet signer selected by `HOT_WALLET_SIGNER`; see
+/// [`signer_from_env`].
+pub fn hot_wallet_signer_from_env() -> StellarResult<Option<Arc<dyn TransactionSigner>>> {
+    signer_from_env("HOT_WALLET")
+}
+
+/// Platform signer selected by `{prefix}_SIGNER`:
+///
+/// - `secret` (default): `{prefix}_SECRET_KEY`, or the older
+///   `{prefix}_SECRET`
+/// - `keystore`: `{prefix}_KEYSTORE_PATH`, decrypted with
+///   `{prefix}_KEYSTORE_PASSPHRASE`
+/// - `remote`: `{prefix}_REMOTE_SIGNER_URL` signing as `{prefix}_PUBLIC_KEY`
+/// - `multi`: `{prefix}_MULTISIG_SIGNERS` for account `{prefix}_PUBLIC_KEY`
+///   with `{prefix}_MULTISIG_THRESHOLD`. Entries are comma-separated
+///   `G...=https://...` remote cosigners, or `keystore` for the local
+///   keystore.
+///
+/// Remote signers share `{prefix}_REMOTE_SIGNER_TOKEN` and
+/// `{prefix}_REMOTE_SIGNER_TIMEOUT_SECONDS`. Returns None when the selected
+/// signer is not configured.
+pub fn signer_from_env(prefix: &str) -> StellarResult<Option<Arc<dyn TransactionSigner>>> {
+    let name = |suffix: &str| format!("{}_{}", prefix, suffix);
+    let var = |suffix: &str| {
+        std::env::var(name(suffix))
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let kind = var("SIGNER").unwrap_or_else(|| "secret".to_string());
+
+    let remote = |url: String, public_key: String| -> StellarResult<Arc<dyn TransactionSigner>> {
+        let timeout = var("REMOTE_SIGNER_TIMEOUT_SECONDS")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_REMOTE_TIMEOUT_SECONDS);
+        Ok(Arc::new(RemoteSigner::new(RemoteSignerConfig {
+            url,
+            public_key,
+            auth_token: var("REMOTE_SIGNER_TOKEN"),
+            timeout: Duration::from_secs(timeout),
+        })?))
+    };
+    let keystore = || -> StellarResult<Option<Arc<dyn TransactionSigner>>> {
+        let Some(path) = var("KEYSTORE_PATH") else {
+            return Ok(None);
+        };
+        let passphrase = var("KEYSTORE_PASSPHRASE").ok_or_else(|| {
+            StellarError::config_error(format!("{} is required", name("KEYSTORE_PASSPHRASE")))
+        })?;
+        Ok(Some(Arc::new(LocalSigner::from_keystore_file(
+            path,
+            &passphrase,
+        )?)))
+    };
+
+    match kind.to_lowercase().as_str() {
+        "secret" => var("SECRET_KEY")
+            .or_else(|| var("SECRET"))
+            .map(|secret| {
+                LocalSigner::from_secret(&secret)
+                    .map(|signer| Arc::new(signer) as Arc<dyn TransactionSigner>)
+            })
+            .transpose(),
+        "keystore" => keystore(),
+        "remote" => match (var("REMOTE_SIGNER_URL"), var("PUBLIC_KEY")) {
+            (Some(url), Some(public_key)) => remote(url, public_key).map(Some),
+            _ => Ok(None),
+        },
+        "multi" => {
+            let (Some(account_id), Some(entries)) = (var("PUBLIC_KEY"), var("MULTISIG_SIGNERS"))
+            else {
+                return Ok(None);
+            };
+            let mut signers = Vec::new();
+            for entry in entries.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                if entry.eq_ignore_ascii_case("keystore") {
+                    signers.push(keystore()?.ok_or_else(|| {
+                        StellarError::config_error(format!(
+                            "multisig entry `keystore` needs {}",
+                            name("KEYSTORE_PATH")
+                        ))
+                    })?);
+                } else {
+                    let (public_key, url) = entry.split_once('=').ok_or_else(|| {
+                        StellarError::config_error(format!(
+                            "multisig entry `{}` is not `G...=url` or `keystore`",
+                            entry
+                        ))
+                    })?;
+                    signers.push(remote(
+                        url.trim().to_string(),
+                        public_key.trim().to_string(),
+                    )?);
+                }
+            }
+            let threshold = var("MULTISIG_THRESHOLD")
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(signers.len());
+            Ok(Some(Arc::new(MultiSigner::new(
+                account_id, signers, threshold,
+            )?)))
+        }
+        other => Err(StellarError::config_error(format!(
+            "unknown {} `{}` (expected secret, keystore, remote or multi)",
+            name("SIGNER"),
+            other
+        ))),
+    }
+}
+
+fn strkey_account_id(verifying_key: &VerifyingKey) -> String {
+    StrkeyPublicKey(verifying_key.to_bytes())
+        .to_string()
+        .as_str()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stellar_strkey::ed25519::PrivateKey as StrkeyPrivateKey;
+
+    fn secret(seed: u8) -> String {
+        StrkeyPrivateKey([seed; 32]).to_string().as_str().to_owned()
+    }
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let keystore =
+            Keystore::encrypt_with_iterations(&secret(5), "correct horse", 1_000).unwrap();
+        let signer = keystore.decrypt("correct horse").unwrap();
+        assert_eq!(
+            signer.account_id(),
+            LocalSigner::from_secret(&secret(5)).unwrap().account_id()
+        );
+        assert_eq!(keystore.account_id, signer.account_id());
+
+        assert!(keystore.decrypt("wrong").is_err());
+
+        let mut tampered = keystore.clone();
+        tampered.account_id = LocalSigner::from_secret(&secret(6))
+            .unwrap()
+            .account_id()
+            .to_string();
+        assert!(tampered.decrypt("correct horse").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multi_signer_collects_threshold_signatures() {
+        let signers: Vec<Arc<dyn TransactionSigner>> = (1..=3)
+            .map(|seed| {
+                Arc::new(LocalSigner::from_secret(&secret(seed)).unwrap())
+                    as Arc<dyn TransactionSigner>
+            })
+            .collect();
+        let account = signers[0].account_id().to_string();
+        let multi = MultiSigner::new(account.clone(), signers.clone(), 2).unwrap();
+
+        let signatures = multi.sign_hash(&[7; 32]).await.unwrap();
+        assert_eq!(multi.account_id(), account);
+        assert_eq!(signatures.len(), 2);
+        assert_ne!(signatures[0].hint, signatures[1].hint);
+
+        assert!(MultiSigner::new(account.clone(), signers.clone(), 0).is_err());
+        assert!(MultiSigner::new(account, signers, 4).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multi_signer_fails_below_threshold() {
+        let local: Arc<dyn TransactionSigner> =
+            Arc::new(LocalSigner::from_secret(&secret(1)).unwrap());
+        let unreachable: Arc<dyn TransactionSigner> = Arc::new(
+            RemoteSigner::new(RemoteSignerConfig {
+                url: "http://127.0.0.1:9/sign".to_string(),
+                public_key: LocalSigner::from_secret(&secret(2))
+                    .unwrap()
+                    .account_id()
+                    .to_string(),
+                auth_token: None,
+                timeout: Duration::from_millis(200),
+            })
+            .unwrap(),
+        );
+        let account = local.account_id().to_string();
+        let multi = MultiSigner::new(account, vec![local, unreachable], 2).unwrap();
+        assert!(multi.sign_hash(&[7; 32]).await.is_err());
+    }
+}
@@ -0,0 +1,477 @@
+//! Horizon Server-Sent Events streaming
+//!
+//! Horizon keeps `/accounts/{id}/payments` open as an event stream when asked
+//! for `text/event-stream`, pushing one JSON record per operation as ledgers
+//! close. Each record's `paging_token` is a valid cursor for resuming the
+//! stream after a reconnect.
+
+use crate::chains::stellar::client::{
+    encode_form_component, HorizonTransactionRecord, StellarClient,
+};
+use crate::chains::stellar::errors::{StellarError, StellarResult};
+use crate::chains::stellar::types::is_valid_account_id;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::debug;
+
+/// Payment-like operation from Horizon's payments endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HorizonPaymentRecord {
+    pub id: String,
+    pub paging_token: String,
+    #[serde(rename = "type")]
+    pub operation_type: String,
+    pub transaction_hash: String,
+    #[serde(default)]
+    pub transaction_successful: bool,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// SEP-23 M-address the payment was sent to, when the destination was muxed
+    #[serde(default)]
+    pub to_muxed: Option<String>,
+    /// Muxed sub-account ID; Horizon encodes the uint64 as a string
+    #[serde(default)]
+    pub to_muxed_id: Option<String>,
+    pub asset_type: Option<String>,
+    pub asset_code: Option<String>,
+    pub asset_issuer: Option<String>,
+    pub amount: Option<String>,
+    pub created_at: Option<String>,
+    /// Parent transaction, present when requested with `join=transactions`
+    pub transaction: Option<HorizonTransactionRecord>,
+}
+
+impl HorizonPaymentRecord {
+    /// Whether the operation moves an asset to `to` (`payment` and both path payments)
+    pub fn is_payment(&self) -> bool {
+        matches!(
+            self.operation_type.as_str(),
+            "payment" | "path_payment_strict_receive" | "path_payment_strict_send"
+        )
+    }
+
+    /// Zero-based position of the operation inside its transaction, decoded
+    /// from the low 12 bits of the operation TOID paging token
+    pub fn operation_index(&self) -> Option<i32> {
+        let toid = self.paging_token.parse::<i64>().ok()?;
+        let application_order = (toid & 0xFFF) as i32;
+        (application_order > 0).then_some(application_order - 1)
+    }
+
+    /// Muxed ID of the destination, if the payment targeted an M-address
+    pub fn muxed_id(&self) -> Option<u64> {
+        self.to_muxed_id.as_deref()?.parse().ok()
+    }
+
+    /// Ledger sequence encoded in the high 32 bits of the paging token
+    pub fn ledger(&self) -> Option<i64> {
+        self.paging_token.parse::<i64>().ok().map(|toid| toid >> 32)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SSE decoding
+// ---------------------------------------------------------------------------
+
+/// One dispatched Server-Sent Event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental decoder for the `text/event-stream` format. Feed it raw chunks
+/// as they arrive; complete events come out, partial lines stay buffered.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    has_data: bool,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let line = String::from_utf8_lossy(&line);
+
+            if line.is_empty() {
+                // Blank line dispatches the event; events without data are dropped
+                let event = std::mem::take(&mut self.current);
+                if std::mem::take(&mut self.has_data) {
+                    events.push(event);
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue; // comment / keep-alive
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line.as_ref(), ""),
+            };
+            match field {
+                "data" => {
+                    if self.has_data {
+                        self.current.data.push('\n');
+                    }
+                    self.current.data.push_str(value);
+                    self.has_data = true;
+                }
+                "event" => self.current.event = Some(value.to_string()),
+                "id" => self.current.id = Some(value.to_string()),
+                _ => {} // `retry` and unknown fields
+            }
+        }
+
+        events
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Payment stream
+// ---------------------------------------------------------------------------
+
+/// Open stream of payments for one account
+pub struct PaymentStream {
+    response: reqwest::Response,
+    decoder: SseDecoder,
+    pending: VecDeque<SseEvent>,
+    idle_timeout: Duration,
+}
+
+impl PaymentStream {
+    /// Next payment record. `Ok(None)` means the stream ended (closed by
+    /// Horizon or idle too long) and the caller should reconnect from the last
+    /// seen `paging_token`.
+    pub async fn next_payment(&mut self) -> StellarResult<Option<HorizonPaymentRecord>> {
+        loop {
+            while let Some(event) = self.pending.pop_front() {
+                // Horizon greets with `"hello"` and says `"byebye"` before closing
+                if !event.data.trim_start().starts_with('{') {
+                    debug!(data = %event.data, "horizon stream message");
+                    continue;
+                }
+                let record =
+                    serde_json::from_str::<HorizonPaymentRecord>(&event.data).map_err(|e| {
+                        StellarError::serialization_error(format!(
+                            "invalid payment record in stream: {}",
+                            e
+                        ))
+                    })?;
+                return Ok(Some(record));
+            }
+
+            let Ok(chunk) = timeout(self.idle_timeout, self.response.chunk()).await else {
+                // A silent connection may be dead without us noticing; start over
+                debug!(
+                    idle_secs = self.idle_timeout.as_secs(),
+                    "horizon stream idle, closing"
+                );
+                return Ok(None);
+            };
+            let chunk = chunk
+                .map_err(|e| StellarError::network_error(format!("Horizon stream error: {}", e)))?;
+            match chunk {
+                Some(bytes) => self.pending.extend(self.decoder.push(&bytes)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl StellarClient {
+    /// Open the payments event stream for `account`, with parent transactions
+    /// joined in. `cursor` resumes after a `paging_token`; `None` starts from now.
+    ///
+    /// The stream has no overall deadline; it ends when nothing (not even a
+    /// keep-alive) arrives for `idle_timeout`.
+    pub async fn stream_account_payments(
+        &self,
+        account: &str,
+        cursor: Option<&str>,
+        idle_timeout: Duration,
+    ) -> StellarResult<PaymentStream> {
+        if !is_valid_account_id(account) {
+            return Err(StellarError::invalid_address(account));
+        }
+
+        let url = format!(
+            "{}/accounts/{}/payments?join=transactions&cursor={}",
+            self.config().horizon_url(),
+            account,
+            encode_form_component(cursor.unwrap_or("now"))
+        );
+
+        // The shared client's request timeout would cut the stream off
+        let http = reqwest::Client::builder()
+            .connect_timeout(self.config().request_timeout)
+            .user_agent("Aframp-Backend/1.0")
+            .build()
+            .map_err(|e| {
+                StellarError::config_error(format!("Failed to create HTTP client: {}", e))
+            })?;
+
+        let response = timeout(
+            self.config().request_timeout,
+            http.get(&url)
+                .header(reqwest::header::ACCEPT, "text/event-stream")
+                .send(),
+        )
+        .await
+        .map_err(|_| StellarError::timeout_error(self.config().request_timeout.as_secs()))?
+        .map_err(|e| StellarError::network_error(format!("Horizon stream connect error: {}", e)))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(StellarError::RateLimitError);
+        }
+        if !status.is_success() {
+            return Err(StellarError::network_error(format!(
+                "Horizon stream returned {}",
+                status
+            )));
+        }
+
+        debug!(account = %account, cursor = ?cursor, "horizon payment stream opened");
+        Ok(PaymentStream {
+            response,
+            decoder: SseDecoder::new(),
+            pending: VecDeque::new(),
+            idle_timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::stellar::config::{StellarConfig, StellarNetwork};
+    use axum::{extract::RawQuery, response::IntoResponse, routing::get, Router};
+
+    const ACCOUNT: &str = "GCJRI5CIWK5IU67Q6DGA7QW52JDKRO7JEAHQKFNDUJUPEZGURDBX3LDX";
+
+    fn payment_record(paging_token: &str, memo: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": paging_token,
+            "paging_token": paging_token,
+            "type": "payment",
+            "transaction_hash": format!("hash-{}", paging_token),
+            "transaction_successful": true,
+            "from": "GSENDER",
+            "to": ACCOUNT,
+            "asset_type": "credit_alphanum4",
+            "asset_code": "cNGN",
+            "asset_issuer": "GISSUER",
+            "amount": "5000.0000000",
+            "transaction": {
+                "hash": format!("hash-{}", paging_token),
+                "successful": true,
+                "ledger": 100,
+                "memo_type": "text",
+                "memo": memo
+            }
+        })
+    }
+
+    fn payment_event(paging_token: &str, memo: &str) -> String {
+        let record = payment_record(paging_token, memo);
+        format!("id: {}\ndata: {}\n\n", paging_token, record)
+    }
+
+    /// Local stand-in for Horizon's payments stream: replies with the given
+    /// events and closes, echoing the query string back through `queries`
+    async fn spawn_sse_server(
+        body: String,
+    ) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let (query_tx, query_rx) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/accounts/{account}/payments",
+            get(move |RawQuery(query): RawQuery| {
+                let body = body.clone();
+                let query_tx = query_tx.clone();
+                async move {
+                    let _ = query_tx.send(query.unwrap_or_default());
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "text/event-stream")],
+                        body,
+                    )
+                        .into_response()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), query_rx)
+    }
+
+    fn client(base_url: String) -> StellarClient {
+        StellarClient::new(StellarConfig {
+            network: StellarNetwork::Testnet,
+            horizon_url_override: Some(base_url),
+            request_timeout: Duration::from_secs(5),
+            max_retries: 1,
+            health_check_interval: Duration::from_secs(30),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_operation_index_and_ledger_from_paging_token() {
+        let mut record: HorizonPaymentRecord =
+            serde_json::from_value(payment_record("0", "")).unwrap();
+
+        // ledger 100, transaction order 3, third operation
+        record.paging_token = ((100i64 << 32) | (3 << 12) | 3).to_string();
+        assert_eq!(record.operation_index(), Some(2));
+        assert_eq!(record.ledger(), Some(100));
+
+        record.paging_token = (100i64 << 32).to_string();
+        assert_eq!(record.operation_index(), None);
+        record.paging_token = "now".to_string();
+        assert_eq!(record.ledger(), None);
+    }
+
+    #[test]
+    fn test_muxed_destination_id() {
+        let mut value = payment_record("0", "");
+        let record: HorizonPaymentRecord = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(record.muxed_id(), None);
+
+        value["to_muxed"] = serde_json::json!("MAAAA");
+        value["to_muxed_id"] = serde_json::json!("18446744073709551615");
+        let record: HorizonPaymentRecord = serde_json::from_value(value).unwrap();
+        assert_eq!(record.muxed_id(), Some(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_list_account_payments_pages_in_ledger_order() {
+        let record = payment_record("429496729601", "ref");
+        let (query_tx, mut query_rx) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/accounts/{account}/payments",
+            get(move |RawQuery(query): RawQuery| {
+                let record = record.clone();
+                let query_tx = query_tx.clone();
+                async move {
+                    let _ = query_tx.send(query.unwrap_or_default());
+                    axum::Json(serde_json::json!({ "_embedded": { "records": [record] } }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let page = client(format!("http://{}", addr))
+            .list_account_payments(ACCOUNT, 50, Some("429496729600"))
+            .await
+            .unwrap();
+
+        assert_eq!(page.records.len(), 1);
+        assert_eq!(page.records[0].paging_token, "429496729601");
+        assert_eq!(
+            query_rx.recv().await.unwrap(),
+            "order=asc&limit=50&join=transactions&cursor=429496729600"
+        );
+    }
+
+    #[test]
+    fn test_decoder_handles_split_chunks_and_comments() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"retry: 1000\nevent: open\nda").is_empty());
+        let events = decoder
+            .push(b"ta: \"hello\"\r\n\r\n: keep-alive\n\nid: 12\ndata: {\"a\":\ndata: 1}\n\n");
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    id: None,
+                    event: Some("open".to_string()),
+                    data: "\"hello\"".to_string(),
+                },
+                SseEvent {
+                    id: Some("12".to_string()),
+                    event: None,
+                    data: "{\"a\":\n1}".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decoder_keeps_split_utf8() {
+        let mut decoder = SseDecoder::new();
+        let bytes = "data: ₦5000\n\n".as_bytes();
+        assert!(decoder.push(&bytes[..8]).is_empty());
+        let events = decoder.push(&bytes[8..]);
+        assert_eq!(events[0].data, "₦5000");
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_payments_until_closed() {
+        let body = format!(
+            "retry: 1000\nevent: open\ndata: \"hello\"\n\n{}{}",
+            payment_event("1001", "tx-1"),
+            payment_event("1002", "WD-tx-2")
+        );
+        let (base_url, mut queries) = spawn_sse_server(body).await;
+
+        let mut stream = client(base_url)
+            .stream_account_payments(ACCOUNT, Some("1000"), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let first = stream.next_payment().await.unwrap().unwrap();
+        assert_eq!(first.paging_token, "1001");
+        assert!(first.is_payment());
+        assert_eq!(
+            first.transaction.as_ref().and_then(|t| t.memo.as_deref()),
+            Some("tx-1")
+        );
+        let second = stream.next_payment().await.unwrap().unwrap();
+        assert_eq!(second.paging_token, "1002");
+        assert!(stream.next_payment().await.unwrap().is_none());
+
+        assert_eq!(
+            queries.recv().await.unwrap(),
+            "join=transactions&cursor=1000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_cursor_starts_now() {
+        let (base_url, mut queries) = spawn_sse_server(String::new()).await;
+        let mut stream = client(base_url)
+            .stream_account_payments(ACCOUNT, None, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert!(stream.next_payment().await.unwrap().is_none());
+        assert_eq!(
+            queries.recv().await.unwrap(),
+            "join=transactions&cursor=now"
+        );
+    }
+}
@@ -0,0 +1,760 @@
+//! Submission queue for a platform signing account
+//!
+//! Every payment signed by one account goes through a single queue, which
+//! hands out sequence numbers locally instead of each caller reading the
+//! account from Horizon and racing the others into `tx_bad_seq`. Payments
+//! that arrive together and share a memo are batched into one transaction of
+//! up to 100 operations. A `tx_bad_seq` resyncs the sequence from Horizon; an
+//! envelope whose outcome is unknown is looked up by hash until its
+//! timebounds expire, and only then rebuilt and resubmitted.
+//!
+//! With a channel account pool, each batch that finds a free channel is
+//! submitted from it in parallel, with the account still the source of every
+//! operation. Batches fall back to the account itself when all channels are
+//! busy.
+
+use crate::chains::stellar::channel::{ChannelAccountPool, ChannelLease};
+use crate::chains::stellar::client::StellarClient;
+use crate::chains::stellar::errors::{StellarError, StellarResult};
+use crate::chains::stellar::payment::{
+    build_asset, decimal_to_stroops, memo_to_xdr, network_id, parse_account_id,
+    parse_muxed_account, unix_time, CngnMemo,
+};
+use crate::chains::stellar::signer::TransactionSigner;
+use crate::chains::stellar::trustline::CngnAssetConfig;
+use crate::chains::stellar::types::{is_valid_account_id, is_valid_stellar_address};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use stellar_xdr::next::{
+    Asset, CreateAccountOp, Limits, Operation, OperationBody, PaymentOp, Preconditions,
+    SequenceNumber, TimeBounds, TimePoint, Transaction, TransactionEnvelope, TransactionExt,
+    TransactionV1Envelope, VecM, WriteXdr,
+};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+
+/// Protocol limit on operations per transaction
+pub const MAX_OPERATIONS_PER_TX: usize = 100;
+
+/// Ledger close times can trail the local clock by a few seconds
+const TIMEBOUNDS_GRACE_SECONDS: u64 = 10;
+/// How long past its timebounds an envelope is looked up while Horizon is
+/// failing, before its outcome is reported as unknown
+const UNKNOWN_OUTCOME_GRACE_SECONDS: u64 = 120;
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct SubmissionConfig {
+    /// How long the first payment of a batch waits for others to join it
+    pub batch_window: Duration,
+    pub max_batch_operations: usize,
+    /// Upper timebound of each envelope, from when it is built
+    pub tx_timeout: Duration,
+    /// Envelopes built per batch, including resubmissions
+    pub max_attempts: u32,
+    /// Per operation
+    pub base_fee_stroops: u32,
+    /// Between hash lookups while an outcome is unknown
+    pub poll_interval: Duration,
+}
+
+impl Default for SubmissionConfig {
+    fn default() -> Self {
+        Self {
+            batch_window: Duration::from_millis(250),
+            max_batch_operations: MAX_OPERATIONS_PER_TX,
+            tx_timeout: Duration::from_secs(60),
+            max_attempts: 5,
+            base_fee_stroops: 100,
+            poll_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+impl SubmissionConfig {
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+
+        cfg.batch_window = Duration::from_millis(
+            std::env::var("STELLAR_SUBMISSION_BATCH_WINDOW_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.batch_window.as_millis() as u64),
+        );
+
+        cfg.max_batch_operations = std::env::var("STELLAR_SUBMISSION_MAX_BATCH_OPERATIONS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(cfg.max_batch_operations)
+            .clamp(1, MAX_OPERATIONS_PER_TX);
+
+        cfg.tx_timeout = Duration::from_secs(
+            std::env::var("STELLAR_SUBMISSION_TX_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.tx_timeout.as_secs()),
+        );
+
+        cfg.max_attempts = std::env::var("STELLAR_SUBMISSION_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(cfg.max_attempts)
+            .max(1);
+
+        cfg.base_fee_stroops = std::env::var("STELLAR_SUBMISSION_BASE_FEE_STROOPS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(cfg.base_fee_stroops);
+
+        cfg
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Requests and results
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentAsset {
+    Cngn,
+    Native,
+    /// Native XLM sent as the starting balance of a new account
+    CreateAccount,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueuedPayment {
+    /// `G...` or SEP-23 `M...` address
+    pub destination: String,
+    pub amount: String,
+    pub asset: PaymentAsset,
+    /// Transaction-level, so only payments with the same memo share a batch
+    pub memo: CngnMemo,
+}
+
+impl QueuedPayment {
+    pub fn cngn(destination: impl Into<String>, amount: impl Into<String>, memo: CngnMemo) -> Self {
+        Self {
+            destination: destination.into(),
+            amount: amount.into(),
+            asset: PaymentAsset::Cngn,
+            memo,
+        }
+    }
+}
+
+/// Where a payment landed
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmittedPayment {
+    pub tx_hash: String,
+    pub sequence: i64,
+    pub operation_index: usize,
+    pub operation_count: usize,
+    /// Channel account the transaction was sent from, if any
+    pub channel_account: Option<String>,
+    /// Upper time bound of the envelope, unix seconds
+    pub valid_until: u64,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{error}")]
+pub struct SubmissionError {
+    #[source]
+    pub error: StellarError,
+    /// Envelope that may still be applied. Check it on-chain before paying
+    /// again.
+    pub in_flight_hash: Option<String>,
+    /// Upper time bound of the in-flight envelope, unix seconds
+    pub in_flight_valid_until: Option<u64>,
+}
+
+impl SubmissionError {
+    fn rejected(error: StellarError) -> Self {
+        Self {
+            error,
+            in_flight_hash: None,
+            in_flight_valid_until: None,
+        }
+    }
+
+    /// No envelope carrying the payment can still be applied, so it is safe
+    /// to retry or refund
+    pub fn nothing_sent(&self) -> bool {
+        self.in_flight_hash.is_none()
+    }
+}
+
+type SubmissionResult = Result<SubmittedPayment, SubmissionError>;
+
+struct Job {
+    payment: QueuedPayment,
+    reply: oneshot::Sender<SubmissionResult>,
+}
+
+// ---------------------------------------------------------------------------
+// Queue
+// ---------------------------------------------------------------------------
+
+/// Handle to the queue for one signing account. Cheap to clone; create one
+/// per account and share it, since two queues for the same account would
+/// race each other for sequence numbers.
+#[derive(Debug, Clone)]
+pub struct SubmissionQueue {
+    account_id: String,
+    sender: mpsc::UnboundedSender<Job>,
+}
+
+impl SubmissionQueue {
+    /// Spawns the queue's worker task; it stops once every handle is dropped
+    pub fn new(
+        stellar_client: StellarClient,
+        signer: Arc<dyn TransactionSigner>,
+        config: SubmissionConfig,
+    ) -> Self {
+        Self::spawn(stellar_client, signer, config, None)
+    }
+
+    /// Like [`new`](SubmissionQueue::new), submitting batches from channel
+    /// accounts leased from `channels` whenever one is free
+    pub fn with_channel_pool(
+        stellar_client: StellarClient,
+        signer: Arc<dyn TransactionSigner>,
+        config: SubmissionConfig,
+        channels: ChannelAccountPool,
+    ) -> Self {
+        Self::spawn(stellar_client, signer, config, Some(channels))
+    }
+
+    fn spawn(
+        stellar_client: StellarClient,
+        signer: Arc<dyn TransactionSigner>,
+        config: SubmissionConfig,
+        channels: Option<ChannelAccountPool>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let account_id = signer.account_id().to_string();
+        let worker = QueueWorker {
+            submitter: Arc::new(Submitter {
+                asset_config: CngnAssetConfig::from_env(),
+                stellar_client,
+                signer,
+                config,
+            }),
+            channels,
+            own_lane: Lane::Account {
+                next_sequence: None,
+            },
+        };
+        tokio::spawn(worker.run(receiver));
+        Self { account_id, sender }
+    }
+
+    /// Source account of every payment sent through this queue
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    /// Queue a payment and wait until it is applied, rejected, or its outcome
+    /// can no longer be determined
+    pub async fn pay(&self, payment: QueuedPayment) -> SubmissionResult {
+        let valid_destination = match payment.asset {
+            PaymentAsset::CreateAccount => is_valid_account_id(&payment.destination),
+            _ => is_valid_stellar_address(&payment.destination),
+        };
+        if !valid_destination {
+            return Err(SubmissionError::rejected(StellarError::invalid_address(
+                &payment.destination,
+            )));
+        }
+        decimal_to_stroops(&payment.amount).map_err(SubmissionError::rejected)?;
+
+        let (reply, response) = oneshot::channel();
+        self.sender.send(Job { payment, reply }).map_err(|_| {
+            SubmissionError::rejected(StellarError::unexpected_error(
+                "submission queue has stopped",
+            ))
+        })?;
+        response.await.map_err(|_| {
+            SubmissionError::rejected(StellarError::unexpected_error(
+                "submission queue dropped the payment",
+            ))
+        })?
+    }
+}
+
+struct BuiltEnvelope {
+    hash: String,
+    envelope_xdr: String,
+    max_time: u64,
+    channel_account: Option<String>,
+}
+
+enum Outcome {
+    Applied { successful: bool },
+    Expired,
+    Unknown,
+}
+
+/// Transaction source a batch is submitted from
+enum Lane {
+    /// The queue's own account
+    Account {
+        /// Next unused sequence, or `None` to read it from Horizon
+        next_sequence: Option<i64>,
+    },
+    Channel(ChannelLease),
+}
+
+impl Lane {
+    fn channel(&self) -> Option<&ChannelLease> {
+        match self {
+            Lane::Account { .. } => None,
+            Lane::Channel(lease) => Some(lease),
+        }
+    }
+
+    fn next_sequence(&self) -> Option<i64> {
+        match self {
+            Lane::Account { next_sequence } => *next_sequence,
+            Lane::Channel(lease) => lease.next_sequence(),
+        }
+    }
+
+    fn set_next_sequence(&mut self, sequence: Option<i64>) {
+        match self {
+            Lane::Account { next_sequence } => *next_sequence = sequence,
+            Lane::Channel(lease) => lease.set_next_sequence(sequence),
+        }
+    }
+}
+
+struct QueueWorker {
+    submitter: Arc<Submitter>,
+    channels: Option<ChannelAccountPool>,
+    own_lane: Lane,
+}
+
+impl QueueWorker {
+    async fn run(mut self, mut receiver: mpsc::UnboundedReceiver<Job>) {
+        let config = self.submitter.config.clone();
+        while let Some(first) = receiver.recv().await {
+            let mut jobs = vec![first];
+            let deadline = tokio::time::Instant::now() + config.batch_window;
+            while jobs.len() < config.max_batch_operations {
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(job)) => jobs.push(job),
+                    _ => break,
+                }
+            }
+
+            for batch in group_by_memo(jobs, config.max_batch_operations) {
+                match self.channels.as_ref().and_then(|c| c.try_lease()) {
+                    Some(lease) => {
+                        let submitter = self.submitter.clone();
+                        tokio::spawn(async move {
+                            submitter
+                                .submit_batch(&mut Lane::Channel(lease), batch)
+                                .await;
+                        });
+                    }
+                    None => self.submitter.submit_batch(&mut self.own_lane, batch).await,
+                }
+            }
+        }
+        debug!(account = %self.submitter.signer.account_id(), "submission queue stopped");
+    }
+}
+
+struct Submitter {
+    stellar_client: StellarClient,
+    signer: Arc<dyn TransactionSigner>,
+    config: SubmissionConfig,
+    asset_config: CngnAssetConfig,
+}
+
+impl Submitter {
+    fn source<'a>(&'a self, lane: &'a Lane) -> &'a str {
+        lane.channel()
+            .map(|lease| lease.account_id())
+            .unwrap_or_else(|| self.signer.account_id())
+    }
+
+    async fn submit_batch(&self, lane: &mut Lane, mut pending: Vec<Job>) {
+        let mut attempt = 0;
+        while !pending.is_empty() {
+            attempt += 1;
+            if attempt > self.config.max_attempts {
+                let error = StellarError::transaction_failed(format!(
+                    "payment not submitted after {} attempts",
+                    self.config.max_attempts
+                ));
+                fail_all(pending, &SubmissionError::rejected(error));
+                return;
+            }
+
+            let sequence = match self.reserve_sequence(lane).await {
+                Ok(sequence) => sequence,
+                Err(e) => {
+                    fail_all(pending, &SubmissionError::rejected(e));
+                    return;
+                }
+            };
+            let built = match self.build(lane, &pending, sequence).await {
+                Ok(built) => built,
+                Err(e) => {
+                    fail_all(pending, &SubmissionError::rejected(e));
+                    return;
+                }
+            };
+
+            let error = match self
+                .stellar_client
+                .submit_transaction_xdr(&built.envelope_xdr)
+                .await
+            {
+                Ok(_) => {
+                    lane.set_next_sequence(Some(sequence + 1));
+                    succeed_all(pending, &built, sequence);
+                    return;
+                }
+                Err(e) => e,
+            };
+
+            // Whether the sequence was consumed depends on the failure; Horizon knows
+            lane.set_next_sequence(None);
+            let codes = horizon_result_codes(&error);
+            match codes.as_ref().map(|c| c.transaction.as_str()) {
+                Some("tx_bad_seq") | Some("tx_too_late") => {
+                    debug!(
+                        account = %self.source(lane),
+                        sequence,
+                        error = %error,
+                        "resyncing sequence and resubmitting"
+                    );
+                }
+                Some("tx_failed") => {
+                    let operations = codes.map(|c| c.operations).unwrap_or_default();
+                    let (failed, rest) = split_failed_operations(pending, &operations);
+                    if failed.is_empty() {
+                        fail_all(rest, &SubmissionError::rejected(error));
+                        return;
+                    }
+                    for (job, code) in failed {
+                        warn!(destination = %job.payment.destination, code = %code, "payment operation rejected");
+                        let _ = job.reply.send(Err(SubmissionError::rejected(
+                            StellarError::transaction_failed(format!(
+                                "payment operation failed ({})",
+                                code
+                            )),
+                        )));
+                    }
+                    pending = rest;
+                }
+                _ if error.is_definitive_rejection() => {
+                    fail_all(pending, &SubmissionError::rejected(error));
+                    return;
+                }
+                _ => match self.resolve(&built).await {
+                    Outcome::Applied { successful: true } => {
+                        succeed_all(pending, &built, sequence);
+                        return;
+                    }
+                    Outcome::Applied { successful: false } => {
+                        let error = StellarError::transaction_failed(format!(
+                            "transaction {} failed on-ledger",
+                            built.hash
+                        ));
+                        fail_all(pending, &SubmissionError::rejected(error));
+                        return;
+                    }
+                    Outcome::Expired => {
+                        info!(
+                            tx_hash = %built.hash,
+                            "unconfirmed envelope expired, resubmitting"
+                        );
+                    }
+                    Outcome::Unknown => {
+                        error!(
+                            tx_hash = %built.hash,
+                            error = %error,
+                            "payment outcome unknown; check the hash on-chain"
+                        );
+                        let error = SubmissionError {
+                            error,
+                            in_flight_hash: Some(built.hash),
+                            in_flight_valid_until: Some(built.max_time),
+                        };
+                        fail_all(pending, &error);
+                        return;
+                    }
+                },
+            }
+        }
+    }
+
+    async fn reserve_sequence(&self, lane: &Lane) -> StellarResult<i64> {
+        if let Some(sequence) = lane.next_sequence() {
+            return Ok(sequence);
+        }
+        let account = self.stellar_client.get_account(self.source(lane)).await?;
+        Ok(account.sequence + 1)
+    }
+
+    async fn build(
+        &self,
+        lane: &Lane,
+        jobs: &[Job],
+        sequence: i64,
+    ) -> StellarResult<BuiltEnvelope> {
+        let issuer = self
+            .asset_config
+            .issuer_for_network(self.stellar_client.network());
+        let account = parse_muxed_account(self.signer.account_id())?;
+        // From a channel, the account stays the source of each operation
+        let operation_source = lane.channel().map(|_| account.clone());
+        let operations = jobs
+            .iter()
+            .map(|job| {
+                let amount = decimal_to_stroops(&job.payment.amount)?;
+                let body = match job.payment.asset {
+                    PaymentAsset::CreateAccount => OperationBody::CreateAccount(CreateAccountOp {
+                        destination: parse_account_id(&job.payment.destination)?,
+                        starting_balance: amount,
+                    }),
+                    asset => OperationBody::Payment(PaymentOp {
+                        destination: parse_muxed_account(&job.payment.destination)?,
+                        asset: match asset {
+                            PaymentAsset::Cngn => {
+                                build_asset(&self.asset_config.asset_code, issuer)?
+                            }
+                            _ => Asset::Native,
+                        },
+                        amount,
+                    }),
+                };
+                Ok(Operation {
+                    source_account: operation_source.clone(),
+                    body,
+                })
+            })
+            .collect::<StellarResult<Vec<_>>>()?;
+
+        let max_time = unix_time() + self.config.tx_timeout.as_secs();
+        let tx = Transaction {
+            source_account: parse_muxed_account(self.source(lane))?,
+            fee: self
+                .config
+                .base_fee_stroops
+                .saturating_mul(operations.len() as u32),
+            seq_num: SequenceNumber(sequence),
+            cond: Preconditions::Time(TimeBounds {
+                min_time: TimePoint(0),
+                max_time: TimePoint(max_time),
+            }),
+            memo: memo_to_xdr(&jobs[0].payment.memo)?,
+            operations: VecM::try_from(operations)
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+            ext: TransactionExt::V0,
+        };
+
+        let hash = tx
+            .hash(network_id(
+                self.stellar_client.network().network_passphrase(),
+            ))
+            .map_err(|e| StellarError::serialization_error(e.to_string()))?;
+        let mut signatures = self.signer.sign_hash(&hash).await?;
+        if let Some(lease) = lane.channel() {
+            signatures.extend(lease.signer().sign_hash(&hash).await?);
+        }
+        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::try_from(signatures)
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+        });
+        let envelope_xdr = envelope
+            .to_xdr_base64(Limits::none())
+            .map_err(|e| StellarError::serialization_error(e.to_string()))?;
+
+        Ok(BuiltEnvelope {
+            hash: hex::encode(hash),
+            envelope_xdr,
+            max_time,
+            channel_account: lane.channel().map(|lease| lease.account_id().to_string()),
+        })
+    }
+
+    /// Look the envelope up by hash until it shows up or can no longer apply
+    async fn resolve(&self, built: &BuiltEnvelope) -> Outcome {
+        loop {
+            let now = unix_time();
+            match self
+                .stellar_client
+                .get_transaction_by_hash(&built.hash)
+                .await
+            {
+                Ok(record) => {
+                    return Outcome::Applied {
+                        successful: record.successful,
+                    }
+                }
+                Err(e) if is_not_found(&e) => {
+                    if now > built.max_time + TIMEBOUNDS_GRACE_SECONDS {
+                        return Outcome::Expired;
+                    }
+                }
+                Err(e) => {
+                    if now > built.max_time + UNKNOWN_OUTCOME_GRACE_SECONDS {
+                        return Outcome::Unknown;
+                    }
+                    debug!(tx_hash = %built.hash, error = %e, "transaction lookup failed");
+                }
+            }
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+}
+
+/// Group jobs by memo, keeping arrival order, into batches of at most
+/// `max_operations`
+fn group_by_memo(jobs: Vec<Job>, max_operations: usize) -> Vec<Vec<Job>> {
+    let mut groups: Vec<Vec<Job>> = Vec::new();
+    for job in jobs {
+        match groups
+            .iter_mut()
+            .find(|g| g.len() < max_operations && g[0].payment.memo == job.payment.memo)
+        {
+            Some(group) => group.push(job),
+            None => groups.push(vec![job]),
+        }
+    }
+    groups
+}
+
+/// Split a `tx_failed` batch into the payments whose operation failed, with
+/// their result codes, and the rest
+fn split_failed_operations(
+    jobs: Vec<Job>,
+    operation_codes: &[String],
+) -> (Vec<(Job, String)>, Vec<Job>) {
+    let mut failed = Vec::new();
+    let mut rest = Vec::new();
+    for (index, job) in jobs.into_iter().enumerate() {
+        match operation_codes.get(index) {
+            Some(code) if code != "op_success" => failed.push((job, code.clone())),
+            _ => rest.push(job),
+        }
+    }
+    (failed, rest)
+}
+
+fn succeed_all(jobs: Vec<Job>, built: &BuiltEnvelope, sequence: i64) {
+    let operation_count = jobs.len();
+    for (operation_index, job) in jobs.into_iter().enumerate() {
+        let _ = job.reply.send(Ok(SubmittedPayment {
+            tx_hash: built.hash.clone(),
+            sequence,
+            operation_index,
+            operation_count,
+            channel_account: built.channel_account.clone(),
+            valid_until: built.max_time,
+        }));
+    }
+}
+
+fn fail_all(jobs: Vec<Job>, error: &SubmissionError) {
+    for job in jobs {
+        let _ = job.reply.send(Err(error.clone()));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ResultCodes {
+    transaction: String,
+    operations: Vec<String>,
+}
+
+/// `extras.result_codes` from a Horizon submission error body
+fn horizon_result_codes(error: &StellarError) -> Option<ResultCodes> {
+    let StellarError::TransactionFailed { message } = error else {
+        return None;
+    };
+    let body: serde_json::Value = serde_json::from_str(&message[message.find('{')?..]).ok()?;
+    let codes = body.get("extras")?.get("result_codes")?;
+    Some(ResultCodes {
+        transaction: codes.get("transaction")?.as_str()?.to_string(),
+        operations: codes
+            .get("operations")
+            .and_then(|ops| ops.as_array())
+            .map(|ops| {
+                ops.iter()
+                    .filter_map(|op| op.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+fn is_not_found(error: &StellarError) -> bool {
+    matches!(error, StellarError::TransactionFailed { message } if message.contains("transaction not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(memo: &str) -> (Job, oneshot::Receiver<SubmissionResult>) {
+        let (reply, receiver) = oneshot::channel();
+        let job = Job {
+            payment: QueuedPayment::cngn("GDEST", "1", CngnMemo::Text(memo.to_string())),
+            reply,
+        };
+        (job, receiver)
+    }
+
+    #[test]
+    fn test_group_by_memo_splits_memos_and_caps_batches() {
+        let jobs = ["a", "b", "a", "a", "b"]
+            .iter()
+            .map(|memo| job(memo).0)
+            .collect();
+        let groups = group_by_memo(jobs, 2);
+        let sizes: Vec<_> = groups.iter().map(|g| g.len()).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(groups[0][0].payment.memo, CngnMemo::Text("a".to_string()));
+        assert_eq!(groups[1][0].payment.memo, CngnMemo::Text("b".to_string()));
+        assert_eq!(groups[2][0].payment.memo, CngnMemo::Text("a".to_string()));
+    }
+
+    #[test]
+    fn test_horizon_result_codes_parsed_from_submit_error() {
+        let error = StellarError::transaction_failed(
+            r#"Horizon submit failed (status 400 Bad Request): {"status":400,"extras":{"result_codes":{"transaction":"tx_failed","operations":["op_success","op_no_trust"]}}}"#,
+        );
+        assert_eq!(
+            horizon_result_codes(&error),
+            Some(ResultCodes {
+                transaction: "tx_failed".to_string(),
+                operations: vec!["op_success".to_string(), "op_no_trust".to_string()],
+            })
+        );
+        assert_eq!(horizon_result_codes(&StellarError::timeout_error(15)), None);
+    }
+
+    #[test]
+    fn test_failed_operations_are_split_from_the_batch() {
+        let jobs = (0..3).map(|_| job("a").0).collect();
+        let codes = vec![
+            "op_success".to_string(),
+            "op_no_trust".to_string(),
+            "op_success".to_string(),
+        ];
+        let (failed, rest) = split_failed_operations(jobs, &codes);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].1, "op_no_trust");
+        assert_eq!(rest.len(), 2);
+    }
+}
@@ -4,7 +4,10 @@ mod tests {
     use crate::chains::stellar::{
         client::StellarClient,
         config::{StellarConfig, StellarNetwork},
-        types::{extract_asset_balance, is_valid_stellar_address, AssetBalance},
+        types::{
+            extract_asset_balance, is_valid_account_id, is_valid_muxed_address,
+            is_valid_stellar_address, muxed_address, AssetBalance, StellarAddress,
+        },
     };
     use std::time::Duration;
     use stellar_strkey::ed25519::PublicKey as StrkeyPublicKey;
@@ -78,6 +81,31 @@ mod tests {
         assert!(!is_valid_stellar_address(wrong_length));
     }
 
+    #[test]
+    fn test_muxed_address_round_trip() {
+        let muxed = muxed_address(TEST_ADDRESS, 1_234_567).unwrap();
+        assert!(muxed.starts_with('M'));
+        assert_eq!(muxed.len(), 69);
+        assert!(is_valid_stellar_address(&muxed));
+        assert!(is_valid_muxed_address(&muxed));
+        assert!(!is_valid_account_id(&muxed));
+
+        let parsed = StellarAddress::parse(&muxed).unwrap();
+        assert_eq!(parsed.account_id(), TEST_ADDRESS);
+        assert_eq!(parsed.muxed_id(), Some(1_234_567));
+        assert_eq!(parsed.as_str(), muxed);
+
+        let plain = StellarAddress::parse(TEST_ADDRESS).unwrap();
+        assert_eq!(plain.account_id(), TEST_ADDRESS);
+        assert_eq!(plain.muxed_id(), None);
+        assert!(!is_valid_muxed_address(TEST_ADDRESS));
+
+        let mut corrupted = muxed.clone();
+        corrupted.replace_range(10..11, if &muxed[10..11] == "A" { "B" } else { "A" });
+        assert!(!is_valid_stellar_address(&corrupted));
+        assert!(muxed_address("INVALID", 1).is_none());
+    }
+
     #[tokio::test]
     async fn test_stellar_client_creation() {
         let config = test_config();
@@ -1,15 +1,17 @@
 use crate::chains::stellar::client::StellarClient;
 use crate::chains::stellar::config::StellarNetwork;
 use crate::chains::stellar::errors::{StellarError, StellarResult};
-use crate::chains::stellar::types::{is_valid_stellar_address, AssetBalance};
+use crate::chains::stellar::payment::network_id;
+use crate::chains::stellar::signer::{signer_from_env, LocalSigner, TransactionSigner};
+use crate::chains::stellar::types::{is_valid_account_id, AssetBalance};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use std::sync::Arc;
 use stellar_strkey::ed25519::PublicKey as StrkeyPublicKey;
 use stellar_xdr::next::{
-    AccountId, AlphaNum12, AlphaNum4, AssetCode12, AssetCode4, ChangeTrustAsset, ChangeTrustOp,
-    Limits, MuxedAccount, Operation, OperationBody, Preconditions, PublicKey, SequenceNumber,
-    Transaction, TransactionEnvelope, TransactionExt, TransactionV1Envelope, Uint256, VecM,
-    WriteXdr,
+    AccountId, AlphaNum12, AlphaNum4, AssetCode12, AssetCode4, BeginSponsoringFutureReservesOp,
+    ChangeTrustAsset, ChangeTrustOp, Limits, MuxedAccount, Operation, OperationBody, Preconditions,
+    PublicKey, SequenceNumber, Transaction, TransactionEnvelope, TransactionExt,
+    TransactionV1Envelope, Uint256, VecM, WriteXdr,
 };
 
 const BASE_RESERVE_XLM: f64 = 0.5;
@@ -76,12 +78,47 @@ pub struct UnsignedTrustlineTransaction {
     pub transaction_hash: String,
     pub unsigned_envelope_xdr: String,
     pub limit: Option<String>,
+    /// Submit with `sponsor_fee` so the platform fee account pays via fee-bump
+    #[serde(default)]
+    pub fee_sponsored: bool,
+    /// Account paying the trustline reserve (CAP-33). The envelope already
+    /// carries its signature; the account only adds its own.
+    #[serde(default)]
+    pub reserve_sponsor: Option<String>,
+}
+
+/// Platform account that pays trustline base reserves on behalf of users
+#[derive(Debug, Clone)]
+pub struct ReserveSponsor {
+    signer: Arc<dyn TransactionSigner>,
+}
+
+impl ReserveSponsor {
+    pub fn new(signer: Arc<dyn TransactionSigner>) -> Self {
+        Self { signer }
+    }
+
+    pub fn from_secret(secret_seed: &str) -> StellarResult<Self> {
+        Ok(Self::new(Arc::new(LocalSigner::from_secret(secret_seed)?)))
+    }
+
+    /// Signer selected by `STELLAR_RESERVE_SPONSOR_SIGNER` (see
+    /// [`signer_from_env`]); None when reserve sponsorship is off
+    pub fn from_env() -> StellarResult<Option<Self>> {
+        Ok(signer_from_env("STELLAR_RESERVE_SPONSOR")?.map(Self::new))
+    }
+
+    pub fn account_id(&self) -> &str {
+        self.signer.account_id()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CngnTrustlineManager {
     stellar_client: StellarClient,
     config: CngnAssetConfig,
+    fee_sponsored: bool,
+    reserve_sponsor: Option<ReserveSponsor>,
 }
 
 impl CngnTrustlineManager {
@@ -89,6 +126,8 @@ impl CngnTrustlineManager {
         Self {
             stellar_client,
             config: CngnAssetConfig::from_env(),
+            fee_sponsored: false,
+            reserve_sponsor: None,
         }
     }
 
@@ -96,9 +135,25 @@ impl CngnTrustlineManager {
         Self {
             stellar_client,
             config,
+            fee_sponsored: false,
+            reserve_sponsor: None,
         }
     }
 
+    /// Preflight and build for fee-bump submission: the account still needs
+    /// the reserves, but not the XLM buffer for fees
+    pub fn with_fee_sponsorship(mut self, sponsored: bool) -> Self {
+        self.fee_sponsored = sponsored;
+        self
+    }
+
+    /// Build trustlines whose reserve is paid by `sponsor`, so the account
+    /// needs no XLM for the new subentry
+    pub fn with_reserve_sponsor(mut self, sponsor: ReserveSponsor) -> Self {
+        self.reserve_sponsor = Some(sponsor);
+        self
+    }
+
     pub fn asset_code(&self) -> &str {
         &self.config.asset_code
     }
@@ -109,7 +164,7 @@ impl CngnTrustlineManager {
     }
 
     pub async fn check_trustline(&self, account_id: &str) -> StellarResult<TrustlineStatus> {
-        if !is_valid_stellar_address(account_id) {
+        if !is_valid_account_id(account_id) {
             return Err(StellarError::invalid_address(account_id));
         }
 
@@ -145,7 +200,11 @@ impl CngnTrustlineManager {
     ) -> StellarResult<TrustlinePreflight> {
         let account = self.stellar_client.get_account(account_id).await?;
         let available_xlm = account_xlm_balance(&account.balances);
-        let required_xlm = required_xlm_for_trustline(account.subentry_count);
+        let required_xlm = required_xlm_with_sponsorship(
+            account.reserve_entries(),
+            self.reserve_sponsor.is_some(),
+            self.fee_sponsored,
+        );
         let can_create = available_xlm >= required_xlm;
 
         Ok(TrustlinePreflight {
@@ -171,7 +230,7 @@ impl CngnTrustlineManager {
         limit: Option<&str>,
         fee_stroops: Option<u32>,
     ) -> StellarResult<UnsignedTrustlineTransaction> {
-        if !is_valid_stellar_address(account_id) {
+        if !is_valid_account_id(account_id) {
             return Err(StellarError::invalid_address(account_id));
         }
 
@@ -191,9 +250,11 @@ impl CngnTrustlineManager {
             ));
         }
 
+        if let Some(sponsor) = self.reserve_sponsor.as_ref() {
+            self.ensure_sponsor_can_cover_reserve(sponsor).await?;
+        }
+
         let account = self.stellar_client.get_account(account_id).await?;
-        let fee = fee_stroops.unwrap_or(DEFAULT_BASE_FEE_STROOPS);
-        let sequence = account.sequence + 1;
         let selected_limit = limit
             .map(|v| v.to_string())
             .or_else(|| self.config.default_limit.clone());
@@ -202,42 +263,156 @@ impl CngnTrustlineManager {
             None => i64::MAX,
         };
 
-        let source = parse_muxed_account(account_id)?;
         let trustline_asset = build_change_trust_asset(self.asset_code(), self.issuer())?;
-        let op = Operation {
+        let change_trust = Operation {
             source_account: None,
             body: OperationBody::ChangeTrust(ChangeTrustOp {
                 line: trustline_asset,
                 limit: limit_i64,
             }),
         };
+        let operations = match self.reserve_sponsor.as_ref() {
+            Some(sponsor) => vec![
+                Operation {
+                    source_account: Some(parse_muxed_account(sponsor.account_id())?),
+                    body: OperationBody::BeginSponsoringFutureReserves(
+                        BeginSponsoringFutureReservesOp {
+                            sponsored_id: parse_account_id(account_id)?,
+                        },
+                    ),
+                },
+                change_trust,
+                Operation {
+                    source_account: None,
+                    body: OperationBody::EndSponsoringFutureReserves,
+                },
+            ],
+            None => vec![change_trust],
+        };
+
+        self.build_envelope(
+            account_id,
+            account.sequence + 1,
+            operations,
+            fee_stroops,
+            selected_limit,
+            self.reserve_sponsor.as_ref(),
+        )
+        .await
+    }
+
+    /// Remove the cNGN trustline (`ChangeTrust` with a zero limit). The
+    /// balance must be zero. A sponsored reserve goes back to its sponsor.
+    pub async fn build_close_trustline_transaction(
+        &self,
+        account_id: &str,
+        fee_stroops: Option<u32>,
+    ) -> StellarResult<UnsignedTrustlineTransaction> {
+        if !is_valid_account_id(account_id) {
+            return Err(StellarError::invalid_address(account_id));
+        }
+
+        let account = self.stellar_client.get_account(account_id).await?;
+        let Some(trustline) = find_trustline(&account.balances, self.asset_code(), self.issuer())
+        else {
+            return Err(StellarError::transaction_failed(format!(
+                "account {} has no {} trustline to close",
+                account_id,
+                self.asset_code()
+            )));
+        };
+        if decimal_to_int64_stroops(&trustline.balance)? != 0 {
+            return Err(StellarError::transaction_failed(format!(
+                "trustline balance must be zero before it can be closed (balance {})",
+                trustline.balance
+            )));
+        }
+
+        let op = Operation {
+            source_account: None,
+            body: OperationBody::ChangeTrust(ChangeTrustOp {
+                line: build_change_trust_asset(self.asset_code(), self.issuer())?,
+                limit: 0,
+            }),
+        };
+        self.build_envelope(
+            account_id,
+            account.sequence + 1,
+            vec![op],
+            fee_stroops,
+            Some("0".to_string()),
+            None,
+        )
+        .await
+    }
+
+    /// Sponsoring one more trustline locks another base reserve in the
+    /// sponsor account
+    async fn ensure_sponsor_can_cover_reserve(
+        &self,
+        sponsor: &ReserveSponsor,
+    ) -> StellarResult<()> {
+        let account = self
+            .stellar_client
+            .get_account(sponsor.account_id())
+            .await?;
+        let available = account_xlm_balance(&account.balances);
+        let required = (BASE_RESERVE_XLM * 2.0)
+            + (account.reserve_entries() as f64 * TRUSTLINE_RESERVE_XLM)
+            + TRUSTLINE_RESERVE_XLM;
+        if available >= required {
+            Ok(())
+        } else {
+            Err(StellarError::transaction_failed(format!(
+                "reserve sponsor {} cannot cover another trustline reserve (available {:.7} XLM, required {:.7} XLM)",
+                sponsor.account_id(),
+                available,
+                required
+            )))
+        }
+    }
+
+    /// Envelope sourced from `account_id`, paying `fee_stroops` (default base
+    /// fee) per operation and pre-signed by the reserve sponsor, if any
+    async fn build_envelope(
+        &self,
+        account_id: &str,
+        sequence: i64,
+        operations: Vec<Operation>,
+        fee_stroops: Option<u32>,
+        limit: Option<String>,
+        sponsor: Option<&ReserveSponsor>,
+    ) -> StellarResult<UnsignedTrustlineTransaction> {
+        let fee = fee_stroops
+            .unwrap_or(DEFAULT_BASE_FEE_STROOPS)
+            .saturating_mul(operations.len() as u32);
 
         let tx = Transaction {
-            source_account: source,
+            source_account: parse_muxed_account(account_id)?,
             fee,
             seq_num: SequenceNumber(sequence),
             cond: Preconditions::None,
             memo: stellar_xdr::next::Memo::None,
-            operations: VecM::try_from(vec![op])
+            operations: VecM::try_from(operations)
                 .map_err(|e| StellarError::serialization_error(e.to_string()))?,
             ext: TransactionExt::V0,
         };
 
-        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
-            tx: tx.clone(),
-            signatures: VecM::try_from(Vec::new())
-                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
-        });
-        let network_id: [u8; 32] = Sha256::digest(
-            self.stellar_client
-                .network()
-                .network_passphrase()
-                .as_bytes(),
-        )
-        .into();
+        let network_id = network_id(self.stellar_client.network().network_passphrase());
         let hash = tx
             .hash(network_id)
             .map_err(|e| StellarError::serialization_error(e.to_string()))?;
+
+        let signatures = match sponsor {
+            Some(sponsor) => sponsor.signer.sign_hash(&hash).await?,
+            None => Vec::new(),
+        };
+
+        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::try_from(signatures)
+                .map_err(|e| StellarError::serialization_error(e.to_string()))?,
+        });
         let xdr = envelope
             .to_xdr_base64(Limits::none())
             .map_err(|e| StellarError::serialization_error(e.to_string()))?;
@@ -250,7 +425,9 @@ impl CngnTrustlineManager {
             sequence,
             transaction_hash: hex::encode(hash),
             unsigned_envelope_xdr: xdr,
-            limit: selected_limit,
+            limit,
+            fee_sponsored: self.fee_sponsored,
+            reserve_sponsor: sponsor.map(|s| s.account_id().to_string()),
         })
     }
 
@@ -310,11 +487,22 @@ fn account_xlm_balance(balances: &[AssetBalance]) -> f64 {
         .unwrap_or(0.0)
 }
 
-fn required_xlm_for_trustline(current_subentries: u32) -> f64 {
-    (BASE_RESERVE_XLM * 2.0)
-        + (current_subentries as f64 * TRUSTLINE_RESERVE_XLM)
-        + TRUSTLINE_RESERVE_XLM
-        + RECOMMENDED_FEE_BUFFER_XLM
+/// XLM the account must hold itself. A sponsored reserve (CAP-33) is paid by
+/// the sponsor and a sponsored fee (CAP-15) by the platform fee account.
+fn required_xlm_with_sponsorship(
+    current_subentries: u32,
+    reserve_sponsored: bool,
+    fee_sponsored: bool,
+) -> f64 {
+    let mut required =
+        (BASE_RESERVE_XLM * 2.0) + (current_subentries as f64 * TRUSTLINE_RESERVE_XLM);
+    if !reserve_sponsored {
+        required += TRUSTLINE_RESERVE_XLM;
+    }
+    if !fee_sponsored {
+        required += RECOMMENDED_FEE_BUFFER_XLM;
+    }
+    required
 }
 
 fn parse_muxed_account(address: &str) -> StellarResult<MuxedAccount> {
@@ -416,8 +604,11 @@ mod tests {
 
     #[test]
     fn test_required_xlm_for_trustline() {
-        assert_eq!(required_xlm_for_trustline(0), 2.0);
-        assert_eq!(required_xlm_for_trustline(2), 3.0);
+        assert_eq!(required_xlm_with_sponsorship(0, false, false), 2.0);
+        assert_eq!(required_xlm_with_sponsorship(2, false, false), 3.0);
+        assert_eq!(required_xlm_with_sponsorship(0, false, true), 1.5);
+        assert_eq!(required_xlm_with_sponsorship(0, true, false), 1.5);
+        assert_eq!(required_xlm_with_sponsorship(2, true, true), 2.0);
     }
 
     #[test]
@@ -426,4 +617,57 @@ mod tests {
         assert_eq!(decimal_to_int64_stroops("1.5").unwrap(), 15_000_000);
         assert!(decimal_to_int64_stroops("1.12345678").is_err());
     }
+
+    #[tokio::test]
+    async fn test_sponsored_envelope_is_presigned_by_sponsor() {
+        use stellar_strkey::ed25519::PrivateKey as StrkeyPrivateKey;
+        use stellar_xdr::next::ReadXdr;
+
+        let sponsor =
+            ReserveSponsor::from_secret(StrkeyPrivateKey([9; 32]).to_string().as_str()).unwrap();
+        let manager = CngnTrustlineManager::with_config(
+            StellarClient::new(Default::default()).unwrap(),
+            CngnAssetConfig {
+                asset_code: "cNGN".to_string(),
+                issuer_testnet: sponsor.account_id().to_string(),
+                issuer_mainnet: sponsor.account_id().to_string(),
+                default_limit: None,
+            },
+        )
+        .with_reserve_sponsor(sponsor.clone());
+        let user = StrkeyPublicKey([3; 32]).to_string().as_str().to_owned();
+
+        let operations = vec![
+            Operation {
+                source_account: Some(parse_muxed_account(sponsor.account_id()).unwrap()),
+                body: OperationBody::BeginSponsoringFutureReserves(
+                    BeginSponsoringFutureReservesOp {
+                        sponsored_id: parse_account_id(&user).unwrap(),
+                    },
+                ),
+            },
+            Operation {
+                source_account: None,
+                body: OperationBody::EndSponsoringFutureReserves,
+            },
+        ];
+        let unsigned = manager
+            .build_envelope(&user, 42, operations, None, None, Some(&sponsor))
+            .await
+            .unwrap();
+
+        assert_eq!(unsigned.fee_stroops, 2 * DEFAULT_BASE_FEE_STROOPS);
+        assert_eq!(
+            unsigned.reserve_sponsor.as_deref(),
+            Some(sponsor.account_id())
+        );
+        let envelope =
+            TransactionEnvelope::from_xdr_base64(&unsigned.unsigned_envelope_xdr, Limits::none())
+                .unwrap();
+        let TransactionEnvelope::Tx(v1) = envelope else {
+            panic!("expected a v1 envelope");
+        };
+        assert_eq!(v1.signatures.len(), 1);
+        assert_eq!(v1.tx.seq_num, SequenceNumber(42));
+    }
 }
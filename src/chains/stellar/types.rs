@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use stellar_strkey::ed25519::PublicKey as StrkeyPublicKey;
+use stellar_strkey::ed25519::{MuxedAccount as StrkeyMuxedAccount, PublicKey as StrkeyPublicKey};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StellarAccountInfo {
@@ -14,6 +14,19 @@ pub struct StellarAccountInfo {
     pub data: HashMap<String, String>,
     pub last_modified_ledger: u32,
     pub created_at: String,
+    /// Reserves this account pays for entries owned by others (CAP-33)
+    #[serde(default)]
+    pub num_sponsoring: u32,
+    /// Reserves of this account's entries paid by a sponsor
+    #[serde(default)]
+    pub num_sponsored: u32,
+}
+
+impl StellarAccountInfo {
+    /// Entries whose base reserve this account pays, besides its own two
+    pub fn reserve_entries(&self) -> u32 {
+        (self.subentry_count + self.num_sponsoring).saturating_sub(self.num_sponsored)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +80,10 @@ pub struct HorizonAccount {
     pub data: HashMap<String, String>,
     pub last_modified_ledger: u64,
     pub created_at: Option<String>,
+    #[serde(default)]
+    pub num_sponsoring: u32,
+    #[serde(default)]
+    pub num_sponsored: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,6 +129,8 @@ impl From<HorizonAccount> for StellarAccountInfo {
             created_at: account
                 .created_at
                 .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+            num_sponsoring: account.num_sponsoring,
+            num_sponsored: account.num_sponsored,
         }
     }
 }
@@ -131,7 +150,66 @@ impl From<HorizonBalance> for AssetBalance {
     }
 }
 
+/// A payment address: a plain `G...` account, or a SEP-23 muxed `M...`
+/// address that routes to a numbered sub-account of one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StellarAddress {
+    Account(String),
+    Muxed {
+        address: String,
+        account_id: String,
+        id: u64,
+    },
+}
+
+impl StellarAddress {
+    pub fn parse(address: &str) -> Option<Self> {
+        if is_valid_account_id(address) {
+            return Some(Self::Account(address.to_string()));
+        }
+        if address.len() != 69 || !address.starts_with('M') {
+            return None;
+        }
+        let muxed = StrkeyMuxedAccount::from_string(address).ok()?;
+        Some(Self::Muxed {
+            address: address.to_string(),
+            account_id: StrkeyPublicKey(muxed.ed25519)
+                .to_string()
+                .as_str()
+                .to_owned(),
+            id: muxed.id,
+        })
+    }
+
+    /// The address as given
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Account(address) | Self::Muxed { address, .. } => address,
+        }
+    }
+
+    /// The underlying `G...` account that holds balances and trustlines
+    pub fn account_id(&self) -> &str {
+        match self {
+            Self::Account(account_id) | Self::Muxed { account_id, .. } => account_id,
+        }
+    }
+
+    pub fn muxed_id(&self) -> Option<u64> {
+        match self {
+            Self::Account(_) => None,
+            Self::Muxed { id, .. } => Some(*id),
+        }
+    }
+}
+
+/// `G...` account or SEP-23 `M...` muxed address
 pub fn is_valid_stellar_address(address: &str) -> bool {
+    StellarAddress::parse(address).is_some()
+}
+
+/// Plain `G...` account only. Horizon account endpoints and trustlines need one.
+pub fn is_valid_account_id(address: &str) -> bool {
     if address.len() != 56 || !address.starts_with('G') {
         return false;
     }
@@ -139,6 +217,27 @@ pub fn is_valid_stellar_address(address: &str) -> bool {
     StrkeyPublicKey::from_string(address).is_ok()
 }
 
+pub fn is_valid_muxed_address(address: &str) -> bool {
+    matches!(
+        StellarAddress::parse(address),
+        Some(StellarAddress::Muxed { .. })
+    )
+}
+
+/// Muxed `M...` address for sub-account `id` of `account_id`
+pub fn muxed_address(account_id: &str, id: u64) -> Option<String> {
+    let public_key = StrkeyPublicKey::from_string(account_id).ok()?;
+    Some(
+        StrkeyMuxedAccount {
+            ed25519: public_key.0,
+            id,
+        }
+        .to_string()
+        .as_str()
+        .to_owned(),
+    )
+}
+
 pub fn extract_asset_balance(
     balances: &[AssetBalance],
     asset_code: &str,
@@ -0,0 +1,148 @@
+use crate::database::error::DatabaseError;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// API client entity
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiClient {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// API key entity (hash only, never the key itself)
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Repository for API clients, their keys, and linked users
+pub struct ApiClientRepository {
+    pool: PgPool,
+}
+
+impl ApiClientRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Register a new API client
+    pub async fn create_client(&self, name: &str) -> Result<ApiClient, DatabaseError> {
+        sqlx::query_as::<_, ApiClient>(
+            r#"
+            INSERT INTO api_clients (name)
+            VALUES ($1)
+            RETURNING id, name, status, created_at, updated_at
+            "#,
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Store a new key for a client
+    pub async fn create_key(
+        &self,
+        client_id: Uuid,
+        key_prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<ApiKey, DatabaseError> {
+        sqlx::query_as::<_, ApiKey>(
+            r#"
+            INSERT INTO api_keys (client_id, key_prefix, key_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, client_id, key_prefix, key_hash, scopes, expires_at, revoked_at,
+                      last_used_at, created_at, updated_at
+            "#,
+        )
+        .bind(client_id)
+        .bind(key_prefix)
+        .bind(key_hash)
+        .bind(scopes)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Find a usable key by hash: not revoked, not expired, client active
+    pub async fn find_active_key_by_hash(
+        &self,
+        key_hash: &str,
+    ) -> Result<Option<ApiKey>, DatabaseError> {
+        sqlx::query_as::<_, ApiKey>(
+            r#"
+            SELECT k.id, k.client_id, k.key_prefix, k.key_hash, k.scopes, k.expires_at,
+                   k.revoked_at, k.last_used_at, k.created_at, k.updated_at
+            FROM api_keys k
+            JOIN api_clients c ON c.id = k.client_id
+            WHERE k.key_hash = $1
+              AND k.revoked_at IS NULL
+              AND (k.expires_at IS NULL OR k.expires_at > NOW())
+              AND c.status = 'active'
+            "#,
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Record key usage
+    pub async fn touch_key(&self, key_id: Uuid) -> Result<(), DatabaseError> {
+        sqlx::query("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1")
+            .bind(key_id)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Revoke a key
+    pub async fn revoke_key(&self, key_id: Uuid) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(key_id)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Link a user to the client that onboarded them
+    pub async fn link_user(&self, user_id: Uuid, client_id: Uuid) -> Result<(), DatabaseError> {
+        sqlx::query("UPDATE users SET api_client_id = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(client_id)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Client a user belongs to, if any
+    pub async fn find_client_for_user(&self, user_id: Uuid) -> Result<Option<Uuid>, DatabaseError> {
+        let row: Option<(Option<Uuid>,)> =
+            sqlx::query_as("SELECT api_client_id FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(DatabaseError::from_sqlx)?;
+        Ok(row.and_then(|(client_id,)| client_id))
+    }
+}
@@ -1,9 +1,23 @@
 use crate::database::error::{DatabaseError, DatabaseErrorKind};
 use crate::database::repository::{Repository, TransactionalRepository};
+use crate::workers::bill_processor::types::BillTransaction;
 use async_trait::async_trait;
-use sqlx::{FromRow, PgPool};
+use serde_json::Value as JsonValue;
+use sqlx::{FromRow, PgConnection, PgPool};
 use uuid::Uuid;
 
+/// Bill payment joined with its core transaction, as consumed by the bill processor.
+/// `amount` is the bill value in kobo.
+const BILL_PROCESSING_SELECT: &str = r#"
+    SELECT bp.id, bp.transaction_id, t.wallet_address, bp.bill_type,
+           bp.provider_name AS provider_code, bp.account_number,
+           (t.to_amount * 100)::BIGINT AS amount, bp.status, bp.provider_reference,
+           bp.token, bp.provider_response::TEXT AS provider_response, bp.retry_count,
+           bp.last_retry_at, bp.error_message, bp.refund_tx_hash, bp.created_at, bp.updated_at
+    FROM bill_payments bp
+    JOIN transactions t ON t.transaction_id = bp.transaction_id
+"#;
+
 /// Bill Payment entity extending a core transaction
 #[derive(Debug, Clone, FromRow)]
 pub struct BillPayment {
@@ -52,6 +66,34 @@ impl BillPaymentRepository {
         bill_type: &str,
         due_date: Option<chrono::DateTime<chrono::Utc>>,
         paid_with_afri: bool,
+    ) -> Result<BillPayment, DatabaseError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Self::create_bill_payment_in(
+            &mut conn,
+            transaction_id,
+            provider_name,
+            account_number,
+            bill_type,
+            due_date,
+            paid_with_afri,
+        )
+        .await
+    }
+
+    /// `create_bill_payment` on a caller's connection, so the bill is created
+    /// in the same database transaction as its core transaction
+    pub async fn create_bill_payment_in(
+        conn: &mut PgConnection,
+        transaction_id: Uuid,
+        provider_name: &str,
+        account_number: &str,
+        bill_type: &str,
+        due_date: Option<chrono::DateTime<chrono::Utc>>,
+        paid_with_afri: bool,
     ) -> Result<BillPayment, DatabaseError> {
         sqlx::query_as::<_, BillPayment>(
             "INSERT INTO bill_payments (transaction_id, provider_name, account_number, bill_type, due_date, paid_with_afri) 
@@ -64,10 +106,229 @@ impl BillPaymentRepository {
         .bind(bill_type)
         .bind(due_date)
         .bind(paid_with_afri)
-        .fetch_one(&self.pool)
+        .fetch_one(conn)
         .await
         .map_err(DatabaseError::from_sqlx)
     }
+
+    // -----------------------------------------------------------------------
+    // Bill processor state machine
+    // -----------------------------------------------------------------------
+
+    /// Bill payments in a processing state, oldest first
+    pub async fn find_by_processing_status(
+        &self,
+        status: &str,
+        limit: i64,
+    ) -> Result<Vec<BillTransaction>, DatabaseError> {
+        sqlx::query_as::<_, BillTransaction>(&format!(
+            "{} WHERE bp.status = $1 ORDER BY bp.created_at ASC LIMIT $2",
+            BILL_PROCESSING_SELECT
+        ))
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Bill payment for a core transaction, with its processing state
+    pub async fn find_processing_by_transaction_id(
+        &self,
+        transaction_id: Uuid,
+    ) -> Result<Option<BillTransaction>, DatabaseError> {
+        sqlx::query_as::<_, BillTransaction>(&format!(
+            "{} WHERE bp.transaction_id = $1",
+            BILL_PROCESSING_SELECT
+        ))
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Bill payments still awaiting payment whose cNGN deposit has been confirmed on Stellar
+    pub async fn find_funded_pending_payments(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<BillTransaction>, DatabaseError> {
+        sqlx::query_as::<_, BillTransaction>(&format!(
+            "{} WHERE bp.status = 'pending_payment' AND t.status = 'cngn_received' \
+             ORDER BY bp.created_at ASC LIMIT $1",
+            BILL_PROCESSING_SELECT
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Move a bill payment from `from` to `to`. Returns false if another worker got there first.
+    pub async fn transition_processing_status(
+        &self,
+        id: Uuid,
+        from: &str,
+        to: &str,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE bill_payments SET status = $3, updated_at = NOW() WHERE id = $1 AND status = $2",
+        )
+        .bind(id)
+        .bind(from)
+        .bind(to)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set the processing status unconditionally, recording an error message when given
+    pub async fn update_processing_status(
+        &self,
+        id: Uuid,
+        status: &str,
+        error_message: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE bill_payments
+             SET status = $2, error_message = COALESCE($3, error_message), updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(status)
+        .bind(error_message)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Record the outcome of account verification
+    pub async fn record_verification(
+        &self,
+        id: Uuid,
+        status: &str,
+        verified: bool,
+        verification_data: JsonValue,
+        error_message: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE bill_payments
+             SET status = $2, account_verified = $3, verification_data = $4,
+                 error_message = $5, updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(status)
+        .bind(verified)
+        .bind(verification_data)
+        .bind(error_message)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Record a payment accepted by a provider
+    pub async fn record_provider_submission(
+        &self,
+        id: Uuid,
+        status: &str,
+        provider_reference: &str,
+        token: Option<&str>,
+        provider_response: JsonValue,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE bill_payments
+             SET status = $2, provider_reference = $3, token = COALESCE($4, token),
+                 provider_response = $5, error_message = NULL, updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(status)
+        .bind(provider_reference)
+        .bind(token)
+        .bind(provider_response)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Mark the bill paid, storing the delivery token if the provider issued one
+    pub async fn mark_completed(&self, id: Uuid, token: Option<&str>) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE bill_payments
+             SET status = 'completed', token = COALESCE($2, token), error_message = NULL,
+                 updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(token)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Park a failed attempt until its backoff elapses
+    pub async fn schedule_retry(&self, id: Uuid, error_message: &str) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE bill_payments
+             SET status = 'retry_scheduled', error_message = $2, last_retry_at = NOW(),
+                 updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error_message)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Return a scheduled retry to `processing_bill` once `backoff_seconds` have passed.
+    /// Wraps `mark_bill_payment_ready_for_retry`.
+    pub async fn mark_ready_for_retry(
+        &self,
+        id: Uuid,
+        backoff_seconds: i32,
+    ) -> Result<bool, DatabaseError> {
+        sqlx::query_scalar::<_, bool>("SELECT mark_bill_payment_ready_for_retry($1, $2)")
+            .bind(id)
+            .bind(backoff_seconds)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Flag a bill payment for refund. Wraps `transition_bill_to_refund`.
+    pub async fn transition_to_refund(
+        &self,
+        id: Uuid,
+        reason: &str,
+    ) -> Result<bool, DatabaseError> {
+        sqlx::query_scalar::<_, bool>("SELECT transition_bill_to_refund($1, $2)")
+            .bind(id)
+            .bind(reason)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Record a completed refund. Wraps `mark_refund_processed`.
+    pub async fn mark_refund_processed(
+        &self,
+        id: Uuid,
+        refund_tx_hash: &str,
+    ) -> Result<bool, DatabaseError> {
+        sqlx::query_scalar::<_, bool>("SELECT mark_refund_processed($1, $2)")
+            .bind(id)
+            .bind(refund_tx_hash)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,132 @@
+use crate::database::error::DatabaseError;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Prefix for generated memos so support can tell them apart from user text
+const MEMO_PREFIX: &str = "AF";
+/// Random characters after the prefix (5 bits each)
+const MEMO_RANDOM_CHARS: usize = 10;
+/// Crockford base32: no I, L, O or U, so memos survive being read aloud or retyped
+const MEMO_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const MAX_ASSIGN_ATTEMPTS: usize = 3;
+
+/// How a user identifies their deposit: a text memo, or the system wallet's
+/// muxed sub-account `muxed_id` (SEP-23) which needs no memo at all
+#[derive(Debug, Clone, FromRow)]
+pub struct DepositReference {
+    pub memo: String,
+    pub muxed_id: i64,
+}
+
+/// Repository for the per-order deposit memos users attach to cNGN payments
+pub struct DepositMemoRepository {
+    pool: PgPool,
+}
+
+impl DepositMemoRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Memo and muxed ID for `transaction_id`, generating them on first use.
+    /// Calling it again for the same transaction returns the same reference.
+    pub async fn assign(&self, transaction_id: Uuid) -> Result<DepositReference, DatabaseError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let candidate = generate_deposit_memo(Uuid::new_v4().as_bytes());
+            let result = sqlx::query_as::<_, DepositReference>(
+                r#"
+                INSERT INTO deposit_memos (memo, transaction_id)
+                VALUES ($1, $2)
+                ON CONFLICT (transaction_id) DO UPDATE SET memo = deposit_memos.memo
+                RETURNING memo, muxed_id
+                "#,
+            )
+            .bind(&candidate)
+            .bind(transaction_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx);
+
+            match result {
+                // A memo collision is astronomically unlikely; draw again
+                Err(e) if e.is_constraint_violation() && attempt < MAX_ASSIGN_ATTEMPTS => continue,
+                other => return other,
+            }
+        }
+    }
+
+    pub async fn find_transaction_id(&self, memo: &str) -> Result<Option<Uuid>, DatabaseError> {
+        sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT transaction_id FROM deposit_memos WHERE memo = $1
+            "#,
+        )
+        .bind(normalize_deposit_memo(memo))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn find_transaction_id_by_muxed_id(
+        &self,
+        muxed_id: u64,
+    ) -> Result<Option<Uuid>, DatabaseError> {
+        // IDs are issued from a positive BIGINT sequence; anything larger is unknown
+        let Ok(muxed_id) = i64::try_from(muxed_id) else {
+            return Ok(None);
+        };
+        sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT transaction_id FROM deposit_memos WHERE muxed_id = $1
+            "#,
+        )
+        .bind(muxed_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+}
+
+/// Build a memo such as `AF7K2M9QX4TB` from random bytes
+pub fn generate_deposit_memo(random: &[u8]) -> String {
+    let mut memo = String::with_capacity(MEMO_PREFIX.len() + MEMO_RANDOM_CHARS);
+    memo.push_str(MEMO_PREFIX);
+    memo.extend(
+        random
+            .iter()
+            .cycle()
+            .take(MEMO_RANDOM_CHARS)
+            .map(|byte| MEMO_ALPHABET[(*byte & 0x1F) as usize] as char),
+    );
+    memo
+}
+
+/// Wallets and users mangle case and whitespace; memos are stored upper-case
+pub fn normalize_deposit_memo(memo: &str) -> String {
+    memo.trim().to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_memo_shape() {
+        let memo = generate_deposit_memo(Uuid::new_v4().as_bytes());
+        assert_eq!(memo.len(), MEMO_PREFIX.len() + MEMO_RANDOM_CHARS);
+        assert!(memo.len() <= 28);
+        assert!(memo.starts_with(MEMO_PREFIX));
+        assert!(memo[MEMO_PREFIX.len()..]
+            .bytes()
+            .all(|b| MEMO_ALPHABET.contains(&b)));
+
+        assert_eq!(generate_deposit_memo(&[0, 1, 31, 32]), "AF01Z001Z001");
+    }
+
+    #[test]
+    fn test_normalize_deposit_memo() {
+        assert_eq!(normalize_deposit_memo(" af7k2m9qx4tb\n"), "AF7K2M9QX4TB");
+    }
+}
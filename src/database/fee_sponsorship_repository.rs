@@ -0,0 +1,157 @@
+use crate::database::error::DatabaseError;
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Fee-bump envelope signed by the platform fee account
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct SponsoredTransaction {
+    pub id: Uuid,
+    pub fee_account: String,
+    pub inner_source: String,
+    pub inner_tx_hash: String,
+    pub fee_bump_tx_hash: String,
+    pub fee_stroops: i64,
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewSponsoredTransaction {
+    pub fee_account: String,
+    pub inner_source: String,
+    pub inner_tx_hash: String,
+    pub fee_bump_tx_hash: String,
+    pub fee_stroops: i64,
+}
+
+/// Repository for the daily fee sponsorship budget and the envelopes charged to it
+pub struct FeeSponsorshipRepository {
+    pool: PgPool,
+}
+
+impl FeeSponsorshipRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Reserve `fee_stroops` of the day's budget. Returns false, reserving
+    /// nothing, when that would take the day's spend above `budget_stroops`.
+    pub async fn reserve(
+        &self,
+        fee_account: &str,
+        day: NaiveDate,
+        fee_stroops: i64,
+        budget_stroops: i64,
+    ) -> Result<bool, DatabaseError> {
+        let reserved = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO fee_sponsorship_budgets (fee_account, day, spent_stroops, sponsored_count)
+            SELECT $1, $2, $3, 1
+            WHERE $3 <= $4
+            ON CONFLICT (fee_account, day) DO UPDATE
+            SET spent_stroops = fee_sponsorship_budgets.spent_stroops + EXCLUDED.spent_stroops,
+                sponsored_count = fee_sponsorship_budgets.sponsored_count + 1
+            WHERE fee_sponsorship_budgets.spent_stroops + EXCLUDED.spent_stroops <= $4
+            RETURNING spent_stroops
+            "#,
+        )
+        .bind(fee_account)
+        .bind(day)
+        .bind(fee_stroops)
+        .bind(budget_stroops)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(reserved.is_some())
+    }
+
+    /// Give back a reservation for an envelope the network rejected
+    pub async fn release(
+        &self,
+        fee_account: &str,
+        day: NaiveDate,
+        fee_stroops: i64,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE fee_sponsorship_budgets
+            SET spent_stroops = GREATEST(spent_stroops - $3, 0),
+                sponsored_count = GREATEST(sponsored_count - 1, 0)
+            WHERE fee_account = $1 AND day = $2
+            "#,
+        )
+        .bind(fee_account)
+        .bind(day)
+        .bind(fee_stroops)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Stroops reserved so far on `day`
+    pub async fn spent_on(&self, fee_account: &str, day: NaiveDate) -> Result<i64, DatabaseError> {
+        let spent = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT spent_stroops
+            FROM fee_sponsorship_budgets
+            WHERE fee_account = $1 AND day = $2
+            "#,
+        )
+        .bind(fee_account)
+        .bind(day)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(spent.unwrap_or(0))
+    }
+
+    pub async fn record(
+        &self,
+        tx: &NewSponsoredTransaction,
+    ) -> Result<SponsoredTransaction, DatabaseError> {
+        sqlx::query_as::<_, SponsoredTransaction>(
+            r#"
+            INSERT INTO sponsored_transactions
+                (fee_account, inner_source, inner_tx_hash, fee_bump_tx_hash, fee_stroops)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, fee_account, inner_source, inner_tx_hash, fee_bump_tx_hash,
+                      fee_stroops, status, error, created_at, updated_at
+            "#,
+        )
+        .bind(&tx.fee_account)
+        .bind(&tx.inner_source)
+        .bind(&tx.inner_tx_hash)
+        .bind(&tx.fee_bump_tx_hash)
+        .bind(tx.fee_stroops)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn update_status(
+        &self,
+        id: Uuid,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE sponsored_transactions
+            SET status = $2, error = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use crate::database::error::DatabaseError;
+use sqlx::{FromRow, PgPool};
+
+/// Idempotency record entity
+#[derive(Debug, Clone, FromRow)]
+pub struct IdempotencyRecord {
+    pub key: String,
+    pub scope: String,
+    pub payload: serde_json::Value,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Repository for durable idempotency records
+pub struct IdempotencyRepository {
+    pool: PgPool,
+}
+
+impl IdempotencyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Find an unexpired record by key
+    pub async fn find_active(&self, key: &str) -> Result<Option<IdempotencyRecord>, DatabaseError> {
+        sqlx::query_as::<_, IdempotencyRecord>(
+            r#"
+            SELECT key, scope, payload, expires_at, created_at, updated_at
+            FROM idempotency_keys
+            WHERE key = $1 AND expires_at > NOW()
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Insert a record unless an unexpired one already exists for the key.
+    ///
+    /// Expired rows are taken over in place. Returns `true` when this call
+    /// now owns the key.
+    pub async fn try_insert(
+        &self,
+        key: &str,
+        scope: &str,
+        payload: &serde_json::Value,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (key, scope, payload, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (key) DO UPDATE
+                SET scope = EXCLUDED.scope,
+                    payload = EXCLUDED.payload,
+                    expires_at = EXCLUDED.expires_at,
+                    created_at = NOW()
+                WHERE idempotency_keys.expires_at <= NOW()
+            "#,
+        )
+        .bind(key)
+        .bind(scope)
+        .bind(payload)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Insert or overwrite a record
+    pub async fn upsert(
+        &self,
+        key: &str,
+        scope: &str,
+        payload: &serde_json::Value,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (key, scope, payload, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (key) DO UPDATE
+                SET scope = EXCLUDED.scope,
+                    payload = EXCLUDED.payload,
+                    expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(key)
+        .bind(scope)
+        .bind(payload)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Delete a record so the key can be reused
+    pub async fn delete(&self, key: &str) -> Result<bool, DatabaseError> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE key = $1")
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove expired records
+    pub async fn delete_expired(&self) -> Result<u64, DatabaseError> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::database::error::DatabaseError;
+use sqlx::types::BigDecimal;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// What the monitor did with an incoming payment operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionOutcome {
+    Matched,
+    NotPending,
+    Unmatched,
+    NoMemo,
+    Underpaid,
+}
+
+impl IngestionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestionOutcome::Matched => "matched",
+            IngestionOutcome::NotPending => "not_pending",
+            IngestionOutcome::Unmatched => "unmatched",
+            IngestionOutcome::NoMemo => "no_memo",
+            IngestionOutcome::Underpaid => "underpaid",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestedPayment {
+    pub tx_hash: String,
+    pub op_index: i32,
+    pub account_address: String,
+    pub paging_token: String,
+    pub ledger: Option<i64>,
+    pub amount: Option<BigDecimal>,
+    pub asset_code: Option<String>,
+    pub memo: Option<String>,
+    pub transaction_id: Option<Uuid>,
+    pub outcome: IngestionOutcome,
+}
+
+/// Repository for the per-operation incoming payment ledger
+pub struct IncomingPaymentRepository {
+    pool: PgPool,
+}
+
+impl IncomingPaymentRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn is_ingested(&self, tx_hash: &str, op_index: i32) -> Result<bool, DatabaseError> {
+        sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM stellar_incoming_payments
+                WHERE tx_hash = $1 AND op_index = $2
+            )
+            "#,
+        )
+        .bind(tx_hash)
+        .bind(op_index)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Record an applied operation. Returns false when it was already recorded.
+    pub async fn record(&self, payment: &IngestedPayment) -> Result<bool, DatabaseError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Self::record_in(&mut conn, payment).await
+    }
+
+    /// `record` on a caller's connection, so the operation is marked applied
+    /// in the same database transaction that applies it
+    pub async fn record_in(
+        conn: &mut PgConnection,
+        payment: &IngestedPayment,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO stellar_incoming_payments
+                (tx_hash, op_index, account_address, paging_token, ledger, amount,
+                 asset_code, memo, transaction_id, outcome)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (tx_hash, op_index) DO NOTHING
+            "#,
+        )
+        .bind(&payment.tx_hash)
+        .bind(payment.op_index)
+        .bind(&payment.account_address)
+        .bind(&payment.paging_token)
+        .bind(payment.ledger)
+        .bind(&payment.amount)
+        .bind(&payment.asset_code)
+        .bind(&payment.memo)
+        .bind(payment.transaction_id)
+        .bind(payment.outcome.as_str())
+        .execute(conn)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,249 @@
+use crate::database::error::DatabaseError;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Verification state of a customer's submitted fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KycStatus {
+    NeedsInfo,
+    Processing,
+    Accepted,
+    Rejected,
+}
+
+impl KycStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycStatus::NeedsInfo => "needs_info",
+            KycStatus::Processing => "processing",
+            KycStatus::Accepted => "accepted",
+            KycStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "needs_info" => Some(KycStatus::NeedsInfo),
+            "processing" => Some(KycStatus::Processing),
+            "accepted" => Some(KycStatus::Accepted),
+            "rejected" => Some(KycStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct KycCustomer {
+    pub id: Uuid,
+    pub stellar_account: String,
+    pub memo: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub country_code: Option<String>,
+    pub status: String,
+    pub tier: i16,
+    pub requested_tier: Option<i16>,
+    pub provided_fields: Vec<String>,
+    pub fields_nonce: Option<Vec<u8>>,
+    pub fields_ciphertext: Option<Vec<u8>>,
+    pub provider: Option<String>,
+    pub provider_reference: Option<String>,
+    pub status_message: Option<String>,
+    pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl KycCustomer {
+    pub fn kyc_status(&self) -> KycStatus {
+        KycStatus::parse(&self.status).unwrap_or(KycStatus::NeedsInfo)
+    }
+}
+
+/// Newly encrypted fields and the status they leave the customer in
+#[derive(Debug, Clone)]
+pub struct CustomerFieldsUpdate<'a> {
+    pub country_code: Option<&'a str>,
+    pub provided_fields: &'a [String],
+    pub fields_nonce: &'a [u8],
+    pub fields_ciphertext: &'a [u8],
+    pub status: KycStatus,
+    pub tier: i16,
+    pub requested_tier: Option<i16>,
+    pub status_message: Option<&'a str>,
+}
+
+/// Outcome of a verification run against one version of the fields
+#[derive(Debug, Clone)]
+pub struct VerificationResult<'a> {
+    pub status: KycStatus,
+    pub tier: i16,
+    pub provider: &'a str,
+    pub provider_reference: Option<&'a str>,
+    pub status_message: Option<&'a str>,
+}
+
+const COLUMNS: &str = "id, stellar_account, memo, user_id, country_code, status, tier, \
+     requested_tier, provided_fields, fields_nonce, fields_ciphertext, provider, \
+     provider_reference, status_message, verified_at, created_at, updated_at";
+
+/// Repository for SEP-12 customers. Field values are only ever stored
+/// encrypted; this layer never sees them in the clear.
+pub struct KycCustomerRepository {
+    pool: PgPool,
+}
+
+impl KycCustomerRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find(&self, id: Uuid) -> Result<Option<KycCustomer>, DatabaseError> {
+        sqlx::query_as::<_, KycCustomer>(&format!(
+            "SELECT {COLUMNS} FROM kyc_customers WHERE id = $1"
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn find_by_account(
+        &self,
+        stellar_account: &str,
+        memo: Option<&str>,
+    ) -> Result<Option<KycCustomer>, DatabaseError> {
+        sqlx::query_as::<_, KycCustomer>(&format!(
+            r#"
+            SELECT {COLUMNS}
+            FROM kyc_customers
+            WHERE stellar_account = $1 AND COALESCE(memo, '') = COALESCE($2, '')
+            "#
+        ))
+        .bind(stellar_account)
+        .bind(memo)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Fetch the customer for an account and memo, creating an empty one
+    /// (linked to the user owning the wallet, if any) on first use
+    pub async fn find_or_create(
+        &self,
+        stellar_account: &str,
+        memo: Option<&str>,
+    ) -> Result<KycCustomer, DatabaseError> {
+        sqlx::query_as::<_, KycCustomer>(&format!(
+            r#"
+            INSERT INTO kyc_customers (stellar_account, memo, user_id)
+            VALUES ($1, $2, (SELECT user_id FROM wallets WHERE wallet_address = $1))
+            ON CONFLICT (stellar_account, (COALESCE(memo, '')))
+                DO UPDATE SET stellar_account = EXCLUDED.stellar_account
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(stellar_account)
+        .bind(memo)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn update_fields(
+        &self,
+        id: Uuid,
+        update: &CustomerFieldsUpdate<'_>,
+    ) -> Result<Option<KycCustomer>, DatabaseError> {
+        sqlx::query_as::<_, KycCustomer>(&format!(
+            r#"
+            UPDATE kyc_customers
+            SET country_code = $2,
+                provided_fields = $3,
+                fields_nonce = $4,
+                fields_ciphertext = $5,
+                status = $6,
+                tier = $7,
+                requested_tier = $8,
+                status_message = $9
+            WHERE id = $1
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(update.country_code)
+        .bind(update.provided_fields)
+        .bind(update.fields_nonce)
+        .bind(update.fields_ciphertext)
+        .bind(update.status.as_str())
+        .bind(update.tier)
+        .bind(update.requested_tier)
+        .bind(update.status_message)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Record a verification outcome. Returns `None` when the fields were
+    /// replaced while the provider was checking them (the nonce changes on
+    /// every write), so a stale result never applies to newer data.
+    pub async fn record_verification(
+        &self,
+        id: Uuid,
+        fields_nonce: &[u8],
+        result: &VerificationResult<'_>,
+    ) -> Result<Option<KycCustomer>, DatabaseError> {
+        sqlx::query_as::<_, KycCustomer>(&format!(
+            r#"
+            UPDATE kyc_customers
+            SET status = $3,
+                tier = $4,
+                requested_tier = NULL,
+                provider = $5,
+                provider_reference = $6,
+                status_message = $7,
+                verified_at = CASE WHEN $3 = 'accepted' THEN now() ELSE verified_at END
+            WHERE id = $1 AND fields_nonce = $2 AND status = 'processing'
+            RETURNING {COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(fields_nonce)
+        .bind(result.status.as_str())
+        .bind(result.tier)
+        .bind(result.provider)
+        .bind(result.provider_reference)
+        .bind(result.status_message)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Verified tier of the account holder (no memo); 0 when unknown
+    pub async fn tier_for_account(&self, stellar_account: &str) -> Result<i16, DatabaseError> {
+        let tier: Option<i16> = sqlx::query_scalar(
+            "SELECT tier FROM kyc_customers WHERE stellar_account = $1 AND memo IS NULL",
+        )
+        .bind(stellar_account)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(tier.unwrap_or(0))
+    }
+
+    pub async fn delete(
+        &self,
+        stellar_account: &str,
+        memo: Option<&str>,
+    ) -> Result<u64, DatabaseError> {
+        let result = sqlx::query(
+            "DELETE FROM kyc_customers
+             WHERE stellar_account = $1 AND COALESCE(memo, '') = COALESCE($2, '')",
+        )
+        .bind(stellar_account)
+        .bind(memo)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,362 @@
+use crate::database::error::DatabaseError;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Partner-registered webhook endpoint
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl WebhookEndpoint {
+    /// Whether the endpoint wants this event type (no filter means everything)
+    pub fn subscribes_to(&self, event_type: &str) -> bool {
+        self.event_types.is_empty() || self.event_types.iter().any(|t| t == event_type)
+    }
+}
+
+/// Outgoing delivery of one event to one endpoint
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Option<Uuid>,
+    pub outbound_event_id: Option<Uuid>,
+    pub event_type: Option<String>,
+    pub payload: Option<serde_json::Value>,
+    pub transaction_id: Option<Uuid>,
+    pub url: String,
+    pub status: String,
+    pub response_code: Option<i32>,
+    pub response_body: Option<String>,
+    pub retry_count: i32,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A delivery claimed by the dispatcher, with the secret to sign it
+#[derive(Debug, Clone, FromRow)]
+pub struct DueDelivery {
+    #[sqlx(flatten)]
+    pub delivery: WebhookDelivery,
+    pub secret: String,
+}
+
+const ENDPOINT_COLUMNS: &str =
+    "id, client_id, url, secret, event_types, description, is_active, created_at, updated_at";
+
+const DELIVERY_COLUMNS: &str = "id, endpoint_id, outbound_event_id, event_type, payload, \
+     transaction_id, url, status, response_code, response_body, retry_count, next_attempt_at, \
+     last_error, delivered_at, created_at, updated_at";
+
+/// Repository for merchant webhook endpoints and their deliveries
+pub struct MerchantWebhookRepository {
+    pool: PgPool,
+}
+
+impl MerchantWebhookRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Register an endpoint for a client
+    pub async fn create_endpoint(
+        &self,
+        client_id: Uuid,
+        url: &str,
+        secret: &str,
+        event_types: &[String],
+        description: Option<&str>,
+    ) -> Result<WebhookEndpoint, DatabaseError> {
+        sqlx::query_as::<_, WebhookEndpoint>(&format!(
+            r#"
+            INSERT INTO webhook_endpoints (client_id, url, secret, event_types, description)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {ENDPOINT_COLUMNS}
+            "#
+        ))
+        .bind(client_id)
+        .bind(url)
+        .bind(secret)
+        .bind(event_types)
+        .bind(description)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Active endpoints belonging to a client
+    pub async fn list_endpoints(
+        &self,
+        client_id: Uuid,
+    ) -> Result<Vec<WebhookEndpoint>, DatabaseError> {
+        sqlx::query_as::<_, WebhookEndpoint>(&format!(
+            r#"
+            SELECT {ENDPOINT_COLUMNS}
+            FROM webhook_endpoints
+            WHERE client_id = $1 AND is_active
+            ORDER BY created_at
+            "#
+        ))
+        .bind(client_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Stop delivering to an endpoint. Returns false if it wasn't the client's or was already off.
+    pub async fn deactivate_endpoint(
+        &self,
+        client_id: Uuid,
+        endpoint_id: Uuid,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE webhook_endpoints SET is_active = FALSE WHERE id = $1 AND client_id = $2 AND is_active",
+        )
+        .bind(endpoint_id)
+        .bind(client_id)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// API client that onboarded the owner of a wallet
+    pub async fn find_client_for_wallet(
+        &self,
+        wallet_address: &str,
+    ) -> Result<Option<Uuid>, DatabaseError> {
+        sqlx::query_scalar::<_, Option<Uuid>>(
+            r#"
+            SELECT u.api_client_id
+            FROM wallets w
+            JOIN users u ON u.id = w.user_id
+            WHERE w.wallet_address = $1
+            "#,
+        )
+        .bind(wallet_address)
+        .fetch_optional(&self.pool)
+        .await
+        .map(Option::flatten)
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Active endpoints of a client subscribed to an event type
+    pub async fn subscribed_endpoints(
+        &self,
+        client_id: Uuid,
+        event_type: &str,
+    ) -> Result<Vec<WebhookEndpoint>, DatabaseError> {
+        sqlx::query_as::<_, WebhookEndpoint>(&format!(
+            r#"
+            SELECT {ENDPOINT_COLUMNS}
+            FROM webhook_endpoints
+            WHERE client_id = $1
+              AND is_active
+              AND (cardinality(event_types) = 0 OR $2 = ANY(event_types))
+            "#
+        ))
+        .bind(client_id)
+        .bind(event_type)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Queue an event for delivery to an endpoint
+    pub async fn enqueue_delivery(
+        &self,
+        endpoint: &WebhookEndpoint,
+        outbound_event_id: Uuid,
+        event_type: &str,
+        payload: &serde_json::Value,
+        transaction_id: Option<Uuid>,
+    ) -> Result<WebhookDelivery, DatabaseError> {
+        sqlx::query_as::<_, WebhookDelivery>(&format!(
+            r#"
+            INSERT INTO webhook_deliveries
+                (endpoint_id, outbound_event_id, event_type, payload, transaction_id, url)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING {DELIVERY_COLUMNS}
+            "#
+        ))
+        .bind(endpoint.id)
+        .bind(outbound_event_id)
+        .bind(event_type)
+        .bind(payload)
+        .bind(transaction_id)
+        .bind(&endpoint.url)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Claim due deliveries to active endpoints.
+    ///
+    /// Claimed rows have `next_attempt_at` pushed out by `lease_secs` so other
+    /// dispatcher instances skip them while the attempt is in flight.
+    pub async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: i64,
+    ) -> Result<Vec<DueDelivery>, DatabaseError> {
+        sqlx::query_as::<_, DueDelivery>(
+            r#"
+            UPDATE webhook_deliveries d
+            SET next_attempt_at = NOW() + make_interval(secs => $2)
+            FROM webhook_endpoints e
+            WHERE e.id = d.endpoint_id
+              AND d.id IN (
+                  SELECT dd.id
+                  FROM webhook_deliveries dd
+                  JOIN webhook_endpoints ee ON ee.id = dd.endpoint_id
+                  WHERE dd.status = 'pending'
+                    AND dd.next_attempt_at <= NOW()
+                    AND ee.is_active
+                  ORDER BY dd.next_attempt_at
+                  LIMIT $1
+                  FOR UPDATE OF dd SKIP LOCKED
+              )
+            RETURNING d.id, d.endpoint_id, d.outbound_event_id, d.event_type, d.payload,
+                      d.transaction_id, d.url, d.status, d.response_code, d.response_body,
+                      d.retry_count, d.next_attempt_at, d.last_error, d.delivered_at,
+                      d.created_at, d.updated_at, e.secret
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Record a successful attempt
+    pub async fn mark_delivered(
+        &self,
+        delivery_id: Uuid,
+        response_code: i32,
+        response_body: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivered',
+                response_code = $2,
+                response_body = $3,
+                last_error = NULL,
+                delivered_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(response_code)
+        .bind(response_body)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Record a failed attempt; with no `retry_at` the delivery is given up on
+    pub async fn mark_attempt_failed(
+        &self,
+        delivery_id: Uuid,
+        response_code: Option<i32>,
+        response_body: Option<&str>,
+        error: &str,
+        retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN $5::timestamptz IS NULL THEN 'failed' ELSE 'pending' END,
+                response_code = $2,
+                response_body = $3,
+                last_error = $4,
+                retry_count = retry_count + 1,
+                next_attempt_at = COALESCE($5, next_attempt_at)
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(response_code)
+        .bind(response_body)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Deliveries to a client's endpoints, newest first
+    pub async fn list_deliveries(
+        &self,
+        client_id: Uuid,
+        endpoint_id: Option<Uuid>,
+        status: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT d.id, d.endpoint_id, d.outbound_event_id, d.event_type, d.payload,
+                   d.transaction_id, d.url, d.status, d.response_code, d.response_body,
+                   d.retry_count, d.next_attempt_at, d.last_error, d.delivered_at,
+                   d.created_at, d.updated_at
+            FROM webhook_deliveries d
+            JOIN webhook_endpoints e ON e.id = d.endpoint_id
+            WHERE e.client_id = $1
+              AND ($2::uuid IS NULL OR d.endpoint_id = $2)
+              AND ($3::text IS NULL OR d.status = $3)
+            ORDER BY d.created_at DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(client_id)
+        .bind(endpoint_id)
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Queue a fresh delivery of an earlier one's event to the same, still active, endpoint.
+    ///
+    /// The original row is left untouched so its attempt history is preserved.
+    pub async fn replay_delivery(
+        &self,
+        client_id: Uuid,
+        delivery_id: Uuid,
+    ) -> Result<Option<WebhookDelivery>, DatabaseError> {
+        sqlx::query_as::<_, WebhookDelivery>(&format!(
+            r#"
+            INSERT INTO webhook_deliveries
+                (endpoint_id, outbound_event_id, event_type, payload, transaction_id, url)
+            SELECT d.endpoint_id, d.outbound_event_id, d.event_type, d.payload,
+                   d.transaction_id, e.url
+            FROM webhook_deliveries d
+            JOIN webhook_endpoints e ON e.id = d.endpoint_id
+            WHERE d.id = $1 AND e.client_id = $2 AND e.is_active
+            RETURNING {DELIVERY_COLUMNS}
+            "#
+        ))
+        .bind(delivery_id)
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+}
@@ -1,19 +1,30 @@
 // This module requires std library (not available in WASM)
 
+pub mod api_client_repository;
 pub mod bill_payment_repository;
 pub mod conversion_audit_repository;
+pub mod deposit_memo_repository;
 pub mod error;
 pub mod exchange_rate_repository;
+pub mod fee_sponsorship_repository;
 pub mod fee_structure_repository;
+pub mod idempotency_repository;
+pub mod incoming_payment_repository;
+pub mod kyc_customer_repository;
+pub mod merchant_webhook_repository;
+pub mod notification_repository;
 pub mod onramp_quote_repository;
 pub mod payment_method_repository;
 pub mod payment_repository;
 pub mod provider_config_repository;
 pub mod repository;
+pub mod stellar_cursor_repository;
 pub mod transaction;
 pub mod transaction_repository;
+pub mod treasury_repository;
 pub mod trustline_operation_repository;
 pub mod trustline_repository;
+pub mod unmatched_deposit_repository;
 pub mod wallet_repository;
 pub mod webhook_repository;
 
@@ -0,0 +1,297 @@
+use crate::database::error::DatabaseError;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Channels a user can opt out of, and notification types they muted
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct NotificationPreferences {
+    pub user_id: Uuid,
+    pub email_enabled: bool,
+    pub sms_enabled: bool,
+    pub webhook_enabled: bool,
+    pub muted_types: Vec<String>,
+}
+
+impl NotificationPreferences {
+    /// Preferences of a user who never changed them
+    pub fn defaults(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            email_enabled: true,
+            sms_enabled: true,
+            webhook_enabled: true,
+            muted_types: Vec::new(),
+        }
+    }
+
+    /// Whether `notification_type` may be sent to the user over `channel`
+    pub fn allows(&self, channel: &str, notification_type: &str) -> bool {
+        if self.muted_types.iter().any(|t| t == notification_type) {
+            return false;
+        }
+        match channel {
+            "email" => self.email_enabled,
+            "sms" => self.sms_enabled,
+            "webhook" => self.webhook_enabled,
+            _ => false,
+        }
+    }
+}
+
+/// Contact details and preferences of the user owning a wallet
+#[derive(Debug, Clone, FromRow)]
+pub struct NotificationRecipient {
+    pub email: String,
+    pub phone: Option<String>,
+    #[sqlx(flatten)]
+    pub preferences: NotificationPreferences,
+}
+
+/// Rendered notification queued for one channel
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct OutboxMessage {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub transaction_id: Option<Uuid>,
+    pub notification_type: String,
+    pub channel: String,
+    pub recipient: String,
+    pub subject: Option<String>,
+    pub body: String,
+    pub payload: Option<serde_json::Value>,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+    pub sent_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Fields of an outbox row supplied by the caller
+#[derive(Debug, Clone)]
+pub struct NewOutboxMessage {
+    pub user_id: Option<Uuid>,
+    pub transaction_id: Option<Uuid>,
+    pub notification_type: String,
+    pub channel: String,
+    pub recipient: String,
+    pub subject: Option<String>,
+    pub body: String,
+    pub payload: Option<serde_json::Value>,
+}
+
+const OUTBOX_COLUMNS: &str = "id, user_id, transaction_id, notification_type, channel, recipient, \
+     subject, body, payload, status, attempts, next_attempt_at, last_error, sent_at, created_at, \
+     updated_at";
+
+/// Repository for notification preferences and the notification outbox
+pub struct NotificationRepository {
+    pool: PgPool,
+}
+
+impl NotificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stored preferences of a user, if they ever changed them
+    pub async fn get_preferences(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<NotificationPreferences>, DatabaseError> {
+        sqlx::query_as::<_, NotificationPreferences>(
+            r#"
+            SELECT user_id, email_enabled, sms_enabled, webhook_enabled, muted_types
+            FROM notification_preferences
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn upsert_preferences(
+        &self,
+        preferences: &NotificationPreferences,
+    ) -> Result<NotificationPreferences, DatabaseError> {
+        sqlx::query_as::<_, NotificationPreferences>(
+            r#"
+            INSERT INTO notification_preferences
+                (user_id, email_enabled, sms_enabled, webhook_enabled, muted_types)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE
+            SET email_enabled = EXCLUDED.email_enabled,
+                sms_enabled = EXCLUDED.sms_enabled,
+                webhook_enabled = EXCLUDED.webhook_enabled,
+                muted_types = EXCLUDED.muted_types
+            RETURNING user_id, email_enabled, sms_enabled, webhook_enabled, muted_types
+            "#,
+        )
+        .bind(preferences.user_id)
+        .bind(preferences.email_enabled)
+        .bind(preferences.sms_enabled)
+        .bind(preferences.webhook_enabled)
+        .bind(&preferences.muted_types)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Owner of a wallet with their preferences (defaults when none are stored)
+    pub async fn find_recipient_for_wallet(
+        &self,
+        wallet_address: &str,
+    ) -> Result<Option<NotificationRecipient>, DatabaseError> {
+        sqlx::query_as::<_, NotificationRecipient>(
+            r#"
+            SELECT u.id AS user_id,
+                   u.email,
+                   u.phone,
+                   COALESCE(p.email_enabled, TRUE) AS email_enabled,
+                   COALESCE(p.sms_enabled, TRUE) AS sms_enabled,
+                   COALESCE(p.webhook_enabled, TRUE) AS webhook_enabled,
+                   COALESCE(p.muted_types, '{}') AS muted_types
+            FROM wallets w
+            JOIN users u ON u.id = w.user_id
+            LEFT JOIN notification_preferences p ON p.user_id = u.id
+            WHERE w.wallet_address = $1
+            "#,
+        )
+        .bind(wallet_address)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Queue a message. Returns `None` when the same notification about the
+    /// transaction was already queued for the channel.
+    pub async fn enqueue(
+        &self,
+        message: &NewOutboxMessage,
+    ) -> Result<Option<OutboxMessage>, DatabaseError> {
+        sqlx::query_as::<_, OutboxMessage>(&format!(
+            r#"
+            INSERT INTO notification_outbox
+                (user_id, transaction_id, notification_type, channel, recipient, subject, body, payload)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (transaction_id, notification_type, channel)
+                WHERE transaction_id IS NOT NULL
+                DO NOTHING
+            RETURNING {OUTBOX_COLUMNS}
+            "#
+        ))
+        .bind(message.user_id)
+        .bind(message.transaction_id)
+        .bind(&message.notification_type)
+        .bind(&message.channel)
+        .bind(&message.recipient)
+        .bind(&message.subject)
+        .bind(&message.body)
+        .bind(&message.payload)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Claim due messages for the given channels.
+    ///
+    /// Claimed rows have `next_attempt_at` pushed out by `lease_secs` so other
+    /// dispatcher instances skip them while the send is in flight.
+    pub async fn claim_due(
+        &self,
+        channels: &[String],
+        limit: i64,
+        lease_secs: i64,
+    ) -> Result<Vec<OutboxMessage>, DatabaseError> {
+        sqlx::query_as::<_, OutboxMessage>(&format!(
+            r#"
+            UPDATE notification_outbox
+            SET next_attempt_at = NOW() + make_interval(secs => $3)
+            WHERE id IN (
+                SELECT id
+                FROM notification_outbox
+                WHERE status = 'pending'
+                  AND next_attempt_at <= NOW()
+                  AND channel = ANY($1)
+                ORDER BY next_attempt_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {OUTBOX_COLUMNS}
+            "#
+        ))
+        .bind(channels)
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn mark_sent(&self, id: Uuid) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE notification_outbox
+            SET status = 'sent',
+                attempts = attempts + 1,
+                last_error = NULL,
+                sent_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Record a failed send; with no `retry_at` the message is given up on
+    pub async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: &str,
+        retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE notification_outbox
+            SET status = CASE WHEN $3::timestamptz IS NULL THEN 'failed' ELSE 'pending' END,
+                attempts = attempts + 1,
+                last_error = $2,
+                next_attempt_at = COALESCE($3, next_attempt_at)
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferences_allow_by_channel_and_type() {
+        let mut prefs = NotificationPreferences::defaults(Uuid::new_v4());
+        assert!(prefs.allows("email", "offramp_completed"));
+        assert!(prefs.allows("sms", "offramp_completed"));
+        assert!(!prefs.allows("push", "offramp_completed"));
+
+        prefs.sms_enabled = false;
+        prefs.muted_types = vec!["cngn_received".to_string()];
+        assert!(!prefs.allows("sms", "offramp_completed"));
+        assert!(!prefs.allows("email", "cngn_received"));
+        assert!(prefs.allows("email", "offramp_completed"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::database::error::DatabaseError;
-use sqlx::{FromRow, PgPool};
+use sqlx::{FromRow, PgConnection, PgPool};
 use uuid::Uuid;
 
 /// Onramp quote entity
@@ -16,8 +16,39 @@ pub struct OnrampQuote {
     pub expires_at: chrono::DateTime<chrono::Utc>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// `onramp` (NGN → cNGN) or `offramp` (cNGN → NGN)
+    pub direction: String,
+    /// Account a firm quote was issued to
+    pub wallet_address: Option<String>,
+    /// Flow a firm quote may be used in, e.g. `sep24`
+    pub context: Option<String>,
+    pub sell_amount: Option<sqlx::types::BigDecimal>,
+    pub buy_amount: Option<sqlx::types::BigDecimal>,
+    /// Total fee, in the asset being bought
+    pub fee_total: Option<sqlx::types::BigDecimal>,
 }
 
+/// A firm quote issued to an account
+#[derive(Debug, Clone)]
+pub struct NewFirmQuote<'a> {
+    pub direction: &'a str,
+    pub wallet_address: &'a str,
+    pub context: &'a str,
+    pub amount_ngn: sqlx::types::BigDecimal,
+    pub exchange_rate: sqlx::types::BigDecimal,
+    pub gross_cngn: sqlx::types::BigDecimal,
+    pub fee_cngn: sqlx::types::BigDecimal,
+    pub net_cngn: sqlx::types::BigDecimal,
+    pub sell_amount: sqlx::types::BigDecimal,
+    pub buy_amount: sqlx::types::BigDecimal,
+    pub fee_total: sqlx::types::BigDecimal,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+const COLUMNS: &str = "id, quote_id, amount_ngn, exchange_rate, gross_cngn, fee_cngn, net_cngn, \
+     status, expires_at, created_at, updated_at, direction, wallet_address, context, \
+     sell_amount, buy_amount, fee_total";
+
 /// Repository for onramp quotes
 pub struct OnrampQuoteRepository {
     pool: PgPool,
@@ -38,14 +69,14 @@ impl OnrampQuoteRepository {
         net_cngn: &sqlx::types::BigDecimal,
         expires_at: chrono::DateTime<chrono::Utc>,
     ) -> Result<OnrampQuote, DatabaseError> {
-        sqlx::query_as::<_, OnrampQuote>(
+        sqlx::query_as::<_, OnrampQuote>(&format!(
             r#"
             INSERT INTO onramp_quotes
                 (amount_ngn, exchange_rate, gross_cngn, fee_cngn, net_cngn, status, expires_at)
             VALUES ($1, $2, $3, $4, $5, 'pending', $6)
-            RETURNING id, quote_id, amount_ngn, exchange_rate, gross_cngn, fee_cngn, net_cngn, status, expires_at, created_at, updated_at
+            RETURNING {COLUMNS}
             "#,
-        )
+        ))
         .bind(amount_ngn)
         .bind(exchange_rate)
         .bind(gross_cngn)
@@ -62,26 +93,71 @@ impl OnrampQuoteRepository {
         &self,
         quote_id: Uuid,
     ) -> Result<Option<OnrampQuote>, DatabaseError> {
-        sqlx::query_as::<_, OnrampQuote>(
+        sqlx::query_as::<_, OnrampQuote>(&format!(
             r#"
-            SELECT id, quote_id, amount_ngn, exchange_rate, gross_cngn, fee_cngn, net_cngn, status, expires_at, created_at, updated_at
+            SELECT {COLUMNS}
             FROM onramp_quotes
             WHERE quote_id = $1
             "#,
-        )
+        ))
         .bind(quote_id)
         .fetch_optional(&self.pool)
         .await
         .map_err(DatabaseError::from_sqlx)
     }
 
-    /// Mark quote as consumed
+    /// Create a firm quote for an account
+    pub async fn create_firm(
+        &self,
+        quote: &NewFirmQuote<'_>,
+    ) -> Result<OnrampQuote, DatabaseError> {
+        sqlx::query_as::<_, OnrampQuote>(&format!(
+            r#"
+            INSERT INTO onramp_quotes
+                (amount_ngn, exchange_rate, gross_cngn, fee_cngn, net_cngn, status, expires_at,
+                 direction, wallet_address, context, sell_amount, buy_amount, fee_total)
+            VALUES ($1, $2, $3, $4, $5, 'pending', $6, $7, $8, $9, $10, $11, $12)
+            RETURNING {COLUMNS}
+            "#,
+        ))
+        .bind(&quote.amount_ngn)
+        .bind(&quote.exchange_rate)
+        .bind(&quote.gross_cngn)
+        .bind(&quote.fee_cngn)
+        .bind(&quote.net_cngn)
+        .bind(quote.expires_at)
+        .bind(quote.direction)
+        .bind(quote.wallet_address)
+        .bind(quote.context)
+        .bind(&quote.sell_amount)
+        .bind(&quote.buy_amount)
+        .bind(&quote.fee_total)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Mark quote as consumed. Expired quotes can no longer be consumed.
     pub async fn mark_consumed(&self, quote_id: Uuid) -> Result<bool, DatabaseError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Self::mark_consumed_in(&mut conn, quote_id).await
+    }
+
+    /// `mark_consumed` on a caller's connection, to spend a quote inside a
+    /// wider database transaction
+    pub async fn mark_consumed_in(
+        conn: &mut PgConnection,
+        quote_id: Uuid,
+    ) -> Result<bool, DatabaseError> {
         let result = sqlx::query(
-            "UPDATE onramp_quotes SET status = 'consumed', updated_at = NOW() WHERE quote_id = $1 AND status = 'pending'",
+            "UPDATE onramp_quotes SET status = 'consumed', updated_at = NOW() WHERE quote_id = $1 AND status = 'pending' AND expires_at > NOW()",
         )
         .bind(quote_id)
-        .execute(&self.pool)
+        .execute(conn)
         .await
         .map_err(DatabaseError::from_sqlx)?;
         Ok(result.rows_affected() > 0)
@@ -0,0 +1,102 @@
+use crate::database::error::DatabaseError;
+use sqlx::PgPool;
+
+/// Repository for durable Horizon paging cursors
+pub struct StellarCursorRepository {
+    pool: PgPool,
+}
+
+impl StellarCursorRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Last saved `paging_token` for an account and stream
+    pub async fn get(
+        &self,
+        account_address: &str,
+        stream: &str,
+    ) -> Result<Option<String>, DatabaseError> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT paging_token
+            FROM stellar_cursors
+            WHERE account_address = $1 AND stream = $2
+            "#,
+        )
+        .bind(account_address)
+        .bind(stream)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn save(
+        &self,
+        account_address: &str,
+        stream: &str,
+        paging_token: &str,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            INSERT INTO stellar_cursors (account_address, stream, paging_token)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (account_address, stream) DO UPDATE
+            SET paging_token = EXCLUDED.paging_token
+            "#,
+        )
+        .bind(account_address)
+        .bind(stream)
+        .bind(paging_token)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Ask the consumer to replay from `paging_token`. The saved cursor is
+    /// left alone until the consumer picks the request up.
+    pub async fn request_rescan(
+        &self,
+        account_address: &str,
+        stream: &str,
+        paging_token: &str,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            INSERT INTO stellar_cursors (account_address, stream, paging_token, rescan_from)
+            VALUES ($1, $2, $3, $3)
+            ON CONFLICT (account_address, stream) DO UPDATE
+            SET rescan_from = EXCLUDED.rescan_from
+            "#,
+        )
+        .bind(account_address)
+        .bind(stream)
+        .bind(paging_token)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Claim a pending rescan request, moving the cursor back to it
+    pub async fn take_rescan(
+        &self,
+        account_address: &str,
+        stream: &str,
+    ) -> Result<Option<String>, DatabaseError> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            UPDATE stellar_cursors
+            SET paging_token = rescan_from, rescan_from = NULL
+            WHERE account_address = $1 AND stream = $2 AND rescan_from IS NOT NULL
+            RETURNING paging_token
+            "#,
+        )
+        .bind(account_address)
+        .bind(stream)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+}
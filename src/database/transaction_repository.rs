@@ -1,7 +1,8 @@
 use crate::database::error::{DatabaseError, DatabaseErrorKind};
+use crate::database::onramp_quote_repository::OnrampQuoteRepository;
 use crate::database::repository::{Repository, TransactionalRepository};
 use async_trait::async_trait;
-use sqlx::{types::BigDecimal, FromRow, PgPool};
+use sqlx::{types::BigDecimal, FromRow, PgConnection, PgPool};
 use uuid::Uuid;
 
 /// Transaction entity
@@ -25,6 +26,45 @@ pub struct Transaction {
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Amounts and payment details an `incomplete` transaction is filled with
+#[derive(Debug, Clone)]
+pub struct IncompleteFill<'a> {
+    pub from_amount: BigDecimal,
+    pub to_amount: BigDecimal,
+    pub cngn_amount: BigDecimal,
+    pub status: &'a str,
+    pub payment_provider: Option<&'a str>,
+    pub payment_reference: Option<&'a str>,
+    /// Merged into the existing metadata
+    pub metadata: serde_json::Value,
+    /// Firm quote the amounts came from, consumed with the fill
+    pub firm_quote_id: Option<Uuid>,
+    /// 24-hour volume cap the wallet must stay within once filled
+    pub daily_limit: Option<DailyVolumeLimit<'a>>,
+}
+
+/// Cap on a wallet's volume of `types` transactions over the last 24 hours
+#[derive(Debug, Clone)]
+pub struct DailyVolumeLimit<'a> {
+    pub types: &'a [&'a str],
+    pub limit: BigDecimal,
+}
+
+/// Result of [`TransactionRepository::fill_incomplete`]; nothing is written
+/// unless it is `Filled`
+#[derive(Debug, Clone)]
+pub enum FillOutcome {
+    Filled(Transaction),
+    /// The transaction was already filled or closed
+    NotIncomplete,
+    /// The firm quote was used or expired in the meantime
+    QuoteUnavailable,
+    /// Filling would take the wallet over its daily limit
+    LimitExceeded {
+        limit: BigDecimal,
+    },
+}
+
 /// Repository for managing transactions
 pub struct TransactionRepository {
     pool: PgPool,
@@ -49,6 +89,45 @@ impl TransactionRepository {
         payment_provider: Option<&str>,
         payment_reference: Option<&str>,
         metadata: serde_json::Value,
+    ) -> Result<Transaction, DatabaseError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Self::create_transaction_in(
+            &mut conn,
+            wallet_address,
+            transaction_type,
+            from_currency,
+            to_currency,
+            from_amount,
+            to_amount,
+            cngn_amount,
+            status,
+            payment_provider,
+            payment_reference,
+            metadata,
+        )
+        .await
+    }
+
+    /// `create_transaction` on a caller's connection, so rows that extend the
+    /// transaction can be inserted in the same database transaction
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_transaction_in(
+        conn: &mut PgConnection,
+        wallet_address: &str,
+        transaction_type: &str,
+        from_currency: &str,
+        to_currency: &str,
+        from_amount: BigDecimal,
+        to_amount: BigDecimal,
+        cngn_amount: BigDecimal,
+        status: &str,
+        payment_provider: Option<&str>,
+        payment_reference: Option<&str>,
+        metadata: serde_json::Value,
     ) -> Result<Transaction, DatabaseError> {
         sqlx::query_as::<_, Transaction>(
             "INSERT INTO transactions 
@@ -71,7 +150,7 @@ impl TransactionRepository {
         .bind(payment_provider)
         .bind(payment_reference)
         .bind(metadata)
-        .fetch_one(&self.pool)
+        .fetch_one(conn)
         .await
         .map_err(DatabaseError::from_sqlx)
     }
@@ -138,6 +217,76 @@ impl TransactionRepository {
         .map_err(DatabaseError::from_sqlx)
     }
 
+    /// Move a transaction from `expected_status` to `status`, merging metadata.
+    ///
+    /// The update only applies while the row is still in `expected_status`, so
+    /// concurrent workers cannot both claim the same transaction. Returns `None`
+    /// when another writer got there first.
+    pub async fn transition_status_if(
+        &self,
+        transaction_id: &str,
+        expected_status: &str,
+        status: &str,
+        additional_metadata: serde_json::Value,
+    ) -> Result<Option<Transaction>, DatabaseError> {
+        let uuid = Uuid::parse_str(transaction_id).map_err(|e| {
+            DatabaseError::new(DatabaseErrorKind::Unknown {
+                message: format!("Invalid UUID: {}", e),
+            })
+        })?;
+
+        sqlx::query_as::<_, Transaction>(
+            "UPDATE transactions
+             SET status = $3,
+                 metadata = metadata || $4
+             WHERE transaction_id = $1 AND status = $2
+             RETURNING transaction_id, wallet_address, type, from_currency, to_currency,
+                       from_amount, to_amount, cngn_amount, status, payment_provider,
+                       payment_reference, blockchain_tx_hash, error_message, metadata,
+                       created_at, updated_at",
+        )
+        .bind(uuid)
+        .bind(expected_status)
+        .bind(status)
+        .bind(additional_metadata)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Credit a confirmed deposit on the caller's connection: move the order
+    /// out of `expected_status`, merge metadata and store the deposit hash in
+    /// one update. Returns `None` when the order is no longer in
+    /// `expected_status`.
+    pub async fn credit_deposit_in(
+        conn: &mut PgConnection,
+        transaction_id: Uuid,
+        expected_status: &str,
+        status: &str,
+        additional_metadata: serde_json::Value,
+        blockchain_tx_hash: &str,
+    ) -> Result<Option<Transaction>, DatabaseError> {
+        sqlx::query_as::<_, Transaction>(
+            "UPDATE transactions
+             SET status = $3,
+                 metadata = metadata || $4,
+                 blockchain_tx_hash = $5
+             WHERE transaction_id = $1 AND status = $2
+             RETURNING transaction_id, wallet_address, type, from_currency, to_currency,
+                       from_amount, to_amount, cngn_amount, status, payment_provider,
+                       payment_reference, blockchain_tx_hash, error_message, metadata,
+                       created_at, updated_at",
+        )
+        .bind(transaction_id)
+        .bind(expected_status)
+        .bind(status)
+        .bind(additional_metadata)
+        .bind(blockchain_tx_hash)
+        .fetch_optional(conn)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
     /// Update blockchain transaction hash
     pub async fn update_blockchain_hash(
         &self,
@@ -306,6 +455,202 @@ impl TransactionRepository {
         .await
         .map_err(DatabaseError::from_sqlx)
     }
+
+    /// Find onramp transactions by status, oldest first
+    pub async fn find_onramps_by_status(
+        &self,
+        status: &str,
+        limit: i64,
+    ) -> Result<Vec<Transaction>, DatabaseError> {
+        sqlx::query_as::<_, Transaction>(
+            "SELECT transaction_id, wallet_address, type, from_currency, to_currency,
+                    from_amount, to_amount, cngn_amount, status, payment_provider,
+                    payment_reference, blockchain_tx_hash, error_message, metadata,
+                    created_at, updated_at
+             FROM transactions
+             WHERE status = $1 AND type = 'onramp'
+             ORDER BY created_at ASC
+             LIMIT $2",
+        )
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Fill in an `incomplete` transaction once the user finished the
+    /// interactive flow. A firm quote named by the fill is used up in the same
+    /// database transaction, so a quote is only ever spent on a filled order.
+    /// The daily limit is checked under a per-wallet lock, so concurrent fills
+    /// can't each fit under it alone.
+    pub async fn fill_incomplete(
+        &self,
+        transaction_id: Uuid,
+        fill: &IncompleteFill<'_>,
+    ) -> Result<FillOutcome, DatabaseError> {
+        let mut db_tx = self.pool.begin().await.map_err(DatabaseError::from_sqlx)?;
+
+        let filled = sqlx::query_as::<_, Transaction>(
+            "UPDATE transactions
+             SET from_amount = $2,
+                 to_amount = $3,
+                 cngn_amount = $4,
+                 status = $5,
+                 payment_provider = $6,
+                 payment_reference = $7,
+                 metadata = metadata || $8
+             WHERE transaction_id = $1 AND status = 'incomplete'
+             RETURNING transaction_id, wallet_address, type, from_currency, to_currency,
+                       from_amount, to_amount, cngn_amount, status, payment_provider,
+                       payment_reference, blockchain_tx_hash, error_message, metadata,
+                       created_at, updated_at",
+        )
+        .bind(transaction_id)
+        .bind(&fill.from_amount)
+        .bind(&fill.to_amount)
+        .bind(&fill.cngn_amount)
+        .bind(fill.status)
+        .bind(fill.payment_provider)
+        .bind(fill.payment_reference)
+        .bind(&fill.metadata)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        let Some(filled) = filled else {
+            return Ok(FillOutcome::NotIncomplete);
+        };
+
+        if let Some(daily) = &fill.daily_limit {
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext('volume:' || $1))")
+                .bind(&filled.wallet_address)
+                .execute(&mut *db_tx)
+                .await
+                .map_err(DatabaseError::from_sqlx)?;
+            let since = chrono::Utc::now() - chrono::Duration::hours(24);
+            let others = Self::volume_since_on(
+                &mut db_tx,
+                &filled.wallet_address,
+                daily.types,
+                since,
+                Some(transaction_id),
+            )
+            .await?;
+            if others + &fill.from_amount > daily.limit {
+                return Ok(FillOutcome::LimitExceeded {
+                    limit: daily.limit.clone(),
+                });
+            }
+        }
+
+        if let Some(quote_id) = fill.firm_quote_id {
+            if !OnrampQuoteRepository::mark_consumed_in(&mut db_tx, quote_id).await? {
+                return Ok(FillOutcome::QuoteUnavailable);
+            }
+        }
+
+        db_tx.commit().await.map_err(DatabaseError::from_sqlx)?;
+        Ok(FillOutcome::Filled(filled))
+    }
+
+    /// Find a transaction by its Stellar transaction hash
+    pub async fn find_by_blockchain_hash(
+        &self,
+        blockchain_tx_hash: &str,
+    ) -> Result<Option<Transaction>, DatabaseError> {
+        sqlx::query_as::<_, Transaction>(
+            "SELECT transaction_id, wallet_address, type, from_currency, to_currency,
+                    from_amount, to_amount, cngn_amount, status, payment_provider,
+                    payment_reference, blockchain_tx_hash, error_message, metadata,
+                    created_at, updated_at
+             FROM transactions
+             WHERE blockchain_tx_hash = $1
+             ORDER BY created_at DESC
+             LIMIT 1",
+        )
+        .bind(blockchain_tx_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// A wallet's transactions of the given types, newest first.
+    ///
+    /// `created_after` drops older rows; `before_id` pages past the rows up
+    /// to and including that transaction.
+    pub async fn find_by_wallet_and_types(
+        &self,
+        wallet_address: &str,
+        types: &[&str],
+        created_after: Option<chrono::DateTime<chrono::Utc>>,
+        before_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<Transaction>, DatabaseError> {
+        sqlx::query_as::<_, Transaction>(
+            "SELECT transaction_id, wallet_address, type, from_currency, to_currency,
+                    from_amount, to_amount, cngn_amount, status, payment_provider,
+                    payment_reference, blockchain_tx_hash, error_message, metadata,
+                    created_at, updated_at
+             FROM transactions
+             WHERE wallet_address = $1
+               AND type = ANY($2)
+               AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+               AND ($4::UUID IS NULL
+                    OR created_at < (SELECT created_at FROM transactions WHERE transaction_id = $4))
+             ORDER BY created_at DESC
+             LIMIT $5",
+        )
+        .bind(wallet_address)
+        .bind(types)
+        .bind(created_after)
+        .bind(before_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Total `from_amount` of a wallet's transactions of the given types
+    /// created since `since`, leaving out ones that failed, were refunded or
+    /// were never filled in
+    pub async fn volume_since(
+        &self,
+        wallet_address: &str,
+        types: &[&str],
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<BigDecimal, DatabaseError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Self::volume_since_on(&mut conn, wallet_address, types, since, None).await
+    }
+
+    async fn volume_since_on(
+        conn: &mut PgConnection,
+        wallet_address: &str,
+        types: &[&str],
+        since: chrono::DateTime<chrono::Utc>,
+        excluding: Option<Uuid>,
+    ) -> Result<BigDecimal, DatabaseError> {
+        sqlx::query_scalar::<_, BigDecimal>(
+            "SELECT COALESCE(SUM(from_amount), 0)
+             FROM transactions
+             WHERE wallet_address = $1
+               AND type = ANY($2)
+               AND created_at >= $3
+               AND status NOT IN ('failed', 'refund_initiated', 'refunded', 'incomplete')
+               AND transaction_id IS DISTINCT FROM $4",
+        )
+        .bind(wallet_address)
+        .bind(types)
+        .bind(since)
+        .bind(excluding)
+        .fetch_one(conn)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,344 @@
+use crate::database::error::DatabaseError;
+use serde::Serialize;
+use sqlx::types::BigDecimal;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Which side of its configured range a hot wallet balance is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreasuryAlertKind {
+    BelowMin,
+    AboveMax,
+}
+
+impl TreasuryAlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TreasuryAlertKind::BelowMin => "below_min",
+            TreasuryAlertKind::AboveMax => "above_max",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreasuryTransferKind {
+    /// Excess moved from the hot wallet to cold storage
+    Sweep,
+    /// Funds an operator needs to send from cold storage to the hot wallet
+    TopUp,
+}
+
+impl TreasuryTransferKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TreasuryTransferKind::Sweep => "sweep",
+            TreasuryTransferKind::TopUp => "top_up",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TreasuryAlert {
+    pub id: Uuid,
+    pub wallet_address: String,
+    pub asset: String,
+    pub kind: String,
+    pub balance: BigDecimal,
+    pub threshold: BigDecimal,
+    pub status: String,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TreasuryTransfer {
+    pub id: Uuid,
+    pub kind: String,
+    pub asset: String,
+    pub amount: BigDecimal,
+    pub from_address: String,
+    pub to_address: String,
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolution_note: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewTreasuryTransfer {
+    pub kind: TreasuryTransferKind,
+    pub asset: String,
+    pub amount: BigDecimal,
+    pub from_address: String,
+    pub to_address: String,
+}
+
+const ALERT_COLUMNS: &str = "id, wallet_address, asset, kind, balance, threshold, status, \
+     resolved_at, created_at, updated_at";
+
+const TRANSFER_COLUMNS: &str = "id, kind, asset, amount, from_address, to_address, status, \
+     tx_hash, error, resolved_by, resolution_note, created_at, updated_at";
+
+/// Repository for treasury threshold alerts and rebalancing transfers
+pub struct TreasuryRepository {
+    pool: PgPool,
+}
+
+impl TreasuryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Open an alert. Returns `None` when one is already open for the same
+    /// wallet, asset and kind, so callers only notify on the first breach.
+    pub async fn open_alert(
+        &self,
+        wallet_address: &str,
+        asset: &str,
+        kind: TreasuryAlertKind,
+        balance: &BigDecimal,
+        threshold: &BigDecimal,
+    ) -> Result<Option<TreasuryAlert>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryAlert>(&format!(
+            r#"
+            INSERT INTO treasury_alerts (wallet_address, asset, kind, balance, threshold)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (wallet_address, asset, kind) WHERE status = 'open' DO NOTHING
+            RETURNING {ALERT_COLUMNS}
+            "#
+        ))
+        .bind(wallet_address)
+        .bind(asset)
+        .bind(kind.as_str())
+        .bind(balance)
+        .bind(threshold)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Resolve the asset's open alerts, except those of `keep` if given
+    pub async fn resolve_alerts(
+        &self,
+        wallet_address: &str,
+        asset: &str,
+        keep: Option<TreasuryAlertKind>,
+    ) -> Result<u64, DatabaseError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE treasury_alerts
+            SET status = 'resolved', resolved_at = now()
+            WHERE wallet_address = $1
+              AND asset = $2
+              AND status = 'open'
+              AND ($3::TEXT IS NULL OR kind <> $3)
+            "#,
+        )
+        .bind(wallet_address)
+        .bind(asset)
+        .bind(keep.map(|k| k.as_str()))
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn list_open_alerts(
+        &self,
+        wallet_address: &str,
+    ) -> Result<Vec<TreasuryAlert>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryAlert>(&format!(
+            r#"
+            SELECT {ALERT_COLUMNS}
+            FROM treasury_alerts
+            WHERE wallet_address = $1 AND status = 'open'
+            ORDER BY created_at
+            "#
+        ))
+        .bind(wallet_address)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Queue a transfer. Returns `None` when one is already outstanding for
+    /// the same kind, asset and addresses.
+    pub async fn queue_transfer(
+        &self,
+        transfer: &NewTreasuryTransfer,
+    ) -> Result<Option<TreasuryTransfer>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryTransfer>(&format!(
+            r#"
+            INSERT INTO treasury_transfers (kind, asset, amount, from_address, to_address)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (kind, asset, from_address, to_address)
+                WHERE status IN ('queued', 'submitted') DO NOTHING
+            RETURNING {TRANSFER_COLUMNS}
+            "#
+        ))
+        .bind(transfer.kind.as_str())
+        .bind(&transfer.asset)
+        .bind(&transfer.amount)
+        .bind(&transfer.from_address)
+        .bind(&transfer.to_address)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn find_transfer(&self, id: Uuid) -> Result<Option<TreasuryTransfer>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryTransfer>(&format!(
+            "SELECT {TRANSFER_COLUMNS} FROM treasury_transfers WHERE id = $1"
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn find_queued(
+        &self,
+        kind: TreasuryTransferKind,
+        from_address: &str,
+    ) -> Result<Vec<TreasuryTransfer>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryTransfer>(&format!(
+            r#"
+            SELECT {TRANSFER_COLUMNS}
+            FROM treasury_transfers
+            WHERE kind = $1 AND from_address = $2 AND status = 'queued'
+            ORDER BY created_at
+            "#
+        ))
+        .bind(kind.as_str())
+        .bind(from_address)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Claim a queued transfer for submission. Returns `None` if another
+    /// worker got there first or it was resolved meanwhile.
+    pub async fn mark_submitted(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<TreasuryTransfer>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryTransfer>(&format!(
+            r#"
+            UPDATE treasury_transfers
+            SET status = 'submitted', error = NULL
+            WHERE id = $1 AND status = 'queued'
+            RETURNING {TRANSFER_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Record the outcome of a claimed transfer, keeping any hash already set
+    pub async fn finish_transfer(
+        &self,
+        id: Uuid,
+        status: &str,
+        tx_hash: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE treasury_transfers
+            SET status = $2, tx_hash = COALESCE($3, tx_hash), error = $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(tx_hash)
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Settle a wallet's queued transfers of one kind for an asset whose
+    /// balance no longer needs them, e.g. `completed` once a top-up arrived.
+    pub async fn settle_queued(
+        &self,
+        kind: TreasuryTransferKind,
+        asset: &str,
+        wallet_address: &str,
+        status: &str,
+    ) -> Result<u64, DatabaseError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE treasury_transfers
+            SET status = $4
+            WHERE kind = $1
+              AND asset = $2
+              AND (from_address = $3 OR to_address = $3)
+              AND status = 'queued'
+            "#,
+        )
+        .bind(kind.as_str())
+        .bind(asset)
+        .bind(wallet_address)
+        .bind(status)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected())
+    }
+
+    /// Operator resolution of an outstanding transfer. Returns `None` if it
+    /// was already settled.
+    pub async fn resolve_transfer(
+        &self,
+        id: Uuid,
+        status: &str,
+        resolved_by: &str,
+        note: Option<&str>,
+    ) -> Result<Option<TreasuryTransfer>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryTransfer>(&format!(
+            r#"
+            UPDATE treasury_transfers
+            SET status = $2, resolved_by = $3, resolution_note = $4
+            WHERE id = $1 AND status IN ('queued', 'submitted')
+            RETURNING {TRANSFER_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(status)
+        .bind(resolved_by)
+        .bind(note)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Most recent transfers touching `address`, newest first
+    pub async fn recent_transfers(
+        &self,
+        address: &str,
+        limit: i64,
+    ) -> Result<Vec<TreasuryTransfer>, DatabaseError> {
+        sqlx::query_as::<_, TreasuryTransfer>(&format!(
+            r#"
+            SELECT {TRANSFER_COLUMNS}
+            FROM treasury_transfers
+            WHERE from_address = $1 OR to_address = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#
+        ))
+        .bind(address)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+}
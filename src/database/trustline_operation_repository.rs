@@ -17,6 +17,10 @@ pub struct TrustlineOperation {
     pub transaction_hash: Option<String>,
     pub error_message: Option<String>,
     pub metadata: serde_json::Value,
+    /// Account paying the trustline reserve (CAP-33), if sponsored
+    pub sponsor_account: Option<String>,
+    /// pending, active or revoked
+    pub sponsorship_status: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -47,7 +51,7 @@ impl TrustlineOperationRepository {
             "INSERT INTO trustline_operations 
              (wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata) 
              VALUES ($1, $2, $3, $4, $5, $6, $7, $8) 
-             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at",
+             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at",
         )
         .bind(wallet_address)
         .bind(asset_code)
@@ -74,7 +78,7 @@ impl TrustlineOperationRepository {
             "UPDATE trustline_operations 
              SET status = $2, transaction_hash = $3, error_message = $4, updated_at = NOW()
              WHERE id = $1 
-             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at",
+             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at",
         )
         .bind(id)
         .bind(status)
@@ -92,7 +96,7 @@ impl TrustlineOperationRepository {
         limit: i64,
     ) -> Result<Vec<TrustlineOperation>, DatabaseError> {
         sqlx::query_as::<_, TrustlineOperation>(
-            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at 
+            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at 
              FROM trustline_operations 
              WHERE wallet_address = $1 
              ORDER BY created_at DESC LIMIT $2",
@@ -103,6 +107,80 @@ impl TrustlineOperationRepository {
         .await
         .map_err(DatabaseError::from_sqlx)
     }
+
+    /// Record the account sponsoring this operation's trustline reserve
+    pub async fn set_sponsorship(
+        &self,
+        id: Uuid,
+        sponsor_account: &str,
+        sponsorship_status: &str,
+    ) -> Result<TrustlineOperation, DatabaseError> {
+        sqlx::query_as::<_, TrustlineOperation>(
+            "UPDATE trustline_operations 
+             SET sponsor_account = $2, sponsorship_status = $3, updated_at = NOW()
+             WHERE id = $1 
+             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at",
+        )
+        .bind(id)
+        .bind(sponsor_account)
+        .bind(sponsorship_status)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Mark a sponsored create operation's sponsorship active once the
+    /// trustline exists on-chain
+    pub async fn activate_sponsorship(&self, id: Uuid) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE trustline_operations 
+             SET sponsorship_status = 'active', updated_at = NOW()
+             WHERE id = $1 AND sponsorship_status = 'pending'",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(())
+    }
+
+    /// Mark the wallet's active sponsorship for `asset_code` revoked after
+    /// its trustline was closed and the reserve returned to the sponsor.
+    /// Returns the number of sponsorships revoked.
+    pub async fn revoke_sponsorship(
+        &self,
+        wallet_address: &str,
+        asset_code: &str,
+    ) -> Result<u64, DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE trustline_operations 
+             SET sponsorship_status = 'revoked', updated_at = NOW()
+             WHERE wallet_address = $1 AND asset_code = $2 AND sponsorship_status = 'active'",
+        )
+        .bind(wallet_address)
+        .bind(asset_code)
+        .execute(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)?;
+        Ok(result.rows_affected())
+    }
+
+    /// Sponsored trustlines currently holding a reserve of `sponsor_account`
+    pub async fn find_active_sponsorships(
+        &self,
+        sponsor_account: &str,
+    ) -> Result<Vec<TrustlineOperation>, DatabaseError> {
+        sqlx::query_as::<_, TrustlineOperation>(
+            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at 
+             FROM trustline_operations 
+             WHERE sponsor_account = $1 AND sponsorship_status = 'active' 
+             ORDER BY created_at DESC",
+        )
+        .bind(sponsor_account)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
 }
 
 #[async_trait]
@@ -116,7 +194,7 @@ impl Repository for TrustlineOperationRepository {
             })
         })?;
         sqlx::query_as::<_, TrustlineOperation>(
-            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at 
+            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at 
              FROM trustline_operations WHERE id = $1",
         )
         .bind(uuid)
@@ -127,7 +205,7 @@ impl Repository for TrustlineOperationRepository {
 
     async fn find_all(&self) -> Result<Vec<Self::Entity>, DatabaseError> {
         sqlx::query_as::<_, TrustlineOperation>(
-            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at 
+            "SELECT id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at 
              FROM trustline_operations ORDER BY created_at DESC",
         )
         .fetch_all(&self.pool)
@@ -138,9 +216,9 @@ impl Repository for TrustlineOperationRepository {
     async fn insert(&self, entity: &Self::Entity) -> Result<Self::Entity, DatabaseError> {
         sqlx::query_as::<_, TrustlineOperation>(
             "INSERT INTO trustline_operations 
-             (id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at) 
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) 
-             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at",
+             (id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at) 
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) 
+             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at",
         )
         .bind(entity.id)
         .bind(&entity.wallet_address)
@@ -151,6 +229,8 @@ impl Repository for TrustlineOperationRepository {
         .bind(&entity.transaction_hash)
         .bind(&entity.error_message)
         .bind(&entity.metadata)
+        .bind(&entity.sponsor_account)
+        .bind(&entity.sponsorship_status)
         .bind(entity.created_at)
         .bind(entity.updated_at)
         .fetch_one(&self.pool)
@@ -166,9 +246,9 @@ impl Repository for TrustlineOperationRepository {
         })?;
         sqlx::query_as::<_, TrustlineOperation>(
             "UPDATE trustline_operations 
-             SET wallet_address = $1, asset_code = $2, issuer = $3, operation_type = $4, status = $5, transaction_hash = $6, error_message = $7, metadata = $8, updated_at = NOW()
-             WHERE id = $9 
-             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, created_at, updated_at",
+             SET wallet_address = $1, asset_code = $2, issuer = $3, operation_type = $4, status = $5, transaction_hash = $6, error_message = $7, metadata = $8, sponsor_account = $9, sponsorship_status = $10, updated_at = NOW()
+             WHERE id = $11 
+             RETURNING id, wallet_address, asset_code, issuer, operation_type, status, transaction_hash, error_message, metadata, sponsor_account, sponsorship_status, created_at, updated_at",
         )
         .bind(&entity.wallet_address)
         .bind(&entity.asset_code)
@@ -178,6 +258,8 @@ impl Repository for TrustlineOperationRepository {
         .bind(&entity.transaction_hash)
         .bind(&entity.error_message)
         .bind(&entity.metadata)
+        .bind(&entity.sponsor_account)
+        .bind(&entity.sponsorship_status)
         .bind(uuid)
         .fetch_one(&self.pool)
         .await
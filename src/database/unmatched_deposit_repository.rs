@@ -0,0 +1,244 @@
+use crate::database::error::DatabaseError;
+use serde::Serialize;
+use sqlx::types::BigDecimal;
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Why a deposit could not be credited automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnmatchedReason {
+    NoMemo,
+    UnknownMemo,
+    UnknownMuxedId,
+    NotPending,
+    Overpaid,
+    Underpaid,
+}
+
+impl UnmatchedReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnmatchedReason::NoMemo => "no_memo",
+            UnmatchedReason::UnknownMemo => "unknown_memo",
+            UnmatchedReason::UnknownMuxedId => "unknown_muxed_id",
+            UnmatchedReason::NotPending => "not_pending",
+            UnmatchedReason::Overpaid => "overpaid",
+            UnmatchedReason::Underpaid => "underpaid",
+        }
+    }
+}
+
+/// cNGN received by the system wallet that is waiting for an operator
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct UnmatchedDeposit {
+    pub id: Uuid,
+    pub tx_hash: String,
+    pub op_index: i32,
+    pub source_account: String,
+    pub amount: BigDecimal,
+    pub asset_code: String,
+    pub asset_issuer: Option<String>,
+    pub memo: Option<String>,
+    pub muxed_id: Option<BigDecimal>,
+    pub ledger: Option<i64>,
+    pub reason: String,
+    pub status: String,
+    pub transaction_id: Option<Uuid>,
+    pub refund_tx_hash: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolution_note: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUnmatchedDeposit {
+    pub tx_hash: String,
+    pub op_index: i32,
+    pub source_account: String,
+    pub amount: BigDecimal,
+    pub asset_code: String,
+    pub asset_issuer: Option<String>,
+    pub memo: Option<String>,
+    pub muxed_id: Option<u64>,
+    pub ledger: Option<i64>,
+    pub reason: UnmatchedReason,
+    pub transaction_id: Option<Uuid>,
+}
+
+const COLUMNS: &str = "id, tx_hash, op_index, source_account, amount, asset_code, asset_issuer, \
+     memo, muxed_id, ledger, reason, status, transaction_id, refund_tx_hash, resolved_by, \
+     resolution_note, last_error, created_at, updated_at";
+
+/// Repository for quarantined deposits
+pub struct UnmatchedDepositRepository {
+    pool: PgPool,
+}
+
+impl UnmatchedDepositRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Quarantine a deposit. Returns None when the operation is already held.
+    pub async fn quarantine(
+        &self,
+        deposit: &NewUnmatchedDeposit,
+    ) -> Result<Option<UnmatchedDeposit>, DatabaseError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(DatabaseError::from_sqlx)?;
+        Self::quarantine_in(&mut conn, deposit).await
+    }
+
+    /// `quarantine` on a caller's connection, inside the database transaction
+    /// that ingests the operation
+    pub async fn quarantine_in(
+        conn: &mut PgConnection,
+        deposit: &NewUnmatchedDeposit,
+    ) -> Result<Option<UnmatchedDeposit>, DatabaseError> {
+        sqlx::query_as::<_, UnmatchedDeposit>(&format!(
+            r#"
+            INSERT INTO unmatched_deposits
+                (tx_hash, op_index, source_account, amount, asset_code, asset_issuer,
+                 memo, muxed_id, ledger, reason, transaction_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (tx_hash, op_index) DO NOTHING
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(&deposit.tx_hash)
+        .bind(deposit.op_index)
+        .bind(&deposit.source_account)
+        .bind(&deposit.amount)
+        .bind(&deposit.asset_code)
+        .bind(&deposit.asset_issuer)
+        .bind(&deposit.memo)
+        .bind(deposit.muxed_id.map(BigDecimal::from))
+        .bind(deposit.ledger)
+        .bind(deposit.reason.as_str())
+        .bind(deposit.transaction_id)
+        .fetch_optional(conn)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<UnmatchedDeposit>, DatabaseError> {
+        sqlx::query_as::<_, UnmatchedDeposit>(&format!(
+            "SELECT {} FROM unmatched_deposits WHERE id = $1",
+            COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Oldest first, optionally filtered by status
+    pub async fn list(
+        &self,
+        status: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<UnmatchedDeposit>, DatabaseError> {
+        sqlx::query_as::<_, UnmatchedDeposit>(&format!(
+            r#"
+            SELECT {}
+            FROM unmatched_deposits
+            WHERE ($1::TEXT IS NULL OR status = $1)
+            ORDER BY created_at ASC
+            LIMIT $2
+            "#,
+            COLUMNS
+        ))
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Credit a pending deposit to `transaction_id`. None when it was already
+    /// resolved. Runs on the caller's connection so the order is credited in
+    /// the same database transaction.
+    pub async fn attach_in(
+        conn: &mut PgConnection,
+        id: Uuid,
+        transaction_id: Uuid,
+        resolved_by: &str,
+        note: Option<&str>,
+    ) -> Result<Option<UnmatchedDeposit>, DatabaseError> {
+        sqlx::query_as::<_, UnmatchedDeposit>(&format!(
+            r#"
+            UPDATE unmatched_deposits
+            SET status = 'attached', transaction_id = $2, resolved_by = $3, resolution_note = $4
+            WHERE id = $1 AND status = 'pending'
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(id)
+        .bind(transaction_id)
+        .bind(resolved_by)
+        .bind(note)
+        .fetch_optional(conn)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Claim a pending deposit for refunding. None when it was already resolved.
+    pub async fn begin_refund(
+        &self,
+        id: Uuid,
+        resolved_by: &str,
+        note: Option<&str>,
+    ) -> Result<Option<UnmatchedDeposit>, DatabaseError> {
+        sqlx::query_as::<_, UnmatchedDeposit>(&format!(
+            r#"
+            UPDATE unmatched_deposits
+            SET status = 'refund_processing', resolved_by = $2, resolution_note = $3,
+                last_error = NULL
+            WHERE id = $1 AND status = 'pending'
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(id)
+        .bind(resolved_by)
+        .bind(note)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+
+    /// Settle a refund claimed with `begin_refund`: `refunded` with the refund
+    /// hash, `refund_failed` when the outcome is unknown, or back to `pending`
+    /// when nothing was sent.
+    pub async fn finish_refund(
+        &self,
+        id: Uuid,
+        status: &str,
+        refund_tx_hash: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<UnmatchedDeposit, DatabaseError> {
+        sqlx::query_as::<_, UnmatchedDeposit>(&format!(
+            r#"
+            UPDATE unmatched_deposits
+            SET status = $2, refund_tx_hash = COALESCE($3, refund_tx_hash), last_error = $4
+            WHERE id = $1 AND status = 'refund_processing'
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(id)
+        .bind(status)
+        .bind(refund_tx_hash)
+        .bind(error)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(DatabaseError::from_sqlx)
+    }
+}
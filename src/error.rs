@@ -43,6 +43,8 @@ pub enum ErrorCode {
     InvalidWallet,
     #[serde(rename = "DUPLICATE_TRANSACTION")]
     DuplicateTransaction,
+    #[serde(rename = "KYC_LIMIT_EXCEEDED")]
+    KycLimitExceeded,
 
     // Infrastructure errors (5xx)
     #[serde(rename = "DATABASE_ERROR")]
@@ -62,6 +64,12 @@ pub enum ErrorCode {
     #[serde(rename = "EXTERNAL_SERVICE_TIMEOUT")]
     ExternalServiceTimeout,
 
+    // Authentication (401, 403)
+    #[serde(rename = "UNAUTHORIZED")]
+    Unauthorized,
+    #[serde(rename = "FORBIDDEN")]
+    Forbidden,
+
     // Generic
     #[serde(rename = "INTERNAL_ERROR")]
     InternalError,
@@ -99,6 +107,12 @@ pub enum DomainError {
     },
     /// Insufficient cNGN liquidity on Stellar for onramp
     InsufficientLiquidity { amount: String },
+    /// Amount is over what the wallet's KYC tier allows
+    LimitExceeded {
+        amount: String,
+        limit: String,
+        tier: i16,
+    },
 }
 
 /// Infrastructure-level errors (database, cache, configuration)
@@ -207,6 +221,7 @@ impl AppError {
                 DomainError::DuplicateTransaction { .. } => 409, // Conflict
                 DomainError::TrustlineCreationFailed { .. } => 422,
                 DomainError::InsufficientLiquidity { .. } => 409, // Conflict
+                DomainError::LimitExceeded { .. } => 403,
             },
             AppErrorKind::Infrastructure(err) => match err {
                 InfrastructureError::Database { .. } => 500,
@@ -243,6 +258,7 @@ impl AppError {
                 DomainError::TrustlineCreationFailed { .. } => ErrorCode::TrustlineCreationFailed,
                 DomainError::InsufficientLiquidity { .. } => ErrorCode::InsufficientLiquidity,
                 DomainError::AmountTooLow { .. } => ErrorCode::AmountTooLow,
+                DomainError::LimitExceeded { .. } => ErrorCode::KycLimitExceeded,
             },
             AppErrorKind::Infrastructure(err) => match err {
                 InfrastructureError::Database { .. } => ErrorCode::DatabaseError,
@@ -319,6 +335,16 @@ impl AppError {
                 DomainError::AmountTooLow { .. } => {
                     "Minimum onramp amount is ₦1,000.".to_string()
                 }
+                DomainError::LimitExceeded {
+                    amount,
+                    limit,
+                    tier,
+                } => {
+                    format!(
+                        "Amount {} exceeds the ₦{} limit for KYC tier {}. Complete identity verification to raise it",
+                        amount, limit, tier
+                    )
+                }
             },
             AppErrorKind::Infrastructure(_) => {
                 "Service temporarily unavailable. Please try again later".to_string()
@@ -484,6 +510,12 @@ impl From<StellarError> for AppError {
             SE::ConfigError { message } => {
                 AppErrorKind::Infrastructure(InfrastructureError::Configuration { message })
             }
+            SE::FeeBudgetExhausted { retry_after } => {
+                AppErrorKind::External(ExternalError::RateLimit {
+                    service: "Stellar fee sponsorship".to_string(),
+                    retry_after: Some(retry_after),
+                })
+            }
             _ => AppErrorKind::External(ExternalError::Blockchain {
                 message: err.to_string(),
                 is_retryable: false,
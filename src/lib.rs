@@ -11,6 +11,10 @@ use soroban_sdk::{
 #[cfg(feature = "database")]
 pub mod database;
 
+// API authentication (API keys and JWT sessions)
+#[cfg(feature = "database")]
+pub mod auth;
+
 // Chains module for blockchain integrations
 #[cfg(feature = "database")]
 pub mod chains;
@@ -71,6 +75,10 @@ pub enum Error {
     OrderExpired = 102,
     CannotAcceptOwnOrder = 103,
     TransferFailed = 104,
+    InvalidAmount = 105,
+    InvalidExpiration = 106,
+    OrderNotExpired = 107,
+    InvalidSplit = 108,
 }
 
 #[cfg(not(feature = "database"))]
@@ -249,29 +257,80 @@ impl EscrowContract {
             .ok_or(Error::NotInitialized)
     }
 
-    /// Accept an open sell order and lock funds in escrow
-    pub fn accept_order(env: Env, order_id: u64, buyer: Address) -> Result<(), Error> {
-        buyer.require_auth();
+    /// Create a sell order and deposit the seller's tokens into escrow
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_order(
+        env: Env,
+        seller: Address,
+        token: Address,
+        amount: i128,
+        fiat_currency: Symbol,
+        fiat_amount: i128,
+        rate: i128,
+        payment_method: String,
+        expires_at: u64,
+    ) -> Result<u64, Error> {
+        seller.require_auth();
+        Self::require_not_paused(&env)?;
 
-        let is_paused: bool = env
-            .storage()
-            .instance()
-            .get(&DataKey::IsPaused)
-            .unwrap_or(false);
-        if is_paused {
-            return Err(Error::ContractPaused);
+        if amount <= 0 || fiat_amount <= 0 || rate <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        let created_at = env.ledger().timestamp();
+        if expires_at <= created_at {
+            return Err(Error::InvalidExpiration);
         }
 
-        let mut order: Order = env
+        let order_count: u64 = env
             .storage()
-            .persistent()
-            .get(&DataKey::Order(order_id))
-            .ok_or(Error::OrderNotFound)?;
+            .instance()
+            .get(&DataKey::OrderCount)
+            .ok_or(Error::NotInitialized)?;
+        let order_id = order_count + 1;
 
-        Self::validate_order_acceptance(&env, &order, &buyer)?;
+        let order = Order {
+            id: order_id,
+            seller: seller.clone(),
+            buyer: None,
+            token,
+            amount,
+            fiat_currency,
+            fiat_amount,
+            rate,
+            status: OrderStatus::Open,
+            created_at,
+            expires_at,
+            payment_method,
+        };
 
         Self::lock_escrow_funds(&env, &order)?;
 
+        env.storage()
+            .persistent()
+            .set(&DataKey::Order(order_id), &order);
+        env.storage()
+            .instance()
+            .set(&DataKey::OrderCount, &order_id);
+
+        Self::update_user_orders(&env, &seller, order_id);
+
+        env.events().publish(
+            (Symbol::new(&env, "order_created"),),
+            (order_id, seller, amount),
+        );
+
+        Ok(order_id)
+    }
+
+    /// Accept an open sell order, locking the escrowed funds for the buyer
+    pub fn accept_order(env: Env, order_id: u64, buyer: Address) -> Result<(), Error> {
+        buyer.require_auth();
+        Self::require_not_paused(&env)?;
+
+        let mut order = Self::load_order(&env, order_id)?;
+
+        Self::validate_order_acceptance(&env, &order, &buyer)?;
+
         order.buyer = Some(buyer.clone());
         order.status = OrderStatus::Locked;
 
@@ -289,6 +348,246 @@ impl EscrowContract {
         Ok(())
     }
 
+    /// Buyer confirms the fiat payment has been sent to the seller
+    pub fn mark_payment_sent(env: Env, order_id: u64, buyer: Address) -> Result<(), Error> {
+        buyer.require_auth();
+
+        let mut order = Self::load_order(&env, order_id)?;
+        if order.status != OrderStatus::Locked {
+            return Err(Error::InvalidOrderStatus);
+        }
+        if order.buyer.as_ref() != Some(&buyer) {
+            return Err(Error::Unauthorized);
+        }
+
+        order.status = OrderStatus::PaymentSent;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Order(order_id), &order);
+
+        env.events()
+            .publish((Symbol::new(&env, "payment_sent"),), (order_id, buyer));
+
+        Ok(())
+    }
+
+    /// Seller confirms the fiat payment and releases the escrow to the buyer,
+    /// less the platform fee which goes to the fee treasury
+    pub fn release(env: Env, order_id: u64) -> Result<(), Error> {
+        let mut order = Self::load_order(&env, order_id)?;
+        order.seller.require_auth();
+
+        if order.status != OrderStatus::Locked && order.status != OrderStatus::PaymentSent {
+            return Err(Error::InvalidOrderStatus);
+        }
+        let buyer = order.buyer.clone().ok_or(Error::InvalidOrderStatus)?;
+
+        let fee = Self::pay_buyer(&env, &order, &buyer, order.amount)?;
+
+        order.status = OrderStatus::Completed;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Order(order_id), &order);
+
+        env.events().publish(
+            (Symbol::new(&env, "order_released"),),
+            (order_id, buyer, order.amount - fee, fee),
+        );
+
+        Ok(())
+    }
+
+    /// Cancel an order and refund the seller.
+    ///
+    /// The seller can cancel while the order is open; the buyer can back out
+    /// of a locked order before marking the payment as sent.
+    pub fn cancel(env: Env, order_id: u64, caller: Address) -> Result<(), Error> {
+        caller.require_auth();
+
+        let order = Self::load_order(&env, order_id)?;
+        match order.status {
+            OrderStatus::Open if caller == order.seller => {}
+            OrderStatus::Locked if order.buyer.as_ref() == Some(&caller) => {}
+            OrderStatus::Open | OrderStatus::Locked => return Err(Error::Unauthorized),
+            _ => return Err(Error::InvalidOrderStatus),
+        }
+
+        Self::refund_seller(&env, order)?;
+
+        env.events()
+            .publish((Symbol::new(&env, "order_cancelled"),), (order_id, caller));
+
+        Ok(())
+    }
+
+    /// Refund the seller once an open or locked order has expired.
+    ///
+    /// Anyone may call this. Orders where the buyer has marked the payment as
+    /// sent never expire and must go through a dispute instead.
+    pub fn refund_expired(env: Env, order_id: u64) -> Result<(), Error> {
+        let order = Self::load_order(&env, order_id)?;
+        if order.status != OrderStatus::Open && order.status != OrderStatus::Locked {
+            return Err(Error::InvalidOrderStatus);
+        }
+        if env.ledger().timestamp() <= order.expires_at {
+            return Err(Error::OrderNotExpired);
+        }
+
+        let seller = order.seller.clone();
+        let amount = order.amount;
+        Self::refund_seller(&env, order)?;
+
+        env.events().publish(
+            (Symbol::new(&env, "order_expired"),),
+            (order_id, seller, amount),
+        );
+
+        Ok(())
+    }
+
+    /// Either party opens a dispute after the buyer has marked the payment as sent
+    pub fn open_dispute(env: Env, order_id: u64, caller: Address) -> Result<(), Error> {
+        caller.require_auth();
+
+        let mut order = Self::load_order(&env, order_id)?;
+        if order.status != OrderStatus::PaymentSent {
+            return Err(Error::InvalidOrderStatus);
+        }
+        if caller != order.seller && order.buyer.as_ref() != Some(&caller) {
+            return Err(Error::Unauthorized);
+        }
+
+        order.status = OrderStatus::Disputed;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Order(order_id), &order);
+
+        env.events()
+            .publish((Symbol::new(&env, "dispute_opened"),), (order_id, caller));
+
+        Ok(())
+    }
+
+    /// Settle a disputed order by splitting the escrow between buyer and seller.
+    ///
+    /// `buyer_amount` goes to the buyer less the platform fee; the remainder is
+    /// returned to the seller fee-free.
+    pub fn resolve_dispute(env: Env, order_id: u64, buyer_amount: i128) -> Result<(), Error> {
+        let resolver: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::DisputeResolver)
+            .ok_or(Error::NotInitialized)?;
+        resolver.require_auth();
+
+        let mut order = Self::load_order(&env, order_id)?;
+        if order.status != OrderStatus::Disputed {
+            return Err(Error::InvalidOrderStatus);
+        }
+        if buyer_amount < 0 || buyer_amount > order.amount {
+            return Err(Error::InvalidSplit);
+        }
+        let buyer = order.buyer.clone().ok_or(Error::InvalidOrderStatus)?;
+
+        let fee = Self::pay_buyer(&env, &order, &buyer, buyer_amount)?;
+        let seller_amount = order.amount - buyer_amount;
+        if seller_amount > 0 {
+            token::Client::new(&env, &order.token).transfer(
+                &env.current_contract_address(),
+                &order.seller,
+                &seller_amount,
+            );
+        }
+
+        order.status = OrderStatus::Completed;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Order(order_id), &order);
+
+        env.events().publish(
+            (Symbol::new(&env, "dispute_resolved"),),
+            (order_id, buyer_amount - fee, seller_amount, fee),
+        );
+
+        Ok(())
+    }
+
+    /// Get an order by id
+    pub fn get_order(env: Env, order_id: u64) -> Result<Order, Error> {
+        Self::load_order(&env, order_id)
+    }
+
+    /// Get the ids of every order a user has created or accepted
+    pub fn get_user_orders(env: Env, user: Address) -> Vec<u64> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::UserOrders(user))
+            .unwrap_or(Vec::new(&env))
+    }
+
+    /// Platform fee for an amount, using the configured rate in basis points
+    pub fn calculate_fee(env: Env, amount: i128) -> i128 {
+        let fee_rate: u32 = env.storage().instance().get(&DataKey::FeeRate).unwrap_or(0);
+        amount * fee_rate as i128 / 10_000
+    }
+
+    fn require_not_paused(env: &Env) -> Result<(), Error> {
+        let is_paused: bool = env
+            .storage()
+            .instance()
+            .get(&DataKey::IsPaused)
+            .unwrap_or(false);
+        if is_paused {
+            return Err(Error::ContractPaused);
+        }
+        Ok(())
+    }
+
+    fn load_order(env: &Env, order_id: u64) -> Result<Order, Error> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Order(order_id))
+            .ok_or(Error::OrderNotFound)
+    }
+
+    /// Pay `amount` of the escrow to the buyer, sending the platform fee to the
+    /// treasury. Returns the fee taken.
+    fn pay_buyer(env: &Env, order: &Order, buyer: &Address, amount: i128) -> Result<i128, Error> {
+        let treasury: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::FeeTreasury)
+            .ok_or(Error::NotInitialized)?;
+        let fee = Self::calculate_fee(env.clone(), amount);
+        let token_client = token::Client::new(env, &order.token);
+        let contract = env.current_contract_address();
+
+        if fee > 0 {
+            token_client.transfer(&contract, &treasury, &fee);
+        }
+        if amount - fee > 0 {
+            token_client.transfer(&contract, buyer, &(amount - fee));
+        }
+
+        Ok(fee)
+    }
+
+    /// Return the full escrow to the seller and mark the order cancelled
+    fn refund_seller(env: &Env, mut order: Order) -> Result<(), Error> {
+        token::Client::new(env, &order.token).transfer(
+            &env.current_contract_address(),
+            &order.seller,
+            &order.amount,
+        );
+
+        order.status = OrderStatus::Cancelled;
+        env.storage()
+            .persistent()
+            .set(&DataKey::Order(order.id), &order);
+
+        Ok(())
+    }
+
     /// Validate that an order can be accepted by a buyer
     fn validate_order_acceptance(env: &Env, order: &Order, buyer: &Address) -> Result<(), Error> {
         if order.status != OrderStatus::Open {
@@ -339,8 +638,8 @@ impl EscrowContract {
 #[cfg(all(test, not(feature = "database")))]
 mod tests {
     use super::*;
-    use soroban_sdk::testutils::{Address as _, Ledger};
-    use soroban_sdk::{Address, Env};
+    use soroban_sdk::testutils::{Address as _, Events, Ledger};
+    use soroban_sdk::{Address, Env, TryFromVal};
 
     fn create_env() -> Env {
         Env::default()
@@ -357,9 +656,119 @@ mod tests {
 
     fn create_token(env: &Env, admin: &Address, user: &Address, amount: i128) -> Address {
         let sac = env.register_stellar_asset_contract_v2(admin.clone());
+        token::StellarAssetClient::new(env, &sac.address())
+            .mock_all_auths()
+            .mint(user, &amount);
         sac.address()
     }
 
+    struct Setup {
+        contract_id: Address,
+        seller: Address,
+        buyer: Address,
+        treasury: Address,
+        token: Address,
+    }
+
+    /// Initialized contract with a 0.5% fee and a seller holding 10_000 tokens
+    fn setup_escrow(env: &Env) -> Setup {
+        let contract_id = env.register_contract(None, EscrowContract);
+        let (admin, treasury, resolver, token_admin) = create_addresses(env);
+        let seller = Address::generate(env);
+        let buyer = Address::generate(env);
+        let token = create_token(env, &token_admin, &seller, 10_000);
+
+        env.as_contract(&contract_id, || {
+            EscrowContract::initialize(
+                env.clone(),
+                admin.clone(),
+                50,
+                treasury.clone(),
+                resolver.clone(),
+            )
+            .unwrap();
+        });
+        env.mock_all_auths();
+        env.ledger().set_timestamp(1000);
+
+        Setup {
+            contract_id,
+            seller,
+            buyer,
+            treasury,
+            token,
+        }
+    }
+
+    fn create_order(env: &Env, setup: &Setup, amount: i128) -> u64 {
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::create_order(
+                env.clone(),
+                setup.seller.clone(),
+                setup.token.clone(),
+                amount,
+                Symbol::new(env, "NGN"),
+                amount * 1500,
+                1500,
+                String::from_str(env, "Bank Transfer"),
+                env.ledger().timestamp() + 3600,
+            )
+            .unwrap()
+        })
+    }
+
+    /// Create an order and walk it to `status` with the setup's buyer
+    fn order_in_status(env: &Env, setup: &Setup, status: OrderStatus) -> u64 {
+        let order_id = create_order(env, setup, 1000);
+        if status == OrderStatus::Open {
+            return order_id;
+        }
+        let steps = [
+            OrderStatus::Locked,
+            OrderStatus::PaymentSent,
+            OrderStatus::Disputed,
+        ];
+        for step in steps.iter() {
+            // Each call runs in its own frame so the buyer can authorize every step
+            env.as_contract(&setup.contract_id, || match step {
+                OrderStatus::Locked => {
+                    EscrowContract::accept_order(env.clone(), order_id, setup.buyer.clone())
+                }
+                OrderStatus::PaymentSent => {
+                    EscrowContract::mark_payment_sent(env.clone(), order_id, setup.buyer.clone())
+                }
+                _ => EscrowContract::open_dispute(env.clone(), order_id, setup.buyer.clone()),
+            })
+            .unwrap();
+            if *step == status {
+                break;
+            }
+        }
+        order_id
+    }
+
+    fn balance(env: &Env, token: &Address, id: &Address) -> i128 {
+        token::Client::new(env, token).balance(id)
+    }
+
+    fn get_order(env: &Env, setup: &Setup, order_id: u64) -> Order {
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::get_order(env.clone(), order_id).unwrap()
+        })
+    }
+
+    /// Name of the most recent event published by the escrow contract
+    fn last_event(env: &Env, contract_id: &Address) -> Symbol {
+        let (_, topics, _) = env
+            .events()
+            .all()
+            .iter()
+            .filter(|(id, _, _)| id == contract_id)
+            .last()
+            .expect("no escrow events");
+        Symbol::try_from_val(env, &topics.get(0).unwrap()).unwrap()
+    }
+
     fn create_mock_order(
         env: &Env,
         seller: &Address,
@@ -945,4 +1354,458 @@ mod tests {
 
         assert_eq!(result, Err(Error::InvalidOrderStatus));
     }
+
+    #[test]
+    fn test_create_order_deposits_tokens() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+
+        let first = create_order(&env, &setup, 1000);
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "order_created")
+        );
+        let second = create_order(&env, &setup, 500);
+
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 8500);
+        assert_eq!(balance(&env, &setup.token, &setup.contract_id), 1500);
+
+        let order = get_order(&env, &setup, first);
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.seller, setup.seller);
+        assert_eq!(order.buyer, None);
+        assert_eq!(order.amount, 1000);
+        assert_eq!(order.created_at, 1000);
+        assert_eq!(order.expires_at, 4600);
+
+        let seller_orders = env.as_contract(&setup.contract_id, || {
+            EscrowContract::get_user_orders(env.clone(), setup.seller.clone())
+        });
+        assert_eq!(seller_orders, Vec::from_array(&env, [1u64, 2u64]));
+    }
+
+    #[test]
+    fn test_create_order_invalid_amount() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::create_order(
+                env.clone(),
+                setup.seller.clone(),
+                setup.token.clone(),
+                0,
+                Symbol::new(&env, "NGN"),
+                1500,
+                1500,
+                String::from_str(&env, "Bank Transfer"),
+                env.ledger().timestamp() + 3600,
+            )
+        });
+        assert_eq!(result, Err(Error::InvalidAmount));
+    }
+
+    #[test]
+    fn test_create_order_invalid_expiration() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::create_order(
+                env.clone(),
+                setup.seller.clone(),
+                setup.token.clone(),
+                1000,
+                Symbol::new(&env, "NGN"),
+                1_500_000,
+                1500,
+                String::from_str(&env, "Bank Transfer"),
+                env.ledger().timestamp(),
+            )
+        });
+        assert_eq!(result, Err(Error::InvalidExpiration));
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 10_000);
+    }
+
+    #[test]
+    fn test_create_order_when_paused() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::pause(env.clone()).unwrap();
+        });
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::create_order(
+                env.clone(),
+                setup.seller.clone(),
+                setup.token.clone(),
+                1000,
+                Symbol::new(&env, "NGN"),
+                1_500_000,
+                1500,
+                String::from_str(&env, "Bank Transfer"),
+                env.ledger().timestamp() + 3600,
+            )
+        });
+        assert_eq!(result, Err(Error::ContractPaused));
+    }
+
+    #[test]
+    fn test_accept_order_locks_escrow() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = create_order(&env, &setup, 1000);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::accept_order(env.clone(), order_id, setup.buyer.clone()).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "order_accepted")
+        );
+
+        let order = get_order(&env, &setup, order_id);
+        assert_eq!(order.status, OrderStatus::Locked);
+        assert_eq!(order.buyer, Some(setup.buyer.clone()));
+        // Funds were deposited at creation, accepting does not move them again
+        assert_eq!(balance(&env, &setup.token, &setup.contract_id), 1000);
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 9000);
+
+        let buyer_orders = env.as_contract(&setup.contract_id, || {
+            EscrowContract::get_user_orders(env.clone(), setup.buyer.clone())
+        });
+        assert_eq!(buyer_orders, Vec::from_array(&env, [order_id]));
+    }
+
+    #[test]
+    fn test_full_lifecycle_release_with_fee() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Locked);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::mark_payment_sent(env.clone(), order_id, setup.buyer.clone()).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "payment_sent")
+        );
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::PaymentSent
+        );
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::release(env.clone(), order_id).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "order_released")
+        );
+
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::Completed
+        );
+        assert_eq!(balance(&env, &setup.token, &setup.buyer), 995);
+        assert_eq!(balance(&env, &setup.token, &setup.treasury), 5);
+        assert_eq!(balance(&env, &setup.token, &setup.contract_id), 0);
+    }
+
+    #[test]
+    fn test_release_open_order_fails() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Open);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::release(env.clone(), order_id)
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+    }
+
+    #[test]
+    fn test_release_twice_fails() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::PaymentSent);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::release(env.clone(), order_id).unwrap();
+        });
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::release(env.clone(), order_id)
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+        assert_eq!(balance(&env, &setup.token, &setup.buyer), 995);
+    }
+
+    #[test]
+    fn test_mark_payment_sent_by_other_address() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Locked);
+        let stranger = Address::generate(&env);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::mark_payment_sent(env.clone(), order_id, stranger.clone())
+        });
+        assert_eq!(result, Err(Error::Unauthorized));
+    }
+
+    #[test]
+    fn test_mark_payment_sent_requires_locked_order() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Open);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::mark_payment_sent(env.clone(), order_id, setup.buyer.clone())
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+    }
+
+    #[test]
+    fn test_seller_cancels_open_order() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Open);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::cancel(env.clone(), order_id, setup.seller.clone()).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "order_cancelled")
+        );
+
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::Cancelled
+        );
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 10_000);
+        assert_eq!(balance(&env, &setup.token, &setup.contract_id), 0);
+    }
+
+    #[test]
+    fn test_buyer_cancels_locked_order() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Locked);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::cancel(env.clone(), order_id, setup.buyer.clone()).unwrap();
+        });
+
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::Cancelled
+        );
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 10_000);
+    }
+
+    #[test]
+    fn test_seller_cannot_cancel_locked_order() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Locked);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::cancel(env.clone(), order_id, setup.seller.clone())
+        });
+        assert_eq!(result, Err(Error::Unauthorized));
+    }
+
+    #[test]
+    fn test_cancel_after_payment_sent_fails() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::PaymentSent);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::cancel(env.clone(), order_id, setup.buyer.clone())
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+    }
+
+    #[test]
+    fn test_refund_expired_order() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Locked);
+
+        env.ledger().set_timestamp(4601);
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::refund_expired(env.clone(), order_id).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "order_expired")
+        );
+
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::Cancelled
+        );
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 10_000);
+    }
+
+    #[test]
+    fn test_refund_before_expiry_fails() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Open);
+
+        env.ledger().set_timestamp(4600);
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::refund_expired(env.clone(), order_id)
+        });
+        assert_eq!(result, Err(Error::OrderNotExpired));
+    }
+
+    #[test]
+    fn test_refund_expired_after_payment_sent_fails() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::PaymentSent);
+
+        env.ledger().set_timestamp(10_000);
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::refund_expired(env.clone(), order_id)
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+    }
+
+    #[test]
+    fn test_open_dispute() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::PaymentSent);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::open_dispute(env.clone(), order_id, setup.seller.clone()).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "dispute_opened")
+        );
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::Disputed
+        );
+    }
+
+    #[test]
+    fn test_open_dispute_requires_payment_sent() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Locked);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::open_dispute(env.clone(), order_id, setup.buyer.clone())
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+    }
+
+    #[test]
+    fn test_open_dispute_by_other_address() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::PaymentSent);
+        let stranger = Address::generate(&env);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::open_dispute(env.clone(), order_id, stranger.clone())
+        });
+        assert_eq!(result, Err(Error::Unauthorized));
+    }
+
+    #[test]
+    fn test_resolve_dispute_with_split() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Disputed);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::resolve_dispute(env.clone(), order_id, 600).unwrap();
+        });
+        assert_eq!(
+            last_event(&env, &setup.contract_id),
+            Symbol::new(&env, "dispute_resolved")
+        );
+
+        assert_eq!(
+            get_order(&env, &setup, order_id).status,
+            OrderStatus::Completed
+        );
+        assert_eq!(balance(&env, &setup.token, &setup.buyer), 597);
+        assert_eq!(balance(&env, &setup.token, &setup.treasury), 3);
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 9400);
+        assert_eq!(balance(&env, &setup.token, &setup.contract_id), 0);
+    }
+
+    #[test]
+    fn test_resolve_dispute_fully_to_seller() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Disputed);
+
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::resolve_dispute(env.clone(), order_id, 0).unwrap();
+        });
+
+        assert_eq!(balance(&env, &setup.token, &setup.buyer), 0);
+        assert_eq!(balance(&env, &setup.token, &setup.treasury), 0);
+        assert_eq!(balance(&env, &setup.token, &setup.seller), 10_000);
+    }
+
+    #[test]
+    fn test_resolve_dispute_invalid_split() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Disputed);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::resolve_dispute(env.clone(), order_id, 1001)
+        });
+        assert_eq!(result, Err(Error::InvalidSplit));
+    }
+
+    #[test]
+    fn test_resolve_dispute_requires_disputed_order() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::PaymentSent);
+
+        let result = env.as_contract(&setup.contract_id, || {
+            EscrowContract::resolve_dispute(env.clone(), order_id, 500)
+        });
+        assert_eq!(result, Err(Error::InvalidOrderStatus));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_non_resolver_cannot_resolve_dispute() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+        let order_id = order_in_status(&env, &setup, OrderStatus::Disputed);
+
+        env.set_auths(&[]);
+        env.as_contract(&setup.contract_id, || {
+            EscrowContract::resolve_dispute(env.clone(), order_id, 500).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_calculate_fee() {
+        let env = create_env();
+        let setup = setup_escrow(&env);
+
+        let fee = env.as_contract(&setup.contract_id, || {
+            EscrowContract::calculate_fee(env.clone(), 10_000)
+        });
+        assert_eq!(fee, 50);
+    }
 }
@@ -1,4 +1,5 @@
 mod api;
+mod auth;
 mod cache;
 mod chains;
 mod config;
@@ -15,11 +16,9 @@ mod workers;
 use crate::health::{HealthChecker, HealthStatus};
 use crate::logging::init_tracing;
 use crate::payments::factory::PaymentProviderFactory;
-use crate::payments::types::{
-    CustomerContact, Money, PaymentMethod, PaymentRequest as ProviderPaymentRequest, ProviderName,
-};
+use crate::payments::types::{PaymentMethod, ProviderName};
 use axum::{
-    routing::{get, patch, post},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use cache::{init_cache_pool, CacheConfig, RedisCache};
@@ -36,7 +35,7 @@ use tokio::signal;
 use tokio::sync::watch;
 use tower::ServiceBuilder;
 use tower_http::request_id::{PropagateRequestIdLayer, SetRequestIdLayer};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 /// Graceful shutdown signal handler
@@ -297,8 +296,6 @@ async fn main() -> anyhow::Result<()> {
     info!("🏥 Initializing health checker...");
     let health_checker =
         HealthChecker::new(db_pool.clone(), redis_cache.clone(), stellar_client.clone());
-    // Initialize notification service
-    let notification_service = std::sync::Arc::new(services::notification::NotificationService::new());
 
     // Initialize payment provider factory
     let provider_factory = if db_pool.is_some() {
@@ -314,6 +311,16 @@ async fn main() -> anyhow::Result<()> {
     };
 
     let (worker_shutdown_tx, worker_shutdown_rx) = watch::channel(false);
+
+    // Outgoing merchant webhooks: state transitions queue events, a worker delivers them
+    let merchant_webhook_repo = db_pool.clone().map(|pool| {
+        std::sync::Arc::new(
+            database::merchant_webhook_repository::MerchantWebhookRepository::new(pool),
+        )
+    });
+    let merchant_webhook_service = merchant_webhook_repo.clone().map(|repo| {
+        std::sync::Arc::new(services::merchant_webhooks::MerchantWebhookService::new(repo))
+    });
     
     // Start Transaction Monitor Worker
     let monitor_enabled = std::env::var("TX_MONITOR_ENABLED")
@@ -330,11 +337,14 @@ async fn main() -> anyhow::Result<()> {
                 max_retries = monitor_config.max_retries,
                 "Starting Stellar transaction monitoring worker"
             );
-            let worker = workers::transaction_monitor::TransactionMonitorWorker::new(
+            let mut worker = workers::transaction_monitor::TransactionMonitorWorker::new(
                 pool,
                 client,
                 monitor_config,
             );
+            if let Some(service) = merchant_webhook_service.clone() {
+                worker = worker.with_merchant_webhooks(service);
+            }
             monitor_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
         } else {
             info!(
@@ -345,6 +355,172 @@ async fn main() -> anyhow::Result<()> {
         info!("Stellar transaction monitor worker disabled (TX_MONITOR_ENABLED=false)");
     }
 
+    // Start Merchant Webhook Delivery Worker
+    let merchant_webhooks_enabled = std::env::var("MERCHANT_WEBHOOKS_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+    let mut merchant_webhook_handle = None;
+    if merchant_webhooks_enabled {
+        if let Some(repo) = merchant_webhook_repo.clone() {
+            let config = workers::merchant_webhooks::MerchantWebhookWorkerConfig::from_env();
+            info!(
+                poll_interval_secs = config.poll_interval.as_secs(),
+                max_attempts = config.max_attempts,
+                "Starting merchant webhook worker"
+            );
+            let worker = workers::merchant_webhooks::MerchantWebhookWorker::new(repo, config);
+            merchant_webhook_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
+        } else {
+            info!("Skipping merchant webhook worker (missing db pool)");
+        }
+    } else {
+        info!("Merchant webhook worker disabled (MERCHANT_WEBHOOKS_ENABLED=false)");
+    }
+
+    // User notifications: workers queue rendered messages per channel, a dispatcher sends them
+    let notification_repo = db_pool.clone().map(|pool| {
+        std::sync::Arc::new(database::notification_repository::NotificationRepository::new(pool))
+    });
+    let notification_channels =
+        services::notification::channels::channels_from_env(merchant_webhook_service.clone());
+    let notification_service = std::sync::Arc::new(match notification_repo.clone() {
+        Some(repo) if !notification_channels.is_empty() => {
+            services::notification::NotificationService::new()
+                .with_outbox(repo, notification_channels.keys().copied().collect())
+        }
+        _ => services::notification::NotificationService::new(),
+    });
+
+    let notifications_enabled = std::env::var("NOTIFICATIONS_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+    let mut notification_handle = None;
+    if notifications_enabled {
+        match notification_repo.clone() {
+            Some(_) if notification_channels.is_empty() => {
+                info!("Skipping notification dispatcher (no channels configured)");
+            }
+            Some(repo) => {
+                let config =
+                    workers::notification_dispatcher::NotificationDispatcherConfig::from_env();
+                info!(
+                    poll_interval_secs = config.poll_interval.as_secs(),
+                    max_attempts = config.max_attempts,
+                    "Starting notification dispatcher"
+                );
+                let dispatcher = workers::notification_dispatcher::NotificationDispatcher::new(
+                    repo,
+                    notification_channels,
+                    config,
+                );
+                notification_handle =
+                    Some(tokio::spawn(dispatcher.run(worker_shutdown_rx.clone())));
+            }
+            None => info!("Skipping notification dispatcher (missing db pool)"),
+        }
+    } else {
+        info!("Notification dispatcher disabled (NOTIFICATIONS_ENABLED=false)");
+    }
+
+    // Hot wallet signer shared by the payout workers and admin refunds
+    let hot_wallet_signer = match chains::stellar::signer::hot_wallet_signer_from_env() {
+        Ok(Some(signer)) => {
+            info!(account_id = signer.account_id(), "Hot wallet signer initialized");
+            Some(signer)
+        }
+        Ok(None) => {
+            warn!("No hot wallet signer configured (HOT_WALLET_SIGNER)");
+            None
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to initialize hot wallet signer");
+            None
+        }
+    };
+
+    // Channel accounts let the hot wallet have several payout transactions in flight
+    let channel_pool = match (stellar_client.clone(), chains::stellar::channel::ChannelConfig::from_env()) {
+        (Some(client), Ok(channel_config)) if channel_config.is_enabled() => {
+            match chains::stellar::channel::ChannelAccountPool::new(client, channel_config) {
+                Ok(pool) => {
+                    info!(channels = pool.accounts().len(), "Channel account pool initialized");
+                    Some(pool)
+                }
+                Err(e) => {
+                    error!(error = %e, "Failed to initialize channel account pool");
+                    None
+                }
+            }
+        }
+        (_, Err(e)) => {
+            error!(error = %e, "Failed to load channel account signers");
+            None
+        }
+        _ => None,
+    };
+
+    // Every payout from the hot wallet goes through one submission queue so
+    // concurrent workers never race for the same sequence number
+    let payout_queue = match (stellar_client.clone(), hot_wallet_signer.clone()) {
+        (Some(client), Some(signer)) => {
+            let config = chains::stellar::submission::SubmissionConfig::from_env();
+            Some(match channel_pool.clone() {
+                Some(pool) => chains::stellar::submission::SubmissionQueue::with_channel_pool(
+                    client, signer, config, pool,
+                ),
+                None => chains::stellar::submission::SubmissionQueue::new(client, signer, config),
+            })
+        }
+        _ => None,
+    };
+
+    let mut channel_monitor_handle = None;
+    match (channel_pool.clone(), payout_queue.clone()) {
+        (Some(pool), Some(queue)) => {
+            let worker = workers::channel_account_monitor::ChannelAccountMonitorWorker::new(pool, queue);
+            channel_monitor_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
+        }
+        (Some(_), None) => {
+            warn!("Channel accounts configured but no hot wallet signer to fund them (HOT_WALLET_SIGNER)");
+        }
+        _ => {}
+    }
+
+    // Treasury: hot wallet thresholds, alerts and sweeps to cold storage
+    let treasury_service = match (db_pool.clone(), stellar_client.clone()) {
+        (Some(pool), Some(client)) => match services::treasury::TreasuryConfig::from_env()
+            .and_then(|config| config.validate().map(|_| config))
+        {
+            Ok(config) => Some(std::sync::Arc::new(
+                services::treasury::TreasuryService::new(client, pool, config)
+                    .with_submission_queue(payout_queue.clone()),
+            )),
+            Err(e) => {
+                warn!(error = %e, "Treasury not configured");
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let treasury_monitor_enabled = std::env::var("TREASURY_MONITOR_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+    let mut treasury_handle = None;
+    if treasury_monitor_enabled {
+        if let Some(service) = treasury_service.clone() {
+            let worker = workers::treasury_monitor::TreasuryMonitorWorker::new(service);
+            treasury_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
+        } else {
+            info!("Skipping treasury monitor (treasury not configured)");
+        }
+    } else {
+        info!("Treasury monitor disabled (TREASURY_MONITOR_ENABLED=false)");
+    }
+
     // Start Offramp Processor Worker
     let offramp_enabled = std::env::var("OFFRAMP_PROCESSOR_ENABLED")
         .unwrap_or_else(|_| "true".to_string())
@@ -352,7 +528,8 @@ async fn main() -> anyhow::Result<()> {
     let mut offramp_handle = None;
     if offramp_enabled {
         if let (Some(pool), Some(client), Some(factory)) = (db_pool.clone(), stellar_client.clone(), provider_factory.clone()) {
-            let config = workers::offramp_processor::OfframpProcessorConfig::from_env();
+            let mut config = workers::offramp_processor::OfframpProcessorConfig::from_env();
+            config.payout_queue = payout_queue.clone();
             if let Err(e) = config.validate() {
                 error!(error = %e, "Invalid offramp processor configuration, skipping worker");
             } else {
@@ -361,13 +538,16 @@ async fn main() -> anyhow::Result<()> {
                     batch_size = config.batch_size,
                     "Starting offramp processor worker"
                 );
-                let worker = workers::offramp_processor::OfframpProcessorWorker::new(
+                let mut worker = workers::offramp_processor::OfframpProcessorWorker::new(
                     pool,
                     client,
                     factory,
                     notification_service.clone(),
                     config,
                 );
+                if let Some(service) = merchant_webhook_service.clone() {
+                    worker = worker.with_merchant_webhooks(service);
+                }
                 offramp_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
             }
         } else {
@@ -377,8 +557,93 @@ async fn main() -> anyhow::Result<()> {
         info!("Offramp processor worker disabled (OFFRAMP_PROCESSOR_ENABLED=false)");
     }
 
+    // Start Onramp Processor Worker
+    let onramp_enabled = std::env::var("ONRAMP_PROCESSOR_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+    let mut onramp_handle = None;
+    if onramp_enabled {
+        if let Some(pool) = db_pool.clone() {
+            let mut config = workers::onramp_processor::OnrampProcessorConfig::from_env();
+            config.payout_queue = payout_queue.clone();
+            if let Err(e) = config.validate() {
+                error!(error = %e, "Invalid onramp processor configuration, skipping worker");
+            } else {
+                info!(
+                    poll_interval_secs = config.poll_interval.as_secs(),
+                    batch_size = config.batch_size,
+                    "Starting onramp processor worker"
+                );
+                let mut worker = workers::onramp_processor::OnrampProcessorWorker::new(
+                    pool,
+                    notification_service.clone(),
+                    config,
+                );
+                if let Some(service) = merchant_webhook_service.clone() {
+                    worker = worker.with_merchant_webhooks(service);
+                }
+                onramp_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
+            }
+        } else {
+            info!("Skipping onramp processor worker (missing db pool)");
+        }
+    } else {
+        info!("Onramp processor worker disabled (ONRAMP_PROCESSOR_ENABLED=false)");
+    }
+
+    // Start Bill Processor Worker
+    let bill_processor_enabled = std::env::var("BILL_PROCESSOR_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+    let mut bill_processor_handle = None;
+    if bill_processor_enabled {
+        if let Some(pool) = db_pool.clone() {
+            let mut config = workers::bill_processor::worker::BillProcessorConfig::from_env();
+            config.payout_queue = payout_queue.clone();
+            let providers = workers::bill_processor::providers::providers_from_env();
+            if let Err(e) = config.validate() {
+                error!(error = %e, "Invalid bill processor configuration, skipping worker");
+            } else if providers.is_empty() {
+                warn!("No bill payment provider credentials configured, skipping bill processor worker");
+            } else {
+                info!(
+                    poll_interval_secs = config.poll_interval.as_secs(),
+                    batch_size = config.batch_size,
+                    max_attempts = config.retry.max_attempts,
+                    "Starting bill processor worker"
+                );
+                let mut worker = workers::bill_processor::worker::BillProcessorWorker::new(
+                    pool,
+                    providers,
+                    notification_service.clone(),
+                    config,
+                );
+                if let Some(service) = merchant_webhook_service.clone() {
+                    worker = worker.with_merchant_webhooks(service);
+                }
+                bill_processor_handle = Some(tokio::spawn(worker.run(worker_shutdown_rx.clone())));
+            }
+        } else {
+            info!("Skipping bill processor worker (missing db pool)");
+        }
+    } else {
+        info!("Bill processor worker disabled (BILL_PROCESSOR_ENABLED=false)");
+    }
+
+    // Idempotency store shared by the orchestrator and the Idempotency-Key middleware
+    let idempotency_store = std::sync::Arc::new(services::idempotency::IdempotencyStore::new(
+        redis_cache.clone(),
+        db_pool.clone().map(|pool| {
+            std::sync::Arc::new(
+                database::idempotency_repository::IdempotencyRepository::new(pool),
+            )
+        }),
+    ));
+
     // Initialize webhook processor and retry worker
-    let webhook_routes = if let Some(pool) = db_pool.clone() {
+    let (webhook_routes, payment_orchestrator) = if let Some(pool) = db_pool.clone() {
         let webhook_repo = std::sync::Arc::new(
             database::webhook_repository::WebhookRepository::new(pool.clone()),
         );
@@ -403,18 +668,22 @@ async fn main() -> anyhow::Result<()> {
             }
         }
 
-        let orchestrator =
-            std::sync::Arc::new(services::payment_orchestrator::PaymentOrchestrator::new(
-                providers,
-                transaction_repo,
-                orchestrator_config,
-            ));
+        let mut orchestrator = services::payment_orchestrator::PaymentOrchestrator::new(
+            providers,
+            transaction_repo,
+            orchestrator_config,
+        )
+        .with_idempotency_store(idempotency_store.clone());
+        if let Some(service) = merchant_webhook_service.clone() {
+            orchestrator = orchestrator.with_merchant_webhooks(service);
+        }
+        let orchestrator = std::sync::Arc::new(orchestrator);
 
         let webhook_processor =
             std::sync::Arc::new(services::webhook_processor::WebhookProcessor::new(
                 webhook_repo,
                 provider_factory,
-                orchestrator,
+                orchestrator.clone(),
             ));
 
         // Start webhook retry worker
@@ -438,19 +707,86 @@ async fn main() -> anyhow::Result<()> {
             processor: webhook_processor,
         };
 
-        Router::new()
+        let routes = Router::new()
             .route("/webhooks/{provider}", post(api::webhooks::handle_webhook))
-            .with_state(std::sync::Arc::new(webhook_state))
+            .with_state(std::sync::Arc::new(webhook_state));
+        (routes, Some(orchestrator))
     } else {
         info!("⏭️  Skipping webhook routes (no database)");
-        Router::new()
+        (Router::new(), None)
+    };
+
+    // Authentication: hashed API keys for partners, JWT sessions for end users
+    let auth_enabled = std::env::var("AUTH_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+
+    let auth_service = if auth_enabled {
+        let jwt = match auth::jwt::JwtConfig::from_env()? {
+            Some(config) => {
+                info!(algorithm = config.algorithm().as_str(), "✅ JWT authentication configured");
+                Some(auth::jwt::JwtService::new(config))
+            }
+            None => {
+                warn!("⚠️  JWT authentication not configured (set JWT_SECRET or JWT_ED25519_*)");
+                None
+            }
+        };
+        let clients = db_pool.clone().map(|pool| {
+            std::sync::Arc::new(database::api_client_repository::ApiClientRepository::new(pool))
+        });
+        Some(std::sync::Arc::new(auth::AuthService::new(
+            jwt,
+            clients,
+            redis_cache.clone(),
+        )))
+    } else {
+        warn!("⚠️  API authentication disabled (AUTH_ENABLED=false)");
+        None
+    };
+
+    // Redis-backed rate limiting, shared across instances
+    let rate_limit_enabled = std::env::var("RATE_LIMIT_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+    let rate_limiter = if rate_limit_enabled {
+        middleware::rate_limit::RateLimiter::new(redis_cache.clone())
+    } else {
+        info!("Rate limiting disabled (RATE_LIMIT_ENABLED=false)");
+        middleware::rate_limit::RateLimiter::new(None)
     };
 
+    // Forwarding headers are only believed from these proxies
+    let trusted_proxies = std::sync::Arc::new(
+        middleware::client_ip::TrustedProxies::from_env().map_err(|e| anyhow::anyhow!(e))?,
+    );
+
     // Create the application router with logging middleware
     info!("🛣️  Setting up application routes...");
 
+    // KYC: SEP-12 customers and the tier limits onramp and offramp quotes are held to
+    let kyc_service = match (
+        services::kyc::KycConfig::from_env()?,
+        db_pool.clone(),
+        stellar_client.as_ref(),
+    ) {
+        (Some(config), Some(pool), Some(client)) => {
+            let provider = services::kyc_providers::provider_for(&config.provider, client.network())?;
+            let service = services::kyc::KycService::new(config, pool, provider);
+            info!(provider = service.provider_name(), "✅ KYC verification enabled");
+            Some(std::sync::Arc::new(service))
+        }
+        (Some(_), _, _) => {
+            warn!("⚠️  KYC_ENCRYPTION_KEY set but KYC needs the database and a Stellar client");
+            None
+        }
+        _ => None,
+    };
+
     // Setup onramp routes (quote service)
-    let onramp_routes = if let (Some(pool), Some(cache), Some(client)) =
+    let (onramp_routes, onramp_services) = if let (Some(pool), Some(cache), Some(client)) =
         (db_pool.clone(), redis_cache.clone(), stellar_client.clone())
     {
         let cngn_issuer = std::env::var("CNGN_ISSUER_ADDRESS")
@@ -476,13 +812,20 @@ async fn main() -> anyhow::Result<()> {
             .with_fee_service(fee_service.clone()),
         );
 
-        let quote_service = std::sync::Arc::new(services::onramp_quote::OnrampQuoteService::new(
-            exchange_rate_service,
+        let mut quote_service = services::onramp_quote::OnrampQuoteService::new(
+            exchange_rate_service.clone(),
             fee_service,
             client.clone(),
             cache.clone(),
             cngn_issuer,
-        ));
+        )
+        .with_quote_repository(
+            database::onramp_quote_repository::OnrampQuoteRepository::new(pool.clone()),
+        );
+        if let Some(kyc) = kyc_service.clone() {
+            quote_service = quote_service.with_kyc(kyc);
+        }
+        let quote_service = std::sync::Arc::new(quote_service);
 
         // Setup onramp status service
         let transaction_repo = std::sync::Arc::new(
@@ -501,13 +844,41 @@ async fn main() -> anyhow::Result<()> {
             payment_factory,
         ));
 
-        Router::new()
-            .route("/api/onramp/quote", post(create_onramp_quote))
-            .with_state(quote_service)
-            .route("/api/onramp/status/:tx_id", get(api::onramp::get_onramp_status))
-            .with_state(status_service)
+        let quote_routes = middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route("/api/onramp/quote", post(create_onramp_quote))
+                .with_state(quote_service.clone()),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::quotes(),
+            |routes| {
+                middleware::auth::protect(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::QUOTES_WRITE],
+                )
+            },
+        );
+        let status_routes = middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route("/api/onramp/status/:tx_id", get(api::onramp::get_onramp_status))
+                .with_state(status_service),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+            |routes| {
+                middleware::auth::protect(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::PAYMENTS_READ],
+                )
+            },
+        );
+
+        (
+            quote_routes.merge(status_routes),
+            Some((quote_service, exchange_rate_service)),
+        )
     } else {
-        Router::new()
+        (Router::new(), None)
     };
 
     // Setup wallet routes with balance service
@@ -523,12 +894,299 @@ async fn main() -> anyhow::Result<()> {
         
         let wallet_state = api::wallet::WalletState { balance_service };
         
-        Router::new()
-            .route("/api/wallet/balance", get(api::wallet::get_balance))
-            .with_state(wallet_state)
+        middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route("/api/wallet/balance", get(api::wallet::get_balance))
+                .with_state(wallet_state),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+            |routes| {
+                middleware::auth::protect(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::WALLET_READ],
+                )
+            },
+        )
     } else {
         Router::new()
     };
+
+    // SEP-10: wallets sign a challenge for their account and get a JWT for it
+    let (sep10_routes, sep10_signing_key) = match (
+        auth::sep10::Sep10Config::from_env()?,
+        auth_service.clone(),
+        stellar_client.clone(),
+    ) {
+        (Some(config), Some(auth), Some(client)) => {
+            let service = auth::sep10::Sep10Service::new(
+                config,
+                client.network().network_passphrase(),
+            );
+            info!(
+                signing_key = service.server_account(),
+                home_domain = %service.config().home_domain,
+                "✅ SEP-10 web authentication enabled"
+            );
+            let signing_key = service.server_account().to_string();
+            let routes = middleware::rate_limit::rate_limit(
+                Router::new()
+                    .route(
+                        "/auth",
+                        get(api::sep10::get_challenge).post(api::sep10::post_token),
+                    )
+                    .with_state(api::sep10::Sep10State {
+                        service: std::sync::Arc::new(service),
+                        auth,
+                        stellar_client: client,
+                    }),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::standard(),
+            );
+            (routes, Some(signing_key))
+        }
+        (Some(_), _, _) => {
+            warn!("⚠️  SEP-10 signing key set but SEP-10 needs authentication and a Stellar client");
+            (Router::new(), None)
+        }
+        _ => (Router::new(), None),
+    };
+
+    // SEP-38: indicative prices and firm quotes for NGN <-> cNGN
+    let sep38_enabled = std::env::var("SEP38_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .to_lowercase()
+        != "false";
+
+    let (sep38_routes, sep38_served) = match (
+        onramp_services.clone(),
+        auth_service.clone(),
+        stellar_client.clone(),
+    ) {
+        (Some((quote_service, _)), Some(auth), Some(client)) if sep38_enabled => {
+            let cngn = chains::stellar::trustline::CngnAssetConfig::from_env();
+            let service = services::sep38::Sep38Service::new(
+                quote_service,
+                &cngn.asset_code,
+                cngn.issuer_for_network(client.network()),
+            );
+            info!(asset = service.stellar_asset(), "✅ SEP-38 quotes enabled");
+            let state = api::sep38::Sep38State {
+                service: std::sync::Arc::new(service),
+            };
+
+            let price_routes = middleware::rate_limit::rate_limit(
+                Router::new()
+                    .route("/sep38/info", get(api::sep38::get_info))
+                    .route("/sep38/prices", get(api::sep38::get_prices))
+                    .route("/sep38/price", get(api::sep38::get_price))
+                    .with_state(state.clone()),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::quotes(),
+            );
+            let quote_routes = middleware::rate_limit::rate_limit_protected(
+                Router::new()
+                    .route("/sep38/quote", post(api::sep38::post_quote))
+                    .route("/sep38/quote/{id}", get(api::sep38::get_quote))
+                    .with_state(state),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::quotes(),
+                |routes| {
+                    middleware::auth::protect(routes, Some(&auth), &[auth::scopes::QUOTES_WRITE])
+                },
+            );
+
+            (price_routes.merge(quote_routes), true)
+        }
+        _ => (Router::new(), false),
+    };
+
+    // SEP-24: hosted deposit and withdrawal on top of the onramp and offramp flows.
+    // Every route is also served under /sep24 so stellar.toml can publish a
+    // single TRANSFER_SERVER_SEP0024 base URL.
+    let (sep24_routes, sep24_served) = match (
+        services::sep24::Sep24Config::from_env()?,
+        db_pool.clone(),
+        auth_service.clone(),
+        stellar_client.clone(),
+        payment_orchestrator.clone(),
+        onramp_services.clone(),
+    ) {
+        (
+            Some(mut config),
+            Some(pool),
+            Some(auth),
+            Some(client),
+            Some(orchestrator),
+            Some((quote_service, exchange_rate_service)),
+        ) => {
+            let cngn = chains::stellar::trustline::CngnAssetConfig::from_env();
+            config.asset_issuer = cngn.issuer_for_network(client.network()).to_string();
+            config.asset_code = cngn.asset_code;
+            config.withdraw_account = std::env::var("SYSTEM_WALLET_ADDRESS").unwrap_or_default();
+            info!(
+                interactive_url = %config.interactive_url,
+                asset = %config.stellar_asset(),
+                "✅ SEP-24 interactive deposit and withdrawal enabled"
+            );
+            let mut service = services::sep24::Sep24Service::new(
+                config,
+                pool,
+                quote_service,
+                orchestrator,
+                exchange_rate_service,
+                auth.clone(),
+            );
+            if let Some(kyc) = kyc_service.clone() {
+                service = service.with_kyc(kyc);
+            }
+            let state = api::sep24::Sep24State {
+                service: std::sync::Arc::new(service),
+            };
+
+            let info_routes = middleware::rate_limit::rate_limit(
+                Router::new()
+                    .route("/sep24/info", get(api::sep24::get_info))
+                    .with_state(state.clone()),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::standard(),
+            );
+            let transfer_routes = middleware::rate_limit::rate_limit_protected(
+                Router::new()
+                    .route(
+                        "/transactions/deposit/interactive",
+                        post(api::sep24::post_deposit_interactive),
+                    )
+                    .route(
+                        "/transactions/withdraw/interactive",
+                        post(api::sep24::post_withdraw_interactive),
+                    )
+                    .route(
+                        "/sep24/transactions/deposit/interactive",
+                        post(api::sep24::post_deposit_interactive),
+                    )
+                    .route(
+                        "/sep24/transactions/withdraw/interactive",
+                        post(api::sep24::post_withdraw_interactive),
+                    )
+                    .route(
+                        "/sep24/transactions/{id}/deposit",
+                        post(api::sep24::post_deposit_details),
+                    )
+                    .route(
+                        "/sep24/transactions/{id}/withdraw",
+                        post(api::sep24::post_withdraw_details),
+                    )
+                    .with_state(state.clone()),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::standard(),
+                |routes| {
+                    middleware::auth::protect(routes, Some(&auth), &[auth::scopes::TRANSFERS_WRITE])
+                },
+            );
+            let read_routes = middleware::rate_limit::rate_limit_protected(
+                Router::new()
+                    .route("/transaction", get(api::sep24::get_transaction))
+                    .route("/transactions", get(api::sep24::get_transactions))
+                    .route("/sep24/transaction", get(api::sep24::get_transaction))
+                    .route("/sep24/transactions", get(api::sep24::get_transactions))
+                    .with_state(state),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::standard(),
+                |routes| {
+                    middleware::auth::protect(routes, Some(&auth), &[auth::scopes::WALLET_READ])
+                },
+            );
+
+            (info_routes.merge(transfer_routes).merge(read_routes), true)
+        }
+        (Some(_), ..) => {
+            warn!("⚠️  SEP24_INTERACTIVE_URL set but SEP-24 needs the database, authentication, a Stellar client and the onramp services");
+            (Router::new(), false)
+        }
+        _ => (Router::new(), false),
+    };
+
+    // SEP-12: KYC customer information for the authenticated account
+    let (sep12_routes, sep12_served) = match (kyc_service.clone(), auth_service.clone()) {
+        (Some(kyc), Some(auth)) => {
+            info!("✅ SEP-12 customer endpoints enabled");
+            let routes = middleware::rate_limit::rate_limit_protected(
+                Router::new()
+                    .route(
+                        "/kyc/customer",
+                        get(api::sep12::get_customer).put(api::sep12::put_customer),
+                    )
+                    .route(
+                        "/kyc/customer/{account}",
+                        delete(api::sep12::delete_customer),
+                    )
+                    .with_state(api::sep12::Sep12State { service: kyc }),
+                &rate_limiter,
+                middleware::rate_limit::RateLimitPolicy::standard(),
+                |routes| middleware::auth::protect(routes, Some(&auth), &[auth::scopes::KYC_WRITE]),
+            );
+            (routes, true)
+        }
+        (Some(_), None) => {
+            warn!("⚠️  KYC is enabled but SEP-12 needs authentication");
+            (Router::new(), false)
+        }
+        _ => (Router::new(), false),
+    };
+
+    // SEP-1: stellar.toml advertising the cNGN asset and the SEP endpoints served above.
+    // A misconfigured file is a boot error rather than something wallets discover.
+    let stellar_toml_routes = match (
+        services::stellar_toml::StellarTomlConfig::from_env(),
+        stellar_client.clone(),
+    ) {
+        (Some(config), Some(client)) => {
+            use services::stellar_toml::{Endpoint, StellarToml, StellarTomlError};
+
+            let mut toml = StellarToml::new(
+                config,
+                client.network().clone(),
+                chains::stellar::trustline::CngnAssetConfig::from_env(),
+            );
+            if let Some(signing_key) = sep10_signing_key {
+                toml = toml
+                    .with_signing_key(signing_key)
+                    .with_endpoint(Endpoint::WebAuth, "/auth");
+            }
+            if sep24_served {
+                toml = toml.with_endpoint(Endpoint::TransferServerSep24, "/sep24");
+            }
+            if sep38_served {
+                toml = toml.with_endpoint(Endpoint::QuoteServer, "/sep38");
+            }
+            if sep12_served {
+                toml = toml.with_endpoint(Endpoint::KycServer, "/kyc");
+            }
+            toml.validate()?;
+            match toml.verify_on_network(&client).await {
+                Ok(()) => {}
+                Err(e @ StellarTomlError::IssuerNotOnNetwork { .. }) => return Err(e.into()),
+                Err(e) => warn!(error = %e, "⚠️  Could not confirm the cNGN issuer on the network"),
+            }
+            info!(issuer = toml.issuer(), "✅ stellar.toml enabled");
+
+            Router::new()
+                .route(
+                    "/.well-known/stellar.toml",
+                    get(api::stellar_toml::get_stellar_toml)
+                        .options(api::stellar_toml::options_stellar_toml),
+                )
+                .with_state(api::stellar_toml::StellarTomlState {
+                    body: toml.render().into(),
+                })
+        }
+        (Some(_), None) => {
+            warn!("⚠️  STELLAR_TOML_BASE_URL set but stellar.toml needs a Stellar client");
+            Router::new()
+        }
+        _ => Router::new(),
+    };
     
     // Setup rates API routes with exchange rate service
     let rates_routes = if let Some(pool) = db_pool.clone() {
@@ -549,65 +1207,336 @@ async fn main() -> anyhow::Result<()> {
             cache: redis_cache.clone().map(std::sync::Arc::new),
         };
         
-        Router::new()
-            .route("/api/rates", get(api::rates::get_rates).options(api::rates::options_rates))
-            .with_state(rates_state)
+        middleware::rate_limit::rate_limit(
+            Router::new()
+                .route("/api/rates", get(api::rates::get_rates).options(api::rates::options_rates))
+                .with_state(rates_state),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+        )
     } else {
         info!("⏭️  Skipping rates routes (no database)");
         Router::new()
     };
     
+    // Core routes grouped by the scope they require
+    let trustline_routes = middleware::rate_limit::rate_limit_protected(
+        Router::new()
+            .route(
+                "/api/trustlines/operations",
+                post(create_trustline_operation),
+            )
+            .route(
+                "/api/trustlines/operations/{id}",
+                patch(update_trustline_operation_status),
+            )
+            .route(
+                "/api/trustlines/operations/wallet/{address}",
+                get(list_trustline_operations_by_wallet),
+            )
+            .route("/api/cngn/trustlines/check", post(check_cngn_trustline))
+            .route(
+                "/api/cngn/trustlines/preflight",
+                post(preflight_cngn_trustline),
+            )
+            .route("/api/cngn/trustlines/build", post(build_cngn_trustline))
+            .route("/api/cngn/trustlines/submit", post(submit_cngn_trustline))
+            .route(
+                "/api/cngn/trustlines/close/build",
+                post(build_close_cngn_trustline),
+            )
+            .route(
+                "/api/cngn/trustlines/retry/{id}",
+                post(retry_cngn_trustline),
+            ),
+        &rate_limiter,
+        middleware::rate_limit::RateLimitPolicy::standard(),
+        |routes| {
+            middleware::auth::protect(
+                routes,
+                auth_service.as_ref(),
+                &[auth::scopes::TRUSTLINES_WRITE],
+            )
+        },
+    );
+
+    let quote_routes = middleware::rate_limit::rate_limit_protected(
+        Router::new().route("/api/fees/calculate", post(calculate_fee)),
+        &rate_limiter,
+        middleware::rate_limit::RateLimitPolicy::quotes(),
+        |routes| {
+            middleware::auth::protect(
+                routes,
+                auth_service.as_ref(),
+                &[auth::scopes::QUOTES_WRITE],
+            )
+        },
+    );
+
+    let payment_routes = middleware::rate_limit::rate_limit_protected(
+        Router::new()
+            .route("/api/cngn/payments/build", post(build_cngn_payment))
+            .route("/api/cngn/payments/submit", post(submit_cngn_payment))
+            .route("/api/payments/initiate", post(initiate_payment)),
+        &rate_limiter,
+        middleware::rate_limit::RateLimitPolicy::payments(),
+        |routes| {
+            middleware::auth::protect_payments(
+                routes,
+                auth_service.as_ref(),
+                &[auth::scopes::PAYMENTS_INITIATE],
+            )
+        },
+    );
+
+    // Signing spends from the hot wallet, so only operators may request it
+    let signing_routes = middleware::rate_limit::rate_limit_protected(
+        Router::new().route("/api/cngn/payments/sign", post(sign_cngn_payment)),
+        &rate_limiter,
+        middleware::rate_limit::RateLimitPolicy::payments(),
+        |routes| {
+            middleware::auth::protect_payments(
+                routes,
+                auth_service.as_ref(),
+                &[auth::scopes::ADMIN],
+            )
+        },
+    );
+
+    let merchant_webhook_routes = if let Some(service) = merchant_webhook_service.clone() {
+        middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route(
+                    "/api/webhooks/endpoints",
+                    post(api::merchant_webhooks::register_endpoint)
+                        .get(api::merchant_webhooks::list_endpoints),
+                )
+                .route(
+                    "/api/webhooks/endpoints/{id}",
+                    delete(api::merchant_webhooks::delete_endpoint),
+                )
+                .route(
+                    "/api/webhooks/deliveries",
+                    get(api::merchant_webhooks::list_deliveries),
+                )
+                .route(
+                    "/api/webhooks/deliveries/{id}/replay",
+                    post(api::merchant_webhooks::replay_delivery),
+                )
+                .with_state(api::merchant_webhooks::MerchantWebhookState { service }),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+            |routes| {
+                middleware::auth::protect(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::WEBHOOKS_MANAGE],
+                )
+            },
+        )
+    } else {
+        Router::new()
+    };
+
+    let notification_routes = if let Some(repository) = notification_repo.clone() {
+        middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route(
+                    "/api/notifications/preferences",
+                    get(api::notifications::get_preferences)
+                        .put(api::notifications::update_preferences),
+                )
+                .with_state(api::notifications::NotificationState { repository }),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+            |routes| middleware::auth::protect(routes, auth_service.as_ref(), &[]),
+        )
+    } else {
+        Router::new()
+    };
+
+    // Operator routes
+    let admin_routes = if let Some(pool) = db_pool.clone() {
+        let system_wallet_address = std::env::var("SYSTEM_WALLET_ADDRESS").unwrap_or_default();
+        middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route("/admin/monitor/rescan", post(api::admin::rescan))
+                .with_state(api::admin::MonitorAdminState {
+                    cursor_repo: std::sync::Arc::new(
+                        database::stellar_cursor_repository::StellarCursorRepository::new(
+                            pool.clone(),
+                        ),
+                    ),
+                    system_wallet_address,
+                })
+                .merge(
+                    Router::new()
+                        .route(
+                            "/admin/deposits/unmatched",
+                            get(api::admin::list_unmatched_deposits),
+                        )
+                        .route(
+                            "/admin/deposits/unmatched/{id}/attach",
+                            post(api::admin::attach_unmatched_deposit),
+                        )
+                        .route(
+                            "/admin/deposits/unmatched/{id}/refund",
+                            post(api::admin::refund_unmatched_deposit),
+                        )
+                        .with_state(api::admin::DepositAdminState {
+                            pool: pool.clone(),
+                            deposits: std::sync::Arc::new(
+                                database::unmatched_deposit_repository::UnmatchedDepositRepository::new(
+                                    pool.clone(),
+                                ),
+                            ),
+                            transactions: std::sync::Arc::new(
+                                database::transaction_repository::TransactionRepository::new(
+                                    pool.clone(),
+                                ),
+                            ),
+                            payout_queue: payout_queue.clone(),
+                        }),
+                )
+                .merge(
+                    Router::new()
+                        .route(
+                            "/admin/fee-sponsorship",
+                            get(api::admin::fee_sponsorship_budget),
+                        )
+                        .with_state(api::admin::FeeSponsorshipAdminState {
+                            pool,
+                            stellar_client: stellar_client.clone(),
+                        }),
+                )
+                .merge(
+                    Router::new()
+                        .route("/admin/treasury", get(api::admin::treasury_overview))
+                        .route(
+                            "/admin/treasury/transfers/{id}/resolve",
+                            post(api::admin::resolve_treasury_transfer),
+                        )
+                        .with_state(api::admin::TreasuryAdminState {
+                            service: treasury_service.clone(),
+                        }),
+                ),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+            |routes| {
+                middleware::auth::protect(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::ADMIN],
+                )
+            },
+        )
+    } else {
+        Router::new()
+    };
+
+    // Bill payment routes: the provider catalog is public, payments need a scoped principal
+    let bills_catalog_routes = middleware::rate_limit::rate_limit(
+        Router::new().route("/api/bills/providers", get(api::bills::get_providers)),
+        &rate_limiter,
+        middleware::rate_limit::RateLimitPolicy::standard(),
+    );
+    let bills_routes = if let (Some(pool), Some(client)) = (db_pool.clone(), stellar_client.clone()) {
+        let cngn = chains::stellar::trustline::CngnAssetConfig::from_env();
+        let bills_state = api::bills::BillsState {
+            pool: pool.clone(),
+            bill_repo: std::sync::Arc::new(
+                database::bill_payment_repository::BillPaymentRepository::new(pool.clone()),
+            ),
+            transaction_repo: std::sync::Arc::new(
+                database::transaction_repository::TransactionRepository::new(pool.clone()),
+            ),
+            deposit_memo_repo: std::sync::Arc::new(
+                database::deposit_memo_repository::DepositMemoRepository::new(pool),
+            ),
+            providers: std::sync::Arc::new(workers::bill_processor::providers::providers_from_env()),
+            system_wallet_address: std::env::var("SYSTEM_WALLET_ADDRESS").unwrap_or_default(),
+            cngn_issuer: cngn.issuer_for_network(client.network()).to_string(),
+            cngn_asset_code: cngn.asset_code,
+        };
+
+        let pay_routes = middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route("/api/bills/verify", post(api::bills::verify_bill_account))
+                .route("/api/bills/pay", post(api::bills::pay_bill))
+                .with_state(bills_state.clone()),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::payments(),
+            |routes| {
+                middleware::auth::protect_payments(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::PAYMENTS_INITIATE],
+                )
+            },
+        );
+        let status_routes = middleware::rate_limit::rate_limit_protected(
+            Router::new()
+                .route("/api/bills/{id}", get(api::bills::get_bill_status))
+                .with_state(bills_state),
+            &rate_limiter,
+            middleware::rate_limit::RateLimitPolicy::standard(),
+            |routes| {
+                middleware::auth::protect(
+                    routes,
+                    auth_service.as_ref(),
+                    &[auth::scopes::PAYMENTS_READ],
+                )
+            },
+        );
+
+        bills_catalog_routes.merge(pay_routes).merge(status_routes)
+    } else {
+        info!("⏭️  Skipping bill payment routes (no database or Stellar client)");
+        bills_catalog_routes
+    };
+
     let app = Router::new()
         .route("/", get(root))
         .route("/health", get(health))
         .route("/health/ready", get(readiness))
         .route("/health/live", get(liveness))
         .route("/api/stellar/account/{address}", get(get_stellar_account))
-        .route(
-            "/api/trustlines/operations",
-            post(create_trustline_operation),
-        )
-        .route(
-            "/api/trustlines/operations/{id}",
-            patch(update_trustline_operation_status),
-        )
-        .route(
-            "/api/trustlines/operations/wallet/{address}",
-            get(list_trustline_operations_by_wallet),
-        )
-        .route("/api/fees/calculate", post(calculate_fee))
-        .route("/api/cngn/trustlines/check", post(check_cngn_trustline))
-        .route(
-            "/api/cngn/trustlines/preflight",
-            post(preflight_cngn_trustline),
-        )
-        .route("/api/cngn/trustlines/build", post(build_cngn_trustline))
-        .route("/api/cngn/trustlines/submit", post(submit_cngn_trustline))
-        .route(
-            "/api/cngn/trustlines/retry/{id}",
-            post(retry_cngn_trustline),
-        )
-        .route("/api/cngn/payments/build", post(build_cngn_payment))
-        .route("/api/cngn/payments/sign", post(sign_cngn_payment))
-        .route("/api/cngn/payments/submit", post(submit_cngn_payment))
-        .route("/api/payments/initiate", post(initiate_payment))
+        .merge(trustline_routes)
+        .merge(quote_routes)
+        .merge(payment_routes)
+        .merge(signing_routes)
         .merge(onramp_routes)
         .merge(wallet_routes)
+        .merge(sep10_routes)
+        .merge(sep24_routes)
+        .merge(sep38_routes)
+        .merge(sep12_routes)
+        .merge(stellar_toml_routes)
         .merge(rates_routes)
         .merge(webhook_routes)
+        .merge(merchant_webhook_routes)
+        .merge(notification_routes)
         .merge(bills_routes)
+        .merge(admin_routes)
         .with_state(AppState {
             db_pool,
             redis_cache,
             stellar_client,
             health_checker,
+            hot_wallet_signer,
+            payment_orchestrator,
         })
+        .layer(axum::Extension(idempotency_store))
         .layer(
             ServiceBuilder::new()
                 .layer(SetRequestIdLayer::x_request_id(UuidRequestId))
                 .layer(axum::middleware::from_fn(request_logging_middleware))
                 .layer(PropagateRequestIdLayer::x_request_id()),
-        );
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            trusted_proxies,
+            middleware::client_ip::client_ip_middleware,
+        ));
 
     info!("✅ Routes configured");
 
@@ -668,7 +1597,10 @@ async fn main() -> anyhow::Result<()> {
     );
     info!("✅ Server is ready to accept connections");
 
-    axum::serve(listener, app)
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
         .with_graceful_shutdown(shutdown_signal_with_notify(worker_shutdown_tx.clone()))
         .await
         .unwrap();
@@ -684,6 +1616,36 @@ async fn main() -> anyhow::Result<()> {
             error!(error = %e, "Timed out waiting for offramp worker shutdown");
         }
     }
+    if let Some(handle) = onramp_handle {
+        if let Err(e) = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+            error!(error = %e, "Timed out waiting for onramp worker shutdown");
+        }
+    }
+    if let Some(handle) = bill_processor_handle {
+        if let Err(e) = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+            error!(error = %e, "Timed out waiting for bill processor worker shutdown");
+        }
+    }
+    if let Some(handle) = merchant_webhook_handle {
+        if let Err(e) = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+            error!(error = %e, "Timed out waiting for merchant webhook worker shutdown");
+        }
+    }
+    if let Some(handle) = notification_handle {
+        if let Err(e) = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+            error!(error = %e, "Timed out waiting for notification dispatcher shutdown");
+        }
+    }
+    if let Some(handle) = treasury_handle {
+        if let Err(e) = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+            error!(error = %e, "Timed out waiting for treasury monitor shutdown");
+        }
+    }
+    if let Some(handle) = channel_monitor_handle {
+        if let Err(e) = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+            error!(error = %e, "Timed out waiting for channel account monitor shutdown");
+        }
+    }
 
     info!("👋 Server shutdown complete");
 
@@ -697,6 +1659,9 @@ struct AppState {
     redis_cache: Option<RedisCache>,
     stellar_client: Option<StellarClient>,
     health_checker: HealthChecker,
+    hot_wallet_signer: Option<std::sync::Arc<dyn chains::stellar::signer::TransactionSigner>>,
+    payment_orchestrator:
+        Option<std::sync::Arc<services::payment_orchestrator::PaymentOrchestrator>>,
 }
 
 // Handlers
@@ -909,6 +1874,12 @@ struct FeeCalculationResponse {
 #[derive(Debug, Deserialize)]
 struct TrustlineAccountRequest {
     account_id: String,
+    /// Preflight as if network fees will be sponsored
+    #[serde(default)]
+    sponsor_fee: bool,
+    /// Preflight as if the trustline reserve will be sponsored
+    #[serde(default)]
+    sponsor_reserve: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -921,6 +1892,19 @@ struct CngnTrustlineBuildRequest {
     account_id: String,
     limit: Option<String>,
     fee_stroops: Option<u32>,
+    #[serde(default)]
+    sponsor_fee: bool,
+    /// Have the platform reserve sponsor pay the trustline reserve (CAP-33)
+    #[serde(default)]
+    sponsor_reserve: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct CngnTrustlineCloseRequest {
+    account_id: String,
+    fee_stroops: Option<u32>,
+    #[serde(default)]
+    sponsor_fee: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -928,6 +1912,9 @@ struct CngnTrustlineSubmitRequest {
     signed_envelope_xdr: String,
     account_id: Option<String>,
     operation_id: Option<Uuid>,
+    /// Wrap in a fee-bump paid by the platform fee account
+    #[serde(default)]
+    sponsor_fee: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -940,6 +1927,7 @@ struct CngnTrustlineBuildResponse {
 struct CngnTrustlineSubmitResponse {
     horizon_response: serde_json::Value,
     operation_id: Option<Uuid>,
+    fee_bump: Option<crate::chains::stellar::fee_bump::FeeBumpedTransaction>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -949,18 +1937,22 @@ struct CngnPaymentBuildRequest {
     amount: String,
     memo: Option<crate::chains::stellar::payment::CngnMemo>,
     fee_stroops: Option<u32>,
+    #[serde(default)]
+    sponsor_fee: bool,
 }
 
 #[derive(Debug, Deserialize)]
 struct CngnPaymentSignRequest {
     draft: crate::chains::stellar::payment::CngnPaymentDraft,
-    secret_seed: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct CngnPaymentSubmitRequest {
     signed_envelope_xdr: String,
     transaction_id: Option<String>,
+    /// Wrap in a fee-bump paid by the platform fee account
+    #[serde(default)]
+    sponsor_fee: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -973,6 +1965,7 @@ struct CngnPaymentBuildResponse {
 struct CngnPaymentSubmitResponse {
     horizon_response: serde_json::Value,
     transaction_id: Option<String>,
+    fee_bump: Option<crate::chains::stellar::fee_bump::FeeBumpedTransaction>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1058,6 +2051,8 @@ async fn create_trustline_operation(
 }
 
 async fn initiate_payment(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    principal: Option<crate::auth::Principal>,
     headers: axum::http::HeaderMap,
     Json(payload): Json<InitiatePaymentApiRequest>,
 ) -> Result<
@@ -1069,7 +2064,21 @@ async fn initiate_payment(
 > {
     let request_id = crate::middleware::error::get_request_id_from_headers(&headers);
 
-    if payload.transaction_reference.trim().is_empty() {
+    // The orchestrator keeps the reference reserved when a provider call
+    // times out, so a retry can't charge the customer twice
+    let orchestrator = match state.payment_orchestrator.as_ref() {
+        Some(orchestrator) => orchestrator,
+        None => {
+            return Err(crate::middleware::error::json_error_response(
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "Payments disabled by configuration",
+                request_id,
+            ))
+        }
+    };
+
+    let transaction_reference = payload.transaction_reference.trim().to_string();
+    if transaction_reference.is_empty() {
         return Err(crate::middleware::error::json_error_response(
             axum::http::StatusCode::BAD_REQUEST,
             "transaction_reference is required",
@@ -1083,6 +2092,16 @@ async fn initiate_payment(
             request_id,
         ));
     }
+    let amount = match sqlx::types::BigDecimal::from_str(payload.amount.trim()) {
+        Ok(amount) => amount,
+        Err(_) => {
+            return Err(crate::middleware::error::json_error_response(
+                axum::http::StatusCode::BAD_REQUEST,
+                "amount must be a decimal number",
+                request_id,
+            ))
+        }
+    };
 
     let payment_method = match payload
         .payment_method
@@ -1100,66 +2119,39 @@ async fn initiate_payment(
         _ => PaymentMethod::Other,
     };
 
-    let provider_request = ProviderPaymentRequest {
-        amount: Money {
-            amount: payload.amount,
-            currency: payload.currency.unwrap_or_else(|| "NGN".to_string()),
-        },
-        customer: CustomerContact {
-            email: payload.email,
-            phone: payload.phone,
-        },
-        payment_method,
-        callback_url: payload.callback_url,
-        transaction_reference: payload.transaction_reference,
-        metadata: payload.metadata,
-    };
-
-    let factory = PaymentProviderFactory::from_env().map_err(|e| {
-        crate::middleware::error::json_error_response(
-            axum::http::StatusCode::from_u16(e.http_status_code())
-                .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
-            e.user_message(),
-            request_id.clone(),
-        )
-    })?;
-
-    let provider = match payload.provider {
-        Some(provider_name) => {
-            let provider = ProviderName::from_str(&provider_name).map_err(|e| {
-                crate::middleware::error::json_error_response(
-                    axum::http::StatusCode::from_u16(e.http_status_code())
-                        .unwrap_or(axum::http::StatusCode::BAD_REQUEST),
-                    e.user_message(),
-                    request_id.clone(),
-                )
-            })?;
-            factory.get_provider(provider)
-        }
-        None => factory.get_default_provider(),
-    }
-    .map_err(|e| {
-        crate::middleware::error::json_error_response(
-            axum::http::StatusCode::from_u16(e.http_status_code())
-                .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
-            e.user_message(),
-            request_id.clone(),
-        )
-    })?;
-
-    let response = provider
-        .initiate_payment(provider_request)
-        .await
+    let preferred_provider = payload
+        .provider
+        .as_deref()
+        .map(ProviderName::from_str)
+        .transpose()
         .map_err(|e| {
             crate::middleware::error::json_error_response(
                 axum::http::StatusCode::from_u16(e.http_status_code())
-                    .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
+                    .unwrap_or(axum::http::StatusCode::BAD_REQUEST),
                 e.user_message(),
                 request_id.clone(),
             )
         })?;
 
-    Ok(Json(response))
+    // No wallet is involved, so the orchestrator's key is bound to the caller
+    let caller = crate::middleware::idempotency::caller_scope(principal.as_ref());
+    orchestrator
+        .initiate_payment(services::payment_orchestrator::PaymentInitiationRequest {
+            idempotency_key: Some(format!("payments:{}:{}", caller, transaction_reference)),
+            wallet_address: caller,
+            amount,
+            currency: payload.currency.unwrap_or_else(|| "NGN".to_string()),
+            payment_method,
+            customer_email: payload.email,
+            customer_phone: payload.phone,
+            callback_url: payload.callback_url,
+            metadata: payload.metadata,
+            preferred_provider,
+            transaction_reference: Some(transaction_reference),
+        })
+        .await
+        .map(Json)
+        .map_err(|e| app_error_response(e.into(), request_id))
 }
 
 async fn update_trustline_operation_status(
@@ -1378,6 +2370,70 @@ fn app_error_response(
     )
 }
 
+/// Fee-bump sponsorship needs the database for its daily budget and a
+/// configured fee account
+fn fee_sponsorship_service(
+    state: &AppState,
+    stellar_client: &StellarClient,
+    request_id: Option<String>,
+) -> Result<
+    crate::services::fee_sponsorship::FeeSponsorshipService,
+    (
+        axum::http::StatusCode,
+        Json<crate::middleware::error::ErrorResponse>,
+    ),
+> {
+    let pool = match state.db_pool.as_ref() {
+        Some(pool) => pool,
+        None => {
+            return Err(crate::middleware::error::json_error_response(
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "Database disabled by configuration",
+                request_id,
+            ))
+        }
+    };
+    crate::services::fee_sponsorship::FeeSponsorshipService::from_env(
+        stellar_client.clone(),
+        pool.clone(),
+    )
+    .map_err(|e| {
+        crate::middleware::error::json_error_response(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            format!("fee sponsorship unavailable: {}", e),
+            request_id,
+        )
+    })
+}
+
+fn reserve_sponsor(
+    sponsor_reserve: bool,
+    request_id: Option<String>,
+) -> Result<
+    Option<crate::chains::stellar::trustline::ReserveSponsor>,
+    (
+        axum::http::StatusCode,
+        Json<crate::middleware::error::ErrorResponse>,
+    ),
+> {
+    if !sponsor_reserve {
+        return Ok(None);
+    }
+    match crate::chains::stellar::trustline::ReserveSponsor::from_env() {
+        Ok(Some(sponsor)) => Ok(Some(sponsor)),
+        Ok(None) => Err(crate::middleware::error::json_error_response(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "reserve sponsorship unavailable: STELLAR_RESERVE_SPONSOR_SIGNER is not configured",
+            request_id,
+        )),
+        Err(e) => Err(crate::middleware::error::json_error_response(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            format!("reserve sponsorship unavailable: {}", e),
+            request_id,
+        )),
+    }
+}
+
 async fn check_cngn_trustline(
     axum::extract::State(state): axum::extract::State<AppState>,
     headers: axum::http::HeaderMap,
@@ -1449,8 +2505,12 @@ async fn preflight_cngn_trustline(
         ));
     }
 
-    let manager =
-        crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone());
+    let mut manager =
+        crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone())
+            .with_fee_sponsorship(payload.sponsor_fee);
+    if let Some(sponsor) = reserve_sponsor(payload.sponsor_reserve, request_id.clone())? {
+        manager = manager.with_reserve_sponsor(sponsor);
+    }
     manager
         .preflight_trustline_creation(&payload.account_id)
         .await
@@ -1489,8 +2549,12 @@ async fn build_cngn_trustline(
         ));
     }
 
-    let manager =
-        crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone());
+    let mut manager =
+        crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone())
+            .with_fee_sponsorship(payload.sponsor_fee);
+    if let Some(sponsor) = reserve_sponsor(payload.sponsor_reserve, request_id.clone())? {
+        manager = manager.with_reserve_sponsor(sponsor);
+    }
     let draft = manager
         .build_create_trustline_transaction(
             &payload.account_id,
@@ -1519,7 +2583,101 @@ async fn build_cngn_trustline(
                     "unsigned_envelope_xdr": draft.unsigned_envelope_xdr,
                     "sequence": draft.sequence,
                     "fee_stroops": draft.fee_stroops,
-                    "limit": draft.limit
+                    "limit": draft.limit,
+                    "fee_sponsored": draft.fee_sponsored,
+                    "reserve_sponsor": draft.reserve_sponsor
+                }),
+            )
+            .await
+            .map_err(|e| {
+                crate::middleware::error::json_error_response(
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("failed to log trustline operation: {}", e),
+                    request_id.clone(),
+                )
+            })?;
+        if let Some(sponsor) = draft.reserve_sponsor.as_deref() {
+            repo.set_sponsorship(operation.id, sponsor, "pending")
+                .await
+                .map_err(|e| {
+                    crate::middleware::error::json_error_response(
+                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("failed to log trustline sponsorship: {}", e),
+                        request_id.clone(),
+                    )
+                })?;
+        }
+        operation_id = Some(operation.id);
+    }
+
+    Ok(Json(CngnTrustlineBuildResponse {
+        draft,
+        operation_id,
+    }))
+}
+
+/// Build the transaction removing a zero-balance cNGN trustline. Submitting
+/// it through `/api/cngn/trustlines/submit` returns a sponsored reserve to
+/// its sponsor and marks the sponsorship revoked.
+async fn build_close_cngn_trustline(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<CngnTrustlineCloseRequest>,
+) -> Result<
+    Json<CngnTrustlineBuildResponse>,
+    (
+        axum::http::StatusCode,
+        Json<crate::middleware::error::ErrorResponse>,
+    ),
+> {
+    let request_id = crate::middleware::error::get_request_id_from_headers(&headers);
+    let stellar_client = match state.stellar_client.as_ref() {
+        Some(client) => client,
+        None => {
+            return Err(crate::middleware::error::json_error_response(
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "Stellar client disabled by configuration",
+                request_id,
+            ))
+        }
+    };
+
+    if payload.account_id.trim().is_empty() {
+        return Err(crate::middleware::error::json_error_response(
+            axum::http::StatusCode::BAD_REQUEST,
+            "account_id is required",
+            request_id,
+        ));
+    }
+
+    let manager =
+        crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone())
+            .with_fee_sponsorship(payload.sponsor_fee);
+    let draft = manager
+        .build_close_trustline_transaction(&payload.account_id, payload.fee_stroops)
+        .await
+        .map_err(|e| app_error_response(e.into(), request_id.clone()))?;
+
+    let mut operation_id = None;
+    if let Some(pool) = state.db_pool.as_ref() {
+        let repo =
+            crate::database::trustline_operation_repository::TrustlineOperationRepository::new(
+                pool.clone(),
+            );
+        let operation = repo
+            .create_operation(
+                &draft.account_id,
+                &draft.asset_code,
+                Some(&draft.issuer),
+                "remove",
+                "pending",
+                Some(&draft.transaction_hash),
+                None,
+                serde_json::json!({
+                    "unsigned_envelope_xdr": draft.unsigned_envelope_xdr,
+                    "sequence": draft.sequence,
+                    "fee_stroops": draft.fee_stroops,
+                    "fee_sponsored": draft.fee_sponsored
                 }),
             )
             .await
@@ -1570,22 +2728,41 @@ async fn submit_cngn_trustline(
         ));
     }
 
-    let manager =
-        crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone());
-    let result = manager
-        .submit_signed_trustline_xdr(&payload.signed_envelope_xdr)
-        .await;
+    let result = if payload.sponsor_fee {
+        let sponsorship = fee_sponsorship_service(&state, stellar_client, request_id.clone())?;
+        sponsorship
+            .submit(&payload.signed_envelope_xdr)
+            .await
+            .map(|s| (s.horizon_response, Some(s.fee_bump)))
+    } else {
+        let manager =
+            crate::chains::stellar::trustline::CngnTrustlineManager::new(stellar_client.clone());
+        manager
+            .submit_signed_trustline_xdr(&payload.signed_envelope_xdr)
+            .await
+            .map(|horizon_response| (horizon_response, None))
+    };
 
     match result {
-        Ok(horizon_response) => {
+        Ok((horizon_response, fee_bump)) => {
             if let (Some(pool), Some(op_id)) = (state.db_pool.as_ref(), payload.operation_id) {
                 let repo = crate::database::trustline_operation_repository::TrustlineOperationRepository::new(pool.clone());
                 let tx_hash = horizon_response.get("hash").and_then(|v| v.as_str());
-                let _ = repo.update_status(op_id, "completed", tx_hash, None).await;
+                if let Ok(operation) = repo.update_status(op_id, "completed", tx_hash, None).await {
+                    if operation.operation_type == "remove" {
+                        // Closing the trustline returned any sponsored reserve
+                        let _ = repo
+                            .revoke_sponsorship(&operation.wallet_address, &operation.asset_code)
+                            .await;
+                    } else if operation.sponsorship_status.as_deref() == Some("pending") {
+                        let _ = repo.activate_sponsorship(operation.id).await;
+                    }
+                }
             }
             Ok(Json(CngnTrustlineSubmitResponse {
                 horizon_response,
                 operation_id: payload.operation_id,
+                fee_bump,
             }))
         }
         Err(e) => {
@@ -1672,7 +2849,8 @@ async fn build_cngn_payment(
         ));
     }
 
-    let builder = crate::chains::stellar::payment::CngnPaymentBuilder::new(stellar_client.clone());
+    let builder = crate::chains::stellar::payment::CngnPaymentBuilder::new(stellar_client.clone())
+        .with_fee_sponsorship(payload.sponsor_fee);
     let draft = builder
         .build_payment(
             &payload.source,
@@ -1722,7 +2900,8 @@ async fn build_cngn_payment(
                     "destination": payload.destination,
                     "memo": draft.memo,
                     "stellar_tx_hash": draft.transaction_hash,
-                    "unsigned_envelope_xdr": draft.unsigned_envelope_xdr
+                    "unsigned_envelope_xdr": draft.unsigned_envelope_xdr,
+                    "fee_sponsored": draft.fee_sponsored
                 }),
             )
             .await
@@ -1765,9 +2944,23 @@ async fn sign_cngn_payment(
         }
     };
 
+    // Drafts are signed by the configured hot wallet signer; key material
+    // never travels in requests
+    let signer = match state.hot_wallet_signer.as_ref() {
+        Some(signer) => signer,
+        None => {
+            return Err(crate::middleware::error::json_error_response(
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "No hot wallet signer configured",
+                request_id,
+            ))
+        }
+    };
+
     let builder = crate::chains::stellar::payment::CngnPaymentBuilder::new(stellar_client.clone());
     builder
-        .sign_payment(payload.draft, &payload.secret_seed)
+        .sign_payment(payload.draft, signer.as_ref())
+        .await
         .map(Json)
         .map_err(|e| app_error_response(e.into(), request_id))
 }
@@ -1803,13 +2996,23 @@ async fn submit_cngn_payment(
         ));
     }
 
-    let builder = crate::chains::stellar::payment::CngnPaymentBuilder::new(stellar_client.clone());
-    let submit_result = builder
-        .submit_signed_payment(&payload.signed_envelope_xdr)
-        .await;
+    let submit_result = if payload.sponsor_fee {
+        let sponsorship = fee_sponsorship_service(&state, stellar_client, request_id.clone())?;
+        sponsorship
+            .submit(&payload.signed_envelope_xdr)
+            .await
+            .map(|s| (s.horizon_response, Some(s.fee_bump)))
+    } else {
+        let builder =
+            crate::chains::stellar::payment::CngnPaymentBuilder::new(stellar_client.clone());
+        builder
+            .submit_signed_payment(&payload.signed_envelope_xdr)
+            .await
+            .map(|horizon_response| (horizon_response, None))
+    };
 
     match submit_result {
-        Ok(horizon_response) => {
+        Ok((horizon_response, fee_bump)) => {
             if let (Some(pool), Some(tx_id)) =
                 (state.db_pool.as_ref(), payload.transaction_id.as_deref())
             {
@@ -1827,6 +3030,9 @@ async fn submit_cngn_payment(
                 if let Some(hash) = submitted_hash {
                     metadata["submitted_hash"] = serde_json::json!(hash);
                 }
+                if let Some(fee_bump) = fee_bump.as_ref() {
+                    metadata["fee_bump"] = serde_json::json!(fee_bump);
+                }
                 let _ = repo
                     .update_status_with_metadata(tx_id, "processing", metadata)
                     .await;
@@ -1834,6 +3040,7 @@ async fn submit_cngn_payment(
             Ok(Json(CngnPaymentSubmitResponse {
                 horizon_response,
                 transaction_id: payload.transaction_id,
+                fee_bump,
             }))
         }
         Err(e) => {
@@ -0,0 +1,263 @@
+//! Authentication middleware
+//!
+//! Accepts an API key (`X-API-Key: ak_...` or `Authorization: Bearer ak_...`)
+//! or a JWT (`Authorization: Bearer <jwt>`), checks the route group's scopes,
+//! and injects the resulting [`Principal`] into request extensions. Handlers
+//! receive it by taking `Principal` as an extractor.
+
+use crate::auth::api_key::looks_like_api_key;
+use crate::auth::{AuthError, AuthService, Principal};
+use crate::middleware::error::{get_request_id_from_headers, ErrorResponse};
+use crate::middleware::idempotency::{idempotency_middleware, IdempotencyPolicy};
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Credential presented by the caller
+#[derive(Debug, PartialEq, Eq)]
+pub enum Credential<'a> {
+    ApiKey(&'a str),
+    Bearer(&'a str),
+}
+
+/// Pull the caller's credential out of the request headers
+pub fn extract_credential(headers: &HeaderMap) -> Option<Credential<'_>> {
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(Credential::ApiKey(key.trim()));
+    }
+
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim();
+    if looks_like_api_key(token) {
+        Some(Credential::ApiKey(token))
+    } else {
+        Some(Credential::Bearer(token))
+    }
+}
+
+/// State for [`require_auth`]: the verifier and the scopes a route group needs
+#[derive(Clone)]
+pub struct RequireAuth {
+    service: Arc<AuthService>,
+    scopes: Arc<[&'static str]>,
+}
+
+impl RequireAuth {
+    pub fn new(service: Arc<AuthService>, scopes: &[&'static str]) -> Self {
+        Self {
+            service,
+            scopes: scopes.into(),
+        }
+    }
+}
+
+/// Authenticate the caller and enforce the route group's scopes
+pub async fn require_auth(
+    State(auth): State<RequireAuth>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let request_id = get_request_id_from_headers(request.headers());
+
+    let result = match extract_credential(request.headers()) {
+        Some(Credential::ApiKey(key)) => auth.service.authenticate_api_key(key).await,
+        Some(Credential::Bearer(token)) => auth.service.authenticate_jwt(token).await,
+        None => Err(AuthError::MissingCredentials),
+    };
+
+    let principal = match result {
+        Ok(principal) => principal,
+        Err(e) => return auth_error_response(&e, request_id),
+    };
+
+    let missing = principal.missing_scopes(&auth.scopes);
+    if !missing.is_empty() {
+        debug!(
+            subject = %principal.subject,
+            missing = ?missing,
+            "Rejected request with insufficient scopes"
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::forbidden(request_id, &missing)),
+        )
+            .into_response();
+    }
+
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+/// Put a route group behind authentication with the given scopes, with
+/// `Idempotency-Key` handling keyed by the authenticated caller.
+///
+/// With no auth service (auth disabled) only the idempotency layer is added.
+pub fn protect<S>(
+    router: Router<S>,
+    service: Option<&Arc<AuthService>>,
+    scopes: &[&'static str],
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    protect_with_policy(router, service, scopes, IdempotencyPolicy::Standard)
+}
+
+/// [`protect`] for routes that move money: their keys are never released on
+/// a server error (see [`IdempotencyPolicy::Payments`])
+pub fn protect_payments<S>(
+    router: Router<S>,
+    service: Option<&Arc<AuthService>>,
+    scopes: &[&'static str],
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    protect_with_policy(router, service, scopes, IdempotencyPolicy::Payments)
+}
+
+fn protect_with_policy<S>(
+    router: Router<S>,
+    service: Option<&Arc<AuthService>>,
+    scopes: &[&'static str],
+    policy: IdempotencyPolicy,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    // Route layers added later run first, so idempotency sees the principal
+    let router = router.route_layer(axum::middleware::from_fn_with_state(
+        policy,
+        idempotency_middleware,
+    ));
+    match service {
+        Some(service) => router.route_layer(axum::middleware::from_fn_with_state(
+            RequireAuth::new(service.clone(), scopes),
+            require_auth,
+        )),
+        None => router,
+    }
+}
+
+fn auth_error_response(error: &AuthError, request_id: Option<String>) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    if status.is_server_error() {
+        warn!(error = %error, "Authentication could not be performed");
+        return (status, Json(ErrorResponse::internal_error(request_id))).into_response();
+    }
+
+    let mut response = (
+        status,
+        Json(ErrorResponse::unauthorized(request_id, error.to_string())),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+impl<S> FromRequestParts<S> for Principal
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Principal>().cloned().ok_or_else(|| {
+            auth_error_response(
+                &AuthError::MissingCredentials,
+                get_request_id_from_headers(&parts.headers),
+            )
+        })
+    }
+}
+
+/// `Option<Principal>` is `None` on routes left unprotected because auth is disabled
+impl<S> OptionalFromRequestParts<S> for Principal
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Principal>().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_extract_api_key_header() {
+        let h = headers(&[("x-api-key", "ak_0123456789abcdef")]);
+        assert_eq!(
+            extract_credential(&h),
+            Some(Credential::ApiKey("ak_0123456789abcdef"))
+        );
+    }
+
+    #[test]
+    fn test_extract_bearer_token_and_key() {
+        let h = headers(&[("authorization", "Bearer eyJ.abc.def")]);
+        assert_eq!(
+            extract_credential(&h),
+            Some(Credential::Bearer("eyJ.abc.def"))
+        );
+
+        let h = headers(&[("authorization", "bearer ak_0123456789abcdef")]);
+        assert_eq!(
+            extract_credential(&h),
+            Some(Credential::ApiKey("ak_0123456789abcdef"))
+        );
+    }
+
+    #[test]
+    fn test_extract_rejects_other_schemes() {
+        assert_eq!(
+            extract_credential(&headers(&[("authorization", "Basic dXNlcjpwYXNz")])),
+            None
+        );
+        assert_eq!(extract_credential(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_auth_error_responses() {
+        let response = auth_error_response(&AuthError::Expired, None);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer"
+        );
+
+        let response = auth_error_response(&AuthError::Unavailable("JWT".to_string()), None);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
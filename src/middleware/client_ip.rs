@@ -0,0 +1,168 @@
+//! Client address resolution
+//!
+//! The address a request came from is the TCP peer, taken from axum's
+//! `ConnectInfo`. `X-Forwarded-For` is only believed when that peer is one of
+//! the reverse proxies listed in `TRUSTED_PROXIES`; anyone else could put any
+//! address in the header. [`client_ip_middleware`] resolves the address once
+//! and stores it as a [`ClientIp`] extension for logging, rate limiting and
+//! webhook source checks.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Address of the client that made the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Reverse proxies allowed to report the client address, as addresses or
+/// CIDR ranges
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Load `TRUSTED_PROXIES` (comma separated). Unset trusts no proxy.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("TRUSTED_PROXIES") {
+            Ok(list) => Self::parse(&list),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let networks = list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (addr, prefix) = match entry.split_once('/') {
+                    Some((addr, prefix)) => (addr, Some(prefix)),
+                    None => (entry, None),
+                };
+                let addr: IpAddr = addr
+                    .parse()
+                    .map_err(|_| format!("invalid address in TRUSTED_PROXIES: {}", entry))?;
+                let max = if addr.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|p| *p <= max)
+                        .ok_or_else(|| format!("invalid prefix in TRUSTED_PROXIES: {}", entry))?,
+                    None => max,
+                };
+                Ok((addr, prefix))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.networks
+            .iter()
+            .any(|(network, prefix)| in_network(ip, *network, *prefix))
+    }
+
+    /// Client address for a request from `peer`. Forwarded entries are read
+    /// right to left, skipping trusted proxies, so only hops our own proxies
+    /// appended are believed.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect();
+        forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.contains(**ip))
+            .or_else(|| forwarded.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+}
+
+/// IPv4-mapped IPv6 addresses compare as IPv4
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, canonical(network)) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Resolve the client address and store it as a [`ClientIp`] extension.
+/// Requests without `ConnectInfo` (e.g. in tests) get none.
+pub async fn client_ip_middleware(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        let ip = proxies.client_ip(canonical(peer.ip()), request.headers());
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(forwarded: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", forwarded.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_forwarded_for_only_believed_from_trusted_proxies() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8, 192.168.1.5").unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let spoofed = headers("1.2.3.4, 203.0.113.7");
+
+        // Direct connection: the header is ignored
+        assert_eq!(proxies.client_ip(client, &spoofed), client);
+        // Through our proxy: the rightmost untrusted hop wins, not the
+        // client-supplied leftmost entry
+        let proxy: IpAddr = "10.1.2.3".parse().unwrap();
+        assert_eq!(proxies.client_ip(proxy, &spoofed), client);
+        assert_eq!(
+            proxies.client_ip(proxy, &headers("203.0.113.7, 192.168.1.5")),
+            client
+        );
+        assert_eq!(proxies.client_ip(proxy, &HeaderMap::new()), proxy);
+
+        assert_eq!(TrustedProxies::default().client_ip(proxy, &spoofed), proxy);
+        assert!(proxies.contains("::ffff:10.9.9.9".parse().unwrap()));
+        assert!(!proxies.contains("192.168.1.6".parse().unwrap()));
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("nope").is_err());
+    }
+}
@@ -77,6 +77,30 @@ impl ErrorResponse {
         }
     }
 
+    /// Create an authentication failure response (401)
+    pub fn unauthorized(request_id: Option<String>, message: impl Into<String>) -> Self {
+        Self {
+            error: ErrorCode::Unauthorized,
+            message: message.into(),
+            request_id,
+            timestamp: Utc::now().to_rfc3339(),
+            details: None,
+            retryable: Some(false),
+        }
+    }
+
+    /// Create an authorization failure response (403) listing the missing scopes
+    pub fn forbidden(request_id: Option<String>, missing_scopes: &[&str]) -> Self {
+        Self {
+            error: ErrorCode::Forbidden,
+            message: "Credentials lack the scopes required for this endpoint".to_string(),
+            request_id,
+            timestamp: Utc::now().to_rfc3339(),
+            details: Some(serde_json::json!({ "missing_scopes": missing_scopes })),
+            retryable: Some(false),
+        }
+    }
+
     /// Create a validation error response with field details
     pub fn validation_error(request_id: Option<String>, field: &str, message: &str) -> Self {
         Self {
@@ -0,0 +1,436 @@
+//! Idempotency-Key middleware
+//!
+//! Mutating requests carrying an `Idempotency-Key` header are executed at most
+//! once per key. The first completed response is stored and replayed verbatim
+//! to retries; a retry that arrives while the original is still running gets
+//! 409, and reusing a key with a different body gets 422.
+//!
+//! Keys belong to the authenticated caller, so the layer runs inside auth on
+//! each protected route group (see [`crate::middleware::auth::protect`]) and
+//! two callers can never see each other's responses.
+//!
+//! Routes that move money use [`IdempotencyPolicy::Payments`]: a 5xx there may
+//! follow a charge the provider did make, so it is stored and replayed like
+//! any other response, and keyed requests are refused while the store is down.
+
+use crate::auth::{Principal, PrincipalKind};
+use crate::middleware::error::{get_request_id_from_headers, json_error_response};
+use crate::services::idempotency::{IdempotencyStore, ReserveOutcome};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Extension, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Header carrying the client-supplied key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header set on replayed responses
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted from clients
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request or response body buffered for fingerprinting and replay
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// How long a completed response stays replayable
+const RECORD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Lease on an in-progress record, so a request that died mid-flight doesn't
+/// hold its key for a day
+const IN_PROGRESS_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How a route group treats failures around a keyed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotencyPolicy {
+    /// 5xx responses release the key so the client can retry, and requests
+    /// run unguarded while the store is unavailable
+    Standard,
+    /// 5xx responses are stored and replayed, and requests are refused while
+    /// the store is unavailable
+    Payments,
+}
+
+/// Stored state of a keyed request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordState {
+    InProgress,
+    Completed,
+}
+
+/// Stored request fingerprint and, once finished, its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    pub state: RecordState,
+    pub status_code: Option<u16>,
+    pub content_type: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Whether the method may change server state
+pub fn is_mutating(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    )
+}
+
+/// Validate a client-supplied key
+pub fn validate_key(key: &str) -> Result<(), &'static str> {
+    if key.is_empty() {
+        return Err("Idempotency-Key must not be empty");
+    }
+    if key.len() > MAX_KEY_LENGTH {
+        return Err("Idempotency-Key must be at most 255 characters");
+    }
+    if !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err("Idempotency-Key must contain only visible ASCII characters");
+    }
+    Ok(())
+}
+
+/// Fingerprint of the request the key was first used with
+pub fn request_fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Namespace for a caller's keys
+pub fn caller_scope(principal: Option<&Principal>) -> String {
+    match principal {
+        Some(principal) => {
+            let kind = match principal.kind {
+                PrincipalKind::ApiClient => "client",
+                PrincipalKind::User => "user",
+            };
+            format!("{}:{}", kind, principal.subject)
+        }
+        None => "anonymous".to_string(),
+    }
+}
+
+/// Enforce `Idempotency-Key` semantics on mutating requests
+///
+/// Reads the store from an `Extension<Arc<IdempotencyStore>>` and the caller
+/// from the [`Principal`] auth inserted; without a store requests pass through.
+///
+/// # Example Usage with Axum
+/// ```ignore
+/// let app = Router::new()
+///     .route("/api/onramp/initiate", post(handler))
+///     .route_layer(axum::middleware::from_fn_with_state(
+///         IdempotencyPolicy::Payments,
+///         idempotency_middleware,
+///     ))
+///     .layer(axum::Extension(store));
+/// ```
+pub async fn idempotency_middleware(
+    State(policy): State<IdempotencyPolicy>,
+    store: Option<Extension<Arc<IdempotencyStore>>>,
+    principal: Option<Principal>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(store)) = store else {
+        return next.run(request).await;
+    };
+    if !is_mutating(request.method()) || !store.is_enabled() {
+        return next.run(request).await;
+    }
+    let Some(raw_key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+
+    let request_id = get_request_id_from_headers(request.headers());
+    let key = match raw_key.to_str() {
+        Ok(key) => key.to_string(),
+        Err(_) => {
+            return json_error_response(
+                StatusCode::BAD_REQUEST,
+                "Idempotency-Key must contain only visible ASCII characters",
+                request_id,
+            )
+            .into_response()
+        }
+    };
+    if let Err(message) = validate_key(&key) {
+        return json_error_response(StatusCode::BAD_REQUEST, message, request_id).into_response();
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let scope = format!(
+        "http:{}:{}:{}",
+        caller_scope(principal.as_ref()),
+        method,
+        path
+    );
+
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return json_error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Request body too large for an idempotent request",
+                request_id,
+            )
+            .into_response()
+        }
+    };
+    let request_hash = request_fingerprint(&method, &path, &bytes);
+
+    let pending = IdempotencyRecord {
+        request_hash: request_hash.clone(),
+        state: RecordState::InProgress,
+        status_code: None,
+        content_type: None,
+        body: None,
+    };
+
+    match store.reserve(&scope, &key, &pending, IN_PROGRESS_TTL).await {
+        Ok(ReserveOutcome::Reserved) => {}
+        Ok(ReserveOutcome::Existing(existing)) => {
+            return existing_response(existing, &request_hash, request_id);
+        }
+        Err(e) if policy == IdempotencyPolicy::Payments => {
+            // Without the store a retry could charge the customer twice
+            warn!(key = %key, error = %e, "idempotency store unavailable, refusing payment request");
+            return json_error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Idempotency-Key cannot be honoured right now, retry later",
+                request_id,
+            )
+            .into_response();
+        }
+        Err(e) => {
+            // Running without the guarantee beats refusing every keyed request
+            warn!(key = %key, error = %e, "idempotency store unavailable, processing request");
+            return next
+                .run(Request::from_parts(parts, Body::from(bytes)))
+                .await;
+        }
+    }
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+
+    let status = response.status();
+    if status.is_server_error() && policy == IdempotencyPolicy::Standard {
+        release(&store, &scope, &key).await;
+        return response;
+    }
+
+    let (mut response_parts, response_body) = response.into_parts();
+    let response_bytes = match to_bytes(response_body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!(key = %key, error = %e, "failed to buffer response for idempotency");
+            release_unless_payment(policy, &store, &scope, &key).await;
+            return json_error_response(StatusCode::INTERNAL_SERVER_ERROR, "", request_id)
+                .into_response();
+        }
+    };
+
+    match String::from_utf8(response_bytes.to_vec()) {
+        Ok(body) => {
+            let record = IdempotencyRecord {
+                request_hash,
+                state: RecordState::Completed,
+                status_code: Some(status.as_u16()),
+                content_type: response_parts
+                    .headers
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|s| s.to_string()),
+                body: Some(body),
+            };
+            if let Err(e) = store.put(&scope, &key, &record, RECORD_TTL).await {
+                warn!(key = %key, error = %e, "failed to store idempotent response");
+            }
+        }
+        Err(_) => {
+            // Binary bodies aren't replayable
+            release_unless_payment(policy, &store, &scope, &key).await;
+        }
+    }
+
+    response_parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(response_parts, Body::from(response_bytes))
+}
+
+/// Response for a key that already has a record
+fn existing_response(
+    record: IdempotencyRecord,
+    request_hash: &str,
+    request_id: Option<String>,
+) -> Response {
+    if record.request_hash != request_hash {
+        return json_error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used with a different request",
+            request_id,
+        )
+        .into_response();
+    }
+
+    match record.state {
+        RecordState::InProgress => json_error_response(
+            StatusCode::CONFLICT,
+            "A request with this Idempotency-Key is still being processed",
+            request_id,
+        )
+        .into_response(),
+        RecordState::Completed => {
+            info!("replaying idempotent response");
+            replay(record)
+        }
+    }
+}
+
+/// Rebuild a stored response
+fn replay(record: IdempotencyRecord) -> Response {
+    let status = record
+        .status_code
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(StatusCode::OK);
+    let mut response = Response::new(Body::from(record.body.unwrap_or_default()));
+    *response.status_mut() = status;
+
+    let headers = response.headers_mut();
+    if let Some(content_type) = record
+        .content_type
+        .and_then(|ct| HeaderValue::from_str(&ct).ok())
+    {
+        headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+async fn release(store: &IdempotencyStore, scope: &str, key: &str) {
+    if let Err(e) = store.release(scope, key).await {
+        warn!(key = %key, error = %e, "failed to release idempotency key");
+    }
+}
+
+/// Payment keys whose response couldn't be stored stay leased until the
+/// in-progress TTL runs out, since the request may have charged the customer
+async fn release_unless_payment(
+    policy: IdempotencyPolicy,
+    store: &IdempotencyStore,
+    scope: &str,
+    key: &str,
+) {
+    if policy == IdempotencyPolicy::Standard {
+        release(store, scope, key).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mutating() {
+        assert!(is_mutating(&Method::POST));
+        assert!(is_mutating(&Method::PUT));
+        assert!(is_mutating(&Method::PATCH));
+        assert!(is_mutating(&Method::DELETE));
+        assert!(!is_mutating(&Method::GET));
+        assert!(!is_mutating(&Method::HEAD));
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("3f1c9b0e-7c4d-4a47-9f0a-0b8e6b0e2f11").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key(&"a".repeat(256)).is_err());
+        assert!(validate_key(&"a".repeat(255)).is_ok());
+        assert!(validate_key("has space").is_err());
+    }
+
+    #[test]
+    fn test_caller_scope_separates_callers() {
+        let principal = |kind, subject: &str| Principal {
+            kind,
+            subject: subject.to_string(),
+            client_id: None,
+            user_id: None,
+            scopes: vec![],
+            session_id: None,
+        };
+        let client = principal(PrincipalKind::ApiClient, "abc");
+        let user = principal(PrincipalKind::User, "abc");
+        assert_eq!(caller_scope(Some(&client)), "client:abc");
+        assert_eq!(caller_scope(Some(&user)), "user:abc");
+        assert_eq!(caller_scope(None), "anonymous");
+    }
+
+    #[test]
+    fn test_request_fingerprint() {
+        let a = request_fingerprint(&Method::POST, "/api/onramp/initiate", b"{\"amount\":1}");
+        let b = request_fingerprint(&Method::POST, "/api/onramp/initiate", b"{\"amount\":1}");
+        let c = request_fingerprint(&Method::POST, "/api/onramp/initiate", b"{\"amount\":2}");
+        let d = request_fingerprint(&Method::PUT, "/api/onramp/initiate", b"{\"amount\":1}");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+    }
+
+    #[test]
+    fn test_existing_response_statuses() {
+        let record = IdempotencyRecord {
+            request_hash: "abc".to_string(),
+            state: RecordState::InProgress,
+            status_code: None,
+            content_type: None,
+            body: None,
+        };
+        assert_eq!(
+            existing_response(record.clone(), "other", None).status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            existing_response(record, "abc", None).status(),
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[test]
+    fn test_replay_restores_response() {
+        let record = IdempotencyRecord {
+            request_hash: "abc".to_string(),
+            state: RecordState::Completed,
+            status_code: Some(201),
+            content_type: Some("application/json".to_string()),
+            body: Some("{\"ok\":true}".to_string()),
+        };
+        let response = existing_response(record, "abc", None);
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            response.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap(),
+            "true"
+        );
+    }
+}
@@ -11,6 +11,8 @@ use axum::{
     response::Response,
 };
 #[cfg(feature = "database")]
+use crate::middleware::client_ip::ClientIp;
+#[cfg(feature = "database")]
 use std::time::Instant;
 #[cfg(feature = "database")]
 use tower_http::request_id::{MakeRequestId, RequestId};
@@ -190,32 +192,17 @@ pub async fn request_logging_middleware(
     Ok(response)
 }
 
-/// Extract client IP address from request headers
+/// Client IP address resolved by the client IP middleware
 ///
-/// Checks X-Forwarded-For, X-Real-IP headers before falling back to
-/// the direct connection address.
+/// Forwarding headers are never read here: they are only trusted when the
+/// connection comes from a configured proxy, which `client_ip_middleware`
+/// decides from the connection address.
 #[cfg(feature = "database")]
 pub fn extract_client_ip(request: &Request) -> Option<String> {
-    // Check X-Forwarded-For header (may contain multiple IPs)
-    if let Some(forwarded) = request.headers().get("x-forwarded-for") {
-        if let Ok(forwarded_str) = forwarded.to_str() {
-            if let Some(first_ip) = forwarded_str.split(',').next() {
-                return Some(first_ip.trim().to_string());
-            }
-        }
-    }
-
-    // Check X-Real-IP header
-    if let Some(real_ip) = request.headers().get("x-real-ip") {
-        if let Ok(ip_str) = real_ip.to_str() {
-            return Some(ip_str.to_string());
-        }
-    }
-
-    // Try to get from connection info extension
-    // Note: This requires the ConnectInfo extension to be set by the server
-    // For local development, this will typically be 127.0.0.1
-    None
+    request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| ip.to_string())
 }
 
 /// Middleware for tracking database query performance
@@ -368,13 +355,17 @@ mod tests {
 
     #[test]
     fn test_extract_client_ip() {
-        let request = Request::builder()
+        let mut request = Request::builder()
             .header("x-forwarded-for", "192.168.1.1, 10.0.0.1")
             .body(Body::empty())
             .unwrap();
+        assert_eq!(extract_client_ip(&request), None);
 
+        request
+            .extensions_mut()
+            .insert(ClientIp("203.0.113.7".parse().unwrap()));
         let ip = extract_client_ip(&request);
-        assert_eq!(ip, Some("192.168.1.1".to_string()));
+        assert_eq!(ip, Some("203.0.113.7".to_string()));
     }
 
     #[tokio::test]
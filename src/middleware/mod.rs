@@ -2,8 +2,20 @@
 //!
 //! Provides request/response logging and error handling middleware
 
+#[cfg(feature = "database")]
+pub mod client_ip;
+
 #[cfg(feature = "database")]
 pub mod logging;
 
 #[cfg(feature = "database")]
 pub mod error;
+
+#[cfg(feature = "database")]
+pub mod auth;
+
+#[cfg(feature = "database")]
+pub mod idempotency;
+
+#[cfg(feature = "database")]
+pub mod rate_limit;
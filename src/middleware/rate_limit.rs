@@ -0,0 +1,593 @@
+//! Distributed rate limiting backed by Redis
+//!
+//! Each route group gets a [`RateLimitPolicy`] with optional limits per API
+//! client, per client IP and per wallet address. Counting uses a sliding
+//! window approximated from the current and previous fixed windows, so all
+//! instances share one budget without storing every request timestamp.
+//!
+//! Responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` for the tightest limit; rejected requests get 429 with
+//! `Retry-After`. When Redis is unavailable requests are let through.
+
+use crate::auth::api_key::hash_api_key;
+use crate::auth::Principal;
+use crate::cache::keys::auth::RateLimitKey;
+use crate::cache::RedisCache;
+use crate::error::{AppError, AppErrorKind, ExternalError};
+use crate::middleware::auth::{extract_credential, Credential};
+use crate::middleware::client_ip::ClientIp;
+use crate::middleware::error::get_request_id_from_headers;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Largest JSON body inspected for a `wallet_address` field
+const MAX_INSPECTED_BODY_BYTES: usize = 64 * 1024;
+
+/// A request budget over a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub requests: u64,
+    pub window: Duration,
+}
+
+impl Limit {
+    pub const fn per_minute(requests: u64) -> Self {
+        Self {
+            requests,
+            window: Duration::from_secs(60),
+        }
+    }
+
+    /// Parse `"<requests>/<seconds>"`, e.g. `"60/60"`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (requests, seconds) = value.trim().split_once('/')?;
+        let requests = requests.trim().parse().ok()?;
+        let seconds: u64 = seconds.trim().parse().ok()?;
+        if seconds == 0 {
+            return None;
+        }
+        Some(Self {
+            requests,
+            window: Duration::from_secs(seconds),
+        })
+    }
+}
+
+/// What a limit is keyed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    ApiKey,
+    Ip,
+    Wallet,
+}
+
+impl Dimension {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Dimension::ApiKey => "key",
+            Dimension::Ip => "ip",
+            Dimension::Wallet => "wallet",
+        }
+    }
+}
+
+/// Limits applied to one route group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitPolicy {
+    pub group: &'static str,
+    pub per_api_key: Option<Limit>,
+    pub per_ip: Option<Limit>,
+    pub per_wallet: Option<Limit>,
+}
+
+impl RateLimitPolicy {
+    pub fn new(group: &'static str) -> Self {
+        Self {
+            group,
+            per_api_key: None,
+            per_ip: None,
+            per_wallet: None,
+        }
+    }
+
+    pub fn per_api_key(mut self, limit: Limit) -> Self {
+        self.per_api_key = Some(limit);
+        self
+    }
+
+    pub fn per_ip(mut self, limit: Limit) -> Self {
+        self.per_ip = Some(limit);
+        self
+    }
+
+    pub fn per_wallet(mut self, limit: Limit) -> Self {
+        self.per_wallet = Some(limit);
+        self
+    }
+
+    /// Override limits from `RATE_LIMIT_<GROUP>_{KEY,IP,WALLET}` (`"<requests>/<seconds>"`).
+    /// A value of `off` removes that limit.
+    pub fn with_env_overrides(mut self) -> Self {
+        let prefix = format!("RATE_LIMIT_{}", self.group.to_uppercase());
+        for (suffix, slot) in [
+            ("KEY", &mut self.per_api_key),
+            ("IP", &mut self.per_ip),
+            ("WALLET", &mut self.per_wallet),
+        ] {
+            if let Ok(value) = std::env::var(format!("{}_{}", prefix, suffix)) {
+                if value.trim().eq_ignore_ascii_case("off") {
+                    *slot = None;
+                } else if let Some(limit) = Limit::parse(&value) {
+                    *slot = Some(limit);
+                } else {
+                    warn!(group = self.group, value = %value, "Ignoring invalid rate limit override");
+                }
+            }
+        }
+        self
+    }
+
+    /// Quote and fee endpoints: each request fans out to Horizon and the database
+    pub fn quotes() -> Self {
+        Self::new("quotes")
+            .per_api_key(Limit::per_minute(120))
+            .per_ip(Limit::per_minute(60))
+            .per_wallet(Limit::per_minute(20))
+            .with_env_overrides()
+    }
+
+    /// Payment initiation, signing and submission
+    pub fn payments() -> Self {
+        Self::new("payments")
+            .per_api_key(Limit::per_minute(60))
+            .per_ip(Limit::per_minute(30))
+            .per_wallet(Limit::per_minute(10))
+            .with_env_overrides()
+    }
+
+    /// Everything else behind the API
+    pub fn standard() -> Self {
+        Self::new("standard")
+            .per_api_key(Limit::per_minute(300))
+            .per_ip(Limit::per_minute(120))
+            .with_env_overrides()
+    }
+
+    fn needs_wallet(&self) -> bool {
+        self.per_wallet.is_some()
+    }
+}
+
+/// Outcome of checking one limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the current window rolls over
+    pub reset_after: u64,
+}
+
+/// Sliding-window estimate: the previous window's count weighted by how much
+/// of it still overlaps the sliding window, plus the current count.
+pub fn sliding_window_count(
+    previous: u64,
+    current: u64,
+    elapsed: Duration,
+    window: Duration,
+) -> f64 {
+    let fraction = (elapsed.as_secs_f64() / window.as_secs_f64()).clamp(0.0, 1.0);
+    previous as f64 * (1.0 - fraction) + current as f64
+}
+
+/// Evaluate a limit after counting this request in `current`
+pub fn evaluate(limit: Limit, previous: u64, current: u64, elapsed: Duration) -> Decision {
+    let estimate = sliding_window_count(previous, current, elapsed, limit.window);
+    let reset_after = limit.window.saturating_sub(elapsed).as_secs().max(1);
+    Decision {
+        allowed: estimate <= limit.requests as f64,
+        limit: limit.requests,
+        remaining: (limit.requests as f64 - estimate).floor().max(0.0) as u64,
+        reset_after,
+    }
+}
+
+/// Redis-backed counter store
+#[derive(Clone)]
+pub struct RateLimiter {
+    cache: Option<RedisCache>,
+}
+
+impl RateLimiter {
+    pub fn new(cache: Option<RedisCache>) -> Self {
+        Self { cache }
+    }
+
+    /// Count a request against `limit` for `identifier`. Returns `None` when
+    /// Redis can't be reached, in which case the request is not limited.
+    pub async fn check(&self, group: &str, identifier: &str, limit: Limit) -> Option<Decision> {
+        let cache = self.cache.as_ref()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        let window_secs = limit.window.as_secs().max(1);
+        let index = now.as_secs() / window_secs;
+        let elapsed = now.saturating_sub(Duration::from_secs(index * window_secs));
+
+        let current_key = RateLimitKey::new(identifier, format!("{}:{}", group, index)).to_string();
+        let previous_key =
+            RateLimitKey::new(identifier, format!("{}:{}", group, index.saturating_sub(1)))
+                .to_string();
+
+        let mut conn = match cache.get_connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                debug!(error = %e, "Rate limiter could not reach Redis");
+                return None;
+            }
+        };
+
+        let result: redis::RedisResult<(u64, Option<u64>)> = redis::pipe()
+            .atomic()
+            .cmd("INCR")
+            .arg(&current_key)
+            .cmd("EXPIRE")
+            .arg(&current_key)
+            .arg(window_secs * 2)
+            .ignore()
+            .cmd("GET")
+            .arg(&previous_key)
+            .query_async(&mut *conn)
+            .await;
+
+        let (current, previous) = match result {
+            Ok(counts) => counts,
+            Err(e) => {
+                warn!(error = %e, "Rate limiter Redis command failed");
+                return None;
+            }
+        };
+
+        let decision = evaluate(limit, previous.unwrap_or(0), current, elapsed);
+        if !decision.allowed {
+            // Rejected requests don't consume budget
+            let _: redis::RedisResult<i64> = redis::cmd("DECR")
+                .arg(&current_key)
+                .query_async(&mut *conn)
+                .await;
+        }
+        Some(decision)
+    }
+}
+
+/// State for [`rate_limit_middleware`]
+#[derive(Clone)]
+pub struct RateLimitState {
+    limiter: RateLimiter,
+    policy: Arc<RateLimitPolicy>,
+}
+
+impl RateLimitState {
+    pub fn new(limiter: RateLimiter, policy: RateLimitPolicy) -> Self {
+        Self {
+            limiter,
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+/// Apply a policy to a route group that doesn't require authentication.
+/// Protected groups use [`rate_limit_protected`].
+pub fn rate_limit<S>(router: Router<S>, limiter: &RateLimiter, policy: RateLimitPolicy) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.route_layer(axum::middleware::from_fn_with_state(
+        RateLimitState::new(limiter.clone(), policy),
+        rate_limit_middleware,
+    ))
+}
+
+/// Apply a policy to a route group put behind auth by `protect` (normally a
+/// call to `middleware::auth::protect`).
+///
+/// The per-IP limit runs before authentication, so requests with missing or
+/// invalid credentials are throttled before any API key lookup. The per-key
+/// and per-wallet limits run after it, against the verified principal.
+pub fn rate_limit_protected<S>(
+    router: Router<S>,
+    limiter: &RateLimiter,
+    policy: RateLimitPolicy,
+    protect: impl FnOnce(Router<S>) -> Router<S>,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let callers = RateLimitPolicy {
+        per_ip: None,
+        ..policy.clone()
+    };
+    let addresses = RateLimitPolicy {
+        per_api_key: None,
+        per_wallet: None,
+        ..policy
+    };
+    // Route layers added later run first
+    rate_limit(
+        protect(rate_limit(router, limiter, callers)),
+        limiter,
+        addresses,
+    )
+}
+
+/// Enforce the group's limits and decorate the response with rate limit headers
+pub async fn rate_limit_middleware(
+    State(state): State<RateLimitState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let policy = &state.policy;
+    let (request, wallet) = if policy.needs_wallet() {
+        match wallet_from_request(request).await {
+            Ok(found) => found,
+            Err(response) => return response,
+        }
+    } else {
+        (request, None)
+    };
+
+    let mut checks: Vec<(Dimension, String, Limit)> = Vec::new();
+    if let (Some(limit), Some(id)) = (policy.per_api_key, client_identifier(&request)) {
+        checks.push((Dimension::ApiKey, id, limit));
+    }
+    // Without a resolved address there is no caller to count against; a
+    // shared fallback bucket would let one client throttle everyone
+    if let (Some(limit), Some(ClientIp(ip))) =
+        (policy.per_ip, request.extensions().get::<ClientIp>())
+    {
+        checks.push((Dimension::Ip, ip.to_string(), limit));
+    }
+    if let (Some(limit), Some(wallet)) = (policy.per_wallet, wallet) {
+        checks.push((Dimension::Wallet, wallet, limit));
+    }
+
+    let mut tightest: Option<Decision> = None;
+    for (dimension, id, limit) in checks {
+        let identifier = format!("{}:{}", dimension.as_str(), id);
+        let Some(decision) = state.limiter.check(policy.group, &identifier, limit).await else {
+            continue;
+        };
+
+        if !decision.allowed {
+            debug!(
+                group = policy.group,
+                dimension = dimension.as_str(),
+                "Rate limit exceeded"
+            );
+            return too_many_requests(policy.group, decision, request.headers());
+        }
+        if tightest.is_none_or(|t| decision.remaining < t.remaining) {
+            tightest = Some(decision);
+        }
+    }
+
+    let mut response = next.run(request).await;
+    if let Some(decision) = tightest {
+        apply_headers(response.headers_mut(), &decision);
+    }
+    response
+}
+
+/// Identify the caller's API client or session without re-verifying it
+fn client_identifier(request: &Request) -> Option<String> {
+    if let Some(principal) = request.extensions().get::<Principal>() {
+        return Some(
+            principal
+                .client_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| principal.subject.clone()),
+        );
+    }
+    // Auth disabled: fall back to a digest of whatever credential was sent
+    match extract_credential(request.headers())? {
+        Credential::ApiKey(key) => Some(hash_api_key(key)[..16].to_string()),
+        Credential::Bearer(token) => Some(hash_api_key(token)[..16].to_string()),
+    }
+}
+
+/// Find a wallet address in the query string or a JSON body, re-attaching the body
+async fn wallet_from_request(request: Request) -> Result<(Request, Option<String>), Response> {
+    if let Some(wallet) = request.uri().query().and_then(wallet_from_query) {
+        return Ok((request, Some(wallet)));
+    }
+
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    if !is_json {
+        return Ok((request, None));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_INSPECTED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return Err(crate::middleware::error::json_error_response(
+                axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+                "Request body too large",
+                get_request_id_from_headers(&parts.headers),
+            )
+            .into_response())
+        }
+    };
+    let wallet = wallet_from_json(&bytes);
+    Ok((Request::from_parts(parts, Body::from(bytes)), wallet))
+}
+
+fn wallet_from_query(query: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        matches!(name, "wallet_address" | "address")
+            .then(|| value.to_string())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+fn wallet_from_json(body: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    value
+        .get("wallet_address")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn apply_headers(headers: &mut HeaderMap, decision: &Decision) {
+    // An inner layer may already have reported a tighter limit
+    let reported = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if reported.is_some_and(|remaining| remaining <= decision.remaining) {
+        return;
+    }
+    headers.insert("x-ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert(
+        "x-ratelimit-remaining",
+        HeaderValue::from(decision.remaining),
+    );
+    headers.insert("x-ratelimit-reset", HeaderValue::from(decision.reset_after));
+}
+
+fn too_many_requests(group: &str, decision: Decision, headers: &HeaderMap) -> Response {
+    let mut error = AppError::new(AppErrorKind::External(ExternalError::RateLimit {
+        service: group.to_string(),
+        retry_after: Some(decision.reset_after),
+    }));
+    if let Some(request_id) = get_request_id_from_headers(headers) {
+        error = error.with_request_id(request_id);
+    }
+
+    let mut response = error.into_response();
+    apply_headers(response.headers_mut(), &decision);
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(decision.reset_after));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_parse() {
+        assert_eq!(Limit::parse("60/60"), Some(Limit::per_minute(60)));
+        assert_eq!(
+            Limit::parse(" 5 / 1 "),
+            Some(Limit {
+                requests: 5,
+                window: Duration::from_secs(1)
+            })
+        );
+        assert_eq!(Limit::parse("60"), None);
+        assert_eq!(Limit::parse("60/0"), None);
+        assert_eq!(Limit::parse("x/60"), None);
+    }
+
+    #[test]
+    fn test_sliding_window_weights_previous_window() {
+        let window = Duration::from_secs(60);
+        assert_eq!(sliding_window_count(10, 0, Duration::ZERO, window), 10.0);
+        assert_eq!(
+            sliding_window_count(10, 2, Duration::from_secs(30), window),
+            7.0
+        );
+        assert_eq!(
+            sliding_window_count(10, 2, Duration::from_secs(60), window),
+            2.0
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let limit = Limit::per_minute(10);
+
+        let decision = evaluate(limit, 0, 4, Duration::from_secs(15));
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 6);
+        assert_eq!(decision.reset_after, 45);
+
+        // 10 * 0.5 + 6 = 11 > 10
+        let decision = evaluate(limit, 10, 6, Duration::from_secs(30));
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+    }
+
+    #[test]
+    fn test_wallet_extraction() {
+        assert_eq!(
+            wallet_from_query("foo=1&wallet_address=GABC"),
+            Some("GABC".to_string())
+        );
+        assert_eq!(wallet_from_query("address=GXYZ"), Some("GXYZ".to_string()));
+        assert_eq!(wallet_from_query("wallet_address="), None);
+        assert_eq!(
+            wallet_from_json(br#"{"amount_ngn":5000,"wallet_address":" GABC "}"#),
+            Some("GABC".to_string())
+        );
+        assert_eq!(wallet_from_json(b"not json"), None);
+    }
+
+    #[test]
+    fn test_too_many_requests_response() {
+        let decision = Decision {
+            allowed: false,
+            limit: 10,
+            remaining: 0,
+            reset_after: 42,
+        };
+        let response = too_many_requests("quotes", decision, &HeaderMap::new());
+        assert_eq!(response.status(), axum::http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "42");
+        assert_eq!(response.headers().get("x-ratelimit-limit").unwrap(), "10");
+        assert_eq!(
+            response.headers().get("x-ratelimit-remaining").unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn test_policy_presets() {
+        let quotes = RateLimitPolicy::new("quotes").per_wallet(Limit::per_minute(5));
+        assert!(quotes.needs_wallet());
+        assert!(!RateLimitPolicy::new("standard").needs_wallet());
+    }
+
+    #[test]
+    fn test_headers_keep_the_tightest_limit() {
+        let decision = |limit, remaining| Decision {
+            allowed: true,
+            limit,
+            remaining,
+            reset_after: 30,
+        };
+        let mut headers = HeaderMap::new();
+        apply_headers(&mut headers, &decision(60, 5));
+        apply_headers(&mut headers, &decision(120, 90));
+        assert_eq!(headers.get("x-ratelimit-limit").unwrap(), "60");
+        assert_eq!(headers.get("x-ratelimit-remaining").unwrap(), "5");
+
+        apply_headers(&mut headers, &decision(30, 2));
+        assert_eq!(headers.get("x-ratelimit-remaining").unwrap(), "2");
+    }
+}
@@ -1,16 +1,105 @@
 use crate::payments::error::{PaymentError, PaymentResult};
 use crate::payments::provider::PaymentProvider;
 use crate::payments::types::{
-    PaymentRequest, PaymentResponse, PaymentState, ProviderName, StatusRequest, StatusResponse,
-    WebhookEvent, WebhookVerificationResult, WithdrawalRequest, WithdrawalResponse,
+    Money, PaymentMethod, PaymentRequest, PaymentResponse, PaymentState, ProviderName,
+    StatusRequest, StatusResponse, WebhookEvent, WebhookVerificationResult, WithdrawalMethod,
+    WithdrawalRequest, WithdrawalResponse,
 };
+use crate::payments::utils::{secure_eq, PaymentHttpClient};
 use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Header carrying the callback secret. Daraja can't set headers, so the
+/// gateway in front of the callback URL adds it; a query parameter would end
+/// up in request logs.
+pub const CALLBACK_SECRET_HEADER: &str = "x-mpesa-callback-secret";
+
+/// Prefix of the webhook "signature" built by [`callback_signature`]
+pub const CALLBACK_IP_PREFIX: &str = "ip:";
+
+/// Daraja callbacks are unsigned, so the webhook handler passes the client
+/// address and the secret header (empty when absent) as
+/// `ip:<address> <secret>`.
+pub fn callback_signature(source: IpAddr, secret: &str) -> String {
+    format!("{}{} {}", CALLBACK_IP_PREFIX, source, secret)
+}
+
+/// Safaricom's published callback source addresses
+const DEFAULT_CALLBACK_IPS: [&str; 12] = [
+    "196.201.214.200",
+    "196.201.214.206",
+    "196.201.213.114",
+    "196.201.214.207",
+    "196.201.214.208",
+    "196.201.213.44",
+    "196.201.212.127",
+    "196.201.212.138",
+    "196.201.212.129",
+    "196.201.212.136",
+    "196.201.212.74",
+    "196.201.212.69",
+];
+
+/// Refresh OAuth tokens this long before Daraja expires them
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Daraja's "transaction is being processed" error for STK queries
+const STK_QUERY_PENDING_CODE: &str = "500.001.1001";
 
 #[derive(Debug, Clone)]
 pub struct MpesaConfig {
     pub consumer_key: String,
     pub consumer_secret: String,
     pub passkey: String,
+    /// Paybill or till number used for STK push
+    pub shortcode: String,
+    pub base_url: String,
+    /// Default STK callback URL when the request doesn't carry one
+    pub callback_url: Option<String>,
+    /// Shortcode used as B2C `PartyA`; defaults to `shortcode`
+    pub b2c_shortcode: Option<String>,
+    pub initiator_name: Option<String>,
+    /// Initiator password encrypted with the Daraja certificate
+    pub security_credential: Option<String>,
+    pub b2c_result_url: Option<String>,
+    pub b2c_timeout_url: Option<String>,
+    /// Shared secret expected in [`CALLBACK_SECRET_HEADER`] on callbacks;
+    /// empty skips the check
+    pub callback_secret: String,
+    /// Addresses callbacks may come from; empty skips the check
+    pub allowed_callback_ips: Vec<IpAddr>,
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+}
+
+impl Default for MpesaConfig {
+    fn default() -> Self {
+        Self {
+            consumer_key: String::new(),
+            consumer_secret: String::new(),
+            passkey: String::new(),
+            shortcode: String::new(),
+            base_url: "https://sandbox.safaricom.co.ke".to_string(),
+            callback_url: None,
+            b2c_shortcode: None,
+            initiator_name: None,
+            security_credential: None,
+            b2c_result_url: None,
+            b2c_timeout_url: None,
+            callback_secret: String::new(),
+            allowed_callback_ips: default_callback_ips(),
+            timeout_secs: 30,
+            max_retries: 3,
+        }
+    }
 }
 
 impl MpesaConfig {
@@ -18,62 +107,383 @@ impl MpesaConfig {
         let consumer_key = std::env::var("MPESA_CONSUMER_KEY").unwrap_or_default();
         let consumer_secret = std::env::var("MPESA_CONSUMER_SECRET").unwrap_or_default();
         let passkey = std::env::var("MPESA_PASSKEY").unwrap_or_default();
-        if consumer_key.is_empty() || consumer_secret.is_empty() || passkey.is_empty() {
+        let shortcode = std::env::var("MPESA_SHORTCODE").unwrap_or_default();
+        let callback_secret = std::env::var("MPESA_CALLBACK_SECRET").unwrap_or_default();
+        if consumer_key.is_empty()
+            || consumer_secret.is_empty()
+            || passkey.is_empty()
+            || shortcode.is_empty()
+        {
             return Err(PaymentError::ValidationError {
-                message: "MPESA_CONSUMER_KEY, MPESA_CONSUMER_SECRET and MPESA_PASSKEY are required"
+                message: "MPESA_CONSUMER_KEY, MPESA_CONSUMER_SECRET, MPESA_PASSKEY and MPESA_SHORTCODE are required"
                     .to_string(),
                 field: Some("mpesa".to_string()),
             });
         }
+
+        let allowed_callback_ips = match std::env::var("MPESA_CALLBACK_ALLOWED_IPS") {
+            Ok(list) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| {
+                    ip.parse::<IpAddr>()
+                        .map_err(|_| PaymentError::ValidationError {
+                            message: format!(
+                                "invalid IP address in MPESA_CALLBACK_ALLOWED_IPS: {}",
+                                ip
+                            ),
+                            field: Some("MPESA_CALLBACK_ALLOWED_IPS".to_string()),
+                        })
+                })
+                .collect::<PaymentResult<Vec<_>>>()?,
+            Err(_) => default_callback_ips(),
+        };
+        if callback_secret.is_empty() && allowed_callback_ips.is_empty() {
+            return Err(PaymentError::ValidationError {
+                message: "MPESA_CALLBACK_SECRET or MPESA_CALLBACK_ALLOWED_IPS is required to verify callbacks"
+                    .to_string(),
+                field: Some("mpesa".to_string()),
+            });
+        }
+
         Ok(Self {
             consumer_key,
             consumer_secret,
             passkey,
+            shortcode,
+            base_url: std::env::var("MPESA_BASE_URL")
+                .unwrap_or_else(|_| "https://sandbox.safaricom.co.ke".to_string()),
+            callback_url: std::env::var("MPESA_CALLBACK_URL").ok(),
+            b2c_shortcode: std::env::var("MPESA_B2C_SHORTCODE").ok(),
+            initiator_name: std::env::var("MPESA_INITIATOR_NAME").ok(),
+            security_credential: std::env::var("MPESA_SECURITY_CREDENTIAL").ok(),
+            b2c_result_url: std::env::var("MPESA_B2C_RESULT_URL").ok(),
+            b2c_timeout_url: std::env::var("MPESA_B2C_TIMEOUT_URL").ok(),
+            callback_secret,
+            allowed_callback_ips,
+            timeout_secs: std::env::var("MPESA_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(30),
+            max_retries: std::env::var("MPESA_MAX_RETRIES")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(3),
         })
     }
 }
 
+fn default_callback_ips() -> Vec<IpAddr> {
+    DEFAULT_CALLBACK_IPS
+        .iter()
+        .filter_map(|ip| ip.parse().ok())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct AccessToken {
+    value: String,
+    expires_at: Instant,
+}
+
 pub struct MpesaProvider {
-    _config: MpesaConfig,
+    config: MpesaConfig,
+    http: PaymentHttpClient,
+    token: RwLock<Option<AccessToken>>,
 }
 
 impl MpesaProvider {
-    pub fn from_env() -> PaymentResult<Self> {
+    pub fn new(config: MpesaConfig) -> PaymentResult<Self> {
+        let http =
+            PaymentHttpClient::new(Duration::from_secs(config.timeout_secs), config.max_retries)?;
         Ok(Self {
-            _config: MpesaConfig::from_env()?,
+            config,
+            http,
+            token: RwLock::new(None),
         })
     }
+
+    pub fn from_env() -> PaymentResult<Self> {
+        Self::new(MpesaConfig::from_env()?)
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.config.base_url, path)
+    }
+
+    /// OAuth access token, fetched with client credentials and cached until shortly before expiry
+    async fn access_token(&self) -> PaymentResult<String> {
+        if let Some(token) = self.token.read().await.as_ref() {
+            if token.expires_at > Instant::now() {
+                return Ok(token.value.clone());
+            }
+        }
+
+        let mut slot = self.token.write().await;
+        // Another request may have refreshed the token while we waited for the lock
+        if let Some(token) = slot.as_ref() {
+            if token.expires_at > Instant::now() {
+                return Ok(token.value.clone());
+            }
+        }
+
+        let credentials = STANDARD.encode(format!(
+            "{}:{}",
+            self.config.consumer_key, self.config.consumer_secret
+        ));
+        let authorization = format!("Basic {}", credentials);
+        let raw: MpesaTokenResponse = self
+            .http
+            .request_json(
+                reqwest::Method::GET,
+                &self.endpoint("/oauth/v1/generate?grant_type=client_credentials"),
+                None,
+                None,
+                &[("Authorization", authorization.as_str())],
+            )
+            .await?;
+
+        let lifetime = Duration::from_secs(raw.expires_in.as_secs().unwrap_or(3599));
+        *slot = Some(AccessToken {
+            value: raw.access_token.clone(),
+            expires_at: Instant::now() + lifetime.saturating_sub(TOKEN_REFRESH_MARGIN),
+        });
+        Ok(raw.access_token)
+    }
+
+    /// POST to Daraja with a bearer token, dropping the cached token if it was rejected
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        payload: &JsonValue,
+    ) -> PaymentResult<T> {
+        let token = self.access_token().await?;
+        let result = self
+            .http
+            .request_json(
+                reqwest::Method::POST,
+                &self.endpoint(path),
+                Some(&token),
+                Some(payload),
+                &[("Content-Type", "application/json")],
+            )
+            .await;
+
+        if let Err(PaymentError::ProviderError {
+            provider_code: Some(code),
+            ..
+        }) = &result
+        {
+            if code == "401" {
+                *self.token.write().await = None;
+            }
+        }
+        result
+    }
+
+    /// STK password and the timestamp it was derived from
+    fn stk_password(&self) -> (String, String) {
+        let timestamp = daraja_timestamp();
+        let password = STANDARD.encode(format!(
+            "{}{}{}",
+            self.config.shortcode, self.config.passkey, timestamp
+        ));
+        (password, timestamp)
+    }
+
+    fn require_setting<'a>(value: &'a Option<String>, name: &str) -> PaymentResult<&'a str> {
+        value
+            .as_deref()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| PaymentError::ValidationError {
+                message: format!("{} must be configured for this M-Pesa operation", name),
+                field: Some(name.to_string()),
+            })
+    }
 }
 
 #[async_trait]
 impl PaymentProvider for MpesaProvider {
-    async fn initiate_payment(&self, _request: PaymentRequest) -> PaymentResult<PaymentResponse> {
-        Err(PaymentError::ProviderError {
-            provider: "mpesa".to_string(),
-            message: "not implemented yet".to_string(),
-            provider_code: None,
-            retryable: false,
+    async fn initiate_payment(&self, request: PaymentRequest) -> PaymentResult<PaymentResponse> {
+        request.amount.validate_positive("amount")?;
+        let amount = whole_amount(&request.amount)?;
+        let phone = normalize_msisdn(request.customer.phone.as_deref().unwrap_or(""))?;
+        let callback_url = match request.callback_url.as_ref() {
+            Some(url) => url.as_str(),
+            None => Self::require_setting(&self.config.callback_url, "MPESA_CALLBACK_URL")?,
+        };
+
+        let (password, timestamp) = self.stk_password();
+        let payload = serde_json::json!({
+            "BusinessShortCode": self.config.shortcode,
+            "Password": password,
+            "Timestamp": timestamp,
+            "TransactionType": "CustomerPayBillOnline",
+            "Amount": amount,
+            "PartyA": phone,
+            "PartyB": self.config.shortcode,
+            "PhoneNumber": phone,
+            "CallBackURL": callback_url,
+            "AccountReference": account_reference(&request.transaction_reference),
+            "TransactionDesc": "Payment",
+        });
+
+        let raw: MpesaStkPushResponse = self
+            .post("/mpesa/stkpush/v1/processrequest", &payload)
+            .await?;
+        if raw.response_code != "0" {
+            return Err(PaymentError::ProviderError {
+                provider: "mpesa".to_string(),
+                message: raw.response_description,
+                provider_code: Some(raw.response_code),
+                retryable: false,
+            });
+        }
+        info!(checkout_request_id = %raw.checkout_request_id, "mpesa STK push initiated");
+
+        Ok(PaymentResponse {
+            status: PaymentState::Pending,
+            transaction_reference: request.transaction_reference,
+            provider_reference: Some(raw.checkout_request_id.clone()),
+            payment_url: None,
+            amount_charged: Some(request.amount),
+            fees_charged: None,
+            provider_data: Some(serde_json::json!({
+                "merchant_request_id": raw.merchant_request_id,
+                "checkout_request_id": raw.checkout_request_id,
+                "customer_message": raw.customer_message,
+            })),
         })
     }
 
-    async fn verify_payment(&self, _request: StatusRequest) -> PaymentResult<StatusResponse> {
-        Err(PaymentError::ProviderError {
-            provider: "mpesa".to_string(),
-            message: "not implemented yet".to_string(),
-            provider_code: None,
-            retryable: false,
+    async fn verify_payment(&self, request: StatusRequest) -> PaymentResult<StatusResponse> {
+        let checkout_request_id = request
+            .provider_reference
+            .clone()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or(PaymentError::ValidationError {
+                message: "provider_reference (CheckoutRequestID) is required".to_string(),
+                field: Some("provider_reference".to_string()),
+            })?;
+
+        let (password, timestamp) = self.stk_password();
+        let payload = serde_json::json!({
+            "BusinessShortCode": self.config.shortcode,
+            "Password": password,
+            "Timestamp": timestamp,
+            "CheckoutRequestID": checkout_request_id,
+        });
+
+        let raw: MpesaStkQueryResponse =
+            match self.post("/mpesa/stkpushquery/v1/query", &payload).await {
+                Ok(raw) => raw,
+                Err(PaymentError::ProviderError { message, .. })
+                    if message.contains(STK_QUERY_PENDING_CODE) =>
+                {
+                    return Ok(StatusResponse {
+                        status: PaymentState::Pending,
+                        transaction_reference: request.transaction_reference,
+                        provider_reference: Some(checkout_request_id),
+                        amount: None,
+                        payment_method: Some(PaymentMethod::MobileMoney),
+                        timestamp: None,
+                        failure_reason: None,
+                        provider_data: None,
+                    });
+                }
+                Err(e) => return Err(e),
+            };
+
+        let result_code = raw.result_code.as_ref().and_then(DarajaCode::as_i64);
+        let status = result_code
+            .map(stk_result_state)
+            .unwrap_or(PaymentState::Pending);
+
+        Ok(StatusResponse {
+            failure_reason: match status {
+                PaymentState::Success | PaymentState::Pending => None,
+                _ => raw.result_desc.clone(),
+            },
+            status,
+            transaction_reference: request.transaction_reference,
+            provider_reference: Some(checkout_request_id),
+            amount: None,
+            payment_method: Some(PaymentMethod::MobileMoney),
+            timestamp: None,
+            provider_data: Some(serde_json::json!({
+                "merchant_request_id": raw.merchant_request_id,
+                "result_code": result_code,
+                "result_desc": raw.result_desc,
+            })),
         })
     }
 
     async fn process_withdrawal(
         &self,
-        _request: WithdrawalRequest,
+        request: WithdrawalRequest,
     ) -> PaymentResult<WithdrawalResponse> {
-        Err(PaymentError::ProviderError {
-            provider: "mpesa".to_string(),
-            message: "not implemented yet".to_string(),
-            provider_code: None,
-            retryable: false,
+        request.amount.validate_positive("amount")?;
+        if !matches!(request.withdrawal_method, WithdrawalMethod::MobileMoney) {
+            return Err(PaymentError::ValidationError {
+                message: "mpesa supports mobile money withdrawals only".to_string(),
+                field: Some("withdrawal_method".to_string()),
+            });
+        }
+        let amount = whole_amount(&request.amount)?;
+        let phone = normalize_msisdn(request.recipient.phone_number.as_deref().ok_or(
+            PaymentError::ValidationError {
+                message: "recipient.phone_number is required".to_string(),
+                field: Some("recipient.phone_number".to_string()),
+            },
+        )?)?;
+
+        let initiator_name =
+            Self::require_setting(&self.config.initiator_name, "MPESA_INITIATOR_NAME")?;
+        let security_credential = Self::require_setting(
+            &self.config.security_credential,
+            "MPESA_SECURITY_CREDENTIAL",
+        )?;
+        let result_url =
+            Self::require_setting(&self.config.b2c_result_url, "MPESA_B2C_RESULT_URL")?;
+        let timeout_url = self.config.b2c_timeout_url.as_deref().unwrap_or(result_url);
+
+        let payload = serde_json::json!({
+            "OriginatorConversationID": request.transaction_reference,
+            "InitiatorName": initiator_name,
+            "SecurityCredential": security_credential,
+            "CommandID": "BusinessPayment",
+            "Amount": amount,
+            "PartyA": self.config.b2c_shortcode.as_deref().unwrap_or(&self.config.shortcode),
+            "PartyB": phone,
+            "Remarks": request.reason.as_deref().unwrap_or("Withdrawal"),
+            "QueueTimeOutURL": timeout_url,
+            "ResultURL": result_url,
+            "Occasion": request.transaction_reference,
+        });
+
+        let raw: MpesaB2cResponse = self.post("/mpesa/b2c/v3/paymentrequest", &payload).await?;
+        if raw.response_code != "0" {
+            return Err(PaymentError::ProviderError {
+                provider: "mpesa".to_string(),
+                message: raw.response_description,
+                provider_code: Some(raw.response_code),
+                retryable: false,
+            });
+        }
+        info!(conversation_id = %raw.conversation_id, "mpesa B2C payment requested");
+
+        Ok(WithdrawalResponse {
+            status: PaymentState::Processing,
+            transaction_reference: request.transaction_reference,
+            provider_reference: Some(raw.conversation_id.clone()),
+            amount_debited: Some(request.amount),
+            fees_charged: None,
+            estimated_completion_seconds: Some(60),
+            provider_data: Some(serde_json::json!({
+                "conversation_id": raw.conversation_id,
+                "originator_conversation_id": raw.originator_conversation_id,
+            })),
         })
     }
 
@@ -93,27 +503,541 @@ impl PaymentProvider for MpesaProvider {
         &["KE", "TZ", "UG"]
     }
 
+    /// Daraja doesn't sign callbacks. The signature from
+    /// [`callback_signature`] must carry the secret when one is configured
+    /// and a source address in the allowlist when one is configured; with
+    /// neither configured every callback is rejected.
     fn verify_webhook(
         &self,
         _payload: &[u8],
-        _signature: &str,
+        signature: &str,
     ) -> PaymentResult<WebhookVerificationResult> {
+        let (source, secret) = signature
+            .strip_prefix(CALLBACK_IP_PREFIX)
+            .and_then(|rest| rest.split_once(' '))
+            .unwrap_or(("", ""));
+        let check_secret = !self.config.callback_secret.is_empty();
+        let check_source = !self.config.allowed_callback_ips.is_empty();
+        let (valid, reason) = if !check_secret && !check_source {
+            (false, "mpesa callback verification is not configured")
+        } else if check_secret
+            && !secure_eq(self.config.callback_secret.as_bytes(), secret.as_bytes())
+        {
+            (false, "invalid mpesa callback secret")
+        } else if check_source
+            && !source
+                .parse::<IpAddr>()
+                .map(|ip| self.config.allowed_callback_ips.contains(&ip))
+                .unwrap_or(false)
+        {
+            (false, "mpesa callback from unrecognised source address")
+        } else {
+            (true, "")
+        };
+
+        if !valid {
+            warn!(reason, "rejected mpesa callback");
+        }
         Ok(WebhookVerificationResult {
-            valid: false,
-            reason: Some("not implemented yet".to_string()),
+            valid,
+            reason: if valid {
+                None
+            } else {
+                Some(reason.to_string())
+            },
         })
     }
 
     fn parse_webhook_event(&self, payload: &[u8]) -> PaymentResult<WebhookEvent> {
-        let parsed = serde_json::from_slice(payload).unwrap_or_else(|_| serde_json::json!({}));
-        Ok(WebhookEvent {
-            provider: ProviderName::Mpesa,
-            event_type: "unknown".to_string(),
-            transaction_reference: None,
-            provider_reference: None,
-            status: Some(PaymentState::Unknown),
-            payload: parsed,
-            received_at: chrono::Utc::now().to_rfc3339(),
+        let parsed: JsonValue = serde_json::from_slice(payload).map_err(|e| {
+            PaymentError::WebhookVerificationError {
+                message: format!("invalid webhook JSON payload: {}", e),
+            }
+        })?;
+
+        // STK push callback: {"Body": {"stkCallback": {...}}}
+        if let Some(callback) = parsed.get("Body").and_then(|b| b.get("stkCallback")) {
+            let success = callback.get("ResultCode").and_then(json_code) == Some(0);
+            return Ok(WebhookEvent {
+                provider: ProviderName::Mpesa,
+                event_type: if success {
+                    "charge.success"
+                } else {
+                    "charge.failed"
+                }
+                .to_string(),
+                transaction_reference: None,
+                provider_reference: callback
+                    .get("CheckoutRequestID")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string()),
+                status: Some(
+                    callback
+                        .get("ResultCode")
+                        .and_then(json_code)
+                        .map(stk_result_state)
+                        .unwrap_or(PaymentState::Unknown),
+                ),
+                payload: parsed,
+                received_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+
+        // B2C result or queue timeout: {"Result": {...}}
+        if let Some(result) = parsed.get("Result") {
+            let success = result.get("ResultCode").and_then(json_code) == Some(0);
+            return Ok(WebhookEvent {
+                provider: ProviderName::Mpesa,
+                event_type: if success {
+                    "transfer.success"
+                } else {
+                    "transfer.failed"
+                }
+                .to_string(),
+                transaction_reference: result
+                    .get("OriginatorConversationID")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string()),
+                provider_reference: result
+                    .get("ConversationID")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string()),
+                status: Some(if success {
+                    PaymentState::Success
+                } else {
+                    PaymentState::Failed
+                }),
+                payload: parsed,
+                received_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+
+        Err(PaymentError::WebhookVerificationError {
+            message: "unrecognised mpesa callback payload".to_string(),
         })
     }
 }
+
+/// Daraja timestamps are `YYYYMMDDHHmmss` in East Africa Time
+fn daraja_timestamp() -> String {
+    let eat = chrono::FixedOffset::east_opt(3 * 3600).expect("valid offset");
+    chrono::Utc::now()
+        .with_timezone(&eat)
+        .format("%Y%m%d%H%M%S")
+        .to_string()
+}
+
+/// Daraja only accepts whole currency units
+fn whole_amount(money: &Money) -> PaymentResult<u64> {
+    let invalid = || PaymentError::ValidationError {
+        message: format!("mpesa amounts must be whole units: {}", money.amount),
+        field: Some("amount".to_string()),
+    };
+    let amount = BigDecimal::from_str(&money.amount).map_err(|_| invalid())?;
+    if !amount.is_integer() {
+        return Err(invalid());
+    }
+    amount.to_u64().ok_or_else(invalid)
+}
+
+/// Normalize a phone number to the `2547XXXXXXXX` form Daraja expects
+fn normalize_msisdn(phone: &str) -> PaymentResult<String> {
+    let digits: String = phone
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '+'))
+        .collect();
+    let normalized = match digits.len() {
+        10 if digits.starts_with('0') => format!("254{}", &digits[1..]),
+        9 if digits.starts_with('7') || digits.starts_with('1') => format!("254{}", digits),
+        _ => digits,
+    };
+
+    if normalized.len() != 12 || !normalized.chars().all(|c| c.is_ascii_digit()) {
+        return Err(PaymentError::ValidationError {
+            message: "a valid M-Pesa phone number is required".to_string(),
+            field: Some("phone".to_string()),
+        });
+    }
+    Ok(normalized)
+}
+
+/// `AccountReference` is limited to 12 characters
+fn account_reference(transaction_reference: &str) -> String {
+    let alphanumeric: String = transaction_reference
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    let start = alphanumeric.len().saturating_sub(12);
+    alphanumeric[start..].to_string()
+}
+
+fn stk_result_state(code: i64) -> PaymentState {
+    match code {
+        0 => PaymentState::Success,
+        1032 => PaymentState::Cancelled,
+        _ => PaymentState::Failed,
+    }
+}
+
+/// Result codes arrive as numbers in callbacks and as strings in query responses
+fn json_code(value: &JsonValue) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DarajaCode {
+    Number(i64),
+    Text(String),
+}
+
+impl DarajaCode {
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            DarajaCode::Number(n) => Some(*n),
+            DarajaCode::Text(s) => s.trim().parse().ok(),
+        }
+    }
+
+    fn as_secs(&self) -> Option<u64> {
+        self.as_i64().and_then(|v| u64::try_from(v).ok())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MpesaTokenResponse {
+    access_token: String,
+    expires_in: DarajaCode,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaStkPushResponse {
+    #[serde(rename = "MerchantRequestID")]
+    merchant_request_id: String,
+    #[serde(rename = "CheckoutRequestID")]
+    checkout_request_id: String,
+    response_code: String,
+    response_description: String,
+    #[serde(default)]
+    customer_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaStkQueryResponse {
+    #[serde(rename = "MerchantRequestID", default)]
+    merchant_request_id: Option<String>,
+    #[serde(default)]
+    result_code: Option<DarajaCode>,
+    #[serde(default)]
+    result_desc: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaB2cResponse {
+    #[serde(rename = "ConversationID")]
+    conversation_id: String,
+    #[serde(rename = "OriginatorConversationID", default)]
+    originator_conversation_id: Option<String>,
+    response_code: String,
+    response_description: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payments::types::{CustomerContact, WithdrawalRecipient};
+    use axum::{http::HeaderMap, routing::get, routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const MOCK_TOKEN: &str = "mock-access-token";
+
+    /// Minimal Daraja stand-in; returns its base URL and the OAuth call counter
+    async fn mock_daraja() -> (String, Arc<AtomicUsize>) {
+        let token_calls = Arc::new(AtomicUsize::new(0));
+        let counter = token_calls.clone();
+
+        let authorized = |headers: &HeaderMap| {
+            headers.get("authorization").and_then(|v| v.to_str().ok())
+                == Some(format!("Bearer {}", MOCK_TOKEN).as_str())
+        };
+
+        let app = Router::new()
+            .route(
+                "/oauth/v1/generate",
+                get(move |headers: HeaderMap| {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        let expected = format!("Basic {}", STANDARD.encode("key:secret"));
+                        assert_eq!(
+                            headers.get("authorization").unwrap().to_str().unwrap(),
+                            expected
+                        );
+                        Json(serde_json::json!({
+                            "access_token": MOCK_TOKEN,
+                            "expires_in": "3599"
+                        }))
+                    }
+                }),
+            )
+            .route(
+                "/mpesa/stkpush/v1/processrequest",
+                post(move |headers: HeaderMap, Json(body): Json<JsonValue>| async move {
+                    assert!(authorized(&headers));
+                    assert_eq!(body["PhoneNumber"], "254712345678");
+                    assert_eq!(body["Amount"], 100);
+                    assert_eq!(body["TransactionType"], "CustomerPayBillOnline");
+                    Json(serde_json::json!({
+                        "MerchantRequestID": "29115-34620561-1",
+                        "CheckoutRequestID": "ws_CO_191220191020363925",
+                        "ResponseCode": "0",
+                        "ResponseDescription": "Success. Request accepted for processing",
+                        "CustomerMessage": "Success. Request accepted for processing"
+                    }))
+                }),
+            )
+            .route(
+                "/mpesa/stkpushquery/v1/query",
+                post(move |headers: HeaderMap, Json(body): Json<JsonValue>| async move {
+                    assert!(authorized(&headers));
+                    let code = if body["CheckoutRequestID"] == "ws_CO_cancelled" {
+                        "1032"
+                    } else {
+                        "0"
+                    };
+                    Json(serde_json::json!({
+                        "ResponseCode": "0",
+                        "ResponseDescription": "The service request has been accepted successsfully",
+                        "MerchantRequestID": "22205-34066-1",
+                        "CheckoutRequestID": body["CheckoutRequestID"],
+                        "ResultCode": code,
+                        "ResultDesc": "Request processed"
+                    }))
+                }),
+            )
+            .route(
+                "/mpesa/b2c/v3/paymentrequest",
+                post(move |headers: HeaderMap, Json(body): Json<JsonValue>| async move {
+                    assert!(authorized(&headers));
+                    assert_eq!(body["CommandID"], "BusinessPayment");
+                    assert_eq!(body["PartyB"], "254712345678");
+                    Json(serde_json::json!({
+                        "ConversationID": "AG_20191219_00005797af5d7d75f652",
+                        "OriginatorConversationID": body["OriginatorConversationID"],
+                        "ResponseCode": "0",
+                        "ResponseDescription": "Accept the service request successfully."
+                    }))
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), token_calls)
+    }
+
+    fn config(base_url: &str) -> MpesaConfig {
+        MpesaConfig {
+            consumer_key: "key".to_string(),
+            consumer_secret: "secret".to_string(),
+            passkey: "passkey".to_string(),
+            shortcode: "174379".to_string(),
+            base_url: base_url.to_string(),
+            callback_url: Some("https://example.com/webhooks/mpesa".to_string()),
+            initiator_name: Some("testapi".to_string()),
+            security_credential: Some("credential".to_string()),
+            b2c_result_url: Some("https://example.com/webhooks/mpesa".to_string()),
+            timeout_secs: 5,
+            max_retries: 0,
+            ..MpesaConfig::default()
+        }
+    }
+
+    fn payment_request(phone: &str, amount: &str) -> PaymentRequest {
+        PaymentRequest {
+            amount: Money {
+                amount: amount.to_string(),
+                currency: "KES".to_string(),
+            },
+            customer: CustomerContact {
+                email: None,
+                phone: Some(phone.to_string()),
+            },
+            payment_method: PaymentMethod::MobileMoney,
+            callback_url: None,
+            transaction_reference: "txn-ref-0001".to_string(),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn stk_push_and_query_against_mock_daraja() {
+        let (base_url, token_calls) = mock_daraja().await;
+        let provider = MpesaProvider::new(config(&base_url)).unwrap();
+
+        let response = provider
+            .initiate_payment(payment_request("0712 345 678", "100.00"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, PaymentState::Pending);
+        assert_eq!(
+            response.provider_reference.as_deref(),
+            Some("ws_CO_191220191020363925")
+        );
+
+        let status = provider
+            .verify_payment(StatusRequest {
+                transaction_reference: Some("txn-ref-0001".to_string()),
+                provider_reference: response.provider_reference.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(status.status, PaymentState::Success);
+
+        let cancelled = provider
+            .verify_payment(StatusRequest {
+                transaction_reference: None,
+                provider_reference: Some("ws_CO_cancelled".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(cancelled.status, PaymentState::Cancelled);
+
+        // The OAuth token is fetched once and reused
+        assert_eq!(token_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn b2c_withdrawal_against_mock_daraja() {
+        let (base_url, _) = mock_daraja().await;
+        let provider = MpesaProvider::new(config(&base_url)).unwrap();
+
+        let response = provider
+            .process_withdrawal(WithdrawalRequest {
+                amount: Money {
+                    amount: "500".to_string(),
+                    currency: "KES".to_string(),
+                },
+                recipient: WithdrawalRecipient {
+                    account_name: None,
+                    account_number: None,
+                    bank_code: None,
+                    phone_number: Some("+254712345678".to_string()),
+                },
+                withdrawal_method: WithdrawalMethod::MobileMoney,
+                transaction_reference: "wd-ref-1".to_string(),
+                reason: None,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status, PaymentState::Processing);
+        assert_eq!(
+            response.provider_reference.as_deref(),
+            Some("AG_20191219_00005797af5d7d75f652")
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_fractional_amounts_and_bad_phones() {
+        let provider = MpesaProvider::new(config("http://127.0.0.1:9")).unwrap();
+        assert!(matches!(
+            provider
+                .initiate_payment(payment_request("0712345678", "10.50"))
+                .await,
+            Err(PaymentError::ValidationError { .. })
+        ));
+        assert!(matches!(
+            provider
+                .initiate_payment(payment_request("12345", "10"))
+                .await,
+            Err(PaymentError::ValidationError { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_stk_and_b2c_callbacks() {
+        let provider = MpesaProvider::new(config("http://127.0.0.1:9")).unwrap();
+
+        let stk = br#"{"Body":{"stkCallback":{"MerchantRequestID":"29115-34620561-1","CheckoutRequestID":"ws_CO_191220191020363925","ResultCode":0,"ResultDesc":"The service request is processed successfully.","CallbackMetadata":{"Item":[{"Name":"Amount","Value":100},{"Name":"MpesaReceiptNumber","Value":"NLJ7RT61SV"}]}}}}"#;
+        let event = provider.parse_webhook_event(stk).unwrap();
+        assert_eq!(event.event_type, "charge.success");
+        assert_eq!(
+            event.provider_reference.as_deref(),
+            Some("ws_CO_191220191020363925")
+        );
+        assert_eq!(event.status, Some(PaymentState::Success));
+
+        let b2c = br#"{"Result":{"ResultType":0,"ResultCode":2001,"ResultDesc":"The initiator information is invalid.","OriginatorConversationID":"wd-ref-1","ConversationID":"AG_20191219_00004e48cf7e3533f581","TransactionID":"NLJ41HAY6Q"}}"#;
+        let event = provider.parse_webhook_event(b2c).unwrap();
+        assert_eq!(event.event_type, "transfer.failed");
+        assert_eq!(event.transaction_reference.as_deref(), Some("wd-ref-1"));
+        assert_eq!(event.status, Some(PaymentState::Failed));
+
+        assert!(provider.parse_webhook_event(br#"{"foo":1}"#).is_err());
+    }
+
+    #[test]
+    fn callback_verification_checks_what_is_configured() {
+        let safaricom: IpAddr = "196.201.214.200".parse().unwrap();
+        let other: IpAddr = "10.0.0.1".parse().unwrap();
+
+        let provider = MpesaProvider::new(config("http://127.0.0.1:9")).unwrap();
+        let verify = |signature: &str| provider.verify_webhook(b"{}", signature).unwrap().valid;
+        assert!(verify(&callback_signature(safaricom, "")));
+        assert!(!verify(&callback_signature(other, "")));
+
+        let provider = MpesaProvider::new(MpesaConfig {
+            callback_secret: "s3cret".to_string(),
+            allowed_callback_ips: Vec::new(),
+            ..config("http://127.0.0.1:9")
+        })
+        .unwrap();
+        let verify = |signature: &str| provider.verify_webhook(b"{}", signature).unwrap().valid;
+        assert!(verify(&callback_signature(other, "s3cret")));
+        assert!(!verify(&callback_signature(safaricom, "")));
+
+        let provider = MpesaProvider::new(MpesaConfig {
+            allowed_callback_ips: Vec::new(),
+            ..config("http://127.0.0.1:9")
+        })
+        .unwrap();
+        assert!(
+            !provider
+                .verify_webhook(b"{}", &callback_signature(safaricom, ""))
+                .unwrap()
+                .valid
+        );
+
+        let provider = MpesaProvider::new(MpesaConfig {
+            callback_secret: "s3cret".to_string(),
+            ..config("http://127.0.0.1:9")
+        })
+        .unwrap();
+        let verify = |signature: &str| provider.verify_webhook(b"{}", signature).unwrap().valid;
+        assert!(verify(&callback_signature(safaricom, "s3cret")));
+        assert!(!verify(&callback_signature(safaricom, "wrong")));
+        assert!(!verify(&callback_signature(other, "s3cret")));
+        assert!(!verify("s3cret"));
+        assert!(!verify("ip:196.201.214.200"));
+    }
+
+    #[test]
+    fn normalizes_phone_numbers_and_references() {
+        assert_eq!(normalize_msisdn("0712345678").unwrap(), "254712345678");
+        assert_eq!(normalize_msisdn("712345678").unwrap(), "254712345678");
+        assert_eq!(
+            normalize_msisdn("+254 712-345-678").unwrap(),
+            "254712345678"
+        );
+        assert_eq!(account_reference("txn-ref-000123456789"), "000123456789");
+    }
+}
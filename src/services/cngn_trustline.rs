@@ -67,6 +67,9 @@ pub struct TrustlineTransaction {
     pub limit: Option<String>,
     pub estimated_fee: String,
     pub min_balance_required: String,
+    /// The new trustline's reserve is paid by the platform sponsor account
+    #[serde(default)]
+    pub reserve_sponsored: bool,
 }
 
 /// Manager for cNGN trustline operations
@@ -75,6 +78,7 @@ pub struct CngnTrustlineService {
     cngn_config: CngnAssetConfig,
     verification_timeout: Duration,
     polling_interval: Duration,
+    reserve_sponsored: bool,
 }
 
 impl CngnTrustlineService {
@@ -84,6 +88,7 @@ impl CngnTrustlineService {
             cngn_config: CngnAssetConfig::from_env(),
             verification_timeout: Duration::from_secs(30),
             polling_interval: Duration::from_secs(2),
+            reserve_sponsored: false,
         }
     }
 
@@ -93,9 +98,17 @@ impl CngnTrustlineService {
             cngn_config,
             verification_timeout: Duration::from_secs(30),
             polling_interval: Duration::from_secs(2),
+            reserve_sponsored: false,
         }
     }
 
+    /// Validate balances for trustlines built with a CAP-33 reserve sponsor,
+    /// where the new subentry reserve is paid by the sponsor account
+    pub fn with_sponsored_reserve(mut self, sponsored: bool) -> Self {
+        self.reserve_sponsored = sponsored;
+        self
+    }
+
     /// Check if an account has a trustline for cNGN
     ///
     /// # Arguments
@@ -175,13 +188,8 @@ impl CngnTrustlineService {
             .await
             .map_err(AppError::from)?;
 
-        // Calculate required reserves
-        let num_subentries = account_info.subentry_count as f64;
-        let base_reserve = BASE_RESERVE_XLM * 2.0; // Account base reserve
-        let subentry_reserve = num_subentries * TRUSTLINE_RESERVE_XLM;
-        let new_trustline_reserve = TRUSTLINE_RESERVE_XLM;
-        let required_balance =
-            base_reserve + subentry_reserve + new_trustline_reserve + MIN_BALANCE_BUFFER_XLM;
+        // Sponsored entries don't count against this account's reserve
+        let required_balance = self.calculate_required_balance(account_info.reserve_entries());
 
         // Get current XLM balance
         let xlm_balance = account_info
@@ -259,10 +267,7 @@ impl CngnTrustlineService {
             .await
             .map_err(AppError::from)?;
 
-        let num_subentries = account_info.subentry_count as f64;
-        let min_balance_required = BASE_RESERVE_XLM * 2.0
-            + (num_subentries + 1.0) * TRUSTLINE_RESERVE_XLM
-            + MIN_BALANCE_BUFFER_XLM;
+        let min_balance_required = self.calculate_required_balance(account_info.reserve_entries());
 
         let tx_details = TrustlineTransaction {
             account_id: account_id.to_string(),
@@ -271,6 +276,7 @@ impl CngnTrustlineService {
             limit: self.cngn_config.default_limit.clone(),
             estimated_fee: "0.00001".to_string(), // Base fee
             min_balance_required: format!("{:.7}", min_balance_required),
+            reserve_sponsored: self.reserve_sponsored,
         };
 
         info!(
@@ -380,9 +386,14 @@ impl CngnTrustlineService {
 
     /// Get required XLM balance for trustline creation
     pub fn calculate_required_balance(&self, current_subentries: u32) -> f64 {
+        let new_trustline_reserve = if self.reserve_sponsored {
+            0.0
+        } else {
+            TRUSTLINE_RESERVE_XLM
+        };
         BASE_RESERVE_XLM * 2.0
             + (current_subentries as f64) * TRUSTLINE_RESERVE_XLM
-            + TRUSTLINE_RESERVE_XLM
+            + new_trustline_reserve
             + MIN_BALANCE_BUFFER_XLM
     }
 }
@@ -410,6 +421,10 @@ mod tests {
         // Account with 2 subentries
         let balance = manager.calculate_required_balance(2);
         assert_eq!(balance, 3.0); // 1.0 base + 1.0 existing + 0.5 trustline + 0.5 buffer
+
+        // Sponsor pays the new trustline's reserve
+        let manager = manager.with_sponsored_reserve(true);
+        assert_eq!(manager.calculate_required_balance(0), 1.5);
     }
 
     #[test]
@@ -0,0 +1,192 @@
+//! Fee sponsorship
+//!
+//! Submits user-signed transactions inside CAP-15 fee-bump envelopes paid by
+//! the platform fee account, so users can act on Stellar without holding XLM
+//! for fees. Every sponsored fee is reserved against a daily budget in Postgres
+//! before the envelope is submitted.
+
+use crate::chains::stellar::{
+    client::StellarClient,
+    errors::{StellarError, StellarResult},
+    fee_bump::{FeeBumpConfig, FeeBumpSponsor, FeeBumpedTransaction},
+    trustline::CngnAssetConfig,
+};
+use crate::database::error::DatabaseError;
+use crate::database::fee_sponsorship_repository::{
+    FeeSponsorshipRepository, NewSponsoredTransaction,
+};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SponsoredSubmission {
+    pub fee_bump: FeeBumpedTransaction,
+    pub horizon_response: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeBudgetStatus {
+    pub fee_account: String,
+    pub day: NaiveDate,
+    pub spent_stroops: i64,
+    pub budget_stroops: i64,
+    pub max_fee_stroops: i64,
+}
+
+pub struct FeeSponsorshipService {
+    stellar_client: StellarClient,
+    sponsor: FeeBumpSponsor,
+    repo: FeeSponsorshipRepository,
+    config: FeeBumpConfig,
+}
+
+impl FeeSponsorshipService {
+    /// Fails with a configuration error when no fee account is set
+    pub fn new(
+        stellar_client: StellarClient,
+        pool: PgPool,
+        config: FeeBumpConfig,
+    ) -> StellarResult<Self> {
+        let sponsor = FeeBumpSponsor::new(
+            &config,
+            &CngnAssetConfig::from_env(),
+            stellar_client.network(),
+        )?;
+        Ok(Self {
+            stellar_client,
+            sponsor,
+            repo: FeeSponsorshipRepository::new(pool),
+            config,
+        })
+    }
+
+    pub fn from_env(stellar_client: StellarClient, pool: PgPool) -> StellarResult<Self> {
+        Self::new(stellar_client, pool, FeeBumpConfig::from_env()?)
+    }
+
+    /// Wrap a user-signed envelope, charge its fee to today's budget and
+    /// submit it. The reservation is returned only if Horizon rejects the
+    /// envelope before it reaches a ledger.
+    pub async fn submit(&self, signed_inner_xdr: &str) -> StellarResult<SponsoredSubmission> {
+        let fee_bump = self.sponsor.wrap(signed_inner_xdr).await?;
+        let fee_account = self.sponsor.fee_source();
+        let day = Utc::now().date_naive();
+
+        let reserved = self
+            .repo
+            .reserve(
+                fee_account,
+                day,
+                fee_bump.fee_stroops,
+                self.config.daily_budget_stroops,
+            )
+            .await
+            .map_err(ledger_error)?;
+        if !reserved {
+            warn!(
+                fee_account,
+                inner_tx_hash = %fee_bump.inner_transaction_hash,
+                "daily fee sponsorship budget exhausted"
+            );
+            return Err(StellarError::fee_budget_exhausted(seconds_until_next_day()));
+        }
+
+        let record = match self
+            .repo
+            .record(&NewSponsoredTransaction {
+                fee_account: fee_account.to_string(),
+                inner_source: fee_bump.inner_source.clone(),
+                inner_tx_hash: fee_bump.inner_transaction_hash.clone(),
+                fee_bump_tx_hash: fee_bump.transaction_hash.clone(),
+                fee_stroops: fee_bump.fee_stroops,
+            })
+            .await
+        {
+            Ok(record) => record,
+            Err(e) => {
+                self.release(day, fee_bump.fee_stroops).await;
+                return Err(ledger_error(e));
+            }
+        };
+
+        match self
+            .stellar_client
+            .submit_transaction_xdr(&fee_bump.envelope_xdr)
+            .await
+        {
+            Ok(horizon_response) => {
+                if let Err(e) = self.repo.update_status(record.id, "submitted", None).await {
+                    warn!(id = %record.id, error = %e, "failed to mark sponsored transaction submitted");
+                }
+                info!(
+                    fee_account,
+                    fee_bump_tx_hash = %fee_bump.transaction_hash,
+                    fee_stroops = fee_bump.fee_stroops,
+                    "sponsored transaction submitted"
+                );
+                Ok(SponsoredSubmission {
+                    fee_bump,
+                    horizon_response,
+                })
+            }
+            Err(e) => {
+                // A transaction that failed while being applied still paid
+                // its fee, so only validation rejections give budget back
+                if e.is_rejected_before_apply() {
+                    self.release(day, fee_bump.fee_stroops).await;
+                }
+                if let Err(db_err) = self
+                    .repo
+                    .update_status(record.id, "failed", Some(&e.to_string()))
+                    .await
+                {
+                    warn!(id = %record.id, error = %db_err, "failed to mark sponsored transaction failed");
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn budget_status(&self) -> StellarResult<FeeBudgetStatus> {
+        let day = Utc::now().date_naive();
+        let spent_stroops = self
+            .repo
+            .spent_on(self.sponsor.fee_source(), day)
+            .await
+            .map_err(ledger_error)?;
+        Ok(FeeBudgetStatus {
+            fee_account: self.sponsor.fee_source().to_string(),
+            day,
+            spent_stroops,
+            budget_stroops: self.config.daily_budget_stroops,
+            max_fee_stroops: self.config.max_fee_stroops,
+        })
+    }
+
+    async fn release(&self, day: NaiveDate, fee_stroops: i64) {
+        if let Err(e) = self
+            .repo
+            .release(self.sponsor.fee_source(), day, fee_stroops)
+            .await
+        {
+            warn!(error = %e, fee_stroops, "failed to release fee sponsorship reservation");
+        }
+    }
+}
+
+fn ledger_error(err: DatabaseError) -> StellarError {
+    StellarError::unexpected_error(format!("fee sponsorship ledger: {}", err))
+}
+
+/// Budgets reset at UTC midnight
+fn seconds_until_next_day() -> u64 {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .map(|t| t.and_utc());
+    midnight
+        .map(|m| (m - now).num_seconds().max(1) as u64)
+        .unwrap_or(60)
+}
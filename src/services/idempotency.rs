@@ -0,0 +1,255 @@
+//! Idempotency key storage
+//!
+//! Shared by the payment orchestrator and the `Idempotency-Key` HTTP middleware.
+//! Records are read from Redis first and mirrored to Postgres, so a replay still
+//! resolves after a cache eviction or while Redis is unavailable.
+
+use crate::cache::cache::Cache;
+use crate::cache::keys::idempotency::IdempotencyKey;
+use crate::cache::RedisCache;
+use crate::database::error::DatabaseError;
+use crate::database::idempotency_repository::IdempotencyRepository;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdempotencyError {
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Outcome of trying to claim an idempotency key
+#[derive(Debug)]
+pub enum ReserveOutcome<T> {
+    /// The key was free and now belongs to the caller
+    Reserved,
+    /// The key is already held; carries the stored record
+    Existing(T),
+}
+
+/// Redis-backed idempotency store with a Postgres fallback
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    cache: Option<RedisCache>,
+    repository: Option<Arc<IdempotencyRepository>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(cache: Option<RedisCache>, repository: Option<Arc<IdempotencyRepository>>) -> Self {
+        Self { cache, repository }
+    }
+
+    /// Whether any backend is configured. Without one, every key is treated as new.
+    pub fn is_enabled(&self) -> bool {
+        self.cache.is_some() || self.repository.is_some()
+    }
+
+    /// Look up a record, falling back to Postgres on a cache miss and
+    /// re-populating Redis from it.
+    pub async fn get<T>(&self, scope: &str, key: &str) -> Result<Option<T>, IdempotencyError>
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let cache_key = IdempotencyKey::new(scope, key).to_string();
+
+        if let Some(cache) = &self.cache {
+            match <RedisCache as Cache<T>>::get(cache, &cache_key).await {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => {}
+                Err(e) => warn!(key = %cache_key, error = %e, "idempotency cache lookup failed"),
+            }
+        }
+
+        let Some(repository) = &self.repository else {
+            return Ok(None);
+        };
+        let Some(record) = repository.find_active(&cache_key).await? else {
+            return Ok(None);
+        };
+
+        let value: T = serde_json::from_value(record.payload)?;
+        if let Some(cache) = &self.cache {
+            let remaining = (record.expires_at - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default();
+            if !remaining.is_zero() {
+                let _ = cache.set(&cache_key, &value, Some(remaining)).await;
+            }
+        }
+        debug!(key = %cache_key, "idempotency record restored from database");
+        Ok(Some(value))
+    }
+
+    /// Claim a key by storing `value` only if no live record exists.
+    pub async fn reserve<T>(
+        &self,
+        scope: &str,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<ReserveOutcome<T>, IdempotencyError>
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        // Postgres may still hold a record that Redis has evicted.
+        if let Some(existing) = self.get::<T>(scope, key).await? {
+            return Ok(ReserveOutcome::Existing(existing));
+        }
+
+        let cache_key = IdempotencyKey::new(scope, key).to_string();
+        let payload = serde_json::to_value(value)?;
+
+        let reserved = match self.redis_set_nx(&cache_key, &payload, ttl).await {
+            Some(true) => {
+                if let Some(repository) = &self.repository {
+                    repository
+                        .upsert(&cache_key, scope, &payload, expires_at(ttl))
+                        .await?;
+                }
+                true
+            }
+            Some(false) => false,
+            // Redis unavailable or not configured: Postgres decides.
+            None => match &self.repository {
+                Some(repository) => {
+                    repository
+                        .try_insert(&cache_key, scope, &payload, expires_at(ttl))
+                        .await?
+                }
+                None => true,
+            },
+        };
+
+        if reserved {
+            return Ok(ReserveOutcome::Reserved);
+        }
+
+        match self.get::<T>(scope, key).await? {
+            Some(existing) => Ok(ReserveOutcome::Existing(existing)),
+            // The holder released the key between our attempts; report it as
+            // taken rather than racing again.
+            None => Ok(ReserveOutcome::Existing(serde_json::from_value(payload)?)),
+        }
+    }
+
+    /// Store or overwrite a record in every configured backend.
+    pub async fn put<T>(
+        &self,
+        scope: &str,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<(), IdempotencyError>
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let cache_key = IdempotencyKey::new(scope, key).to_string();
+
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.set(&cache_key, value, Some(ttl)).await {
+                warn!(key = %cache_key, error = %e, "failed to cache idempotency record");
+            }
+        }
+        if let Some(repository) = &self.repository {
+            let payload = serde_json::to_value(value)?;
+            repository
+                .upsert(&cache_key, scope, &payload, expires_at(ttl))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Drop a record so the client can retry with the same key.
+    pub async fn release(&self, scope: &str, key: &str) -> Result<(), IdempotencyError> {
+        let cache_key = IdempotencyKey::new(scope, key).to_string();
+
+        if let Some(cache) = &self.cache {
+            if let Err(e) =
+                <RedisCache as Cache<serde_json::Value>>::delete(cache, &cache_key).await
+            {
+                warn!(key = %cache_key, error = %e, "failed to delete cached idempotency record");
+            }
+        }
+        if let Some(repository) = &self.repository {
+            repository.delete(&cache_key).await?;
+        }
+        Ok(())
+    }
+
+    /// `SET key value NX EX ttl`. Returns `None` when Redis can't answer.
+    async fn redis_set_nx(
+        &self,
+        cache_key: &str,
+        payload: &serde_json::Value,
+        ttl: Duration,
+    ) -> Option<bool> {
+        let cache = self.cache.as_ref()?;
+        let mut conn = cache.get_connection().await.ok()?;
+
+        let result: redis::RedisResult<Option<String>> = redis::cmd("SET")
+            .arg(cache_key)
+            .arg(payload.to_string())
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl.as_secs().max(1))
+            .query_async(&mut *conn)
+            .await;
+
+        match result {
+            Ok(reply) => Some(reply.is_some()),
+            Err(e) => {
+                warn!(key = %cache_key, error = %e, "Redis SET NX failed for idempotency key");
+                None
+            }
+        }
+    }
+}
+
+fn expires_at(ttl: Duration) -> chrono::DateTime<chrono::Utc> {
+    chrono::Utc::now()
+        + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        value: u32,
+    }
+
+    #[tokio::test]
+    async fn store_without_backends_treats_every_key_as_new() {
+        let store = IdempotencyStore::new(None, None);
+        assert!(!store.is_enabled());
+
+        let found: Option<Record> = store.get("test", "k1").await.unwrap();
+        assert!(found.is_none());
+
+        let outcome = store
+            .reserve("test", "k1", &Record { value: 1 }, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(matches!(outcome, ReserveOutcome::Reserved));
+
+        store
+            .put("test", "k1", &Record { value: 2 }, Duration::from_secs(60))
+            .await
+            .unwrap();
+        store.release("test", "k1").await.unwrap();
+    }
+
+    #[test]
+    fn expiry_is_in_the_future() {
+        let at = expires_at(Duration::from_secs(3600));
+        let delta = at - chrono::Utc::now();
+        assert!(delta.num_seconds() > 3500 && delta.num_seconds() <= 3600);
+    }
+}
@@ -0,0 +1,1143 @@
+//! KYC for SEP-12 customers and the transaction limits it unlocks
+//!
+//! A customer is a Stellar account, plus a memo for shared accounts, that has
+//! submitted SEP-9 fields: names and birth date, and the identity number of
+//! its country (BVN or NIN in Nigeria, the national ID in Kenya). Values are
+//! encrypted with AES-256-GCM before they reach the database. Once the fields
+//! of a tier are complete they go to a [`KycVerificationProvider`] in the
+//! background, and the customer is `PROCESSING` until it answers.
+//!
+//! The tier verified for the account holder caps what its onramps and
+//! offramps may move, per transaction and per rolling 24 hours. cNGN counts at
+//! par with NGN.
+
+use crate::database::error::DatabaseError;
+use crate::database::kyc_customer_repository::{
+    CustomerFieldsUpdate, KycCustomer, KycCustomerRepository, KycStatus, VerificationResult,
+};
+use crate::database::transaction_repository::{DailyVolumeLimit, TransactionRepository};
+use crate::error::{AppError, AppErrorKind, DomainError};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Transaction types the limits apply to
+const LIMITED_TYPES: [&str; 2] = ["onramp", "offramp"];
+
+/// Fields whose change voids a verification already granted
+const IDENTITY_FIELDS: [&str; 7] = [
+    "first_name",
+    "last_name",
+    "birth_date",
+    "address_country_code",
+    "bvn",
+    "nin",
+    "national_id",
+];
+
+const MAX_FIELD_LENGTH: usize = 200;
+
+#[derive(Debug, Error)]
+pub enum KycError {
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("customer not found")]
+    NotFound,
+
+    #[error("KYC configuration error: {0}")]
+    Config(String),
+
+    #[error("KYC data could not be decrypted")]
+    Decryption,
+
+    #[error("verification provider error: {0}")]
+    Provider(String),
+
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+impl KycError {
+    /// HTTP status for this error
+    pub fn status_code(&self) -> u16 {
+        match self {
+            KycError::InvalidRequest(_) => 400,
+            KycError::Forbidden(_) => 403,
+            KycError::NotFound => 404,
+            KycError::Config(_) | KycError::Decryption | KycError::Database(_) => 500,
+            KycError::Provider(_) => 502,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tiers, countries and fields
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KycTier {
+    /// Nothing verified
+    Unverified = 0,
+    /// Names, birth date and a national identity number
+    Basic = 1,
+    /// Basic plus address, phone and, in Nigeria, both BVN and NIN
+    Full = 2,
+}
+
+impl KycTier {
+    pub fn from_i16(tier: i16) -> Self {
+        match tier {
+            2.. => KycTier::Full,
+            1 => KycTier::Basic,
+            _ => KycTier::Unverified,
+        }
+    }
+
+    pub fn as_i16(&self) -> i16 {
+        *self as i16
+    }
+
+    /// SEP-12 customer `type` asking for this tier
+    pub fn customer_type(&self) -> &'static str {
+        match self {
+            KycTier::Unverified | KycTier::Basic => "basic",
+            KycTier::Full => "full",
+        }
+    }
+
+    /// Tier a SEP-12 customer `type` stands for; `basic` when absent
+    pub fn from_customer_type(customer_type: Option<&str>) -> Result<Self, KycError> {
+        match customer_type.map(str::trim).filter(|t| !t.is_empty()) {
+            None | Some("basic") => Ok(KycTier::Basic),
+            Some("full") => Ok(KycTier::Full),
+            Some(other) => Err(KycError::InvalidRequest(format!(
+                "unsupported customer type '{}'; use 'basic' or 'full'",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Country {
+    Nigeria,
+    Kenya,
+}
+
+impl Country {
+    /// ISO 3166-1 alpha-3 code, as SEP-9 `address_country_code` uses
+    pub fn code(&self) -> &'static str {
+        match self {
+            Country::Nigeria => "NGA",
+            Country::Kenya => "KEN",
+        }
+    }
+
+    pub fn parse(code: &str) -> Option<Self> {
+        match code.trim().to_uppercase().as_str() {
+            "NGA" => Some(Country::Nigeria),
+            "KEN" => Some(Country::Kenya),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldFormat {
+    Text,
+    Date,
+    Country,
+    Email,
+    Phone,
+    /// Exactly this many digits, or a range of lengths
+    Digits(usize, usize),
+}
+
+struct FieldSpec {
+    name: &'static str,
+    description: &'static str,
+    format: FieldFormat,
+}
+
+/// SEP-9 fields, plus the identity numbers the Nigerian and Kenyan tiers use
+static FIELDS: [FieldSpec; 10] = [
+    FieldSpec {
+        name: "first_name",
+        description: "First or given name",
+        format: FieldFormat::Text,
+    },
+    FieldSpec {
+        name: "last_name",
+        description: "Last or family name",
+        format: FieldFormat::Text,
+    },
+    FieldSpec {
+        name: "birth_date",
+        description: "Date of birth, YYYY-MM-DD",
+        format: FieldFormat::Date,
+    },
+    FieldSpec {
+        name: "address_country_code",
+        description: "Country of residence, ISO 3166-1 alpha-3",
+        format: FieldFormat::Country,
+    },
+    FieldSpec {
+        name: "address",
+        description: "Full residential address",
+        format: FieldFormat::Text,
+    },
+    FieldSpec {
+        name: "mobile_number",
+        description: "Mobile number in E.164 format",
+        format: FieldFormat::Phone,
+    },
+    FieldSpec {
+        name: "email_address",
+        description: "Email address",
+        format: FieldFormat::Email,
+    },
+    FieldSpec {
+        name: "bvn",
+        description: "Nigerian Bank Verification Number",
+        format: FieldFormat::Digits(11, 11),
+    },
+    FieldSpec {
+        name: "nin",
+        description: "Nigerian National Identification Number",
+        format: FieldFormat::Digits(11, 11),
+    },
+    FieldSpec {
+        name: "national_id",
+        description: "Kenyan national ID card number",
+        format: FieldFormat::Digits(7, 9),
+    },
+];
+
+fn field_spec(name: &str) -> Option<&'static FieldSpec> {
+    FIELDS.iter().find(|spec| spec.name == name)
+}
+
+/// Fields a tier needs. Each entry is satisfied by any one of its names.
+fn requirements(country: Option<Country>, tier: KycTier) -> Vec<&'static [&'static str]> {
+    if tier == KycTier::Unverified {
+        return Vec::new();
+    }
+    let mut fields: Vec<&'static [&'static str]> = vec![
+        &["first_name"],
+        &["last_name"],
+        &["birth_date"],
+        &["address_country_code"],
+    ];
+    match (country, tier) {
+        (Some(Country::Nigeria), KycTier::Full) => fields.extend([&["bvn"][..], &["nin"]]),
+        (Some(Country::Nigeria), _) => fields.push(&["bvn", "nin"]),
+        (Some(Country::Kenya), _) => fields.push(&["national_id"]),
+        (None, _) => {}
+    }
+    if tier == KycTier::Full {
+        fields.extend([&["address"][..], &["mobile_number"]]);
+    }
+    fields
+}
+
+/// Requirements of `tier` not covered by the provided fields
+fn missing(
+    provided: &[String],
+    country: Option<Country>,
+    tier: KycTier,
+) -> Vec<&'static [&'static str]> {
+    requirements(country, tier)
+        .into_iter()
+        .filter(|names| !names.iter().any(|name| provided.iter().any(|p| p == name)))
+        .collect()
+}
+
+/// Highest tier the provided fields are complete for
+fn highest_tier(provided: &[String], country: Option<Country>) -> KycTier {
+    if country.is_none() {
+        return KycTier::Unverified;
+    }
+    [KycTier::Full, KycTier::Basic]
+        .into_iter()
+        .find(|tier| missing(provided, country, *tier).is_empty())
+        .unwrap_or(KycTier::Unverified)
+}
+
+fn validate_field(spec: &FieldSpec, value: &str) -> Result<String, KycError> {
+    let value = value.trim();
+    let invalid = |reason: &str| KycError::InvalidRequest(format!("{} {}", spec.name, reason));
+    if value.len() > MAX_FIELD_LENGTH {
+        return Err(invalid("is too long"));
+    }
+    match spec.format {
+        FieldFormat::Text => Ok(value.to_string()),
+        FieldFormat::Date => {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| invalid("must be a date in YYYY-MM-DD format"))?;
+            if date >= Utc::now().date_naive() {
+                return Err(invalid("must be in the past"));
+            }
+            Ok(date.to_string())
+        }
+        FieldFormat::Country => Country::parse(value)
+            .map(|country| country.code().to_string())
+            .ok_or_else(|| invalid("must be NGA or KEN")),
+        FieldFormat::Email => {
+            let valid = value
+                .split_once('@')
+                .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+            if !valid {
+                return Err(invalid("is not a valid email address"));
+            }
+            Ok(value.to_lowercase())
+        }
+        FieldFormat::Phone => {
+            let digits = value.strip_prefix('+').unwrap_or_default();
+            if !(8..=15).contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid("must be in E.164 format, e.g. +2348012345678"));
+            }
+            Ok(value.to_string())
+        }
+        FieldFormat::Digits(min, max) => {
+            if !(min..=max).contains(&value.len()) || !value.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid(&if min == max {
+                    format!("must be {} digits", min)
+                } else {
+                    format!("must be {} to {} digits", min, max)
+                }));
+            }
+            Ok(value.to_string())
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// Caps for one tier, in NGN; `None` is unlimited
+#[derive(Debug, Clone, Default)]
+pub struct TierLimits {
+    pub per_transaction: Option<BigDecimal>,
+    pub daily: Option<BigDecimal>,
+}
+
+/// Limits that apply to one account
+#[derive(Debug, Clone)]
+pub struct AccountLimits {
+    pub tier: KycTier,
+    pub limits: TierLimits,
+}
+
+impl AccountLimits {
+    pub fn check_transaction(&self, amount: &BigDecimal) -> Result<(), AppError> {
+        match self.limits.per_transaction.as_ref() {
+            Some(limit) if amount > limit => Err(self.exceeded(amount, limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The 24-hour cap, for the database to enforce when an order is filled
+    pub fn daily(&self) -> Option<DailyVolumeLimit<'static>> {
+        self.limits.daily.clone().map(|limit| DailyVolumeLimit {
+            types: &LIMITED_TYPES,
+            limit,
+        })
+    }
+
+    pub fn exceeded(&self, amount: &BigDecimal, limit: &BigDecimal) -> AppError {
+        limit_exceeded(self.tier, amount, limit)
+    }
+}
+
+pub fn limit_exceeded(tier: KycTier, amount: &BigDecimal, limit: &BigDecimal) -> AppError {
+    AppError::new(AppErrorKind::Domain(DomainError::LimitExceeded {
+        amount: amount.to_string(),
+        limit: limit.to_string(),
+        tier: tier.as_i16(),
+    }))
+}
+
+#[derive(Debug, Clone)]
+pub struct KycConfig {
+    pub cipher: KycCipher,
+    /// Verification provider name (`KYC_PROVIDER`)
+    pub provider: String,
+    /// Limits by tier, indexed by the tier number
+    pub limits: [TierLimits; 3],
+}
+
+impl KycConfig {
+    /// Load from environment. Returns `Ok(None)` when no encryption key is
+    /// set, in which case neither SEP-12 nor tier limits are enabled.
+    pub fn from_env() -> Result<Option<Self>, KycError> {
+        let Ok(key) = std::env::var("KYC_ENCRYPTION_KEY") else {
+            return Ok(None);
+        };
+        let limits = |tier: u8, per_transaction: i64, daily: i64| -> Result<TierLimits, KycError> {
+            Ok(TierLimits {
+                per_transaction: limit_from_env(
+                    &format!("KYC_TIER{}_TRANSACTION_LIMIT", tier),
+                    per_transaction,
+                )?,
+                daily: limit_from_env(&format!("KYC_TIER{}_DAILY_LIMIT", tier), daily)?,
+            })
+        };
+        Ok(Some(Self {
+            cipher: KycCipher::from_hex(&key)?,
+            provider: std::env::var("KYC_PROVIDER").unwrap_or_else(|_| "mock".to_string()),
+            limits: [
+                limits(0, 50_000, 50_000)?,
+                limits(1, 500_000, 2_000_000)?,
+                limits(2, 5_000_000, 25_000_000)?,
+            ],
+        }))
+    }
+
+    pub fn limits_for(&self, tier: KycTier) -> &TierLimits {
+        &self.limits[tier as usize]
+    }
+}
+
+/// Amount from the environment; `none` lifts the limit
+fn limit_from_env(name: &str, default: i64) -> Result<Option<BigDecimal>, KycError> {
+    match std::env::var(name) {
+        Ok(value) if value.trim().eq_ignore_ascii_case("none") => Ok(None),
+        Ok(value) => BigDecimal::from_str(value.trim())
+            .map(Some)
+            .map_err(|_| KycError::Config(format!("{} is not a number", name))),
+        Err(_) => Ok(Some(BigDecimal::from(default))),
+    }
+}
+
+/// Encrypts customer fields. The customer id is bound in as associated data,
+/// so a ciphertext copied onto another row does not decrypt.
+#[derive(Clone)]
+pub struct KycCipher {
+    cipher: Aes256Gcm,
+}
+
+impl fmt::Debug for KycCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KycCipher(..)")
+    }
+}
+
+impl KycCipher {
+    /// From a hex-encoded 32-byte key
+    pub fn from_hex(key: &str) -> Result<Self, KycError> {
+        let key = hex::decode(key.trim())
+            .ok()
+            .filter(|key| key.len() == 32)
+            .ok_or_else(|| {
+                KycError::Config("KYC_ENCRYPTION_KEY must be 32 bytes, hex encoded".to_string())
+            })?;
+        let key: [u8; 32] = key.try_into().map_err(|_| {
+            KycError::Config("KYC_ENCRYPTION_KEY must be 32 bytes, hex encoded".to_string())
+        })?;
+        Ok(Self {
+            cipher: Aes256Gcm::new(&Key::<Aes256Gcm>::from(key)),
+        })
+    }
+
+    /// Returns the nonce and ciphertext
+    fn encrypt(
+        &self,
+        customer_id: Uuid,
+        fields: &BTreeMap<String, String>,
+    ) -> Result<(Vec<u8>, Vec<u8>), KycError> {
+        let plaintext = serde_json::to_vec(fields)
+            .map_err(|e| KycError::Config(format!("failed to encode fields: {}", e)))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: customer_id.as_bytes(),
+                },
+            )
+            .map_err(|_| KycError::Config("failed to encrypt fields".to_string()))?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    fn decrypt(
+        &self,
+        customer_id: Uuid,
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<BTreeMap<String, String>, KycError> {
+        let nonce: [u8; 12] = nonce.try_into().map_err(|_| KycError::Decryption)?;
+        let plaintext = self
+            .cipher
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: customer_id.as_bytes(),
+                },
+            )
+            .map_err(|_| KycError::Decryption)?;
+        serde_json::from_slice(&plaintext).map_err(|_| KycError::Decryption)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Verification providers
+// ---------------------------------------------------------------------------
+
+/// Fields of one customer, sent for verification at a tier. Deliberately not
+/// `Debug`, so values cannot end up in logs.
+pub struct VerificationRequest {
+    pub customer_id: Uuid,
+    pub country: Country,
+    pub tier: KycTier,
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationOutcome {
+    Verified {
+        reference: Option<String>,
+    },
+    Rejected {
+        reason: String,
+        reference: Option<String>,
+    },
+}
+
+/// Identity verification service checking customer fields
+#[async_trait]
+pub trait KycVerificationProvider: Send + Sync {
+    /// Check the fields against the provider's records
+    async fn verify(&self, request: &VerificationRequest) -> Result<VerificationOutcome, KycError>;
+
+    /// Get provider name
+    fn name(&self) -> &str;
+}
+
+// ---------------------------------------------------------------------------
+// SEP-12 requests and responses
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CustomerQuery {
+    pub id: Option<String>,
+    pub account: Option<String>,
+    pub memo: Option<String>,
+    pub memo_type: Option<String>,
+    #[serde(rename = "type")]
+    pub customer_type: Option<String>,
+}
+
+/// `PUT /customer` body. Field values are everything besides the SEP-12
+/// parameters; names this anchor does not collect are ignored.
+#[derive(Clone, Default, Deserialize)]
+pub struct PutCustomer {
+    pub id: Option<String>,
+    pub account: Option<String>,
+    pub memo: Option<String>,
+    pub memo_type: Option<String>,
+    #[serde(rename = "type")]
+    pub customer_type: Option<String>,
+    #[serde(flatten)]
+    pub fields: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldDescription {
+    #[serde(rename = "type")]
+    pub field_type: &'static str,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub choices: Option<Vec<&'static str>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvidedField {
+    #[serde(rename = "type")]
+    pub field_type: &'static str,
+    pub description: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomerInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, FieldDescription>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub provided_fields: BTreeMap<String, ProvidedField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// SEP-12 status of a customer for the tier asked about
+fn sep12_status(customer: Option<&KycCustomer>, wanted: KycTier) -> &'static str {
+    let Some(customer) = customer else {
+        return "NEEDS_INFO";
+    };
+    if KycTier::from_i16(customer.tier) >= wanted {
+        return "ACCEPTED";
+    }
+    match customer.kyc_status() {
+        KycStatus::Processing if customer.requested_tier.map(KycTier::from_i16) >= Some(wanted) => {
+            "PROCESSING"
+        }
+        KycStatus::Rejected => "REJECTED",
+        _ => "NEEDS_INFO",
+    }
+}
+
+fn field_type(spec: &FieldSpec) -> &'static str {
+    match spec.format {
+        FieldFormat::Date => "date",
+        _ => "string",
+    }
+}
+
+fn describe_missing(names: &[&'static str]) -> BTreeMap<String, FieldDescription> {
+    let mut fields = BTreeMap::new();
+    for name in names {
+        let Some(spec) = field_spec(name) else {
+            continue;
+        };
+        let mut description = spec.description.to_string();
+        if names.len() > 1 {
+            description.push_str(&format!(" (one of {} is required)", names.join(", ")));
+        }
+        fields.insert(
+            spec.name.to_string(),
+            FieldDescription {
+                field_type: field_type(spec),
+                description,
+                choices: (spec.format == FieldFormat::Country).then(|| vec!["NGA", "KEN"]),
+                optional: names.len() > 1,
+            },
+        );
+    }
+    fields
+}
+
+fn parse_memo(memo: Option<&str>, memo_type: Option<&str>) -> Result<Option<String>, KycError> {
+    if memo_type.is_some_and(|t| !t.is_empty() && t != "id") {
+        return Err(KycError::InvalidRequest(
+            "only memo_type 'id' is supported".to_string(),
+        ));
+    }
+    match memo.map(str::trim).filter(|m| !m.is_empty()) {
+        None => Ok(None),
+        Some(memo) => memo
+            .parse::<u64>()
+            .map(|id| Some(id.to_string()))
+            .map_err(|_| KycError::InvalidRequest("memo must be an id memo".to_string())),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Service
+// ---------------------------------------------------------------------------
+
+#[derive(Clone)]
+pub struct KycService {
+    customers: Arc<KycCustomerRepository>,
+    transactions: Arc<TransactionRepository>,
+    cipher: Arc<KycCipher>,
+    limits: Arc<[TierLimits; 3]>,
+    provider: Arc<dyn KycVerificationProvider>,
+}
+
+impl KycService {
+    pub fn new(
+        config: KycConfig,
+        pool: PgPool,
+        provider: Arc<dyn KycVerificationProvider>,
+    ) -> Self {
+        Self {
+            customers: Arc::new(KycCustomerRepository::new(pool.clone())),
+            transactions: Arc::new(TransactionRepository::new(pool)),
+            cipher: Arc::new(config.cipher),
+            limits: Arc::new(config.limits),
+            provider,
+        }
+    }
+
+    pub fn provider_name(&self) -> &str {
+        self.provider.name()
+    }
+
+    /// `GET /customer`: where the caller's customer stands for a tier and
+    /// what is still needed for it
+    pub async fn customer(
+        &self,
+        account: &str,
+        query: CustomerQuery,
+    ) -> Result<CustomerInfo, KycError> {
+        check_account(account, query.account.as_deref())?;
+        let wanted = KycTier::from_customer_type(query.customer_type.as_deref())?;
+        let memo = parse_memo(query.memo.as_deref(), query.memo_type.as_deref())?;
+        let customer = match query.id.as_deref() {
+            Some(id) => Some(self.owned(account, id).await?),
+            None => {
+                self.customers
+                    .find_by_account(account, memo.as_deref())
+                    .await?
+            }
+        };
+        Ok(self.describe(customer.as_ref(), wanted))
+    }
+
+    /// `PUT /customer`: merge in new field values and start verification when
+    /// they complete a higher tier. Returns the customer id.
+    pub async fn put_customer(
+        &self,
+        account: &str,
+        request: PutCustomer,
+    ) -> Result<Uuid, KycError> {
+        check_account(account, request.account.as_deref())?;
+        if let Some(customer_type) = request.customer_type.as_deref() {
+            KycTier::from_customer_type(Some(customer_type))?;
+        }
+        let memo = parse_memo(request.memo.as_deref(), request.memo_type.as_deref())?;
+
+        let mut updates = BTreeMap::new();
+        for (name, value) in &request.fields {
+            let Some(spec) = field_spec(name) else {
+                debug!(field = %name, "Ignoring SEP-12 field that is not collected");
+                continue;
+            };
+            if value.trim().is_empty() {
+                continue;
+            }
+            updates.insert(spec.name.to_string(), validate_field(spec, value)?);
+        }
+
+        let customer = match request.id.as_deref() {
+            Some(id) => self.owned(account, id).await?,
+            None => {
+                self.customers
+                    .find_or_create(account, memo.as_deref())
+                    .await?
+            }
+        };
+        let existing = self.fields(&customer)?;
+        let mut fields = existing.clone();
+        fields.extend(updates);
+
+        let changed = fields != existing;
+        let current = customer.kyc_status();
+        if !changed && matches!(current, KycStatus::Processing | KycStatus::Rejected) {
+            return Ok(customer.id);
+        }
+
+        let identity_changed = IDENTITY_FIELDS
+            .iter()
+            .any(|name| fields.get(*name) != existing.get(*name));
+        let held = if identity_changed {
+            KycTier::Unverified
+        } else {
+            KycTier::from_i16(customer.tier)
+        };
+        let country = fields
+            .get("address_country_code")
+            .and_then(|code| Country::parse(code));
+        let provided: Vec<String> = fields.keys().cloned().collect();
+        let target = highest_tier(&provided, country);
+        let (status, requested_tier) = if target > held {
+            (KycStatus::Processing, Some(target))
+        } else if held > KycTier::Unverified {
+            (KycStatus::Accepted, None)
+        } else {
+            (KycStatus::NeedsInfo, None)
+        };
+        if !changed && status == current && requested_tier.is_none() {
+            return Ok(customer.id);
+        }
+
+        let (nonce, ciphertext) = self.cipher.encrypt(customer.id, &fields)?;
+        let updated = self
+            .customers
+            .update_fields(
+                customer.id,
+                &CustomerFieldsUpdate {
+                    country_code: country.map(|c| c.code()),
+                    provided_fields: &provided,
+                    fields_nonce: &nonce,
+                    fields_ciphertext: &ciphertext,
+                    status,
+                    tier: held.as_i16(),
+                    requested_tier: requested_tier.map(|t| t.as_i16()),
+                    status_message: None,
+                },
+            )
+            .await?
+            .ok_or(KycError::NotFound)?;
+        info!(
+            customer_id = %updated.id,
+            status = status.as_str(),
+            tier = updated.tier,
+            "SEP-12 customer updated"
+        );
+
+        if let (Some(tier), Some(country)) = (requested_tier, country) {
+            let service = self.clone();
+            let request = VerificationRequest {
+                customer_id: updated.id,
+                country,
+                tier,
+                fields,
+            };
+            tokio::spawn(async move { service.verify(request, nonce, held).await });
+        }
+        Ok(updated.id)
+    }
+
+    /// `DELETE /customer/{account}`: erase the customer and its fields
+    pub async fn delete_customer(
+        &self,
+        account: &str,
+        target: &str,
+        memo: Option<&str>,
+    ) -> Result<(), KycError> {
+        check_account(account, Some(target))?;
+        let memo = parse_memo(memo, None)?;
+        if self.customers.delete(account, memo.as_deref()).await? == 0 {
+            return Err(KycError::NotFound);
+        }
+        info!(account, "SEP-12 customer deleted");
+        Ok(())
+    }
+
+    /// Verified tier of the account holder
+    pub async fn tier(&self, account: &str) -> Result<KycTier, KycError> {
+        Ok(KycTier::from_i16(
+            self.customers.tier_for_account(account).await?,
+        ))
+    }
+
+    /// Refuse an onramp or offramp of `amount` (NGN, or cNGN at par) that
+    /// goes over the per-transaction or 24-hour limit of the account's tier.
+    /// This is an early check; the order itself is only written through
+    /// `TransactionRepository::fill_incomplete` with the `daily_limit` from
+    /// [`AccountLimits`], which holds even against concurrent orders.
+    pub async fn check_limit(&self, account: &str, amount: &BigDecimal) -> Result<(), AppError> {
+        let limits = self.limits(account).await?;
+        limits.check_transaction(amount)?;
+        if let Some(limit) = &limits.limits.daily {
+            let since = Utc::now() - chrono::Duration::hours(24);
+            let volume = self
+                .transactions
+                .volume_since(account, &LIMITED_TYPES, since)
+                .await?;
+            if &volume + amount > *limit {
+                return Err(limits.exceeded(amount, limit));
+            }
+        }
+        Ok(())
+    }
+
+    /// Limits of the account's verified tier
+    pub async fn limits(&self, account: &str) -> Result<AccountLimits, AppError> {
+        let tier = KycTier::from_i16(self.customers.tier_for_account(account).await?);
+        Ok(AccountLimits {
+            tier,
+            limits: self.limits[tier as usize].clone(),
+        })
+    }
+
+    async fn verify(&self, request: VerificationRequest, nonce: Vec<u8>, held: KycTier) {
+        let customer_id = request.customer_id;
+        let provider = self.provider.name();
+        let result = match self.provider.verify(&request).await {
+            Ok(VerificationOutcome::Verified { reference }) => {
+                (KycStatus::Accepted, request.tier, reference, None)
+            }
+            Ok(VerificationOutcome::Rejected { reason, reference }) => {
+                (KycStatus::Rejected, held, reference, Some(reason))
+            }
+            Err(e) => {
+                warn!(customer_id = %customer_id, provider, error = %e, "KYC verification failed");
+                let status = if held > KycTier::Unverified {
+                    KycStatus::Accepted
+                } else {
+                    KycStatus::NeedsInfo
+                };
+                (
+                    status,
+                    held,
+                    None,
+                    Some(
+                        "verification could not be completed; submit the fields again to retry"
+                            .to_string(),
+                    ),
+                )
+            }
+        };
+        let (status, tier, reference, message) = result;
+
+        match self
+            .customers
+            .record_verification(
+                customer_id,
+                &nonce,
+                &VerificationResult {
+                    status,
+                    tier: tier.as_i16(),
+                    provider,
+                    provider_reference: reference.as_deref(),
+                    status_message: message.as_deref(),
+                },
+            )
+            .await
+        {
+            Ok(Some(customer)) => info!(
+                customer_id = %customer.id,
+                status = status.as_str(),
+                tier = customer.tier,
+                provider,
+                "KYC verification recorded"
+            ),
+            Ok(None) => debug!(
+                customer_id = %customer_id,
+                "KYC fields changed during verification; result discarded"
+            ),
+            Err(e) => {
+                error!(customer_id = %customer_id, error = %e, "Failed to record KYC verification")
+            }
+        }
+    }
+
+    async fn owned(&self, account: &str, id: &str) -> Result<KycCustomer, KycError> {
+        let Ok(id) = Uuid::parse_str(id.trim()) else {
+            return Err(KycError::NotFound);
+        };
+        self.customers
+            .find(id)
+            .await?
+            .filter(|customer| customer.stellar_account == account)
+            .ok_or(KycError::NotFound)
+    }
+
+    fn fields(&self, customer: &KycCustomer) -> Result<BTreeMap<String, String>, KycError> {
+        match (&customer.fields_nonce, &customer.fields_ciphertext) {
+            (Some(nonce), Some(ciphertext)) => self.cipher.decrypt(customer.id, nonce, ciphertext),
+            _ => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn describe(&self, customer: Option<&KycCustomer>, wanted: KycTier) -> CustomerInfo {
+        let status = sep12_status(customer, wanted);
+        let provided: &[String] = customer.map_or(&[], |c| &c.provided_fields);
+        let country = customer
+            .and_then(|c| c.country_code.as_deref())
+            .and_then(Country::parse);
+
+        let mut fields = BTreeMap::new();
+        if matches!(status, "NEEDS_INFO" | "REJECTED") {
+            for names in missing(provided, country, wanted) {
+                fields.extend(describe_missing(names));
+            }
+        }
+
+        let mut provided_fields = BTreeMap::new();
+        if let Some(customer) = customer {
+            let verified = requirements(country, KycTier::from_i16(customer.tier));
+            for name in provided {
+                let Some(spec) = field_spec(name) else {
+                    continue;
+                };
+                let field_status = if verified.iter().any(|names| names.contains(&spec.name)) {
+                    Some("ACCEPTED")
+                } else {
+                    match customer.kyc_status() {
+                        KycStatus::Processing => Some("PROCESSING"),
+                        KycStatus::Rejected => Some("REJECTED"),
+                        _ => None,
+                    }
+                };
+                provided_fields.insert(
+                    spec.name.to_string(),
+                    ProvidedField {
+                        field_type: field_type(spec),
+                        description: spec.description,
+                        status: field_status,
+                        error: (field_status == Some("REJECTED"))
+                            .then(|| customer.status_message.clone())
+                            .flatten(),
+                    },
+                );
+            }
+        }
+
+        CustomerInfo {
+            id: customer.map(|c| c.id.to_string()),
+            status,
+            fields,
+            provided_fields,
+            message: customer
+                .filter(|_| status != "ACCEPTED")
+                .and_then(|c| c.status_message.clone()),
+        }
+    }
+}
+
+/// SEP-12 `account` parameters must name the authenticated account
+fn check_account(account: &str, requested: Option<&str>) -> Result<(), KycError> {
+    match requested.map(str::trim).filter(|a| !a.is_empty()) {
+        Some(requested) if requested != account => Err(KycError::Forbidden(
+            "account does not match the authenticated account".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|f| f.to_string()).collect()
+    }
+
+    fn customer(status: KycStatus, tier: i16, requested_tier: Option<i16>) -> KycCustomer {
+        KycCustomer {
+            id: Uuid::new_v4(),
+            stellar_account: "GABC".to_string(),
+            memo: None,
+            user_id: None,
+            country_code: Some("NGA".to_string()),
+            status: status.as_str().to_string(),
+            tier,
+            requested_tier,
+            provided_fields: Vec::new(),
+            fields_nonce: None,
+            fields_ciphertext: None,
+            provider: None,
+            provider_reference: None,
+            status_message: None,
+            verified_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_highest_tier_by_country() {
+        let basic = [
+            "first_name",
+            "last_name",
+            "birth_date",
+            "address_country_code",
+        ];
+        let nigeria = Some(Country::Nigeria);
+
+        assert_eq!(highest_tier(&names(&basic), nigeria), KycTier::Unverified);
+        assert_eq!(
+            highest_tier(&names(&[&basic[..], &["nin"]].concat()), nigeria),
+            KycTier::Basic
+        );
+        let full = [&basic[..], &["bvn", "address", "mobile_number"]].concat();
+        assert_eq!(highest_tier(&names(&full), nigeria), KycTier::Basic);
+        assert_eq!(
+            highest_tier(&names(&[&full[..], &["nin"]].concat()), nigeria),
+            KycTier::Full
+        );
+
+        // A BVN means nothing for a Kenyan customer
+        let kenya = Some(Country::Kenya);
+        assert_eq!(highest_tier(&names(&full), kenya), KycTier::Unverified);
+        assert_eq!(
+            highest_tier(&names(&[&basic[..], &["national_id"]].concat()), kenya),
+            KycTier::Basic
+        );
+        assert_eq!(highest_tier(&names(&full), None), KycTier::Unverified);
+    }
+
+    #[test]
+    fn test_sep12_status_for_requested_tier() {
+        assert_eq!(sep12_status(None, KycTier::Basic), "NEEDS_INFO");
+
+        let basic = customer(KycStatus::Accepted, 1, None);
+        assert_eq!(sep12_status(Some(&basic), KycTier::Basic), "ACCEPTED");
+        assert_eq!(sep12_status(Some(&basic), KycTier::Full), "NEEDS_INFO");
+
+        let upgrading = customer(KycStatus::Processing, 1, Some(2));
+        assert_eq!(sep12_status(Some(&upgrading), KycTier::Basic), "ACCEPTED");
+        assert_eq!(sep12_status(Some(&upgrading), KycTier::Full), "PROCESSING");
+
+        let rejected = customer(KycStatus::Rejected, 0, None);
+        assert_eq!(sep12_status(Some(&rejected), KycTier::Basic), "REJECTED");
+    }
+
+    #[test]
+    fn test_cipher_round_trip_is_bound_to_customer() {
+        let cipher = KycCipher::from_hex(&"11".repeat(32)).unwrap();
+        let id = Uuid::new_v4();
+        let mut fields = BTreeMap::new();
+        fields.insert("bvn".to_string(), "22123456789".to_string());
+
+        let (nonce, ciphertext) = cipher.encrypt(id, &fields).unwrap();
+        assert!(!ciphertext
+            .windows(11)
+            .any(|window| window == b"22123456789"));
+        assert_eq!(cipher.decrypt(id, &nonce, &ciphertext).unwrap(), fields);
+        assert!(matches!(
+            cipher.decrypt(Uuid::new_v4(), &nonce, &ciphertext),
+            Err(KycError::Decryption)
+        ));
+
+        assert!(KycCipher::from_hex("abcd").is_err());
+    }
+
+    #[test]
+    fn test_field_validation() {
+        let spec = |name| field_spec(name).unwrap();
+
+        assert_eq!(
+            validate_field(spec("bvn"), " 22123456789 ").unwrap(),
+            "22123456789"
+        );
+        assert!(validate_field(spec("bvn"), "2212345678").is_err());
+        assert!(validate_field(spec("nin"), "2212345678a").is_err());
+        assert!(validate_field(spec("national_id"), "12345678").is_ok());
+        assert_eq!(
+            validate_field(spec("address_country_code"), "nga").unwrap(),
+            "NGA"
+        );
+        assert!(validate_field(spec("address_country_code"), "GHA").is_err());
+        assert!(validate_field(spec("birth_date"), "1990-02-30").is_err());
+        assert!(validate_field(spec("birth_date"), "2999-01-01").is_err());
+        assert!(validate_field(spec("mobile_number"), "+2348012345678").is_ok());
+        assert!(validate_field(spec("mobile_number"), "08012345678").is_err());
+
+        assert_eq!(
+            parse_memo(Some("42"), Some("id")).unwrap(),
+            Some("42".to_string())
+        );
+        assert!(parse_memo(Some("abc"), None).is_err());
+        assert!(parse_memo(Some("42"), Some("text")).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! KYC verification providers
+//!
+//! Implements:
+//! - MockKycProvider: deterministic results for development and testnet
+
+use super::kyc::{KycError, KycVerificationProvider, VerificationOutcome, VerificationRequest};
+use crate::chains::stellar::config::StellarNetwork;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Identity number fields the mock checks
+const IDENTITY_NUMBERS: [&str; 3] = ["bvn", "nin", "national_id"];
+
+/// Provider named by `KYC_PROVIDER`. The mock accepts made-up identities, so
+/// it is refused on mainnet.
+pub fn provider_for(
+    name: &str,
+    network: &StellarNetwork,
+) -> Result<Arc<dyn KycVerificationProvider>, KycError> {
+    match (name.trim().to_lowercase().as_str(), network) {
+        ("mock", StellarNetwork::Mainnet) => Err(KycError::Config(
+            "KYC_PROVIDER=mock cannot be used on mainnet".to_string(),
+        )),
+        ("mock", _) => Ok(Arc::new(MockKycProvider::new())),
+        (other, _) => Err(KycError::Config(format!(
+            "unsupported KYC_PROVIDER '{}'",
+            other
+        ))),
+    }
+}
+
+/// Accepts every customer except those with an identity number ending in
+/// `0000`, which it rejects, so both outcomes can be exercised end to end
+pub struct MockKycProvider;
+
+impl MockKycProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for MockKycProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl KycVerificationProvider for MockKycProvider {
+    async fn verify(&self, request: &VerificationRequest) -> Result<VerificationOutcome, KycError> {
+        let reference = Some(format!("mock_{}", request.customer_id.simple()));
+        debug!(
+            customer_id = %request.customer_id,
+            tier = request.tier.as_i16(),
+            country = request.country.code(),
+            "Mock KYC verification"
+        );
+
+        let unmatched = IDENTITY_NUMBERS.iter().find(|name| {
+            request
+                .fields
+                .get(**name)
+                .is_some_and(|value| value.ends_with("0000"))
+        });
+        Ok(match unmatched {
+            Some(name) => VerificationOutcome::Rejected {
+                reason: format!("{} could not be matched to the name and birth date", name),
+                reference,
+            },
+            None => VerificationOutcome::Verified { reference },
+        })
+    }
+
+    fn name(&self) -> &str {
+        "mock"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::kyc::{Country, KycTier};
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    fn request(bvn: &str) -> VerificationRequest {
+        let mut fields = BTreeMap::new();
+        fields.insert("first_name".to_string(), "Ada".to_string());
+        fields.insert("bvn".to_string(), bvn.to_string());
+        VerificationRequest {
+            customer_id: Uuid::new_v4(),
+            country: Country::Nigeria,
+            tier: KycTier::Basic,
+            fields,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_outcomes() {
+        let provider = MockKycProvider::new();
+
+        let outcome = provider.verify(&request("22123456789")).await.unwrap();
+        assert!(matches!(
+            outcome,
+            VerificationOutcome::Verified { reference: Some(_) }
+        ));
+
+        let outcome = provider.verify(&request("22123450000")).await.unwrap();
+        assert!(
+            matches!(outcome, VerificationOutcome::Rejected { ref reason, .. } if reason.starts_with("bvn"))
+        );
+    }
+
+    #[test]
+    fn test_mock_provider_is_refused_on_mainnet() {
+        assert!(provider_for("mock", &StellarNetwork::Testnet).is_ok());
+        assert!(matches!(
+            provider_for("mock", &StellarNetwork::Mainnet),
+            Err(KycError::Config(_))
+        ));
+        assert!(provider_for("smileid", &StellarNetwork::Testnet).is_err());
+    }
+}
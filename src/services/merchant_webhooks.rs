@@ -0,0 +1,518 @@
+//! Outgoing webhooks to partner-registered endpoints
+//!
+//! State changes are published as events, fanned out to the subscribed
+//! endpoints of the API client that onboarded the wallet's owner, and queued in
+//! `webhook_deliveries`. `workers::merchant_webhooks` performs the HTTP calls.
+//!
+//! Every request carries `X-Aframp-Signature: t=<unix>,v1=<hex>`, where `v1` is
+//! HMAC-SHA256 of `"<t>.<body>"` keyed with the endpoint's secret.
+//!
+//! Endpoint hosts must resolve to public addresses only. The check runs at
+//! registration and again before every delivery, whose connection is pinned
+//! to the addresses that passed, so DNS can't be re-pointed at our network.
+
+use crate::database::error::DatabaseError;
+use crate::database::merchant_webhook_repository::{MerchantWebhookRepository, WebhookEndpoint};
+use crate::database::transaction_repository::Transaction;
+use crate::payments::utils::secure_eq;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Header carrying the delivery signature
+pub const SIGNATURE_HEADER: &str = "x-aframp-signature";
+/// Header carrying the event ID (stable across retries and replays)
+pub const EVENT_ID_HEADER: &str = "x-aframp-event-id";
+/// Header carrying the event type
+pub const EVENT_TYPE_HEADER: &str = "x-aframp-event-type";
+/// Header carrying the delivery ID
+pub const DELIVERY_ID_HEADER: &str = "x-aframp-delivery-id";
+
+/// Event types partners can subscribe to
+pub mod events {
+    pub const TRANSACTION_PAYMENT_CONFIRMED: &str = "transaction.payment_confirmed";
+    pub const TRANSACTION_PROCESSING: &str = "transaction.processing";
+    pub const TRANSACTION_COMPLETED: &str = "transaction.completed";
+    pub const TRANSACTION_FAILED: &str = "transaction.failed";
+    pub const TRANSACTION_REFUND_INITIATED: &str = "transaction.refund_initiated";
+    pub const TRANSACTION_REFUNDED: &str = "transaction.refunded";
+    pub const OFFRAMP_COMPLETED: &str = "offramp.completed";
+    pub const OFFRAMP_REFUND_INITIATED: &str = "offramp.refund_initiated";
+    pub const OFFRAMP_REFUNDED: &str = "offramp.refunded";
+    pub const OFFRAMP_FAILED: &str = "offramp.failed";
+    pub const BILL_TOKEN_ISSUED: &str = "bill.token_issued";
+    pub const USER_NOTIFICATION: &str = "user.notification";
+
+    pub const ALL: &[&str] = &[
+        TRANSACTION_PAYMENT_CONFIRMED,
+        TRANSACTION_PROCESSING,
+        TRANSACTION_COMPLETED,
+        TRANSACTION_FAILED,
+        TRANSACTION_REFUND_INITIATED,
+        TRANSACTION_REFUNDED,
+        OFFRAMP_COMPLETED,
+        OFFRAMP_REFUND_INITIATED,
+        OFFRAMP_REFUNDED,
+        OFFRAMP_FAILED,
+        BILL_TOKEN_ISSUED,
+        USER_NOTIFICATION,
+    ];
+
+    pub fn is_known(event_type: &str) -> bool {
+        ALL.contains(&event_type)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MerchantWebhookError {
+    #[error("invalid endpoint URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("endpoint address not allowed: {0}")]
+    ForbiddenAddress(String),
+
+    #[error("unknown event type: {0}")]
+    UnknownEventType(String),
+
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+/// Endpoint as returned at registration, the only time the secret is shown
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisteredEndpoint {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+/// Publishes events and manages partner endpoints
+pub struct MerchantWebhookService {
+    repository: Arc<MerchantWebhookRepository>,
+}
+
+impl MerchantWebhookService {
+    pub fn new(repository: Arc<MerchantWebhookRepository>) -> Self {
+        Self { repository }
+    }
+
+    pub fn repository(&self) -> &MerchantWebhookRepository {
+        &self.repository
+    }
+
+    /// Register an endpoint for a client, generating its signing secret
+    pub async fn register_endpoint(
+        &self,
+        client_id: Uuid,
+        url: &str,
+        event_types: &[String],
+        description: Option<&str>,
+    ) -> Result<RegisteredEndpoint, MerchantWebhookError> {
+        validate_endpoint_url(url).await?;
+        if let Some(unknown) = event_types.iter().find(|t| !events::is_known(t)) {
+            return Err(MerchantWebhookError::UnknownEventType(unknown.clone()));
+        }
+
+        let secret = generate_secret();
+        let endpoint = self
+            .repository
+            .create_endpoint(client_id, url, &secret, event_types, description)
+            .await?;
+        info!(endpoint_id = %endpoint.id, client_id = %client_id, "merchant webhook endpoint registered");
+
+        Ok(RegisteredEndpoint { endpoint, secret })
+    }
+
+    /// Queue an event about a transaction for the owning client's endpoints.
+    ///
+    /// Returns the number of deliveries queued.
+    pub async fn publish(
+        &self,
+        event_type: &str,
+        transaction: &Transaction,
+        data: Option<JsonValue>,
+    ) -> Result<usize, DatabaseError> {
+        self.fan_out(
+            event_type,
+            &transaction.wallet_address,
+            Some(transaction.transaction_id),
+            |event_id| event_envelope(event_id, event_type, transaction, data),
+        )
+        .await
+    }
+
+    /// Queue an event that isn't described by a transaction row, with `data`
+    /// as the event's `data` object.
+    pub async fn publish_for_wallet(
+        &self,
+        event_type: &str,
+        wallet_address: &str,
+        transaction_id: Option<Uuid>,
+        data: JsonValue,
+    ) -> Result<usize, DatabaseError> {
+        self.fan_out(event_type, wallet_address, transaction_id, |event_id| {
+            serde_json::json!({
+                "id": event_id,
+                "type": event_type,
+                "created_at": chrono::Utc::now().to_rfc3339(),
+                "data": data,
+            })
+        })
+        .await
+    }
+
+    async fn fan_out(
+        &self,
+        event_type: &str,
+        wallet_address: &str,
+        transaction_id: Option<Uuid>,
+        payload: impl FnOnce(Uuid) -> JsonValue,
+    ) -> Result<usize, DatabaseError> {
+        let Some(client_id) = self
+            .repository
+            .find_client_for_wallet(wallet_address)
+            .await?
+        else {
+            debug!(
+                event_type,
+                "no API client for wallet, skipping merchant webhook"
+            );
+            return Ok(0);
+        };
+
+        let endpoints = self
+            .repository
+            .subscribed_endpoints(client_id, event_type)
+            .await?;
+        if endpoints.is_empty() {
+            return Ok(0);
+        }
+
+        let event_id = Uuid::new_v4();
+        let payload = payload(event_id);
+        for endpoint in &endpoints {
+            self.repository
+                .enqueue_delivery(endpoint, event_id, event_type, &payload, transaction_id)
+                .await?;
+        }
+
+        info!(
+            event_id = %event_id,
+            event_type,
+            transaction_id = ?transaction_id,
+            endpoints = endpoints.len(),
+            "merchant webhook event queued"
+        );
+        Ok(endpoints.len())
+    }
+
+    /// [`publish`](Self::publish) for callers in the middle of a state
+    /// transition: failures are logged, never propagated.
+    pub async fn notify(
+        &self,
+        event_type: &str,
+        transaction: &Transaction,
+        data: Option<JsonValue>,
+    ) {
+        if let Err(e) = self.publish(event_type, transaction, data).await {
+            warn!(
+                event_type,
+                transaction_id = %transaction.transaction_id,
+                error = %e,
+                "failed to queue merchant webhook"
+            );
+        }
+    }
+}
+
+/// Event body sent to partners
+pub fn event_envelope(
+    event_id: Uuid,
+    event_type: &str,
+    transaction: &Transaction,
+    data: Option<JsonValue>,
+) -> JsonValue {
+    let mut body = serde_json::json!({
+        "transaction_id": transaction.transaction_id,
+        "type": transaction.r#type,
+        "status": transaction.status,
+        "wallet_address": transaction.wallet_address,
+        "from_currency": transaction.from_currency,
+        "to_currency": transaction.to_currency,
+        "from_amount": transaction.from_amount.to_string(),
+        "to_amount": transaction.to_amount.to_string(),
+        "cngn_amount": transaction.cngn_amount.to_string(),
+        "payment_provider": transaction.payment_provider,
+        "payment_reference": transaction.payment_reference,
+        "blockchain_tx_hash": transaction.blockchain_tx_hash,
+        "updated_at": transaction.updated_at.to_rfc3339(),
+    });
+    if let (Some(JsonValue::Object(extra)), Some(map)) = (data, body.as_object_mut()) {
+        map.extend(extra);
+    }
+
+    serde_json::json!({
+        "id": event_id,
+        "type": event_type,
+        "created_at": chrono::Utc::now().to_rfc3339(),
+        "data": body,
+    })
+}
+
+/// Value of the signature header for a body sent at `timestamp`
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!(
+        "t={},v1={}",
+        timestamp,
+        signature_digest(secret, timestamp, body)
+    )
+}
+
+/// Check a signature header, rejecting timestamps older than `tolerance_secs`
+pub fn verify_signature(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    tolerance_secs: i64,
+) -> bool {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", v)) => timestamp = v.parse::<i64>().ok(),
+            Some(("v1", v)) => signature = Some(v),
+            _ => {}
+        }
+    }
+
+    match (timestamp, signature) {
+        (Some(t), Some(sig)) if (now - t).abs() <= tolerance_secs => {
+            secure_eq(signature_digest(secret, t, body).as_bytes(), sig.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+fn signature_digest(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Random signing secret, `whsec_` followed by 64 hex characters
+pub fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Addresses an endpoint URL resolved to, all of them public
+#[derive(Debug, Clone)]
+pub struct ResolvedEndpoint {
+    /// Host name to pin to `addrs`; `None` when the URL holds an IP literal
+    pub domain: Option<String>,
+    pub addrs: Vec<SocketAddr>,
+}
+
+/// Endpoints must be HTTPS and every address their host resolves to must be
+/// public: no loopback, private, link-local (including the cloud metadata
+/// service at 169.254.169.254) or otherwise internal ranges
+pub async fn validate_endpoint_url(url: &str) -> Result<ResolvedEndpoint, MerchantWebhookError> {
+    let parsed =
+        reqwest::Url::parse(url).map_err(|e| MerchantWebhookError::InvalidUrl(e.to_string()))?;
+    if parsed.scheme() != "https" {
+        return Err(MerchantWebhookError::InvalidUrl(format!(
+            "scheme {} is not allowed, use https",
+            parsed.scheme()
+        )));
+    }
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| MerchantWebhookError::InvalidUrl("URL has no host".to_string()))?;
+    let literal = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok();
+    let (domain, addrs) = match literal {
+        Some(ip) => (None, vec![SocketAddr::new(ip, port)]),
+        None => {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| {
+                    MerchantWebhookError::InvalidUrl(format!("cannot resolve {}: {}", host, e))
+                })?
+                .collect();
+            (Some(host.to_string()), addrs)
+        }
+    };
+
+    if addrs.is_empty() {
+        return Err(MerchantWebhookError::InvalidUrl(
+            "host resolved to no addresses".to_string(),
+        ));
+    }
+    if let Some(blocked) = addrs.iter().find(|addr| !is_public_address(addr.ip())) {
+        return Err(MerchantWebhookError::ForbiddenAddress(
+            blocked.ip().to_string(),
+        ));
+    }
+    Ok(ResolvedEndpoint { domain, addrs })
+}
+
+/// Whether an address is reachable on the public internet
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_ipv4(v4),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_ipv4(v4),
+            None => is_public_ipv6(v6),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network", carrier-grade NAT and benchmarking ranges
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local fc00::/7 and link-local fe80::/10
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_round_trip() {
+        let body = br#"{"type":"transaction.completed"}"#;
+        let header = sign_payload("whsec_test", 1_700_000_000, body);
+        assert!(header.starts_with("t=1700000000,v1="));
+
+        assert!(verify_signature(
+            "whsec_test",
+            &header,
+            body,
+            1_700_000_100,
+            300
+        ));
+        assert!(!verify_signature(
+            "whsec_other",
+            &header,
+            body,
+            1_700_000_100,
+            300
+        ));
+        assert!(!verify_signature(
+            "whsec_test",
+            &header,
+            b"{}",
+            1_700_000_100,
+            300
+        ));
+        // Too old
+        assert!(!verify_signature(
+            "whsec_test",
+            &header,
+            body,
+            1_700_001_000,
+            300
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_url_validation() {
+        let resolved = validate_endpoint_url("https://93.184.216.34/hooks")
+            .await
+            .unwrap();
+        assert_eq!(resolved.domain, None);
+        assert_eq!(resolved.addrs, vec!["93.184.216.34:443".parse().unwrap()]);
+
+        assert!(validate_endpoint_url("http://93.184.216.34/hooks")
+            .await
+            .is_err());
+        assert!(validate_endpoint_url("not a url").await.is_err());
+        for blocked in [
+            "https://127.0.0.1/hooks",
+            "https://localhost/hooks",
+            "https://10.0.0.5/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/hooks",
+            "https://[::ffff:192.168.1.1]/hooks",
+        ] {
+            assert!(
+                validate_endpoint_url(blocked).await.is_err(),
+                "{} should be rejected",
+                blocked
+            );
+        }
+    }
+
+    #[test]
+    fn test_public_addresses() {
+        assert!(is_public_address("93.184.216.34".parse().unwrap()));
+        assert!(is_public_address("2606:2800:220:1::1".parse().unwrap()));
+        for ip in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_event_types() {
+        assert!(events::is_known(events::OFFRAMP_REFUNDED));
+        assert!(events::is_known("bill.token_issued"));
+        assert!(!events::is_known("transaction.exploded"));
+    }
+
+    #[test]
+    fn test_generated_secrets_are_unique() {
+        let secret = generate_secret();
+        assert!(secret.starts_with("whsec_"));
+        assert_eq!(secret.len(), "whsec_".len() + 64);
+        assert_ne!(secret, generate_secret());
+    }
+}
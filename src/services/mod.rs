@@ -12,14 +12,32 @@ pub mod exchange_rate;
 #[cfg(feature = "database")]
 pub mod fee_calculation;
 #[cfg(feature = "database")]
+pub mod fee_sponsorship;
+#[cfg(feature = "database")]
 pub mod fee_structure;
 #[cfg(feature = "database")]
+pub mod idempotency;
+#[cfg(feature = "database")]
+pub mod kyc;
+#[cfg(feature = "database")]
+pub mod kyc_providers;
+#[cfg(feature = "database")]
+pub mod merchant_webhooks;
+#[cfg(feature = "database")]
 pub mod onramp_quote;
 #[cfg(feature = "database")]
 pub mod payment_orchestrator;
 #[cfg(feature = "database")]
 pub mod rate_providers;
 #[cfg(feature = "database")]
+pub mod sep24;
+#[cfg(feature = "database")]
+pub mod sep38;
+#[cfg(feature = "database")]
+pub mod stellar_toml;
+#[cfg(feature = "database")]
+pub mod treasury;
+#[cfg(feature = "database")]
 pub mod trustline_operation;
 pub mod webhook_processor;
 pub mod notification;
@@ -0,0 +1,118 @@
+//! Delivery channels for queued notifications
+
+use super::email::{EmailChannel, SmtpConfig};
+use super::sms::{SmsChannel, SmsConfig};
+use crate::database::notification_repository::OutboxMessage;
+use crate::services::merchant_webhooks::{events, MerchantWebhookService};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+pub const EMAIL: &str = "email";
+pub const SMS: &str = "sms";
+pub const WEBHOOK: &str = "webhook";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChannelError {
+    /// The message can never be delivered (bad address, rejected content)
+    #[error("rejected: {0}")]
+    Rejected(String),
+
+    /// Temporary failure; the send should be retried
+    #[error("unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl ChannelError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ChannelError::Unavailable(_))
+    }
+}
+
+/// A way of reaching a user
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Value stored in `notification_outbox.channel`
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, message: &OutboxMessage) -> Result<(), ChannelError>;
+}
+
+/// Configured channels keyed by name
+pub type NotificationChannels = HashMap<&'static str, Arc<dyn NotificationChannel>>;
+
+/// Forwards notifications to the partner that onboarded the user, through the
+/// signed merchant webhook pipeline (`user.notification` events)
+pub struct WebhookChannel {
+    merchant_webhooks: Arc<MerchantWebhookService>,
+}
+
+impl WebhookChannel {
+    pub fn new(merchant_webhooks: Arc<MerchantWebhookService>) -> Self {
+        Self { merchant_webhooks }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &'static str {
+        WEBHOOK
+    }
+
+    async fn send(&self, message: &OutboxMessage) -> Result<(), ChannelError> {
+        let data = serde_json::json!({
+            "notification_id": message.id,
+            "notification_type": message.notification_type,
+            "subject": message.subject,
+            "message": message.body,
+            "details": message.payload,
+        });
+        self.merchant_webhooks
+            .publish_for_wallet(
+                events::USER_NOTIFICATION,
+                &message.recipient,
+                message.transaction_id,
+                data,
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| ChannelError::Unavailable(e.to_string()))
+    }
+}
+
+/// Channels with credentials in the environment.
+///
+/// Email needs `SMTP_HOST` and `SMTP_FROM`, SMS needs `SMS_PROVIDER` and
+/// `SMS_API_KEY`; partner webhooks are available whenever merchant webhooks are.
+pub fn channels_from_env(
+    merchant_webhooks: Option<Arc<MerchantWebhookService>>,
+) -> NotificationChannels {
+    let mut channels: NotificationChannels = HashMap::new();
+
+    match SmtpConfig::from_env() {
+        Some(Ok(config)) => {
+            info!(host = %config.host, port = config.port, "email notifications enabled");
+            channels.insert(EMAIL, Arc::new(EmailChannel::new(config)));
+        }
+        Some(Err(e)) => {
+            warn!(error = %e, "invalid SMTP configuration, email notifications disabled")
+        }
+        None => {}
+    }
+
+    match SmsConfig::from_env() {
+        Some(Ok(config)) => {
+            info!(gateway = ?config.gateway, "SMS notifications enabled");
+            channels.insert(SMS, Arc::new(SmsChannel::new(config)));
+        }
+        Some(Err(e)) => warn!(error = %e, "invalid SMS configuration, SMS notifications disabled"),
+        None => {}
+    }
+
+    if let Some(service) = merchant_webhooks {
+        channels.insert(WEBHOOK, Arc::new(WebhookChannel::new(service)));
+    }
+
+    channels
+}
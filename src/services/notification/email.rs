@@ -0,0 +1,595 @@
+//! Email notifications over SMTP
+//!
+//! Delivery goes through `lettre` with rustls: STARTTLS or implicit TLS, AUTH
+//! PLAIN and one plain-text message per connection. Credentials are only ever
+//! sent over TLS.
+
+use super::channels::{ChannelError, NotificationChannel, EMAIL};
+use crate::database::notification_repository::OutboxMessage;
+use async_trait::async_trait;
+use lettre::message::header::{ContentTransferEncoding, ContentType};
+use lettre::message::{Body, Mailbox};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::extension::ClientId;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+const DEFAULT_SUBJECT: &str = "Aframp notification";
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain TCP, only for local relays that need no credentials
+    None,
+    /// Upgrade with STARTTLS after the greeting (usually port 587)
+    StartTls,
+    /// TLS from the first byte (usually port 465)
+    Tls,
+}
+
+impl SmtpSecurity {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "none" | "plain" => Some(Self::None),
+            "starttls" => Some(Self::StartTls),
+            "tls" | "ssl" | "smtps" => Some(Self::Tls),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from_address: String,
+    pub from_name: Option<String>,
+    /// Name announced in EHLO
+    pub helo_name: String,
+    /// Limit for a whole send, from connect to the final reply
+    pub timeout: Duration,
+}
+
+impl SmtpConfig {
+    pub fn new(host: impl Into<String>, port: u16, from_address: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            security: SmtpSecurity::StartTls,
+            username: None,
+            password: None,
+            from_address: from_address.into(),
+            from_name: Some("Aframp".to_string()),
+            helo_name: "localhost".to_string(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// `None` when `SMTP_HOST` is not set
+    pub fn from_env() -> Option<Result<Self, String>> {
+        let host = std::env::var("SMTP_HOST").ok().filter(|h| !h.is_empty())?;
+        Some(Self::from_env_with_host(host))
+    }
+
+    fn from_env_with_host(host: String) -> Result<Self, String> {
+        let from_address = std::env::var("SMTP_FROM")
+            .map_err(|_| "SMTP_FROM is required when SMTP_HOST is set".to_string())?;
+        let port = match std::env::var("SMTP_PORT") {
+            Ok(port) => port
+                .parse::<u16>()
+                .map_err(|_| format!("invalid SMTP_PORT: {}", port))?,
+            Err(_) => 587,
+        };
+
+        let mut config = Self::new(host, port, from_address);
+        if let Ok(security) = std::env::var("SMTP_SECURITY") {
+            config.security = SmtpSecurity::parse(&security)
+                .ok_or_else(|| format!("invalid SMTP_SECURITY: {}", security))?;
+        } else if port == 465 {
+            config.security = SmtpSecurity::Tls;
+        }
+        config.username = std::env::var("SMTP_USERNAME")
+            .ok()
+            .filter(|v| !v.is_empty());
+        config.password = std::env::var("SMTP_PASSWORD").ok();
+        if let Ok(name) = std::env::var("SMTP_FROM_NAME") {
+            config.from_name = Some(name).filter(|n| !n.is_empty());
+        }
+        if let Ok(helo) = std::env::var("SMTP_HELO_NAME") {
+            config.helo_name = helo;
+        }
+        if let Some(secs) = std::env::var("SMTP_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            config.timeout = Duration::from_secs(secs);
+        }
+        if config.username.is_some() && config.security == SmtpSecurity::None {
+            return Err(
+                "SMTP_USERNAME needs SMTP_SECURITY=starttls or tls; credentials are never sent in cleartext"
+                    .to_string(),
+            );
+        }
+        Ok(config)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Client
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum SmtpError {
+    #[error("SMTP error: {0}")]
+    Transport(#[from] lettre::transport::smtp::Error),
+
+    #[error("invalid message: {0}")]
+    Message(String),
+
+    #[error("invalid recipient: {0}")]
+    InvalidRecipient(String),
+
+    #[error("credentials are only sent over STARTTLS or TLS")]
+    InsecureAuth,
+
+    #[error("timed out")]
+    Timeout,
+}
+
+impl From<SmtpError> for ChannelError {
+    fn from(e: SmtpError) -> Self {
+        match &e {
+            SmtpError::InvalidRecipient(_) => ChannelError::Rejected(e.to_string()),
+            // Authentication failures are configuration problems, not the message's fault
+            SmtpError::Transport(err)
+                if err.is_permanent()
+                    && !matches!(err.status().map(u16::from), Some(530 | 534 | 535)) =>
+            {
+                ChannelError::Rejected(e.to_string())
+            }
+            _ => ChannelError::Unavailable(e.to_string()),
+        }
+    }
+}
+
+/// A plain-text email
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Send one email through the configured relay
+pub async fn send_mail(config: &SmtpConfig, mail: &Mail) -> Result<(), SmtpError> {
+    let message = build_message(config, mail)?;
+    let transport = transport(config)?;
+    tokio::time::timeout(config.timeout, transport.send(message))
+        .await
+        .map_err(|_| SmtpError::Timeout)??;
+    Ok(())
+}
+
+fn transport(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, SmtpError> {
+    let builder = match config.security {
+        SmtpSecurity::None => {
+            if config.username.is_some() {
+                return Err(SmtpError::InsecureAuth);
+            }
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        }
+        SmtpSecurity::StartTls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+        }
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+    };
+    let mut builder = builder
+        .port(config.port)
+        .hello_name(ClientId::Domain(config.helo_name.clone()))
+        .timeout(Some(config.timeout));
+    if let Some(username) = &config.username {
+        builder = builder
+            .credentials(Credentials::new(
+                username.clone(),
+                config.password.clone().unwrap_or_default(),
+            ))
+            .authentication(vec![Mechanism::Plain]);
+    }
+    Ok(builder.build())
+}
+
+/// Plain-text message with a base64 body
+fn build_message(config: &SmtpConfig, mail: &Mail) -> Result<Message, SmtpError> {
+    let to = mail
+        .to
+        .parse::<Address>()
+        .map_err(|_| SmtpError::InvalidRecipient(mail.to.clone()))?;
+    let from = config
+        .from_address
+        .parse::<Address>()
+        .map_err(|e| SmtpError::Message(format!("invalid SMTP_FROM: {}", e)))?;
+    let body = Body::new_with_encoding(mail.body.clone(), ContentTransferEncoding::Base64)
+        .map_err(|_| SmtpError::Message("body cannot be encoded".to_string()))?;
+    let message_id = format!("<{}@{}>", uuid::Uuid::new_v4().simple(), from.domain());
+
+    Message::builder()
+        .message_id(Some(message_id))
+        .from(Mailbox::new(config.from_name.clone(), from))
+        .to(Mailbox::new(None, to))
+        .subject(mail.subject.as_str())
+        .header(ContentType::TEXT_PLAIN)
+        .body(body)
+        .map_err(|e| SmtpError::Message(e.to_string()))
+}
+
+// ---------------------------------------------------------------------------
+// Channel
+// ---------------------------------------------------------------------------
+
+pub struct EmailChannel {
+    config: SmtpConfig,
+}
+
+impl EmailChannel {
+    pub fn new(config: SmtpConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &'static str {
+        EMAIL
+    }
+
+    async fn send(&self, message: &OutboxMessage) -> Result<(), ChannelError> {
+        let mail = Mail {
+            to: message.recipient.clone(),
+            subject: message
+                .subject
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SUBJECT.to_string()),
+            body: message.body.clone(),
+        };
+        send_mail(&self.config, &mail).await.map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufStream};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Message accepted by [`MockSmtpServer`]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct ReceivedMail {
+        from: String,
+        recipients: Vec<String>,
+        /// Raw DATA section: headers and encoded body
+        data: String,
+    }
+
+    impl ReceivedMail {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.data
+                .split("\r\n\r\n")
+                .next()?
+                .lines()
+                .find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    key.eq_ignore_ascii_case(name).then(|| value.trim())
+                })
+        }
+
+        /// Decoded text body
+        fn body_text(&self) -> Option<String> {
+            let (_, body) = self.data.split_once("\r\n\r\n")?;
+            let encoded: String = body.split_whitespace().collect();
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .ok()?;
+            String::from_utf8(bytes).ok()
+        }
+    }
+
+    /// In-process SMTP server that accepts every message (except for configured
+    /// recipients) and records it. Listens on 127.0.0.1 without TLS.
+    struct MockSmtpServer {
+        addr: SocketAddr,
+        received: Arc<Mutex<Vec<ReceivedMail>>>,
+        task: tokio::task::JoinHandle<()>,
+    }
+
+    impl MockSmtpServer {
+        async fn start() -> std::io::Result<Self> {
+            Self::start_rejecting(&[]).await
+        }
+
+        /// Start a server that answers `550` for the given recipients
+        async fn start_rejecting(rejected: &[&str]) -> std::io::Result<Self> {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let rejected: Arc<Vec<String>> =
+                Arc::new(rejected.iter().map(|r| r.to_string()).collect());
+
+            let sink = received.clone();
+            let task = tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    let sink = sink.clone();
+                    let rejected = rejected.clone();
+                    tokio::spawn(async move {
+                        let _ = mock_session(socket, sink, rejected).await;
+                    });
+                }
+            });
+
+            Ok(Self {
+                addr,
+                received,
+                task,
+            })
+        }
+
+        fn port(&self) -> u16 {
+            self.addr.port()
+        }
+
+        /// Client configuration pointing at this server
+        fn config(&self, from_address: &str) -> SmtpConfig {
+            let mut config = SmtpConfig::new("127.0.0.1", self.port(), from_address);
+            config.security = SmtpSecurity::None;
+            config.timeout = Duration::from_secs(5);
+            config
+        }
+
+        fn received(&self) -> Vec<ReceivedMail> {
+            self.received.lock().map(|m| m.clone()).unwrap_or_default()
+        }
+    }
+
+    impl Drop for MockSmtpServer {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+
+    async fn mock_session(
+        socket: TcpStream,
+        sink: Arc<Mutex<Vec<ReceivedMail>>>,
+        rejected: Arc<Vec<String>>,
+    ) -> std::io::Result<()> {
+        let mut stream = BufStream::new(socket);
+        let mut from = String::new();
+        let mut recipients = Vec::new();
+
+        stream.write_all(b"220 mock ESMTP ready\r\n").await?;
+        stream.flush().await?;
+
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let command = line.trim_end().to_string();
+            let verb = command
+                .split([' ', ':'])
+                .next()
+                .unwrap_or_default()
+                .to_uppercase();
+
+            let reply: &[u8] = match verb.as_str() {
+                "EHLO" | "HELO" => b"250-mock\r\n250-AUTH PLAIN\r\n250 8BITMIME\r\n",
+                "AUTH" => b"235 2.7.0 authenticated\r\n",
+                "MAIL" => {
+                    from = angle_address(&command);
+                    recipients.clear();
+                    b"250 2.1.0 ok\r\n"
+                }
+                "RCPT" => {
+                    let to = angle_address(&command);
+                    if rejected.contains(&to) {
+                        b"550 5.1.1 no such user\r\n"
+                    } else {
+                        recipients.push(to);
+                        b"250 2.1.5 ok\r\n"
+                    }
+                }
+                "DATA" => {
+                    stream
+                        .write_all(b"354 end data with <CR><LF>.<CR><LF>\r\n")
+                        .await?;
+                    stream.flush().await?;
+                    let mut data = String::new();
+                    loop {
+                        let mut line = String::new();
+                        if stream.read_line(&mut line).await? == 0 {
+                            return Ok(());
+                        }
+                        if line == ".\r\n" || line == ".\n" {
+                            break;
+                        }
+                        data.push_str(line.strip_prefix('.').unwrap_or(&line));
+                    }
+                    if let Ok(mut sink) = sink.lock() {
+                        sink.push(ReceivedMail {
+                            from: from.clone(),
+                            recipients: std::mem::take(&mut recipients),
+                            data,
+                        });
+                    }
+                    b"250 2.0.0 queued\r\n"
+                }
+                "RSET" | "NOOP" => b"250 2.0.0 ok\r\n",
+                "QUIT" => {
+                    stream.write_all(b"221 2.0.0 bye\r\n").await?;
+                    stream.flush().await?;
+                    return Ok(());
+                }
+                _ => b"502 5.5.2 command not recognized\r\n",
+            };
+            stream.write_all(reply).await?;
+            stream.flush().await?;
+        }
+    }
+
+    fn angle_address(command: &str) -> String {
+        command
+            .split_once('<')
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .map(|(address, _)| address.to_string())
+            .unwrap_or_default()
+    }
+
+    fn outbox_message(recipient: &str) -> OutboxMessage {
+        let now = chrono::Utc::now();
+        OutboxMessage {
+            id: uuid::Uuid::new_v4(),
+            user_id: None,
+            transaction_id: None,
+            notification_type: "bill_payment_completed".to_string(),
+            channel: EMAIL.to_string(),
+            recipient: recipient.to_string(),
+            subject: Some("Your bill payment is complete".to_string()),
+            body: "Token: 1234-5678-9012\nThank you.".to_string(),
+            payload: None,
+            status: "pending".to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            sent_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_email_channel_delivers_to_mock_server() {
+        let server = MockSmtpServer::start().await.unwrap();
+        let channel = EmailChannel::new(server.config("noreply@aframp.com"));
+
+        channel
+            .send(&outbox_message("ada@example.com"))
+            .await
+            .unwrap();
+
+        let received = server.received();
+        assert_eq!(received.len(), 1);
+        let mail = &received[0];
+        assert_eq!(mail.from, "noreply@aframp.com");
+        assert_eq!(mail.recipients, vec!["ada@example.com".to_string()]);
+        assert_eq!(
+            mail.header("Subject"),
+            Some("Your bill payment is complete")
+        );
+        assert_eq!(mail.header("From"), Some("Aframp <noreply@aframp.com>"));
+        assert_eq!(
+            mail.body_text().as_deref(),
+            Some("Token: 1234-5678-9012\r\nThank you.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_not_sent_without_tls() {
+        let server = MockSmtpServer::start().await.unwrap();
+        let mut config = server.config("noreply@aframp.com");
+        config.username = Some("user".to_string());
+        config.password = Some("secret".to_string());
+        let mail = Mail {
+            to: "ada@example.com".to_string(),
+            subject: "hi".to_string(),
+            body: "hi".to_string(),
+        };
+
+        assert!(matches!(
+            send_mail(&config, &mail).await,
+            Err(SmtpError::InsecureAuth)
+        ));
+        assert!(server.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_recipient_is_not_retryable() {
+        let server = MockSmtpServer::start_rejecting(&["gone@example.com"])
+            .await
+            .unwrap();
+        let channel = EmailChannel::new(server.config("noreply@aframp.com"));
+
+        let err = channel
+            .send(&outbox_message("gone@example.com"))
+            .await
+            .unwrap_err();
+        assert!(!err.is_retryable(), "{}", err);
+        assert!(server.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_is_retryable() {
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let mut config = SmtpConfig::new("127.0.0.1", port, "noreply@aframp.com");
+        config.security = SmtpSecurity::None;
+        let channel = EmailChannel::new(config);
+
+        let err = channel
+            .send(&outbox_message("ada@example.com"))
+            .await
+            .unwrap_err();
+        assert!(err.is_retryable(), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_address_rejected_without_connecting() {
+        let config = SmtpConfig::new("127.0.0.1", 1, "noreply@aframp.com");
+        let mail = Mail {
+            to: "ada@example.com>\r\nBcc: <x@example.com".to_string(),
+            subject: "hi".to_string(),
+            body: "hi".to_string(),
+        };
+        assert!(matches!(
+            send_mail(&config, &mail).await,
+            Err(SmtpError::InvalidRecipient(_))
+        ));
+    }
+
+    #[test]
+    fn test_build_message_encodes_subject_and_blocks_header_injection() {
+        let config = SmtpConfig::new("smtp.example.com", 587, "noreply@aframp.com");
+        let mail = Mail {
+            to: "ada@example.com".to_string(),
+            subject: "Paid ₦5,000\r\nBcc: victim@example.com".to_string(),
+            body: "hello".to_string(),
+        };
+        let message =
+            String::from_utf8(build_message(&config, &mail).unwrap().formatted()).unwrap();
+        let headers = message.split("\r\n\r\n").next().unwrap();
+
+        assert!(!headers.contains("\r\nBcc:"));
+        assert!(headers.contains("Subject: Paid =?utf-8?b?"), "{}", headers);
+        assert!(headers.contains("Message-ID: <"));
+        assert!(headers.contains("@aframp.com>"));
+    }
+
+    #[test]
+    fn test_security_parse() {
+        assert_eq!(
+            SmtpSecurity::parse("STARTTLS"),
+            Some(SmtpSecurity::StartTls)
+        );
+        assert_eq!(SmtpSecurity::parse("ssl"), Some(SmtpSecurity::Tls));
+        assert_eq!(SmtpSecurity::parse("none"), Some(SmtpSecurity::None));
+        assert_eq!(SmtpSecurity::parse("maybe"), None);
+    }
+}
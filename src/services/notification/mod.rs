@@ -0,0 +1,274 @@
+//! User notifications
+//!
+//! Workers call [`NotificationService::send_notification`]; with an outbox
+//! configured, the notification is rendered from its template and queued once
+//! per channel the user allows. `workers::notification_dispatcher` delivers
+//! the queued messages through the [`channels::NotificationChannel`]s.
+
+pub mod channels;
+pub mod email;
+pub mod sms;
+pub mod templates;
+
+use crate::database::notification_repository::{NewOutboxMessage, NotificationRepository};
+use crate::database::transaction_repository::Transaction;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NotificationType {
+    OfframpCompleted,
+    OfframpFailed,
+    OfframpRefunded,
+    CngnReceived,
+    OnrampFailed,
+    BillPaymentCompleted,
+    BillPaymentRefunded,
+}
+
+impl NotificationType {
+    pub const ALL: &'static [NotificationType] = &[
+        NotificationType::OfframpCompleted,
+        NotificationType::OfframpFailed,
+        NotificationType::OfframpRefunded,
+        NotificationType::CngnReceived,
+        NotificationType::OnrampFailed,
+        NotificationType::BillPaymentCompleted,
+        NotificationType::BillPaymentRefunded,
+    ];
+
+    /// Name stored in the outbox and used in user preferences
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationType::OfframpCompleted => "offramp_completed",
+            NotificationType::OfframpFailed => "offramp_failed",
+            NotificationType::OfframpRefunded => "offramp_refunded",
+            NotificationType::CngnReceived => "cngn_received",
+            NotificationType::OnrampFailed => "onramp_failed",
+            NotificationType::BillPaymentCompleted => "bill_payment_completed",
+            NotificationType::BillPaymentRefunded => "bill_payment_refunded",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.iter().find(|t| t.as_str() == s).cloned()
+    }
+}
+
+struct Outbox {
+    repository: Arc<NotificationRepository>,
+    channels: Vec<&'static str>,
+}
+
+pub struct NotificationService {
+    outbox: Option<Outbox>,
+}
+
+impl Default for NotificationService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationService {
+    /// Log-only service
+    pub fn new() -> Self {
+        Self { outbox: None }
+    }
+
+    /// Also queue every notification for delivery over `channels`
+    pub fn with_outbox(
+        mut self,
+        repository: Arc<NotificationRepository>,
+        channels: Vec<&'static str>,
+    ) -> Self {
+        self.outbox = Some(Outbox {
+            repository,
+            channels,
+        });
+        self
+    }
+
+    pub async fn send_notification(
+        &self,
+        tx: &Transaction,
+        notification_type: NotificationType,
+        message: &str,
+    ) {
+        match notification_type {
+            NotificationType::OfframpCompleted => {
+                info!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    amount = %tx.to_amount,
+                    currency = %tx.to_currency,
+                    "🔔 NOTIFICATION: Offramp Completed - {}", message
+                );
+            }
+            NotificationType::OfframpFailed => {
+                error!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    "🔔 NOTIFICATION: Offramp Failed - {}", message
+                );
+            }
+            NotificationType::OfframpRefunded => {
+                info!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    "🔔 NOTIFICATION: Offramp Refunded - {}", message
+                );
+            }
+            NotificationType::CngnReceived => {
+                info!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    amount = %tx.cngn_amount,
+                    "🔔 NOTIFICATION: cNGN Received - {}", message
+                );
+            }
+            NotificationType::OnrampFailed => {
+                error!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    "🔔 NOTIFICATION: Onramp Failed - {}", message
+                );
+            }
+            NotificationType::BillPaymentCompleted => {
+                info!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    amount = %tx.to_amount,
+                    "🔔 NOTIFICATION: Bill Payment Completed - {}", message
+                );
+            }
+            NotificationType::BillPaymentRefunded => {
+                info!(
+                    transaction_id = %tx.transaction_id,
+                    wallet = %tx.wallet_address,
+                    "🔔 NOTIFICATION: Bill Payment Refunded - {}", message
+                );
+            }
+        }
+
+        if let Some(outbox) = &self.outbox {
+            outbox.enqueue(tx, &notification_type, message).await;
+        }
+    }
+}
+
+impl Outbox {
+    /// Queue the notification on every allowed channel. Failures are logged
+    /// and never reach the calling worker.
+    async fn enqueue(&self, tx: &Transaction, notification_type: &NotificationType, message: &str) {
+        let recipient = match self
+            .repository
+            .find_recipient_for_wallet(&tx.wallet_address)
+            .await
+        {
+            Ok(recipient) => recipient,
+            Err(e) => {
+                warn!(
+                    transaction_id = %tx.transaction_id,
+                    error = %e,
+                    "Failed to look up notification recipient"
+                );
+                return;
+            }
+        };
+
+        let type_name = notification_type.as_str();
+        let rendered = templates::render(notification_type, tx, message);
+        let payload = serde_json::json!({
+            "transaction_id": tx.transaction_id,
+            "type": tx.r#type,
+            "status": tx.status,
+            "amount": tx.to_amount.to_string(),
+            "currency": tx.to_currency,
+            "cngn_amount": tx.cngn_amount.to_string(),
+            "wallet_address": tx.wallet_address,
+        });
+
+        for channel in &self.channels {
+            let (address, subject, body) = match (*channel, &recipient) {
+                (channels::EMAIL, Some(r)) => {
+                    (r.email.clone(), Some(&rendered.subject), &rendered.body)
+                }
+                (channels::SMS, Some(r)) => match &r.phone {
+                    Some(phone) => (phone.clone(), None, &rendered.sms),
+                    None => continue,
+                },
+                // Partners know their users by wallet, registered or not
+                (channels::WEBHOOK, _) => (
+                    tx.wallet_address.clone(),
+                    Some(&rendered.subject),
+                    &rendered.body,
+                ),
+                _ => continue,
+            };
+            if let Some(r) = &recipient {
+                if !r.preferences.allows(channel, type_name) {
+                    debug!(
+                        transaction_id = %tx.transaction_id,
+                        channel,
+                        notification_type = type_name,
+                        "Notification muted by user preferences"
+                    );
+                    continue;
+                }
+            }
+
+            let queued = self
+                .repository
+                .enqueue(&NewOutboxMessage {
+                    user_id: recipient.as_ref().map(|r| r.preferences.user_id),
+                    transaction_id: Some(tx.transaction_id),
+                    notification_type: type_name.to_string(),
+                    channel: channel.to_string(),
+                    recipient: address,
+                    subject: subject.cloned(),
+                    body: body.clone(),
+                    payload: Some(payload.clone()),
+                })
+                .await;
+            match queued {
+                Ok(Some(row)) => debug!(
+                    notification_id = %row.id,
+                    transaction_id = %tx.transaction_id,
+                    channel,
+                    "Notification queued"
+                ),
+                Ok(None) => debug!(
+                    transaction_id = %tx.transaction_id,
+                    channel,
+                    notification_type = type_name,
+                    "Notification already queued"
+                ),
+                Err(e) => warn!(
+                    transaction_id = %tx.transaction_id,
+                    channel,
+                    error = %e,
+                    "Failed to queue notification"
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_type_names_round_trip() {
+        for notification_type in NotificationType::ALL {
+            let name = notification_type.as_str();
+            assert_eq!(
+                NotificationType::parse(name).map(|t| t.as_str()),
+                Some(name)
+            );
+        }
+        assert!(NotificationType::parse("OfframpCompleted").is_none());
+    }
+}
@@ -0,0 +1,417 @@
+//! SMS notifications through an HTTP gateway (Termii or Africa's Talking)
+
+use super::channels::{ChannelError, NotificationChannel, SMS};
+use crate::database::notification_repository::OutboxMessage;
+use async_trait::async_trait;
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+use tracing::debug;
+
+/// Longest text sent; gateways split anything above 160 characters into
+/// several billed messages
+const MAX_SMS_CHARS: usize = 459;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmsGateway {
+    Termii,
+    AfricasTalking,
+}
+
+impl SmsGateway {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace(['-', '_', '\'', ' '], "").as_str() {
+            "termii" => Some(Self::Termii),
+            "africastalking" | "at" => Some(Self::AfricasTalking),
+            _ => None,
+        }
+    }
+
+    fn default_base_url(&self) -> &'static str {
+        match self {
+            Self::Termii => "https://api.ng.termii.com",
+            Self::AfricasTalking => "https://api.africastalking.com",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SmsConfig {
+    pub gateway: SmsGateway,
+    pub base_url: String,
+    pub api_key: String,
+    /// Registered sender ID or short code
+    pub sender_id: Option<String>,
+    /// Africa's Talking application username
+    pub username: Option<String>,
+    pub timeout: Duration,
+}
+
+impl SmsConfig {
+    pub fn new(gateway: SmsGateway, api_key: impl Into<String>) -> Self {
+        Self {
+            gateway,
+            base_url: gateway.default_base_url().to_string(),
+            api_key: api_key.into(),
+            sender_id: None,
+            username: None,
+            timeout: Duration::from_secs(15),
+        }
+    }
+
+    /// `None` when `SMS_PROVIDER` is not set
+    pub fn from_env() -> Option<Result<Self, String>> {
+        let provider = std::env::var("SMS_PROVIDER")
+            .ok()
+            .filter(|p| !p.is_empty())?;
+        Some(Self::from_env_with_provider(&provider))
+    }
+
+    fn from_env_with_provider(provider: &str) -> Result<Self, String> {
+        let gateway = SmsGateway::parse(provider)
+            .ok_or_else(|| format!("unsupported SMS_PROVIDER: {}", provider))?;
+        let api_key = std::env::var("SMS_API_KEY")
+            .map_err(|_| "SMS_API_KEY is required when SMS_PROVIDER is set".to_string())?;
+
+        let mut config = Self::new(gateway, api_key);
+        if let Ok(base_url) = std::env::var("SMS_BASE_URL") {
+            config.base_url = base_url.trim_end_matches('/').to_string();
+        }
+        config.sender_id = std::env::var("SMS_SENDER_ID")
+            .ok()
+            .filter(|v| !v.is_empty());
+        config.username = std::env::var("SMS_USERNAME").ok().filter(|v| !v.is_empty());
+        if gateway == SmsGateway::AfricasTalking && config.username.is_none() {
+            return Err("SMS_USERNAME is required for Africa's Talking".to_string());
+        }
+        Ok(config)
+    }
+}
+
+pub struct SmsChannel {
+    config: SmsConfig,
+    http: reqwest::Client,
+}
+
+impl SmsChannel {
+    pub fn new(config: SmsConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Self { config, http }
+    }
+
+    async fn send_termii(&self, to: &str, text: &str) -> Result<(), ChannelError> {
+        let body = serde_json::json!({
+            "api_key": self.config.api_key,
+            "to": to,
+            "from": self.config.sender_id.as_deref().unwrap_or("Aframp"),
+            "sms": text,
+            "type": "plain",
+            "channel": "generic",
+        });
+        let response = self
+            .http
+            .post(format!("{}/api/sms/send", self.config.base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ChannelError::Unavailable(e.to_string()))?;
+        let (status, body) = read_response(response).await?;
+
+        if status.is_success() {
+            debug!(message_id = %body["message_id"], "termii accepted sms");
+            Ok(())
+        } else {
+            Err(status_error(status, &body))
+        }
+    }
+
+    async fn send_africas_talking(&self, to: &str, text: &str) -> Result<(), ChannelError> {
+        let to = format!("+{}", to);
+        let mut form = vec![
+            (
+                "username",
+                self.config.username.as_deref().unwrap_or_default(),
+            ),
+            ("to", to.as_str()),
+            ("message", text),
+        ];
+        if let Some(sender_id) = &self.config.sender_id {
+            form.push(("from", sender_id.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(format!("{}/version1/messaging", self.config.base_url))
+            .header("apiKey", &self.config.api_key)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(form_encode(&form))
+            .send()
+            .await
+            .map_err(|e| ChannelError::Unavailable(e.to_string()))?;
+        let (status, body) = read_response(response).await?;
+        if !status.is_success() {
+            return Err(status_error(status, &body));
+        }
+
+        // A 201 still carries per-recipient results
+        let recipient = &body["SMSMessageData"]["Recipients"][0];
+        match recipient["statusCode"].as_i64() {
+            Some(100..=102) => {
+                debug!(message_id = %recipient["messageId"], "africa's talking accepted sms");
+                Ok(())
+            }
+            // Invalid or blacklisted number, or the user opted out
+            Some(403 | 404 | 406) => Err(ChannelError::Rejected(
+                recipient["status"]
+                    .as_str()
+                    .unwrap_or("rejected")
+                    .to_string(),
+            )),
+            _ => Err(ChannelError::Unavailable(format!(
+                "unexpected gateway response: {}",
+                body["SMSMessageData"]["Message"]
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SmsChannel {
+    fn name(&self) -> &'static str {
+        SMS
+    }
+
+    async fn send(&self, message: &OutboxMessage) -> Result<(), ChannelError> {
+        let to = normalize_msisdn(&message.recipient).ok_or_else(|| {
+            ChannelError::Rejected(format!("invalid phone number: {}", message.recipient))
+        })?;
+        let text: String = message.body.chars().take(MAX_SMS_CHARS).collect();
+
+        match self.config.gateway {
+            SmsGateway::Termii => self.send_termii(&to, &text).await,
+            SmsGateway::AfricasTalking => self.send_africas_talking(&to, &text).await,
+        }
+    }
+}
+
+async fn read_response(
+    response: reqwest::Response,
+) -> Result<(reqwest::StatusCode, JsonValue), ChannelError> {
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .map_err(|e| ChannelError::Unavailable(e.to_string()))?;
+    let body = serde_json::from_str(&text).unwrap_or(JsonValue::String(text));
+    Ok((status, body))
+}
+
+/// Client errors mean the request itself is bad; anything else may pass later
+fn status_error(status: reqwest::StatusCode, body: &JsonValue) -> ChannelError {
+    let message = format!("gateway returned {}: {}", status, body);
+    if status.is_client_error()
+        && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        && status != reqwest::StatusCode::UNAUTHORIZED
+    {
+        ChannelError::Rejected(message)
+    } else {
+        ChannelError::Unavailable(message)
+    }
+}
+
+fn form_encode(pairs: &[(&str, &str)]) -> String {
+    let mut url = reqwest::Url::parse("http://localhost").expect("static url");
+    url.query_pairs_mut().extend_pairs(pairs);
+    url.query().unwrap_or_default().to_string()
+}
+
+/// International form without `+` (`2348012345678`). Local Nigerian numbers
+/// (`08012345678`) get the 234 prefix; anything else must already be international.
+pub fn normalize_msisdn(phone: &str) -> Option<String> {
+    let digits: String = phone
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '(' | ')'))
+        .collect();
+    let digits = digits.strip_prefix('+').unwrap_or(&digits);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let normalized = match digits.len() {
+        11 if digits.starts_with('0') => format!("234{}", &digits[1..]),
+        _ => digits.to_string(),
+    };
+    (10..=15).contains(&normalized.len()).then_some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+
+    fn outbox_message(recipient: &str) -> OutboxMessage {
+        let now = chrono::Utc::now();
+        OutboxMessage {
+            id: uuid::Uuid::new_v4(),
+            user_id: None,
+            transaction_id: None,
+            notification_type: "offramp_completed".to_string(),
+            channel: SMS.to_string(),
+            recipient: recipient.to_string(),
+            subject: None,
+            body: "Aframp: 5000 NGN has been sent to your bank account.".to_string(),
+            payload: None,
+            status: "pending".to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            sent_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_termii_send() {
+        let app = Router::new().route(
+            "/api/sms/send",
+            post(|Json(body): Json<JsonValue>| async move {
+                assert_eq!(body["api_key"], "termii-key");
+                assert_eq!(body["to"], "2348012345678");
+                assert_eq!(body["from"], "Aframp");
+                assert_eq!(body["channel"], "generic");
+                Json(serde_json::json!({
+                    "message_id": "3017544054459",
+                    "message": "Successfully Sent",
+                    "balance": 9,
+                    "user": "Aframp"
+                }))
+            }),
+        );
+        let mut config = SmsConfig::new(SmsGateway::Termii, "termii-key");
+        config.base_url = serve(app).await;
+        let channel = SmsChannel::new(config);
+
+        channel
+            .send(&outbox_message("0801 234 5678"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_africas_talking_send_and_rejection() {
+        let app = Router::new().route(
+            "/version1/messaging",
+            post(|headers: HeaderMap, body: String| async move {
+                assert_eq!(headers["apiKey"], "at-key");
+                assert!(body.contains("username=aframp"));
+                let (status_code, status) = if body.contains("to=%2B2348012345678") {
+                    (101, "Success")
+                } else {
+                    (403, "InvalidPhoneNumber")
+                };
+                (
+                    axum::http::StatusCode::CREATED,
+                    Json(serde_json::json!({
+                        "SMSMessageData": {
+                            "Message": "Sent to 1/1 Total Cost: NGN 4.0000",
+                            "Recipients": [{
+                                "statusCode": status_code,
+                                "number": "+2348012345678",
+                                "status": status,
+                                "cost": "NGN 4.0000",
+                                "messageId": "ATXid_1"
+                            }]
+                        }
+                    })),
+                )
+            }),
+        );
+        let mut config = SmsConfig::new(SmsGateway::AfricasTalking, "at-key");
+        config.base_url = serve(app).await;
+        config.username = Some("aframp".to_string());
+        let channel = SmsChannel::new(config);
+
+        channel
+            .send(&outbox_message("+234 801 234 5678"))
+            .await
+            .unwrap();
+        let err = channel
+            .send(&outbox_message("2348099999999"))
+            .await
+            .unwrap_err();
+        assert!(!err.is_retryable(), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_errors_are_classified() {
+        let app = Router::new().route(
+            "/api/sms/send",
+            post(|Json(body): Json<JsonValue>| async move {
+                let status = if body["to"] == "2348011111111" {
+                    axum::http::StatusCode::BAD_REQUEST
+                } else {
+                    axum::http::StatusCode::SERVICE_UNAVAILABLE
+                };
+                (status, Json(serde_json::json!({ "message": "nope" })))
+            }),
+        );
+        let mut config = SmsConfig::new(SmsGateway::Termii, "termii-key");
+        config.base_url = serve(app).await;
+        let channel = SmsChannel::new(config);
+
+        let rejected = channel
+            .send(&outbox_message("08011111111"))
+            .await
+            .unwrap_err();
+        assert!(!rejected.is_retryable());
+        let unavailable = channel
+            .send(&outbox_message("08022222222"))
+            .await
+            .unwrap_err();
+        assert!(unavailable.is_retryable());
+    }
+
+    #[test]
+    fn test_normalize_msisdn() {
+        assert_eq!(
+            normalize_msisdn("0801 234 5678").as_deref(),
+            Some("2348012345678")
+        );
+        assert_eq!(
+            normalize_msisdn("+234-801-234-5678").as_deref(),
+            Some("2348012345678")
+        );
+        assert_eq!(
+            normalize_msisdn("254712345678").as_deref(),
+            Some("254712345678")
+        );
+        assert_eq!(normalize_msisdn("12345"), None);
+        assert_eq!(normalize_msisdn("not a number"), None);
+    }
+
+    #[test]
+    fn test_gateway_parse() {
+        assert_eq!(SmsGateway::parse("Termii"), Some(SmsGateway::Termii));
+        assert_eq!(
+            SmsGateway::parse("africas_talking"),
+            Some(SmsGateway::AfricasTalking)
+        );
+        assert_eq!(SmsGateway::parse("twilio"), None);
+    }
+}
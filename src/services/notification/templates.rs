@@ -0,0 +1,162 @@
+//! Message templates per notification type
+//!
+//! Templates use `{placeholder}` substitution. Available placeholders:
+//! `{transaction_id}`, `{amount}`, `{currency}`, `{cngn_amount}`, `{message}`.
+
+use super::NotificationType;
+use crate::database::transaction_repository::Transaction;
+use std::collections::HashMap;
+
+/// A notification rendered for every channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedNotification {
+    pub subject: String,
+    /// Email body
+    pub body: String,
+    /// Short text for SMS
+    pub sms: String,
+}
+
+/// Templates for one notification type
+#[derive(Debug, Clone)]
+pub struct NotificationTemplate {
+    pub subject: &'static str,
+    pub body: &'static str,
+    pub sms: &'static str,
+}
+
+impl NotificationTemplate {
+    pub fn render(&self, values: &HashMap<&str, String>) -> RenderedNotification {
+        RenderedNotification {
+            subject: fill(self.subject, values),
+            body: fill(self.body, values),
+            sms: fill(self.sms, values),
+        }
+    }
+}
+
+/// Built-in template for a notification type
+pub fn template_for(notification_type: &NotificationType) -> NotificationTemplate {
+    match notification_type {
+        NotificationType::OfframpCompleted => NotificationTemplate {
+            subject: "Your withdrawal of {amount} {currency} is complete",
+            body: "Hello,\n\n{amount} {currency} has been sent to your bank account.\n\n{message}\n\nReference: {transaction_id}\n\nThank you for using Aframp.",
+            sms: "Aframp: {amount} {currency} has been sent to your bank account. Ref {transaction_id}",
+        },
+        NotificationType::OfframpFailed => NotificationTemplate {
+            subject: "Your withdrawal could not be completed",
+            body: "Hello,\n\nWe could not complete your withdrawal of {amount} {currency}.\n\n{message}\n\nYour cNGN will be returned to your wallet. Reference: {transaction_id}",
+            sms: "Aframp: your withdrawal of {amount} {currency} failed. Your cNGN will be refunded. Ref {transaction_id}",
+        },
+        NotificationType::OfframpRefunded => NotificationTemplate {
+            subject: "Your cNGN has been refunded",
+            body: "Hello,\n\n{cngn_amount} cNGN has been returned to your wallet.\n\n{message}\n\nReference: {transaction_id}",
+            sms: "Aframp: {cngn_amount} cNGN has been refunded to your wallet. Ref {transaction_id}",
+        },
+        NotificationType::CngnReceived => NotificationTemplate {
+            subject: "We received your {cngn_amount} cNGN",
+            body: "Hello,\n\nYour payment of {cngn_amount} cNGN has arrived and is being processed.\n\n{message}\n\nReference: {transaction_id}",
+            sms: "Aframp: {cngn_amount} cNGN received and being processed. Ref {transaction_id}",
+        },
+        NotificationType::OnrampFailed => NotificationTemplate {
+            subject: "Your cNGN purchase could not be completed",
+            body: "Hello,\n\nWe could not complete your purchase of {cngn_amount} cNGN.\n\n{message}\n\nReference: {transaction_id}",
+            sms: "Aframp: your cNGN purchase failed. Ref {transaction_id}",
+        },
+        NotificationType::BillPaymentCompleted => NotificationTemplate {
+            subject: "Your bill payment of {amount} {currency} is complete",
+            body: "Hello,\n\nYour bill payment of {amount} {currency} was successful.\n\n{message}\n\nReference: {transaction_id}",
+            sms: "Aframp: bill payment of {amount} {currency} successful. {message}",
+        },
+        NotificationType::BillPaymentRefunded => NotificationTemplate {
+            subject: "Your bill payment was refunded",
+            body: "Hello,\n\nWe could not pay your bill, so {cngn_amount} cNGN has been returned to your wallet.\n\n{message}\n\nReference: {transaction_id}",
+            sms: "Aframp: bill payment failed, {cngn_amount} cNGN refunded. Ref {transaction_id}",
+        },
+    }
+}
+
+/// Render the built-in template for a transaction
+pub fn render(
+    notification_type: &NotificationType,
+    tx: &Transaction,
+    message: &str,
+) -> RenderedNotification {
+    let values = HashMap::from([
+        ("transaction_id", tx.transaction_id.to_string()),
+        ("amount", tx.to_amount.to_string()),
+        ("currency", tx.to_currency.clone()),
+        ("cngn_amount", tx.cngn_amount.to_string()),
+        ("message", message.to_string()),
+    ]);
+    template_for(notification_type).render(&values)
+}
+
+fn fill(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if values.contains_key(&after[..end]) => {
+                out.push_str(&values[&after[..end]]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_replaces_known_placeholders_only() {
+        let values = HashMap::from([("amount", "5000".to_string())]);
+        assert_eq!(
+            fill("Sent {amount} {unknown}", &values),
+            "Sent 5000 {unknown}"
+        );
+        assert_eq!(fill("{amount}{amount}", &values), "50005000");
+        assert_eq!(fill("no placeholders", &values), "no placeholders");
+    }
+
+    #[test]
+    fn test_every_type_has_a_template() {
+        let values = HashMap::from([
+            ("transaction_id", "tx-1".to_string()),
+            ("amount", "5000".to_string()),
+            ("currency", "NGN".to_string()),
+            ("cngn_amount", "5025".to_string()),
+            ("message", "Token: 1234".to_string()),
+        ]);
+        for notification_type in NotificationType::ALL {
+            let rendered = template_for(notification_type).render(&values);
+            assert!(!rendered.subject.is_empty());
+            assert!(!rendered.subject.contains('{'), "{:?}", notification_type);
+            assert!(!rendered.body.contains('{'), "{:?}", notification_type);
+            assert!(!rendered.sms.contains('{'), "{:?}", notification_type);
+        }
+    }
+
+    #[test]
+    fn test_bill_payment_sms_carries_token_message() {
+        let values = HashMap::from([
+            ("amount", "5000".to_string()),
+            ("currency", "NGN".to_string()),
+            ("message", "Token: 1234-5678".to_string()),
+        ]);
+        let rendered = template_for(&NotificationType::BillPaymentCompleted).render(&values);
+        assert_eq!(
+            rendered.sms,
+            "Aframp: bill payment of 5000 NGN successful. Token: 1234-5678"
+        );
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! Handles NGN → cNGN quote creation: rate snapshot, fee calculation,
 //! liquidity check, trustline verification, and Redis storage.
+//!
+//! Also prices both directions (NGN → cNGN and cNGN → NGN) from either the
+//! amount sold or the amount bought, and stores firm quotes issued to an
+//! account in `onramp_quotes` for SEP-38. With a `KycService` attached,
+//! quotes are refused above the limits of the wallet's KYC tier.
 
 use crate::cache::cache::Cache;
 use crate::cache::keys::onramp::QuoteKey;
@@ -9,10 +14,12 @@ use crate::cache::RedisCache;
 use crate::chains::stellar::client::StellarClient;
 use crate::chains::stellar::trustline::CngnTrustlineManager;
 use crate::chains::stellar::types::{extract_cngn_balance, is_valid_stellar_address};
+use crate::database::onramp_quote_repository::{NewFirmQuote, OnrampQuote, OnrampQuoteRepository};
 use crate::error::{AppError, AppErrorKind, DomainError, ValidationError};
 use crate::services::exchange_rate::{ConversionDirection, ConversionRequest, ExchangeRateService};
 use crate::services::fee_structure::{FeeCalculationInput, FeeStructureService};
-use bigdecimal::{BigDecimal, Zero};
+use crate::services::kyc::KycService;
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
@@ -25,7 +32,10 @@ use uuid::Uuid;
 const MIN_ONRAMP_AMOUNT_NGN: i64 = 1000;
 
 /// Quote TTL in seconds (3 minutes)
-const QUOTE_TTL_SECS: u64 = 180;
+pub const QUOTE_TTL_SECS: u64 = 180;
+
+/// Rounds spent closing in on the amount to sell for a fixed amount bought
+const MAX_PRICE_ITERATIONS: usize = 8;
 
 /// Provider enum for onramp
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -91,6 +101,100 @@ fn decimal_to_i64_trunc(value: &BigDecimal) -> i64 {
         .unwrap_or(0)
 }
 
+/// Which way a quote converts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteDirection {
+    /// NGN sold for cNGN
+    Onramp,
+    /// cNGN sold for NGN
+    Offramp,
+}
+
+impl QuoteDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuoteDirection::Onramp => "onramp",
+            QuoteDirection::Offramp => "offramp",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "onramp" => Some(QuoteDirection::Onramp),
+            "offramp" => Some(QuoteDirection::Offramp),
+            _ => None,
+        }
+    }
+
+    pub fn sell_currency(&self) -> &'static str {
+        match self {
+            QuoteDirection::Onramp => "NGN",
+            QuoteDirection::Offramp => "cNGN",
+        }
+    }
+
+    pub fn buy_currency(&self) -> &'static str {
+        match self {
+            QuoteDirection::Onramp => "cNGN",
+            QuoteDirection::Offramp => "NGN",
+        }
+    }
+
+    /// Decimal places amounts of `currency` are rounded to
+    pub fn decimals(currency: &str) -> i64 {
+        if currency == "NGN" {
+            2
+        } else {
+            7
+        }
+    }
+
+    fn conversion(&self) -> ConversionDirection {
+        match self {
+            QuoteDirection::Onramp => ConversionDirection::Buy,
+            QuoteDirection::Offramp => ConversionDirection::Sell,
+        }
+    }
+}
+
+/// The side of a quote the caller fixed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteAmount {
+    Sell(BigDecimal),
+    Buy(BigDecimal),
+}
+
+/// Amounts of a priced conversion; the fee is in the currency bought
+#[derive(Debug, Clone)]
+pub struct PricedQuote {
+    pub direction: QuoteDirection,
+    pub sell_amount: BigDecimal,
+    pub buy_amount: BigDecimal,
+    pub fee: BigDecimal,
+    /// Units bought per unit sold, before fees
+    pub rate: BigDecimal,
+}
+
+impl PricedQuote {
+    /// Units sold per unit bought, before fees
+    pub fn price(&self) -> BigDecimal {
+        price(&self.sell_amount, &(&self.buy_amount + &self.fee))
+    }
+
+    /// Units sold per unit bought, fees included
+    pub fn total_price(&self) -> BigDecimal {
+        price(&self.sell_amount, &self.buy_amount)
+    }
+}
+
+fn price(sell: &BigDecimal, buy: &BigDecimal) -> BigDecimal {
+    if buy.is_zero() {
+        return BigDecimal::zero();
+    }
+    (sell / buy).with_scale_round(7, RoundingMode::HalfEven)
+}
+
 /// API request for onramp quote
 #[derive(Debug, Clone, Deserialize)]
 pub struct OnrampQuoteRequest {
@@ -158,6 +262,8 @@ pub struct OnrampQuoteService {
     redis_cache: RedisCache,
     cngn_issuer: String,
     liquidity_check_enabled: bool,
+    quote_repository: Option<OnrampQuoteRepository>,
+    kyc: Option<Arc<KycService>>,
 }
 
 impl OnrampQuoteService {
@@ -180,9 +286,23 @@ impl OnrampQuoteService {
             redis_cache,
             cngn_issuer,
             liquidity_check_enabled,
+            quote_repository: None,
+            kyc: None,
         }
     }
 
+    /// Store firm quotes in `onramp_quotes`
+    pub fn with_quote_repository(mut self, repository: OnrampQuoteRepository) -> Self {
+        self.quote_repository = Some(repository);
+        self
+    }
+
+    /// Hold quotes to the limits of the wallet's KYC tier
+    pub fn with_kyc(mut self, kyc: Arc<KycService>) -> Self {
+        self.kyc = Some(kyc);
+        self
+    }
+
     /// Create an onramp quote
     pub async fn create_quote(
         &self,
@@ -217,6 +337,9 @@ impl OnrampQuoteService {
         }
 
         let amount_bd = BigDecimal::from(request.amount_ngn);
+        if let Some(kyc) = &self.kyc {
+            kyc.check_limit(wallet_address, &amount_bd).await?;
+        }
         let requested_chain = request
             .chain
             .as_deref()
@@ -373,6 +496,206 @@ impl OnrampQuoteService {
         })
     }
 
+    /// Price a conversion in either direction from the amount sold or the
+    /// amount bought. Amounts are rounded in the anchor's favour: the amount
+    /// bought down and the amount to sell up.
+    pub async fn price(
+        &self,
+        direction: QuoteDirection,
+        amount: QuoteAmount,
+    ) -> Result<PricedQuote, AppError> {
+        let sell_decimals = QuoteDirection::decimals(direction.sell_currency());
+        match amount {
+            QuoteAmount::Sell(sell) => {
+                let sell = sell.with_scale_round(sell_decimals, RoundingMode::Down);
+                self.price_sell(direction, &sell).await
+            }
+            QuoteAmount::Buy(buy) => {
+                let buy_decimals = QuoteDirection::decimals(direction.buy_currency());
+                let buy = buy.with_scale_round(buy_decimals, RoundingMode::Up);
+                if buy <= BigDecimal::zero() {
+                    return Err(invalid_amount(&buy, "Amount must be greater than zero"));
+                }
+
+                // Fees depend on the amount sold, so start from the fee-free
+                // amount and add the shortfall until the fees are covered
+                let probe = self.convert(direction, &buy).await?;
+                let mut sell =
+                    (&buy / &probe.rate).with_scale_round(sell_decimals, RoundingMode::Up);
+                if direction == QuoteDirection::Onramp {
+                    sell = sell.max(BigDecimal::from(MIN_ONRAMP_AMOUNT_NGN));
+                }
+                for _ in 0..MAX_PRICE_ITERATIONS {
+                    let mut quote = self.price_sell(direction, &sell).await?;
+                    if quote.buy_amount >= buy {
+                        // Rounding the amount sold up leaves a sliver over,
+                        // which is counted as fee so the amounts add up
+                        quote.fee += &quote.buy_amount - &buy;
+                        quote.buy_amount = buy;
+                        return Ok(quote);
+                    }
+                    let shortfall = &buy - &quote.buy_amount;
+                    sell = (&sell + &shortfall / &probe.rate)
+                        .with_scale_round(sell_decimals, RoundingMode::Up);
+                }
+                Err(invalid_amount(&buy, "Could not price the requested amount"))
+            }
+        }
+    }
+
+    /// Store a firm quote for `wallet_address`, valid for the quote TTL
+    pub async fn create_firm_quote(
+        &self,
+        wallet_address: &str,
+        context: &str,
+        quote: &PricedQuote,
+    ) -> Result<OnrampQuote, AppError> {
+        let repository = self.quote_repository()?;
+        if let Some(kyc) = &self.kyc {
+            kyc.check_limit(wallet_address, &quote.sell_amount).await?;
+        }
+        if quote.direction == QuoteDirection::Onramp && self.liquidity_check_enabled {
+            self.check_liquidity(&quote.buy_amount).await?;
+        }
+
+        let (amount_ngn, gross_cngn, fee_cngn, net_cngn) = match quote.direction {
+            QuoteDirection::Onramp => (
+                quote.sell_amount.clone(),
+                &quote.buy_amount + &quote.fee,
+                quote.fee.clone(),
+                quote.buy_amount.clone(),
+            ),
+            QuoteDirection::Offramp => (
+                quote.buy_amount.clone(),
+                quote.sell_amount.clone(),
+                BigDecimal::zero(),
+                quote.sell_amount.clone(),
+            ),
+        };
+        let stored = repository
+            .create_firm(&NewFirmQuote {
+                direction: quote.direction.as_str(),
+                wallet_address,
+                context,
+                amount_ngn,
+                exchange_rate: quote.rate.clone(),
+                gross_cngn,
+                fee_cngn,
+                net_cngn,
+                sell_amount: quote.sell_amount.clone(),
+                buy_amount: quote.buy_amount.clone(),
+                fee_total: quote.fee.clone(),
+                expires_at: Utc::now() + chrono::Duration::seconds(QUOTE_TTL_SECS as i64),
+            })
+            .await
+            .map_err(database_error)?;
+
+        info!(
+            quote_id = %stored.quote_id,
+            direction = quote.direction.as_str(),
+            wallet_address,
+            "Firm quote created"
+        );
+        Ok(stored)
+    }
+
+    /// Look up a firm quote
+    pub async fn firm_quote(&self, quote_id: Uuid) -> Result<Option<OnrampQuote>, AppError> {
+        self.quote_repository()?
+            .find_by_quote_id(quote_id)
+            .await
+            .map_err(database_error)
+    }
+
+    /// Quote the amount bought for a fixed amount sold
+    async fn price_sell(
+        &self,
+        direction: QuoteDirection,
+        sell: &BigDecimal,
+    ) -> Result<PricedQuote, AppError> {
+        if direction == QuoteDirection::Onramp && *sell < MIN_ONRAMP_AMOUNT_NGN {
+            return Err(AppError::new(AppErrorKind::Domain(
+                DomainError::AmountTooLow {
+                    amount: sell.to_string(),
+                    minimum: MIN_ONRAMP_AMOUNT_NGN.to_string(),
+                },
+            )));
+        }
+
+        let conversion = self.convert(direction, sell).await?;
+        let mut fee = conversion.fee;
+        if fee.is_zero() && direction == QuoteDirection::Onramp {
+            // Same onramp fee types create_quote falls back to, charged in NGN
+            let (platform_fee, provider_fee) = self.calculate_onramp_fees(sell).await?;
+            fee = (platform_fee + provider_fee) * &conversion.rate;
+        }
+
+        let buy_decimals = QuoteDirection::decimals(direction.buy_currency());
+        let fee = fee.with_scale_round(buy_decimals, RoundingMode::Up);
+        let buy_amount =
+            (&conversion.gross - &fee).with_scale_round(buy_decimals, RoundingMode::Down);
+        if buy_amount <= BigDecimal::zero() {
+            return Err(invalid_amount(
+                sell,
+                "Amount after fees must be greater than zero",
+            ));
+        }
+
+        Ok(PricedQuote {
+            direction,
+            sell_amount: sell.clone(),
+            buy_amount,
+            fee,
+            rate: conversion.rate,
+        })
+    }
+
+    async fn convert(
+        &self,
+        direction: QuoteDirection,
+        amount: &BigDecimal,
+    ) -> Result<Conversion, AppError> {
+        let conversion = self
+            .exchange_rate_service
+            .calculate_conversion(ConversionRequest {
+                from_currency: direction.sell_currency().to_string(),
+                to_currency: direction.buy_currency().to_string(),
+                amount: amount.clone(),
+                direction: direction.conversion(),
+            })
+            .await
+            .map_err(|e| {
+                AppError::new(AppErrorKind::External(
+                    crate::error::ExternalError::Blockchain {
+                        message: e.to_string(),
+                        is_retryable: true,
+                    },
+                ))
+            })?;
+
+        let parse =
+            |value: &str| BigDecimal::from_str(value).unwrap_or_else(|_| BigDecimal::zero());
+        let rate = parse(&conversion.base_rate);
+        if rate <= BigDecimal::zero() {
+            return Err(invalid_amount(amount, "No exchange rate is available"));
+        }
+        Ok(Conversion {
+            rate,
+            gross: parse(&conversion.gross_amount),
+            fee: parse(&conversion.fees.total_fees),
+        })
+    }
+
+    fn quote_repository(&self) -> Result<&OnrampQuoteRepository, AppError> {
+        self.quote_repository.as_ref().ok_or_else(|| {
+            AppError::new(AppErrorKind::Infrastructure(
+                crate::error::InfrastructureError::Configuration {
+                    message: "firm quotes need the quote repository".to_string(),
+                },
+            ))
+        })
+    }
+
     async fn calculate_onramp_fees(
         &self,
         amount_ngn: &BigDecimal,
@@ -488,6 +811,29 @@ impl OnrampQuoteService {
     }
 }
 
+/// Rate, gross amount and fee of one exchange rate conversion
+struct Conversion {
+    rate: BigDecimal,
+    gross: BigDecimal,
+    fee: BigDecimal,
+}
+
+fn invalid_amount(amount: &BigDecimal, reason: &str) -> AppError {
+    AppError::new(AppErrorKind::Domain(DomainError::InvalidAmount {
+        amount: amount.to_string(),
+        reason: reason.to_string(),
+    }))
+}
+
+fn database_error(e: crate::database::error::DatabaseError) -> AppError {
+    AppError::new(AppErrorKind::Infrastructure(
+        crate::error::InfrastructureError::Database {
+            message: e.to_string(),
+            is_retryable: true,
+        },
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -509,6 +855,33 @@ mod tests {
         assert_eq!(PaymentProvider::from("other").as_str(), "other");
     }
 
+    #[test]
+    fn test_quote_direction() {
+        let onramp = QuoteDirection::parse("onramp").unwrap();
+        assert_eq!(onramp.sell_currency(), "NGN");
+        assert_eq!(onramp.buy_currency(), "cNGN");
+        assert_eq!(QuoteDirection::Offramp.sell_currency(), "cNGN");
+        assert_eq!(QuoteDirection::decimals("NGN"), 2);
+        assert_eq!(QuoteDirection::decimals("cNGN"), 7);
+        assert!(QuoteDirection::parse("sideways").is_none());
+    }
+
+    #[test]
+    fn test_priced_quote_prices() {
+        let quote = PricedQuote {
+            direction: QuoteDirection::Onramp,
+            sell_amount: BigDecimal::from(10_000),
+            buy_amount: BigDecimal::from(9_800),
+            fee: BigDecimal::from(200),
+            rate: BigDecimal::from(1),
+        };
+        assert_eq!(quote.price(), BigDecimal::from(1));
+        assert_eq!(
+            quote.total_price(),
+            BigDecimal::from_str("1.0204082").unwrap()
+        );
+    }
+
     #[test]
     fn test_chain_from_str() {
         assert_eq!(Chain::from("stellar").as_str(), "stellar");
@@ -8,11 +8,14 @@ use crate::database::repository::Repository;
 use crate::database::transaction_repository::Transaction;
 use crate::database::transaction_repository::TransactionRepository;
 use crate::error::{AppError, AppErrorKind, DomainError, ExternalError, InfrastructureError};
+use crate::payments::error::PaymentError;
 use crate::payments::provider::PaymentProvider;
 use crate::payments::types::{
     Money, PaymentMethod, PaymentRequest, PaymentResponse, PaymentState, ProviderName,
     StatusRequest, StatusResponse,
 };
+use crate::services::idempotency::{IdempotencyStore, ReserveOutcome};
+use crate::services::merchant_webhooks::{events, MerchantWebhookService};
 use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -294,6 +297,19 @@ impl OrchestrationState {
             OrchestrationState::Refunded => "refunded",
         }
     }
+
+    /// Merchant webhook event announcing entry into this state, if partners are told about it
+    pub fn merchant_event(&self) -> Option<&'static str> {
+        match self {
+            OrchestrationState::PaymentConfirmed => Some(events::TRANSACTION_PAYMENT_CONFIRMED),
+            OrchestrationState::ProcessingBlockchain => Some(events::TRANSACTION_PROCESSING),
+            OrchestrationState::Completed => Some(events::TRANSACTION_COMPLETED),
+            OrchestrationState::Failed => Some(events::TRANSACTION_FAILED),
+            OrchestrationState::RefundInitiated => Some(events::TRANSACTION_REFUND_INITIATED),
+            OrchestrationState::Refunded => Some(events::TRANSACTION_REFUNDED),
+            OrchestrationState::Created | OrchestrationState::PendingPayment => None,
+        }
+    }
 }
 
 // Add BlockchainFailed state (missing from original)
@@ -305,6 +321,9 @@ impl OrchestrationState {
 // Idempotency Types
 // ============================================================================
 
+/// Namespace for orchestrator records in the idempotency store
+const IDEMPOTENCY_SCOPE: &str = "orchestrator";
+
 /// Idempotency key info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IdempotencyKeyInfo {
@@ -316,6 +335,16 @@ pub struct IdempotencyKeyInfo {
     pub operation: String, // "onramp" or "offramp"
     pub created_at: u64,
     pub expires_at: u64,
+    /// Provider response, recorded once initiation succeeds so retries can replay it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<PaymentResponse>,
+}
+
+impl IdempotencyKeyInfo {
+    /// Whether a retry carries the same payload as the original request
+    pub fn matches_request(&self, wallet_address: &str, amount: &str, currency: &str) -> bool {
+        self.wallet_address == wallet_address && self.amount == amount && self.currency == currency
+    }
 }
 
 /// Idempotency check result
@@ -327,6 +356,8 @@ pub enum IdempotencyCheckResult {
     ExistingPending {
         transaction_id: String,
         idempotency_key: String,
+        /// Original response to replay; `None` while the first request is still in flight
+        response: Option<PaymentResponse>,
     },
     /// Existing key found with completed transaction - error (duplicate)
     Duplicate {
@@ -340,6 +371,42 @@ pub enum IdempotencyCheckResult {
     },
 }
 
+impl IdempotencyCheckResult {
+    /// Classify a stored key against the current state of its transaction.
+    ///
+    /// When no transaction row exists yet, the stored provider response decides.
+    pub fn classify(info: IdempotencyKeyInfo, state: Option<OrchestrationState>) -> Self {
+        let settled = match state {
+            Some(OrchestrationState::Completed) | Some(OrchestrationState::Refunded) => Some(true),
+            Some(OrchestrationState::Failed) => Some(false),
+            Some(_) => None,
+            None => match info.response.as_ref().map(|r| &r.status) {
+                Some(PaymentState::Success) => Some(true),
+                Some(PaymentState::Failed)
+                | Some(PaymentState::Cancelled)
+                | Some(PaymentState::Reversed) => Some(false),
+                _ => None,
+            },
+        };
+
+        match settled {
+            Some(true) => IdempotencyCheckResult::Duplicate {
+                transaction_id: info.transaction_id,
+                idempotency_key: info.key,
+            },
+            Some(false) => IdempotencyCheckResult::AllowRetry {
+                existing_transaction_id: info.transaction_id,
+                idempotency_key: info.key,
+            },
+            None => IdempotencyCheckResult::ExistingPending {
+                transaction_id: info.transaction_id,
+                idempotency_key: info.key,
+                response: info.response,
+            },
+        }
+    }
+}
+
 // ============================================================================
 // Payment Routing Types
 // ============================================================================
@@ -383,6 +450,10 @@ pub struct PaymentInitiationRequest {
     pub callback_url: Option<String>,
     pub idempotency_key: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    /// Provider to use instead of the selection strategy, when available
+    pub preferred_provider: Option<ProviderName>,
+    /// Reference sent to the provider; generated when absent
+    pub transaction_reference: Option<String>,
 }
 
 // ============================================================================
@@ -403,12 +474,21 @@ pub enum OrchestratorError {
     IdempotencyKeyNotFound,
     /// Duplicate transaction
     DuplicateTransaction { transaction_id: String },
+    /// Idempotency key reused with a different request payload
+    IdempotencyKeyReused { idempotency_key: String },
+    /// Idempotency store could not be read or written
+    IdempotencyStoreFailed { message: String },
     /// Max retries exceeded
     MaxRetriesExceeded { transaction_id: String },
     /// Provider selection failed
     ProviderSelectionFailed { reason: String },
     /// All providers failed
     AllProvidersFailed { errors: Vec<String> },
+    /// The provider never gave a definitive answer, so the payment may exist
+    PaymentOutcomeUnknown {
+        transaction_id: String,
+        message: String,
+    },
     /// Blockchain operation failed
     BlockchainFailed { message: String },
     /// Transaction not found
@@ -428,6 +508,16 @@ impl std::fmt::Display for OrchestratorError {
             Self::DuplicateTransaction { transaction_id } => {
                 write!(f, "Duplicate transaction: {}", transaction_id)
             }
+            Self::IdempotencyKeyReused { idempotency_key } => {
+                write!(
+                    f,
+                    "Idempotency key {} was already used for a different request",
+                    idempotency_key
+                )
+            }
+            Self::IdempotencyStoreFailed { message } => {
+                write!(f, "Idempotency store error: {}", message)
+            }
             Self::MaxRetriesExceeded { transaction_id } => {
                 write!(
                     f,
@@ -441,6 +531,16 @@ impl std::fmt::Display for OrchestratorError {
             Self::AllProvidersFailed { errors } => {
                 write!(f, "All providers failed: {}", errors.join("; "))
             }
+            Self::PaymentOutcomeUnknown {
+                transaction_id,
+                message,
+            } => {
+                write!(
+                    f,
+                    "Payment outcome unknown for transaction {}: {}",
+                    transaction_id, message
+                )
+            }
             Self::BlockchainFailed { message } => {
                 write!(f, "Blockchain operation failed: {}", message)
             }
@@ -466,11 +566,24 @@ impl From<OrchestratorError> for AppError {
                     is_retryable: true,
                 })
             }
-            OrchestratorError::DuplicateTransaction { .. } => {
+            OrchestratorError::DuplicateTransaction { .. }
+            | OrchestratorError::IdempotencyKeyReused { .. } => {
                 AppErrorKind::Domain(DomainError::DuplicateTransaction {
                     transaction_id: "unknown".to_string(),
                 })
             }
+            OrchestratorError::PaymentOutcomeUnknown { .. } => {
+                AppErrorKind::External(ExternalError::PaymentProvider {
+                    provider: "orchestrator".to_string(),
+                    message: err.to_string(),
+                    is_retryable: true,
+                })
+            }
+            OrchestratorError::IdempotencyStoreFailed { .. } => {
+                AppErrorKind::Infrastructure(InfrastructureError::Cache {
+                    message: err.to_string(),
+                })
+            }
             OrchestratorError::TransactionNotFound { .. } => {
                 AppErrorKind::Domain(DomainError::TransactionNotFound {
                     transaction_id: "unknown".to_string(),
@@ -516,6 +629,8 @@ pub struct PaymentOrchestrator {
     config: OrchestratorConfig,
     provider_metrics: Arc<RwLock<HashMap<ProviderName, ProviderMetrics>>>,
     round_robin_index: Arc<RwLock<usize>>,
+    idempotency: Option<Arc<IdempotencyStore>>,
+    merchant_webhooks: Option<Arc<MerchantWebhookService>>,
 }
 
 impl PaymentOrchestrator {
@@ -537,9 +652,23 @@ impl PaymentOrchestrator {
             config,
             provider_metrics: Arc::new(RwLock::new(metrics)),
             round_robin_index: Arc::new(RwLock::new(0)),
+            idempotency: None,
+            merchant_webhooks: None,
         }
     }
 
+    /// Back idempotency checks with a Redis/Postgres store
+    pub fn with_idempotency_store(mut self, store: Arc<IdempotencyStore>) -> Self {
+        self.idempotency = Some(store);
+        self
+    }
+
+    /// Publish state transitions to partner webhook endpoints
+    pub fn with_merchant_webhooks(mut self, service: Arc<MerchantWebhookService>) -> Self {
+        self.merchant_webhooks = Some(service);
+        self
+    }
+
     /// Add a provider to the orchestrator
     pub fn add_provider(&mut self, provider: Arc<dyn PaymentProvider>) {
         let name = provider.name();
@@ -784,22 +913,59 @@ impl PaymentOrchestrator {
         &self,
         idempotency_key: &str,
     ) -> OrchestratorResult<IdempotencyCheckResult> {
-        // Try to get from cache first
-        // In production, implement actual cache lookup
-        // For now, return NewTransaction to proceed
+        match self.load_idempotency_key(idempotency_key).await? {
+            Some(info) => Ok(self.classify_idempotency(info).await),
+            None => Ok(IdempotencyCheckResult::NewTransaction),
+        }
+    }
 
-        // This would be implemented with actual Redis cache:
-        // let cache_key = format!("idempotency:{}", idempotency_key);
-        // if let Some(info) = self.cache.get(&cache_key).await? { ... }
+    /// Load the stored record for an idempotency key
+    async fn load_idempotency_key(
+        &self,
+        idempotency_key: &str,
+    ) -> OrchestratorResult<Option<IdempotencyKeyInfo>> {
+        let Some(store) = &self.idempotency else {
+            return Ok(None);
+        };
 
-        Ok(IdempotencyCheckResult::NewTransaction)
+        store
+            .get::<IdempotencyKeyInfo>(IDEMPOTENCY_SCOPE, idempotency_key)
+            .await
+            .map_err(|e| OrchestratorError::IdempotencyStoreFailed {
+                message: e.to_string(),
+            })
+    }
+
+    /// Classify an existing key using the linked transaction, if one was recorded
+    async fn classify_idempotency(&self, info: IdempotencyKeyInfo) -> IdempotencyCheckResult {
+        let state = match self
+            .transaction_repo
+            .find_by_payment_reference(&info.transaction_id)
+            .await
+        {
+            Ok(tx) => tx.and_then(|tx| OrchestrationState::from_db_status(&tx.status)),
+            Err(e) => {
+                warn!(
+                    transaction_id = %info.transaction_id,
+                    error = %e,
+                    "Failed to load transaction for idempotency check"
+                );
+                None
+            }
+        };
+        IdempotencyCheckResult::classify(info, state)
     }
 
-    /// Store idempotency key info
+    /// Store idempotency key info, overwriting any previous record
     pub async fn store_idempotency_key(&self, info: &IdempotencyKeyInfo) -> OrchestratorResult<()> {
-        // In production, store in Redis with TTL
-        // let cache_key = format!("idempotency:{}", info.key);
-        // self.cache.set(&cache_key, info, Some(Duration::from_secs(info.expires_at - info.created_at))).await?;
+        if let Some(store) = &self.idempotency {
+            store
+                .put(IDEMPOTENCY_SCOPE, &info.key, info, self.idempotency_ttl())
+                .await
+                .map_err(|e| OrchestratorError::IdempotencyStoreFailed {
+                    message: e.to_string(),
+                })?;
+        }
 
         info!(
             key = %info.key,
@@ -810,6 +976,42 @@ impl PaymentOrchestrator {
         Ok(())
     }
 
+    /// Claim an idempotency key for a new request.
+    ///
+    /// Returns the existing record when another request already holds the key.
+    async fn reserve_idempotency_key(
+        &self,
+        info: &IdempotencyKeyInfo,
+    ) -> OrchestratorResult<Option<IdempotencyKeyInfo>> {
+        let Some(store) = &self.idempotency else {
+            return Ok(None);
+        };
+
+        match store
+            .reserve(IDEMPOTENCY_SCOPE, &info.key, info, self.idempotency_ttl())
+            .await
+        {
+            Ok(ReserveOutcome::Reserved) => Ok(None),
+            Ok(ReserveOutcome::Existing(existing)) => Ok(Some(existing)),
+            Err(e) => Err(OrchestratorError::IdempotencyStoreFailed {
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    /// Drop a key after a failed initiation so the client can retry with it
+    async fn release_idempotency_key(&self, idempotency_key: &str) {
+        if let Some(store) = &self.idempotency {
+            if let Err(e) = store.release(IDEMPOTENCY_SCOPE, idempotency_key).await {
+                warn!(key = %idempotency_key, error = %e, "Failed to release idempotency key");
+            }
+        }
+    }
+
+    fn idempotency_ttl(&self) -> Duration {
+        Duration::from_secs(self.config.idempotency_key_expiration_secs)
+    }
+
     // =========================================================================
     // Transaction State Management
     // =========================================================================
@@ -879,6 +1081,12 @@ impl PaymentOrchestrator {
             "Transaction state transitioned"
         );
 
+        if let (Some(webhooks), Some(event_type)) =
+            (&self.merchant_webhooks, target_state.merchant_event())
+        {
+            webhooks.notify(event_type, &updated, None).await;
+        }
+
         Ok(updated)
     }
 
@@ -904,19 +1112,65 @@ impl PaymentOrchestrator {
             )
         });
 
-        // Check idempotency
-        match self.check_idempotency(&idempotency_key).await? {
-            IdempotencyCheckResult::ExistingPending { transaction_id, .. } => {
-                // Return existing pending transaction
+        let amount_str = amount.to_string();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut idempotency_info = IdempotencyKeyInfo {
+            key: idempotency_key.clone(),
+            transaction_id: request
+                .transaction_reference
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            wallet_address: request.wallet_address.clone(),
+            amount: amount_str.clone(),
+            currency: currency.clone(),
+            operation: "onramp".to_string(),
+            created_at: now,
+            expires_at: now + self.config.idempotency_key_expiration_secs,
+            response: None,
+        };
+
+        // Check idempotency, claiming the key when it is free so concurrent
+        // retries can't both reach the provider
+        let existing = match self.load_idempotency_key(&idempotency_key).await? {
+            Some(existing) => Some(existing),
+            None => self.reserve_idempotency_key(&idempotency_info).await?,
+        };
+        let check = match existing {
+            Some(existing) => {
+                if !existing.matches_request(&request.wallet_address, &amount_str, &currency) {
+                    return Err(OrchestratorError::IdempotencyKeyReused { idempotency_key });
+                }
+                self.classify_idempotency(existing).await
+            }
+            None => IdempotencyCheckResult::NewTransaction,
+        };
+
+        match check {
+            IdempotencyCheckResult::ExistingPending {
+                transaction_id,
+                response: Some(response),
+                ..
+            } => {
                 info!(transaction_id = %transaction_id, "Returning existing pending transaction");
-                // In production, fetch and return existing transaction
-                return Err(OrchestratorError::DuplicateTransaction { transaction_id });
+                return Ok(response);
             }
-            IdempotencyCheckResult::Duplicate { transaction_id, .. } => {
+            IdempotencyCheckResult::ExistingPending { transaction_id, .. }
+            | IdempotencyCheckResult::Duplicate { transaction_id, .. } => {
                 return Err(OrchestratorError::DuplicateTransaction { transaction_id });
             }
-            IdempotencyCheckResult::AllowRetry { .. } => {
-                // Allow retry with new key
+            IdempotencyCheckResult::AllowRetry {
+                existing_transaction_id,
+                ..
+            } => {
+                // Previous attempt failed: take the key over for this attempt
+                info!(
+                    previous_transaction_id = %existing_transaction_id,
+                    "Retrying payment with idempotency key of failed transaction"
+                );
+                self.store_idempotency_key(&idempotency_info).await?;
             }
             IdempotencyCheckResult::NewTransaction => {
                 // Proceed with new transaction
@@ -928,9 +1182,10 @@ impl PaymentOrchestrator {
             amount: amount.clone(),
             currency: currency.clone(),
             payment_method: request.payment_method.clone(),
-            user_preferred_provider: None,
+            user_preferred_provider: request.preferred_provider.clone(),
             country: None,
-            strategy: if amount >= self.config.large_transaction_threshold
+            strategy: if request.preferred_provider.is_none()
+                && amount >= self.config.large_transaction_threshold
                 && self.config.fee_comparison_enabled
             {
                 SelectionStrategy::CostBased
@@ -939,20 +1194,11 @@ impl PaymentOrchestrator {
             },
         };
 
-        // Select provider
-        let provider_name = self.select_provider(&context).await?;
-
-        // Get provider
-        let provider = self
-            .providers
-            .get(&provider_name)
-            .ok_or(OrchestratorError::NoProviderAvailable)?;
-
         // Create payment request
-        let transaction_reference = Uuid::new_v4().to_string();
+        let transaction_reference = idempotency_info.transaction_id.clone();
         let payment_request = PaymentRequest {
             amount: Money {
-                amount: amount.to_string(),
+                amount: amount_str.clone(),
                 currency: currency.clone(),
             },
             customer: crate::payments::types::CustomerContact {
@@ -965,27 +1211,47 @@ impl PaymentOrchestrator {
             metadata: request.metadata.clone(),
         };
 
-        // Initiate payment with retry logic
-        let response = self
-            .initiate_with_retry(provider.as_ref(), payment_request)
-            .await?;
-
-        // Store idempotency key
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let idempotency_info = IdempotencyKeyInfo {
-            key: idempotency_key.clone(),
-            transaction_id: transaction_reference.clone(),
-            wallet_address: request.wallet_address.clone(),
-            amount: amount.to_string(),
-            currency: currency.clone(),
-            operation: "onramp".to_string(),
-            created_at: now,
-            expires_at: now + self.config.idempotency_key_expiration_secs,
+        // Select provider and initiate payment with retry logic
+        let result = match self.select_provider(&context).await {
+            Ok(provider_name) => match self.providers.get(&provider_name) {
+                Some(provider) => self
+                    .initiate_with_retry(provider.as_ref(), payment_request)
+                    .await
+                    .map(|response| (provider_name, response)),
+                None => Err(OrchestratorError::NoProviderAvailable),
+            },
+            Err(e) => Err(e),
+        };
+        let (provider_name, response) = match result {
+            Ok(result) => result,
+            Err(e @ OrchestratorError::PaymentOutcomeUnknown { .. }) => {
+                // The provider may have created the payment; releasing the key
+                // would let a retry charge the customer twice
+                warn!(
+                    transaction_id = %transaction_reference,
+                    key = %idempotency_key,
+                    error = %e,
+                    "Payment outcome unknown, keeping idempotency key reserved"
+                );
+                return Err(e);
+            }
+            Err(e) => {
+                // The provider refused the payment, so the key is free again
+                self.release_idempotency_key(&idempotency_key).await;
+                return Err(e);
+            }
         };
-        self.store_idempotency_key(&idempotency_info).await?;
+
+        // Store idempotency key with the response for replays
+        idempotency_info.response = Some(response.clone());
+        if let Err(e) = self.store_idempotency_key(&idempotency_info).await {
+            // The payment went through; a failed write must not surface as an error
+            error!(
+                transaction_id = %transaction_reference,
+                error = %e,
+                "Failed to record response for idempotency key"
+            );
+        }
 
         // Record metrics
         {
@@ -1032,8 +1298,18 @@ impl PaymentOrchestrator {
                         });
                     }
 
-                    last_error = Some(OrchestratorError::AllProvidersFailed {
-                        errors: vec![e.to_string()],
+                    // A rate limit is a refusal; a timeout or provider-side
+                    // error may follow a payment the provider did create
+                    last_error = Some(match &e {
+                        PaymentError::RateLimitError { .. } => {
+                            OrchestratorError::AllProvidersFailed {
+                                errors: vec![e.to_string()],
+                            }
+                        }
+                        _ => OrchestratorError::PaymentOutcomeUnknown {
+                            transaction_id: request.transaction_reference.clone(),
+                            message: e.to_string(),
+                        },
                     });
 
                     // Calculate delay with exponential backoff
@@ -1455,6 +1731,19 @@ mod tests {
             .contains(&OrchestrationState::Failed));
     }
 
+    #[test]
+    fn test_merchant_events_for_states() {
+        assert_eq!(
+            OrchestrationState::Completed.merchant_event(),
+            Some("transaction.completed")
+        );
+        assert_eq!(
+            OrchestrationState::Refunded.merchant_event(),
+            Some("transaction.refunded")
+        );
+        assert_eq!(OrchestrationState::PendingPayment.merchant_event(), None);
+    }
+
     #[test]
     fn test_state_transitions_invalid() {
         // Can't go from pending directly to completed
@@ -1531,4 +1820,84 @@ mod tests {
         assert_eq!(OrchestrationState::PendingPayment.to_db_status(), "pending");
         assert_eq!(OrchestrationState::Completed.to_db_status(), "completed");
     }
+
+    fn idempotency_info(response_status: Option<PaymentState>) -> IdempotencyKeyInfo {
+        IdempotencyKeyInfo {
+            key: "key-1".to_string(),
+            transaction_id: "tx-1".to_string(),
+            wallet_address: "GWALLET".to_string(),
+            amount: "5000".to_string(),
+            currency: "NGN".to_string(),
+            operation: "onramp".to_string(),
+            created_at: 0,
+            expires_at: 86400,
+            response: response_status.map(|status| PaymentResponse {
+                status,
+                transaction_reference: "tx-1".to_string(),
+                provider_reference: Some("prov-1".to_string()),
+                payment_url: Some("https://pay.example.com/tx-1".to_string()),
+                amount_charged: None,
+                fees_charged: None,
+                provider_data: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_idempotency_classify_by_transaction_state() {
+        let info = idempotency_info(Some(PaymentState::Pending));
+
+        assert!(matches!(
+            IdempotencyCheckResult::classify(info.clone(), Some(OrchestrationState::Completed)),
+            IdempotencyCheckResult::Duplicate { .. }
+        ));
+        assert!(matches!(
+            IdempotencyCheckResult::classify(info.clone(), Some(OrchestrationState::Failed)),
+            IdempotencyCheckResult::AllowRetry { .. }
+        ));
+        match IdempotencyCheckResult::classify(info, Some(OrchestrationState::PendingPayment)) {
+            IdempotencyCheckResult::ExistingPending { response, .. } => {
+                assert_eq!(
+                    response.unwrap().payment_url.as_deref(),
+                    Some("https://pay.example.com/tx-1")
+                );
+            }
+            other => panic!("expected ExistingPending, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_idempotency_classify_by_stored_response() {
+        assert!(matches!(
+            IdempotencyCheckResult::classify(idempotency_info(Some(PaymentState::Success)), None),
+            IdempotencyCheckResult::Duplicate { .. }
+        ));
+        assert!(matches!(
+            IdempotencyCheckResult::classify(idempotency_info(Some(PaymentState::Failed)), None),
+            IdempotencyCheckResult::AllowRetry { .. }
+        ));
+        // Reserved but not yet answered by the provider
+        assert!(matches!(
+            IdempotencyCheckResult::classify(idempotency_info(None), None),
+            IdempotencyCheckResult::ExistingPending { response: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_idempotency_info_matches_request() {
+        let info = idempotency_info(None);
+        assert!(info.matches_request("GWALLET", "5000", "NGN"));
+        assert!(!info.matches_request("GWALLET", "5001", "NGN"));
+        assert!(!info.matches_request("GOTHER", "5000", "NGN"));
+    }
+
+    #[test]
+    fn test_idempotency_info_deserializes_without_response() {
+        let json = serde_json::json!({
+            "key": "k", "transaction_id": "t", "wallet_address": "w", "amount": "1",
+            "currency": "NGN", "operation": "onramp", "created_at": 1, "expires_at": 2
+        });
+        let info: IdempotencyKeyInfo = serde_json::from_value(json).unwrap();
+        assert!(info.response.is_none());
+    }
 }
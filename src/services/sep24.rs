@@ -0,0 +1,1158 @@
+//! SEP-24 hosted deposit and withdrawal
+//!
+//! A wallet authenticated with SEP-10 opens a deposit or withdrawal and gets
+//! back the URL of our interactive flow. The transaction is recorded as
+//! `incomplete` until that flow submits the details: a deposit is priced by
+//! `OnrampQuoteService` and charged through the payment orchestrator, and a
+//! withdrawal becomes an offramp waiting for the user's cNGN with a deposit
+//! memo. A transaction opened with a SEP-38 `quote_id` is held to that firm
+//! quote instead, which is consumed when the details are submitted. From then on the regular onramp and offramp processors drive it and
+//! this module only translates their states into SEP-24 statuses.
+
+use crate::auth::{scopes, AuthError, AuthService};
+use crate::database::deposit_memo_repository::DepositMemoRepository;
+use crate::database::error::DatabaseError;
+use crate::database::onramp_quote_repository::OnrampQuote;
+use crate::database::repository::Repository;
+use crate::database::transaction_repository::{
+    FillOutcome, IncompleteFill, Transaction, TransactionRepository,
+};
+use crate::error::{AppError, AppErrorKind, DomainError};
+use crate::payments::types::PaymentMethod;
+use crate::services::exchange_rate::{
+    ConversionDirection, ConversionRequest, ExchangeRateError, ExchangeRateService,
+};
+use crate::services::kyc::{limit_exceeded, KycService, KycTier};
+use crate::services::onramp_quote::{OnrampQuoteRequest, OnrampQuoteService, QuoteDirection};
+use crate::services::payment_orchestrator::{
+    OrchestrationState, OrchestratorError, PaymentInitiationRequest, PaymentOrchestrator,
+};
+use crate::services::sep38::NGN_ASSET;
+use crate::workers::offramp_processor::{OfframpMetadata, OfframpState};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+use uuid::Uuid;
+
+/// Status of a transaction opened through SEP-24 whose details are not in yet
+pub const INCOMPLETE: &str = "incomplete";
+
+/// Rows `/transactions` returns when the wallet does not ask for fewer
+const DEFAULT_LIST_LIMIT: i64 = 50;
+const MAX_LIST_LIMIT: i64 = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Sep24Error {
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    #[error("transaction not found")]
+    NotFound,
+
+    #[error("{}", .0.user_message())]
+    Quote(AppError),
+
+    #[error("payment could not be initiated: {0}")]
+    Payment(OrchestratorError),
+
+    #[error("exchange rate unavailable: {0}")]
+    Rate(#[from] ExchangeRateError),
+
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+impl Sep24Error {
+    /// HTTP status for this error
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Sep24Error::InvalidRequest(_) => 400,
+            Sep24Error::NotFound => 404,
+            Sep24Error::Quote(e) => e.status_code(),
+            Sep24Error::Payment(_) => 502,
+            Sep24Error::Rate(_) => 503,
+            Sep24Error::Auth(e) => e.status_code(),
+            Sep24Error::Database(_) => 500,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct Sep24Config {
+    /// Page that runs the interactive flow; gets `transaction_id` and `token`
+    pub interactive_url: String,
+    pub asset_code: String,
+    pub asset_issuer: String,
+    /// Account withdrawals are paid into (the system wallet)
+    pub withdraw_account: String,
+    /// NGN limits for deposits
+    pub deposit_min_amount: BigDecimal,
+    pub deposit_max_amount: Option<BigDecimal>,
+    /// cNGN limits for withdrawals
+    pub withdraw_min_amount: BigDecimal,
+    pub withdraw_max_amount: Option<BigDecimal>,
+    /// Lifetime of the token handed to the interactive flow
+    pub interactive_token_ttl: Duration,
+}
+
+impl Sep24Config {
+    /// Load from environment. Returns `Ok(None)` when no interactive URL is set.
+    ///
+    /// The asset and withdrawal account are filled in by the caller from the
+    /// cNGN and system wallet settings.
+    pub fn from_env() -> Result<Option<Self>, Sep24Error> {
+        let Ok(interactive_url) = std::env::var("SEP24_INTERACTIVE_URL") else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            interactive_url,
+            asset_code: String::new(),
+            asset_issuer: String::new(),
+            withdraw_account: String::new(),
+            deposit_min_amount: amount_from_env("SEP24_DEPOSIT_MIN_AMOUNT")?
+                .unwrap_or_else(|| BigDecimal::from(1000)),
+            deposit_max_amount: amount_from_env("SEP24_DEPOSIT_MAX_AMOUNT")?,
+            withdraw_min_amount: amount_from_env("SEP24_WITHDRAW_MIN_AMOUNT")?
+                .unwrap_or_else(|| BigDecimal::from(1)),
+            withdraw_max_amount: amount_from_env("SEP24_WITHDRAW_MAX_AMOUNT")?,
+            interactive_token_ttl: Duration::from_secs(
+                std::env::var("SEP24_INTERACTIVE_TOKEN_TTL_SECONDS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(600),
+            ),
+        }))
+    }
+
+    /// SEP-38 identifier of the Stellar asset
+    pub fn stellar_asset(&self) -> String {
+        format!("stellar:{}:{}", self.asset_code, self.asset_issuer)
+    }
+
+    fn limits(&self, kind: Sep24Kind) -> (&BigDecimal, Option<&BigDecimal>) {
+        match kind {
+            Sep24Kind::Deposit => (&self.deposit_min_amount, self.deposit_max_amount.as_ref()),
+            Sep24Kind::Withdrawal => (&self.withdraw_min_amount, self.withdraw_max_amount.as_ref()),
+        }
+    }
+}
+
+fn amount_from_env(name: &str) -> Result<Option<BigDecimal>, Sep24Error> {
+    match std::env::var(name) {
+        Ok(value) => BigDecimal::from_str(value.trim())
+            .map(Some)
+            .map_err(|_| Sep24Error::InvalidRequest(format!("{} is not a number", name))),
+        Err(_) => Ok(None),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Kinds and statuses
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sep24Kind {
+    Deposit,
+    Withdrawal,
+}
+
+impl Sep24Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sep24Kind::Deposit => "deposit",
+            Sep24Kind::Withdrawal => "withdrawal",
+        }
+    }
+
+    /// `transactions.type` the kind is stored as
+    pub fn transaction_type(&self) -> &'static str {
+        match self {
+            Sep24Kind::Deposit => "onramp",
+            Sep24Kind::Withdrawal => "offramp",
+        }
+    }
+
+    pub fn from_transaction_type(transaction_type: &str) -> Option<Self> {
+        match transaction_type {
+            "onramp" => Some(Sep24Kind::Deposit),
+            "offramp" => Some(Sep24Kind::Withdrawal),
+            _ => None,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "deposit" => Some(Sep24Kind::Deposit),
+            "withdrawal" | "withdraw" => Some(Sep24Kind::Withdrawal),
+            _ => None,
+        }
+    }
+}
+
+/// Transaction statuses defined by SEP-24
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sep24Status {
+    Incomplete,
+    PendingUserTransferStart,
+    PendingExternal,
+    PendingAnchor,
+    PendingStellar,
+    Completed,
+    Refunded,
+    Expired,
+    Error,
+}
+
+impl Sep24Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sep24Status::Incomplete => "incomplete",
+            Sep24Status::PendingUserTransferStart => "pending_user_transfer_start",
+            Sep24Status::PendingExternal => "pending_external",
+            Sep24Status::PendingAnchor => "pending_anchor",
+            Sep24Status::PendingStellar => "pending_stellar",
+            Sep24Status::Completed => "completed",
+            Sep24Status::Refunded => "refunded",
+            Sep24Status::Expired => "expired",
+            Sep24Status::Error => "error",
+        }
+    }
+
+    /// SEP-24 status of a stored transaction
+    pub fn of(kind: Sep24Kind, status: &str) -> Self {
+        if status == INCOMPLETE {
+            return Sep24Status::Incomplete;
+        }
+        let mapped = match kind {
+            Sep24Kind::Deposit => OrchestrationState::from_db_status(status).map(Self::from),
+            Sep24Kind::Withdrawal => OfframpState::from_str(status).map(Self::from),
+        };
+        mapped.unwrap_or(match status {
+            "expired" => Sep24Status::Expired,
+            "cancelled" => Sep24Status::Error,
+            _ => Sep24Status::PendingAnchor,
+        })
+    }
+}
+
+impl From<OrchestrationState> for Sep24Status {
+    fn from(state: OrchestrationState) -> Self {
+        match state {
+            OrchestrationState::Created | OrchestrationState::PendingPayment => {
+                Sep24Status::PendingUserTransferStart
+            }
+            OrchestrationState::PaymentConfirmed | OrchestrationState::RefundInitiated => {
+                Sep24Status::PendingAnchor
+            }
+            OrchestrationState::ProcessingBlockchain => Sep24Status::PendingStellar,
+            OrchestrationState::Completed => Sep24Status::Completed,
+            OrchestrationState::Refunded => Sep24Status::Refunded,
+            OrchestrationState::Failed => Sep24Status::Error,
+        }
+    }
+}
+
+impl From<OfframpState> for Sep24Status {
+    fn from(state: OfframpState) -> Self {
+        match state {
+            OfframpState::PendingPayment => Sep24Status::PendingUserTransferStart,
+            OfframpState::CngnReceived
+            | OfframpState::VerifyingAmount
+            | OfframpState::ProcessingWithdrawal
+            | OfframpState::RefundInitiated
+            | OfframpState::Refunding => Sep24Status::PendingAnchor,
+            OfframpState::TransferPending => Sep24Status::PendingExternal,
+            OfframpState::Completed => Sep24Status::Completed,
+            OfframpState::Refunded => Sep24Status::Refunded,
+            OfframpState::Failed => Sep24Status::Error,
+            OfframpState::Expired => Sep24Status::Expired,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetInfo {
+    pub enabled: bool,
+    pub min_amount: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureFlags {
+    pub account_creation: bool,
+    pub claimable_balances: bool,
+}
+
+/// `GET /sep24/info`
+#[derive(Debug, Clone, Serialize)]
+pub struct Sep24Info {
+    pub deposit: HashMap<String, AssetInfo>,
+    pub withdraw: HashMap<String, AssetInfo>,
+    pub fee: serde_json::Value,
+    pub features: FeatureFlags,
+}
+
+/// Body of the interactive deposit and withdraw requests
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractiveRequest {
+    pub asset_code: String,
+    #[serde(default)]
+    pub asset_issuer: Option<String>,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub amount: Option<String>,
+    #[serde(default)]
+    pub lang: Option<String>,
+    /// SEP-38 firm quote the transaction must follow
+    #[serde(default)]
+    pub quote_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InteractiveResponse {
+    pub r#type: &'static str,
+    pub url: String,
+    pub id: String,
+}
+
+/// What the interactive flow collects for a deposit
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepositDetails {
+    pub amount_ngn: i64,
+    pub provider: String,
+    pub email: String,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub payment_method: Option<PaymentMethod>,
+    #[serde(default)]
+    pub callback_url: Option<String>,
+}
+
+/// What the interactive flow collects for a withdrawal
+#[derive(Debug, Clone, Deserialize)]
+pub struct WithdrawalDetails {
+    /// cNGN the user will send
+    pub amount: String,
+    pub account_name: String,
+    pub account_number: String,
+    pub bank_code: String,
+    #[serde(default)]
+    pub bank_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DepositSubmitted {
+    pub transaction: Sep24Transaction,
+    /// Provider checkout page the user pays on
+    pub payment_url: Option<String>,
+}
+
+/// Ways `GET /transaction` can identify a transaction
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionLookup {
+    pub id: Option<String>,
+    pub stellar_transaction_id: Option<String>,
+    pub external_transaction_id: Option<String>,
+}
+
+/// Filters for `GET /transactions`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionsQuery {
+    pub asset_code: Option<String>,
+    pub kind: Option<String>,
+    pub no_older_than: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: Option<i64>,
+    pub paging_id: Option<String>,
+}
+
+/// A transaction as SEP-24 describes it
+#[derive(Debug, Clone, Serialize)]
+pub struct Sep24Transaction {
+    pub id: String,
+    pub kind: Sep24Kind,
+    pub status: Sep24Status,
+    pub more_info_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_in: Option<String>,
+    pub amount_in_asset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_out: Option<String>,
+    pub amount_out_asset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_fee: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_fee_asset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote_id: Option<String>,
+    pub started_at: String,
+    pub updated_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stellar_transaction_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_transaction_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub refunded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdraw_anchor_account: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdraw_memo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdraw_memo_type: Option<&'static str>,
+}
+
+/// SEP-24 fields kept under `metadata.sep24`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Sep24Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lang: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requested_amount: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amount_fee: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amount_fee_asset: Option<String>,
+    /// SEP-38 firm quote
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quote_id: Option<String>,
+    /// Indicative quote from `OnrampQuoteService::create_quote`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    onramp_quote_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payment_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    withdraw_memo: Option<String>,
+}
+
+impl Sep24Metadata {
+    fn from_transaction(tx: &Transaction) -> Self {
+        tx.metadata
+            .get("sep24")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({ "sep24": self })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Service
+// ---------------------------------------------------------------------------
+
+pub struct Sep24Service {
+    config: Sep24Config,
+    transactions: TransactionRepository,
+    deposit_memos: DepositMemoRepository,
+    quotes: Arc<OnrampQuoteService>,
+    orchestrator: Arc<PaymentOrchestrator>,
+    exchange_rates: Arc<ExchangeRateService>,
+    auth: Arc<AuthService>,
+    kyc: Option<Arc<KycService>>,
+}
+
+impl Sep24Service {
+    pub fn new(
+        config: Sep24Config,
+        pool: PgPool,
+        quotes: Arc<OnrampQuoteService>,
+        orchestrator: Arc<PaymentOrchestrator>,
+        exchange_rates: Arc<ExchangeRateService>,
+        auth: Arc<AuthService>,
+    ) -> Self {
+        Self {
+            config,
+            transactions: TransactionRepository::new(pool.clone()),
+            deposit_memos: DepositMemoRepository::new(pool),
+            quotes,
+            orchestrator,
+            exchange_rates,
+            auth,
+            kyc: None,
+        }
+    }
+
+    /// Hold withdrawals to the limits of the account's KYC tier. Deposits
+    /// are held to them by the quote service when they are priced.
+    pub fn with_kyc(mut self, kyc: Arc<KycService>) -> Self {
+        self.kyc = Some(kyc);
+        self
+    }
+
+    pub fn config(&self) -> &Sep24Config {
+        &self.config
+    }
+
+    pub fn info(&self) -> Sep24Info {
+        let asset = |kind: Sep24Kind| {
+            let (min, max) = self.config.limits(kind);
+            let mut assets = HashMap::new();
+            assets.insert(
+                self.config.asset_code.clone(),
+                AssetInfo {
+                    enabled: true,
+                    min_amount: min.to_string(),
+                    max_amount: max.map(ToString::to_string),
+                },
+            );
+            assets
+        };
+        Sep24Info {
+            deposit: asset(Sep24Kind::Deposit),
+            withdraw: asset(Sep24Kind::Withdrawal),
+            fee: json!({ "enabled": false }),
+            features: FeatureFlags {
+                account_creation: false,
+                claimable_balances: false,
+            },
+        }
+    }
+
+    /// Open an incomplete deposit or withdrawal for `account` and return the
+    /// interactive URL that collects the rest
+    pub async fn start(
+        &self,
+        kind: Sep24Kind,
+        account: &str,
+        request: InteractiveRequest,
+    ) -> Result<InteractiveResponse, Sep24Error> {
+        if request.asset_code != self.config.asset_code {
+            return Err(Sep24Error::InvalidRequest(format!(
+                "unsupported asset {}",
+                request.asset_code
+            )));
+        }
+        if let Some(issuer) = request.asset_issuer.as_deref() {
+            if issuer != self.config.asset_issuer {
+                return Err(Sep24Error::InvalidRequest(
+                    "asset_issuer does not match the served asset".to_string(),
+                ));
+            }
+        }
+        if let Some(requested) = request.account.as_deref() {
+            if requested != account {
+                return Err(Sep24Error::InvalidRequest(
+                    "account must be the authenticated account".to_string(),
+                ));
+            }
+        }
+        let mut requested_amount = match request.amount.as_deref() {
+            Some(amount) => Some(self.check_amount(kind, amount)?),
+            None => None,
+        };
+        if let Some(quote_id) = request.quote_id.as_deref() {
+            let quote = self.firm_quote(account, kind, quote_id).await?;
+            let sell_amount = quote.sell_amount.unwrap_or_default();
+            if requested_amount.as_ref().is_some_and(|a| *a != sell_amount) {
+                return Err(Sep24Error::InvalidRequest(
+                    "amount does not match the quote".to_string(),
+                ));
+            }
+            requested_amount = Some(sell_amount);
+        }
+
+        let (from_currency, to_currency) = match kind {
+            Sep24Kind::Deposit => ("NGN", self.config.asset_code.as_str()),
+            Sep24Kind::Withdrawal => (self.config.asset_code.as_str(), "NGN"),
+        };
+        let metadata = Sep24Metadata {
+            lang: request.lang,
+            requested_amount: requested_amount.map(|a| a.to_string()),
+            quote_id: request.quote_id,
+            ..Default::default()
+        };
+        let tx = self
+            .transactions
+            .create_transaction(
+                account,
+                kind.transaction_type(),
+                from_currency,
+                to_currency,
+                BigDecimal::zero(),
+                BigDecimal::zero(),
+                BigDecimal::zero(),
+                INCOMPLETE,
+                None,
+                None,
+                metadata.to_json(),
+            )
+            .await?;
+
+        let token = self.interactive_token(account)?;
+        let id = tx.transaction_id.to_string();
+        info!(transaction_id = %id, kind = kind.as_str(), account, "SEP-24 transaction opened");
+
+        Ok(InteractiveResponse {
+            r#type: "interactive_customer_info_needed",
+            url: format!(
+                "{}?transaction_id={}&token={}",
+                self.config.interactive_url, id, token
+            ),
+            id,
+        })
+    }
+
+    /// Price an incomplete deposit and start the fiat payment for it
+    pub async fn submit_deposit(
+        &self,
+        id: &str,
+        account: &str,
+        details: DepositDetails,
+    ) -> Result<DepositSubmitted, Sep24Error> {
+        let tx = self.incomplete(id, account, Sep24Kind::Deposit).await?;
+        self.check_amount(Sep24Kind::Deposit, &details.amount_ngn.to_string())?;
+        if details.email.trim().is_empty() {
+            return Err(Sep24Error::InvalidRequest("email is required".to_string()));
+        }
+
+        let mut metadata = Sep24Metadata::from_transaction(&tx);
+        let amount_ngn = BigDecimal::from(details.amount_ngn);
+        let mut firm_quote_id = None;
+        let (cngn, provider) = match metadata.quote_id.clone() {
+            Some(quote_id) => {
+                let quote = self
+                    .firm_quote_for_amount(account, Sep24Kind::Deposit, &quote_id, &amount_ngn)
+                    .await?;
+                firm_quote_id = Some(quote.quote_id);
+                metadata.amount_fee = quote.fee_total.map(|fee| fee.to_string());
+                metadata.amount_fee_asset = Some(self.config.stellar_asset());
+                (
+                    quote.buy_amount.unwrap_or_default(),
+                    details.provider.trim().to_lowercase(),
+                )
+            }
+            None => {
+                let quote = self
+                    .quotes
+                    .create_quote(OnrampQuoteRequest {
+                        amount_ngn: details.amount_ngn,
+                        wallet_address: account.to_string(),
+                        provider: details.provider.clone(),
+                        chain: None,
+                    })
+                    .await
+                    .map_err(Sep24Error::Quote)?;
+                metadata.amount_fee = Some(quote.fees.total_fee_ngn.to_string());
+                metadata.amount_fee_asset = Some(NGN_ASSET.to_string());
+                metadata.onramp_quote_id = Some(quote.quote_id);
+                (
+                    BigDecimal::from(quote.output.amount_cngn),
+                    quote.input.provider,
+                )
+            }
+        };
+
+        let payment = self
+            .orchestrator
+            .initiate_payment(PaymentInitiationRequest {
+                wallet_address: account.to_string(),
+                amount: amount_ngn.clone(),
+                currency: "NGN".to_string(),
+                payment_method: details.payment_method.unwrap_or(PaymentMethod::Card),
+                customer_email: Some(details.email),
+                customer_phone: details.phone,
+                callback_url: details.callback_url,
+                idempotency_key: Some(format!("sep24:{}", tx.transaction_id)),
+                metadata: Some(json!({ "sep24_transaction_id": tx.transaction_id })),
+                preferred_provider: None,
+                transaction_reference: None,
+            })
+            .await
+            .map_err(Sep24Error::Payment)?;
+
+        metadata.payment_url = payment.payment_url.clone();
+
+        // The quote is spent with the fill, once the payment exists
+        let tx = self
+            .fill(
+                account,
+                tx.transaction_id,
+                IncompleteFill {
+                    from_amount: amount_ngn,
+                    to_amount: cngn.clone(),
+                    cngn_amount: cngn,
+                    status: OrchestrationState::PendingPayment.to_db_status(),
+                    payment_provider: Some(&provider),
+                    payment_reference: Some(&payment.transaction_reference),
+                    metadata: metadata.to_json(),
+                    firm_quote_id,
+                    daily_limit: None,
+                },
+            )
+            .await?;
+
+        info!(
+            transaction_id = %tx.transaction_id,
+            payment_reference = %payment.transaction_reference,
+            "SEP-24 deposit submitted"
+        );
+        Ok(DepositSubmitted {
+            transaction: self.describe(&tx),
+            payment_url: payment.payment_url,
+        })
+    }
+
+    /// Price an incomplete withdrawal and turn it into an offramp waiting for
+    /// the user's cNGN
+    pub async fn submit_withdrawal(
+        &self,
+        id: &str,
+        account: &str,
+        details: WithdrawalDetails,
+    ) -> Result<Sep24Transaction, Sep24Error> {
+        let tx = self.incomplete(id, account, Sep24Kind::Withdrawal).await?;
+        let amount = self.check_amount(Sep24Kind::Withdrawal, &details.amount)?;
+        for (field, value) in [
+            ("account_name", &details.account_name),
+            ("account_number", &details.account_number),
+            ("bank_code", &details.bank_code),
+        ] {
+            if value.trim().is_empty() {
+                return Err(Sep24Error::InvalidRequest(format!("{} is required", field)));
+            }
+        }
+
+        let mut sep24 = Sep24Metadata::from_transaction(&tx);
+        let mut firm_quote_id = None;
+        let payout = match sep24.quote_id.clone() {
+            Some(quote_id) => {
+                let quote = self
+                    .firm_quote_for_amount(account, Sep24Kind::Withdrawal, &quote_id, &amount)
+                    .await?;
+                firm_quote_id = Some(quote.quote_id);
+                sep24.amount_fee = quote.fee_total.map(|fee| fee.to_string());
+                quote.buy_amount.unwrap_or_default()
+            }
+            None => {
+                if let Some(kyc) = &self.kyc {
+                    kyc.check_limit(account, &amount)
+                        .await
+                        .map_err(Sep24Error::Quote)?;
+                }
+                let conversion = self
+                    .exchange_rates
+                    .calculate_conversion(ConversionRequest {
+                        from_currency: "cNGN".to_string(),
+                        to_currency: "NGN".to_string(),
+                        amount: amount.clone(),
+                        direction: ConversionDirection::Sell,
+                    })
+                    .await?;
+                sep24.amount_fee = Some(conversion.fees.total_fees);
+                BigDecimal::from_str(&conversion.net_amount)
+                    .ok()
+                    .filter(|v| *v > BigDecimal::zero())
+                    .ok_or_else(|| {
+                        Sep24Error::InvalidRequest("amount does not cover the fees".to_string())
+                    })?
+            }
+        };
+        sep24.amount_fee_asset = Some(NGN_ASSET.to_string());
+
+        let reference = self.deposit_memos.assign(tx.transaction_id).await?;
+
+        let mut offramp = OfframpMetadata::new(
+            details.account_name.trim().to_string(),
+            details.account_number.trim().to_string(),
+            details.bank_code.trim().to_string(),
+        );
+        offramp.bank_name = details.bank_name;
+        let mut metadata = offramp.to_json();
+        sep24.withdraw_memo = Some(reference.memo);
+        metadata["sep24"] = json!(sep24);
+
+        let tx = self
+            .fill(
+                account,
+                tx.transaction_id,
+                IncompleteFill {
+                    from_amount: amount.clone(),
+                    to_amount: payout,
+                    cngn_amount: amount,
+                    status: OfframpState::PendingPayment.as_str(),
+                    payment_provider: None,
+                    payment_reference: None,
+                    metadata,
+                    firm_quote_id,
+                    daily_limit: None,
+                },
+            )
+            .await?;
+
+        info!(transaction_id = %tx.transaction_id, "SEP-24 withdrawal submitted");
+        Ok(self.describe(&tx))
+    }
+
+    /// `GET /transaction` for the authenticated account
+    pub async fn transaction(
+        &self,
+        account: &str,
+        lookup: TransactionLookup,
+    ) -> Result<Sep24Transaction, Sep24Error> {
+        let tx = if let Some(id) = lookup.id.as_deref() {
+            self.find(id).await?
+        } else if let Some(hash) = lookup.stellar_transaction_id.as_deref() {
+            self.transactions.find_by_blockchain_hash(hash).await?
+        } else if let Some(reference) = lookup.external_transaction_id.as_deref() {
+            self.transactions
+                .find_by_payment_reference(reference)
+                .await?
+        } else {
+            return Err(Sep24Error::InvalidRequest(
+                "id, stellar_transaction_id or external_transaction_id is required".to_string(),
+            ));
+        };
+
+        tx.filter(|tx| {
+            tx.wallet_address == account && Sep24Kind::from_transaction_type(&tx.r#type).is_some()
+        })
+        .map(|tx| self.describe(&tx))
+        .ok_or(Sep24Error::NotFound)
+    }
+
+    /// `GET /transactions` for the authenticated account, newest first
+    pub async fn transactions(
+        &self,
+        account: &str,
+        query: TransactionsQuery,
+    ) -> Result<Vec<Sep24Transaction>, Sep24Error> {
+        if let Some(asset_code) = query.asset_code.as_deref() {
+            if asset_code != self.config.asset_code {
+                return Err(Sep24Error::InvalidRequest(format!(
+                    "unsupported asset {}",
+                    asset_code
+                )));
+            }
+        }
+        let types = match query.kind.as_deref() {
+            Some(kind) => vec![Sep24Kind::parse(kind)
+                .ok_or_else(|| Sep24Error::InvalidRequest(format!("unknown kind {}", kind)))?
+                .transaction_type()],
+            None => vec![
+                Sep24Kind::Deposit.transaction_type(),
+                Sep24Kind::Withdrawal.transaction_type(),
+            ],
+        };
+        let paging_id = match query.paging_id.as_deref() {
+            Some(id) => Some(
+                Uuid::parse_str(id)
+                    .map_err(|_| Sep24Error::InvalidRequest("invalid paging_id".to_string()))?,
+            ),
+            None => None,
+        };
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .clamp(1, MAX_LIST_LIMIT);
+
+        let rows = self
+            .transactions
+            .find_by_wallet_and_types(account, &types, query.no_older_than, paging_id, limit)
+            .await?;
+        Ok(rows.iter().map(|tx| self.describe(tx)).collect())
+    }
+
+    /// Describe a stored onramp or offramp in SEP-24 terms
+    pub fn describe(&self, tx: &Transaction) -> Sep24Transaction {
+        let kind = Sep24Kind::from_transaction_type(&tx.r#type).unwrap_or(Sep24Kind::Deposit);
+        let status = Sep24Status::of(kind, &tx.status);
+        let sep24 = Sep24Metadata::from_transaction(tx);
+        let metadata_str = |key: &str| {
+            tx.metadata
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        let id = tx.transaction_id.to_string();
+        let fiat = NGN_ASSET.to_string();
+
+        let (amount_in, amount_out, amount_in_asset, amount_out_asset) = match kind {
+            Sep24Kind::Deposit => (
+                &tx.from_amount,
+                &tx.cngn_amount,
+                fiat,
+                self.config.stellar_asset(),
+            ),
+            Sep24Kind::Withdrawal => (
+                &tx.from_amount,
+                &tx.to_amount,
+                self.config.stellar_asset(),
+                fiat,
+            ),
+        };
+        let (stellar_transaction_id, external_transaction_id) = match kind {
+            Sep24Kind::Deposit => (
+                tx.blockchain_tx_hash
+                    .clone()
+                    .or_else(|| metadata_str("submitted_hash")),
+                tx.payment_reference.clone(),
+            ),
+            Sep24Kind::Withdrawal => (
+                metadata_str("stellar_tx_hash").or_else(|| tx.blockchain_tx_hash.clone()),
+                metadata_str("provider_reference"),
+            ),
+        };
+        let withdraw_memo = sep24
+            .withdraw_memo
+            .filter(|_| kind == Sep24Kind::Withdrawal);
+
+        Sep24Transaction {
+            more_info_url: format!("{}?transaction_id={}", self.config.interactive_url, id),
+            id,
+            kind,
+            status,
+            amount_in: positive(amount_in),
+            amount_in_asset,
+            amount_out: positive(amount_out),
+            amount_out_asset,
+            amount_fee: sep24.amount_fee,
+            amount_fee_asset: sep24.amount_fee_asset,
+            quote_id: sep24.quote_id,
+            started_at: tx.created_at.to_rfc3339(),
+            updated_at: tx.updated_at.to_rfc3339(),
+            completed_at: matches!(status, Sep24Status::Completed | Sep24Status::Refunded)
+                .then(|| tx.updated_at.to_rfc3339()),
+            stellar_transaction_id,
+            external_transaction_id,
+            message: tx
+                .error_message
+                .clone()
+                .or_else(|| metadata_str("failure_reason")),
+            refunded: status == Sep24Status::Refunded,
+            from: (kind == Sep24Kind::Withdrawal).then(|| tx.wallet_address.clone()),
+            to: (kind == Sep24Kind::Deposit).then(|| tx.wallet_address.clone()),
+            withdraw_anchor_account: withdraw_memo
+                .as_ref()
+                .map(|_| self.config.withdraw_account.clone()),
+            withdraw_memo_type: withdraw_memo.as_ref().map(|_| "text"),
+            withdraw_memo,
+        }
+    }
+
+    async fn incomplete(
+        &self,
+        id: &str,
+        account: &str,
+        kind: Sep24Kind,
+    ) -> Result<Transaction, Sep24Error> {
+        let tx = self
+            .find(id)
+            .await?
+            .filter(|tx| tx.wallet_address == account && tx.r#type == kind.transaction_type())
+            .ok_or(Sep24Error::NotFound)?;
+        if tx.status != INCOMPLETE {
+            return Err(Sep24Error::InvalidRequest(
+                "transaction details were already submitted".to_string(),
+            ));
+        }
+        Ok(tx)
+    }
+
+    /// A firm quote `account` may open a `kind` transaction with
+    async fn firm_quote(
+        &self,
+        account: &str,
+        kind: Sep24Kind,
+        quote_id: &str,
+    ) -> Result<OnrampQuote, Sep24Error> {
+        let invalid = |reason: &str| Sep24Error::InvalidRequest(format!("quote {}", reason));
+        let id = Uuid::parse_str(quote_id).map_err(|_| invalid("not found"))?;
+        let quote = self
+            .quotes
+            .firm_quote(id)
+            .await
+            .map_err(Sep24Error::Quote)?
+            .filter(|quote| quote.wallet_address.as_deref() == Some(account))
+            .ok_or_else(|| invalid("not found"))?;
+
+        let direction = match kind {
+            Sep24Kind::Deposit => QuoteDirection::Onramp,
+            Sep24Kind::Withdrawal => QuoteDirection::Offramp,
+        };
+        if quote.direction != direction.as_str() {
+            return Err(invalid("is for the other direction"));
+        }
+        if quote.context.as_deref() != Some("sep24") {
+            return Err(invalid("was not issued for SEP-24"));
+        }
+        if quote.status != "pending" || quote.expires_at <= chrono::Utc::now() {
+            return Err(invalid("has expired or was already used"));
+        }
+        Ok(quote)
+    }
+
+    /// Check `amount` against the transaction's firm quote. The quote is
+    /// consumed by `fill`.
+    async fn firm_quote_for_amount(
+        &self,
+        account: &str,
+        kind: Sep24Kind,
+        quote_id: &str,
+        amount: &BigDecimal,
+    ) -> Result<OnrampQuote, Sep24Error> {
+        let quote = self.firm_quote(account, kind, quote_id).await?;
+        if quote.sell_amount.as_ref() != Some(amount) {
+            return Err(Sep24Error::InvalidRequest(
+                "amount does not match the quote".to_string(),
+            ));
+        }
+        Ok(quote)
+    }
+
+    /// Write the order, re-checking the account's KYC limits against the
+    /// final amount in the same database transaction
+    async fn fill(
+        &self,
+        account: &str,
+        transaction_id: Uuid,
+        mut fill: IncompleteFill<'_>,
+    ) -> Result<Transaction, Sep24Error> {
+        let limits = match &self.kyc {
+            Some(kyc) => Some(kyc.limits(account).await.map_err(Sep24Error::Quote)?),
+            None => None,
+        };
+        if let Some(limits) = &limits {
+            limits
+                .check_transaction(&fill.from_amount)
+                .map_err(Sep24Error::Quote)?;
+            fill.daily_limit = limits.daily();
+        }
+
+        match self
+            .transactions
+            .fill_incomplete(transaction_id, &fill)
+            .await?
+        {
+            FillOutcome::Filled(tx) => Ok(tx),
+            FillOutcome::LimitExceeded { limit } => Err(Sep24Error::Quote(limit_exceeded(
+                limits.map_or(KycTier::Unverified, |limits| limits.tier),
+                &fill.from_amount,
+                &limit,
+            ))),
+            FillOutcome::NotIncomplete => Err(Sep24Error::NotFound),
+            FillOutcome::QuoteUnavailable => Err(Sep24Error::Quote(AppError::new(
+                AppErrorKind::Domain(DomainError::RateExpired {
+                    quote_id: fill.firm_quote_id.unwrap_or_default().to_string(),
+                }),
+            ))),
+        }
+    }
+
+    /// Look a transaction up by id; ids that are not UUIDs are simply unknown
+    async fn find(&self, id: &str) -> Result<Option<Transaction>, Sep24Error> {
+        if Uuid::parse_str(id).is_err() {
+            return Ok(None);
+        }
+        Ok(self.transactions.find_by_id(id).await?)
+    }
+
+    fn check_amount(&self, kind: Sep24Kind, amount: &str) -> Result<BigDecimal, Sep24Error> {
+        let amount = BigDecimal::from_str(amount.trim())
+            .ok()
+            .filter(|a| *a > BigDecimal::zero())
+            .ok_or_else(|| {
+                Sep24Error::InvalidRequest("amount must be a positive number".to_string())
+            })?;
+        let (min, max) = self.config.limits(kind);
+        if amount < *min {
+            return Err(Sep24Error::InvalidRequest(format!(
+                "amount is below the minimum of {}",
+                min
+            )));
+        }
+        if let Some(max) = max.filter(|max| amount > **max) {
+            return Err(Sep24Error::InvalidRequest(format!(
+                "amount is above the maximum of {}",
+                max
+            )));
+        }
+        Ok(amount)
+    }
+
+    /// Short-lived token the interactive flow submits the details with
+    fn interactive_token(&self, account: &str) -> Result<String, Sep24Error> {
+        let jwt = self
+            .auth
+            .jwt()
+            .ok_or_else(|| AuthError::Unavailable("JWT".to_string()))?;
+        let mut claims = jwt.claims_for(account, &[scopes::TRANSFERS_WRITE]);
+        claims.exp = claims.iat + self.config.interactive_token_ttl.as_secs() as i64;
+        Ok(jwt.encode(&claims)?)
+    }
+}
+
+fn positive(amount: &BigDecimal) -> Option<String> {
+    (*amount > BigDecimal::zero()).then(|| amount.normalized().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deposit_statuses() {
+        let of = |status| Sep24Status::of(Sep24Kind::Deposit, status);
+        assert_eq!(of(INCOMPLETE), Sep24Status::Incomplete);
+        assert_eq!(of("pending"), Sep24Status::PendingUserTransferStart);
+        assert_eq!(of("payment_confirmed"), Sep24Status::PendingAnchor);
+        assert_eq!(of("processing"), Sep24Status::PendingStellar);
+        assert_eq!(of("completed"), Sep24Status::Completed);
+        assert_eq!(of("refunded"), Sep24Status::Refunded);
+        assert_eq!(of("failed"), Sep24Status::Error);
+    }
+
+    #[test]
+    fn test_withdrawal_statuses() {
+        let of = |status| Sep24Status::of(Sep24Kind::Withdrawal, status);
+        assert_eq!(of("pending_payment"), Sep24Status::PendingUserTransferStart);
+        assert_eq!(of("cngn_received"), Sep24Status::PendingAnchor);
+        assert_eq!(of("transfer_pending"), Sep24Status::PendingExternal);
+        assert_eq!(of("completed"), Sep24Status::Completed);
+        assert_eq!(of("expired"), Sep24Status::Expired);
+        assert_eq!(
+            Sep24Status::PendingUserTransferStart.as_str(),
+            "pending_user_transfer_start"
+        );
+    }
+
+    #[test]
+    fn test_kind_round_trip() {
+        for kind in [Sep24Kind::Deposit, Sep24Kind::Withdrawal] {
+            assert_eq!(Sep24Kind::parse(kind.as_str()), Some(kind));
+            assert_eq!(
+                Sep24Kind::from_transaction_type(kind.transaction_type()),
+                Some(kind)
+            );
+        }
+        assert_eq!(Sep24Kind::from_transaction_type("bill_payment"), None);
+    }
+}
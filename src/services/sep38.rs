@@ -0,0 +1,492 @@
+//! SEP-38 anchor RFQ
+//!
+//! Indicative prices and firm quotes for the two pairs we serve, NGN → cNGN
+//! and cNGN → NGN, priced by `OnrampQuoteService`. Firm quotes are stored in
+//! `onramp_quotes` against the SEP-10 account that requested them and are
+//! consumed when a SEP-24 transaction using them is submitted.
+
+use crate::database::onramp_quote_repository::OnrampQuote;
+use crate::error::AppError;
+use crate::services::onramp_quote::{
+    OnrampQuoteService, PricedQuote, QuoteAmount, QuoteDirection, QUOTE_TTL_SECS,
+};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Contexts a firm quote can be requested for; only SEP-24 transfers exist here
+pub const SUPPORTED_CONTEXTS: &[&str] = &["sep24"];
+
+/// Fiat side of every pair
+pub const NGN_ASSET: &str = "iso4217:NGN";
+
+/// How NGN can be paid in
+const SELL_DELIVERY_METHODS: &[(&str, &str)] = &[
+    ("card", "Pay with a debit or credit card"),
+    ("bank_transfer", "Pay by bank transfer"),
+];
+
+/// How NGN can be paid out
+const BUY_DELIVERY_METHODS: &[(&str, &str)] =
+    &[("bank_account", "Paid out to a Nigerian bank account")];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Sep38Error {
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    #[error("quote not found")]
+    NotFound,
+
+    #[error("{}", .0.user_message())]
+    Quote(AppError),
+}
+
+impl Sep38Error {
+    /// HTTP status for this error
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Sep38Error::InvalidRequest(_) => 400,
+            Sep38Error::NotFound => 404,
+            Sep38Error::Quote(e) => e.status_code(),
+        }
+    }
+}
+
+impl From<AppError> for Sep38Error {
+    fn from(e: AppError) -> Self {
+        Sep38Error::Quote(e)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryMethod {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetEntry {
+    pub asset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sell_delivery_methods: Option<Vec<DeliveryMethod>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buy_delivery_methods: Option<Vec<DeliveryMethod>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_codes: Option<Vec<&'static str>>,
+}
+
+/// `GET /info`
+#[derive(Debug, Clone, Serialize)]
+pub struct Sep38Info {
+    pub assets: Vec<AssetEntry>,
+}
+
+/// Query of `GET /prices`; exactly one of the sell or buy side is given
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PricesQuery {
+    pub sell_asset: Option<String>,
+    pub sell_amount: Option<String>,
+    pub buy_asset: Option<String>,
+    pub buy_amount: Option<String>,
+    pub sell_delivery_method: Option<String>,
+    pub buy_delivery_method: Option<String>,
+    pub country_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetPrice {
+    pub asset: String,
+    pub price: String,
+    pub decimals: i64,
+}
+
+/// Response of `GET /prices`
+#[derive(Debug, Clone, Serialize)]
+pub struct PricesResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buy_assets: Option<Vec<AssetPrice>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sell_assets: Option<Vec<AssetPrice>>,
+}
+
+/// Query of `GET /price` and body of `POST /quote`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PriceRequest {
+    pub sell_asset: String,
+    pub buy_asset: String,
+    pub sell_amount: Option<String>,
+    pub buy_amount: Option<String>,
+    pub context: Option<String>,
+    pub sell_delivery_method: Option<String>,
+    pub buy_delivery_method: Option<String>,
+    pub country_code: Option<String>,
+    /// Only used by `POST /quote`: the latest expiry the client accepts
+    pub expire_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Fee {
+    pub total: String,
+    pub asset: String,
+}
+
+/// Response of `GET /price`
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceResponse {
+    pub total_price: String,
+    pub price: String,
+    pub sell_amount: String,
+    pub buy_amount: String,
+    pub fee: Fee,
+}
+
+/// A firm quote as SEP-38 describes it
+#[derive(Debug, Clone, Serialize)]
+pub struct Sep38Quote {
+    pub id: String,
+    pub expires_at: String,
+    pub total_price: String,
+    pub price: String,
+    pub sell_asset: String,
+    pub sell_amount: String,
+    pub buy_asset: String,
+    pub buy_amount: String,
+    pub fee: Fee,
+}
+
+// ---------------------------------------------------------------------------
+// Service
+// ---------------------------------------------------------------------------
+
+pub struct Sep38Service {
+    quotes: Arc<OnrampQuoteService>,
+    /// SEP-38 identifier of cNGN, `stellar:<code>:<issuer>`
+    stellar_asset: String,
+}
+
+impl Sep38Service {
+    pub fn new(quotes: Arc<OnrampQuoteService>, asset_code: &str, asset_issuer: &str) -> Self {
+        Self {
+            quotes,
+            stellar_asset: format!("stellar:{}:{}", asset_code, asset_issuer),
+        }
+    }
+
+    pub fn stellar_asset(&self) -> &str {
+        &self.stellar_asset
+    }
+
+    pub fn info(&self) -> Sep38Info {
+        let methods = |list: &[(&'static str, &'static str)]| -> Vec<DeliveryMethod> {
+            list.iter()
+                .map(|&(name, description)| DeliveryMethod { name, description })
+                .collect()
+        };
+        Sep38Info {
+            assets: vec![
+                AssetEntry {
+                    asset: self.stellar_asset.clone(),
+                    sell_delivery_methods: None,
+                    buy_delivery_methods: None,
+                    country_codes: None,
+                },
+                AssetEntry {
+                    asset: NGN_ASSET.to_string(),
+                    sell_delivery_methods: Some(methods(SELL_DELIVERY_METHODS)),
+                    buy_delivery_methods: Some(methods(BUY_DELIVERY_METHODS)),
+                    country_codes: Some(vec!["NGA"]),
+                },
+            ],
+        }
+    }
+
+    /// `GET /prices`: indicative prices against the one counter asset
+    pub async fn prices(&self, query: PricesQuery) -> Result<PricesResponse, Sep38Error> {
+        self.check_country(query.country_code.as_deref())?;
+        match (query.sell_asset, query.buy_asset) {
+            (Some(sell_asset), None) => {
+                let amount = parse_amount("sell_amount", query.sell_amount.as_deref())?;
+                let direction = self.direction(&sell_asset, None)?;
+                self.check_delivery(
+                    direction,
+                    query.sell_delivery_method.as_deref(),
+                    query.buy_delivery_method.as_deref(),
+                )?;
+                let quote = self
+                    .quotes
+                    .price(direction, QuoteAmount::Sell(amount))
+                    .await?;
+                Ok(PricesResponse {
+                    buy_assets: Some(vec![AssetPrice {
+                        asset: self.asset_id(direction.buy_currency()),
+                        price: quote.total_price().to_string(),
+                        decimals: QuoteDirection::decimals(direction.buy_currency()),
+                    }]),
+                    sell_assets: None,
+                })
+            }
+            (None, Some(buy_asset)) => {
+                let amount = parse_amount("buy_amount", query.buy_amount.as_deref())?;
+                let direction = self.direction_to(&buy_asset)?;
+                self.check_delivery(
+                    direction,
+                    query.sell_delivery_method.as_deref(),
+                    query.buy_delivery_method.as_deref(),
+                )?;
+                let quote = self
+                    .quotes
+                    .price(direction, QuoteAmount::Buy(amount))
+                    .await?;
+                Ok(PricesResponse {
+                    buy_assets: None,
+                    sell_assets: Some(vec![AssetPrice {
+                        asset: self.asset_id(direction.sell_currency()),
+                        price: quote.total_price().to_string(),
+                        decimals: QuoteDirection::decimals(direction.sell_currency()),
+                    }]),
+                })
+            }
+            _ => Err(Sep38Error::InvalidRequest(
+                "exactly one of sell_asset or buy_asset is required".to_string(),
+            )),
+        }
+    }
+
+    /// `GET /price`: an indicative price for one pair
+    pub async fn price(&self, request: PriceRequest) -> Result<PriceResponse, Sep38Error> {
+        if let Some(context) = request.context.as_deref() {
+            check_context(context)?;
+        }
+        let quote = self.priced(&request).await?;
+        Ok(PriceResponse {
+            total_price: quote.total_price().to_string(),
+            price: quote.price().to_string(),
+            sell_amount: quote.sell_amount.to_string(),
+            buy_amount: quote.buy_amount.to_string(),
+            fee: Fee {
+                total: quote.fee.to_string(),
+                asset: self.asset_id(quote.direction.buy_currency()),
+            },
+        })
+    }
+
+    /// `POST /quote`: a firm quote for `account`
+    pub async fn create_quote(
+        &self,
+        account: &str,
+        request: PriceRequest,
+    ) -> Result<Sep38Quote, Sep38Error> {
+        let context = request
+            .context
+            .as_deref()
+            .ok_or_else(|| Sep38Error::InvalidRequest("context is required".to_string()))?;
+        check_context(context)?;
+        if let Some(expire_after) = request.expire_after {
+            let latest = Utc::now() + chrono::Duration::seconds(QUOTE_TTL_SECS as i64);
+            if expire_after > latest {
+                return Err(Sep38Error::InvalidRequest(format!(
+                    "quotes are held for at most {} seconds",
+                    QUOTE_TTL_SECS
+                )));
+            }
+        }
+
+        let quote = self.priced(&request).await?;
+        let stored = self
+            .quotes
+            .create_firm_quote(account, context, &quote)
+            .await?;
+        self.describe(&stored).ok_or(Sep38Error::NotFound)
+    }
+
+    /// `GET /quote/{id}`: a firm quote issued to `account`
+    pub async fn quote(&self, account: &str, id: &str) -> Result<Sep38Quote, Sep38Error> {
+        let quote = self.owned_quote(account, id).await?;
+        self.describe(&quote).ok_or(Sep38Error::NotFound)
+    }
+
+    /// A firm quote issued to `account`, whatever its status
+    pub async fn owned_quote(&self, account: &str, id: &str) -> Result<OnrampQuote, Sep38Error> {
+        let quote_id = Uuid::parse_str(id).map_err(|_| Sep38Error::NotFound)?;
+        self.quotes
+            .firm_quote(quote_id)
+            .await?
+            .filter(|quote| quote.wallet_address.as_deref() == Some(account))
+            .ok_or(Sep38Error::NotFound)
+    }
+
+    /// Describe a stored firm quote; `None` for quotes not issued through SEP-38
+    pub fn describe(&self, quote: &OnrampQuote) -> Option<Sep38Quote> {
+        let direction = QuoteDirection::parse(&quote.direction)?;
+        let priced = PricedQuote {
+            direction,
+            sell_amount: quote.sell_amount.clone()?,
+            buy_amount: quote.buy_amount.clone()?,
+            fee: quote.fee_total.clone()?,
+            rate: quote.exchange_rate.clone(),
+        };
+        Some(Sep38Quote {
+            id: quote.quote_id.to_string(),
+            expires_at: quote.expires_at.to_rfc3339(),
+            total_price: priced.total_price().to_string(),
+            price: priced.price().to_string(),
+            sell_asset: self.asset_id(direction.sell_currency()),
+            sell_amount: priced.sell_amount.to_string(),
+            buy_asset: self.asset_id(direction.buy_currency()),
+            buy_amount: priced.buy_amount.to_string(),
+            fee: Fee {
+                total: priced.fee.to_string(),
+                asset: self.asset_id(direction.buy_currency()),
+            },
+        })
+    }
+
+    async fn priced(&self, request: &PriceRequest) -> Result<PricedQuote, Sep38Error> {
+        self.check_country(request.country_code.as_deref())?;
+        let direction = self.direction(&request.sell_asset, Some(&request.buy_asset))?;
+        self.check_delivery(
+            direction,
+            request.sell_delivery_method.as_deref(),
+            request.buy_delivery_method.as_deref(),
+        )?;
+        let amount = match (&request.sell_amount, &request.buy_amount) {
+            (Some(sell), None) => QuoteAmount::Sell(parse_amount("sell_amount", Some(sell))?),
+            (None, Some(buy)) => QuoteAmount::Buy(parse_amount("buy_amount", Some(buy))?),
+            _ => {
+                return Err(Sep38Error::InvalidRequest(
+                    "exactly one of sell_amount or buy_amount is required".to_string(),
+                ))
+            }
+        };
+        Ok(self.quotes.price(direction, amount).await?)
+    }
+
+    /// Direction of a conversion selling `sell_asset`, checked against
+    /// `buy_asset` when given
+    fn direction(
+        &self,
+        sell_asset: &str,
+        buy_asset: Option<&str>,
+    ) -> Result<QuoteDirection, Sep38Error> {
+        let direction = if sell_asset == NGN_ASSET {
+            QuoteDirection::Onramp
+        } else if sell_asset == self.stellar_asset {
+            QuoteDirection::Offramp
+        } else {
+            return Err(Sep38Error::InvalidRequest(format!(
+                "unsupported asset {}",
+                sell_asset
+            )));
+        };
+        if let Some(buy_asset) = buy_asset {
+            if buy_asset != self.asset_id(direction.buy_currency()) {
+                return Err(Sep38Error::InvalidRequest(format!(
+                    "{} cannot be exchanged for {}",
+                    sell_asset, buy_asset
+                )));
+            }
+        }
+        Ok(direction)
+    }
+
+    /// Direction of a conversion buying `buy_asset`
+    fn direction_to(&self, buy_asset: &str) -> Result<QuoteDirection, Sep38Error> {
+        if buy_asset == self.stellar_asset {
+            Ok(QuoteDirection::Onramp)
+        } else if buy_asset == NGN_ASSET {
+            Ok(QuoteDirection::Offramp)
+        } else {
+            Err(Sep38Error::InvalidRequest(format!(
+                "unsupported asset {}",
+                buy_asset
+            )))
+        }
+    }
+
+    fn asset_id(&self, currency: &str) -> String {
+        if currency == "NGN" {
+            NGN_ASSET.to_string()
+        } else {
+            self.stellar_asset.clone()
+        }
+    }
+
+    fn check_delivery(
+        &self,
+        direction: QuoteDirection,
+        sell_method: Option<&str>,
+        buy_method: Option<&str>,
+    ) -> Result<(), Sep38Error> {
+        let (method, known) = match direction {
+            // Only the NGN side has delivery methods
+            QuoteDirection::Onramp => (sell_method, SELL_DELIVERY_METHODS),
+            QuoteDirection::Offramp => (buy_method, BUY_DELIVERY_METHODS),
+        };
+        match method {
+            Some(method) if !known.iter().any(|(name, _)| *name == method) => Err(
+                Sep38Error::InvalidRequest(format!("unsupported delivery method {}", method)),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_country(&self, country_code: Option<&str>) -> Result<(), Sep38Error> {
+        match country_code {
+            Some(code) if code != "NGA" => Err(Sep38Error::InvalidRequest(format!(
+                "unsupported country {}",
+                code
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_context(context: &str) -> Result<(), Sep38Error> {
+    if SUPPORTED_CONTEXTS.contains(&context) {
+        Ok(())
+    } else {
+        Err(Sep38Error::InvalidRequest(format!(
+            "unsupported context {}",
+            context
+        )))
+    }
+}
+
+fn parse_amount(field: &str, value: Option<&str>) -> Result<BigDecimal, Sep38Error> {
+    let value =
+        value.ok_or_else(|| Sep38Error::InvalidRequest(format!("{} is required", field)))?;
+    BigDecimal::from_str(value.trim())
+        .ok()
+        .filter(|amount| *amount > BigDecimal::zero())
+        .ok_or_else(|| Sep38Error::InvalidRequest(format!("{} must be a positive number", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(
+            parse_amount("sell_amount", Some("100.5")).unwrap(),
+            BigDecimal::from_str("100.5").unwrap()
+        );
+        assert!(parse_amount("sell_amount", Some("0")).is_err());
+        assert!(parse_amount("sell_amount", Some("abc")).is_err());
+        assert!(parse_amount("sell_amount", None).is_err());
+    }
+
+    #[test]
+    fn test_contexts() {
+        assert!(check_context("sep24").is_ok());
+        assert!(check_context("sep31").is_err());
+    }
+}
@@ -0,0 +1,388 @@
+//! SEP-1 stellar.toml
+//!
+//! Wallets discover the anchor through `/.well-known/stellar.toml`. The file
+//! is rendered once at boot from the cNGN asset configuration for the network
+//! we run on, the SEP-10 signing key, the SEP servers that are enabled and
+//! the `ORG_*` organization settings. It is validated before it is served so
+//! a testnet issuer or key configured for mainnet stops the server instead of
+//! being published.
+
+use crate::chains::stellar::client::StellarClient;
+use crate::chains::stellar::config::StellarNetwork;
+use crate::chains::stellar::errors::StellarError;
+use crate::chains::stellar::trustline::CngnAssetConfig;
+use crate::chains::stellar::types::is_valid_account_id;
+use std::fmt::Write;
+
+/// SEP-1 version the file follows
+const SEP1_VERSION: &str = "2.7.0";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StellarTomlError {
+    #[error("invalid stellar.toml configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+
+    #[error(
+        "cNGN issuer {issuer} does not exist on {network}; is it the issuer for the other network?"
+    )]
+    IssuerNotOnNetwork { issuer: String, network: String },
+
+    #[error("could not check the issuer on the network: {0}")]
+    Network(StellarError),
+}
+
+/// SEP servers a stellar.toml can point to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    WebAuth,
+    TransferServerSep24,
+    QuoteServer,
+    KycServer,
+}
+
+impl Endpoint {
+    /// Key of the endpoint in stellar.toml
+    pub fn key(&self) -> &'static str {
+        match self {
+            Endpoint::WebAuth => "WEB_AUTH_ENDPOINT",
+            Endpoint::TransferServerSep24 => "TRANSFER_SERVER_SEP0024",
+            Endpoint::QuoteServer => "ANCHOR_QUOTE_SERVER",
+            Endpoint::KycServer => "KYC_SERVER",
+        }
+    }
+}
+
+/// Organization details published in the `[DOCUMENTATION]` table
+#[derive(Debug, Clone, Default)]
+pub struct OrgDocumentation {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub logo: Option<String>,
+    pub description: Option<String>,
+    pub physical_address: Option<String>,
+    pub official_email: Option<String>,
+    pub support_email: Option<String>,
+    pub twitter: Option<String>,
+}
+
+impl OrgDocumentation {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self {
+            name: var("ORG_NAME"),
+            url: var("ORG_URL"),
+            logo: var("ORG_LOGO"),
+            description: var("ORG_DESCRIPTION"),
+            physical_address: var("ORG_PHYSICAL_ADDRESS"),
+            official_email: var("ORG_OFFICIAL_EMAIL"),
+            support_email: var("ORG_SUPPORT_EMAIL"),
+            twitter: var("ORG_TWITTER"),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, Option<&String>); 8] {
+        [
+            ("ORG_NAME", self.name.as_ref()),
+            ("ORG_URL", self.url.as_ref()),
+            ("ORG_LOGO", self.logo.as_ref()),
+            ("ORG_DESCRIPTION", self.description.as_ref()),
+            ("ORG_PHYSICAL_ADDRESS", self.physical_address.as_ref()),
+            ("ORG_OFFICIAL_EMAIL", self.official_email.as_ref()),
+            ("ORG_SUPPORT_EMAIL", self.support_email.as_ref()),
+            ("ORG_TWITTER", self.twitter.as_ref()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StellarTomlConfig {
+    /// Public URL this API is reached at; SEP endpoints are published under it
+    pub base_url: String,
+    /// Extra accounts the organization controls, published as `ACCOUNTS`
+    pub accounts: Vec<String>,
+    pub asset_name: String,
+    pub asset_description: Option<String>,
+    pub documentation: OrgDocumentation,
+}
+
+impl StellarTomlConfig {
+    /// Load from environment. Returns `None` when no public base URL is set.
+    pub fn from_env() -> Option<Self> {
+        let base_url = std::env::var("STELLAR_TOML_BASE_URL").ok()?;
+        Some(Self {
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            accounts: std::env::var("STELLAR_TOML_ACCOUNTS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|a| !a.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            asset_name: std::env::var("CNGN_ASSET_NAME")
+                .unwrap_or_else(|_| "Nigerian Naira stablecoin".to_string()),
+            asset_description: std::env::var("CNGN_ASSET_DESCRIPTION").ok(),
+            documentation: OrgDocumentation::from_env(),
+        })
+    }
+}
+
+pub struct StellarToml {
+    config: StellarTomlConfig,
+    network: StellarNetwork,
+    asset: CngnAssetConfig,
+    signing_key: Option<String>,
+    endpoints: Vec<(Endpoint, String)>,
+}
+
+impl StellarToml {
+    pub fn new(config: StellarTomlConfig, network: StellarNetwork, asset: CngnAssetConfig) -> Self {
+        Self {
+            config,
+            network,
+            asset,
+            signing_key: None,
+            endpoints: Vec::new(),
+        }
+    }
+
+    /// Publish the SEP-10 signing key
+    pub fn with_signing_key(mut self, signing_key: impl Into<String>) -> Self {
+        self.signing_key = Some(signing_key.into());
+        self
+    }
+
+    /// Publish an enabled SEP server, mounted at `path` under the base URL
+    pub fn with_endpoint(mut self, endpoint: Endpoint, path: &str) -> Self {
+        self.endpoints
+            .push((endpoint, format!("{}{}", self.config.base_url, path)));
+        self
+    }
+
+    pub fn issuer(&self) -> &str {
+        self.asset.issuer_for_network(&self.network)
+    }
+
+    /// Check the configuration against the network we run on
+    pub fn validate(&self) -> Result<(), StellarTomlError> {
+        let mainnet = matches!(self.network, StellarNetwork::Mainnet);
+        let mut problems = Vec::new();
+
+        let issuer = self.issuer();
+        if !is_valid_account_id(issuer) {
+            problems.push(format!(
+                "cNGN issuer for {} is not a Stellar account: {}",
+                network_name(&self.network),
+                issuer
+            ));
+        }
+        if mainnet && self.asset.issuer_mainnet == self.asset.issuer_testnet {
+            problems.push("CNGN_ISSUER_MAINNET is the same account as CNGN_ISSUER_TESTNET".into());
+        }
+        if let Some(key) = &self.signing_key {
+            if !is_valid_account_id(key) {
+                problems.push(format!("signing key is not a Stellar account: {}", key));
+            }
+        }
+        for account in &self.config.accounts {
+            if !is_valid_account_id(account) {
+                problems.push(format!(
+                    "STELLAR_TOML_ACCOUNTS entry is not a Stellar account: {}",
+                    account
+                ));
+            }
+        }
+
+        let scheme_ok =
+            |url: &str| url.starts_with("https://") || (!mainnet && url.starts_with("http://"));
+        if !scheme_ok(&self.config.base_url) {
+            problems.push(format!(
+                "STELLAR_TOML_BASE_URL must be an {} URL: {}",
+                if mainnet { "https" } else { "http(s)" },
+                self.config.base_url
+            ));
+        }
+        if let Some(url) = &self.config.documentation.url {
+            if !url.starts_with("https://") {
+                problems.push(format!("ORG_URL must be an https URL: {}", url));
+            }
+        }
+        if mainnet && self.config.documentation.name.is_none() {
+            problems.push("ORG_NAME is required on mainnet".into());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(StellarTomlError::Invalid(problems))
+        }
+    }
+
+    /// Check that the issuer exists on the network we run on, which catches
+    /// an issuer configured for the other network
+    pub async fn verify_on_network(&self, client: &StellarClient) -> Result<(), StellarTomlError> {
+        match client.get_account(self.issuer()).await {
+            Ok(_) => Ok(()),
+            Err(StellarError::AccountNotFound { .. }) => {
+                Err(StellarTomlError::IssuerNotOnNetwork {
+                    issuer: self.issuer().to_string(),
+                    network: network_name(&self.network).to_string(),
+                })
+            }
+            Err(e) => Err(StellarTomlError::Network(e)),
+        }
+    }
+
+    /// Render the file
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "VERSION = {}", toml_string(SEP1_VERSION));
+        let _ = writeln!(
+            out,
+            "NETWORK_PASSPHRASE = {}",
+            toml_string(self.network.network_passphrase())
+        );
+        if let Some(key) = &self.signing_key {
+            let _ = writeln!(out, "SIGNING_KEY = {}", toml_string(key));
+        }
+        for (endpoint, url) in &self.endpoints {
+            let _ = writeln!(out, "{} = {}", endpoint.key(), toml_string(url));
+        }
+        if !self.config.accounts.is_empty() {
+            let accounts: Vec<String> = self
+                .config
+                .accounts
+                .iter()
+                .map(|a| toml_string(a))
+                .collect();
+            let _ = writeln!(out, "ACCOUNTS = [{}]", accounts.join(", "));
+        }
+
+        let documentation: Vec<_> = self
+            .config
+            .documentation
+            .fields()
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|v| (key, v)))
+            .collect();
+        if !documentation.is_empty() {
+            out.push_str("\n[DOCUMENTATION]\n");
+            for (key, value) in documentation {
+                let _ = writeln!(out, "{} = {}", key, toml_string(value));
+            }
+        }
+
+        out.push_str("\n[[CURRENCIES]]\n");
+        let _ = writeln!(out, "code = {}", toml_string(&self.asset.asset_code));
+        let _ = writeln!(out, "issuer = {}", toml_string(self.issuer()));
+        let status = match self.network {
+            StellarNetwork::Mainnet => "live",
+            StellarNetwork::Testnet => "test",
+        };
+        let _ = writeln!(out, "status = {}", toml_string(status));
+        out.push_str("display_decimals = 2\n");
+        let _ = writeln!(out, "name = {}", toml_string(&self.config.asset_name));
+        if let Some(desc) = &self.config.asset_description {
+            let _ = writeln!(out, "desc = {}", toml_string(desc));
+        }
+        out.push_str("is_asset_anchored = true\n");
+        out.push_str("anchor_asset_type = \"fiat\"\n");
+        out.push_str("anchor_asset = \"NGN\"\n");
+        out
+    }
+}
+
+fn network_name(network: &StellarNetwork) -> &'static str {
+    match network {
+        StellarNetwork::Testnet => "testnet",
+        StellarNetwork::Mainnet => "mainnet",
+    }
+}
+
+/// Quote a TOML basic string
+fn toml_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TESTNET_ISSUER: &str = "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7";
+
+    fn toml(network: StellarNetwork, base_url: &str) -> StellarToml {
+        StellarToml::new(
+            StellarTomlConfig {
+                base_url: base_url.to_string(),
+                accounts: Vec::new(),
+                asset_name: "Nigerian Naira stablecoin".to_string(),
+                asset_description: None,
+                documentation: OrgDocumentation {
+                    name: Some("Aframp \"Labs\"".to_string()),
+                    ..Default::default()
+                },
+            },
+            network,
+            CngnAssetConfig {
+                asset_code: "cNGN".to_string(),
+                issuer_testnet: TESTNET_ISSUER.to_string(),
+                issuer_mainnet: TESTNET_ISSUER.to_string(),
+                default_limit: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_render_testnet() {
+        let rendered = toml(StellarNetwork::Testnet, "http://localhost:8000")
+            .with_signing_key(TESTNET_ISSUER)
+            .with_endpoint(Endpoint::WebAuth, "/auth")
+            .render();
+        assert!(rendered.contains("NETWORK_PASSPHRASE = \"Test SDF Network ; September 2015\""));
+        assert!(rendered.contains("WEB_AUTH_ENDPOINT = \"http://localhost:8000/auth\""));
+        assert!(rendered.contains("ORG_NAME = \"Aframp \\\"Labs\\\"\""));
+        assert!(rendered.contains("status = \"test\""));
+        assert!(!rendered.contains("TRANSFER_SERVER_SEP0024"));
+    }
+
+    #[test]
+    fn test_validate_testnet() {
+        assert!(toml(StellarNetwork::Testnet, "http://localhost:8000")
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_catches_testnet_config_on_mainnet() {
+        let Err(StellarTomlError::Invalid(problems)) =
+            toml(StellarNetwork::Mainnet, "http://localhost:8000").validate()
+        else {
+            panic!("expected validation to fail");
+        };
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("CNGN_ISSUER_MAINNET"));
+        assert!(problems[1].contains("https"));
+    }
+
+    #[test]
+    fn test_toml_string_escapes() {
+        assert_eq!(toml_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+}
@@ -0,0 +1,687 @@
+//! Treasury
+//!
+//! Keeps the hot wallet that pays refunds and payouts between configured cNGN
+//! and XLM thresholds. A balance above its maximum is swept to the cold wallet;
+//! a balance below its minimum raises an alert and a top-up request for
+//! operators to fund from cold storage, before payouts start failing.
+
+use crate::chains::stellar::{
+    client::StellarClient,
+    errors::StellarError,
+    payment::CngnMemo,
+    submission::{PaymentAsset, QueuedPayment, SubmissionQueue},
+    trustline::CngnAssetConfig,
+    types::{extract_asset_balance, is_valid_account_id},
+};
+use crate::database::error::DatabaseError;
+use crate::database::treasury_repository::{
+    NewTreasuryTransfer, TreasuryAlert, TreasuryAlertKind, TreasuryRepository, TreasuryTransfer,
+    TreasuryTransferKind,
+};
+use bigdecimal::{BigDecimal, Zero};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+pub const XLM: &str = "XLM";
+
+const SWEEP_MEMO: &str = "treasury sweep";
+const RECENT_TRANSFER_LIMIT: i64 = 20;
+/// Stellar amounts carry seven decimal places
+const STELLAR_SCALE: i64 = 7;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TreasuryError {
+    #[error("treasury configuration: {0}")]
+    Config(String),
+
+    #[error("stellar error: {0}")]
+    Stellar(#[from] StellarError),
+
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// Range an asset's hot wallet balance should stay within. Either bound may
+/// be unset.
+#[derive(Debug, Clone, Default)]
+pub struct BalanceThresholds {
+    pub min: Option<BigDecimal>,
+    pub max: Option<BigDecimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum BalanceState {
+    Ok,
+    BelowMin {
+        threshold: BigDecimal,
+        shortfall: BigDecimal,
+    },
+    AboveMax {
+        threshold: BigDecimal,
+        excess: BigDecimal,
+    },
+}
+
+impl BalanceThresholds {
+    fn from_env(prefix: &str) -> Result<Self, TreasuryError> {
+        Ok(Self {
+            min: amount_from_env(&format!("{}_MIN_BALANCE", prefix))?,
+            max: amount_from_env(&format!("{}_MAX_BALANCE", prefix))?,
+        })
+    }
+
+    /// Balance rebalancing aims for: midway between the bounds, or the one
+    /// bound that is set
+    pub fn target(&self) -> Option<BigDecimal> {
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) => {
+                Some(((min + max) / BigDecimal::from(2)).with_scale(STELLAR_SCALE))
+            }
+            (Some(bound), None) | (None, Some(bound)) => Some(bound.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Where `balance` sits, with the amount that would bring it to target
+    pub fn evaluate(&self, balance: &BigDecimal) -> BalanceState {
+        let Some(target) = self.target() else {
+            return BalanceState::Ok;
+        };
+        if let Some(min) = self.min.as_ref().filter(|min| balance < *min) {
+            return BalanceState::BelowMin {
+                threshold: min.clone(),
+                shortfall: (&target - balance).with_scale(STELLAR_SCALE),
+            };
+        }
+        if let Some(max) = self.max.as_ref().filter(|max| balance > *max) {
+            return BalanceState::AboveMax {
+                threshold: max.clone(),
+                excess: (balance - &target).with_scale(STELLAR_SCALE),
+            };
+        }
+        BalanceState::Ok
+    }
+
+    fn validate(&self, asset: &str) -> Result<(), TreasuryError> {
+        for bound in [&self.min, &self.max].into_iter().flatten() {
+            if *bound < BigDecimal::zero() {
+                return Err(TreasuryError::Config(format!(
+                    "{} thresholds must not be negative",
+                    asset
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            if min > max {
+                return Err(TreasuryError::Config(format!(
+                    "{} minimum balance {} is above its maximum {}",
+                    asset, min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TreasuryConfig {
+    /// Wallet that pays refunds and payouts
+    pub hot_wallet_address: String,
+    /// Sweep destination and top-up source. Without it only alerts are raised.
+    pub cold_wallet_address: Option<String>,
+    pub check_interval: Duration,
+    pub cngn: BalanceThresholds,
+    pub xlm: BalanceThresholds,
+    /// Submit queued sweeps through the hot wallet's submission queue
+    pub auto_sweep: bool,
+    /// Receives a JSON `{"text": ...}` POST for each new alert
+    pub alert_webhook_url: Option<String>,
+}
+
+impl Default for TreasuryConfig {
+    fn default() -> Self {
+        Self {
+            hot_wallet_address: String::new(),
+            cold_wallet_address: None,
+            check_interval: Duration::from_secs(60),
+            cngn: BalanceThresholds::default(),
+            xlm: BalanceThresholds::default(),
+            auto_sweep: true,
+            alert_webhook_url: None,
+        }
+    }
+}
+
+impl TreasuryConfig {
+    pub fn from_env() -> Result<Self, TreasuryError> {
+        let mut cfg = Self::default();
+
+        cfg.hot_wallet_address = std::env::var("TREASURY_HOT_WALLET_ADDRESS")
+            .or_else(|_| std::env::var("SYSTEM_WALLET_ADDRESS"))
+            .unwrap_or_default();
+        cfg.cold_wallet_address = std::env::var("TREASURY_COLD_WALLET_ADDRESS")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+
+        cfg.check_interval = Duration::from_secs(
+            std::env::var("TREASURY_CHECK_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.check_interval.as_secs()),
+        );
+
+        cfg.cngn = BalanceThresholds::from_env("TREASURY_CNGN")?;
+        cfg.xlm = BalanceThresholds::from_env("TREASURY_XLM")?;
+
+        cfg.auto_sweep = std::env::var("TREASURY_AUTO_SWEEP")
+            .map(|v| v.to_lowercase() != "false")
+            .unwrap_or(cfg.auto_sweep);
+        cfg.alert_webhook_url = std::env::var("TREASURY_ALERT_WEBHOOK_URL")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+
+        Ok(cfg)
+    }
+
+    pub fn validate(&self) -> Result<(), TreasuryError> {
+        if !is_valid_account_id(&self.hot_wallet_address) {
+            return Err(TreasuryError::Config(
+                "TREASURY_HOT_WALLET_ADDRESS or SYSTEM_WALLET_ADDRESS must be a G... account"
+                    .to_string(),
+            ));
+        }
+        if let Some(cold) = &self.cold_wallet_address {
+            if !is_valid_account_id(cold) {
+                return Err(TreasuryError::Config(
+                    "TREASURY_COLD_WALLET_ADDRESS must be a G... account".to_string(),
+                ));
+            }
+            if *cold == self.hot_wallet_address {
+                return Err(TreasuryError::Config(
+                    "cold wallet must differ from the hot wallet".to_string(),
+                ));
+            }
+        }
+        self.cngn.validate("cNGN")?;
+        self.xlm.validate(XLM)
+    }
+}
+
+fn amount_from_env(key: &str) -> Result<Option<BigDecimal>, TreasuryError> {
+    match std::env::var(key) {
+        Ok(v) if !v.trim().is_empty() => BigDecimal::from_str(v.trim())
+            .map(Some)
+            .map_err(|_| TreasuryError::Config(format!("{} must be a decimal amount", key))),
+        _ => Ok(None),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Service
+// ---------------------------------------------------------------------------
+
+/// One asset's hot wallet balance against its thresholds
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetPosition {
+    pub asset: String,
+    pub balance: BigDecimal,
+    pub min: Option<BigDecimal>,
+    pub max: Option<BigDecimal>,
+    pub target: Option<BigDecimal>,
+    #[serde(flatten)]
+    pub state: BalanceState,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TreasurySnapshot {
+    pub hot_wallet: String,
+    pub cold_wallet: Option<String>,
+    pub auto_sweep: bool,
+    pub positions: Vec<AssetPosition>,
+    pub open_alerts: Vec<TreasuryAlert>,
+    pub recent_transfers: Vec<TreasuryTransfer>,
+}
+
+pub struct TreasuryService {
+    stellar_client: StellarClient,
+    repo: TreasuryRepository,
+    config: TreasuryConfig,
+    asset_config: CngnAssetConfig,
+    payout_queue: Option<SubmissionQueue>,
+    http: reqwest::Client,
+}
+
+impl TreasuryService {
+    pub fn new(stellar_client: StellarClient, pool: PgPool, config: TreasuryConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Self {
+            stellar_client,
+            repo: TreasuryRepository::new(pool),
+            config,
+            asset_config: CngnAssetConfig::from_env(),
+            payout_queue: None,
+            http,
+        }
+    }
+
+    /// Hot wallet submission queue used to send sweeps
+    pub fn with_submission_queue(mut self, queue: Option<SubmissionQueue>) -> Self {
+        self.payout_queue = queue;
+        self
+    }
+
+    pub fn config(&self) -> &TreasuryConfig {
+        &self.config
+    }
+
+    /// Current hot wallet balances against their thresholds
+    pub async fn positions(&self) -> Result<Vec<AssetPosition>, TreasuryError> {
+        let account = self
+            .stellar_client
+            .get_account(&self.config.hot_wallet_address)
+            .await?;
+        let issuer = self
+            .asset_config
+            .issuer_for_network(self.stellar_client.network());
+
+        let cngn_balance = extract_asset_balance(
+            &account.balances,
+            &self.asset_config.asset_code,
+            Some(issuer),
+        );
+        let xlm_balance = account
+            .balances
+            .iter()
+            .find(|b| b.asset_type == "native")
+            .map(|b| b.balance.clone());
+
+        Ok(vec![
+            position(
+                &self.asset_config.asset_code,
+                cngn_balance,
+                &self.config.cngn,
+            ),
+            position(XLM, xlm_balance, &self.config.xlm),
+        ])
+    }
+
+    /// Compare balances with their thresholds, opening or resolving alerts
+    /// and queueing the sweeps and top-ups that bring them back to target
+    pub async fn check(&self) -> Result<Vec<AssetPosition>, TreasuryError> {
+        let positions = self.positions().await?;
+        for position in &positions {
+            self.reconcile(position).await?;
+        }
+        Ok(positions)
+    }
+
+    async fn reconcile(&self, position: &AssetPosition) -> Result<(), TreasuryError> {
+        let hot = self.config.hot_wallet_address.as_str();
+        let asset = position.asset.as_str();
+
+        match &position.state {
+            BalanceState::Ok => {
+                if self.repo.resolve_alerts(hot, asset, None).await? > 0 {
+                    info!(asset, balance = %position.balance, "treasury balance back within thresholds");
+                }
+                self.repo
+                    .settle_queued(TreasuryTransferKind::TopUp, asset, hot, "completed")
+                    .await?;
+                self.repo
+                    .settle_queued(TreasuryTransferKind::Sweep, asset, hot, "cancelled")
+                    .await?;
+            }
+            BalanceState::BelowMin {
+                threshold,
+                shortfall,
+            } => {
+                self.repo
+                    .resolve_alerts(hot, asset, Some(TreasuryAlertKind::BelowMin))
+                    .await?;
+                self.repo
+                    .settle_queued(TreasuryTransferKind::Sweep, asset, hot, "cancelled")
+                    .await?;
+                self.raise(position, TreasuryAlertKind::BelowMin, threshold)
+                    .await?;
+                self.queue(TreasuryTransferKind::TopUp, asset, shortfall)
+                    .await?;
+            }
+            BalanceState::AboveMax { threshold, excess } => {
+                self.repo
+                    .resolve_alerts(hot, asset, Some(TreasuryAlertKind::AboveMax))
+                    .await?;
+                self.repo
+                    .settle_queued(TreasuryTransferKind::TopUp, asset, hot, "completed")
+                    .await?;
+                self.raise(position, TreasuryAlertKind::AboveMax, threshold)
+                    .await?;
+                self.queue(TreasuryTransferKind::Sweep, asset, excess)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn raise(
+        &self,
+        position: &AssetPosition,
+        kind: TreasuryAlertKind,
+        threshold: &BigDecimal,
+    ) -> Result<(), TreasuryError> {
+        let Some(alert) = self
+            .repo
+            .open_alert(
+                &self.config.hot_wallet_address,
+                &position.asset,
+                kind,
+                &position.balance,
+                threshold,
+            )
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let text = match kind {
+            TreasuryAlertKind::BelowMin => format!(
+                "Hot wallet {} balance {} is below the minimum {}",
+                alert.asset, alert.balance, alert.threshold
+            ),
+            TreasuryAlertKind::AboveMax => format!(
+                "Hot wallet {} balance {} is above the maximum {}",
+                alert.asset, alert.balance, alert.threshold
+            ),
+        };
+        warn!(
+            alert_id = %alert.id,
+            wallet = %alert.wallet_address,
+            asset = %alert.asset,
+            kind = kind.as_str(),
+            balance = %alert.balance,
+            threshold = %alert.threshold,
+            "treasury alert: {}",
+            text
+        );
+
+        if let Some(url) = &self.config.alert_webhook_url {
+            let body = json!({
+                "text": text,
+                "alert_id": alert.id,
+                "wallet": alert.wallet_address,
+                "asset": alert.asset,
+                "kind": alert.kind,
+                "balance": alert.balance.to_string(),
+                "threshold": alert.threshold.to_string(),
+            });
+            match self.http.post(url).json(&body).send().await {
+                Ok(resp) if resp.status().is_success() => {}
+                Ok(resp) => {
+                    warn!(alert_id = %alert.id, status = %resp.status(), "treasury alert webhook rejected")
+                }
+                Err(e) => warn!(alert_id = %alert.id, error = %e, "treasury alert webhook failed"),
+            }
+        }
+        Ok(())
+    }
+
+    async fn queue(
+        &self,
+        kind: TreasuryTransferKind,
+        asset: &str,
+        amount: &BigDecimal,
+    ) -> Result<(), TreasuryError> {
+        let Some(cold) = self.config.cold_wallet_address.clone() else {
+            return Ok(());
+        };
+        if *amount <= BigDecimal::zero() {
+            return Ok(());
+        }
+        let hot = self.config.hot_wallet_address.clone();
+        let (from_address, to_address) = match kind {
+            TreasuryTransferKind::Sweep => (hot, cold),
+            TreasuryTransferKind::TopUp => (cold, hot),
+        };
+
+        if let Some(transfer) = self
+            .repo
+            .queue_transfer(&NewTreasuryTransfer {
+                kind,
+                asset: asset.to_string(),
+                amount: amount.clone(),
+                from_address,
+                to_address,
+            })
+            .await?
+        {
+            info!(
+                transfer_id = %transfer.id,
+                kind = %transfer.kind,
+                asset = %transfer.asset,
+                amount = %transfer.amount,
+                from = %transfer.from_address,
+                to = %transfer.to_address,
+                "treasury transfer queued"
+            );
+        }
+        Ok(())
+    }
+
+    /// Submit queued sweeps. Returns how many were sent.
+    pub async fn execute_sweeps(&self) -> Result<usize, TreasuryError> {
+        if !self.config.auto_sweep {
+            return Ok(0);
+        }
+        let queued = self
+            .repo
+            .find_queued(TreasuryTransferKind::Sweep, &self.config.hot_wallet_address)
+            .await?;
+        if queued.is_empty() {
+            return Ok(0);
+        }
+        let Some(queue) = self.payout_queue.clone() else {
+            debug!(
+                count = queued.len(),
+                "sweeps queued but no hot wallet signer configured"
+            );
+            return Ok(0);
+        };
+
+        let mut submitted = 0;
+        for transfer in queued {
+            if self.execute_sweep(&transfer, &queue).await? {
+                submitted += 1;
+            }
+        }
+        Ok(submitted)
+    }
+
+    async fn execute_sweep(
+        &self,
+        transfer: &TreasuryTransfer,
+        queue: &SubmissionQueue,
+    ) -> Result<bool, TreasuryError> {
+        if transfer.from_address != queue.account_id() {
+            debug!(transfer_id = %transfer.id, "sweep source is not the hot wallet signer");
+            return Ok(false);
+        }
+        // Claimed before sending so another monitor can't send it twice
+        if self.repo.mark_submitted(transfer.id).await?.is_none() {
+            return Ok(false);
+        }
+
+        let payment = QueuedPayment {
+            destination: transfer.to_address.clone(),
+            amount: transfer.amount.with_scale(STELLAR_SCALE).to_string(),
+            asset: if transfer.asset == XLM {
+                PaymentAsset::Native
+            } else {
+                PaymentAsset::Cngn
+            },
+            memo: CngnMemo::Text(SWEEP_MEMO.to_string()),
+        };
+        match queue.pay(payment).await {
+            Ok(submitted) => {
+                self.repo
+                    .finish_transfer(transfer.id, "completed", Some(&submitted.tx_hash), None)
+                    .await?;
+                info!(
+                    transfer_id = %transfer.id,
+                    asset = %transfer.asset,
+                    amount = %transfer.amount,
+                    tx_hash = %submitted.tx_hash,
+                    "treasury sweep completed"
+                );
+            }
+            Err(e) if e.nothing_sent() => {
+                // Rejections are final; anything else is retried next cycle
+                let status = if e.error.is_definitive_rejection() {
+                    "failed"
+                } else {
+                    "queued"
+                };
+                warn!(transfer_id = %transfer.id, status, error = %e, "treasury sweep not sent");
+                self.repo
+                    .finish_transfer(transfer.id, status, None, Some(&e.to_string()))
+                    .await?;
+                return Ok(false);
+            }
+            Err(e) => {
+                // Left in `submitted` so it is not sent twice
+                error!(
+                    transfer_id = %transfer.id,
+                    tx_hash = ?e.in_flight_hash,
+                    error = %e,
+                    "treasury sweep outcome unknown; check the hash on-chain before resolving"
+                );
+                self.repo
+                    .finish_transfer(
+                        transfer.id,
+                        "submitted",
+                        e.in_flight_hash.as_deref(),
+                        Some(&e.to_string()),
+                    )
+                    .await?;
+            }
+        }
+        Ok(true)
+    }
+
+    pub async fn snapshot(&self) -> Result<TreasurySnapshot, TreasuryError> {
+        let positions = self.positions().await?;
+        let hot = &self.config.hot_wallet_address;
+        Ok(TreasurySnapshot {
+            hot_wallet: hot.clone(),
+            cold_wallet: self.config.cold_wallet_address.clone(),
+            auto_sweep: self.config.auto_sweep,
+            positions,
+            open_alerts: self.repo.list_open_alerts(hot).await?,
+            recent_transfers: self
+                .repo
+                .recent_transfers(hot, RECENT_TRANSFER_LIMIT)
+                .await?,
+        })
+    }
+
+    /// Operator resolution of a queued or submitted transfer, e.g. marking a
+    /// top-up `completed` once it has been funded from cold storage
+    pub async fn resolve_transfer(
+        &self,
+        id: Uuid,
+        status: &str,
+        operator: &str,
+        note: Option<&str>,
+    ) -> Result<Option<TreasuryTransfer>, TreasuryError> {
+        Ok(self
+            .repo
+            .resolve_transfer(id, status, operator, note)
+            .await?)
+    }
+}
+
+fn position(asset: &str, balance: Option<String>, thresholds: &BalanceThresholds) -> AssetPosition {
+    let balance = balance
+        .and_then(|b| BigDecimal::from_str(&b).ok())
+        .unwrap_or_else(BigDecimal::zero);
+    AssetPosition {
+        asset: asset.to_string(),
+        state: thresholds.evaluate(&balance),
+        balance,
+        min: thresholds.min.clone(),
+        max: thresholds.max.clone(),
+        target: thresholds.target(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(v: &str) -> BigDecimal {
+        BigDecimal::from_str(v).unwrap()
+    }
+
+    fn thresholds(min: Option<&str>, max: Option<&str>) -> BalanceThresholds {
+        BalanceThresholds {
+            min: min.map(dec),
+            max: max.map(dec),
+        }
+    }
+
+    #[test]
+    fn test_target_is_midpoint_or_single_bound() {
+        assert_eq!(
+            thresholds(Some("1000"), Some("5000")).target(),
+            Some(dec("3000"))
+        );
+        assert_eq!(thresholds(Some("1000"), None).target(), Some(dec("1000")));
+        assert_eq!(thresholds(None, Some("5000")).target(), Some(dec("5000")));
+        assert_eq!(thresholds(None, None).target(), None);
+    }
+
+    #[test]
+    fn test_evaluate_brings_balance_to_target() {
+        let t = thresholds(Some("1000"), Some("5000"));
+        assert_eq!(t.evaluate(&dec("2500")), BalanceState::Ok);
+        assert_eq!(
+            t.evaluate(&dec("400")),
+            BalanceState::BelowMin {
+                threshold: dec("1000"),
+                shortfall: dec("2600"),
+            }
+        );
+        assert_eq!(
+            t.evaluate(&dec("7000.5")),
+            BalanceState::AboveMax {
+                threshold: dec("5000"),
+                excess: dec("4000.5"),
+            }
+        );
+        assert_eq!(thresholds(None, None).evaluate(&dec("0")), BalanceState::Ok);
+    }
+
+    #[test]
+    fn test_validate_rejects_inverted_or_negative_thresholds() {
+        assert!(thresholds(Some("10"), Some("5")).validate("cNGN").is_err());
+        assert!(thresholds(Some("-1"), None).validate("cNGN").is_err());
+        assert!(thresholds(Some("5"), Some("10")).validate("cNGN").is_ok());
+
+        let config = TreasuryConfig {
+            hot_wallet_address: "not-an-account".to_string(),
+            ..TreasuryConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -76,6 +76,12 @@ impl WebhookProcessor {
         // Extract event ID for idempotency
         let event_id = self.extract_event_id(&event.payload, provider_name);
 
+        // M-Pesa "signatures" are the callback secret or source IP, not a payload MAC
+        let stored_signature = match provider {
+            ProviderName::Mpesa => None,
+            _ => Some(signature),
+        };
+
         // Check idempotency - log event (will fail if duplicate)
         let webhook_event = self
             .webhook_repo
@@ -84,7 +90,7 @@ impl WebhookProcessor {
                 provider_name,
                 &event.event_type,
                 payload.clone(),
-                Some(signature),
+                stored_signature,
                 None,
             )
             .await
@@ -195,6 +201,14 @@ impl WebhookProcessor {
                         .map(|id| id.to_string())
                 })
                 .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            "mpesa" => payload
+                .get("Body")
+                .and_then(|b| b.get("stkCallback"))
+                .and_then(|c| c.get("CheckoutRequestID"))
+                .or_else(|| payload.get("Result").and_then(|r| r.get("ConversationID")))
+                .and_then(|v| v.as_str())
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
             _ => Uuid::new_v4().to_string(),
         }
     }
@@ -2,8 +2,10 @@ use super::types::{
     AccountInfo, BillPaymentRequest, BillPaymentResponse, PaymentStatus, ProcessingError,
 };
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::debug;
 
 // ---------------------------------------------------------------------------
@@ -54,6 +56,108 @@ pub fn get_backup_providers(bill_type: &str) -> Vec<&'static str> {
     }
 }
 
+/// Configured aggregators, keyed by the names used in [`get_primary_provider`]
+/// and [`get_backup_providers`]
+pub type BillProviders = HashMap<&'static str, Arc<dyn BillPaymentProvider>>;
+
+/// Provider names to try for a bill type, primary first
+pub fn provider_order(bill_type: &str) -> Vec<&'static str> {
+    let primary = get_primary_provider(bill_type);
+    let mut order = vec![primary];
+    order.extend(
+        get_backup_providers(bill_type)
+            .into_iter()
+            .filter(|name| *name != primary),
+    );
+    order
+}
+
+/// The configured providers for a bill type in the order they should be tried
+pub fn provider_chain(
+    providers: &BillProviders,
+    bill_type: &str,
+) -> Vec<(&'static str, Arc<dyn BillPaymentProvider>)> {
+    provider_order(bill_type)
+        .into_iter()
+        .filter_map(|name| providers.get(name).map(|p| (name, p.clone())))
+        .collect()
+}
+
+/// Error for a payment request that got no answer. Only a failed connection
+/// proves the provider never saw it; a timeout or dropped response may
+/// follow a payment that went through.
+fn payment_request_error(provider: &str, err: reqwest::Error) -> ProcessingError {
+    let reason = format!("payment request failed: {}", err);
+    if err.is_connect() {
+        ProcessingError::ProviderRejected {
+            provider: provider.to_string(),
+            reason,
+        }
+    } else {
+        ProcessingError::ProviderError {
+            provider: provider.to_string(),
+            reason,
+        }
+    }
+}
+
+/// Error for a payment the provider answered with a non-success status. A
+/// 4xx is a refusal, except request timeouts and conflicts, which can mean
+/// the reference was already paid; a 5xx leaves the outcome unknown.
+fn payment_status_error(provider: &str, status: StatusCode, body: &str) -> ProcessingError {
+    let reason = format!("payment failed with status: {} - {}", status, body);
+    let refused = status.is_client_error()
+        && status != StatusCode::REQUEST_TIMEOUT
+        && status != StatusCode::CONFLICT;
+    if refused {
+        ProcessingError::ProviderRejected {
+            provider: provider.to_string(),
+            reason,
+        }
+    } else {
+        ProcessingError::ProviderError {
+            provider: provider.to_string(),
+            reason,
+        }
+    }
+}
+
+/// Build adapters for every aggregator with credentials in the environment
+pub fn providers_from_env() -> BillProviders {
+    let mut providers: BillProviders = HashMap::new();
+
+    if let Ok(key) = std::env::var("FLUTTERWAVE_SECRET_KEY") {
+        // The adapter appends the API version itself
+        let base_url = std::env::var("FLUTTERWAVE_BASE_URL")
+            .unwrap_or_else(|_| "https://api.flutterwave.com".to_string());
+        let base_url = base_url.trim_end_matches('/').trim_end_matches("/v3");
+        if let Ok(adapter) = FlutterwaveAdapter::new(key, base_url.to_string()) {
+            providers.insert("flutterwave", Arc::new(adapter));
+        }
+    }
+
+    if let (Ok(key), Ok(secret)) = (
+        std::env::var("VTPASS_API_KEY"),
+        std::env::var("VTPASS_SECRET_KEY"),
+    ) {
+        let base_url = std::env::var("VTPASS_BASE_URL")
+            .unwrap_or_else(|_| "https://sandbox.vtpass.com".to_string());
+        if let Ok(adapter) = VTPassAdapter::new(key, secret, base_url) {
+            providers.insert("vtpass", Arc::new(adapter));
+        }
+    }
+
+    if let Ok(key) = std::env::var("PAYSTACK_SECRET_KEY") {
+        let base_url = std::env::var("PAYSTACK_BASE_URL")
+            .unwrap_or_else(|_| "https://api.paystack.co".to_string());
+        if let Ok(adapter) = PaystackAdapter::new(key, base_url) {
+            providers.insert("paystack", Arc::new(adapter));
+        }
+    }
+
+    providers
+}
+
 // ---------------------------------------------------------------------------
 // Flutterwave Adapter
 // ---------------------------------------------------------------------------
@@ -174,18 +278,12 @@ impl BillPaymentProvider for FlutterwaveAdapter {
             .json(&payload)
             .send()
             .await
-            .map_err(|e| ProcessingError::ProviderError {
-                provider: "flutterwave".to_string(),
-                reason: format!("payment request failed: {}", e),
-            })?;
+            .map_err(|e| payment_request_error("flutterwave", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_msg = response.text().await.unwrap_or_default();
-            return Err(ProcessingError::ProviderError {
-                provider: "flutterwave".to_string(),
-                reason: format!("payment failed with status: {} - {}", status, error_msg),
-            });
+            return Err(payment_status_error("flutterwave", status, &error_msg));
         }
 
         let data =
@@ -328,18 +426,12 @@ impl BillPaymentProvider for VTPassAdapter {
             .json(&payload)
             .send()
             .await
-            .map_err(|e| ProcessingError::ProviderError {
-                provider: "vtpass".to_string(),
-                reason: format!("payment request failed: {}", e),
-            })?;
+            .map_err(|e| payment_request_error("vtpass", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_msg = response.text().await.unwrap_or_default();
-            return Err(ProcessingError::ProviderError {
-                provider: "vtpass".to_string(),
-                reason: format!("payment failed with status: {} - {}", status, error_msg),
-            });
+            return Err(payment_status_error("vtpass", status, &error_msg));
         }
 
         let data =
@@ -462,18 +554,12 @@ impl BillPaymentProvider for PaystackAdapter {
             .json(&payload)
             .send()
             .await
-            .map_err(|e| ProcessingError::ProviderError {
-                provider: "paystack".to_string(),
-                reason: format!("payment request failed: {}", e),
-            })?;
+            .map_err(|e| payment_request_error("paystack", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_msg = response.text().await.unwrap_or_default();
-            return Err(ProcessingError::ProviderError {
-                provider: "paystack".to_string(),
-                reason: format!("payment failed with status: {} - {}", status, error_msg),
-            });
+            return Err(payment_status_error("paystack", status, &error_msg));
         }
 
         let data =
@@ -517,6 +603,33 @@ impl BillPaymentProvider for PaystackAdapter {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_only_refusals_are_rejections() {
+        let rejected = |status| {
+            matches!(
+                payment_status_error("vtpass", status, ""),
+                ProcessingError::ProviderRejected { .. }
+            )
+        };
+        assert!(rejected(StatusCode::BAD_REQUEST));
+        assert!(rejected(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!rejected(StatusCode::CONFLICT));
+        assert!(!rejected(StatusCode::REQUEST_TIMEOUT));
+        assert!(!rejected(StatusCode::BAD_GATEWAY));
+    }
+
+    #[test]
+    fn test_provider_order_starts_with_primary() {
+        assert_eq!(
+            provider_order("electricity"),
+            vec!["flutterwave", "vtpass", "paystack"]
+        );
+        assert_eq!(
+            provider_order("airtime"),
+            vec!["vtpass", "flutterwave", "paystack"]
+        );
+    }
+
     #[test]
     fn test_flutterwave_creation() {
         let result = FlutterwaveAdapter::new(
@@ -18,6 +18,11 @@ pub enum ProcessingError {
     #[error("provider error: {provider} - {reason}")]
     ProviderError { provider: String, reason: String },
 
+    /// The provider refused the payment outright, so no money moved and
+    /// another provider may be tried
+    #[error("provider rejected payment: {provider} - {reason}")]
+    ProviderRejected { provider: String, reason: String },
+
     #[error("amount mismatch: expected {expected}, got {actual}")]
     AmountMismatch { expected: String, actual: String },
 
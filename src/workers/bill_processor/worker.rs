@@ -0,0 +1,958 @@
+use super::account_verification::AccountVerifier;
+use super::payment_executor::PaymentExecutor;
+use super::providers::{provider_chain, BillPaymentProvider, BillProviders};
+use super::refund_handler::RefundHandler;
+use super::token_manager::TokenManager;
+use super::types::{
+    BillPaymentRequest, BillProcessingState, BillTransaction, ProcessingError, RetryConfig,
+    VerificationRequest,
+};
+use crate::chains::stellar::payment::CngnMemo;
+use crate::chains::stellar::submission::{QueuedPayment, SubmissionQueue};
+use crate::database::bill_payment_repository::BillPaymentRepository;
+use crate::database::error::DatabaseError;
+use crate::database::repository::Repository;
+use crate::database::transaction_repository::{Transaction, TransactionRepository};
+use crate::services::merchant_webhooks::{events, MerchantWebhookService};
+use crate::services::notification::{NotificationService, NotificationType};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+/// Stellar text memos are limited to 28 bytes
+const MAX_MEMO_LEN: usize = 28;
+
+/// Transaction status while the bill is with this worker. It is not one of
+/// the statuses `TransactionMonitorWorker` polls, so the monitor's Stellar
+/// timeout does not fail bills that are waiting on a provider.
+const TX_STATUS_BILL_PROCESSING: &str = "bill_processing";
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct BillProcessorConfig {
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    pub retry: RetryConfig,
+    /// How long a provider may leave a payment pending before it is retried
+    pub token_timeout: Duration,
+    /// Submits refunds from the system wallet; set at boot from `HOT_WALLET_SIGNER`
+    pub payout_queue: Option<SubmissionQueue>,
+    pub system_wallet_address: String,
+}
+
+impl Default for BillProcessorConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            batch_size: 50,
+            retry: RetryConfig::default(),
+            token_timeout: Duration::from_secs(30 * 60),
+            payout_queue: None,
+            system_wallet_address: String::new(),
+        }
+    }
+}
+
+impl BillProcessorConfig {
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+
+        cfg.poll_interval = Duration::from_secs(
+            std::env::var("BILL_PROCESSOR_POLL_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.poll_interval.as_secs()),
+        );
+
+        cfg.batch_size = std::env::var("BILL_PROCESSOR_BATCH_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(cfg.batch_size);
+
+        cfg.retry.max_attempts = std::env::var("BILL_PROCESSOR_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(cfg.retry.max_attempts);
+
+        if let Some(backoff) = std::env::var("BILL_PROCESSOR_BACKOFF_SECONDS")
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .filter_map(|s| s.trim().parse::<u64>().ok())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|v| !v.is_empty())
+        {
+            cfg.retry.backoff_seconds = backoff;
+        }
+
+        cfg.token_timeout = Duration::from_secs(
+            std::env::var("BILL_PROCESSOR_TOKEN_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.token_timeout.as_secs()),
+        );
+
+        cfg.system_wallet_address = std::env::var("SYSTEM_WALLET_ADDRESS").unwrap_or_default();
+
+        cfg
+    }
+
+    pub fn validate(&self) -> Result<(), ProcessingError> {
+        let Some(queue) = &self.payout_queue else {
+            return Err(ProcessingError::InvalidState(
+                "a hot wallet signer is required (HOT_WALLET_SIGNER)".to_string(),
+            ));
+        };
+        if self.system_wallet_address.is_empty() {
+            return Err(ProcessingError::InvalidState(
+                "SYSTEM_WALLET_ADDRESS is required".to_string(),
+            ));
+        }
+        if queue.account_id() != self.system_wallet_address {
+            return Err(ProcessingError::InvalidState(
+                "HOT_WALLET_SIGNER does not sign for SYSTEM_WALLET_ADDRESS".to_string(),
+            ));
+        }
+        if self.retry.max_attempts == 0 {
+            return Err(ProcessingError::InvalidState(
+                "BILL_PROCESSOR_MAX_ATTEMPTS must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Backoff before retry number `retry_count + 1`; the last configured step repeats
+    pub fn backoff_for(&self, retry_count: i32) -> u64 {
+        let steps = &self.retry.backoff_seconds;
+        let idx = (retry_count.max(0) as usize).min(steps.len().saturating_sub(1));
+        steps.get(idx).copied().unwrap_or(300)
+    }
+}
+
+impl From<DatabaseError> for ProcessingError {
+    fn from(e: DatabaseError) -> Self {
+        ProcessingError::Database(e.to_string())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Worker Implementation
+// ---------------------------------------------------------------------------
+
+/// Drives bill payments from a confirmed cNGN deposit to a delivered bill or a refund:
+///
+/// `pending_payment → cngn_received → verifying_account → processing_bill →
+/// provider_processing → completed`, with failed attempts parked in
+/// `retry_scheduled` and exhausted or invalid ones going through
+/// `refund_initiated → refund_processing → refunded`.
+pub struct BillProcessorWorker {
+    bills: BillPaymentRepository,
+    transactions: TransactionRepository,
+    providers: BillProviders,
+    notification_service: Arc<NotificationService>,
+    merchant_webhooks: Option<Arc<MerchantWebhookService>>,
+    config: BillProcessorConfig,
+}
+
+impl BillProcessorWorker {
+    pub fn new(
+        pool: PgPool,
+        providers: BillProviders,
+        notification_service: Arc<NotificationService>,
+        config: BillProcessorConfig,
+    ) -> Self {
+        Self {
+            bills: BillPaymentRepository::new(pool.clone()),
+            transactions: TransactionRepository::new(pool),
+            providers,
+            notification_service,
+            merchant_webhooks: None,
+            config,
+        }
+    }
+
+    /// Publish completed bills and refunds to partner webhook endpoints
+    pub fn with_merchant_webhooks(mut self, service: Arc<MerchantWebhookService>) -> Self {
+        self.merchant_webhooks = Some(service);
+        self
+    }
+
+    pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
+        info!(
+            providers = ?self.providers.keys().collect::<Vec<_>>(),
+            "Starting bill processor worker..."
+        );
+
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_cycle().await {
+                        error!(error = %e, "bill processor cycle failed");
+                    }
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        info!("Bill processor worker received shutdown signal");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("Bill processor worker stopped");
+    }
+
+    #[instrument(skip(self), name = "bill_processor_cycle")]
+    async fn run_cycle(&self) -> Result<(), ProcessingError> {
+        debug!("Running bill processor cycle");
+
+        // Stage 1: Pick up payments whose cNGN deposit has been confirmed
+        if let Err(e) = self.process_funded_payments().await {
+            error!(error = %e, "failed to pick up funded bill payments");
+        }
+
+        // Stage 2: Account verification
+        if let Err(e) = self.process_verifications().await {
+            error!(error = %e, "failed to process account verifications");
+        }
+
+        // Stage 3: Payment execution
+        if let Err(e) = self.process_payments().await {
+            error!(error = %e, "failed to process bill payments");
+        }
+
+        // Stage 4: Token retrieval
+        if let Err(e) = self.process_token_retrieval().await {
+            error!(error = %e, "failed to process token retrieval");
+        }
+
+        // Stage 5: Scheduled retries
+        if let Err(e) = self.process_retries().await {
+            error!(error = %e, "failed to process scheduled retries");
+        }
+
+        // Stage 6: Refunds
+        if let Err(e) = self.process_refunds().await {
+            error!(error = %e, "failed to process bill refunds");
+        }
+
+        Ok(())
+    }
+
+    /// Stage 1: move `pending_payment` bills whose transaction reached `cngn_received`
+    async fn process_funded_payments(&self) -> Result<(), ProcessingError> {
+        let bills = self
+            .bills
+            .find_funded_pending_payments(self.config.batch_size)
+            .await?;
+
+        for bill in bills {
+            if !self
+                .claim(
+                    &bill,
+                    BillProcessingState::PendingPayment,
+                    BillProcessingState::CngnReceived,
+                )
+                .await?
+            {
+                continue;
+            }
+            let tx = self
+                .transactions
+                .update_status(&bill.transaction_id.to_string(), TX_STATUS_BILL_PROCESSING)
+                .await?;
+            info!(transaction_id = %bill.transaction_id, "cNGN received for bill payment");
+            self.notification_service
+                .send_notification(
+                    &tx,
+                    NotificationType::CngnReceived,
+                    "Stellar payment received, processing bill payment",
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Stage 2: verify the customer account with the first provider that answers
+    async fn process_verifications(&self) -> Result<(), ProcessingError> {
+        let bills = self
+            .bills
+            .find_by_processing_status(
+                BillProcessingState::CngnReceived.as_str(),
+                self.config.batch_size,
+            )
+            .await?;
+
+        for bill in bills {
+            if !self
+                .claim(
+                    &bill,
+                    BillProcessingState::CngnReceived,
+                    BillProcessingState::VerifyingAccount,
+                )
+                .await?
+            {
+                continue;
+            }
+            let Some(tx) = self.load_transaction(&bill).await? else {
+                continue;
+            };
+
+            let request = VerificationRequest {
+                provider_code: bill.provider_code.clone(),
+                account_number: bill.account_number.clone(),
+                account_type: metadata_str(&tx.metadata, "account_type")
+                    .unwrap_or("prepaid")
+                    .to_string(),
+                bill_type: bill.bill_type.clone(),
+            };
+
+            let mut last_error = None;
+            for (name, provider) in self.provider_chain(&bill.bill_type) {
+                match AccountVerifier::verify(provider.as_ref(), &request).await {
+                    Ok(account) => {
+                        info!(
+                            transaction_id = %bill.transaction_id,
+                            provider = name,
+                            "bill account verified"
+                        );
+                        self.bills
+                            .record_verification(
+                                bill.id,
+                                BillProcessingState::ProcessingBill.as_str(),
+                                true,
+                                serde_json::to_value(&account).unwrap_or_default(),
+                                None,
+                            )
+                            .await?;
+                        last_error = None;
+                        break;
+                    }
+                    Err(ProcessingError::AccountVerificationFailed { reason }) => {
+                        warn!(transaction_id = %bill.transaction_id, reason = %reason, "bill account invalid");
+                        self.bills
+                            .record_verification(
+                                bill.id,
+                                BillProcessingState::AccountInvalid.as_str(),
+                                false,
+                                serde_json::json!({ "reason": reason }),
+                                Some(&reason),
+                            )
+                            .await?;
+                        let reason = RefundHandler::format_refund_reason("account_invalid", None);
+                        self.bills.transition_to_refund(bill.id, &reason).await?;
+                        last_error = None;
+                        break;
+                    }
+                    Err(e) => {
+                        warn!(
+                            transaction_id = %bill.transaction_id,
+                            provider = name,
+                            error = %e,
+                            "account verification unavailable, trying next provider"
+                        );
+                        last_error = Some(e.to_string());
+                    }
+                }
+            }
+
+            // Every provider was unreachable (or none is configured): try again next cycle
+            if let Some(reason) = last_error.or_else(|| {
+                self.provider_chain(&bill.bill_type)
+                    .is_empty()
+                    .then(|| "no bill payment provider configured".to_string())
+            }) {
+                self.bills
+                    .update_processing_status(
+                        bill.id,
+                        BillProcessingState::CngnReceived.as_str(),
+                        Some(&reason),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stage 3: pay the bill, falling back to backup providers only when a
+    /// provider refuses the payment. When the outcome is unknown the bill
+    /// stays with that provider, since another one could pay it twice.
+    async fn process_payments(&self) -> Result<(), ProcessingError> {
+        let bills = self
+            .bills
+            .find_by_processing_status(
+                BillProcessingState::ProcessingBill.as_str(),
+                self.config.batch_size,
+            )
+            .await?;
+
+        for bill in bills {
+            if !self
+                .claim(
+                    &bill,
+                    BillProcessingState::ProcessingBill,
+                    BillProcessingState::ProviderProcessing,
+                )
+                .await?
+            {
+                continue;
+            }
+            let Some(tx) = self.load_transaction(&bill).await? else {
+                continue;
+            };
+
+            let request = BillPaymentRequest {
+                transaction_id: bill.transaction_id.to_string(),
+                provider_code: bill.provider_code.clone(),
+                account_number: bill.account_number.clone(),
+                account_type: metadata_str(&tx.metadata, "account_type")
+                    .unwrap_or("prepaid")
+                    .to_string(),
+                bill_type: bill.bill_type.clone(),
+                amount: bill.amount,
+                phone_number: metadata_str(&tx.metadata, "phone_number").map(str::to_string),
+                variation_code: metadata_str(&tx.metadata, "variation_code").map(str::to_string),
+            };
+
+            let mut last_error = "no bill payment provider configured".to_string();
+            let mut submitted = false;
+            for (name, provider) in self.provider_chain(&bill.bill_type) {
+                // Record the provider before calling it so a request whose
+                // answer is lost can still be looked up by our reference,
+                // which every adapter sends with the payment
+                self.bills
+                    .record_provider_submission(
+                        bill.id,
+                        BillProcessingState::ProviderProcessing.as_str(),
+                        &request.transaction_id,
+                        None,
+                        serde_json::json!({ "provider": name }),
+                    )
+                    .await?;
+                match PaymentExecutor::execute(provider.as_ref(), request.clone()).await {
+                    Ok(response) => {
+                        let provider_response = serde_json::json!({
+                            "provider": name,
+                            "response": response,
+                        });
+                        self.bills
+                            .record_provider_submission(
+                                bill.id,
+                                BillProcessingState::ProviderProcessing.as_str(),
+                                &response.provider_reference,
+                                response.token.as_deref(),
+                                provider_response,
+                            )
+                            .await?;
+                        if is_final_success(&response.status) {
+                            self.complete(
+                                &bill,
+                                &tx,
+                                response.token.as_deref(),
+                                &response.provider_reference,
+                            )
+                            .await?;
+                        }
+                        submitted = true;
+                        break;
+                    }
+                    Err(e @ ProcessingError::ProviderRejected { .. }) => {
+                        warn!(
+                            transaction_id = %bill.transaction_id,
+                            provider = name,
+                            error = %e,
+                            "bill payment rejected, trying next provider"
+                        );
+                        last_error = e.to_string();
+                    }
+                    Err(e) => {
+                        warn!(
+                            transaction_id = %bill.transaction_id,
+                            provider = name,
+                            error = %e,
+                            "bill payment outcome unknown, waiting for provider status"
+                        );
+                        self.bills
+                            .update_processing_status(
+                                bill.id,
+                                BillProcessingState::ProviderProcessing.as_str(),
+                                Some(&e.to_string()),
+                            )
+                            .await?;
+                        submitted = true;
+                        break;
+                    }
+                }
+            }
+
+            if !submitted {
+                self.fail_attempt(&bill, &last_error).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stage 4: poll providers for payments they accepted, or may have, but
+    /// have not finished. Only a failure the provider reports is retried; a
+    /// payment that stays unconfirmed is flagged for review instead, since
+    /// the provider may still complete it.
+    async fn process_token_retrieval(&self) -> Result<(), ProcessingError> {
+        let bills = self
+            .bills
+            .find_by_processing_status(
+                BillProcessingState::ProviderProcessing.as_str(),
+                self.config.batch_size,
+            )
+            .await?;
+
+        for bill in bills {
+            let timed_out = chrono::Utc::now() - bill.updated_at
+                > chrono::Duration::from_std(self.config.token_timeout)
+                    .unwrap_or_else(|_| chrono::Duration::minutes(30));
+
+            let Some(reference) = bill.provider_reference.as_deref() else {
+                // Claimed but stopped before any provider was called
+                if timed_out {
+                    self.fail_provider(&bill, "provider submission was not recorded")
+                        .await?;
+                }
+                continue;
+            };
+            let Some(provider) = self.submitting_provider(&bill) else {
+                if timed_out {
+                    self.flag_unconfirmed(&bill, "submitting provider is no longer configured")
+                        .await?;
+                }
+                continue;
+            };
+
+            match PaymentExecutor::check_status_and_retrieve_token(provider.as_ref(), reference)
+                .await
+            {
+                Ok((status, token)) if is_final_success(&status) => {
+                    let Some(tx) = self.load_transaction(&bill).await? else {
+                        continue;
+                    };
+                    self.complete(&bill, &tx, token.as_deref(), reference)
+                        .await?;
+                }
+                Ok((status, _)) if is_final_failure(&status) => {
+                    self.fail_provider(&bill, &format!("provider reported payment {}", status))
+                        .await?;
+                }
+                Ok(_) if timed_out => {
+                    self.flag_unconfirmed(&bill, "provider did not complete the payment in time")
+                        .await?;
+                }
+                Ok(_) => {}
+                Err(e) if timed_out => {
+                    self.flag_unconfirmed(&bill, &format!("provider status unavailable: {}", e))
+                        .await?;
+                }
+                Err(e) => {
+                    warn!(transaction_id = %bill.transaction_id, error = %e, "failed to query bill payment status");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stage 5: return scheduled retries to `processing_bill` once their backoff has elapsed
+    async fn process_retries(&self) -> Result<(), ProcessingError> {
+        let bills = self
+            .bills
+            .find_by_processing_status(
+                BillProcessingState::RetryScheduled.as_str(),
+                self.config.batch_size,
+            )
+            .await?;
+
+        for bill in bills {
+            let backoff = self.config.backoff_for(bill.retry_count);
+            if self
+                .bills
+                .mark_ready_for_retry(bill.id, backoff.min(i32::MAX as u64) as i32)
+                .await?
+            {
+                info!(
+                    transaction_id = %bill.transaction_id,
+                    attempt = bill.retry_count + 2,
+                    "retrying bill payment"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stage 6: send the cNGN back to the user's wallet
+    async fn process_refunds(&self) -> Result<(), ProcessingError> {
+        let Some(queue) = self.config.payout_queue.clone() else {
+            return Err(ProcessingError::InvalidState(
+                "hot wallet signer is not configured".to_string(),
+            ));
+        };
+        let bills = self
+            .bills
+            .find_by_processing_status(
+                BillProcessingState::RefundInitiated.as_str(),
+                self.config.batch_size,
+            )
+            .await?;
+
+        for bill in bills {
+            if !self
+                .claim(
+                    &bill,
+                    BillProcessingState::RefundInitiated,
+                    BillProcessingState::RefundProcessing,
+                )
+                .await?
+            {
+                continue;
+            }
+            let Some(tx) = self.load_transaction(&bill).await? else {
+                continue;
+            };
+            let tx_id = tx.transaction_id.to_string();
+            info!(transaction_id = %tx_id, "processing bill payment refund");
+
+            let payment = QueuedPayment::cngn(
+                &tx.wallet_address,
+                tx.cngn_amount.to_string(),
+                CngnMemo::Text(refund_memo(&tx.transaction_id)),
+            );
+
+            match queue.pay(payment).await {
+                Ok(submitted) => {
+                    let refund_hash = submitted.tx_hash;
+                    self.bills
+                        .mark_refund_processed(bill.id, &refund_hash)
+                        .await?;
+                    let tx = self.transactions.update_status(&tx_id, "refunded").await?;
+                    info!(transaction_id = %tx_id, refund_tx_hash = %refund_hash, "bill payment refunded");
+
+                    let reason = bill
+                        .error_message
+                        .as_deref()
+                        .unwrap_or("Bill payment could not be completed");
+                    self.notification_service
+                        .send_notification(&tx, NotificationType::BillPaymentRefunded, reason)
+                        .await;
+                    self.publish(
+                        events::TRANSACTION_REFUNDED,
+                        &tx,
+                        serde_json::json!({ "refund_tx_hash": refund_hash, "reason": reason }),
+                    )
+                    .await;
+                }
+                Err(e) if e.nothing_sent() => {
+                    // Nothing was sent; put the refund back in the queue
+                    error!(transaction_id = %tx_id, error = %e, "failed to send bill refund");
+                    self.bills
+                        .update_processing_status(
+                            bill.id,
+                            BillProcessingState::RefundInitiated.as_str(),
+                            Some(&format!("Stellar payment error: {}", e)),
+                        )
+                        .await?;
+                }
+                Err(e) => {
+                    // The submission may still land; leave it in refund_processing for review
+                    // rather than risk paying the refund twice
+                    let hash = e.in_flight_hash.clone().unwrap_or_default();
+                    error!(transaction_id = %tx_id, tx_hash = %hash, error = %e, "failed to submit bill refund");
+                    let message = format!("Stellar submission error (tx {}): {}", hash, e);
+                    self.bills
+                        .update_processing_status(
+                            bill.id,
+                            BillProcessingState::RefundProcessing.as_str(),
+                            Some(&message),
+                        )
+                        .await?;
+                    let tx = self.transactions.update_error(&tx_id, &message).await?;
+                    self.publish(events::TRANSACTION_FAILED, &tx, serde_json::json!({}))
+                        .await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Helpers
+    // -----------------------------------------------------------------------
+
+    /// Primary provider for the bill type followed by its configured backups
+    fn provider_chain(&self, bill_type: &str) -> Vec<(&'static str, Arc<dyn BillPaymentProvider>)> {
+        provider_chain(&self.providers, bill_type)
+    }
+
+    /// Provider that accepted the payment, as recorded in `provider_response`
+    fn submitting_provider(&self, bill: &BillTransaction) -> Option<Arc<dyn BillPaymentProvider>> {
+        let response: JsonValue = serde_json::from_str(bill.provider_response.as_deref()?).ok()?;
+        let name = response.get("provider")?.as_str()?;
+        self.providers.get(name).cloned()
+    }
+
+    async fn claim(
+        &self,
+        bill: &BillTransaction,
+        from: BillProcessingState,
+        to: BillProcessingState,
+    ) -> Result<bool, ProcessingError> {
+        let claimed = self
+            .bills
+            .transition_processing_status(bill.id, from.as_str(), to.as_str())
+            .await?;
+        if !claimed {
+            debug!(transaction_id = %bill.transaction_id, state = from.as_str(), "bill payment already claimed");
+        }
+        Ok(claimed)
+    }
+
+    async fn load_transaction(
+        &self,
+        bill: &BillTransaction,
+    ) -> Result<Option<Transaction>, ProcessingError> {
+        let tx = self
+            .transactions
+            .find_by_id(&bill.transaction_id.to_string())
+            .await?;
+        if tx.is_none() {
+            error!(transaction_id = %bill.transaction_id, "transaction for bill payment not found");
+        }
+        Ok(tx)
+    }
+
+    async fn complete(
+        &self,
+        bill: &BillTransaction,
+        tx: &Transaction,
+        token: Option<&str>,
+        provider_reference: &str,
+    ) -> Result<(), ProcessingError> {
+        if let Some(token) = token {
+            let (valid, reason) = TokenManager::validate_token(token, &bill.bill_type);
+            if !valid {
+                // The provider's token is what the customer needs; keep it but flag it
+                warn!(transaction_id = %bill.transaction_id, reason = %reason, "provider token failed validation");
+            }
+        }
+
+        self.bills.mark_completed(bill.id, token).await?;
+        let tx = self
+            .transactions
+            .update_status(&tx.transaction_id.to_string(), "completed")
+            .await?;
+        info!(
+            transaction_id = %bill.transaction_id,
+            provider_reference,
+            has_token = token.is_some(),
+            "bill payment completed"
+        );
+
+        let message = TokenManager::format_for_notification(token, &bill.bill_type);
+        self.notification_service
+            .send_notification(&tx, NotificationType::BillPaymentCompleted, &message)
+            .await;
+
+        let data = serde_json::json!({
+            "bill_type": bill.bill_type,
+            "provider_code": bill.provider_code,
+            "account_number": bill.account_number,
+            "provider_reference": provider_reference,
+            "token": token,
+        });
+        if token.is_some() {
+            self.publish(events::BILL_TOKEN_ISSUED, &tx, data.clone())
+                .await;
+        }
+        self.publish(events::TRANSACTION_COMPLETED, &tx, data).await;
+        Ok(())
+    }
+
+    /// Record a provider-side failure for a submitted payment, then retry or refund
+    async fn fail_provider(
+        &self,
+        bill: &BillTransaction,
+        reason: &str,
+    ) -> Result<(), ProcessingError> {
+        self.bills
+            .update_processing_status(
+                bill.id,
+                BillProcessingState::ProviderFailed.as_str(),
+                Some(reason),
+            )
+            .await?;
+        self.fail_attempt(bill, reason).await
+    }
+
+    /// Keep polling a payment the provider has neither completed nor failed,
+    /// raising it for manual review. Recording the reason restarts the
+    /// timeout, so the alert repeats while the payment stays unresolved.
+    async fn flag_unconfirmed(
+        &self,
+        bill: &BillTransaction,
+        reason: &str,
+    ) -> Result<(), ProcessingError> {
+        error!(
+            transaction_id = %bill.transaction_id,
+            provider_reference = ?bill.provider_reference,
+            reason,
+            "bill payment unconfirmed by provider, needs manual review"
+        );
+        self.bills
+            .update_processing_status(
+                bill.id,
+                BillProcessingState::ProviderProcessing.as_str(),
+                Some(reason),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Schedule another attempt, or refund once the retry budget is spent
+    async fn fail_attempt(
+        &self,
+        bill: &BillTransaction,
+        reason: &str,
+    ) -> Result<(), ProcessingError> {
+        let attempts = bill.retry_count.max(0) as u32 + 1;
+        if attempts < self.config.retry.max_attempts {
+            warn!(
+                transaction_id = %bill.transaction_id,
+                attempts,
+                reason,
+                "bill payment attempt failed, scheduling retry"
+            );
+            self.bills.schedule_retry(bill.id, reason).await?;
+        } else {
+            error!(
+                transaction_id = %bill.transaction_id,
+                attempts,
+                reason,
+                "bill payment failed permanently, initiating refund"
+            );
+            let refund_reason = format!(
+                "{}: {}",
+                RefundHandler::format_refund_reason("max_retries", None),
+                reason
+            );
+            self.bills
+                .transition_to_refund(bill.id, &refund_reason)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, event_type: &str, tx: &Transaction, data: JsonValue) {
+        if let Some(webhooks) = &self.merchant_webhooks {
+            webhooks.notify(event_type, tx, Some(data)).await;
+        }
+    }
+}
+
+fn is_final_success(status: &str) -> bool {
+    matches!(
+        status.to_lowercase().as_str(),
+        "completed" | "success" | "successful" | "delivered"
+    )
+}
+
+fn is_final_failure(status: &str) -> bool {
+    matches!(
+        status.to_lowercase().as_str(),
+        "failed" | "reversed" | "cancelled"
+    )
+}
+
+/// The full transaction id, base64url encoded to fit beside the prefix, so
+/// no two refunds share a memo
+fn refund_memo(tx_id: &Uuid) -> String {
+    format!("REFUND{}", URL_SAFE_NO_PAD.encode(tx_id.as_bytes()))
+}
+
+fn metadata_str<'a>(metadata: &'a JsonValue, key: &str) -> Option<&'a str> {
+    metadata.get(key).and_then(|v| v.as_str())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::stellar::client::StellarClient;
+    use crate::chains::stellar::config::StellarConfig;
+    use crate::chains::stellar::signer::{LocalSigner, TransactionSigner};
+    use crate::chains::stellar::submission::SubmissionConfig;
+
+    #[test]
+    fn backoff_repeats_last_step() {
+        let config = BillProcessorConfig::default();
+        assert_eq!(config.backoff_for(0), 10);
+        assert_eq!(config.backoff_for(1), 60);
+        assert_eq!(config.backoff_for(2), 300);
+        assert_eq!(config.backoff_for(7), 300);
+    }
+
+    #[test]
+    fn provider_status_classification() {
+        assert!(is_final_success("Delivered"));
+        assert!(is_final_success("completed"));
+        assert!(!is_final_success("pending"));
+        assert!(is_final_failure("failed"));
+        assert!(!is_final_failure("processing"));
+    }
+
+    #[test]
+    fn refund_memo_fits_stellar_limit() {
+        let a = Uuid::parse_str("7f1c2a9e-8d4b-4c3a-9f2e-1b5d6c7e8f90").unwrap();
+        let b = Uuid::parse_str("7f1c2a9e-8d4b-4c3a-9f2e-1b5d6c7e8f91").unwrap();
+        let memo = refund_memo(&a);
+        assert!(memo.len() <= MAX_MEMO_LEN);
+        assert!(memo.starts_with("REFUND"));
+        assert_ne!(memo, refund_memo(&b));
+    }
+
+    #[tokio::test]
+    async fn config_validation_requires_secrets() {
+        let mut config = BillProcessorConfig::default();
+        assert!(config.validate().is_err());
+        let signer = LocalSigner::from_secret(
+            stellar_strkey::ed25519::PrivateKey([1; 32])
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+        config.system_wallet_address = signer.account_id().to_string();
+        config.payout_queue = Some(SubmissionQueue::new(
+            StellarClient::new(StellarConfig::default()).unwrap(),
+            Arc::new(signer),
+            SubmissionConfig::default(),
+        ));
+        assert!(config.validate().is_ok());
+
+        config.system_wallet_address = "G".to_string();
+        assert!(config.validate().is_err());
+    }
+}
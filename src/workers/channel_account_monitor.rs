@@ -0,0 +1,58 @@
+use crate::chains::stellar::channel::ChannelAccountPool;
+use crate::chains::stellar::submission::SubmissionQueue;
+use tokio::sync::watch;
+use tracing::{info, instrument, warn};
+
+/// Keeps the channel account pool usable: creates channels that don't exist
+/// yet and tops up those running low on XLM, funding both from the hot
+/// wallet's submission queue
+pub struct ChannelAccountMonitorWorker {
+    pool: ChannelAccountPool,
+    funder: SubmissionQueue,
+}
+
+impl ChannelAccountMonitorWorker {
+    pub fn new(pool: ChannelAccountPool, funder: SubmissionQueue) -> Self {
+        Self { pool, funder }
+    }
+
+    pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
+        let check_interval = self.pool.config().health_check_interval;
+        info!(
+            channels = self.pool.accounts().len(),
+            interval_secs = check_interval.as_secs(),
+            "Starting channel account monitor..."
+        );
+
+        let mut interval = tokio::time::interval(check_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => self.run_cycle().await,
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        info!("Channel account monitor received shutdown signal");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("Channel account monitor stopped");
+    }
+
+    #[instrument(skip(self), name = "channel_account_check_cycle")]
+    async fn run_cycle(&self) {
+        let statuses = self.pool.check(&self.funder).await;
+        let healthy = statuses.iter().filter(|s| s.healthy).count();
+        if healthy < statuses.len() {
+            warn!(
+                healthy,
+                total = statuses.len(),
+                "some channel accounts are unavailable"
+            );
+        } else {
+            info!(healthy, "channel accounts checked");
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::database::error::DatabaseError;
+use crate::database::merchant_webhook_repository::{DueDelivery, MerchantWebhookRepository};
+use crate::services::merchant_webhooks::{
+    sign_payload, validate_endpoint_url, ResolvedEndpoint, DELIVERY_ID_HEADER, EVENT_ID_HEADER,
+    EVENT_TYPE_HEADER, SIGNATURE_HEADER,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Response bodies are stored for debugging, truncated to this many bytes
+const MAX_STORED_RESPONSE_BYTES: usize = 1024;
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct MerchantWebhookWorkerConfig {
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    /// Attempts before a delivery is marked failed
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on every attempt
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for MerchantWebhookWorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            batch_size: 50,
+            max_attempts: 8,
+            base_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(6 * 60 * 60),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl MerchantWebhookWorkerConfig {
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+
+        cfg.poll_interval = Duration::from_secs(
+            std::env::var("MERCHANT_WEBHOOK_POLL_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.poll_interval.as_secs()),
+        );
+
+        cfg.batch_size = std::env::var("MERCHANT_WEBHOOK_BATCH_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(cfg.batch_size);
+
+        cfg.max_attempts = std::env::var("MERCHANT_WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(cfg.max_attempts);
+
+        cfg.base_backoff = Duration::from_secs(
+            std::env::var("MERCHANT_WEBHOOK_BASE_BACKOFF_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.base_backoff.as_secs()),
+        );
+
+        cfg.request_timeout = Duration::from_secs(
+            std::env::var("MERCHANT_WEBHOOK_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.request_timeout.as_secs()),
+        );
+
+        cfg
+    }
+
+    /// Delay before retrying after `attempt` failed attempts (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Worker
+// ---------------------------------------------------------------------------
+
+/// Outcome of a single HTTP attempt
+enum Attempt {
+    Delivered { status: u16, body: String },
+    Rejected { status: u16, body: String },
+    Unreachable(String),
+}
+
+/// Delivers queued merchant webhook events with exponential backoff
+pub struct MerchantWebhookWorker {
+    repository: Arc<MerchantWebhookRepository>,
+    config: MerchantWebhookWorkerConfig,
+}
+
+impl MerchantWebhookWorker {
+    pub fn new(
+        repository: Arc<MerchantWebhookRepository>,
+        config: MerchantWebhookWorkerConfig,
+    ) -> Self {
+        Self { repository, config }
+    }
+
+    pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
+        info!("Starting merchant webhook worker...");
+
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_cycle().await {
+                        error!(error = %e, "merchant webhook cycle failed");
+                    }
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        info!("Merchant webhook worker received shutdown signal");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("Merchant webhook worker stopped");
+    }
+
+    #[instrument(skip(self), name = "merchant_webhook_cycle")]
+    async fn run_cycle(&self) -> Result<(), DatabaseError> {
+        // Hold claimed rows a little longer than one request can take
+        let lease_secs = self.config.request_timeout.as_secs() as i64 * 2 + 30;
+        let due = self
+            .repository
+            .claim_due_deliveries(self.config.batch_size, lease_secs)
+            .await?;
+        if due.is_empty() {
+            return Ok(());
+        }
+        debug!(count = due.len(), "delivering merchant webhooks");
+
+        for delivery in due {
+            if let Err(e) = self.deliver(delivery).await {
+                error!(error = %e, "failed to record merchant webhook attempt");
+            }
+        }
+        Ok(())
+    }
+
+    async fn deliver(&self, due: DueDelivery) -> Result<(), DatabaseError> {
+        let delivery = &due.delivery;
+        let body = serde_json::to_vec(&delivery.payload).unwrap_or_default();
+
+        match self.attempt(&due, body).await {
+            Attempt::Delivered { status, body } => {
+                info!(delivery_id = %delivery.id, status, "merchant webhook delivered");
+                self.repository
+                    .mark_delivered(delivery.id, i32::from(status), Some(&body))
+                    .await
+            }
+            Attempt::Rejected { status, body } => {
+                self.record_failure(&due, Some(status), Some(&body), &format!("HTTP {}", status))
+                    .await
+            }
+            Attempt::Unreachable(reason) => self.record_failure(&due, None, None, &reason).await,
+        }
+    }
+
+    async fn attempt(&self, due: &DueDelivery, body: Vec<u8>) -> Attempt {
+        let delivery = &due.delivery;
+
+        // Resolved again on every attempt: the host may have been re-pointed
+        // since registration
+        let endpoint = match validate_endpoint_url(&delivery.url).await {
+            Ok(endpoint) => endpoint,
+            Err(e) => return Attempt::Unreachable(e.to_string()),
+        };
+        let http = match self.client_for(&endpoint) {
+            Ok(http) => http,
+            Err(e) => return Attempt::Unreachable(e.to_string()),
+        };
+
+        let signature = sign_payload(&due.secret, chrono::Utc::now().timestamp(), &body);
+        let mut request = http
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::USER_AGENT, "Aframp-Webhooks/1.0")
+            .header(SIGNATURE_HEADER, signature)
+            .header(DELIVERY_ID_HEADER, delivery.id.to_string());
+        if let Some(event_id) = delivery.outbound_event_id {
+            request = request.header(EVENT_ID_HEADER, event_id.to_string());
+        }
+        if let Some(event_type) = &delivery.event_type {
+            request = request.header(EVENT_TYPE_HEADER, event_type.as_str());
+        }
+
+        match request.body(body).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let body = truncate(response.text().await.unwrap_or_default());
+                if status.is_success() {
+                    Attempt::Delivered {
+                        status: status.as_u16(),
+                        body,
+                    }
+                } else {
+                    Attempt::Rejected {
+                        status: status.as_u16(),
+                        body,
+                    }
+                }
+            }
+            Err(e) => Attempt::Unreachable(e.to_string()),
+        }
+    }
+
+    /// Client that can only connect to the addresses that were checked
+    fn client_for(&self, endpoint: &ResolvedEndpoint) -> reqwest::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(self.config.request_timeout)
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = &endpoint.domain {
+            builder = builder.resolve_to_addrs(domain, &endpoint.addrs);
+        }
+        builder.build()
+    }
+
+    async fn record_failure(
+        &self,
+        due: &DueDelivery,
+        status: Option<u16>,
+        body: Option<&str>,
+        reason: &str,
+    ) -> Result<(), DatabaseError> {
+        let delivery = &due.delivery;
+        let attempts = delivery.retry_count.max(0) as u32 + 1;
+        let retry_at = (attempts < self.config.max_attempts).then(|| {
+            chrono::Utc::now()
+                + chrono::Duration::from_std(self.config.backoff(attempts))
+                    .unwrap_or_else(|_| chrono::Duration::hours(6))
+        });
+
+        match retry_at {
+            Some(at) => warn!(
+                delivery_id = %delivery.id,
+                attempts,
+                retry_at = %at,
+                reason,
+                "merchant webhook attempt failed, will retry"
+            ),
+            None => error!(
+                delivery_id = %delivery.id,
+                attempts,
+                reason,
+                "merchant webhook delivery failed permanently"
+            ),
+        }
+
+        self.repository
+            .mark_attempt_failed(delivery.id, status.map(i32::from), body, reason, retry_at)
+            .await
+    }
+}
+
+fn truncate(mut body: String) -> String {
+    if body.len() > MAX_STORED_RESPONSE_BYTES {
+        let mut end = MAX_STORED_RESPONSE_BYTES;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+    }
+    body
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let config = MerchantWebhookWorkerConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(30));
+        assert_eq!(config.backoff(2), Duration::from_secs(60));
+        assert_eq!(config.backoff(4), Duration::from_secs(240));
+        assert_eq!(config.backoff(30), config.max_backoff);
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        let long = "é".repeat(MAX_STORED_RESPONSE_BYTES);
+        let truncated = truncate(long);
+        assert!(truncated.len() <= MAX_STORED_RESPONSE_BYTES);
+        assert_eq!(truncate("ok".to_string()), "ok");
+    }
+}
@@ -1,5 +1,10 @@
+pub mod channel_account_monitor;
+pub mod merchant_webhooks;
+pub mod notification_dispatcher;
 pub mod offramp_processor;
+pub mod onramp_processor;
 pub mod transaction_monitor;
+pub mod treasury_monitor;
 pub mod webhook_retry;
 pub mod bill_processor {
     pub mod account_verification;
@@ -8,6 +13,7 @@ pub mod bill_processor {
     pub mod refund_handler;
     pub mod token_manager;
     pub mod types;
+    pub mod worker;
 }
 
 /// Determine if a failed payment is eligible for refund
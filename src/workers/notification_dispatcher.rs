@@ -0,0 +1,232 @@
+use crate::database::error::DatabaseError;
+use crate::database::notification_repository::{NotificationRepository, OutboxMessage};
+use crate::services::notification::channels::{ChannelError, NotificationChannels};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument, warn};
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct NotificationDispatcherConfig {
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    /// Attempts before a message is marked failed
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on every attempt
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long a claimed message stays invisible to other dispatchers
+    pub lease: Duration,
+}
+
+impl Default for NotificationDispatcherConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            batch_size: 50,
+            max_attempts: 5,
+            base_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60 * 60),
+            lease: Duration::from_secs(120),
+        }
+    }
+}
+
+impl NotificationDispatcherConfig {
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+
+        cfg.poll_interval = Duration::from_secs(
+            std::env::var("NOTIFICATION_POLL_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.poll_interval.as_secs()),
+        );
+
+        cfg.batch_size = std::env::var("NOTIFICATION_BATCH_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(cfg.batch_size);
+
+        cfg.max_attempts = std::env::var("NOTIFICATION_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(cfg.max_attempts);
+
+        cfg.base_backoff = Duration::from_secs(
+            std::env::var("NOTIFICATION_BASE_BACKOFF_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.base_backoff.as_secs()),
+        );
+
+        cfg
+    }
+
+    /// Delay before retrying after `attempt` failed attempts (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Worker
+// ---------------------------------------------------------------------------
+
+/// Sends queued notifications through the configured channels, retrying
+/// temporary failures with exponential backoff
+pub struct NotificationDispatcher {
+    repository: Arc<NotificationRepository>,
+    channels: NotificationChannels,
+    config: NotificationDispatcherConfig,
+}
+
+impl NotificationDispatcher {
+    pub fn new(
+        repository: Arc<NotificationRepository>,
+        channels: NotificationChannels,
+        config: NotificationDispatcherConfig,
+    ) -> Self {
+        Self {
+            repository,
+            channels,
+            config,
+        }
+    }
+
+    pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
+        info!(
+            channels = ?self.channels.keys().collect::<Vec<_>>(),
+            "Starting notification dispatcher..."
+        );
+
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_cycle().await {
+                        error!(error = %e, "notification dispatch cycle failed");
+                    }
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        info!("Notification dispatcher received shutdown signal");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("Notification dispatcher stopped");
+    }
+
+    #[instrument(skip(self), name = "notification_dispatch_cycle")]
+    async fn run_cycle(&self) -> Result<(), DatabaseError> {
+        // Only claim messages for channels this instance can send on
+        let channels: Vec<String> = self.channels.keys().map(|c| c.to_string()).collect();
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let due = self
+            .repository
+            .claim_due(
+                &channels,
+                self.config.batch_size,
+                self.config.lease.as_secs() as i64,
+            )
+            .await?;
+        if due.is_empty() {
+            return Ok(());
+        }
+        debug!(count = due.len(), "dispatching notifications");
+
+        for message in due {
+            if let Err(e) = self.dispatch(&message).await {
+                error!(notification_id = %message.id, error = %e, "failed to record notification attempt");
+            }
+        }
+        Ok(())
+    }
+
+    async fn dispatch(&self, message: &OutboxMessage) -> Result<(), DatabaseError> {
+        let Some(channel) = self.channels.get(message.channel.as_str()) else {
+            return Ok(());
+        };
+
+        match channel.send(message).await {
+            Ok(()) => {
+                info!(
+                    notification_id = %message.id,
+                    channel = %message.channel,
+                    notification_type = %message.notification_type,
+                    "notification sent"
+                );
+                self.repository.mark_sent(message.id).await
+            }
+            Err(e) => self.record_failure(message, &e).await,
+        }
+    }
+
+    async fn record_failure(
+        &self,
+        message: &OutboxMessage,
+        failure: &ChannelError,
+    ) -> Result<(), DatabaseError> {
+        let attempts = message.attempts.max(0) as u32 + 1;
+        let retry_at = (failure.is_retryable() && attempts < self.config.max_attempts).then(|| {
+            chrono::Utc::now()
+                + chrono::Duration::from_std(self.config.backoff(attempts))
+                    .unwrap_or_else(|_| chrono::Duration::hours(1))
+        });
+        let reason = failure.to_string();
+
+        match retry_at {
+            Some(at) => warn!(
+                notification_id = %message.id,
+                channel = %message.channel,
+                attempts,
+                retry_at = %at,
+                reason = %reason,
+                "notification send failed, will retry"
+            ),
+            None => error!(
+                notification_id = %message.id,
+                channel = %message.channel,
+                attempts,
+                reason = %reason,
+                "notification send failed permanently"
+            ),
+        }
+
+        self.repository
+            .mark_failed(message.id, &reason, retry_at)
+            .await
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let config = NotificationDispatcherConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(60));
+        assert_eq!(config.backoff(2), Duration::from_secs(120));
+        assert_eq!(config.backoff(3), Duration::from_secs(240));
+        assert_eq!(config.backoff(20), config.max_backoff);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::chains::stellar::client::StellarClient;
-use crate::chains::stellar::payment::{CngnMemo, CngnPaymentBuilder};
+use crate::chains::stellar::payment::CngnMemo;
+use crate::chains::stellar::submission::{QueuedPayment, SubmissionQueue};
 use crate::database::error::DatabaseError;
 use crate::database::transaction_repository::{TransactionRepository, Transaction};
 use crate::payments::error::PaymentError;
 use crate::payments::factory::PaymentProviderFactory;
+use crate::services::merchant_webhooks::{events, MerchantWebhookService};
 use crate::services::notification::{NotificationService, NotificationType};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -238,7 +240,8 @@ pub struct OfframpProcessorConfig {
     pub max_retries: u32,
     pub retry_timeout: Duration,
     pub lock_timeout: Duration,
-    pub hot_wallet_secret: String,
+    /// Submits refunds from the system wallet; set at boot from `HOT_WALLET_SIGNER`
+    pub payout_queue: Option<SubmissionQueue>,
     pub system_wallet_address: String,
 }
 
@@ -250,7 +253,7 @@ impl Default for OfframpProcessorConfig {
             max_retries: 5,
             retry_timeout: Duration::from_secs(24 * 60 * 60), // 24 hours
             lock_timeout: Duration::from_secs(30),
-            hot_wallet_secret: String::new(),
+            payout_queue: None,
             system_wallet_address: String::new(),
         }
     }
@@ -292,23 +295,27 @@ impl OfframpProcessorConfig {
                 .unwrap_or(cfg.lock_timeout.as_secs()),
         );
 
-        cfg.hot_wallet_secret = std::env::var("HOT_WALLET_SECRET_KEY").unwrap_or_default();
         cfg.system_wallet_address = std::env::var("SYSTEM_WALLET_ADDRESS").unwrap_or_default();
 
         cfg
     }
 
     pub fn validate(&self) -> Result<(), OfframpError> {
-        if self.hot_wallet_secret.is_empty() {
+        let Some(queue) = &self.payout_queue else {
             return Err(OfframpError::Internal(
-                "HOT_WALLET_SECRET_KEY is required".to_string(),
+                "a hot wallet signer is required (HOT_WALLET_SIGNER)".to_string(),
             ));
-        }
+        };
         if self.system_wallet_address.is_empty() {
             return Err(OfframpError::Internal(
                 "SYSTEM_WALLET_ADDRESS is required".to_string(),
             ));
         }
+        if queue.account_id() != self.system_wallet_address {
+            return Err(OfframpError::Internal(
+                "HOT_WALLET_SIGNER does not sign for SYSTEM_WALLET_ADDRESS".to_string(),
+            ));
+        }
         Ok(())
     }
 }
@@ -322,6 +329,7 @@ pub struct OfframpProcessorWorker {
     stellar_client: StellarClient,
     provider_factory: Arc<PaymentProviderFactory>,
     notification_service: Arc<NotificationService>,
+    merchant_webhooks: Option<Arc<MerchantWebhookService>>,
     config: OfframpProcessorConfig,
 }
 
@@ -338,10 +346,17 @@ impl OfframpProcessorWorker {
             stellar_client,
             provider_factory,
             notification_service,
+            merchant_webhooks: None,
             config,
         }
     }
 
+    /// Publish offramp state changes to partner webhook endpoints
+    pub fn with_merchant_webhooks(mut self, service: Arc<MerchantWebhookService>) -> Self {
+        self.merchant_webhooks = Some(service);
+        self
+    }
+
     pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
         info!("Starting offramp processor worker...");
 
@@ -393,6 +408,27 @@ impl OfframpProcessorWorker {
         Ok(())
     }
 
+    /// Queue a merchant webhook for a transition `tx` (loaded before the update) just made
+    async fn publish_event(&self, tx: &Transaction, state: OfframpState) {
+        let Some(webhooks) = &self.merchant_webhooks else {
+            return;
+        };
+        let event_type = match state {
+            OfframpState::Completed => events::OFFRAMP_COMPLETED,
+            OfframpState::RefundInitiated => events::OFFRAMP_REFUND_INITIATED,
+            OfframpState::Refunded => events::OFFRAMP_REFUNDED,
+            OfframpState::Failed => events::OFFRAMP_FAILED,
+            _ => return,
+        };
+        webhooks
+            .notify(
+                event_type,
+                tx,
+                Some(serde_json::json!({ "status": state.as_str() })),
+            )
+            .await;
+    }
+
     /// Stage 1: Receipt Verification
     /// Selects transactions with 'cngn_received' status and verifies the amount.
     async fn process_received_payments(&self) -> Result<(), OfframpError> {
@@ -419,6 +455,7 @@ impl OfframpProcessorWorker {
                     error!(transaction_id = %tx_id, "no incoming hash found in metadata for cngn_received state");
                     metadata.failure_reason = Some("Missing incoming hash".to_string());
                     repo.update_status_with_metadata(&tx_id, OfframpState::RefundInitiated.as_str(), metadata.to_json()).await?;
+                    self.publish_event(&tx, OfframpState::RefundInitiated).await;
                     continue;
                 }
             };
@@ -460,6 +497,7 @@ impl OfframpProcessorWorker {
                     error!(transaction_id = %tx_id, "could not find cNGN payment operation in transaction {}", hash);
                     metadata.failure_reason = Some("No cNGN payment found in tx".to_string());
                     repo.update_status_with_metadata(&tx_id, OfframpState::RefundInitiated.as_str(), metadata.to_json()).await?;
+                    self.publish_event(&tx, OfframpState::RefundInitiated).await;
                     continue;
                 }
             };
@@ -474,6 +512,7 @@ impl OfframpProcessorWorker {
                     error!(transaction_id = %tx_id, "invalid expected amount format in DB");
                     metadata.failure_reason = Some("Invalid expected amount format in DB".to_string());
                     repo.update_status_with_metadata(&tx_id, OfframpState::RefundInitiated.as_str(), metadata.to_json()).await?;
+                    self.publish_event(&tx, OfframpState::RefundInitiated).await;
                     continue;
                 }
             };
@@ -484,6 +523,7 @@ impl OfframpProcessorWorker {
                     error!(transaction_id = %tx_id, "invalid actual amount format from Stellar");
                     metadata.failure_reason = Some("Invalid actual amount format from Stellar".to_string());
                     repo.update_status_with_metadata(&tx_id, OfframpState::RefundInitiated.as_str(), metadata.to_json()).await?;
+                    self.publish_event(&tx, OfframpState::RefundInitiated).await;
                     continue;
                 }
             };
@@ -499,6 +539,7 @@ impl OfframpProcessorWorker {
                 
                 // Transition to refund
                 repo.update_status_with_metadata(&tx_id, OfframpState::RefundInitiated.as_str(), metadata.to_json()).await?;
+                self.publish_event(&tx, OfframpState::RefundInitiated).await;
                 self.notification_service.send_notification(&tx, NotificationType::OfframpFailed, "Received amount does not precisely match expected amount, initiating refund").await;
                 continue;
             }
@@ -569,6 +610,7 @@ impl OfframpProcessorWorker {
                     // Force failure logic below if we can't even load a provider
                     metadata.failure_reason = Some(format!("Provider {} not configured", provider_name));
                     repo.update_status_with_metadata(&tx_id, OfframpState::RefundInitiated.as_str(), metadata.to_json()).await?;
+                    self.publish_event(&tx, OfframpState::RefundInitiated).await;
                     continue;
                 }
             };
@@ -607,6 +649,7 @@ impl OfframpProcessorWorker {
                             metadata.to_json(),
                         )
                         .await?;
+                        self.publish_event(&tx, OfframpState::RefundInitiated).await;
                         self.notification_service.send_notification(&tx, NotificationType::OfframpFailed, "Bank transfer initiation failed, initiating refund").await;
                     } else {
                         // Schedule retry
@@ -670,6 +713,7 @@ impl OfframpProcessorWorker {
                         crate::payments::types::PaymentState::Success => {
                             info!(transaction_id = %tx_id, "transfer confirmed successful by provider");
                             repo.update_status_with_metadata(&tx_id, OfframpState::Completed.as_str(), metadata.to_json()).await?;
+                            self.publish_event(&tx, OfframpState::Completed).await;
                             self.notification_service.send_notification(&tx, NotificationType::OfframpCompleted, "Funds have been sent to your bank account").await;
                         }
                         crate::payments::types::PaymentState::Failed => {
@@ -683,6 +727,7 @@ impl OfframpProcessorWorker {
                                 metadata.to_json(),
                             )
                             .await?;
+                            self.publish_event(&tx, OfframpState::RefundInitiated).await;
                             self.notification_service.send_notification(&tx, NotificationType::OfframpFailed, "Bank transfer failed, initiating refund").await;
                         }
                         crate::payments::types::PaymentState::Pending
@@ -702,6 +747,7 @@ impl OfframpProcessorWorker {
                                     metadata.to_json(),
                                 )
                                 .await?;
+                                self.publish_event(&tx, OfframpState::RefundInitiated).await;
                             }
                         }
                         _ => {
@@ -725,6 +771,7 @@ impl OfframpProcessorWorker {
                             metadata.to_json(),
                         )
                         .await?;
+                        self.publish_event(&tx, OfframpState::RefundInitiated).await;
                         self.notification_service.send_notification(&tx, NotificationType::OfframpFailed, "Bank transfer monitoring failed, initiating refund").await;
                     } else {
                         // Delay mapping: Attempt 1 = 30s, Attempt 2 = 120s (2m), Attempt 3 = 600s (10m)
@@ -758,6 +805,11 @@ impl OfframpProcessorWorker {
     /// Stage 4: Refund Processing
     /// Selects transactions with 'refund_initiated' status and processes the Stellar refund.
     async fn process_refunds(&self) -> Result<(), OfframpError> {
+        let Some(queue) = self.config.payout_queue.clone() else {
+            return Err(OfframpError::Internal(
+                "hot wallet signer is not configured".to_string(),
+            ));
+        };
         let repo = TransactionRepository::new(self.pool.clone());
         let transactions = repo
             .find_offramps_by_status("refund_initiated", self.config.batch_size)
@@ -769,9 +821,6 @@ impl OfframpProcessorWorker {
 
             let mut metadata = OfframpMetadata::from_json(&tx.metadata)?;
 
-            // Build refund payment on Stellar
-            let builder = CngnPaymentBuilder::new(self.stellar_client.clone());
-
             let amount_str = tx.cngn_amount.to_string();
             // The user req is: `REFUND-{original_memo}`. Here the original memo used was either the tx_id or WD-{tx_id}.
             // We ensure it fits the 28 char Stellar text memo limit.
@@ -787,71 +836,41 @@ impl OfframpProcessorWorker {
             repo.update_status(&tx_id, OfframpState::Refunding.as_str())
                 .await?;
 
-            match builder
-                .build_payment(
-                    &self.config.system_wallet_address,
-                    &tx.wallet_address,
-                    &amount_str,
-                    memo,
-                    None,
-                )
+            // Refunds share the hot wallet's submission queue with the other
+            // payout paths, so sequence numbers never collide between them
+            match queue
+                .pay(QueuedPayment::cngn(&tx.wallet_address, amount_str.clone(), memo))
                 .await
             {
-                Ok(draft) => match builder.sign_payment(draft, &self.config.hot_wallet_secret) {
-                    Ok(signed) => {
-                        match builder
-                            .submit_signed_payment(&signed.signed_envelope_xdr)
-                            .await
-                        {
-                            Ok(response) => {
-                                info!(transaction_id = %tx_id, "refund submitted successfully to Stellar");
-
-                                metadata.refund_tx_hash = Some(signed.draft.transaction_hash);
-                                metadata.refund_amount = Some(amount_str);
-                                metadata.refund_confirmed_at =
-                                    Some(chrono::Utc::now().to_rfc3339());
+                Ok(submitted) => {
+                    info!(transaction_id = %tx_id, tx_hash = %submitted.tx_hash, "refund submitted successfully to Stellar");
 
-                                repo.update_status_with_metadata(
-                                    &tx_id,
-                                    OfframpState::Refunded.as_str(),
-                                    metadata.to_json(),
-                                )
-                                .await?;
-                                self.notification_service.send_notification(&tx, NotificationType::OfframpRefunded, "Refund successful on Stellar").await;
-                            }
-                            Err(e) => {
-                                error!(transaction_id = %tx_id, error = %e, "failed to submit refund transaction");
-                                metadata.failure_reason =
-                                    Some(format!("Stellar submission error: {}", e));
-                                repo.update_status_with_metadata(
-                                    &tx_id,
-                                    OfframpState::Failed.as_str(),
-                                    metadata.to_json(),
-                                )
-                                .await?;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!(transaction_id = %tx_id, error = %e, "failed to sign refund transaction");
-                        metadata.failure_reason = Some(format!("Stellar signing error: {}", e));
-                        repo.update_status_with_metadata(
-                            &tx_id,
-                            OfframpState::Failed.as_str(),
-                            metadata.to_json(),
-                        )
-                        .await?;
-                    }
-                },
+                    metadata.refund_tx_hash = Some(submitted.tx_hash);
+                    metadata.refund_amount = Some(amount_str);
+                    metadata.refund_confirmed_at = Some(chrono::Utc::now().to_rfc3339());
+
+                    repo.update_status_with_metadata(
+                        &tx_id,
+                        OfframpState::Refunded.as_str(),
+                        metadata.to_json(),
+                    )
+                    .await?;
+                    self.publish_event(&tx, OfframpState::Refunded).await;
+                    self.notification_service.send_notification(&tx, NotificationType::OfframpRefunded, "Refund successful on Stellar").await;
+                }
                 Err(e) => {
-                    error!(transaction_id = %tx_id, error = %e, "failed to build refund transaction");
-                    metadata.failure_reason = Some(format!("Stellar build error: {}", e));
+                    error!(transaction_id = %tx_id, error = %e, "failed to submit refund transaction");
+                    metadata.failure_reason = Some(format!("Stellar submission error: {}", e));
+                    // Keep the hash of an envelope that may still land so the
+                    // refund can be checked on-chain before any retry
+                    metadata.refund_tx_hash = e.in_flight_hash;
                     repo.update_status_with_metadata(
                         &tx_id,
                         OfframpState::Failed.as_str(),
                         metadata.to_json(),
                     )
                     .await?;
+                    self.publish_event(&tx, OfframpState::Failed).await;
                 }
             }
         }
@@ -867,6 +886,9 @@ impl OfframpProcessorWorker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chains::stellar::config::StellarConfig;
+    use crate::chains::stellar::signer::{LocalSigner, TransactionSigner};
+    use crate::chains::stellar::submission::SubmissionConfig;
 
     #[test]
     fn offramp_state_transitions_are_validated() {
@@ -935,15 +957,29 @@ mod tests {
         assert_eq!(parsed.retry_count, 0);
     }
 
-    #[test]
-    fn config_validation_requires_secrets() {
+    #[tokio::test]
+    async fn config_validation_requires_secrets() {
         let mut config = OfframpProcessorConfig::default();
         assert!(config.validate().is_err());
 
-        config.hot_wallet_secret = "SECRET".to_string();
+        let signer = LocalSigner::from_secret(
+            stellar_strkey::ed25519::PrivateKey([1; 32])
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+        let account_id = signer.account_id().to_string();
+        config.payout_queue = Some(SubmissionQueue::new(
+            StellarClient::new(StellarConfig::default()).unwrap(),
+            Arc::new(signer),
+            SubmissionConfig::default(),
+        ));
         assert!(config.validate().is_err());
 
         config.system_wallet_address = "GADDRESS".to_string();
+        assert!(config.validate().is_err());
+
+        config.system_wallet_address = account_id;
         assert!(config.validate().is_ok());
     }
 }
@@ -0,0 +1,585 @@
+use crate::chains::stellar::errors::StellarError;
+use crate::chains::stellar::payment::CngnMemo;
+use crate::chains::stellar::submission::{QueuedPayment, SubmissionQueue};
+use crate::database::error::DatabaseError;
+use crate::database::transaction_repository::{Transaction, TransactionRepository};
+use crate::services::merchant_webhooks::{events, MerchantWebhookService};
+use crate::services::notification::{NotificationService, NotificationType};
+use crate::services::payment_orchestrator::OrchestrationState;
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument, warn};
+
+// ---------------------------------------------------------------------------
+// Error Types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum OnrampError {
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl From<anyhow::Error> for OnrampError {
+    fn from(e: anyhow::Error) -> Self {
+        OnrampError::Internal(e.to_string())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Fulfillment Metadata
+// ---------------------------------------------------------------------------
+
+/// Fields the processor merges into `transactions.metadata`.
+///
+/// `submitted_hash` is the key `TransactionMonitorWorker` reads to confirm the
+/// payment on Horizon. It is written once the submission queue reports the
+/// hash, since a resubmitted envelope gets a new one. `submitted_valid_until`
+/// is that envelope's upper time bound; the monitor won't have the payment
+/// rebuilt before it has passed.
+/// Attempts are tracked under their own key so they don't interfere with the
+/// monitor's `retry_count`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnrampFulfillmentMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted_valid_until: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fulfillment_sequence: Option<i64>,
+
+    #[serde(default)]
+    pub fulfillment_attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_fulfillment_after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+}
+
+impl OnrampFulfillmentMetadata {
+    pub fn to_json(&self) -> JsonValue {
+        serde_json::to_value(self).unwrap_or_else(|_| serde_json::json!({}))
+    }
+
+    /// Extract the fulfillment fields from a transaction's metadata, ignoring
+    /// everything else the orchestrator stored there.
+    pub fn from_json(value: &JsonValue) -> Self {
+        serde_json::from_value(value.clone()).unwrap_or_default()
+    }
+
+    /// Whether a scheduled retry window has not yet elapsed.
+    pub fn is_waiting_for_retry(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.next_fulfillment_after
+            .as_deref()
+            .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+            .map(|next| now < next.with_timezone(&chrono::Utc))
+            .unwrap_or(false)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct OnrampProcessorConfig {
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    pub max_retries: u32,
+    /// Submits payouts from the system wallet; set at boot from `HOT_WALLET_SIGNER`
+    pub payout_queue: Option<SubmissionQueue>,
+    pub system_wallet_address: String,
+}
+
+impl Default for OnrampProcessorConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            batch_size: 50,
+            max_retries: 5,
+            payout_queue: None,
+            system_wallet_address: String::new(),
+        }
+    }
+}
+
+impl OnrampProcessorConfig {
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+
+        cfg.poll_interval = Duration::from_secs(
+            std::env::var("ONRAMP_POLL_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.poll_interval.as_secs()),
+        );
+
+        cfg.batch_size = std::env::var("ONRAMP_BATCH_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(cfg.batch_size);
+
+        cfg.max_retries = std::env::var("ONRAMP_MAX_RETRIES")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(cfg.max_retries);
+
+        cfg.system_wallet_address = std::env::var("SYSTEM_WALLET_ADDRESS").unwrap_or_default();
+
+        cfg
+    }
+
+    pub fn validate(&self) -> Result<(), OnrampError> {
+        let Some(queue) = &self.payout_queue else {
+            return Err(OnrampError::Internal(
+                "a hot wallet signer is required (HOT_WALLET_SIGNER)".to_string(),
+            ));
+        };
+        if self.system_wallet_address.is_empty() {
+            return Err(OnrampError::Internal(
+                "SYSTEM_WALLET_ADDRESS is required".to_string(),
+            ));
+        }
+        if queue.account_id() != self.system_wallet_address {
+            return Err(OnrampError::Internal(
+                "HOT_WALLET_SIGNER does not sign for SYSTEM_WALLET_ADDRESS".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Worker Implementation
+// ---------------------------------------------------------------------------
+
+/// Delivers cNGN for onramps whose fiat payment has been confirmed.
+///
+/// Each `payment_confirmed` onramp is claimed into `processing` and its
+/// payment handed to the hot wallet's submission queue, which records the
+/// envelope hash once it is on its way. Ledger
+/// confirmation and the final move to `completed` are left to
+/// `TransactionMonitorWorker`. An onramp that runs out of attempts is
+/// `failed` with `needs_manual_refund` set, for an operator to refund.
+pub struct OnrampProcessorWorker {
+    pool: PgPool,
+    notification_service: Arc<NotificationService>,
+    merchant_webhooks: Option<Arc<MerchantWebhookService>>,
+    config: OnrampProcessorConfig,
+}
+
+impl OnrampProcessorWorker {
+    pub fn new(
+        pool: PgPool,
+        notification_service: Arc<NotificationService>,
+        config: OnrampProcessorConfig,
+    ) -> Self {
+        Self {
+            pool,
+            notification_service,
+            merchant_webhooks: None,
+            config,
+        }
+    }
+
+    pub fn with_merchant_webhooks(mut self, service: Arc<MerchantWebhookService>) -> Self {
+        self.merchant_webhooks = Some(service);
+        self
+    }
+
+    pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
+        info!("Starting onramp processor worker...");
+
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_cycle().await {
+                        error!(error = %e, "onramp processor cycle failed");
+                    }
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        info!("Onramp processor worker received shutdown signal");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("Onramp processor worker stopped");
+    }
+
+    #[instrument(skip(self), name = "onramp_processor_cycle")]
+    async fn run_cycle(&self) -> Result<(), OnrampError> {
+        debug!("Running onramp processor cycle");
+
+        let repo = TransactionRepository::new(self.pool.clone());
+        let transactions = repo
+            .find_onramps_by_status(
+                OrchestrationState::PaymentConfirmed.to_db_status(),
+                self.config.batch_size,
+            )
+            .await?;
+
+        for tx in transactions {
+            let tx_id = tx.transaction_id.to_string();
+            if let Err(e) = self.fulfill(&repo, &tx).await {
+                error!(transaction_id = %tx_id, error = %e, "failed to fulfill onramp");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build, sign and submit the cNGN payment for a single confirmed onramp.
+    async fn fulfill(
+        &self,
+        repo: &TransactionRepository,
+        tx: &Transaction,
+    ) -> Result<(), OnrampError> {
+        let tx_id = tx.transaction_id.to_string();
+        let mut metadata = OnrampFulfillmentMetadata::from_json(&tx.metadata);
+
+        if metadata.is_waiting_for_retry(chrono::Utc::now()) {
+            debug!(transaction_id = %tx_id, "skipping onramp, waiting for next retry window");
+            return Ok(());
+        }
+
+        if tx.cngn_amount <= BigDecimal::zero() {
+            let reason = format!("Invalid cNGN amount {}", tx.cngn_amount);
+            return self.retry_or_fail(repo, tx, metadata, reason, false).await;
+        }
+
+        info!(
+            transaction_id = %tx_id,
+            wallet = %tx.wallet_address,
+            amount = %tx.cngn_amount,
+            "sending cNGN for confirmed onramp"
+        );
+
+        let Some(queue) = self.config.payout_queue.clone() else {
+            return self
+                .retry_or_fail(
+                    repo,
+                    tx,
+                    metadata,
+                    "hot wallet signer is not configured".to_string(),
+                    false,
+                )
+                .await;
+        };
+
+        // Claim the transaction before queueing the payment so that two
+        // workers can't pay the same onramp.
+        metadata.submitted_at = Some(chrono::Utc::now().to_rfc3339());
+        metadata.next_fulfillment_after = None;
+        metadata.failure_reason = None;
+
+        let claimed = repo
+            .transition_status_if(
+                &tx_id,
+                OrchestrationState::PaymentConfirmed.to_db_status(),
+                OrchestrationState::ProcessingBlockchain.to_db_status(),
+                metadata.to_json(),
+            )
+            .await?;
+        let Some(claimed) = claimed else {
+            debug!(transaction_id = %tx_id, "onramp already claimed by another worker");
+            return Ok(());
+        };
+        self.publish(events::TRANSACTION_PROCESSING, &claimed).await;
+
+        let payment = QueuedPayment::cngn(
+            &tx.wallet_address,
+            tx.cngn_amount.to_string(),
+            CngnMemo::Text(onramp_memo(&tx_id)),
+        );
+        match queue.pay(payment).await {
+            Ok(submitted) => {
+                info!(
+                    transaction_id = %tx_id,
+                    tx_hash = %submitted.tx_hash,
+                    "cNGN payment submitted, awaiting ledger confirmation"
+                );
+                metadata.submitted_hash = Some(submitted.tx_hash);
+                metadata.submitted_valid_until = Some(submitted.valid_until);
+                metadata.fulfillment_sequence = Some(submitted.sequence);
+                repo.update_status_with_metadata(
+                    &tx_id,
+                    OrchestrationState::ProcessingBlockchain.to_db_status(),
+                    metadata.to_json(),
+                )
+                .await?;
+                Ok(())
+            }
+            Err(e) => match e.in_flight_hash.clone() {
+                None => {
+                    warn!(transaction_id = %tx_id, error = %e, "cNGN payment not submitted");
+                    metadata.submitted_at = None;
+                    metadata.fulfillment_sequence = None;
+                    let retryable = is_retryable_build_error(&e.error);
+                    self.retry_or_fail(repo, tx, metadata, e.to_string(), retryable)
+                        .await
+                }
+                Some(hash) => {
+                    // The envelope may still land; leave the transaction in
+                    // `processing` and let the monitor settle it by hash.
+                    warn!(
+                        transaction_id = %tx_id,
+                        tx_hash = %hash,
+                        error = %e,
+                        "cNGN payment submission outcome unknown, deferring to monitor"
+                    );
+                    metadata.submitted_hash = Some(hash);
+                    metadata.submitted_valid_until = e.in_flight_valid_until;
+                    repo.update_status_with_metadata(
+                        &tx_id,
+                        OrchestrationState::ProcessingBlockchain.to_db_status(),
+                        metadata.to_json(),
+                    )
+                    .await?;
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// Schedule another attempt with exponential backoff, or give up and hand
+    /// fail the transaction and flag its fiat for a manual refund.
+    async fn retry_or_fail(
+        &self,
+        repo: &TransactionRepository,
+        tx: &Transaction,
+        mut metadata: OnrampFulfillmentMetadata,
+        reason: String,
+        retryable: bool,
+    ) -> Result<(), OnrampError> {
+        let tx_id = tx.transaction_id.to_string();
+        let attempt = metadata.fulfillment_attempts + 1;
+        metadata.fulfillment_attempts = attempt;
+        metadata.failure_reason = Some(reason);
+
+        let give_up = !retryable || attempt >= self.config.max_retries;
+        let next_retry = chrono::Utc::now() + retry_delay(attempt);
+        if !give_up {
+            metadata.next_fulfillment_after = Some(next_retry.to_rfc3339());
+        }
+
+        // No envelope is in flight at this point. The JSONB merge keeps keys
+        // that are merely omitted, so a rejected hash has to be nulled out or
+        // the monitor would keep polling for it.
+        let mut update = metadata.to_json();
+        update["submitted_hash"] = JsonValue::Null;
+        update["submitted_valid_until"] = JsonValue::Null;
+
+        if give_up {
+            // Nothing refunds onramp fiat automatically, so an operator has to
+            update["needs_manual_refund"] = JsonValue::Bool(true);
+            error!(
+                transaction_id = %tx_id,
+                attempts = attempt,
+                amount = %tx.from_amount,
+                currency = %tx.from_currency,
+                "onramp fulfillment failed permanently, fiat needs a manual refund"
+            );
+            let failed = repo
+                .update_status_with_metadata(
+                    &tx_id,
+                    OrchestrationState::Failed.to_db_status(),
+                    update,
+                )
+                .await?;
+            self.notify_failed(tx).await;
+            self.publish(events::TRANSACTION_FAILED, &failed).await;
+            return Ok(());
+        }
+
+        repo.update_status_with_metadata(
+            &tx_id,
+            OrchestrationState::PaymentConfirmed.to_db_status(),
+            update,
+        )
+        .await?;
+        info!(transaction_id = %tx_id, attempt = attempt, next_retry = %next_retry, "scheduled onramp fulfillment retry");
+
+        Ok(())
+    }
+
+    async fn publish(&self, event_type: &str, tx: &Transaction) {
+        if let Some(webhooks) = &self.merchant_webhooks {
+            webhooks.notify(event_type, tx, None).await;
+        }
+    }
+
+    async fn notify_failed(&self, tx: &Transaction) {
+        self.notification_service
+            .send_notification(
+                tx,
+                NotificationType::OnrampFailed,
+                "cNGN could not be delivered to your wallet; our team will refund your payment",
+            )
+            .await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Text memo attached to onramp payments, truncated to Stellar's 28-byte limit.
+fn onramp_memo(tx_id: &str) -> String {
+    let memo = format!("ONRAMP-{}", tx_id);
+    if memo.len() > 28 {
+        memo[..28].to_string()
+    } else {
+        memo
+    }
+}
+
+/// Delay before the next fulfillment attempt: 30s, 60s, 120s, ... capped at 10m.
+fn retry_delay(attempt: u32) -> chrono::Duration {
+    let secs = 30i64.saturating_mul(1i64 << attempt.saturating_sub(1).min(5));
+    chrono::Duration::seconds(secs.min(600))
+}
+
+/// Errors raised while building a payment that are worth retrying. Missing
+/// trustlines, unfunded destinations and a low hot wallet can all be fixed
+/// without operator intervention on the transaction itself.
+fn is_retryable_build_error(err: &StellarError) -> bool {
+    !matches!(
+        err,
+        StellarError::InvalidAddress { .. }
+            | StellarError::ConfigError { .. }
+            | StellarError::SigningError { .. }
+            | StellarError::SerializationError { .. }
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::stellar::client::StellarClient;
+    use crate::chains::stellar::config::StellarConfig;
+    use crate::chains::stellar::signer::{LocalSigner, TransactionSigner};
+    use crate::chains::stellar::submission::SubmissionConfig;
+
+    #[test]
+    fn onramp_memo_fits_stellar_text_limit() {
+        let memo = onramp_memo("4f8c2b1e-9a7d-4c3b-8e2f-1a2b3c4d5e6f");
+        assert_eq!(memo.len(), 28);
+        assert!(memo.starts_with("ONRAMP-4f8c2b1e"));
+        assert_eq!(onramp_memo("abc"), "ONRAMP-abc");
+    }
+
+    #[test]
+    fn retry_delay_grows_and_is_capped() {
+        assert_eq!(retry_delay(1).num_seconds(), 30);
+        assert_eq!(retry_delay(2).num_seconds(), 60);
+        assert_eq!(retry_delay(3).num_seconds(), 120);
+        assert_eq!(retry_delay(10).num_seconds(), 600);
+    }
+
+    #[test]
+    fn submission_errors_are_classified() {
+        assert!(StellarError::RateLimitError.is_definitive_rejection());
+        assert!(StellarError::transaction_failed(
+            "Horizon submit failed (status 400 Bad Request): tx_bad_seq"
+        )
+        .is_definitive_rejection());
+        assert!(!StellarError::transaction_failed(
+            "Horizon submit failed (status 504 Gateway Timeout): timeout"
+        )
+        .is_definitive_rejection());
+        assert!(!StellarError::timeout_error(30).is_definitive_rejection());
+        assert!(!StellarError::network_error("reset").is_definitive_rejection());
+    }
+
+    #[test]
+    fn build_errors_are_classified() {
+        assert!(is_retryable_build_error(&StellarError::network_error(
+            "down"
+        )));
+        assert!(is_retryable_build_error(&StellarError::transaction_failed(
+            "destination has no cNGN trustline"
+        )));
+        assert!(!is_retryable_build_error(&StellarError::invalid_address(
+            "bad"
+        )));
+        assert!(!is_retryable_build_error(&StellarError::signing_error(
+            "bad key"
+        )));
+    }
+
+    #[test]
+    fn fulfillment_metadata_ignores_unrelated_fields() {
+        let json = serde_json::json!({
+            "quote_id": "q-1",
+            "provider": "paystack",
+            "fulfillment_attempts": 2,
+            "failure_reason": "network"
+        });
+        let metadata = OnrampFulfillmentMetadata::from_json(&json);
+        assert_eq!(metadata.fulfillment_attempts, 2);
+        assert_eq!(metadata.failure_reason.as_deref(), Some("network"));
+        assert!(metadata.submitted_hash.is_none());
+
+        let out = metadata.to_json();
+        assert!(out.get("quote_id").is_none());
+        assert!(out.get("submitted_hash").is_none());
+    }
+
+    #[test]
+    fn retry_window_is_respected() {
+        let now = chrono::Utc::now();
+        let mut metadata = OnrampFulfillmentMetadata::default();
+        assert!(!metadata.is_waiting_for_retry(now));
+
+        metadata.next_fulfillment_after = Some((now + chrono::Duration::seconds(60)).to_rfc3339());
+        assert!(metadata.is_waiting_for_retry(now));
+
+        metadata.next_fulfillment_after = Some((now - chrono::Duration::seconds(60)).to_rfc3339());
+        assert!(!metadata.is_waiting_for_retry(now));
+    }
+
+    #[tokio::test]
+    async fn config_validation_requires_secrets() {
+        let mut config = OnrampProcessorConfig::default();
+        assert!(config.validate().is_err());
+
+        let signer = LocalSigner::from_secret(
+            stellar_strkey::ed25519::PrivateKey([1; 32])
+                .to_string()
+                .as_str(),
+        )
+        .unwrap();
+        let account_id = signer.account_id().to_string();
+        config.payout_queue = Some(SubmissionQueue::new(
+            StellarClient::new(StellarConfig::default()).unwrap(),
+            Arc::new(signer),
+            SubmissionConfig::default(),
+        ));
+        assert!(config.validate().is_err());
+
+        config.system_wallet_address = "GADDRESS".to_string();
+        assert!(config.validate().is_err());
+
+        config.system_wallet_address = account_id;
+        assert!(config.validate().is_ok());
+    }
+}
@@ -1,14 +1,38 @@
 use crate::chains::stellar::client::{HorizonTransactionRecord, StellarClient};
+use crate::chains::stellar::streaming::{HorizonPaymentRecord, PaymentStream};
+use crate::database::deposit_memo_repository::DepositMemoRepository;
+use crate::database::error::DatabaseError;
+use crate::database::incoming_payment_repository::{
+    IncomingPaymentRepository, IngestedPayment, IngestionOutcome,
+};
 use crate::database::repository::Repository;
-use crate::database::transaction_repository::TransactionRepository;
+use crate::database::stellar_cursor_repository::StellarCursorRepository;
+use crate::database::transaction_repository::{Transaction, TransactionRepository};
+use crate::database::unmatched_deposit_repository::{
+    NewUnmatchedDeposit, UnmatchedDepositRepository, UnmatchedReason,
+};
 use crate::database::webhook_repository::WebhookRepository;
+use crate::services::merchant_webhooks::{events, MerchantWebhookService};
+use crate::services::payment_orchestrator::OrchestrationState;
 use serde_json::{json, Value as JsonValue};
-use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::watch;
-use tracing::{error, info, warn};
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// Cursor name for the system wallet scan in `stellar_cursors`. Streaming,
+/// polling and backfill all read the account's payments endpoint, so they
+/// share one operation paging token and resume from where another stopped.
+pub const INCOMING_CURSOR_STREAM: &str = "incoming";
+
+/// Seconds past an envelope's upper time bound before it is taken to be
+/// unable to apply, covering a ledger that was closing at the time
+const ENVELOPE_EXPIRY_GRACE_SECONDS: u64 = 10;
+
 // ---------------------------------------------------------------------------
 // Custom error type
 // ---------------------------------------------------------------------------
@@ -51,6 +75,26 @@ impl From<anyhow::Error> for MonitorError {
 // Configuration
 // ---------------------------------------------------------------------------
 
+/// How incoming payments to the system wallet are detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncomingMode {
+    /// Page through the account's payments every `poll_interval`.
+    Poll,
+    /// Follow Horizon's payments event stream, polling only while the stream
+    /// is down.
+    Stream,
+}
+
+impl IncomingMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "poll" | "polling" => Some(Self::Poll),
+            "stream" | "streaming" | "sse" => Some(Self::Stream),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TransactionMonitorConfig {
     /// How often the worker wakes up to poll Stellar.
@@ -65,10 +109,20 @@ pub struct TransactionMonitorConfig {
     pub pending_batch_size: i64,
     /// How far back (in hours) to search for pending transactions.
     pub monitoring_window_hours: i32,
-    /// Maximum number of incoming transactions fetched per cursor page.
+    /// Maximum number of incoming payments fetched per cursor page.
     pub incoming_limit: usize,
+    /// Pages fetched per poll while catching up after startup or a rescan.
+    pub backfill_max_pages: u32,
     /// If set, the worker also scans this address for incoming cNGN payments.
     pub system_wallet_address: Option<String>,
+    /// Streaming or polling detection of incoming payments.
+    pub incoming_mode: IncomingMode,
+    /// A stream that delivers nothing for this long is reopened.
+    pub stream_idle_timeout: Duration,
+    /// Delay before reconnecting after a stream failure; doubles per
+    /// consecutive failure up to `stream_reconnect_max`.
+    pub stream_reconnect_base: Duration,
+    pub stream_reconnect_max: Duration,
 }
 
 impl Default for TransactionMonitorConfig {
@@ -80,7 +134,12 @@ impl Default for TransactionMonitorConfig {
             pending_batch_size: 200,
             monitoring_window_hours: 24,
             incoming_limit: 100,
+            backfill_max_pages: 10,
             system_wallet_address: None,
+            incoming_mode: IncomingMode::Stream,
+            stream_idle_timeout: Duration::from_secs(90),
+            stream_reconnect_base: Duration::from_secs(1),
+            stream_reconnect_max: Duration::from_secs(60),
         }
     }
 }
@@ -116,9 +175,39 @@ impl TransactionMonitorConfig {
             .ok()
             .and_then(|v| v.parse::<usize>().ok())
             .unwrap_or(cfg.incoming_limit);
+        cfg.backfill_max_pages = std::env::var("TX_MONITOR_BACKFILL_MAX_PAGES")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|pages| *pages > 0)
+            .unwrap_or(cfg.backfill_max_pages);
         cfg.system_wallet_address = std::env::var("SYSTEM_WALLET_ADDRESS").ok();
+        cfg.incoming_mode = std::env::var("TX_MONITOR_INCOMING_MODE")
+            .ok()
+            .and_then(|v| IncomingMode::parse(&v))
+            .unwrap_or(cfg.incoming_mode);
+        cfg.stream_idle_timeout = Duration::from_secs(
+            std::env::var("TX_MONITOR_STREAM_IDLE_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.stream_idle_timeout.as_secs()),
+        );
+        cfg.stream_reconnect_max = Duration::from_secs(
+            std::env::var("TX_MONITOR_STREAM_RECONNECT_MAX_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(cfg.stream_reconnect_max.as_secs()),
+        );
         cfg
     }
+
+    /// Delay before the next stream connection attempt after `failures`
+    /// consecutive failures (1-based).
+    pub fn stream_reconnect_delay(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.stream_reconnect_base
+            .saturating_mul(factor)
+            .min(self.stream_reconnect_max)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -130,6 +219,13 @@ pub struct TransactionMonitorWorker {
     stellar_client: StellarClient,
     config: TransactionMonitorConfig,
     incoming_cursor: Option<String>,
+    /// Whether `incoming_cursor` has been loaded or seeded. Scanning waits for
+    /// it, since no cursor would replay the wallet's whole history.
+    cursor_ready: bool,
+    /// Paging through history up to the chain head; the stream is only opened
+    /// once a short page shows nothing is left to backfill.
+    catching_up: bool,
+    merchant_webhooks: Option<Arc<MerchantWebhookService>>,
 }
 
 impl TransactionMonitorWorker {
@@ -143,9 +239,18 @@ impl TransactionMonitorWorker {
             stellar_client,
             config,
             incoming_cursor: None,
+            cursor_ready: false,
+            catching_up: true,
+            merchant_webhooks: None,
         }
     }
 
+    /// Tell partners when the onramps this worker settles complete or fail
+    pub fn with_merchant_webhooks(mut self, service: Arc<MerchantWebhookService>) -> Self {
+        self.merchant_webhooks = Some(service);
+        self
+    }
+
     pub async fn run(mut self, mut shutdown_rx: watch::Receiver<bool>) {
         info!(
             poll_interval_secs = self.config.poll_interval.as_secs(),
@@ -153,10 +258,47 @@ impl TransactionMonitorWorker {
             max_retries = self.config.max_retries,
             monitoring_window_hours = self.config.monitoring_window_hours,
             has_system_wallet = self.config.system_wallet_address.is_some(),
+            incoming_mode = ?self.config.incoming_mode,
             "stellar transaction monitor worker started"
         );
 
+        self.load_incoming_cursor().await;
+
+        let mut poll = tokio::time::interval_at(
+            Instant::now() + self.config.poll_interval,
+            self.config.poll_interval,
+        );
+        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // While the stream is down the poll cycle also scans for incoming payments
+        let mut stream: Option<PaymentStream> = None;
+        let mut stream_failures: u32 = 0;
+        let mut reconnect_at = Instant::now();
+
         loop {
+            if stream.is_none() && self.wants_stream() && Instant::now() >= reconnect_at {
+                match self.open_stream().await {
+                    Ok(opened) => {
+                        if stream_failures > 0 {
+                            info!("horizon payment stream reconnected");
+                        }
+                        stream_failures = 0;
+                        stream = Some(opened);
+                    }
+                    Err(e) => {
+                        stream_failures += 1;
+                        let delay = self.config.stream_reconnect_delay(stream_failures);
+                        reconnect_at = Instant::now() + delay;
+                        warn!(
+                            error = %e,
+                            failures = stream_failures,
+                            retry_in_secs = delay.as_secs(),
+                            "failed to open horizon payment stream; polling until it reconnects"
+                        );
+                    }
+                }
+            }
+
             tokio::select! {
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
@@ -164,20 +306,52 @@ impl TransactionMonitorWorker {
                         break;
                     }
                 }
-                _ = tokio::time::sleep(self.config.poll_interval) => {
-                    if let Err(e) = self.run_cycle().await {
+                _ = poll.tick() => {
+                    if self.take_rescan_request().await {
+                        stream = None;
+                    }
+                    if let Err(e) = self.run_cycle(stream.is_none()).await {
                         warn!(error = %e, "transaction monitor cycle failed");
                     }
                 }
+                _ = tokio::time::sleep_until(reconnect_at), if stream.is_none() && self.wants_stream() => {}
+                next = next_streamed_payment(&mut stream) => {
+                    let result = match next {
+                        Ok(Some(payment)) => self.handle_streamed_payment(payment).await,
+                        Ok(None) => {
+                            debug!("horizon payment stream ended, reconnecting");
+                            stream = None;
+                            reconnect_at = Instant::now() + self.config.stream_reconnect_base;
+                            continue;
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+                    if let Err(e) = result {
+                        // Reconnecting resumes from the last persisted cursor,
+                        // so the failed payment is delivered again
+                        stream = None;
+                        stream_failures += 1;
+                        let delay = self.config.stream_reconnect_delay(stream_failures);
+                        reconnect_at = Instant::now() + delay;
+                        warn!(
+                            error = %e,
+                            failures = stream_failures,
+                            retry_in_secs = delay.as_secs(),
+                            "horizon payment stream failed; polling until it reconnects"
+                        );
+                    }
+                }
             }
         }
 
         info!("stellar transaction monitor worker stopped");
     }
 
-    async fn run_cycle(&mut self) -> anyhow::Result<()> {
+    async fn run_cycle(&mut self, scan_incoming: bool) -> anyhow::Result<()> {
         self.process_pending_transactions().await?;
-        self.scan_incoming_transactions().await?;
+        if scan_incoming {
+            self.scan_incoming_payments().await?;
+        }
         Ok(())
     }
 
@@ -198,12 +372,14 @@ impl TransactionMonitorWorker {
             let tx_id = tx.transaction_id.to_string();
 
             // ------------------------------------------------------------------
-            // 1. Absolute timeout check (uses created_at so retries don't reset
+            // 1. Absolute timeout check (counts from the latest submission, or
+            //    creation if there was none, so monitor retries don't reset
             //    the clock).
             // ------------------------------------------------------------------
-            if is_timed_out(tx.created_at, self.config.pending_timeout) {
+            let started_at = timeout_started_at(&tx.metadata, tx.created_at);
+            if is_timed_out(started_at, self.config.pending_timeout) {
                 let elapsed = chrono::Utc::now()
-                    .signed_duration_since(tx.created_at)
+                    .signed_duration_since(started_at)
                     .to_std()
                     .unwrap_or_default();
                 let err = MonitorError::ConfirmationTimeout {
@@ -211,8 +387,7 @@ impl TransactionMonitorWorker {
                     elapsed_secs: elapsed.as_secs(),
                 };
                 warn!(error = %err, "transaction exceeded absolute deadline");
-                self.handle_absolute_timeout(&tx_id, Some(tx.metadata.clone()))
-                    .await?;
+                self.handle_absolute_timeout(&tx).await?;
                 continue;
             }
 
@@ -245,13 +420,8 @@ impl TransactionMonitorWorker {
             // ------------------------------------------------------------------
             match self.stellar_client.get_transaction_by_hash(&tx_hash).await {
                 Ok(record) => {
-                    self.handle_horizon_status(
-                        &tx_id,
-                        record,
-                        Some(tx.metadata.clone()),
-                        retry_count,
-                    )
-                    .await?;
+                    self.handle_horizon_status(&tx, record, Some(tx.metadata.clone()), retry_count)
+                        .await?;
                 }
                 Err(e) => {
                     let message = e.to_string().to_lowercase();
@@ -263,7 +433,7 @@ impl TransactionMonitorWorker {
                     {
                         continue;
                     }
-                    self.fail_or_retry(&tx_id, Some(tx.metadata.clone()), &e.to_string())
+                    self.fail_or_retry(&tx, Some(tx.metadata.clone()), &e.to_string())
                         .await?;
                 }
             }
@@ -278,11 +448,12 @@ impl TransactionMonitorWorker {
 
     async fn handle_horizon_status(
         &self,
-        transaction_id: &str,
+        tx: &Transaction,
         record: HorizonTransactionRecord,
         metadata: Option<JsonValue>,
         attempts: u32,
     ) -> anyhow::Result<()> {
+        let transaction_id = &tx.transaction_id.to_string();
         let mut updated = metadata.unwrap_or_else(|| json!({}));
         merge_status_fields(&mut updated, &record);
 
@@ -294,7 +465,7 @@ impl TransactionMonitorWorker {
                 .await?;
 
             // Also write the confirmed hash to the dedicated column.
-            tx_repo
+            let completed = tx_repo
                 .update_blockchain_hash(transaction_id, &record.hash)
                 .await?;
 
@@ -308,35 +479,69 @@ impl TransactionMonitorWorker {
 
             self.log_webhook_event(transaction_id, "stellar.transaction.confirmed", updated)
                 .await;
+            self.notify_onramp(events::TRANSACTION_COMPLETED, &completed)
+                .await;
         } else {
             let reason = record
                 .result_xdr
                 .as_deref()
                 .unwrap_or("transaction failed on horizon");
-            self.fail_or_retry(transaction_id, Some(updated), reason)
-                .await?;
+            self.fail_or_retry(tx, Some(updated), reason).await?;
         }
         Ok(())
     }
 
-    /// Called when the absolute `pending_timeout` (measured from `created_at`)
-    /// is exceeded. The transaction is failed immediately without further retries.
-    async fn handle_absolute_timeout(
-        &self,
-        transaction_id: &str,
-        metadata: Option<JsonValue>,
-    ) -> anyhow::Result<()> {
-        let mut updated = metadata.unwrap_or_else(|| json!({}));
+    /// Called when the absolute `pending_timeout` is exceeded. A submitted
+    /// envelope is looked up once more first: it may have landed since the
+    /// last poll, and one Horizon still doesn't know is retried once its
+    /// recorded time bounds have passed. Anything else is failed immediately
+    /// without further retries.
+    async fn handle_absolute_timeout(&self, tx: &Transaction) -> anyhow::Result<()> {
+        let transaction_id = &tx.transaction_id.to_string();
+        if let Some(tx_hash) = extract_tx_hash(Some(&tx.metadata)) {
+            match self.stellar_client.get_transaction_by_hash(&tx_hash).await {
+                Ok(record) => {
+                    let attempts = get_retry_count(Some(&tx.metadata));
+                    return self
+                        .handle_horizon_status(tx, record, Some(tx.metadata.clone()), attempts)
+                        .await;
+                }
+                Err(e) if !e.to_string().to_lowercase().contains("not found") => {
+                    // The payment may have landed; don't fail it blind
+                    warn!(
+                        transaction_id = %transaction_id,
+                        tx_hash = %tx_hash,
+                        error = %e,
+                        "could not look up timed-out transaction, will check again"
+                    );
+                    return Ok(());
+                }
+                Err(_) if retry_status(&tx.r#type) != "pending" => {
+                    return self
+                        .fail_or_retry(
+                            tx,
+                            Some(tx.metadata.clone()),
+                            "confirmation timeout: transaction never reached the ledger",
+                        )
+                        .await;
+                }
+                Err(_) => {}
+            }
+        }
+
+        let mut updated = tx.metadata.clone();
         updated["last_monitor_error"] = json!("absolute pending timeout exceeded");
         updated["timed_out_at"] = json!(chrono::Utc::now().to_rfc3339());
 
         let tx_repo = TransactionRepository::new(self.pool.clone());
-        tx_repo
+        let failed = tx_repo
             .update_status_with_metadata(transaction_id, "failed", updated.clone())
             .await?;
 
         self.log_webhook_event(transaction_id, "stellar.transaction.timeout", updated)
             .await;
+        self.notify_onramp(events::TRANSACTION_FAILED, &failed)
+            .await;
 
         warn!(
             transaction_id = %transaction_id,
@@ -347,10 +552,11 @@ impl TransactionMonitorWorker {
 
     async fn fail_or_retry(
         &self,
-        transaction_id: &str,
+        tx: &Transaction,
         metadata: Option<JsonValue>,
         error_message: &str,
     ) -> anyhow::Result<()> {
+        let transaction_id = &tx.transaction_id.to_string();
         let retries = next_retry_count(metadata.as_ref());
         let retryable = is_retryable_error(error_message);
         let mut updated = metadata.unwrap_or_else(|| json!({}));
@@ -362,8 +568,25 @@ impl TransactionMonitorWorker {
         let tx_repo = TransactionRepository::new(self.pool.clone());
 
         if retryable && retries <= self.config.max_retries {
+            let status = retry_status(&tx.r#type);
+            if status != "pending" {
+                let now = chrono::Utc::now().timestamp().max(0) as u64;
+                if envelope_may_still_apply(&updated, now) {
+                    // Rebuilding now could pay twice; look again next pass
+                    info!(
+                        transaction_id = %transaction_id,
+                        error = %error_message,
+                        "payout envelope is still within its time bounds, not rebuilding yet"
+                    );
+                    return Ok(());
+                }
+                // The payment is rebuilt, so the old hash must not be polled
+                updated["submitted_hash"] = JsonValue::Null;
+                updated["submitted_valid_until"] = JsonValue::Null;
+                updated["submitted_at"] = JsonValue::Null;
+            }
             tx_repo
-                .update_status_with_metadata(transaction_id, "pending", updated.clone())
+                .update_status_with_metadata(transaction_id, status, updated.clone())
                 .await?;
 
             let next_backoff = backoff_delay(retries);
@@ -375,7 +598,7 @@ impl TransactionMonitorWorker {
                 "transaction failed with retryable error; scheduled for retry"
             );
         } else {
-            tx_repo
+            let failed = tx_repo
                 .update_status_with_metadata(transaction_id, "failed", updated.clone())
                 .await?;
 
@@ -387,6 +610,8 @@ impl TransactionMonitorWorker {
 
             self.log_webhook_event(transaction_id, "stellar.transaction.failed", updated)
                 .await;
+            self.notify_onramp(events::TRANSACTION_FAILED, &failed)
+                .await;
         }
         Ok(())
     }
@@ -395,150 +620,405 @@ impl TransactionMonitorWorker {
     // Incoming payment scanning
     // -----------------------------------------------------------------------
 
-    async fn scan_incoming_transactions(&mut self) -> anyhow::Result<()> {
-        let system_wallet = match self.config.system_wallet_address.as_deref() {
-            Some(addr) => addr,
-            None => return Ok(()),
+    /// Page through payments after the cursor. While catching up this keeps
+    /// going (up to `backfill_max_pages`) until Horizon returns a short page.
+    async fn scan_incoming_payments(&mut self) -> anyhow::Result<()> {
+        if self.config.system_wallet_address.is_none() {
+            return Ok(());
+        }
+        if !self.cursor_ready {
+            self.load_incoming_cursor().await;
+            if !self.cursor_ready {
+                return Ok(());
+            }
+        }
+        let max_pages = if self.catching_up {
+            self.config.backfill_max_pages
+        } else {
+            1
         };
 
-        let page = self
-            .stellar_client
-            .list_account_transactions(
-                system_wallet,
-                self.config.incoming_limit,
-                self.incoming_cursor.as_deref(),
-            )
-            .await?;
+        for _ in 0..max_pages {
+            let system_wallet = self.config.system_wallet_address.as_deref().unwrap_or("");
+            let page = self
+                .stellar_client
+                .list_account_payments(
+                    system_wallet,
+                    self.config.incoming_limit,
+                    self.incoming_cursor.as_deref(),
+                )
+                .await?;
 
-        let mut newest_cursor = self.incoming_cursor.clone();
-        for tx in page.records {
-            if !tx.successful {
-                continue;
+            let fetched = page.records.len();
+            let Some(newest_cursor) = page.records.last().map(|p| p.paging_token.clone()) else {
+                self.finish_catch_up();
+                return Ok(());
+            };
+            for payment in &page.records {
+                self.ingest_payment(payment).await?;
             }
+            self.advance_incoming_cursor(newest_cursor).await;
 
-            if let Some(cursor) = tx.paging_token.clone() {
-                newest_cursor = Some(cursor);
+            if fetched < self.config.incoming_limit.min(200) {
+                self.finish_catch_up();
+                return Ok(());
             }
+        }
+        Ok(())
+    }
 
-            let memo = match tx.memo.as_deref() {
-                Some(m) if !m.trim().is_empty() => m,
-                _ => continue,
-            };
+    /// Ingest a streamed payment, then move the cursor past it.
+    async fn handle_streamed_payment(
+        &mut self,
+        payment: HorizonPaymentRecord,
+    ) -> anyhow::Result<()> {
+        self.ingest_payment(&payment).await?;
+        self.advance_incoming_cursor(payment.paging_token).await;
+        Ok(())
+    }
 
-            let looks_like_incoming = self
-                .is_incoming_cngn_payment(&tx.hash, system_wallet)
-                .await
-                .unwrap_or(false);
-            if !looks_like_incoming {
-                continue;
+    /// Apply a cNGN deposit to the system wallet exactly once per
+    /// `(tx_hash, op_index)`, however often polling, streaming or a rescan
+    /// delivers it.
+    async fn ingest_payment(&self, payment: &HorizonPaymentRecord) -> anyhow::Result<()> {
+        let system_wallet = self.config.system_wallet_address.as_deref().unwrap_or("");
+        let is_deposit = payment.transaction_successful
+            && payment.is_payment()
+            && is_cngn_deposit(
+                payment.to.as_deref().unwrap_or(""),
+                payment.asset_code.as_deref().unwrap_or(""),
+                payment.asset_issuer.as_deref().unwrap_or(""),
+                system_wallet,
+                &cngn_issuer(),
+            );
+        if !is_deposit {
+            return Ok(());
+        }
+
+        let Some(op_index) = payment.operation_index() else {
+            warn!(
+                tx_hash = %payment.transaction_hash,
+                paging_token = %payment.paging_token,
+                "incoming payment has an unrecognised paging token; skipping"
+            );
+            return Ok(());
+        };
+        let Some(tx) = payment.transaction.as_ref() else {
+            warn!(
+                tx_hash = %payment.transaction_hash,
+                "incoming payment has no joined transaction; skipping"
+            );
+            return Ok(());
+        };
+
+        if IncomingPaymentRepository::new(self.pool.clone())
+            .is_ingested(&payment.transaction_hash, op_index)
+            .await?
+        {
+            debug!(
+                tx_hash = %payment.transaction_hash,
+                op_index,
+                "incoming payment already ingested"
+            );
+            return Ok(());
+        }
+
+        let amount = payment
+            .amount
+            .as_deref()
+            .and_then(|a| a.parse::<BigDecimal>().ok());
+        let memo = tx.memo.as_deref().map(str::trim).filter(|m| !m.is_empty());
+        // A muxed destination names the order on its own; the memo is ignored
+        let muxed_id = payment.muxed_id();
+        let key = match (muxed_id, memo) {
+            (Some(id), _) => Some(DepositKey::MuxedId(id)),
+            (None, Some(memo)) => Some(DepositKey::Memo(memo)),
+            (None, None) => None,
+        };
+        // Crediting the order, quarantining any excess and recording the
+        // operation commit together, so a crash can't credit a deposit that is
+        // then delivered and credited again
+        let mut db_tx = self.pool.begin().await.map_err(DatabaseError::from_sqlx)?;
+        let applied = match key {
+            Some(key) => {
+                self.apply_incoming_payment(&mut db_tx, key, tx, amount.as_ref())
+                    .await?
             }
+            None => AppliedDeposit {
+                outcome: IngestionOutcome::NoMemo,
+                transaction_id: None,
+                expected_amount: None,
+                credited: None,
+            },
+        };
 
-            let tx_repo = TransactionRepository::new(self.pool.clone());
-            let (tx_id_str, is_offramp) = if memo.starts_with("WD-") {
-                (&memo[3..], true)
-            } else {
-                (memo, false)
-            };
+        match amount.as_ref().and_then(|received| {
+            quarantined_share(
+                applied.outcome,
+                muxed_id.is_some(),
+                received,
+                applied.expected_amount.as_ref(),
+            )
+        }) {
+            Some((reason, held)) => {
+                let quarantined = UnmatchedDepositRepository::quarantine_in(
+                    &mut db_tx,
+                    &NewUnmatchedDeposit {
+                        tx_hash: payment.transaction_hash.clone(),
+                        op_index,
+                        source_account: payment.from.clone().unwrap_or_default(),
+                        amount: held.clone(),
+                        asset_code: payment.asset_code.clone().unwrap_or_default(),
+                        asset_issuer: payment.asset_issuer.clone(),
+                        memo: memo.map(str::to_string),
+                        muxed_id,
+                        ledger: tx.ledger.or_else(|| payment.ledger()),
+                        reason,
+                        transaction_id: applied.transaction_id,
+                    },
+                )
+                .await?;
+                if let Some(deposit) = quarantined {
+                    warn!(
+                        deposit_id = %deposit.id,
+                        tx_hash = %payment.transaction_hash,
+                        amount = %held,
+                        reason = reason.as_str(),
+                        "incoming cNGN quarantined for operator review"
+                    );
+                }
+            }
+            None if amount.is_none() && applied.outcome != IngestionOutcome::Matched => warn!(
+                tx_hash = %payment.transaction_hash,
+                amount = ?payment.amount,
+                "incoming payment has an unreadable amount; not quarantined"
+            ),
+            None => {}
+        }
 
-            let tx_repo = TransactionRepository::new(self.pool.clone());
-            match tx_repo.find_by_id(tx_id_str).await {
-                Ok(Some(db_tx)) => {
-                    let is_pending = db_tx.status == "pending"
-                        || db_tx.status == "processing"
-                        || db_tx.status == "pending_payment";
-                    if !is_pending {
-                        continue;
-                    }
+        IncomingPaymentRepository::record_in(
+            &mut db_tx,
+            &IngestedPayment {
+                tx_hash: payment.transaction_hash.clone(),
+                op_index,
+                account_address: system_wallet.to_string(),
+                paging_token: payment.paging_token.clone(),
+                ledger: tx.ledger.or_else(|| payment.ledger()),
+                amount,
+                asset_code: payment.asset_code.clone(),
+                memo: memo.map(str::to_string),
+                transaction_id: applied.transaction_id,
+                outcome: applied.outcome,
+            },
+        )
+        .await?;
+        db_tx.commit().await.map_err(DatabaseError::from_sqlx)?;
+
+        if let Some(credited) = applied.credited {
+            credited.announce(&self.pool).await;
+        }
+        Ok(())
+    }
 
-                    let mut metadata = db_tx.metadata.clone();
-                    metadata["incoming_hash"] = json!(tx.hash);
-                    metadata["incoming_ledger"] = json!(tx.ledger);
-                    metadata["incoming_confirmed_at"] = json!(chrono::Utc::now().to_rfc3339());
+    /// Move a pending transaction forward for a confirmed cNGN deposit whose
+    /// muxed ID or memo names it. Generated deposit memos are looked up first;
+    /// bare transaction IDs (optionally `WD-` prefixed) are still accepted for
+    /// orders created before memos were issued.
+    async fn apply_incoming_payment(
+        &self,
+        conn: &mut PgConnection,
+        key: DepositKey<'_>,
+        tx: &HorizonTransactionRecord,
+        amount: Option<&BigDecimal>,
+    ) -> anyhow::Result<AppliedDeposit> {
+        let unmatched = AppliedDeposit {
+            outcome: IngestionOutcome::Unmatched,
+            transaction_id: None,
+            expected_amount: None,
+            credited: None,
+        };
 
-                    let next_status = if is_offramp || db_tx.r#type == "offramp" {
-                        "cngn_received"
-                    } else {
-                        "completed"
+        let memos = DepositMemoRepository::new(self.pool.clone());
+        let (transaction_id, is_offramp) = match key {
+            DepositKey::MuxedId(muxed_id) => {
+                match memos.find_transaction_id_by_muxed_id(muxed_id).await? {
+                    Some(id) => (id, false),
+                    None => {
+                        self.log_unmatched_incoming(key, tx).await;
+                        return Ok(unmatched);
+                    }
+                }
+            }
+            DepositKey::Memo(memo) => match memos.find_transaction_id(memo).await? {
+                Some(id) => (id, false),
+                None => {
+                    let (tx_id_str, is_offramp) = match memo.strip_prefix("WD-") {
+                        Some(rest) => (rest, true),
+                        None => (memo, false),
                     };
+                    match Uuid::parse_str(tx_id_str) {
+                        Ok(id) => (id, is_offramp),
+                        Err(_) => {
+                            self.log_unmatched_incoming(key, tx).await;
+                            return Ok(unmatched);
+                        }
+                    }
+                }
+            },
+        };
 
-                    tx_repo
-                        .update_status_with_metadata(
-                            &db_tx.transaction_id.to_string(),
-                            next_status,
-                            metadata.clone(),
-                        )
-                        .await?;
+        let tx_repo = TransactionRepository::new(self.pool.clone());
+        let Some(db_tx) = tx_repo.find_by_id(&transaction_id.to_string()).await? else {
+            self.log_unmatched_incoming(key, tx).await;
+            return Ok(unmatched);
+        };
 
-                    // Also persist the confirmed hash to the dedicated column.
-                    tx_repo
-                        .update_blockchain_hash(&db_tx.transaction_id.to_string(), &tx.hash)
-                        .await?;
+        let not_pending = AppliedDeposit {
+            outcome: IngestionOutcome::NotPending,
+            transaction_id: Some(db_tx.transaction_id),
+            expected_amount: None,
+            credited: None,
+        };
+        if !is_awaiting_deposit(&db_tx.status) {
+            return Ok(not_pending);
+        }
 
-                    info!(
-                        transaction_id = %db_tx.transaction_id,
-                        incoming_hash = %tx.hash,
-                        ledger = ?tx.ledger,
-                        status = next_status,
-                        "incoming cNGN payment matched and updated"
-                    );
+        // A deposit short of the order is held for an operator; the order
+        // keeps waiting rather than being credited for money it didn't get
+        if !covers_expected(amount, &db_tx.cngn_amount) {
+            warn!(
+                transaction_id = %db_tx.transaction_id,
+                expected = %db_tx.cngn_amount,
+                received = ?amount,
+                "incoming cNGN is below the amount the order expects"
+            );
+            return Ok(AppliedDeposit {
+                outcome: IngestionOutcome::Underpaid,
+                transaction_id: Some(db_tx.transaction_id),
+                expected_amount: Some(db_tx.cngn_amount.clone()),
+                credited: None,
+            });
+        }
 
-                    let event_type = if next_status == "completed" {
-                        "stellar.incoming.matched"
-                    } else {
-                        "stellar.offramp.received"
-                    };
+        let next_status = credited_status(&db_tx.r#type, is_offramp);
+        let Some(credited) =
+            credit_deposit(conn, &db_tx, next_status, &tx.hash, tx.ledger, amount).await?
+        else {
+            // Settled by someone else since it was read
+            return Ok(not_pending);
+        };
+        Ok(AppliedDeposit {
+            outcome: IngestionOutcome::Matched,
+            transaction_id: Some(db_tx.transaction_id),
+            expected_amount: Some(db_tx.cngn_amount.clone()),
+            credited: Some(credited),
+        })
+    }
 
-                    self.log_webhook_event(&db_tx.transaction_id.to_string(), event_type, metadata)
-                        .await;
-                }
-                Ok(_) => {
-                    self.log_unmatched_incoming(memo, &tx).await;
-                }
-                Err(e) => {
-                    warn!(
-                        memo = %memo,
-                        error = %e,
-                        "failed to look up memo for incoming transaction"
-                    );
-                }
-            }
+    // -----------------------------------------------------------------------
+    // Streaming and cursor helpers
+    // -----------------------------------------------------------------------
+
+    fn wants_stream(&self) -> bool {
+        self.config.incoming_mode == IncomingMode::Stream
+            && self.config.system_wallet_address.is_some()
+            && self.cursor_ready
+            && !self.catching_up
+    }
+
+    fn finish_catch_up(&mut self) {
+        if self.catching_up {
+            info!(cursor = ?self.incoming_cursor, "incoming payment backfill caught up");
+            self.catching_up = false;
         }
+    }
 
-        self.incoming_cursor = newest_cursor;
-        Ok(())
+    /// Pick up an operator rescan request. Returns true when the cursor was
+    /// moved back, in which case any open stream must be dropped.
+    async fn take_rescan_request(&mut self) -> bool {
+        let Some(system_wallet) = self.config.system_wallet_address.as_deref() else {
+            return false;
+        };
+        let repo = StellarCursorRepository::new(self.pool.clone());
+        match repo
+            .take_rescan(system_wallet, INCOMING_CURSOR_STREAM)
+            .await
+        {
+            Ok(Some(cursor)) => {
+                info!(cursor = %cursor, "replaying incoming payments from rescan cursor");
+                self.incoming_cursor = Some(cursor);
+                self.cursor_ready = true;
+                self.catching_up = true;
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                warn!(error = %e, "failed to check for incoming payment rescan request");
+                false
+            }
+        }
     }
 
-    async fn is_incoming_cngn_payment(
-        &self,
-        tx_hash: &str,
-        system_wallet: &str,
-    ) -> anyhow::Result<bool> {
-        let issuer = std::env::var("CNGN_ISSUER_TESTNET")
-            .or_else(|_| std::env::var("CNGN_ISSUER_MAINNET"))
-            .unwrap_or_default();
-        let operations = self
+    async fn open_stream(&self) -> anyhow::Result<PaymentStream> {
+        let system_wallet = self.config.system_wallet_address.as_deref().unwrap_or("");
+        let stream = self
             .stellar_client
-            .get_transaction_operations(tx_hash)
+            .stream_account_payments(
+                system_wallet,
+                self.incoming_cursor.as_deref(),
+                self.config.stream_idle_timeout,
+            )
             .await?;
-        for op in operations {
-            let op_type = op.get("type").and_then(|v| v.as_str()).unwrap_or("");
-            if op_type != "payment" {
-                continue;
-            }
+        Ok(stream)
+    }
 
-            let destination = op.get("to").and_then(|v| v.as_str()).unwrap_or("");
-            let asset_code = op.get("asset_code").and_then(|v| v.as_str()).unwrap_or("");
-            let asset_issuer = op
-                .get("asset_issuer")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            if destination == system_wallet && asset_code.eq_ignore_ascii_case("cngn") {
-                if issuer.is_empty() || asset_issuer == issuer {
-                    return Ok(true);
+    /// Resume from the persisted cursor. Without one, start at the wallet's
+    /// latest payment: older history predates this deployment and is only
+    /// replayed on an operator rescan.
+    async fn load_incoming_cursor(&mut self) {
+        let Some(system_wallet) = self.config.system_wallet_address.clone() else {
+            return;
+        };
+        let repo = StellarCursorRepository::new(self.pool.clone());
+        match repo.get(&system_wallet, INCOMING_CURSOR_STREAM).await {
+            Ok(Some(cursor)) => {
+                info!(cursor = %cursor, "resuming incoming payment scan from saved cursor");
+                self.incoming_cursor = Some(cursor);
+                self.cursor_ready = true;
+            }
+            Ok(None) => match self
+                .stellar_client
+                .latest_account_payment(&system_wallet)
+                .await
+            {
+                Ok(Some(latest)) => {
+                    info!(
+                        cursor = %latest.paging_token,
+                        "no saved incoming payment cursor; starting from the latest payment"
+                    );
+                    self.advance_incoming_cursor(latest.paging_token).await;
+                    self.cursor_ready = true;
                 }
+                // Nothing has been paid to the wallet yet, so there is no
+                // history to skip
+                Ok(None) => self.cursor_ready = true,
+                Err(e) => warn!(error = %e, "failed to seed incoming payment cursor"),
+            },
+            Err(e) => warn!(error = %e, "failed to load incoming payment cursor"),
+        }
+    }
+
+    async fn advance_incoming_cursor(&mut self, cursor: String) {
+        if let Some(system_wallet) = self.config.system_wallet_address.as_deref() {
+            let repo = StellarCursorRepository::new(self.pool.clone());
+            if let Err(e) = repo
+                .save(system_wallet, INCOMING_CURSOR_STREAM, &cursor)
+                .await
+            {
+                warn!(error = %e, cursor = %cursor, "failed to persist incoming payment cursor");
             }
         }
-        Ok(false)
+        self.incoming_cursor = Some(cursor);
     }
 
     // -----------------------------------------------------------------------
@@ -546,34 +1026,29 @@ impl TransactionMonitorWorker {
     // -----------------------------------------------------------------------
 
     async fn log_webhook_event(&self, transaction_id: &str, event_type: &str, payload: JsonValue) {
-        let parsed_tx_id = Uuid::parse_str(transaction_id).ok();
-        let repo = WebhookRepository::new(self.pool.clone());
-        let event_id = format!("{}:{}", event_type, transaction_id);
-        if let Err(e) = repo
-            .log_event(
-                &event_id,
-                "stellar",
-                event_type,
-                payload,
-                None,
-                parsed_tx_id,
-            )
-            .await
-        {
-            warn!(
-                transaction_id = %transaction_id,
-                event_type = %event_type,
-                error = %e,
-                "failed to write webhook event"
-            );
+        record_webhook_event(&self.pool, transaction_id, event_type, payload).await;
+    }
+
+    /// Offramps and bills publish their own events from their processors
+    async fn notify_onramp(&self, event_type: &str, tx: &Transaction) {
+        if tx.r#type != "onramp" {
+            return;
+        }
+        if let Some(webhooks) = &self.merchant_webhooks {
+            webhooks.notify(event_type, tx, None).await;
         }
     }
 
-    async fn log_unmatched_incoming(&self, memo: &str, tx: &HorizonTransactionRecord) {
+    async fn log_unmatched_incoming(&self, key: DepositKey<'_>, tx: &HorizonTransactionRecord) {
         let repo = WebhookRepository::new(self.pool.clone());
         let event_id = format!("unmatched:{}", tx.hash);
+        let (memo, muxed_id) = match key {
+            DepositKey::Memo(memo) => (Some(memo), None),
+            DepositKey::MuxedId(id) => (None, Some(id)),
+        };
         let payload = json!({
             "memo": memo,
+            "muxed_id": muxed_id,
             "hash": tx.hash,
             "ledger": tx.ledger,
             "created_at": tx.created_at,
@@ -594,10 +1069,200 @@ impl TransactionMonitorWorker {
     }
 }
 
+/// What an incoming deposit carries to identify its order
+#[derive(Debug, Clone, Copy)]
+enum DepositKey<'a> {
+    /// Sent to the system wallet's SEP-23 M-address for the order
+    MuxedId(u64),
+    Memo(&'a str),
+}
+
+/// Outcome of matching one incoming deposit
+struct AppliedDeposit {
+    outcome: IngestionOutcome,
+    transaction_id: Option<Uuid>,
+    /// cNGN the matched order expected, for overpayment detection
+    expected_amount: Option<BigDecimal>,
+    /// Set when the order was credited; announced once the ingestion commits
+    credited: Option<CreditedDeposit>,
+}
+
+/// A deposit credited inside a database transaction, waiting for it to
+/// commit before its stellar event is emitted
+pub struct CreditedDeposit {
+    transaction_id: String,
+    next_status: &'static str,
+    deposit_hash: String,
+    ledger: Option<i64>,
+    metadata: JsonValue,
+}
+
+impl CreditedDeposit {
+    pub async fn announce(self, pool: &PgPool) {
+        info!(
+            transaction_id = %self.transaction_id,
+            incoming_hash = %self.deposit_hash,
+            ledger = ?self.ledger,
+            status = self.next_status,
+            "incoming cNGN payment matched and updated"
+        );
+
+        let event_type = if self.next_status == "completed" {
+            "stellar.incoming.matched"
+        } else {
+            "stellar.offramp.received"
+        };
+        record_webhook_event(pool, &self.transaction_id, event_type, self.metadata).await;
+    }
+}
+
+/// Record a confirmed cNGN deposit against an order awaiting it, on the
+/// caller's database transaction. Returns the stellar event to announce once
+/// that commits, or `None` if the order left `db_tx.status` in the meantime.
+/// Shared with operators attaching quarantined deposits.
+pub async fn credit_deposit(
+    conn: &mut PgConnection,
+    db_tx: &Transaction,
+    next_status: &'static str,
+    deposit_hash: &str,
+    ledger: Option<i64>,
+    amount: Option<&BigDecimal>,
+) -> anyhow::Result<Option<CreditedDeposit>> {
+    let mut metadata = db_tx.metadata.clone();
+    metadata["incoming_hash"] = json!(deposit_hash);
+    metadata["incoming_ledger"] = json!(ledger);
+    metadata["incoming_confirmed_at"] = json!(chrono::Utc::now().to_rfc3339());
+    if let Some(amount) = amount {
+        metadata["incoming_amount"] = json!(amount.to_string());
+    }
+
+    let credited = TransactionRepository::credit_deposit_in(
+        conn,
+        db_tx.transaction_id,
+        &db_tx.status,
+        next_status,
+        metadata.clone(),
+        deposit_hash,
+    )
+    .await?;
+
+    Ok(credited.map(|_| CreditedDeposit {
+        transaction_id: db_tx.transaction_id.to_string(),
+        next_status,
+        deposit_hash: deposit_hash.to_string(),
+        ledger,
+        metadata,
+    }))
+}
+
+async fn record_webhook_event(
+    pool: &PgPool,
+    transaction_id: &str,
+    event_type: &str,
+    payload: JsonValue,
+) {
+    let parsed_tx_id = Uuid::parse_str(transaction_id).ok();
+    let repo = WebhookRepository::new(pool.clone());
+    let event_id = format!("{}:{}", event_type, transaction_id);
+    if let Err(e) = repo
+        .log_event(
+            &event_id,
+            "stellar",
+            event_type,
+            payload,
+            None,
+            parsed_tx_id,
+        )
+        .await
+    {
+        warn!(
+            transaction_id = %transaction_id,
+            event_type = %event_type,
+            error = %e,
+            "failed to write webhook event"
+        );
+    }
+}
+
+/// Next event from the payment stream; never resolves while there is none.
+async fn next_streamed_payment(
+    stream: &mut Option<PaymentStream>,
+) -> crate::chains::stellar::errors::StellarResult<Option<HorizonPaymentRecord>> {
+    match stream {
+        Some(stream) => stream.next_payment().await,
+        None => std::future::pending().await,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Pure helper functions
 // ---------------------------------------------------------------------------
 
+/// Configured cNGN issuer; empty when unset, which accepts any issuer.
+fn cngn_issuer() -> String {
+    std::env::var("CNGN_ISSUER_TESTNET")
+        .or_else(|_| std::env::var("CNGN_ISSUER_MAINNET"))
+        .unwrap_or_default()
+}
+
+/// Statuses in which an order is still waiting for the user's cNGN
+pub fn is_awaiting_deposit(status: &str) -> bool {
+    matches!(status, "pending" | "processing" | "pending_payment")
+}
+
+/// Status an order moves to once its deposit lands. Offramps and bill
+/// payments still have fiat work to do; anything else is done.
+pub fn credited_status(transaction_type: &str, offramp_memo: bool) -> &'static str {
+    if offramp_memo || transaction_type == "offramp" || transaction_type == "bill_payment" {
+        "cngn_received"
+    } else {
+        "completed"
+    }
+}
+
+/// Whether a deposit pays for the whole order. An unreadable amount never
+/// does.
+pub fn covers_expected(received: Option<&BigDecimal>, expected: &BigDecimal) -> bool {
+    received.is_some_and(|received| received >= expected)
+}
+
+/// Part of a deposit that cannot be credited and must be held for an
+/// operator: all of it when nothing matched or it fell short, the excess when
+/// overpaid.
+fn quarantined_share(
+    outcome: IngestionOutcome,
+    by_muxed_id: bool,
+    received: &BigDecimal,
+    expected: Option<&BigDecimal>,
+) -> Option<(UnmatchedReason, BigDecimal)> {
+    match outcome {
+        IngestionOutcome::NoMemo => Some((UnmatchedReason::NoMemo, received.clone())),
+        IngestionOutcome::Unmatched if by_muxed_id => {
+            Some((UnmatchedReason::UnknownMuxedId, received.clone()))
+        }
+        IngestionOutcome::Unmatched => Some((UnmatchedReason::UnknownMemo, received.clone())),
+        IngestionOutcome::NotPending => Some((UnmatchedReason::NotPending, received.clone())),
+        IngestionOutcome::Underpaid => Some((UnmatchedReason::Underpaid, received.clone())),
+        IngestionOutcome::Matched => {
+            let expected = expected.filter(|e| **e > 0)?;
+            (received > expected).then(|| (UnmatchedReason::Overpaid, received - expected))
+        }
+    }
+}
+
+/// Returns `true` when a payment operation credits cNGN to the system wallet.
+fn is_cngn_deposit(
+    destination: &str,
+    asset_code: &str,
+    asset_issuer: &str,
+    system_wallet: &str,
+    issuer: &str,
+) -> bool {
+    destination == system_wallet
+        && asset_code.eq_ignore_ascii_case("cngn")
+        && (issuer.is_empty() || asset_issuer == issuer)
+}
+
 /// Extract the Stellar transaction hash from metadata, trying several known keys.
 fn extract_tx_hash(metadata: Option<&JsonValue>) -> Option<String> {
     let metadata = metadata?;
@@ -679,6 +1344,40 @@ fn is_timed_out(last_updated: chrono::DateTime<chrono::Utc>, timeout: Duration)
     elapsed.to_std().map(|d| d > timeout).unwrap_or(false)
 }
 
+/// When the absolute timeout starts counting: the latest submission if the
+/// payment was submitted, otherwise the transaction's creation.
+fn timeout_started_at(
+    metadata: &JsonValue,
+    created_at: chrono::DateTime<chrono::Utc>,
+) -> chrono::DateTime<chrono::Utc> {
+    metadata
+        .get("submitted_at")
+        .and_then(|v| v.as_str())
+        .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .unwrap_or(created_at)
+}
+
+/// Status a retryable failure is moved back to. Onramp payouts are rebuilt
+/// by `OnrampProcessorWorker`, which only picks up `payment_confirmed`; other
+/// transactions keep being polled here.
+fn retry_status(transaction_type: &str) -> &'static str {
+    match transaction_type {
+        "onramp" => OrchestrationState::PaymentConfirmed.to_db_status(),
+        _ => "pending",
+    }
+}
+
+/// Whether the payout envelope recorded in `metadata` (see
+/// `OnrampFulfillmentMetadata::submitted_valid_until`) could still be applied
+/// at `now`
+fn envelope_may_still_apply(metadata: &JsonValue, now: u64) -> bool {
+    metadata
+        .get("submitted_valid_until")
+        .and_then(|v| v.as_u64())
+        .is_some_and(|valid_until| now <= valid_until + ENVELOPE_EXPIRY_GRACE_SECONDS)
+}
+
 /// Returns `true` for transient Stellar error codes that are worth retrying.
 fn is_retryable_error(message: &str) -> bool {
     let m = message.to_lowercase();
@@ -803,6 +1502,36 @@ mod tests {
         assert!(is_timed_out(old, Duration::from_secs(30)));
     }
 
+    #[test]
+    fn timeout_counts_from_submission() {
+        let created_at = chrono::Utc::now() - chrono::Duration::seconds(3600);
+        let submitted_at = chrono::Utc::now() - chrono::Duration::seconds(5);
+        let metadata = json!({ "submitted_at": submitted_at.to_rfc3339() });
+
+        let started_at = timeout_started_at(&metadata, created_at);
+        assert!(!is_timed_out(started_at, Duration::from_secs(600)));
+        assert_eq!(timeout_started_at(&json!({}), created_at), created_at);
+        assert_eq!(
+            timeout_started_at(&json!({ "submitted_at": null }), created_at),
+            created_at
+        );
+    }
+
+    #[test]
+    fn onramp_retries_go_back_to_the_processor() {
+        assert_eq!(retry_status("onramp"), "payment_confirmed");
+        assert_eq!(retry_status("offramp"), "pending");
+    }
+
+    #[test]
+    fn unexpired_payout_envelopes_are_not_rebuilt() {
+        let metadata = json!({ "submitted_valid_until": 1_000 });
+        assert!(envelope_may_still_apply(&metadata, 1_000));
+        assert!(envelope_may_still_apply(&metadata, 1_010));
+        assert!(!envelope_may_still_apply(&metadata, 1_011));
+        assert!(!envelope_may_still_apply(&json!({}), 1_000));
+    }
+
     // --- exponential backoff schedule ---------------------------------------
 
     #[test]
@@ -890,6 +1619,103 @@ mod tests {
         assert_eq!(metadata["submitted_hash"], json!("stellar_hash_1"));
     }
 
+    // --- incoming payment helpers -------------------------------------------
+
+    #[test]
+    fn cngn_deposit_requires_wallet_asset_and_issuer() {
+        let wallet = "GSYSTEM";
+        assert!(is_cngn_deposit(
+            wallet, "cNGN", "GISSUER", wallet, "GISSUER"
+        ));
+        assert!(is_cngn_deposit(wallet, "CNGN", "GANY", wallet, ""));
+        assert!(!is_cngn_deposit(
+            "GOTHER", "cNGN", "GISSUER", wallet, "GISSUER"
+        ));
+        assert!(!is_cngn_deposit(
+            wallet, "USDC", "GISSUER", wallet, "GISSUER"
+        ));
+        assert!(!is_cngn_deposit(wallet, "cNGN", "GFAKE", wallet, "GISSUER"));
+    }
+
+    #[test]
+    fn quarantine_holds_unmatched_deposits_and_overpayment_excess() {
+        let received = BigDecimal::from(5_000);
+        let expected = BigDecimal::from(4_000);
+
+        let (reason, held) =
+            quarantined_share(IngestionOutcome::NoMemo, false, &received, None).unwrap();
+        assert_eq!(reason, UnmatchedReason::NoMemo);
+        assert_eq!(held, received);
+        let (reason, _) =
+            quarantined_share(IngestionOutcome::NotPending, false, &received, None).unwrap();
+        assert_eq!(reason, UnmatchedReason::NotPending);
+        let (reason, _) =
+            quarantined_share(IngestionOutcome::Unmatched, false, &received, None).unwrap();
+        assert_eq!(reason, UnmatchedReason::UnknownMemo);
+        let (reason, _) =
+            quarantined_share(IngestionOutcome::Unmatched, true, &received, None).unwrap();
+        assert_eq!(reason, UnmatchedReason::UnknownMuxedId);
+        let short = BigDecimal::from(3_000);
+        let (reason, held) =
+            quarantined_share(IngestionOutcome::Underpaid, false, &short, Some(&expected)).unwrap();
+        assert_eq!(reason, UnmatchedReason::Underpaid);
+        assert_eq!(held, short);
+
+        let (reason, held) =
+            quarantined_share(IngestionOutcome::Matched, false, &received, Some(&expected))
+                .unwrap();
+        assert_eq!(reason, UnmatchedReason::Overpaid);
+        assert_eq!(held, BigDecimal::from(1_000));
+
+        assert!(
+            quarantined_share(IngestionOutcome::Matched, false, &expected, Some(&received))
+                .is_none()
+        );
+        assert!(quarantined_share(
+            IngestionOutcome::Matched,
+            false,
+            &received,
+            Some(&BigDecimal::from(0))
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn only_deposits_covering_the_order_are_credited() {
+        let expected = BigDecimal::from(4_000);
+        assert!(covers_expected(Some(&BigDecimal::from(4_000)), &expected));
+        assert!(covers_expected(Some(&BigDecimal::from(5_000)), &expected));
+        assert!(!covers_expected(Some(&BigDecimal::from(3_999)), &expected));
+        assert!(!covers_expected(None, &expected));
+    }
+
+    #[test]
+    fn credited_status_by_transaction_type() {
+        assert_eq!(credited_status("offramp", false), "cngn_received");
+        assert_eq!(credited_status("bill_payment", false), "cngn_received");
+        assert_eq!(credited_status("onramp", true), "cngn_received");
+        assert_eq!(credited_status("onramp", false), "completed");
+        assert!(is_awaiting_deposit("pending_payment"));
+        assert!(!is_awaiting_deposit("cngn_received"));
+    }
+
+    #[test]
+    fn incoming_mode_parsing() {
+        assert_eq!(IncomingMode::parse("stream"), Some(IncomingMode::Stream));
+        assert_eq!(IncomingMode::parse("SSE"), Some(IncomingMode::Stream));
+        assert_eq!(IncomingMode::parse("poll"), Some(IncomingMode::Poll));
+        assert_eq!(IncomingMode::parse("carrier-pigeon"), None);
+    }
+
+    #[test]
+    fn stream_reconnect_delay_doubles_and_caps() {
+        let config = TransactionMonitorConfig::default();
+        assert_eq!(config.stream_reconnect_delay(1), Duration::from_secs(1));
+        assert_eq!(config.stream_reconnect_delay(2), Duration::from_secs(2));
+        assert_eq!(config.stream_reconnect_delay(5), Duration::from_secs(16));
+        assert_eq!(config.stream_reconnect_delay(40), Duration::from_secs(60));
+    }
+
     // --- MonitorError display -----------------------------------------------
 
     #[test]
@@ -0,0 +1,60 @@
+use crate::services::treasury::{TreasuryError, TreasuryService};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument};
+
+/// Periodically checks hot wallet balances against the treasury thresholds
+/// and submits the sweeps that bring them back to target
+pub struct TreasuryMonitorWorker {
+    service: Arc<TreasuryService>,
+    interval: Duration,
+}
+
+impl TreasuryMonitorWorker {
+    pub fn new(service: Arc<TreasuryService>) -> Self {
+        let interval = service.config().check_interval;
+        Self { service, interval }
+    }
+
+    pub async fn run(self, mut shutdown_rx: watch::Receiver<bool>) {
+        info!(
+            hot_wallet = %self.service.config().hot_wallet_address,
+            interval_secs = self.interval.as_secs(),
+            "Starting treasury monitor..."
+        );
+
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_cycle().await {
+                        error!(error = %e, "treasury check failed");
+                    }
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        info!("Treasury monitor received shutdown signal");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!("Treasury monitor stopped");
+    }
+
+    #[instrument(skip(self), name = "treasury_check_cycle")]
+    async fn run_cycle(&self) -> Result<(), TreasuryError> {
+        let positions = self.service.check().await?;
+        for position in &positions {
+            debug!(asset = %position.asset, balance = %position.balance, "treasury balance");
+        }
+        let submitted = self.service.execute_sweeps().await?;
+        if submitted > 0 {
+            info!(submitted, "treasury sweeps submitted");
+        }
+        Ok(())
+    }
+}